@@ -92,17 +92,21 @@ export type ChangePasswordRequest = z.infer<typeof ChangePasswordRequestSchema>;
 export const SessionSchema = z.object({
     id: z.uuid(),
     user_id: z.uuid(),
-    session_token: z.string(),
     expires_at: z.string(),
     ip_address: z.string().nullable().optional(),
     user_agent: z.string().nullable().optional(),
     created_at: z.string(),
+    is_current: z.boolean().optional(),
+    device: z.string().optional(),
+    name: z.string().nullable().optional(),
 });
 
 export type Session = z.infer<typeof SessionSchema>;
 
 export const SessionListResponseSchema = z.object({
     sessions: z.array(SessionSchema),
+    active_sessions: z.number().optional(),
+    max_sessions: z.number().nullable().optional(),
 });
 
 export type SessionListResponse = z.infer<typeof SessionListResponseSchema>;
@@ -0,0 +1,5 @@
+DROP TRIGGER IF EXISTS log_sessions_impersonation_stop ON sessions;
+DROP FUNCTION IF EXISTS log_impersonation_stop();
+DROP TABLE IF EXISTS impersonation_audit_log;
+DROP INDEX IF EXISTS idx_sessions_impersonated_by;
+ALTER TABLE sessions DROP COLUMN IF EXISTS impersonated_by;
@@ -0,0 +1,33 @@
+-- Flag sessions created by an admin impersonating another user
+ALTER TABLE sessions
+ADD COLUMN IF NOT EXISTS impersonated_by UUID REFERENCES users(id) ON DELETE CASCADE;
+CREATE INDEX IF NOT EXISTS idx_sessions_impersonated_by ON sessions(impersonated_by)
+WHERE impersonated_by IS NOT NULL;
+-- Create impersonation audit table
+-- No foreign keys: audit rows must outlive the users and sessions they reference
+CREATE TABLE IF NOT EXISTS impersonation_audit_log (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    admin_id UUID NOT NULL,
+    target_user_id UUID NOT NULL,
+    session_id UUID NOT NULL,
+    action VARCHAR(16) NOT NULL CHECK (action IN ('start', 'stop')),
+    ip_address INET,
+    user_agent TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+-- Create indexes
+CREATE INDEX IF NOT EXISTS idx_impersonation_audit_admin_id ON impersonation_audit_log(admin_id);
+CREATE INDEX IF NOT EXISTS idx_impersonation_audit_target_user_id ON impersonation_audit_log(target_user_id);
+CREATE INDEX IF NOT EXISTS idx_impersonation_audit_created_at ON impersonation_audit_log(created_at);
+-- Record a 'stop' whenever an impersonated session ends, however it is removed
+-- (signout, revocation by the target user, expiry cleanup, or cascade)
+CREATE OR REPLACE FUNCTION log_impersonation_stop() RETURNS TRIGGER AS $$ BEGIN
+INSERT INTO impersonation_audit_log (admin_id, target_user_id, session_id, action)
+VALUES (OLD.impersonated_by, OLD.user_id, OLD.id, 'stop');
+RETURN OLD;
+END;
+$$ language 'plpgsql';
+DROP TRIGGER IF EXISTS log_sessions_impersonation_stop ON sessions;
+CREATE TRIGGER log_sessions_impersonation_stop
+AFTER DELETE ON sessions FOR EACH ROW
+WHEN (OLD.impersonated_by IS NOT NULL) EXECUTE FUNCTION log_impersonation_stop();
@@ -0,0 +1,3 @@
+DROP TRIGGER IF EXISTS record_sessions_revocation ON sessions;
+DROP FUNCTION IF EXISTS record_revoked_session();
+DROP TABLE IF EXISTS revoked_sessions;
@@ -0,0 +1,23 @@
+-- Create revoked sessions table
+-- Denylist for stateless access tokens: a JWT stays valid until it expires,
+-- so sessions deleted early are recorded here and checked in memory
+CREATE TABLE IF NOT EXISTS revoked_sessions (
+    session_id UUID PRIMARY KEY,
+    expires_at TIMESTAMPTZ NOT NULL,
+    revoked_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+-- Create indexes
+CREATE INDEX IF NOT EXISTS idx_revoked_sessions_expires_at ON revoked_sessions(expires_at);
+-- Record every session removed before its natural expiry. Access tokens live
+-- at most one day, so entries never need to outlive that.
+CREATE OR REPLACE FUNCTION record_revoked_session() RETURNS TRIGGER AS $$ BEGIN
+INSERT INTO revoked_sessions (session_id, expires_at)
+VALUES (OLD.id, LEAST(OLD.expires_at, NOW() + INTERVAL '1 day'))
+ON CONFLICT (session_id) DO NOTHING;
+RETURN OLD;
+END;
+$$ language 'plpgsql';
+DROP TRIGGER IF EXISTS record_sessions_revocation ON sessions;
+CREATE TRIGGER record_sessions_revocation
+AFTER DELETE ON sessions FOR EACH ROW
+WHEN (OLD.expires_at > NOW()) EXECUTE FUNCTION record_revoked_session();
@@ -0,0 +1,17 @@
+-- Create user token usage table
+-- Daily totals of tokens consumed by chat responses (UTC days)
+CREATE TABLE IF NOT EXISTS user_token_usage (
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    period_start DATE NOT NULL,
+    tokens_used BIGINT NOT NULL DEFAULT 0,
+    PRIMARY KEY (user_id, period_start)
+);
+-- Create user quotas table
+-- A missing row or NULL limit means the user is unlimited
+CREATE TABLE IF NOT EXISTS user_quotas (
+    user_id UUID PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+    max_tokens_per_day BIGINT CHECK (max_tokens_per_day >= 0),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+-- Create indexes
+CREATE INDEX IF NOT EXISTS idx_user_token_usage_period_start ON user_token_usage(period_start);
@@ -0,0 +1,3 @@
+-- Count wrong OTP guesses so a verification code locks after too many
+ALTER TABLE verification_tokens
+ADD COLUMN IF NOT EXISTS otp_attempts INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,13 @@
+-- Create pending messages table
+-- Holds chat messages accepted while the Intelligence service was unreachable,
+-- so the client can resend them later instead of losing what the user typed.
+CREATE TABLE IF NOT EXISTS pending_messages (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    conversation_id UUID NOT NULL REFERENCES conversations(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    content TEXT NOT NULL,
+    config JSONB,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+-- Create indexes
+CREATE INDEX IF NOT EXISTS idx_pending_messages_conversation ON pending_messages(conversation_id, created_at);
@@ -0,0 +1,3 @@
+-- Add a user-chosen name to sessions
+-- Lets users label their devices in the session list ("Work laptop").
+ALTER TABLE sessions ADD COLUMN IF NOT EXISTS name TEXT;
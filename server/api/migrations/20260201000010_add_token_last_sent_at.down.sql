@@ -0,0 +1,2 @@
+ALTER TABLE password_reset_tokens DROP COLUMN IF EXISTS last_sent_at;
+ALTER TABLE verification_tokens DROP COLUMN IF EXISTS last_sent_at;
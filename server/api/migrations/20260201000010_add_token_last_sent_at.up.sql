@@ -0,0 +1,7 @@
+-- When a token was last emailed, so resends can be rate limited while the
+-- token itself is reused
+ALTER TABLE verification_tokens
+ADD COLUMN IF NOT EXISTS last_sent_at TIMESTAMPTZ NOT NULL DEFAULT NOW();
+
+ALTER TABLE password_reset_tokens
+ADD COLUMN IF NOT EXISTS last_sent_at TIMESTAMPTZ NOT NULL DEFAULT NOW();
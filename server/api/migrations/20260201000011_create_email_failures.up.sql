@@ -0,0 +1,13 @@
+-- Create email failures table
+-- One row per email the background worker gave up delivering.
+CREATE TABLE IF NOT EXISTS email_failures (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    to_email VARCHAR(255) NOT NULL,
+    subject TEXT NOT NULL,
+    error TEXT NOT NULL,
+    attempts INTEGER NOT NULL,
+    attempted_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Create indexes
+CREATE INDEX IF NOT EXISTS idx_email_failures_attempted_at ON email_failures(attempted_at DESC);
@@ -0,0 +1,6 @@
+-- Create data exports table
+-- When each user last requested a data export, so exports can be rate limited.
+CREATE TABLE IF NOT EXISTS data_exports (
+    user_id UUID PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+    requested_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
@@ -0,0 +1,30 @@
+-- Make usernames unique regardless of case
+--
+-- Usernames that differ only by case are renamed first: the oldest account
+-- keeps its username and the others get the first 8 characters of their ID
+-- appended, so the index can be built. Each rename is reported.
+DO $$
+DECLARE
+    renamed RECORD;
+BEGIN
+    FOR renamed IN
+        WITH ranked AS (
+            SELECT id, username,
+                   ROW_NUMBER() OVER (
+                       PARTITION BY LOWER(username) ORDER BY created_at, id
+                   ) AS position
+            FROM users
+            WHERE username IS NOT NULL
+        )
+        UPDATE users u
+        SET username = LEFT(r.username, 23) || '-' || LEFT(u.id::text, 8)
+        FROM ranked r
+        WHERE u.id = r.id AND r.position > 1
+        RETURNING u.id, r.username AS old_username, u.username AS new_username
+    LOOP
+        RAISE NOTICE 'Renamed username % of user % to % (case-only duplicate)',
+            renamed.old_username, renamed.id, renamed.new_username;
+    END LOOP;
+END $$;
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_users_username_lower ON users(LOWER(username));
@@ -0,0 +1,3 @@
+-- Add per-user preferences
+-- Theme, locale and the default chat config, merged key by key on update.
+ALTER TABLE users ADD COLUMN IF NOT EXISTS preferences JSONB NOT NULL DEFAULT '{}'::jsonb;
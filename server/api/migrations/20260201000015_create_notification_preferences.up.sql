@@ -0,0 +1,9 @@
+-- Create notification preferences table
+-- Opt-outs for optional email; a missing row means every category is on
+CREATE TABLE IF NOT EXISTS notification_preferences (
+    user_id UUID PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+    security_alerts BOOLEAN NOT NULL DEFAULT TRUE,
+    product_updates BOOLEAN NOT NULL DEFAULT TRUE,
+    weekly_digest BOOLEAN NOT NULL DEFAULT TRUE,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
@@ -0,0 +1,14 @@
+-- Create system notices table
+-- Announcements shown to every visitor while NOW() is within the active window
+CREATE TABLE IF NOT EXISTS system_notices (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    title TEXT NOT NULL,
+    body TEXT NOT NULL,
+    severity TEXT NOT NULL CHECK (severity IN ('info', 'warning', 'critical')),
+    active_from TIMESTAMPTZ NOT NULL,
+    active_until TIMESTAMPTZ NOT NULL CHECK (active_until > active_from),
+    created_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+-- Create indexes
+CREATE INDEX IF NOT EXISTS idx_system_notices_active_window ON system_notices(active_from, active_until);
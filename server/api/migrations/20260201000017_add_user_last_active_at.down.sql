@@ -0,0 +1,5 @@
+DROP TRIGGER IF EXISTS update_users_updated_at ON users;
+CREATE TRIGGER update_users_updated_at BEFORE
+UPDATE ON users FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
+DROP INDEX IF EXISTS idx_users_last_active_at;
+ALTER TABLE users DROP COLUMN IF EXISTS last_active_at;
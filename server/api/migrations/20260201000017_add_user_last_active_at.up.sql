@@ -0,0 +1,14 @@
+-- Track when users were last seen
+-- Written by the auth middleware at most once per 5 minutes per user
+ALTER TABLE users ADD COLUMN IF NOT EXISTS last_active_at TIMESTAMPTZ;
+CREATE INDEX IF NOT EXISTS idx_users_last_active_at ON users(last_active_at);
+-- Activity updates shouldn't count as profile changes
+DROP TRIGGER IF EXISTS update_users_updated_at ON users;
+CREATE TRIGGER update_users_updated_at BEFORE
+UPDATE ON users FOR EACH ROW
+WHEN (NEW.last_active_at IS NOT DISTINCT FROM OLD.last_active_at)
+EXECUTE FUNCTION update_updated_at_column();
+-- Start from the most recent sign-in
+UPDATE users
+SET last_active_at = (SELECT MAX(created_at) FROM sessions WHERE sessions.user_id = users.id)
+WHERE last_active_at IS NULL;
@@ -0,0 +1,7 @@
+-- Create system config table
+-- Runtime settings changed by admins that must survive restarts (e.g. maintenance mode)
+CREATE TABLE IF NOT EXISTS system_config (
+    key TEXT PRIMARY KEY,
+    value JSONB NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
@@ -0,0 +1,6 @@
+-- Add authenticated_at to sessions
+-- When the user last proved who they are; kept when a session is refreshed,
+-- so sensitive actions can ask for a recent sign-in
+ALTER TABLE sessions ADD COLUMN IF NOT EXISTS authenticated_at TIMESTAMPTZ NOT NULL DEFAULT NOW();
+
+UPDATE sessions SET authenticated_at = created_at;
@@ -0,0 +1,7 @@
+DROP TRIGGER IF EXISTS update_users_updated_at ON users;
+CREATE TRIGGER update_users_updated_at BEFORE
+UPDATE ON users FOR EACH ROW
+WHEN (NEW.last_active_at IS NOT DISTINCT FROM OLD.last_active_at)
+EXECUTE FUNCTION update_updated_at_column();
+ALTER TABLE users DROP COLUMN IF EXISTS locked_until;
+ALTER TABLE users DROP COLUMN IF EXISTS failed_login_attempts;
@@ -0,0 +1,14 @@
+-- Lock accounts after repeated failed sign-ins
+-- Consecutive failures since the last successful sign-in, and when the current lock ends
+ALTER TABLE users ADD COLUMN IF NOT EXISTS failed_login_attempts INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE users ADD COLUMN IF NOT EXISTS locked_until TIMESTAMPTZ;
+-- Sign-in bookkeeping shouldn't count as a profile change either
+DROP TRIGGER IF EXISTS update_users_updated_at ON users;
+CREATE TRIGGER update_users_updated_at BEFORE
+UPDATE ON users FOR EACH ROW
+WHEN (
+    NEW.last_active_at IS NOT DISTINCT FROM OLD.last_active_at
+    AND NEW.failed_login_attempts IS NOT DISTINCT FROM OLD.failed_login_attempts
+    AND NEW.locked_until IS NOT DISTINCT FROM OLD.locked_until
+)
+EXECUTE FUNCTION update_updated_at_column();
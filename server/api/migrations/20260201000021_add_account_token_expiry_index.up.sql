@@ -0,0 +1,4 @@
+-- Lets the token refresh task find provider tokens nearing expiry
+CREATE INDEX IF NOT EXISTS idx_accounts_refreshable_expires_at
+    ON accounts(expires_at)
+    WHERE refresh_token IS NOT NULL;
@@ -0,0 +1,4 @@
+-- Add per-user monthly token budgets
+-- NULL falls back to CHAT_MONTHLY_TOKEN_BUDGET; usage is summed from user_token_usage
+ALTER TABLE user_quotas
+    ADD COLUMN IF NOT EXISTS max_tokens_per_month BIGINT CHECK (max_tokens_per_month >= 0);
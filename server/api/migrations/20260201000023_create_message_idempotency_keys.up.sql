@@ -0,0 +1,15 @@
+-- Create message idempotency keys table
+-- Caches send_message responses by the client's Idempotency-Key header, so a
+-- retried POST returns the original reply instead of sending the message twice.
+-- Keys are scoped to the user and conversation. A row is claimed before the
+-- message is sent, with a NULL response until the reply is stored.
+CREATE TABLE IF NOT EXISTS message_idempotency_keys (
+    key TEXT NOT NULL,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    conversation_id UUID NOT NULL REFERENCES conversations(id) ON DELETE CASCADE,
+    response_json JSONB,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (user_id, conversation_id, key)
+);
+-- Create indexes
+CREATE INDEX IF NOT EXISTS idx_message_idempotency_keys_created_at ON message_idempotency_keys(created_at);
@@ -0,0 +1,10 @@
+-- Create conversation shares table
+-- One public, read-only link per conversation; minting a new link replaces the
+-- old one, and deleting the row revokes it.
+CREATE TABLE IF NOT EXISTS conversation_shares (
+    conversation_id UUID PRIMARY KEY REFERENCES conversations(id) ON DELETE CASCADE,
+    token TEXT NOT NULL UNIQUE,
+    read_only BOOLEAN NOT NULL DEFAULT TRUE,
+    expires_at TIMESTAMPTZ NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
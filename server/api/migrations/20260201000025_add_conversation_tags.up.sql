@@ -0,0 +1,4 @@
+-- User-chosen conversation tags, lowercased by the API
+ALTER TABLE conversations
+ADD COLUMN IF NOT EXISTS tags TEXT[] NOT NULL DEFAULT '{}';
+CREATE INDEX IF NOT EXISTS idx_conversations_tags ON conversations USING GIN (tags);
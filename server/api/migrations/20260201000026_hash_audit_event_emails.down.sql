@@ -0,0 +1,2 @@
+-- Hashed emails cannot be recovered; nothing to undo
+SELECT 1;
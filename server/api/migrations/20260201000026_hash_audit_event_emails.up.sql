@@ -0,0 +1,9 @@
+-- Sign-in failures for unknown accounts kept the submitted email; replace it
+-- with the same SHA-256 the API now records
+UPDATE auth_events
+SET metadata = (metadata - 'email')
+    || jsonb_build_object(
+        'email_hash',
+        encode(sha256(convert_to(lower(btrim(metadata->>'email')), 'UTF8')), 'hex')
+    )
+WHERE metadata ? 'email';
@@ -0,0 +1,5 @@
+-- Hashed links cannot be recovered; drop them so users request new ones
+DELETE FROM verification_tokens;
+
+ALTER INDEX IF EXISTS idx_verification_tokens_token_hash RENAME TO idx_verification_tokens_token;
+ALTER TABLE verification_tokens RENAME COLUMN token_hash TO token;
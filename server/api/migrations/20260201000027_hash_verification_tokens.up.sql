@@ -0,0 +1,7 @@
+-- Verification links were stored as sent; keep only their SHA-256 so the
+-- table no longer holds usable links
+UPDATE verification_tokens
+SET token = encode(sha256(convert_to(token, 'UTF8')), 'hex');
+
+ALTER TABLE verification_tokens RENAME COLUMN token TO token_hash;
+ALTER INDEX IF EXISTS idx_verification_tokens_token RENAME TO idx_verification_tokens_token_hash;
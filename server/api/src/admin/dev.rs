@@ -0,0 +1,77 @@
+//! Development-only admin endpoints
+//!
+//! Every route here returns 404 unless its feature flag is enabled.
+
+use axum::{
+    Json,
+    extract::{Query, State},
+    http::StatusCode,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::email::mailbox::CapturedEmail;
+use crate::gateway::AppState;
+
+#[derive(Debug, Deserialize)]
+pub struct MailboxQuery {
+    /// Only return emails sent to this address
+    pub to: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MailboxResponse {
+    pub emails: Vec<CapturedEmail>,
+    pub total: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ClearMailboxResponse {
+    pub cleared: usize,
+}
+
+/// List captured emails, newest first
+/// GET /admin/dev/mailbox
+#[utoipa::path(
+    get,
+    path = "/admin/dev/mailbox",
+    tag = "admin",
+    responses(
+        (status = 200, description = "Captured emails"),
+        (status = 404, description = "Development mailbox disabled"),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn list_mailbox(
+    State(state): State<AppState>,
+    Query(query): Query<MailboxQuery>,
+) -> Result<Json<MailboxResponse>, StatusCode> {
+    let mailbox = state.email.mailbox().ok_or(StatusCode::NOT_FOUND)?;
+
+    let emails = mailbox.list(query.to.as_deref());
+    Ok(Json(MailboxResponse {
+        total: emails.len(),
+        emails,
+    }))
+}
+
+/// Discard all captured emails
+/// DELETE /admin/dev/mailbox
+#[utoipa::path(
+    delete,
+    path = "/admin/dev/mailbox",
+    tag = "admin",
+    responses(
+        (status = 200, description = "Captured emails cleared"),
+        (status = 404, description = "Development mailbox disabled"),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn clear_mailbox(
+    State(state): State<AppState>,
+) -> Result<Json<ClearMailboxResponse>, StatusCode> {
+    let mailbox = state.email.mailbox().ok_or(StatusCode::NOT_FOUND)?;
+
+    Ok(Json(ClearMailboxResponse {
+        cleared: mailbox.clear(),
+    }))
+}
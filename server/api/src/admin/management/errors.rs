@@ -0,0 +1,75 @@
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde_json::json;
+use thiserror::Error;
+
+use crate::config::database;
+
+#[derive(Debug, Error)]
+pub enum ManagementError {
+    #[error("User not found")]
+    UserNotFound,
+
+    #[error("Not found: {0}")]
+    NotFound(String),
+
+    #[error("Forbidden: {0}")]
+    Forbidden(String),
+
+    #[error("Validation error: {0}")]
+    Validation(String),
+
+    #[error("Database error: {0}")]
+    Database(#[from] sqlx::Error),
+
+    #[error("Intelligence service error: {0}")]
+    Intelligence(#[from] tonic::Status),
+
+    #[error("Internal server error")]
+    Internal,
+}
+
+impl IntoResponse for ManagementError {
+    fn into_response(self) -> Response {
+        if let ManagementError::Database(e) = &self
+            && database::is_pool_exhausted(e)
+        {
+            return database::pool_exhausted_response();
+        }
+
+        let (status, message) = match &self {
+            ManagementError::UserNotFound => (StatusCode::NOT_FOUND, "User not found".to_string()),
+            ManagementError::NotFound(msg) => (StatusCode::NOT_FOUND, msg.clone()),
+            ManagementError::Forbidden(msg) => (StatusCode::FORBIDDEN, msg.clone()),
+            ManagementError::Validation(msg) => (StatusCode::BAD_REQUEST, msg.clone()),
+            ManagementError::Database(e) => {
+                tracing::error!("Admin management database error: {}", e);
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "Database error".to_string(),
+                )
+            }
+            ManagementError::Intelligence(status) => {
+                tracing::warn!("Admin management Intelligence error: {}", status);
+                (
+                    StatusCode::SERVICE_UNAVAILABLE,
+                    "Intelligence service unavailable".to_string(),
+                )
+            }
+            ManagementError::Internal => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Internal server error".to_string(),
+            ),
+        };
+
+        let body = Json(json!({
+            "error": message,
+            "message": message,
+        }));
+
+        (status, body).into_response()
+    }
+}
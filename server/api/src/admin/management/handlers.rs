@@ -1,65 +1,284 @@
 use axum::{
-    extract::{Path, Query, State},
+    body::{Body, Bytes},
+    extract::{Extension, Path, Query, State},
+    http::{header, HeaderMap, HeaderValue},
+    response::{IntoResponse, Response},
     Json,
 };
+use futures::TryStreamExt;
+use sqlx::{Postgres, QueryBuilder};
 use tracing::error;
+use uuid::Uuid;
 
+use super::errors::ManagementError;
+use super::timeseries;
 use super::types::*;
+use crate::auth::audit::{self, AuthEventListResponse, AuthEventQuery, AuthEventType, ClientInfo};
+use crate::auth::service::VerificationResend;
+use crate::auth::{
+    AuthError, BulkResendVerificationRequest, BulkResendVerificationResponse, Role,
+    service as auth_service, session,
+};
+use crate::chat::feedback::{self, FeedbackListResponse, FeedbackQuery};
+use crate::chat::quota;
+use crate::email::failures::{self, EmailQueueQuery};
 use crate::gateway::AppState;
+use crate::gateway::openapi::ErrorBody;
+use crate::middleware::ClientIp;
+use crate::user::purge::{self, PurgeError};
 
-/// List users with pagination and search
+/// List users with pagination, search and filters
 /// GET /admin/users
+///
+/// Filters by `search` (email substring), `role`, `verified`,
+/// `created_after`/`created_before` (RFC 3339) and `has_oauth`; the ones in
+/// effect are echoed in `filters_applied`. `sort_by` is `created_at`
+/// (default), `updated_at`, `email`, `role` or `last_active_at`, and `order`
+/// (or `sort_dir`) is `asc` or `desc` (default). Users never seen active sort
+/// last in descending order. Ties are broken by ID so pages are stable.
+#[utoipa::path(
+    get,
+    path = "/admin/users",
+    tag = "admin",
+    responses(
+        (status = 200, description = "Users, filtered and paginated"),
+    ),
+    security(("bearer_auth" = []))
+)]
 pub async fn list_users(
     State(state): State<AppState>,
     Query(params): Query<UserListQuery>,
 ) -> Result<Json<UserListResponse>, String> {
-    let limit = params.limit.unwrap_or(20) as i64;
-    let offset = params.offset.unwrap_or(0) as i64;
+    let limit = params.limit.unwrap_or(20).clamp(1, 100);
+    let offset = params.offset.unwrap_or(0).max(0);
+    let filters = params.filters();
 
-    // Implement search
-    let search_term = params.search.clone();
+    let mut query = QueryBuilder::new(
+        "SELECT id, email, name AS full_name, role::text AS role, \
+         email_verified AS is_verified, created_at, updated_at, last_active_at \
+         FROM users u",
+    );
+    push_user_filters(&mut query, &filters);
+    push_user_order(&mut query, params.sort_by, params.order);
+    query.push(" LIMIT ").push_bind(limit);
+    query.push(" OFFSET ").push_bind(offset);
 
-    let users = sqlx::query_as!(
-        UserAdminView,
+    let users = query
+        .build_query_as::<UserAdminView>()
+        .fetch_all(&state.db)
+        .await
+        .map_err(|e| {
+            error!("Failed to fetch users: {}", e);
+            e.to_string()
+        })?;
+
+    let mut count = QueryBuilder::new("SELECT count(*) FROM users u");
+    push_user_filters(&mut count, &filters);
+    let total_count: i64 = count
+        .build_query_scalar()
+        .fetch_one(&state.db)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    Ok(Json(UserListResponse {
+        filters_applied: filters,
+        ..UserListResponse::new(users, total_count, limit, offset)
+    }))
+}
+
+/// Append a WHERE clause for the filters; every value is a bind parameter
+fn push_user_filters(query: &mut QueryBuilder<'_, Postgres>, filters: &UserFilters) {
+    query.push(" WHERE TRUE");
+    if let Some(search) = &filters.search {
+        query
+            .push(" AND u.email ILIKE '%' || ")
+            .push_bind(search.clone())
+            .push(" || '%'");
+    }
+    if let Some(role) = filters.role {
+        query.push(" AND u.role = ").push_bind(role);
+    }
+    if let Some(verified) = filters.verified {
+        query.push(" AND u.email_verified = ").push_bind(verified);
+    }
+    if let Some(created_after) = filters.created_after {
+        query.push(" AND u.created_at >= ").push_bind(created_after);
+    }
+    if let Some(created_before) = filters.created_before {
+        query.push(" AND u.created_at < ").push_bind(created_before);
+    }
+    if let Some(has_oauth) = filters.has_oauth {
+        query
+            .push(if has_oauth { " AND EXISTS" } else { " AND NOT EXISTS" })
+            .push(" (SELECT 1 FROM accounts a WHERE a.user_id = u.id)");
+    }
+}
+
+/// Append the ORDER BY clause; both parts come from allowlisted enums
+fn push_user_order(
+    query: &mut QueryBuilder<'_, Postgres>,
+    sort_by: UserSortField,
+    order: SortOrder,
+) {
+    let direction = order.as_sql();
+    query.push(format!(" ORDER BY u.{} {}", sort_by.column(), direction));
+    if sort_by == UserSortField::LastActiveAt && !order.is_asc() {
+        query.push(" NULLS LAST");
+    }
+    query.push(format!(", u.id {}", direction));
+}
+
+/// Columns of the user export, in `UserAdminView` field order
+const USER_EXPORT_COLUMNS: [&str; 8] = [
+    "id",
+    "email",
+    "full_name",
+    "role",
+    "is_verified",
+    "created_at",
+    "updated_at",
+    "last_active_at",
+];
+
+/// Export rows are sent once this much CSV has accumulated
+const EXPORT_CHUNK_BYTES: usize = 64 * 1024;
+
+/// Export users as CSV
+/// GET /admin/users/export
+///
+/// Filters by `search` (email), `role` and `verified`. Rows are written as
+/// they are read from the database, so large exports are never held in
+/// memory. `X-Total-Count` is the number of matching users.
+#[utoipa::path(
+    get,
+    path = "/admin/users/export",
+    tag = "admin",
+    responses(
+        (status = 200, description = "Users as CSV or JSON"),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn export_users(
+    State(state): State<AppState>,
+    Query(params): Query<UserExportQuery>,
+) -> Result<Response, ManagementError> {
+    let ExportFormat::Csv = params.format;
+    let role = params.role.map(|role| role.to_string());
+
+    let total_count = sqlx::query_scalar!(
         r#"
-        SELECT 
-            id, email as "email!", name as "full_name?", role::text as "role!", email_verified as "is_verified!", created_at as "created_at!", updated_at as "updated_at!"
+        SELECT COUNT(*) AS "count!"
         FROM users
-        WHERE ($3::text IS NULL OR email ILIKE '%' || $3 || '%')
-        ORDER BY created_at DESC
-        LIMIT $1 OFFSET $2
+        WHERE ($1::text IS NULL OR email ILIKE '%' || $1 || '%')
+          AND ($2::text IS NULL OR role::text = $2)
+          AND ($3::bool IS NULL OR email_verified = $3)
         "#,
-        limit,
-        offset,
-        search_term
-    )
-    .fetch_all(&state.db)
-    .await
-    .map_err(|e| {
-        error!("Failed to fetch users: {}", e);
-        e.to_string()
-    })?;
-
-    // Get total count (filtered)
-    let total_count = sqlx::query_scalar!(
-        "SELECT count(*) FROM users WHERE ($1::text IS NULL OR email ILIKE '%' || $1 || '%')",
-        search_term
+        params.search,
+        role,
+        params.verified
     )
     .fetch_one(&state.db)
-    .await
-    .map_err(|e| e.to_string())?
-    .unwrap_or(0);
+    .await?;
 
-    Ok(Json(UserListResponse {
-        users,
-        total_count,
-        limit: limit as i32,
-        offset: offset as i32,
-    }))
+    let db = state.db.clone();
+    let stream = async_stream::try_stream! {
+        let mut writer = csv_writer();
+        writer.write_record(USER_EXPORT_COLUMNS).map_err(std::io::Error::other)?;
+
+        let mut users = sqlx::query_as!(
+            UserAdminView,
+            r#"
+            SELECT
+                id, email as "email!", name as "full_name?", role::text as "role!", email_verified as "is_verified!", created_at as "created_at!", updated_at as "updated_at!", last_active_at
+            FROM users
+            WHERE ($1::text IS NULL OR email ILIKE '%' || $1 || '%')
+              AND ($2::text IS NULL OR role::text = $2)
+              AND ($3::bool IS NULL OR email_verified = $3)
+            ORDER BY created_at, id
+            "#,
+            params.search,
+            role,
+            params.verified
+        )
+        .fetch(&db);
+
+        while let Some(user) = users.try_next().await.map_err(std::io::Error::other)? {
+            writer.serialize(csv_safe_user(user)).map_err(std::io::Error::other)?;
+            writer.flush()?;
+            if writer.get_ref().len() >= EXPORT_CHUNK_BYTES {
+                yield take_csv(&mut writer)?;
+            }
+        }
+
+        yield take_csv(&mut writer)?;
+    };
+
+    let headers = [
+        (header::CONTENT_TYPE, HeaderValue::from_static("text/csv; charset=utf-8")),
+        (
+            header::CONTENT_DISPOSITION,
+            HeaderValue::from_static("attachment; filename=\"users-export.csv\""),
+        ),
+        (
+            header::HeaderName::from_static("x-total-count"),
+            HeaderValue::from(total_count),
+        ),
+    ];
+
+    let body = Body::from_stream(stream.map_err(|e: std::io::Error| e));
+    Ok((headers, body).into_response())
+}
+
+/// CSV writer for the export; the header row is written explicitly so that
+/// an export with no users still has one
+fn csv_writer() -> csv::Writer<Vec<u8>> {
+    csv::WriterBuilder::new()
+        .has_headers(false)
+        .from_writer(Vec::new())
+}
+
+/// Quote a cell a spreadsheet would otherwise run as a formula
+///
+/// Cells starting with `=`, `+`, `-` or `@` (or a tab or carriage return,
+/// which some spreadsheets skip) get a leading `'`.
+fn csv_safe(value: String) -> String {
+    if value.starts_with(['=', '+', '-', '@', '\t', '\r']) {
+        format!("'{}", value)
+    } else {
+        value
+    }
+}
+
+/// The user with its free-text fields made safe to open in a spreadsheet
+fn csv_safe_user(user: UserAdminView) -> UserAdminView {
+    UserAdminView {
+        email: csv_safe(user.email),
+        full_name: user.full_name.map(csv_safe),
+        ..user
+    }
+}
+
+/// Take the CSV written so far, leaving a fresh writer in its place
+fn take_csv(writer: &mut csv::Writer<Vec<u8>>) -> std::io::Result<Bytes> {
+    std::mem::replace(writer, csv_writer())
+        .into_inner()
+        .map(Bytes::from)
+        .map_err(|e| e.into_error())
 }
 
 /// Get single user details
 /// GET /admin/users/{id}
+#[utoipa::path(
+    get,
+    path = "/admin/users/{id}",
+    tag = "admin",
+    params(("id" = Uuid, Path, description = "User ID")),
+    responses(
+        (status = 200, description = "User"),
+        (status = 404, description = "User not found", body = ErrorBody),
+    ),
+    security(("bearer_auth" = []))
+)]
 pub async fn get_user(
     State(state): State<AppState>,
     Path(user_id): Path<uuid::Uuid>,
@@ -68,7 +287,7 @@ pub async fn get_user(
         UserAdminView,
         r#"
         SELECT 
-            id, email as "email!", name as "full_name?", role::text as "role!", email_verified as "is_verified!", created_at as "created_at!", updated_at as "updated_at!"
+            id, email as "email!", name as "full_name?", role::text as "role!", email_verified as "is_verified!", created_at as "created_at!", updated_at as "updated_at!", last_active_at
         FROM users
         WHERE id = $1
         "#,
@@ -84,41 +303,121 @@ pub async fn get_user(
     }
 }
 
+/// Get a user's activity totals
+/// GET /admin/users/{id}/usage
+///
+/// Counts come from aggregate queries; resources are counted by the
+/// intelligence service and reported as `null` if it is unavailable.
+#[utoipa::path(
+    get,
+    path = "/admin/users/{id}/usage",
+    tag = "admin",
+    params(("id" = Uuid, Path, description = "User ID")),
+    responses(
+        (status = 200, description = "User's chat and token usage"),
+        (status = 404, description = "User not found", body = ErrorBody),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn get_user_usage(
+    State(state): State<AppState>,
+    Path(user_id): Path<Uuid>,
+) -> Result<Json<UserUsageView>, ManagementError> {
+    // Conversations and documents store the user ID as text
+    let owner = user_id.to_string();
+
+    let usage = sqlx::query!(
+        r#"
+        SELECT
+            u.last_active_at,
+            (SELECT COUNT(*) FROM conversations c WHERE c.user_id = $2) AS "conversation_count!",
+            (SELECT COUNT(*)
+             FROM chat_messages m
+             JOIN conversations c ON c.id = m.conversation_id
+             WHERE c.user_id = $2) AS "message_count!",
+            (SELECT COUNT(*) FROM documents d WHERE d.user_id = $2) AS "resource_count!",
+            (SELECT COALESCE(SUM(tokens_used), 0)::BIGINT
+             FROM user_token_usage t
+             WHERE t.user_id = u.id) AS "total_tokens_used!"
+        FROM users u
+        WHERE u.id = $1
+        "#,
+        user_id,
+        owner
+    )
+    .fetch_optional(&state.db)
+    .await?
+    .ok_or(ManagementError::UserNotFound)?;
+
+    Ok(Json(UserUsageView {
+        user_id,
+        conversation_count: usage.conversation_count,
+        message_count: usage.message_count,
+        resource_count: usage.resource_count,
+        last_active_at: usage.last_active_at,
+        total_tokens_used: usage.total_tokens_used,
+    }))
+}
+
 /// Update user role
 /// PATCH /admin/users/{id}/role
+#[utoipa::path(
+    patch,
+    path = "/admin/users/{id}/role",
+    tag = "admin",
+    params(("id" = Uuid, Path, description = "User ID")),
+    responses(
+        (status = 200, description = "User with the new role"),
+        (status = 404, description = "User not found", body = ErrorBody),
+    ),
+    security(("bearer_auth" = []))
+)]
 pub async fn update_user_role(
     State(state): State<AppState>,
     Path(user_id): Path<uuid::Uuid>,
     Json(req): Json<UpdateRoleRequest>,
-) -> Result<Json<UserAdminView>, String> {
+) -> Result<Json<UserAdminView>, ManagementError> {
     let user = sqlx::query_as!(
         UserAdminView,
         r#"
         UPDATE users
         SET role = $2::text::user_role, updated_at = NOW()
         WHERE id = $1
-        RETURNING id, email as "email!", name as "full_name?", role::text as "role!", email_verified as "is_verified!", created_at as "created_at!", updated_at as "updated_at!"
+        RETURNING id, email as "email!", name as "full_name?", role::text as "role!", email_verified as "is_verified!", created_at as "created_at!", updated_at as "updated_at!", last_active_at
         "#,
         user_id,
         req.role.to_string()
     )
     .fetch_optional(&state.db)
-    .await
-    .map_err(|e| e.to_string())?;
+    .await?
+    .ok_or(ManagementError::UserNotFound)?;
 
-    match user {
-        Some(u) => Ok(Json(u)),
-        None => Err("User not found".to_string()),
-    }
+    Ok(Json(user))
 }
 
 /// Delete user (Hard Delete)
 /// DELETE /admin/users/{id}
+#[utoipa::path(
+    delete,
+    path = "/admin/users/{id}",
+    tag = "admin",
+    params(("id" = Uuid, Path, description = "User ID")),
+    responses(
+        (status = 200, description = "User deleted"),
+        (status = 404, description = "User not found", body = ErrorBody),
+    ),
+    security(("bearer_auth" = []))
+)]
 pub async fn delete_user(
     State(state): State<AppState>,
     Path(user_id): Path<uuid::Uuid>,
 ) -> Result<Json<serde_json::Value>, String> {
-    // Check if user exists first? Nah, just delete.
+    // Drop sessions first so cached ones are evicted too (the cascade would not)
+    let revoked = session::invalidate_all_user_sessions(&state.db, state.cache.as_ref(), user_id)
+        .await
+        .map_err(|e| e.to_string())?;
+    tracing::info!(%user_id, revoked, "Revoked sessions of deleted user");
+
     let result = sqlx::query!("DELETE FROM users WHERE id = $1", user_id)
         .execute(&state.db)
         .await
@@ -136,6 +435,15 @@ pub async fn delete_user(
 
 /// Get system stats
 /// GET /admin/stats
+#[utoipa::path(
+    get,
+    path = "/admin/stats",
+    tag = "admin",
+    responses(
+        (status = 200, description = "System totals"),
+    ),
+    security(("bearer_auth" = []))
+)]
 pub async fn get_stats(State(state): State<AppState>) -> Result<Json<AdminStats>, String> {
     let users_count = sqlx::query_scalar!("SELECT count(*) FROM users")
         .fetch_one(&state.db)
@@ -144,7 +452,7 @@ pub async fn get_stats(State(state): State<AppState>) -> Result<Json<AdminStats>
         .unwrap_or(0);
 
     let active_24h = sqlx::query_scalar!(
-        "SELECT count(*) FROM users WHERE updated_at > NOW() - INTERVAL '24 hours'"
+        "SELECT count(*) FROM users WHERE last_active_at > NOW() - INTERVAL '24 hours'"
     )
     .fetch_one(&state.db)
     .await
@@ -163,10 +471,818 @@ pub async fn get_stats(State(state): State<AppState>) -> Result<Json<AdminStats>
         .map_err(|e| e.to_string())?
         .unwrap_or(0);
 
+    let positive_feedback_rate = feedback::positive_feedback_rate(&state.db)
+        .await
+        .map_err(|e| e.to_string())?;
+
     Ok(Json(AdminStats {
         total_users: users_count as i32,
         active_users_24h: active_24h as i32,
         total_conversations: total_conversations as i32,
         total_messages: total_messages as i32,
+        positive_feedback_rate,
+    }))
+}
+
+/// Bucketed counts of signups, messages or conversations for trend charts
+/// GET /admin/stats/timeseries
+///
+/// `metric` is `signups`, `messages` or `conversations`; `interval` (or
+/// `granularity`) is `hour`, `day` (default) or `week`. The series covers
+/// `from` to `to` (RFC 3339, `to` defaulting to now), or `range` back from
+/// `to`, e.g. `24h`, `30d` (default) or `12w`, for up to three years.
+/// Buckets are in UTC and every bucket in the range is returned, with a zero
+/// count when nothing happened. The body is streamed and, with Redis, cached
+/// for one bucket width.
+#[utoipa::path(
+    get,
+    path = "/admin/stats/timeseries",
+    tag = "admin",
+    responses(
+        (status = 200, description = "Zero-filled buckets for the requested metric"),
+        (status = 400, description = "Invalid metric, interval or range", body = ErrorBody),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn get_stats_timeseries(
+    State(state): State<AppState>,
+    Query(query): Query<TimeseriesQuery>,
+) -> Result<Response, ManagementError> {
+    let window = query
+        .window(chrono::Utc::now())
+        .map_err(ManagementError::Validation)?;
+
+    let key = timeseries::cache_key(query.metric, query.interval, window);
+    let body = match timeseries::cached(state.cache.as_ref(), &key).await {
+        Some(body) => Body::from(body),
+        None => {
+            timeseries::timeseries_body(
+                state.db.clone(),
+                state.cache.clone(),
+                query.metric,
+                query.interval,
+                window,
+            )
+            .await?
+        }
+    };
+
+    Ok(([(header::CONTENT_TYPE, "application/json")], body).into_response())
+}
+
+/// List message feedback
+/// GET /admin/feedback
+#[utoipa::path(
+    get,
+    path = "/admin/feedback",
+    tag = "admin",
+    responses(
+        (status = 200, description = "Message feedback, newest first"),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn list_feedback(
+    State(state): State<AppState>,
+    Query(query): Query<FeedbackQuery>,
+) -> Result<Json<FeedbackListResponse>, ManagementError> {
+    let feedback = feedback::list_feedback(&state.db, &query).await?;
+    Ok(Json(feedback))
+}
+
+/// Email delivery queue depth and, with `failed=true`, undelivered emails
+/// GET /admin/email-queue
+#[utoipa::path(
+    get,
+    path = "/admin/email-queue",
+    tag = "admin",
+    responses(
+        (status = 200, description = "Email queue status and recent failures"),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn get_email_queue(
+    State(state): State<AppState>,
+    Query(query): Query<EmailQueueQuery>,
+) -> Result<Json<EmailQueueResponse>, ManagementError> {
+    let queue = state.email.queue();
+
+    let failed = if query.failed {
+        Some(failures::list_failures(&state.db, &query).await?)
+    } else {
+        None
+    };
+
+    Ok(Json(EmailQueueResponse {
+        queued: queue.queued(),
+        capacity: queue.capacity(),
+        failed,
+    }))
+}
+
+/// Start impersonating a user
+/// POST /admin/users/{id}/impersonate
+///
+/// Creates a 1-hour session for the target user flagged with the admin's ID.
+/// The session can't be refreshed. Admin accounts cannot be impersonated.
+#[utoipa::path(
+    post,
+    path = "/admin/users/{id}/impersonate",
+    tag = "admin",
+    params(("id" = Uuid, Path, description = "User ID")),
+    responses(
+        (status = 200, description = "Session signed in as the user"),
+        (status = 404, description = "User not found", body = ErrorBody),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn impersonate_user(
+    State(state): State<AppState>,
+    Extension(admin_id): Extension<Uuid>,
+    Path(user_id): Path<Uuid>,
+    headers: HeaderMap,
+    client_ip: ClientIp,
+) -> Result<Json<ImpersonateResponse>, ManagementError> {
+    let target = sqlx::query!(
+        r#"
+        SELECT role as "role: Role"
+        FROM users
+        WHERE id = $1 AND deleted_at IS NULL
+        "#,
+        user_id
+    )
+    .fetch_optional(&state.db)
+    .await?
+    .ok_or(ManagementError::UserNotFound)?;
+
+    if target.role == Role::Admin {
+        return Err(ManagementError::Forbidden(
+            "Admin accounts cannot be impersonated".to_string(),
+        ));
+    }
+
+    let client = ClientInfo::new(&headers, client_ip);
+
+    let (session_id, session_token, expires_at) = session::create_impersonation_session(
+        &state.db,
+        user_id,
+        target.role,
+        admin_id,
+        client.ip_address,
+        client.user_agent.clone(),
+    )
+    .await
+    .map_err(|e| {
+        error!("Failed to create impersonation session: {}", e);
+        ManagementError::Internal
+    })?;
+
+    sqlx::query!(
+        r#"
+        INSERT INTO impersonation_audit_log (admin_id, target_user_id, session_id, action, ip_address, user_agent)
+        VALUES ($1, $2, $3, 'start', $4, $5)
+        "#,
+        admin_id,
+        user_id,
+        session_id,
+        client.ip_address,
+        client.user_agent
+    )
+    .execute(&state.db)
+    .await?;
+
+    audit::record(
+        &state.db,
+        Some(user_id),
+        AuthEventType::ImpersonationStart,
+        &client,
+        serde_json::json!({ "admin_id": admin_id, "session_id": session_id }),
+    )
+    .await;
+
+    tracing::warn!(%admin_id, target_user_id = %user_id, "Admin started impersonation session");
+
+    Ok(Json(ImpersonateResponse {
+        session_token,
+        session_id,
+        user_id,
+        expires_at,
     }))
 }
+
+/// Stop all impersonation of a user
+/// DELETE /admin/users/{id}/impersonate
+///
+/// Ends every impersonation session open for the user, whichever admin
+/// started it. The user's own sessions are left alone.
+#[utoipa::path(
+    delete,
+    path = "/admin/users/{id}/impersonate",
+    tag = "admin",
+    params(("id" = Uuid, Path, description = "User ID")),
+    responses(
+        (status = 200, description = "Impersonation sessions revoked"),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn stop_impersonation(
+    State(state): State<AppState>,
+    Extension(admin_id): Extension<Uuid>,
+    Path(user_id): Path<Uuid>,
+    headers: HeaderMap,
+    client_ip: ClientIp,
+) -> Result<Json<RevokeSessionsResponse>, ManagementError> {
+    let exists = sqlx::query_scalar!("SELECT id FROM users WHERE id = $1", user_id)
+        .fetch_optional(&state.db)
+        .await?
+        .is_some();
+    if !exists {
+        return Err(ManagementError::UserNotFound);
+    }
+
+    // Each deleted session is logged as a 'stop' in impersonation_audit_log by trigger
+    let revoked =
+        session::invalidate_impersonation_sessions(&state.db, state.cache.as_ref(), user_id)
+            .await
+            .map_err(|e| {
+                error!("Failed to end impersonation of user {}: {}", user_id, e);
+                ManagementError::Internal
+            })?;
+
+    if revoked > 0 {
+        audit::record(
+            &state.db,
+            Some(user_id),
+            AuthEventType::ImpersonationStop,
+            &ClientInfo::new(&headers, client_ip),
+            serde_json::json!({ "stopped_by": admin_id, "count": revoked }),
+        )
+        .await;
+    }
+
+    tracing::warn!(
+        %admin_id,
+        target_user_id = %user_id,
+        revoked,
+        "Admin ended impersonation sessions"
+    );
+
+    Ok(Json(RevokeSessionsResponse { user_id, revoked }))
+}
+
+/// End all of a user's sessions
+/// POST /admin/users/{id}/revoke-sessions
+///
+/// Signs the user out everywhere, e.g. after disabling a compromised account.
+/// JWT access tokens stop working once the denylist next refreshes.
+#[utoipa::path(
+    post,
+    path = "/admin/users/{id}/revoke-sessions",
+    tag = "admin",
+    params(("id" = Uuid, Path, description = "User ID")),
+    responses(
+        (status = 200, description = "All of the user's sessions revoked"),
+        (status = 404, description = "User not found", body = ErrorBody),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn revoke_user_sessions(
+    State(state): State<AppState>,
+    Extension(admin_id): Extension<Uuid>,
+    Path(user_id): Path<Uuid>,
+    headers: HeaderMap,
+    client_ip: ClientIp,
+) -> Result<Json<RevokeSessionsResponse>, ManagementError> {
+    let revoked = end_all_sessions(&state, user_id).await?;
+
+    audit::record(
+        &state.db,
+        Some(user_id),
+        AuthEventType::SessionRevoked,
+        &ClientInfo::new(&headers, client_ip),
+        serde_json::json!({ "revoked_by": admin_id, "count": revoked }),
+    )
+    .await;
+
+    tracing::warn!(
+        %admin_id,
+        target_user_id = %user_id,
+        revoked,
+        "Admin revoked all user sessions"
+    );
+
+    Ok(Json(RevokeSessionsResponse { user_id, revoked }))
+}
+
+/// Force a user to sign out everywhere
+/// DELETE /admin/users/{id}/sessions
+///
+/// For compromised accounts that should be locked out without being deleted.
+/// Recorded in the user's events as `admin_force_signout`.
+#[utoipa::path(
+    delete,
+    path = "/admin/users/{id}/sessions",
+    tag = "admin",
+    params(("id" = Uuid, Path, description = "User ID")),
+    responses(
+        (status = 200, description = "Sessions ended; `revoked_count` is 0 if there were none"),
+        (status = 404, description = "User not found", body = ErrorBody),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn force_signout_user(
+    State(state): State<AppState>,
+    Extension(admin_id): Extension<Uuid>,
+    Path(user_id): Path<Uuid>,
+    headers: HeaderMap,
+    client_ip: ClientIp,
+) -> Result<Json<ForceSignoutResponse>, ManagementError> {
+    let revoked_count = end_all_sessions(&state, user_id).await?;
+
+    audit::record(
+        &state.db,
+        Some(user_id),
+        AuthEventType::AdminForceSignout,
+        &ClientInfo::new(&headers, client_ip),
+        serde_json::json!({ "admin_id": admin_id, "revoked_count": revoked_count }),
+    )
+    .await;
+
+    tracing::warn!(
+        %admin_id,
+        target_user_id = %user_id,
+        revoked_count,
+        "Admin forced user signout"
+    );
+
+    Ok(Json(ForceSignoutResponse { revoked_count }))
+}
+
+/// Purge a user and their Intelligence-side data now
+/// POST /admin/users/{id}/purge
+///
+/// Skips the recovery window of a soft-deleted account; active accounts,
+/// including the admin's own, can't be purged. The user's conversations and
+/// personal resources are deleted in the Intelligence service and their
+/// avatar from storage first; if that fails nothing is removed here and the
+/// purge can be retried. Sessions end only once the purge has succeeded.
+#[utoipa::path(
+    post,
+    path = "/admin/users/{id}/purge",
+    tag = "admin",
+    params(("id" = Uuid, Path, description = "User ID")),
+    responses(
+        (status = 200, description = "User purged, with counts of deleted Intelligence data"),
+        (status = 403, description = "Account isn't deleted", body = ErrorBody),
+        (status = 404, description = "User not found", body = ErrorBody),
+        (status = 503, description = "Intelligence service unavailable", body = ErrorBody),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn purge_user(
+    State(state): State<AppState>,
+    Extension(admin_id): Extension<Uuid>,
+    Path(user_id): Path<Uuid>,
+) -> Result<Json<PurgeUserResponse>, ManagementError> {
+    let deleted_at = sqlx::query_scalar!("SELECT deleted_at FROM users WHERE id = $1", user_id)
+        .fetch_optional(&state.db)
+        .await?
+        .ok_or(ManagementError::UserNotFound)?;
+    if deleted_at.is_none() {
+        return Err(ManagementError::Forbidden(
+            "Only deleted accounts can be purged".to_string(),
+        ));
+    }
+
+    let targets = purge::PurgeTargets {
+        client: state.intelligence_client.clone(),
+        storage: state.storage.clone(),
+        cache: state.cache.clone(),
+        api_url: state.config.storage.public_url.clone(),
+    };
+    let purged = purge::purge_account(&state.db, &targets, user_id)
+        .await
+        .map_err(|e| match e {
+            PurgeError::Database(e) => ManagementError::Database(e),
+            PurgeError::Intelligence(status) => ManagementError::Intelligence(status),
+            PurgeError::Storage(e) => {
+                error!("Failed to delete avatar of purged user: {}", e);
+                ManagementError::Internal
+            }
+        })?
+        .ok_or(ManagementError::UserNotFound)?;
+
+    tracing::warn!(
+        %admin_id,
+        target_user_id = %user_id,
+        conversations = purged.conversations,
+        resources = purged.resources,
+        "Admin purged user"
+    );
+
+    Ok(Json(PurgeUserResponse {
+        user_id,
+        conversations: purged.conversations,
+        resources: purged.resources,
+    }))
+}
+
+/// Delete every session of an existing user, returning how many there were
+async fn end_all_sessions(state: &AppState, user_id: Uuid) -> Result<u64, ManagementError> {
+    let exists = sqlx::query_scalar!("SELECT id FROM users WHERE id = $1", user_id)
+        .fetch_optional(&state.db)
+        .await?
+        .is_some();
+    if !exists {
+        return Err(ManagementError::UserNotFound);
+    }
+
+    session::invalidate_all_user_sessions(&state.db, state.cache.as_ref(), user_id)
+        .await
+        .map_err(|e| {
+            error!("Failed to revoke sessions for user {}: {}", user_id, e);
+            ManagementError::Internal
+        })
+}
+
+/// Set a user's daily token quota and monthly token budget
+/// PUT /admin/users/{id}/quota
+#[utoipa::path(
+    put,
+    path = "/admin/users/{id}/quota",
+    tag = "admin",
+    params(("id" = Uuid, Path, description = "User ID")),
+    responses(
+        (status = 200, description = "Quota and budget in effect"),
+        (status = 400, description = "Negative limit", body = ErrorBody),
+        (status = 404, description = "User not found", body = ErrorBody),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn update_user_quota(
+    State(state): State<AppState>,
+    Path(user_id): Path<Uuid>,
+    Json(req): Json<UpdateQuotaRequest>,
+) -> Result<Json<UserQuotaView>, ManagementError> {
+    if req.max_tokens_per_day.as_set().is_some_and(|limit| *limit < 0) {
+        return Err(ManagementError::Validation(
+            "max_tokens_per_day must not be negative".to_string(),
+        ));
+    }
+    if req.max_tokens_per_month.as_set().is_some_and(|budget| *budget < 0) {
+        return Err(ManagementError::Validation(
+            "max_tokens_per_month must not be negative".to_string(),
+        ));
+    }
+
+    let exists = sqlx::query_scalar!("SELECT id FROM users WHERE id = $1", user_id)
+        .fetch_optional(&state.db)
+        .await?
+        .is_some();
+    if !exists {
+        return Err(ManagementError::UserNotFound);
+    }
+
+    let (update_limit, limit) = req.max_tokens_per_day.into_update();
+    if update_limit {
+        quota::set_daily_limit(&state.db, user_id, limit).await?;
+    }
+    let (update_budget, budget) = req.max_tokens_per_month.into_update();
+    if update_budget {
+        quota::set_monthly_limit(&state.db, user_id, budget).await?;
+    }
+    let quota = quota::get_daily_quota(&state.db, user_id).await?;
+    let budget =
+        quota::get_monthly_budget(&state.db, user_id, state.config.chat.monthly_token_budget)
+            .await?;
+
+    Ok(Json(UserQuotaView {
+        user_id,
+        max_tokens_per_day: quota.max_tokens_per_day,
+        tokens_used_today: quota.tokens_used,
+        token_budget: budget.token_budget,
+        tokens_used_this_month: budget.tokens_used,
+    }))
+}
+
+/// List a user's authentication events
+/// GET /admin/users/{id}/events
+#[utoipa::path(
+    get,
+    path = "/admin/users/{id}/events",
+    tag = "admin",
+    params(
+        ("id" = Uuid, Path, description = "User ID"),
+        AuthEventQuery,
+    ),
+    responses(
+        (
+            status = 200,
+            description = "User's authentication events, newest first",
+            body = AuthEventListResponse
+        ),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn get_user_events(
+    State(state): State<AppState>,
+    Path(user_id): Path<Uuid>,
+    Query(query): Query<AuthEventQuery>,
+) -> Result<Json<AuthEventListResponse>, ManagementError> {
+    let events = audit::list_user_events(&state.db, user_id, &query).await?;
+    Ok(Json(events))
+}
+
+/// Re-send verification emails to unverified users created in a time window
+/// POST /admin/users/resend-verifications
+#[utoipa::path(
+    post,
+    path = "/admin/users/resend-verifications",
+    tag = "admin",
+    request_body = BulkResendVerificationRequest,
+    responses(
+        (
+            status = 200,
+            description = "Verification emails queued",
+            body = BulkResendVerificationResponse
+        ),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn resend_verifications(
+    State(state): State<AppState>,
+    Json(req): Json<BulkResendVerificationRequest>,
+) -> Result<Json<BulkResendVerificationResponse>, ManagementError> {
+    if let (Some(after), Some(before)) = (req.created_after, req.created_before)
+        && after >= before
+    {
+        return Err(ManagementError::Validation(
+            "created_after must be earlier than created_before".to_string(),
+        ));
+    }
+
+    let result = auth_service::resend_verification_emails(&state.db, &req, &state.email).await?;
+    Ok(Json(result))
+}
+
+/// Re-send the verification email to one user
+/// POST /admin/users/{id}/resend-verification
+///
+/// Unlike the public endpoint, the user is looked up by ID and the resend
+/// cooldown doesn't apply.
+#[utoipa::path(
+    post,
+    path = "/admin/users/{id}/resend-verification",
+    tag = "admin",
+    params(("id" = Uuid, Path, description = "User ID")),
+    responses(
+        (status = 200, description = "Verification email queued"),
+        (status = 404, description = "User not found", body = ErrorBody),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn resend_user_verification(
+    State(state): State<AppState>,
+    Extension(admin_id): Extension<Uuid>,
+    Path(user_id): Path<Uuid>,
+) -> Result<Json<AdminResendVerificationResponse>, ManagementError> {
+    let outcome = auth_service::resend_verification_for_user(&state.db, user_id, &state.email)
+        .await
+        .map_err(|e| match e {
+            AuthError::Database(e) => ManagementError::Database(e),
+            _ => ManagementError::Internal,
+        })?
+        .ok_or(ManagementError::UserNotFound)?;
+
+    let response = match outcome {
+        VerificationResend::Sent => {
+            tracing::info!(%admin_id, target_user_id = %user_id, "Admin resent verification email");
+            AdminResendVerificationResponse {
+                user_id,
+                sent: true,
+                message: "Verification email sent.".to_string(),
+            }
+        }
+        VerificationResend::AlreadyVerified => AdminResendVerificationResponse {
+            user_id,
+            sent: false,
+            message: "Email is already verified.".to_string(),
+        },
+    };
+
+    Ok(Json(response))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_user_filters_are_bound() {
+        let filters = UserFilters {
+            search: Some("x' OR '1'='1".to_string()),
+            role: Some(Role::Admin),
+            verified: Some(true),
+            created_after: Some("2026-01-01T00:00:00Z".parse().unwrap()),
+            created_before: Some("2026-02-01T00:00:00Z".parse().unwrap()),
+            has_oauth: Some(false),
+        };
+
+        let mut query = QueryBuilder::new("SELECT count(*) FROM users u");
+        push_user_filters(&mut query, &filters);
+        push_user_order(&mut query, UserSortField::LastActiveAt, SortOrder::Desc);
+
+        let sql = query.sql();
+        assert!(!sql.contains("OR '1'='1"));
+        assert!(sql.contains("u.email ILIKE '%' || $1 || '%'"));
+        assert!(sql.contains("u.role = $2 AND u.email_verified = $3"));
+        assert!(sql.contains("u.created_at >= $4 AND u.created_at < $5"));
+        assert!(sql.contains("AND NOT EXISTS (SELECT 1 FROM accounts a WHERE a.user_id = u.id)"));
+        assert!(sql.ends_with("ORDER BY u.last_active_at DESC NULLS LAST, u.id DESC"));
+
+        let mut query = QueryBuilder::new("SELECT count(*) FROM users u");
+        push_user_filters(&mut query, &UserFilters::default());
+        assert_eq!(query.sql(), "SELECT count(*) FROM users u WHERE TRUE");
+    }
+
+    #[test]
+    fn test_csv_export_rows() {
+        let created_at = "2026-01-02T03:04:05Z".parse().unwrap();
+        let user = UserAdminView {
+            id: Uuid::nil(),
+            email: "ada@example.com".to_string(),
+            full_name: None,
+            role: "admin".to_string(),
+            is_verified: true,
+            created_at,
+            updated_at: created_at,
+            last_active_at: None,
+        };
+        let mut writer = csv_writer();
+        writer.write_record(USER_EXPORT_COLUMNS).unwrap();
+        writer.serialize(&user).unwrap();
+        let csv = take_csv(&mut writer).unwrap();
+
+        assert_eq!(
+            std::str::from_utf8(&csv).unwrap(),
+            "id,email,full_name,role,is_verified,created_at,updated_at,last_active_at\n\
+             00000000-0000-0000-0000-000000000000,ada@example.com,,admin,true,\
+             2026-01-02T03:04:05Z,2026-01-02T03:04:05Z,\n"
+        );
+
+        // The replacement writer starts empty and quotes fields with commas
+        writer
+            .serialize(&UserAdminView {
+                full_name: Some("Lovelace, Ada".to_string()),
+                ..user
+            })
+            .unwrap();
+        let csv = take_csv(&mut writer).unwrap();
+        assert!(std::str::from_utf8(&csv).unwrap().contains(",\"Lovelace, Ada\","));
+    }
+
+    #[test]
+    fn test_csv_export_neutralizes_formulas() {
+        for (value, expected) in [
+            ("=HYPERLINK(\"http://evil\")", "'=HYPERLINK(\"http://evil\")"),
+            ("+1 555", "'+1 555"),
+            ("-2+3", "'-2+3"),
+            ("@SUM(A1)", "'@SUM(A1)"),
+            ("\t=1", "'\t=1"),
+            ("Ada", "Ada"),
+            ("a=b", "a=b"),
+            ("", ""),
+        ] {
+            assert_eq!(csv_safe(value.to_string()), expected, "{:?}", value);
+        }
+
+        let created_at = "2026-01-02T03:04:05Z".parse().unwrap();
+        let user = csv_safe_user(UserAdminView {
+            id: Uuid::nil(),
+            email: "=1+1@example.com".to_string(),
+            full_name: Some("@cmd".to_string()),
+            role: "user".to_string(),
+            is_verified: false,
+            created_at,
+            updated_at: created_at,
+            last_active_at: None,
+        });
+        assert_eq!(user.email, "'=1+1@example.com");
+        assert_eq!(user.full_name.as_deref(), Some("'@cmd"));
+    }
+
+    #[sqlx::test]
+    async fn test_force_signout_rejects_existing_tokens(db: sqlx::PgPool) {
+        use axum::{
+            body::Body,
+            extract::ConnectInfo,
+            http::{Request, StatusCode},
+        };
+        use std::net::SocketAddr;
+        use tower::ServiceExt;
+
+        let mut tokens = Vec::new();
+        let mut user_ids = Vec::new();
+        let accounts = [("admin@example.com", Role::Admin), ("user@example.com", Role::User)];
+        for (email, role) in accounts {
+            let user_id = sqlx::query_scalar!(
+                "INSERT INTO users (email, role) VALUES ($1, $2) RETURNING id",
+                email,
+                role as Role
+            )
+            .fetch_one(&db)
+            .await
+            .unwrap();
+            let (_, token, _) = session::create_session(&db, None, user_id, role, None, None, 0)
+                .await
+                .unwrap();
+            tokens.push(token);
+            user_ids.push(user_id);
+        }
+        let (admin, user, user_id) = (&tokens[0], &tokens[1], user_ids[1]);
+
+        let app = crate::gateway::test_router(db.clone()).await;
+        let send = |method: &str, uri: String, token: &str| {
+            let mut request = Request::builder()
+                .method(method)
+                .uri(uri)
+                .header("authorization", format!("Bearer {}", token))
+                .body(Body::empty())
+                .unwrap();
+            request
+                .extensions_mut()
+                .insert(ConnectInfo(SocketAddr::from(([127, 0, 0, 1], 4000))));
+            app.clone().oneshot(request)
+        };
+
+        let me = send("GET", "/user/me".to_string(), user).await.unwrap();
+        assert_eq!(me.status(), StatusCode::OK);
+
+        let uri = format!("/admin/users/{}/sessions", user_id);
+        let response = send("DELETE", uri.clone(), admin).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["revoked_count"], 1);
+
+        let me = send("GET", "/user/me".to_string(), user).await.unwrap();
+        assert_eq!(me.status(), StatusCode::UNAUTHORIZED);
+
+        let event = sqlx::query_scalar!(
+            "SELECT event_type FROM auth_events WHERE user_id = $1",
+            user_id
+        )
+        .fetch_one(&db)
+        .await
+        .unwrap();
+        assert_eq!(event, "admin_force_signout");
+
+        // Signing out a user with no sessions left isn't an error
+        let response = send("DELETE", uri, admin).await.unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["revoked_count"], 0);
+    }
+
+    #[sqlx::test]
+    async fn test_role_update_of_unknown_user_is_not_found(db: sqlx::PgPool) {
+        use axum::{
+            body::Body,
+            extract::ConnectInfo,
+            http::{Request, StatusCode},
+        };
+        use std::net::SocketAddr;
+        use tower::ServiceExt;
+
+        let admin_id = sqlx::query_scalar!(
+            "INSERT INTO users (email, role) VALUES ('admin@example.com', 'admin') RETURNING id"
+        )
+        .fetch_one(&db)
+        .await
+        .unwrap();
+        let (_, token, _) = session::create_session(&db, None, admin_id, Role::Admin, None, None, 0)
+            .await
+            .unwrap();
+
+        let app = crate::gateway::test_router(db).await;
+        let mut request = Request::patch(format!("/admin/users/{}/role", Uuid::new_v4()))
+            .header("authorization", format!("Bearer {}", token))
+            .header("content-type", "application/json")
+            .body(Body::from(r#"{"role":"admin"}"#))
+            .unwrap();
+        request
+            .extensions_mut()
+            .insert(ConnectInfo(SocketAddr::from(([127, 0, 0, 1], 4000))));
+
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["error"], "User not found");
+    }
+}
@@ -1,4 +1,6 @@
+pub mod errors;
 pub mod handlers;
+pub mod timeseries;
 pub mod types;
 
 pub use handlers::*;
@@ -0,0 +1,300 @@
+//! Admin time series of signups, messages and conversations
+//!
+//! Buckets are read from PostgreSQL as a stream and written straight into
+//! the response, so a long range of hourly buckets is never held in memory
+//! as a whole. With Redis enabled, a finished series is cached for one
+//! bucket width under its metric, interval and bucket-aligned window;
+//! dashboards reloading within the same bucket skip the query, at the cost
+//! of the current bucket's count lagging by up to one bucket.
+//!
+//! The response only ever holds bucket starts and counts, never user IDs
+//! or anything else that identifies an account.
+
+use axum::body::{Body, Bytes};
+use futures::{Stream, StreamExt, TryStreamExt};
+use redis::AsyncCommands;
+use sqlx::PgPool;
+
+use super::types::{StatsInterval, StatsMetric, TimeseriesPoint, TimeseriesWindow};
+use crate::config::cache::RedisPool;
+
+/// Largest series kept in the cache; longer ones are always streamed fresh
+const MAX_CACHED_BYTES: usize = 256 * 1024;
+
+/// Cache key for a series
+pub fn cache_key(metric: StatsMetric, interval: StatsInterval, window: TimeseriesWindow) -> String {
+    format!(
+        "stats:timeseries:{}:{}:{}:{}",
+        metric.as_str(),
+        interval.as_str(),
+        window.from.timestamp(),
+        window.to.timestamp()
+    )
+}
+
+/// The cached response body for a series, if the cache is enabled and has it
+pub async fn cached(cache: Option<&RedisPool>, key: &str) -> Option<Bytes> {
+    let mut conn = cache?.clone();
+    match conn.get::<_, Option<Vec<u8>>>(key).await {
+        Ok(body) => body.map(Bytes::from),
+        Err(e) => {
+            tracing::warn!("Timeseries cache read failed: {}", e);
+            None
+        }
+    }
+}
+
+/// The series as a streamed response body
+///
+/// The query is started and its first bucket read before returning, so a
+/// failing query is reported as an error instead of a truncated 200.
+pub async fn timeseries_body(
+    db: PgPool,
+    cache: Option<RedisPool>,
+    metric: StatsMetric,
+    interval: StatsInterval,
+    window: TimeseriesWindow,
+) -> Result<Body, sqlx::Error> {
+    let mut stream = Box::pin(timeseries_stream(db, cache, metric, interval, window));
+    let first = stream.try_next().await.map_err(|e| match e.downcast::<sqlx::Error>() {
+        Ok(e) => e,
+        Err(e) => sqlx::Error::Io(e),
+    })?;
+
+    Ok(Body::from_stream(
+        futures::stream::iter(first.map(Ok)).chain(stream),
+    ))
+}
+
+/// Stream the series as a JSON document, caching it once complete
+///
+/// The document is `{"metric", "interval", "from", "to", "points"}` with
+/// points oldest first and a zero count for buckets without any events. The
+/// header is only yielded once the first bucket has been read.
+fn timeseries_stream(
+    db: PgPool,
+    cache: Option<RedisPool>,
+    metric: StatsMetric,
+    interval: StatsInterval,
+    window: TimeseriesWindow,
+) -> impl Stream<Item = std::io::Result<Bytes>> {
+    async_stream::try_stream! {
+        let mut rows = sqlx::query!(
+            r#"
+            WITH buckets AS (
+                SELECT generate_series(
+                    date_trunc($1, $2::timestamptz, 'UTC'),
+                    date_trunc($1, $3::timestamptz, 'UTC'),
+                    ('1 ' || $1)::interval
+                ) AS bucket
+            ),
+            bounds AS (
+                SELECT date_trunc($1, $2::timestamptz, 'UTC') AS start,
+                       date_trunc($1, $3::timestamptz, 'UTC') + ('1 ' || $1)::interval AS finish
+            ),
+            events AS (
+                SELECT created_at FROM users, bounds
+                WHERE $4 = 'signups' AND created_at >= start AND created_at < finish
+                UNION ALL
+                SELECT created_at FROM chat_messages, bounds
+                WHERE $4 = 'messages' AND created_at >= start AND created_at < finish
+                UNION ALL
+                SELECT created_at FROM conversations, bounds
+                WHERE $4 = 'conversations' AND created_at >= start AND created_at < finish
+            )
+            SELECT b.bucket as "bucket!", COUNT(e.created_at) as "count!"
+            FROM buckets b
+            LEFT JOIN events e ON date_trunc($1, e.created_at, 'UTC') = b.bucket
+            GROUP BY b.bucket
+            ORDER BY b.bucket
+            "#,
+            interval.as_str(),
+            window.from,
+            window.to,
+            metric.as_str()
+        )
+        .fetch(&db);
+        let mut next = rows.try_next().await.map_err(std::io::Error::other)?;
+
+        let mut body = Vec::new();
+        let header = Bytes::from(json_header(metric, interval, window)?);
+        body.extend_from_slice(&header);
+        yield header;
+
+        let mut first = true;
+        while let Some(row) = next {
+            let point = TimeseriesPoint {
+                bucket: row.bucket,
+                count: row.count,
+            };
+            let chunk = Bytes::from(json_point(&point, first)?);
+            first = false;
+
+            if body.len() <= MAX_CACHED_BYTES {
+                body.extend_from_slice(&chunk);
+            }
+            yield chunk;
+
+            next = rows.try_next().await.map_err(std::io::Error::other)?;
+        }
+
+        let footer = Bytes::from_static(b"]}");
+        body.extend_from_slice(&footer);
+        yield footer;
+
+        if body.len() <= MAX_CACHED_BYTES {
+            let key = cache_key(metric, interval, window);
+            cache_put(cache, &key, body, interval.seconds() as u64).await;
+        }
+    }
+}
+
+async fn cache_put(cache: Option<RedisPool>, key: &str, body: Vec<u8>, ttl_seconds: u64) {
+    let Some(mut conn) = cache else {
+        return;
+    };
+    if let Err(e) = conn.set_ex::<_, _, ()>(key, body, ttl_seconds).await {
+        tracing::warn!("Timeseries cache write failed: {}", e);
+    }
+}
+
+fn json_header(
+    metric: StatsMetric,
+    interval: StatsInterval,
+    window: TimeseriesWindow,
+) -> std::io::Result<String> {
+    Ok(format!(
+        "{{\"metric\":{},\"interval\":{},\"from\":{},\"to\":{},\"points\":[",
+        serde_json::to_string(&metric)?,
+        serde_json::to_string(&interval)?,
+        serde_json::to_string(&window.from)?,
+        serde_json::to_string(&window.to)?
+    ))
+}
+
+fn json_point(point: &TimeseriesPoint, first: bool) -> std::io::Result<String> {
+    let json = serde_json::to_string(point)?;
+    Ok(if first { json } else { format!(",{}", json) })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{DateTime, Duration, TimeZone, Utc};
+
+    #[test]
+    fn test_streamed_chunks_form_one_document() {
+        let from = DateTime::from_timestamp(1_700_006_400, 0).unwrap();
+        let window = TimeseriesWindow {
+            from,
+            to: from + Duration::days(1),
+        };
+        let points = [
+            TimeseriesPoint {
+                bucket: window.from,
+                count: 3,
+            },
+            TimeseriesPoint {
+                bucket: window.to,
+                count: 0,
+            },
+        ];
+
+        let document = [
+            json_header(StatsMetric::Messages, StatsInterval::Day, window).unwrap(),
+            json_point(&points[0], true).unwrap(),
+            json_point(&points[1], false).unwrap(),
+            "]}".to_string(),
+        ]
+        .concat();
+
+        let parsed: serde_json::Value = serde_json::from_str(&document).unwrap();
+        assert_eq!(parsed["metric"], "messages");
+        assert_eq!(parsed["interval"], "day");
+        assert_eq!(parsed["points"][0]["count"], 3);
+        assert_eq!(parsed["points"][1]["count"], 0);
+        assert_eq!(
+            serde_json::from_value::<DateTime<chrono::Utc>>(parsed["to"].clone()).unwrap(),
+            window.to
+        );
+    }
+
+    #[sqlx::test]
+    async fn test_window_bounds_the_counted_events(db: PgPool) {
+        // Owned by the Intelligence service, so not created by our migrations
+        sqlx::query("CREATE TABLE chat_messages (created_at TIMESTAMPTZ NOT NULL)")
+            .execute(&db)
+            .await
+            .unwrap();
+        let day = |d, h| Utc.with_ymd_and_hms(2026, 3, d, h, 0, 0).unwrap();
+        for (i, created_at) in [day(1, 10), day(3, 23), day(4, 1)].into_iter().enumerate() {
+            sqlx::query!(
+                "INSERT INTO users (email, created_at) VALUES ($1, $2)",
+                format!("user{}@example.com", i),
+                created_at
+            )
+            .execute(&db)
+            .await
+            .unwrap();
+        }
+
+        let window = TimeseriesWindow {
+            from: day(1, 12),
+            to: day(3, 12),
+        };
+        let body = timeseries_body(db, None, StatsMetric::Signups, StatsInterval::Day, window)
+            .await
+            .unwrap();
+        let body = axum::body::to_bytes(body, usize::MAX).await.unwrap();
+        let parsed: serde_json::Value = serde_json::from_slice(&body).unwrap();
+
+        let counts: Vec<i64> = parsed["points"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|point| point["count"].as_i64().unwrap())
+            .collect();
+        assert_eq!(counts, vec![1, 0, 1]);
+    }
+
+    #[sqlx::test]
+    async fn test_query_errors_fail_before_the_body_starts(db: PgPool) {
+        // Without the Intelligence service's chat_messages table the query
+        // fails, whichever metric is asked for
+        let from = DateTime::from_timestamp(1_700_006_400, 0).unwrap();
+        let window = TimeseriesWindow {
+            from,
+            to: from + Duration::days(1),
+        };
+        let body =
+            timeseries_body(db, None, StatsMetric::Messages, StatsInterval::Day, window).await;
+        assert!(matches!(body, Err(sqlx::Error::Database(_))));
+    }
+
+    #[test]
+    fn test_cache_key_depends_on_every_input() {
+        let from = DateTime::from_timestamp(1_700_006_400, 0).unwrap();
+        let window = TimeseriesWindow {
+            from,
+            to: from + Duration::days(7),
+        };
+        let key = cache_key(StatsMetric::Signups, StatsInterval::Day, window);
+
+        assert_ne!(
+            key,
+            cache_key(StatsMetric::Messages, StatsInterval::Day, window)
+        );
+        assert_ne!(
+            key,
+            cache_key(StatsMetric::Signups, StatsInterval::Hour, window)
+        );
+        let later = TimeseriesWindow {
+            from,
+            to: window.to + Duration::days(1),
+        };
+        assert_ne!(
+            key,
+            cache_key(StatsMetric::Signups, StatsInterval::Day, later)
+        );
+    }
+}
@@ -1,7 +1,11 @@
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Duration, Utc};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
+use crate::auth::Role;
+use crate::common::patch::Patch;
+use crate::email::failures::EmailFailureList;
+
 // ============================================================================
 // ADMIN STATS
 // ============================================================================
@@ -12,13 +16,159 @@ pub struct AdminStats {
     pub active_users_24h: i32,
     pub total_conversations: i32,
     pub total_messages: i32,
+    /// Share of message feedback that is positive (0.0 with no feedback)
+    pub positive_feedback_rate: f32,
+}
+
+/// Longest range a time series may cover, about three years
+pub const MAX_TIMESERIES_DAYS: i64 = 3 * 366;
+
+#[derive(Debug, Deserialize)]
+pub struct TimeseriesQuery {
+    pub metric: StatsMetric,
+    /// Also accepted as `granularity`
+    #[serde(default, alias = "granularity")]
+    pub interval: StatsInterval,
+    /// `<n>h`, `<n>d` or `<n>w` back from `to`; defaults to `30d`
+    pub range: Option<String>,
+    /// Start of the series, instead of `range`
+    pub from: Option<DateTime<Utc>>,
+    /// End of the series; defaults to (and is capped at) now
+    pub to: Option<DateTime<Utc>>,
+}
+
+/// The buckets a time series covers, by the start of its first and last
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimeseriesWindow {
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+}
+
+impl TimeseriesQuery {
+    /// The buckets to return, checked against the length limit
+    pub fn window(&self, now: DateTime<Utc>) -> Result<TimeseriesWindow, String> {
+        let to = self.to.unwrap_or(now).min(now);
+        let from = match (self.from, self.range.as_deref()) {
+            (Some(_), Some(_)) => return Err("pass either from or range, not both".to_string()),
+            (Some(from), None) => from,
+            (None, range) => to - parse_range(range.unwrap_or("30d"))?,
+        };
+
+        if from >= to {
+            return Err("from must be before to".to_string());
+        }
+        if to - from > Duration::days(MAX_TIMESERIES_DAYS) {
+            return Err(format!(
+                "a series can cover at most {} days",
+                MAX_TIMESERIES_DAYS
+            ));
+        }
+
+        Ok(TimeseriesWindow {
+            from: self.interval.bucket_start(from),
+            to: self.interval.bucket_start(to),
+        })
+    }
+}
+
+/// Parse `<n>h`, `<n>d` or `<n>w` with n > 0
+fn parse_range(range: &str) -> Result<Duration, String> {
+    let invalid = || format!("invalid range '{}', expected e.g. 24h, 30d or 12w", range);
+
+    let unit = range.chars().last().ok_or_else(invalid)?;
+    let amount: i64 = range[..range.len() - unit.len_utf8()]
+        .parse()
+        .map_err(|_| invalid())?;
+    let unit_hours = match unit {
+        'h' => 1,
+        'd' => 24,
+        'w' => 7 * 24,
+        _ => return Err(invalid()),
+    };
+
+    amount
+        .checked_mul(unit_hours)
+        .filter(|hours| (1..=MAX_TIMESERIES_DAYS * 24).contains(hours))
+        .map(Duration::hours)
+        .ok_or_else(|| {
+            format!(
+                "range must be between 1h and {}d, got '{}'",
+                MAX_TIMESERIES_DAYS, range
+            )
+        })
+}
+
+/// Counts available as a time series; anything else is rejected
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StatsMetric {
+    Signups,
+    Messages,
+    Conversations,
+}
+
+impl StatsMetric {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            StatsMetric::Signups => "signups",
+            StatsMetric::Messages => "messages",
+            StatsMetric::Conversations => "conversations",
+        }
+    }
+}
+
+/// Bucket widths, passed to `date_trunc`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StatsInterval {
+    Hour,
+    #[default]
+    Day,
+    Week,
+}
+
+impl StatsInterval {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            StatsInterval::Hour => "hour",
+            StatsInterval::Day => "day",
+            StatsInterval::Week => "week",
+        }
+    }
+
+    pub fn seconds(self) -> i64 {
+        match self {
+            StatsInterval::Hour => 3600,
+            StatsInterval::Day => 86_400,
+            StatsInterval::Week => 7 * 86_400,
+        }
+    }
+
+    /// Start of the UTC bucket containing `at`, as `date_trunc` computes it
+    pub fn bucket_start(self, at: DateTime<Utc>) -> DateTime<Utc> {
+        // The Unix epoch fell on a Thursday and weeks start on Monday
+        let offset = match self {
+            StatsInterval::Week => 3 * 86_400,
+            _ => 0,
+        };
+        let seconds = at.timestamp() + offset;
+        let start = seconds - seconds.rem_euclid(self.seconds()) - offset;
+        DateTime::from_timestamp(start, 0).unwrap_or(at)
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct TimeseriesPoint {
+    /// Start of the bucket (UTC)
+    pub bucket: DateTime<Utc>,
+    pub count: i64,
 }
 
 // ============================================================================
 // USER MANAGEMENT
 // ============================================================================
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, sqlx::FromRow)]
 pub struct UserAdminView {
     pub id: Uuid,
     pub email: String,
@@ -27,24 +177,398 @@ pub struct UserAdminView {
     pub is_verified: bool,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    /// Last authenticated request, to within 5 minutes
+    pub last_active_at: Option<DateTime<Utc>>,
 }
 
 #[derive(Debug, Serialize)]
 pub struct UserListResponse {
     pub users: Vec<UserAdminView>,
+    /// The filters the list was narrowed by, as parsed from the query
+    pub filters_applied: UserFilters,
     pub total_count: i64,
     pub limit: i32,
     pub offset: i32,
+    pub total_pages: i64,
+    pub has_next: bool,
+    pub has_prev: bool,
+}
+
+impl UserListResponse {
+    pub fn new(users: Vec<UserAdminView>, total_count: i64, limit: i64, offset: i64) -> Self {
+        Self {
+            users,
+            filters_applied: UserFilters::default(),
+            total_count,
+            limit: limit as i32,
+            offset: offset as i32,
+            total_pages: (total_count + limit - 1) / limit,
+            has_next: offset + limit < total_count,
+            has_prev: offset > 0,
+        }
+    }
 }
 
 #[derive(Debug, Deserialize)]
 pub struct UserListQuery {
     pub limit: Option<i64>,
     pub offset: Option<i64>,
+    /// Substring of the email
+    pub search: Option<String>,
+    pub role: Option<Role>,
+    pub verified: Option<bool>,
+    pub created_after: Option<DateTime<Utc>>,
+    pub created_before: Option<DateTime<Utc>>,
+    /// Whether the user has linked an OAuth account
+    pub has_oauth: Option<bool>,
+    #[serde(default)]
+    pub sort_by: UserSortField,
+    /// Also accepted as `sort_dir`
+    #[serde(default, alias = "sort_dir")]
+    pub order: SortOrder,
+}
+
+impl UserListQuery {
+    pub fn filters(&self) -> UserFilters {
+        UserFilters {
+            search: self.search.clone(),
+            role: self.role,
+            verified: self.verified,
+            created_after: self.created_after,
+            created_before: self.created_before,
+            has_oauth: self.has_oauth,
+        }
+    }
+}
+
+/// Filters on the user list; unset ones match every user
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct UserFilters {
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub search: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub role: Option<Role>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub verified: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created_after: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created_before: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub has_oauth: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UserExportQuery {
+    #[serde(default)]
+    pub format: ExportFormat,
+    pub search: Option<String>,
+    pub role: Option<Role>,
+    pub verified: Option<bool>,
+}
+
+/// Formats the user export is available in
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    #[default]
+    Csv,
+}
+
+/// Columns the user list can be sorted by; anything else is rejected
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UserSortField {
+    #[default]
+    CreatedAt,
+    UpdatedAt,
+    Email,
+    Role,
+    LastActiveAt,
+}
+
+impl UserSortField {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            UserSortField::CreatedAt => "created_at",
+            UserSortField::UpdatedAt => "updated_at",
+            UserSortField::Email => "email",
+            UserSortField::Role => "role",
+            UserSortField::LastActiveAt => "last_active_at",
+        }
+    }
+
+    /// The SQL expression sorted on
+    pub fn column(self) -> &'static str {
+        match self {
+            UserSortField::Role => "role::text",
+            field => field.as_str(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    Asc,
+    #[default]
+    Desc,
+}
+
+impl SortOrder {
+    pub fn is_asc(self) -> bool {
+        self == SortOrder::Asc
+    }
+
+    pub fn as_sql(self) -> &'static str {
+        match self {
+            SortOrder::Asc => "ASC",
+            SortOrder::Desc => "DESC",
+        }
+    }
 }
 
 #[derive(Debug, Deserialize)]
 pub struct UpdateRoleRequest {
     pub role: String, // "user", "admin", "moderator"
 }
+
+/// Activity totals for one user
+#[derive(Debug, Serialize)]
+pub struct UserUsageView {
+    pub user_id: Uuid,
+    pub conversation_count: i64,
+    pub message_count: i64,
+    /// Ingested documents the user owns
+    pub resource_count: i64,
+    pub last_active_at: Option<DateTime<Utc>>,
+    /// Chat tokens used since the account was created
+    pub total_tokens_used: i64,
+}
+
+// ============================================================================
+// IMPERSONATION
+// ============================================================================
+
+#[derive(Debug, Serialize)]
+pub struct ImpersonateResponse {
+    pub session_token: String,
+    pub session_id: Uuid,
+    pub user_id: Uuid,
+    pub expires_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RevokeSessionsResponse {
+    pub user_id: Uuid,
+    /// Number of sessions that were ended
+    pub revoked: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ForceSignoutResponse {
+    /// Number of sessions that were ended; 0 if the user had none
+    pub revoked_count: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PurgeUserResponse {
+    pub user_id: Uuid,
+    /// Conversations deleted in the Intelligence service
+    pub conversations: u64,
+    /// Personal resources deleted in the Intelligence service
+    pub resources: u64,
+}
+
+// ============================================================================
+// VERIFICATION
+// ============================================================================
+
+#[derive(Debug, Serialize)]
+pub struct AdminResendVerificationResponse {
+    pub user_id: Uuid,
+    /// `false` when the user was already verified
+    pub sent: bool,
+    pub message: String,
+}
+
+// ============================================================================
+// TOKEN QUOTAS
+// ============================================================================
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateQuotaRequest {
+    /// Daily token limit; `null` removes the limit, omit to keep it
+    #[serde(default)]
+    pub max_tokens_per_day: Patch<i64>,
+    /// Monthly token budget; `null` falls back to the default, omit to keep it
+    #[serde(default)]
+    pub max_tokens_per_month: Patch<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UserQuotaView {
+    pub user_id: Uuid,
+    pub max_tokens_per_day: Option<i64>,
+    pub tokens_used_today: i64,
+    /// Budget in effect, the default included
+    pub token_budget: Option<i64>,
+    pub tokens_used_this_month: i64,
+}
+
+// ============================================================================
+// EMAIL QUEUE
+// ============================================================================
+
+#[derive(Debug, Serialize)]
+pub struct EmailQueueResponse {
+    /// Emails waiting for the delivery worker
+    pub queued: usize,
+    pub capacity: usize,
+    /// Undelivered emails, when requested with `failed=true`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub failed: Option<EmailFailureList>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quota_fields_left_out_are_unchanged() {
+        let monthly_only: UpdateQuotaRequest =
+            serde_json::from_str(r#"{"max_tokens_per_month": 5000}"#).unwrap();
+        assert!(monthly_only.max_tokens_per_day.is_unchanged());
+        assert_eq!(monthly_only.max_tokens_per_month.as_set(), Some(&5000));
+
+        let cleared: UpdateQuotaRequest =
+            serde_json::from_str(r#"{"max_tokens_per_day": null}"#).unwrap();
+        assert!(matches!(cleared.max_tokens_per_day, Patch::Clear));
+        assert!(cleared.max_tokens_per_month.is_unchanged());
+    }
+
+    #[test]
+    fn test_user_list_page_flags() {
+        let page = UserListResponse::new(Vec::new(), 45, 20, 0);
+        assert_eq!(page.total_pages, 3);
+        assert!(page.has_next);
+        assert!(!page.has_prev);
+
+        let page = UserListResponse::new(Vec::new(), 45, 20, 40);
+        assert!(!page.has_next);
+        assert!(page.has_prev);
+
+        let page = UserListResponse::new(Vec::new(), 40, 20, 20);
+        assert_eq!(page.total_pages, 2);
+        assert!(!page.has_next);
+
+        let page = UserListResponse::new(Vec::new(), 0, 20, 0);
+        assert_eq!(page.total_pages, 0);
+        assert!(!page.has_next && !page.has_prev);
+    }
+
+    #[test]
+    fn test_sort_params_allowlisted() {
+        let query: UserListQuery =
+            serde_json::from_value(serde_json::json!({"sort_by": "email", "order": "asc"}))
+                .unwrap();
+        assert_eq!(query.sort_by, UserSortField::Email);
+        assert!(query.order.is_asc());
+
+        let query: UserListQuery = serde_json::from_value(serde_json::json!({})).unwrap();
+        assert_eq!(query.sort_by, UserSortField::CreatedAt);
+        assert_eq!(query.order, SortOrder::Desc);
+        assert_eq!(query.filters(), UserFilters::default());
+
+        let query: UserListQuery = serde_json::from_value(
+            serde_json::json!({"sort_by": "updated_at", "sort_dir": "asc"}),
+        )
+        .unwrap();
+        assert_eq!(query.sort_by.column(), "updated_at");
+        assert!(query.order.is_asc());
+        assert_eq!(UserSortField::Role.column(), "role::text");
+
+        for bad in ["password_hash", "email; DROP TABLE users", "EMAIL"] {
+            let result: Result<UserListQuery, _> =
+                serde_json::from_value(serde_json::json!({"sort_by": bad}));
+            assert!(result.is_err(), "{}", bad);
+        }
+        let result: Result<UserListQuery, _> =
+            serde_json::from_value(serde_json::json!({"order": "sideways"}));
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_timeseries_query_validation() {
+        let query = |value: serde_json::Value| serde_json::from_value::<TimeseriesQuery>(value);
+
+        // A Friday afternoon
+        let now = DateTime::parse_from_rfc3339("2026-10-16T15:30:00Z")
+            .unwrap()
+            .to_utc();
+        let at = |s: &str| DateTime::parse_from_rfc3339(s).unwrap().to_utc();
+
+        let q = query(serde_json::json!({"metric": "signups"})).unwrap();
+        assert_eq!(q.interval, StatsInterval::Day);
+        assert_eq!(
+            q.window(now),
+            Ok(TimeseriesWindow {
+                from: at("2026-09-16T00:00:00Z"),
+                to: at("2026-10-16T00:00:00Z"),
+            })
+        );
+
+        let q = query(serde_json::json!({
+            "metric": "messages",
+            "granularity": "hour",
+            "range": "24h"
+        }))
+        .unwrap();
+        assert_eq!(q.interval, StatsInterval::Hour);
+        assert_eq!(q.window(now).unwrap().from, at("2026-10-15T15:00:00Z"));
+
+        // Weeks start on Monday
+        let q = query(serde_json::json!({
+            "metric": "conversations",
+            "interval": "week",
+            "from": "2026-01-01T12:00:00Z",
+            "to": "2026-10-16T09:00:00Z"
+        }))
+        .unwrap();
+        assert_eq!(
+            q.window(now),
+            Ok(TimeseriesWindow {
+                from: at("2025-12-29T00:00:00Z"),
+                to: at("2026-10-12T00:00:00Z"),
+            })
+        );
+
+        // Three years of hourly buckets is allowed, longer is not
+        let q = query(serde_json::json!({
+            "metric": "messages",
+            "interval": "hour",
+            "range": "156w"
+        }))
+        .unwrap();
+        assert!(q.window(now).is_ok());
+        let q = query(serde_json::json!({"metric": "signups", "range": "160w"})).unwrap();
+        assert!(q.window(now).is_err());
+
+        for range in ["", "d", "0d", "-3d", "30", "30m", "1.5d", "3é", "99999999999999d"] {
+            let q = query(serde_json::json!({"metric": "signups", "range": range})).unwrap();
+            assert!(q.window(now).is_err(), "{}", range);
+        }
+
+        for bad in [
+            serde_json::json!({"metric": "signups", "from": "2026-10-01T00:00:00Z", "range": "1d"}),
+            serde_json::json!({"metric": "signups", "from": "2026-10-16T15:30:00Z"}),
+            serde_json::json!({"metric": "signups", "from": "2020-01-01T00:00:00Z"}),
+        ] {
+            assert!(query(bad.clone()).unwrap().window(now).is_err(), "{}", bad);
+        }
+
+        assert!(query(serde_json::json!({"metric": "users"})).is_err());
+        assert!(query(serde_json::json!({"metric": "signups", "interval": "minute"})).is_err());
+        assert!(query(serde_json::json!({})).is_err());
+    }
+}
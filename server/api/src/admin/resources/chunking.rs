@@ -0,0 +1,293 @@
+//! Local chunking preview
+//!
+//! Mirrors the Intelligence service's `TextChunker`: split on paragraphs,
+//! pack paragraphs up to `chunk_size` characters, carry `chunk_overlap`
+//! characters into the next chunk, and fall back to sentence splitting for
+//! paragraphs that are larger than a chunk on their own. Sizes are counted
+//! in characters, as in the Python implementation.
+
+const SEPARATOR: &str = "\n\n";
+
+/// A chunk produced by the preview
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Chunk {
+    pub index: usize,
+    pub content: String,
+    /// Character offset of the chunk's new (non-overlap) content in the input
+    pub start_char: usize,
+    pub end_char: usize,
+    pub overlap_chars: usize,
+}
+
+/// A slice of the input with its byte range
+struct Segment<'a> {
+    text: &'a str,
+    start: usize,
+    end: usize,
+}
+
+/// Converts increasing byte offsets into character offsets in one pass
+struct CharOffsets<'a> {
+    text: &'a str,
+    byte: usize,
+    chars: usize,
+}
+
+impl CharOffsets<'_> {
+    fn advance_to(&mut self, byte: usize) -> usize {
+        self.chars += self.text[self.byte..byte].chars().count();
+        self.byte = byte;
+        self.chars
+    }
+}
+
+fn char_len(s: &str) -> usize {
+    s.chars().count()
+}
+
+/// Last `n` characters of `s`
+fn tail_chars(s: &str, n: usize) -> &str {
+    let skip = char_len(s).saturating_sub(n);
+    match s.char_indices().nth(skip) {
+        Some((idx, _)) => &s[idx..],
+        None => "",
+    }
+}
+
+/// Trimmed slice of `text[start..end]` with adjusted byte offsets
+fn trimmed(text: &str, start: usize, end: usize) -> Segment<'_> {
+    let raw = &text[start..end];
+    let leading = raw.len() - raw.trim_start().len();
+    let trimmed = raw.trim();
+    Segment {
+        text: trimmed,
+        start: start + leading,
+        end: start + leading + trimmed.len(),
+    }
+}
+
+/// Split on the paragraph separator, dropping blank paragraphs
+fn split_paragraphs(text: &str) -> Vec<Segment<'_>> {
+    let mut segments = Vec::new();
+    let mut start = 0;
+
+    for (idx, _) in text.match_indices(SEPARATOR) {
+        segments.push(trimmed(text, start, idx));
+        start = idx + SEPARATOR.len();
+    }
+    segments.push(trimmed(text, start, text.len()));
+
+    segments.retain(|s| !s.text.is_empty());
+    segments
+}
+
+/// Split on sentence punctuation (`.`, `!`, `?`) followed by whitespace
+fn split_sentences<'a>(text: &'a str, offset: usize) -> Vec<Segment<'a>> {
+    let mut segments = Vec::new();
+    let mut start = 0;
+    let mut chars = text.char_indices().peekable();
+
+    while let Some((_, c)) = chars.next() {
+        if !matches!(c, '.' | '!' | '?') {
+            continue;
+        }
+        while chars.next_if(|(_, c)| matches!(c, '.' | '!' | '?')).is_some() {}
+
+        let mut end = None;
+        while let Some((idx, ws)) = chars.next_if(|(_, c)| c.is_whitespace()) {
+            end = Some(idx + ws.len_utf8());
+        }
+
+        if let Some(end) = end {
+            segments.push(Segment {
+                text: &text[start..end],
+                start: offset + start,
+                end: offset + end,
+            });
+            start = end;
+        }
+    }
+
+    if start < text.len() {
+        segments.push(Segment {
+            text: &text[start..],
+            start: offset + start,
+            end: offset + text.len(),
+        });
+    }
+
+    segments.retain(|s| !s.text.trim().is_empty());
+    segments
+}
+
+/// Split `text` into chunks the way ingestion would
+pub fn chunk_text(text: &str, chunk_size: usize, chunk_overlap: usize) -> Vec<Chunk> {
+    if text.trim().is_empty() {
+        return Vec::new();
+    }
+
+    let mut builder = ChunkBuilder {
+        offsets: CharOffsets {
+            text,
+            byte: 0,
+            chars: 0,
+        },
+        chunks: Vec::new(),
+        current: String::new(),
+        start: 0,
+        end: 0,
+        overlap: 0,
+    };
+
+    for part in split_paragraphs(text) {
+        let part_len = char_len(part.text);
+
+        if char_len(&builder.current) + part_len + SEPARATOR.len() <= chunk_size {
+            builder.append(&part, SEPARATOR);
+            continue;
+        }
+
+        if !builder.current.is_empty() {
+            let previous = std::mem::take(&mut builder.current);
+            builder.flush_with(&previous);
+
+            if chunk_overlap > 0 && char_len(&previous) > chunk_overlap {
+                let overlap_text = tail_chars(&previous, chunk_overlap);
+                builder.current = format!("{}{}{}", overlap_text, SEPARATOR, part.text);
+                builder.overlap = chunk_overlap;
+            } else {
+                builder.current = part.text.to_string();
+            }
+            builder.start = part.start;
+            builder.end = part.end;
+        } else if part_len > chunk_size {
+            for sentence in split_sentences(part.text, part.start) {
+                if char_len(&builder.current) + char_len(sentence.text) > chunk_size {
+                    builder.flush();
+                    builder.start_with(&sentence);
+                } else {
+                    builder.append(&sentence, " ");
+                }
+            }
+        } else {
+            builder.start_with(&part);
+        }
+    }
+
+    builder.flush();
+    builder.chunks
+}
+
+/// Accumulates the chunk under construction
+struct ChunkBuilder<'a> {
+    offsets: CharOffsets<'a>,
+    chunks: Vec<Chunk>,
+    current: String,
+    start: usize,
+    end: usize,
+    overlap: usize,
+}
+
+impl ChunkBuilder<'_> {
+    fn start_with(&mut self, segment: &Segment) {
+        self.current = segment.text.to_string();
+        self.start = segment.start;
+        self.end = segment.end;
+        self.overlap = 0;
+    }
+
+    fn append(&mut self, segment: &Segment, joiner: &str) {
+        if self.current.is_empty() {
+            self.start_with(segment);
+        } else {
+            self.current.push_str(joiner);
+            self.current.push_str(segment.text);
+            self.end = segment.end;
+        }
+    }
+
+    fn flush(&mut self) {
+        let current = std::mem::take(&mut self.current);
+        self.flush_with(&current);
+    }
+
+    fn flush_with(&mut self, content: &str) {
+        if content.is_empty() {
+            return;
+        }
+
+        let start_char = self.offsets.advance_to(self.start);
+        let end_char = self.offsets.advance_to(self.end);
+
+        self.chunks.push(Chunk {
+            index: self.chunks.len(),
+            content: content.trim().to_string(),
+            start_char,
+            end_char,
+            overlap_chars: self.overlap,
+        });
+        self.overlap = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_text() -> String {
+        (0..20)
+            .map(|i| format!("Paragraph {} talks about topic {}. It has two sentences.", i, i))
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    }
+
+    #[test]
+    fn test_chunk_settings_change_chunk_count() {
+        let text = sample_text();
+
+        let small = chunk_text(&text, 200, 0);
+        let large = chunk_text(&text, 1000, 0);
+
+        assert!(small.len() > large.len());
+        assert!(small.iter().all(|c| char_len(&c.content) <= 200));
+    }
+
+    #[test]
+    fn test_overlap_carries_previous_tail() {
+        let text = sample_text();
+
+        let without = chunk_text(&text, 200, 0);
+        let with = chunk_text(&text, 200, 50);
+
+        assert_ne!(without, with);
+        assert_eq!(with[0].overlap_chars, 0);
+        assert_eq!(with[1].overlap_chars, 50);
+        assert!(with[1].content.starts_with(tail_chars(&with[0].content, 50).trim_start()));
+    }
+
+    #[test]
+    fn test_large_paragraph_split_by_sentences() {
+        let text = "One sentence here. ".repeat(30);
+
+        let chunks = chunk_text(&text, 100, 0);
+
+        assert!(chunks.len() > 1);
+        assert!(chunks.iter().all(|c| c.content.ends_with('.')));
+    }
+
+    #[test]
+    fn test_offsets_point_into_source() {
+        let text = "Héllo wörld.\n\nSecond paragraph.";
+
+        let chunks = chunk_text(text, 100, 0);
+
+        assert_eq!(chunks.len(), 1);
+        assert_eq!(chunks[0].start_char, 0);
+        assert_eq!(chunks[0].end_char, char_len(text));
+    }
+
+    #[test]
+    fn test_empty_input() {
+        assert!(chunk_text("  \n\n ", 100, 10).is_empty());
+    }
+}
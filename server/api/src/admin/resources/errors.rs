@@ -6,6 +6,9 @@ use axum::{
 use serde_json::json;
 use thiserror::Error;
 
+use super::types::BulkDeleteResponse;
+use crate::config::database;
+
 #[derive(Debug, Error)]
 pub enum ResourceError {
     #[error("Unsupported resource type: {0}")]
@@ -20,12 +23,26 @@ pub enum ResourceError {
     #[error("Content too large")]
     ContentTooLarge,
 
+    /// An uploaded file of a type the Intelligence service can't parse
+    #[error("Unsupported file type: {0}")]
+    UnsupportedFileType(String),
+
+    /// A raw upload body was sent without its length
+    #[error("Content-Length required")]
+    LengthRequired,
+
     #[error("Resource not found")]
-    #[allow(dead_code)]
     ResourceNotFound,
 
+    /// The ingestion job is no longer queued or processing
+    #[error("Ingestion already {0}")]
+    IngestionFinished(String),
+
+    /// The resource's ingestion job is still queued or processing
+    #[error("Ingestion still {0}")]
+    IngestionInProgress(String),
+
     #[error("Failed to add resource")]
-    #[allow(dead_code)]
     AddResourceFailed,
 
     #[error("Failed to list resources")]
@@ -61,12 +78,24 @@ pub enum ResourceError {
 
     #[error("Invalid Content-Type: {0}")]
     InvalidContentType(String),
+
+    #[error("Some resources could not be deleted")]
+    BulkPartialFailure(BulkDeleteResponse),
+
+    #[error("No resources could be deleted")]
+    BulkTotalFailure(BulkDeleteResponse),
 }
 
 impl IntoResponse for ResourceError {
     fn into_response(self) -> Response {
-        let (status, message) = match self {
-            ResourceError::UnsupportedResourceType(ref t) => (
+        if let ResourceError::Database(e) = &self
+            && database::is_pool_exhausted(e)
+        {
+            return database::pool_exhausted_response();
+        }
+
+        let (status, message) = match &self {
+            ResourceError::UnsupportedResourceType(t) => (
                 StatusCode::BAD_REQUEST,
                 format!("Unsupported resource type: {}", t),
             ),
@@ -74,7 +103,7 @@ impl IntoResponse for ResourceError {
                 StatusCode::BAD_REQUEST,
                 "Invalid resource content".to_string(),
             ),
-            ResourceError::InvalidUrl(ref e) => (
+            ResourceError::InvalidUrl(e) => (
                 StatusCode::BAD_REQUEST,
                 format!("URL validation failed: {}", e),
             ),
@@ -82,9 +111,25 @@ impl IntoResponse for ResourceError {
                 StatusCode::PAYLOAD_TOO_LARGE,
                 "Content too large".to_string(),
             ),
+            ResourceError::UnsupportedFileType(t) => (
+                StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                format!("Unsupported file type: {}", t),
+            ),
+            ResourceError::LengthRequired => (
+                StatusCode::LENGTH_REQUIRED,
+                "Content-Length is required for raw uploads".to_string(),
+            ),
             ResourceError::ResourceNotFound => {
                 (StatusCode::NOT_FOUND, "Resource not found".to_string())
             }
+            ResourceError::IngestionFinished(status) => (
+                StatusCode::CONFLICT,
+                format!("Ingestion already {}, nothing to cancel", status),
+            ),
+            ResourceError::IngestionInProgress(status) => (
+                StatusCode::CONFLICT,
+                format!("Ingestion still {}, cancel it or wait for it to finish", status),
+            ),
             ResourceError::AddResourceFailed => (
                 StatusCode::INTERNAL_SERVER_ERROR,
                 "Failed to add resource".to_string(),
@@ -109,7 +154,7 @@ impl IntoResponse for ResourceError {
                 StatusCode::FORBIDDEN,
                 "Insufficient permissions".to_string(),
             ),
-            ResourceError::GrpcError(ref e) => (
+            ResourceError::GrpcError(e) => (
                 StatusCode::INTERNAL_SERVER_ERROR,
                 format!("Service error: {}", e),
             ),
@@ -117,18 +162,34 @@ impl IntoResponse for ResourceError {
                 StatusCode::INTERNAL_SERVER_ERROR,
                 "Database error".to_string(),
             ),
-            ResourceError::Validation(ref msg) => (StatusCode::BAD_REQUEST, msg.clone()),
-            ResourceError::InvalidContentType(ref msg) => {
+            ResourceError::Validation(msg) => (StatusCode::BAD_REQUEST, msg.clone()),
+            ResourceError::InvalidContentType(msg) => {
                 (StatusCode::UNSUPPORTED_MEDIA_TYPE, msg.clone())
             }
             ResourceError::Internal => (
                 StatusCode::INTERNAL_SERVER_ERROR,
                 "Internal server error".to_string(),
             ),
+            // Bulk results carry per-item outcomes instead of a single message
+            ResourceError::BulkPartialFailure(result) => {
+                return (StatusCode::MULTI_STATUS, Json(result)).into_response();
+            }
+            ResourceError::BulkTotalFailure(result) => {
+                return (StatusCode::INTERNAL_SERVER_ERROR, Json(result)).into_response();
+            }
+        };
+
+        // Machine-readable code for errors clients need to act on
+        let error = match self {
+            ResourceError::IngestionFinished(_) => "ingestion_finished".to_string(),
+            ResourceError::IngestionInProgress(_) => "ingestion_in_progress".to_string(),
+            ResourceError::UnsupportedFileType(_) => "unsupported_file_type".to_string(),
+            ResourceError::LengthRequired => "length_required".to_string(),
+            _ => message.clone(),
         };
 
         let body = Json(json!({
-            "error": message,
+            "error": error,
             "message": message,
         }));
 
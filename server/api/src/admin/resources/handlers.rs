@@ -1,15 +1,34 @@
 use axum::{
-    extract::{Extension, Path, Query, State},
+    extract::{Extension, FromRequest, Multipart, Path, Query, Request, State},
     http::{header, HeaderMap},
     body::Bytes,
+    response::sse::{Event, KeepAlive, Sse},
     Json,
 };
+use base64::{Engine, engine::general_purpose::STANDARD};
+use futures::{future::join_all, Stream, StreamExt};
+use std::{convert::Infallible, path::PathBuf, time::Duration};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use uuid::Uuid;
 
+use super::chunking;
+use super::search;
+use super::ssrf;
 use super::types::*;
 use super::errors::ResourceError;
+use crate::common::pagination;
 use crate::gateway::AppState;
+use crate::grpc::IntelligenceClient;
+use crate::grpc::client::{MAX_ADMIN_TIMEOUT_OVERRIDE, timeout_override};
 use crate::grpc::proto::opentier::intelligence::v1 as pb;
+use crate::middleware::request_id;
+
+/// How often ingestion progress is polled while streaming
+const PROGRESS_POLL_INTERVAL: Duration = Duration::from_secs(1);
+/// Progress streams close after this long if the job hasn't finished
+const PROGRESS_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+/// Read size when streaming a spooled upload back out
+const SPOOL_READ_SIZE: usize = 1024 * 1024;
 
 // ============================================================================
 // HANDLERS
@@ -17,12 +36,93 @@ use crate::grpc::proto::opentier::intelligence::v1 as pb;
 
 /// Add a new resource for ingestion
 /// POST /admin/resources
+///
+/// An `X-Timeout-Ms` header overrides the ingestion call's deadline.
+#[utoipa::path(
+    post,
+    path = "/admin/resources",
+    tag = "admin",
+    responses(
+        (status = 200, description = "Ingestion started"),
+    ),
+    security(("bearer_auth" = []))
+)]
 pub async fn add_resource(
     State(state): State<AppState>,
     Extension(user_id): Extension<Uuid>,
     headers: HeaderMap,
     body: Bytes,
 ) -> Result<Json<AddResourceResponse>, ResourceError> {
+    let req = parse_add_request(&state, &headers, &body).await?;
+    let resource_id = Uuid::new_v4().to_string();
+    let response = ingest_resource(&state, user_id, resource_id, &req, &headers).await?;
+    Ok(Json(response))
+}
+
+/// Re-ingest an existing resource, keeping its ID
+/// PUT /admin/resources/{id}
+///
+/// Takes the same body as `POST /admin/resources`. The new content is
+/// ingested under the same `resource_id`, so references to the resource stay
+/// valid. Intelligence swaps in the new document and chunks only once they
+/// are all stored, so a failed ingestion leaves the previous version in
+/// place. Returns 404 for resources the caller doesn't own and 409
+/// `ingestion_in_progress` while the resource's current job is still running.
+#[utoipa::path(
+    put,
+    path = "/admin/resources/{id}",
+    tag = "admin",
+    params(("id" = Uuid, Path, description = "Resource ID")),
+    responses(
+        (status = 200, description = "Re-ingestion started"),
+        (status = 404, description = "No such resource for this user"),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn update_resource(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Path(id): Path<Uuid>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Json<AddResourceResponse>, ResourceError> {
+    let req = parse_add_request(&state, &headers, &body).await?;
+    let mut client = state.intelligence_client.clone();
+
+    let current = fetch_status(
+        &mut client,
+        pb::GetResourceStatusRequest {
+            job_id: String::new(),
+            resource_id: id.to_string(),
+            user_id: user_id.to_string(),
+        },
+    )
+    .await?;
+    if is_cancellable(&current.status) {
+        return Err(ResourceError::IngestionInProgress(current.status));
+    }
+
+    let response = ingest_resource(&state, user_id, id.to_string(), &req, &headers).await?;
+    if response.status == "failed" {
+        return Err(ResourceError::AddResourceFailed);
+    }
+
+    tracing::info!(
+        resource_id = %id,
+        job_id = %response.job_id,
+        updated_by = %user_id,
+        "Resource re-ingested"
+    );
+
+    Ok(Json(response))
+}
+
+/// Parse and validate an `AddResourceRequest` JSON body
+async fn parse_add_request(
+    state: &AppState,
+    headers: &HeaderMap,
+    body: &[u8],
+) -> Result<AddResourceRequest, ResourceError> {
     // Check Content-Type header
     let content_type = headers
         .get(header::CONTENT_TYPE)
@@ -37,39 +137,32 @@ pub async fn add_resource(
     }
 
     // Parse body
-    let req: AddResourceRequest = serde_json::from_slice(&body)
+    let req: AddResourceRequest = serde_json::from_slice(body)
         .map_err(|e| ResourceError::Validation(format!("Invalid JSON: {}", e)))?;
 
     // Validate request
     req.validate()?;
+    if req.resource_type.eq_ignore_ascii_case("url") {
+        ssrf::check_url(&req.content, &state.config.ingestion).await?;
+    }
 
-    let mut client = state.intelligence_client.clone();
+    Ok(req)
+}
 
-    // Generate IDs
-    let resource_id = Uuid::new_v4().to_string();
+/// Send a resource to the Intelligence service for ingestion
+///
+/// An `X-Timeout-Ms` header overrides the ingestion call's deadline.
+async fn ingest_resource(
+    state: &AppState,
+    user_id: Uuid,
+    resource_id: String,
+    req: &AddResourceRequest,
+    headers: &HeaderMap,
+) -> Result<AddResourceResponse, ResourceError> {
+    let mut client = state.intelligence_client.clone();
 
     // Map to appropriate gRPC call based on type
-    let content = match req.resource_type.to_lowercase().as_str() {
-        "url" => Some(pb::add_resource_request::Content::Url(req.content.clone())),
-        "text" | "markdown" | "html" | "code" => {
-            Some(pb::add_resource_request::Content::Text(req.content.clone()))
-        }
-        "file" => Some(pb::add_resource_request::Content::FileContent(
-            req.content.as_bytes().to_vec(),
-        )),
-        _ => return Err(ResourceError::UnsupportedResourceType(req.resource_type.clone())),
-    };
-
-    let resource_type = match req.resource_type.to_lowercase().as_str() {
-        "url" => pb::ResourceType::Website,
-        "text" => pb::ResourceType::Text,
-        "markdown" => pb::ResourceType::Markdown,
-        "pdf" => pb::ResourceType::Pdf,
-        "html" => pb::ResourceType::Html,
-        "code" => pb::ResourceType::Code,
-        "file" => pb::ResourceType::Code,
-        _ => pb::ResourceType::Unspecified,
-    };
+    let (resource_type, content) = resource_content(&req.resource_type, &req.content)?;
 
     let mut metadata = req.metadata.clone().unwrap_or_default();
     
@@ -87,50 +180,282 @@ pub async fn add_resource(
 
     let grpc_req = pb::AddResourceRequest {
         user_id: user_id.to_string(),
-        resource_id: resource_id.clone(),
-        content,
+        resource_id,
+        content: Some(content),
         r#type: resource_type as i32,
         title: req.title.clone(),
         metadata,
-        config: req.config.as_ref().map(|cfg| pb::IngestionConfig {
-            chunk_size: cfg.chunk_size.or(Some(1000)),
-            chunk_overlap: cfg.chunk_overlap.or(Some(200)),
-            auto_clean: cfg.auto_clean.or(Some(true)),
-            generate_embeddings: cfg.generate_embeddings.or(Some(true)),
-            max_depth: cfg.depth.or(Some(1)),
-            follow_links: cfg.follow_links.or(Some(false)),
-        }),
+        config: req.config.as_ref().map(ingestion_config),
         is_global: req.is_global.unwrap_or(false),
     };
 
+    let timeout = timeout_override(headers, MAX_ADMIN_TIMEOUT_OVERRIDE);
     let response = client
-        .add_resource(grpc_req)
+        .add_resource(grpc_req, timeout)
         .await
         .map_err(|e| ResourceError::GrpcError(e.to_string()))?
         .into_inner();
 
     let status = pb::ResourceStatus::try_from(response.status)
         .ok()
-        .and_then(|s| match s {
-            pb::ResourceStatus::Unspecified => Some("unspecified".to_string()),
-            pb::ResourceStatus::Queued => Some("queued".to_string()),
-            pb::ResourceStatus::Processing => Some("processing".to_string()),
-            pb::ResourceStatus::Completed => Some("completed".to_string()),
-            pb::ResourceStatus::Failed => Some("failed".to_string()),
-            pb::ResourceStatus::Partial => Some("partial".to_string()),
+        .map(|s| match s {
+            pb::ResourceStatus::Unspecified => "unspecified".to_string(),
+            pb::ResourceStatus::Queued => "queued".to_string(),
+            pb::ResourceStatus::Processing => "processing".to_string(),
+            pb::ResourceStatus::Completed => "completed".to_string(),
+            pb::ResourceStatus::Failed => "failed".to_string(),
+            pb::ResourceStatus::Partial => "partial".to_string(),
         })
         .unwrap_or_else(|| "queued".to_string());
 
-    Ok(Json(AddResourceResponse {
+    Ok(AddResourceResponse {
         resource_id: response.resource_id,
         job_id: response.job_id,
         status,
         created_at: chrono::Utc::now().timestamp(),
+    })
+}
+
+/// Upload a file for ingestion, streamed straight to the Intelligence service
+/// POST /admin/resources/upload
+///
+/// Accepts either:
+/// - `multipart/form-data` with an optional JSON `metadata` part
+///   (`filename`, `type`, `title`, `metadata`, `config`) followed by a `file` part
+/// - the raw file as the body, described by `filename`, `type` and `title`
+///   query params, with a required `Content-Length` passed on as its size
+///
+/// The Intelligence service needs the file's size before its first byte, so
+/// a multipart file part is spooled to a temporary file first. Either way the
+/// file is forwarded in 10MB chunks, so large documents are never held in
+/// memory.
+#[utoipa::path(
+    post,
+    path = "/admin/resources/upload",
+    tag = "admin",
+    responses(
+        (status = 200, description = "Ingestion of the uploaded file started"),
+        (status = 411, description = "Raw body sent without a Content-Length"),
+        (status = 415, description = "File type the Intelligence service can't parse"),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn upload_resource(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Query(params): Query<UploadResourceQuery>,
+    headers: HeaderMap,
+    request: Request,
+) -> Result<Json<AddResourceResponse>, ResourceError> {
+    let content_type = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or("application/octet-stream");
+
+    if !content_type.starts_with("multipart/form-data") {
+        let upload = FileUpload {
+            details: params.into(),
+            filename: None,
+            content_type: content_type.to_string(),
+            total_size: raw_upload_size(&headers)?,
+        };
+        return upload_file(&state, user_id, upload, request.into_body().into_data_stream()).await;
+    }
+
+    let mut multipart = Multipart::from_request(request, &state)
+        .await
+        .map_err(|e| ResourceError::Validation(e.body_text()))?;
+    let mut details = UploadResourceMetadata::from(params);
+
+    while let Some(field) = multipart
+        .next_field()
+        .await
+        .map_err(|e| ResourceError::Validation(e.body_text()))?
+    {
+        match field.name() {
+            Some("metadata") => {
+                let text = field
+                    .text()
+                    .await
+                    .map_err(|e| ResourceError::Validation(e.body_text()))?;
+                details = serde_json::from_str(&text)
+                    .map_err(|e| ResourceError::Validation(format!("Invalid metadata: {}", e)))?;
+            }
+            Some("file") => {
+                let filename = field.file_name().map(str::to_string);
+                let content_type = field
+                    .content_type()
+                    .unwrap_or("application/octet-stream")
+                    .to_string();
+                let spooled = SpooledFile::write(field).await?;
+
+                let upload = FileUpload {
+                    details,
+                    filename,
+                    content_type,
+                    total_size: spooled.size,
+                };
+                return upload_file(&state, user_id, upload, spooled.into_stream()).await;
+            }
+            _ => {}
+        }
+    }
+
+    Err(ResourceError::Validation("Missing file part".to_string()))
+}
+
+/// Size of a raw upload body, which must be declared up front
+fn raw_upload_size(headers: &HeaderMap) -> Result<u64, ResourceError> {
+    headers
+        .get(header::CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse().ok())
+        .ok_or(ResourceError::LengthRequired)
+}
+
+/// A file being uploaded and what is known about it
+struct FileUpload {
+    details: UploadResourceMetadata,
+    /// Name sent with the file, used when `details` has none
+    filename: Option<String>,
+    content_type: String,
+    /// Exact length of the body
+    total_size: u64,
+}
+
+/// An uploaded file written to a temporary file, which is removed on drop
+struct SpooledFile {
+    path: PathBuf,
+    size: u64,
+}
+
+impl SpooledFile {
+    /// Write `body` to a new temporary file, counting its bytes
+    async fn write<S, E>(body: S) -> Result<Self, ResourceError>
+    where
+        S: Stream<Item = Result<Bytes, E>>,
+        E: std::fmt::Display,
+    {
+        let io_error = |e: std::io::Error| {
+            tracing::error!("Failed to spool upload: {}", e);
+            ResourceError::Internal
+        };
+
+        let mut spooled = SpooledFile {
+            path: std::env::temp_dir().join(format!("opentier-upload-{}", Uuid::new_v4())),
+            size: 0,
+        };
+        let mut file = tokio::fs::File::create(&spooled.path)
+            .await
+            .map_err(io_error)?;
+
+        futures::pin_mut!(body);
+        while let Some(bytes) = body.next().await {
+            let bytes = bytes.map_err(|e| ResourceError::Validation(e.to_string()))?;
+            file.write_all(&bytes).await.map_err(io_error)?;
+            spooled.size += bytes.len() as u64;
+        }
+        file.flush().await.map_err(io_error)?;
+
+        Ok(spooled)
+    }
+
+    /// Read the file back, removing it once the stream is dropped
+    fn into_stream(self) -> impl Stream<Item = std::io::Result<Bytes>> + Send + 'static {
+        async_stream::try_stream! {
+            let mut file = tokio::fs::File::open(&self.path).await?;
+            let mut buffer = vec![0; SPOOL_READ_SIZE];
+            loop {
+                let read = file.read(&mut buffer).await?;
+                if read == 0 {
+                    break;
+                }
+                yield Bytes::copy_from_slice(&buffer[..read]);
+            }
+        }
+    }
+}
+
+impl Drop for SpooledFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// Stream a file to the Intelligence service's chunked upload
+async fn upload_file<S, E>(
+    state: &AppState,
+    user_id: Uuid,
+    upload: FileUpload,
+    body: S,
+) -> Result<Json<AddResourceResponse>, ResourceError>
+where
+    S: Stream<Item = Result<Bytes, E>> + Send + 'static,
+    E: std::fmt::Display + Send + 'static,
+{
+    let FileUpload {
+        details,
+        filename,
+        content_type,
+        total_size,
+    } = upload;
+
+    let filename = details
+        .filename
+        .or(filename)
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+        .ok_or_else(|| ResourceError::Validation("filename is required".to_string()))?;
+    if let Some(config) = &details.config {
+        config.validate()?;
+    }
+
+    let resource_type = upload_resource_type(details.resource_type.as_deref(), &filename)?;
+
+    let mut metadata = details.metadata.unwrap_or_default();
+    metadata.insert(
+        "title".to_string(),
+        details.title.clone().unwrap_or_else(|| filename.clone()),
+    );
+
+    let chunk_metadata = pb::ChunkMetadata {
+        user_id: user_id.to_string(),
+        filename,
+        content_type,
+        r#type: resource_type as i32,
+        title: details.title,
+        metadata,
+        config: details.config.as_ref().map(ingestion_config),
+        ..Default::default()
+    };
+
+    let response = state
+        .intelligence_client
+        .clone()
+        .chunked_upload_stream(chunk_metadata, total_size, body)
+        .await
+        .map_err(|e| ResourceError::GrpcError(e.to_string()))?
+        .into_inner();
+
+    Ok(Json(AddResourceResponse {
+        resource_id: response.resource_id,
+        job_id: response.job_id,
+        status: status_name(response.status).to_string(),
+        created_at: chrono::Utc::now().timestamp(),
     }))
 }
 
 /// List all resources
 /// GET /admin/resources
+#[utoipa::path(
+    get,
+    path = "/admin/resources",
+    tag = "admin",
+    responses(
+        (status = 200, description = "Ingested resources"),
+    ),
+    security(("bearer_auth" = []))
+)]
 pub async fn list_resources(
     State(state): State<AppState>,
     Extension(user_id): Extension<Uuid>,
@@ -138,32 +463,12 @@ pub async fn list_resources(
 ) -> Result<Json<ListResourcesResponse>, ResourceError> {
     let mut client = state.intelligence_client.clone();
 
-    let type_filter = params.resource_type.as_ref().map(|t| {
-        match t.to_lowercase().as_str() {
-            "text" => pb::ResourceType::Text as i32,
-            "markdown" => pb::ResourceType::Markdown as i32,
-            "pdf" => pb::ResourceType::Pdf as i32,
-            "html" => pb::ResourceType::Html as i32,
-            "website" => pb::ResourceType::Website as i32,
-            "code" => pb::ResourceType::Code as i32,
-            _ => pb::ResourceType::Unspecified as i32,
-        }
-    });
-
-    let status_filter = params.status.as_ref().map(|s| {
-        match s.to_lowercase().as_str() {
-            "queued" => pb::ResourceStatus::Queued as i32,
-            "processing" => pb::ResourceStatus::Processing as i32,
-            "completed" => pb::ResourceStatus::Completed as i32,
-            "failed" => pb::ResourceStatus::Failed as i32,
-            "partial" => pb::ResourceStatus::Partial as i32,
-            _ => pb::ResourceStatus::Unspecified as i32,
-        }
-    });
+    let type_filter = params.resource_type.as_deref().map(parse_type_filter);
+    let status_filter = params.status.as_deref().map(parse_status_filter);
 
     // Validate limit
     let limit = params.limit.unwrap_or(20);
-    if limit < 1 || limit > 100 {
+    if !(1..=100).contains(&limit) {
         return Err(ResourceError::InvalidFilters);
     }
 
@@ -171,8 +476,8 @@ pub async fn list_resources(
         user_id: user_id.to_string(),
         limit: Some(limit),
         cursor: params.cursor.clone(),
-        type_filter: type_filter,
-        status_filter: status_filter,
+        type_filter,
+        status_filter,
     };
 
     let response = client
@@ -184,56 +489,7 @@ pub async fn list_resources(
     let items = response
         .items
         .into_iter()
-        .map(|item| {
-            let item_type = pb::ResourceType::try_from(item.r#type)
-                .ok()
-                .map(|t| match t {
-                    pb::ResourceType::Text => "text",
-                    pb::ResourceType::Markdown => "markdown",
-                    pb::ResourceType::Pdf => "pdf",
-                    pb::ResourceType::Html => "html",
-                    pb::ResourceType::Website => "website",
-                    pb::ResourceType::Code => "code",
-                    _ => "unspecified",
-                })
-                .unwrap_or("unspecified")
-                .to_string();
-
-            let item_status = pb::ResourceStatus::try_from(item.status)
-                .ok()
-                .map(|s| match s {
-                    pb::ResourceStatus::Queued => "queued",
-                    pb::ResourceStatus::Processing => "processing",
-                    pb::ResourceStatus::Completed => "completed",
-                    pb::ResourceStatus::Failed => "failed",
-                    pb::ResourceStatus::Partial => "partial",
-                    _ => "unspecified",
-                })
-                .unwrap_or("unspecified")
-                .to_string();
-
-            let title = item.metadata.get("title").cloned();
-            
-            // Prefer original type from metadata if available, otherwise use mapped type
-            let final_type = if let Some(orig) = item.metadata.get("original_type") {
-                orig.clone()
-            } else {
-                item_type
-            };
-
-            ResourceItemResponse {
-                id: item.id,
-                resource_type: final_type,
-                content: item.content,
-                status: item_status,
-                chunks_created: item.stats.as_ref().map(|s| s.chunks).unwrap_or(0),
-                documents: item.stats.as_ref().map(|s| s.documents).unwrap_or(0),
-                metadata: item.metadata,
-                created_at: item.created_at,
-                title,
-                is_global: item.is_global,
-            }
-        })
+        .map(to_item_response)
         .collect();
 
     Ok(Json(ListResourcesResponse {
@@ -243,10 +499,132 @@ pub async fn list_resources(
     }))
 }
 
+/// Search the caller's resources by title, content or metadata value
+/// GET /admin/resources/search
+///
+/// Matches are found in the stored documents, so only fully ingested
+/// resources are returned, newest first.
+#[utoipa::path(
+    get,
+    path = "/admin/resources/search",
+    tag = "admin",
+    responses(
+        (status = 200, description = "Matching resources"),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn search_resources(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Query(params): Query<SearchResourcesQuery>,
+) -> Result<Json<ListResourcesResponse>, ResourceError> {
+    let query = params.q.trim();
+    if query.is_empty() {
+        return Err(ResourceError::Validation(
+            "Search query must not be empty".to_string(),
+        ));
+    }
+
+    let limit = params.limit.unwrap_or(20);
+    if !(1..=100).contains(&limit) {
+        return Err(ResourceError::InvalidFilters);
+    }
+
+    // Stored documents are always fully ingested
+    if let Some(status) = params.status.as_deref()
+        && parse_status_filter(status) != pb::ResourceStatus::Completed as i32
+    {
+        return Ok(Json(ListResourcesResponse {
+            items: Vec::new(),
+            next_cursor: None,
+            total: 0,
+        }));
+    }
+
+    let cursor_secret = &state.config.security.cursor_signing_secret;
+    let after = pagination::decode_keyset_cursor(cursor_secret, params.cursor.as_deref())
+        .map_err(|_| ResourceError::Validation("Invalid cursor".to_string()))?;
+    let resource_type = params.resource_type.as_deref().map(str::to_lowercase);
+
+    let page = search::search(
+        &state.db,
+        search::ResourceSearch {
+            user_id,
+            query,
+            resource_type: resource_type.as_deref(),
+            limit: limit as i64,
+            after,
+        },
+    )
+    .await?;
+
+    let next_cursor = page
+        .last
+        .map(|(created_at, id)| pagination::encode_keyset_cursor(cursor_secret, created_at, id));
+
+    Ok(Json(ListResourcesResponse {
+        items: page.items,
+        next_cursor,
+        total: page.total as i32,
+    }))
+}
+
+/// Preview how content would be chunked, without ingesting it
+/// POST /admin/resources/preview-chunks
+#[utoipa::path(
+    post,
+    path = "/admin/resources/preview-chunks",
+    tag = "admin",
+    responses(
+        (status = 200, description = "How the content would be chunked"),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn preview_chunks(
+    Json(req): Json<PreviewChunksRequest>,
+) -> Result<Json<PreviewChunksResponse>, ResourceError> {
+    let (chunk_size, chunk_overlap) = req.validate()?;
+
+    let chunks: Vec<ChunkPreview> = chunking::chunk_text(&req.content, chunk_size, chunk_overlap)
+        .into_iter()
+        .map(|chunk| ChunkPreview {
+            index: chunk.index,
+            start_char: chunk.start_char,
+            end_char: chunk.end_char,
+            char_count: chunk.content.chars().count(),
+            overlap_chars: chunk.overlap_chars,
+            content: chunk.content,
+        })
+        .collect();
+
+    Ok(Json(PreviewChunksResponse {
+        chunk_size,
+        chunk_overlap,
+        total_chars: req.content.chars().count(),
+        chunk_count: chunks.len(),
+        chunks,
+    }))
+}
+
 /// Get resource status
 /// GET /admin/resources/{id}
+///
+/// Looked up as `user_id` (the requesting admin by default); resources owned
+/// by anyone else are 404.
+#[utoipa::path(
+    get,
+    path = "/admin/resources/{id}",
+    tag = "admin",
+    params(("id" = Uuid, Path, description = "Resource ID")),
+    responses(
+        (status = 200, description = "Ingestion status"),
+        (status = 404, description = "No such resource for this user"),
+    ),
+    security(("bearer_auth" = []))
+)]
 pub async fn get_resource_status(
     State(state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
     Path(id): Path<Uuid>,
     Query(params): Query<GetResourceStatusQuery>,
 ) -> Result<Json<ResourceStatusResponse>, ResourceError> {
@@ -255,40 +633,169 @@ pub async fn get_resource_status(
     let grpc_req = pb::GetResourceStatusRequest {
         job_id: params.job_id.unwrap_or_default(),
         resource_id: id.to_string(),
-        user_id: params.user_id.unwrap_or_default(),
+        user_id: params.user_id.unwrap_or_else(|| user_id.to_string()),
+    };
+
+    Ok(Json(fetch_status(&mut client, grpc_req).await?))
+}
+
+/// Cancel an in-flight ingestion
+/// POST /admin/resources/{id}/cancel
+///
+/// The job is looked up as `user_id` (the requesting admin by default), so
+/// Intelligence only finds resources that user owns. Returns the status after
+/// cancelling, or 409 `ingestion_finished` if the job is no longer queued or
+/// processing, including when it finishes while the cancel is in flight.
+#[utoipa::path(
+    post,
+    path = "/admin/resources/{id}/cancel",
+    tag = "admin",
+    params(("id" = Uuid, Path, description = "Resource ID")),
+    responses(
+        (status = 200, description = "Ingestion cancelled"),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn cancel_ingestion(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Path(id): Path<Uuid>,
+    Query(params): Query<GetResourceStatusQuery>,
+) -> Result<Json<ResourceStatusResponse>, ResourceError> {
+    let mut client = state.intelligence_client.clone();
+
+    let status_request = pb::GetResourceStatusRequest {
+        job_id: params.job_id.unwrap_or_default(),
+        resource_id: id.to_string(),
+        user_id: params.user_id.unwrap_or_else(|| user_id.to_string()),
     };
 
+    let current = fetch_status(&mut client, status_request.clone()).await?;
+    if !is_cancellable(&current.status) {
+        return Err(ResourceError::IngestionFinished(current.status));
+    }
+
     let response = client
-        .get_resource_status(grpc_req)
+        .cancel_ingestion(pb::CancelIngestionRequest {
+            user_id: status_request.user_id.clone(),
+            job_id: current.job_id.clone(),
+        })
         .await
         .map_err(|e| ResourceError::GrpcError(e.to_string()))?
         .into_inner();
 
-    let status = pb::ResourceStatus::try_from(response.status)
-        .ok()
-        .map(|s| match s {
-            pb::ResourceStatus::Unspecified => "unspecified",
-            pb::ResourceStatus::Queued => "queued",
-            pb::ResourceStatus::Processing => "processing",
-            pb::ResourceStatus::Completed => "completed",
-            pb::ResourceStatus::Failed => "failed",
-            pb::ResourceStatus::Partial => "partial",
-        })
-        .unwrap_or("unspecified")
-        .to_string();
+    let updated = fetch_status(&mut client, status_request).await?;
+    if !response.success {
+        if !is_cancellable(&updated.status) {
+            return Err(ResourceError::IngestionFinished(updated.status));
+        }
+        return Err(ResourceError::GrpcError(
+            response
+                .message
+                .unwrap_or_else(|| "Failed to cancel ingestion".to_string()),
+        ));
+    }
 
-    Ok(Json(ResourceStatusResponse {
-        job_id: response.job_id,
-        resource_id: response.resource_id,
-        status,
-        chunks_created: response.chunks_created,
-        error: response.error,
-        progress: response.progress,
-    }))
+    tracing::info!(
+        resource_id = %id,
+        job_id = %current.job_id,
+        cancelled_by = %user_id,
+        "Ingestion cancelled"
+    );
+
+    Ok(Json(updated))
+}
+
+/// Stream ingestion progress (Server-Sent Events)
+/// GET /admin/resources/{id}/progress
+/// GET /admin/resources/{id}/progress/stream (alias, same stream)
+///
+/// Polls the Intelligence service every second, emitting `progress` events
+/// until the job finishes (`done`), or 10 minutes pass (`error`).
+/// The proto has no streaming status RPC, so polling is the only option.
+#[utoipa::path(
+    get,
+    path = "/admin/resources/{id}/progress",
+    tag = "admin",
+    params(("id" = Uuid, Path, description = "Resource ID")),
+    responses(
+        (status = 200, description = "Server-sent events: `progress`, `done` and `error`"),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn stream_resource_progress(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    Query(params): Query<GetResourceStatusQuery>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let mut client = state.intelligence_client.clone();
+
+    let request = pb::GetResourceStatusRequest {
+        job_id: params.job_id.unwrap_or_default(),
+        resource_id: id.to_string(),
+        user_id: params.user_id.unwrap_or_default(),
+    };
+
+    let stream = async_stream::stream! {
+        let deadline = tokio::time::Instant::now() + PROGRESS_TIMEOUT;
+        let mut ticker = tokio::time::interval(PROGRESS_POLL_INTERVAL);
+
+        loop {
+            ticker.tick().await;
+
+            if tokio::time::Instant::now() >= deadline {
+                yield Ok(Event::default()
+                    .event("error")
+                    .data("Timed out waiting for ingestion to complete"));
+                break;
+            }
+
+            let response = match client.get_resource_status(request.clone()).await {
+                Ok(response) => response.into_inner(),
+                Err(e) => {
+                    yield Ok(Event::default()
+                        .event("error")
+                        .data(format!("Failed to get resource status: {}", e.message())));
+                    break;
+                }
+            };
+
+            let status = status_name(response.status);
+            let finished = is_finished(status);
+
+            let event = ResourceProgressEvent {
+                status: status.to_string(),
+                progress: response.progress,
+                chunks_created: response.chunks_created,
+                stage: response.stage.unwrap_or_else(|| status.to_string()),
+                error: response.error,
+            };
+            let data = serde_json::to_string(&event).unwrap_or_default();
+
+            if finished {
+                yield Ok(Event::default().event("done").data(data));
+                break;
+            }
+
+            yield Ok(Event::default().event("progress").data(data));
+        }
+    };
+
+    Sse::new(request_id::scope_stream(stream)).keep_alive(KeepAlive::default())
 }
 
 /// Delete resource and all associated data
 /// DELETE /admin/resources/{id}
+#[utoipa::path(
+    delete,
+    path = "/admin/resources/{id}",
+    tag = "admin",
+    params(("id" = Uuid, Path, description = "Resource ID")),
+    responses(
+        (status = 200, description = "Resource deleted"),
+    ),
+    security(("bearer_auth" = []))
+)]
 pub async fn delete_resource(
     State(state): State<AppState>,
     Extension(user_id): Extension<Uuid>,
@@ -315,3 +822,411 @@ pub async fn delete_resource(
         Err(ResourceError::DeleteResourceFailed)
     }
 }
+
+
+/// Delete multiple resources concurrently
+/// DELETE /admin/resources
+///
+/// Returns 200 when every deletion succeeds, 207 with per-item results on
+/// partial failure, and 500 when nothing could be deleted.
+#[utoipa::path(
+    delete,
+    path = "/admin/resources",
+    tag = "admin",
+    responses(
+        (status = 200, description = "Per-resource deletion results"),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn bulk_delete_resources(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Json(req): Json<BulkDeleteRequest>,
+) -> Result<Json<BulkDeleteResponse>, ResourceError> {
+    req.validate()?;
+
+    let mut ids = req.resource_ids;
+    ids.sort();
+    ids.dedup();
+
+    // Each call gets its own client clone; retries for transient gRPC
+    // errors are handled inside `delete_resource`
+    let results = join_all(ids.into_iter().map(|id| {
+        let mut client = state.intelligence_client.clone();
+        let user_id = user_id.to_string();
+        async move {
+            let result = client
+                .delete_resource(pb::DeleteResourceRequest {
+                    user_id,
+                    resource_id: id.clone(),
+                })
+                .await;
+            (id, result)
+        }
+    }))
+    .await;
+
+    let mut response = BulkDeleteResponse {
+        deleted: Vec::new(),
+        failed: Vec::new(),
+    };
+
+    for (id, result) in results {
+        match result {
+            Ok(res) if res.get_ref().success => response.deleted.push(id),
+            Ok(_) => response.failed.push(BulkDeleteFailure {
+                id,
+                error: "Failed to delete resource".to_string(),
+            }),
+            Err(status) => response.failed.push(BulkDeleteFailure {
+                id,
+                error: status.message().to_string(),
+            }),
+        }
+    }
+
+    if response.failed.is_empty() {
+        Ok(Json(response))
+    } else if response.deleted.is_empty() {
+        Err(ResourceError::BulkTotalFailure(response))
+    } else {
+        Err(ResourceError::BulkPartialFailure(response))
+    }
+}
+
+// ============================================================================
+// HELPERS
+// ============================================================================
+
+async fn fetch_status(
+    client: &mut IntelligenceClient,
+    request: pb::GetResourceStatusRequest,
+) -> Result<ResourceStatusResponse, ResourceError> {
+    let response = client
+        .get_resource_status(request)
+        .await
+        .map_err(|e| match e.code() {
+            tonic::Code::NotFound => ResourceError::ResourceNotFound,
+            _ => ResourceError::GrpcError(e.to_string()),
+        })?
+        .into_inner();
+
+    Ok(status_response(found_status(response)?))
+}
+
+/// Treat a refused lookup as a missing resource
+///
+/// Intelligence answers a job or resource owned by someone else with an
+/// unspecified status and an "Access denied" error rather than `NotFound`.
+/// Both are 404 here, without the error text, so responses don't reveal
+/// that another user's resource exists.
+fn found_status(
+    response: pb::ResourceStatusResponse,
+) -> Result<pb::ResourceStatusResponse, ResourceError> {
+    if response.status == pb::ResourceStatus::Unspecified as i32 && response.error.is_some() {
+        return Err(ResourceError::ResourceNotFound);
+    }
+    Ok(response)
+}
+
+fn status_response(response: pb::ResourceStatusResponse) -> ResourceStatusResponse {
+    ResourceStatusResponse {
+        job_id: response.job_id,
+        resource_id: response.resource_id,
+        status: status_name(response.status).to_string(),
+        chunks_created: response.chunks_created,
+        error: response.error,
+        progress: response.progress,
+    }
+}
+
+/// Whether a job has stopped for good, going by its status alone
+///
+/// The Intelligence service doesn't report a final stage, so the status is
+/// the only reliable signal; partially ingested jobs are finished too.
+fn is_finished(status: &str) -> bool {
+    matches!(status, "completed" | "partial" | "failed")
+}
+
+/// Only jobs that haven't finished can be cancelled
+fn is_cancellable(status: &str) -> bool {
+    matches!(status, "queued" | "processing")
+}
+
+fn status_name(status: i32) -> &'static str {
+    pb::ResourceStatus::try_from(status)
+        .ok()
+        .map(|s| match s {
+            pb::ResourceStatus::Unspecified => "unspecified",
+            pb::ResourceStatus::Queued => "queued",
+            pb::ResourceStatus::Processing => "processing",
+            pb::ResourceStatus::Completed => "completed",
+            pb::ResourceStatus::Failed => "failed",
+            pb::ResourceStatus::Partial => "partial",
+        })
+        .unwrap_or("unspecified")
+}
+
+/// Ingestion settings with defaults for anything left unset
+fn ingestion_config(cfg: &ResourceConfig) -> pb::IngestionConfig {
+    pb::IngestionConfig {
+        chunk_size: cfg.chunk_size.or(Some(1000)),
+        chunk_overlap: cfg.chunk_overlap.or(Some(200)),
+        auto_clean: cfg.auto_clean.or(Some(true)),
+        generate_embeddings: cfg.generate_embeddings.or(Some(true)),
+        max_depth: cfg.depth.or(Some(1)),
+        follow_links: cfg.follow_links.or(Some(false)),
+    }
+}
+
+/// Proto type and content for an `AddResourceRequest` type
+fn resource_content(
+    resource_type: &str,
+    content: &str,
+) -> Result<(pb::ResourceType, pb::add_resource_request::Content), ResourceError> {
+    use pb::add_resource_request::Content;
+
+    let text = || Content::Text(content.to_string());
+    Ok(match resource_type.to_lowercase().as_str() {
+        "url" => (pb::ResourceType::Website, Content::Url(content.to_string())),
+        "text" => (pb::ResourceType::Text, text()),
+        "markdown" => (pb::ResourceType::Markdown, text()),
+        "html" => (pb::ResourceType::Html, text()),
+        "code" => (pb::ResourceType::Code, text()),
+        // JSON content is a string, so a "file" sent this way is plain text
+        "file" => (pb::ResourceType::Text, text()),
+        // PDFs are binary, so JSON requests carry them base64 encoded
+        "pdf" => {
+            let bytes = STANDARD.decode(content.trim()).map_err(|_| {
+                ResourceError::Validation("pdf content must be base64 encoded".to_string())
+            })?;
+            (pb::ResourceType::Pdf, Content::FileContent(bytes))
+        }
+        _ => return Err(ResourceError::UnsupportedResourceType(resource_type.to_string())),
+    })
+}
+
+/// Extensions ingested as source code
+const CODE_EXTENSIONS: &[&str] = &[
+    "c", "cpp", "cs", "go", "h", "hpp", "java", "js", "kt", "py", "rb", "rs", "sh", "swift", "ts",
+];
+
+/// Proto type for an uploaded file, from its declared type or its extension
+///
+/// The Intelligence service treats any type it doesn't parse as plain text,
+/// so files of other types are refused rather than ingested as garbage.
+fn upload_resource_type(
+    declared: Option<&str>,
+    filename: &str,
+) -> Result<pb::ResourceType, ResourceError> {
+    let extension = filename
+        .rsplit_once('.')
+        .map(|(_, extension)| extension.to_lowercase())
+        .unwrap_or_default();
+    let kind = declared
+        .map(str::to_lowercase)
+        .filter(|declared| declared != "file")
+        .unwrap_or(extension);
+
+    Ok(match kind.as_str() {
+        "pdf" => pb::ResourceType::Pdf,
+        "md" | "markdown" => pb::ResourceType::Markdown,
+        "htm" | "html" => pb::ResourceType::Html,
+        "txt" | "text" => pb::ResourceType::Text,
+        "code" => pb::ResourceType::Code,
+        kind if CODE_EXTENSIONS.contains(&kind) => pb::ResourceType::Code,
+        _ => return Err(ResourceError::UnsupportedFileType(kind)),
+    })
+}
+
+fn parse_type_filter(t: &str) -> i32 {
+    match t.to_lowercase().as_str() {
+        "text" => pb::ResourceType::Text as i32,
+        "markdown" => pb::ResourceType::Markdown as i32,
+        "pdf" => pb::ResourceType::Pdf as i32,
+        "html" => pb::ResourceType::Html as i32,
+        "website" => pb::ResourceType::Website as i32,
+        "code" => pb::ResourceType::Code as i32,
+        _ => pb::ResourceType::Unspecified as i32,
+    }
+}
+
+fn parse_status_filter(s: &str) -> i32 {
+    match s.to_lowercase().as_str() {
+        "queued" => pb::ResourceStatus::Queued as i32,
+        "processing" => pb::ResourceStatus::Processing as i32,
+        "completed" => pb::ResourceStatus::Completed as i32,
+        "failed" => pb::ResourceStatus::Failed as i32,
+        "partial" => pb::ResourceStatus::Partial as i32,
+        _ => pb::ResourceStatus::Unspecified as i32,
+    }
+}
+
+fn to_item_response(item: pb::ResourceItem) -> ResourceItemResponse {
+    let item_type = pb::ResourceType::try_from(item.r#type)
+        .ok()
+        .map(|t| match t {
+            pb::ResourceType::Text => "text",
+            pb::ResourceType::Markdown => "markdown",
+            pb::ResourceType::Pdf => "pdf",
+            pb::ResourceType::Html => "html",
+            pb::ResourceType::Website => "website",
+            pb::ResourceType::Code => "code",
+            pb::ResourceType::File => "file",
+            _ => "unspecified",
+        })
+        .unwrap_or("unspecified")
+        .to_string();
+
+    let item_status = pb::ResourceStatus::try_from(item.status)
+        .ok()
+        .map(|s| match s {
+            pb::ResourceStatus::Queued => "queued",
+            pb::ResourceStatus::Processing => "processing",
+            pb::ResourceStatus::Completed => "completed",
+            pb::ResourceStatus::Failed => "failed",
+            pb::ResourceStatus::Partial => "partial",
+            _ => "unspecified",
+        })
+        .unwrap_or("unspecified")
+        .to_string();
+
+    let title = item.metadata.get("title").cloned();
+    
+    // Prefer original type from metadata if available, otherwise use mapped type
+    let final_type = if let Some(orig) = item.metadata.get("original_type") {
+        orig.clone()
+    } else {
+        item_type
+    };
+
+    ResourceItemResponse {
+        id: item.id,
+        resource_type: final_type,
+        content: item.content,
+        status: item_status,
+        chunks_created: item.stats.as_ref().map(|s| s.chunks).unwrap_or(0),
+        documents: item.stats.as_ref().map(|s| s.documents).unwrap_or(0),
+        metadata: item.metadata,
+        created_at: item.created_at,
+        title,
+        is_global: item.is_global,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_every_resource_type_maps_to_proto() {
+        // "cGRm" is base64 for "pdf"
+        for &resource_type in RESOURCE_TYPES {
+            let content = if resource_type == "url" { "https://example.com" } else { "cGRm" };
+            let (proto_type, _) = resource_content(resource_type, content).unwrap();
+            assert_ne!(proto_type, pb::ResourceType::Unspecified, "{}", resource_type);
+        }
+
+        // The Intelligence service has no file type of its own
+        let (file_type, _) = resource_content("file", "data").unwrap();
+        assert_eq!(file_type, pb::ResourceType::Text);
+
+        assert!(resource_content("unknown", "data").is_err());
+    }
+
+    #[test]
+    fn test_partial_ingestion_is_finished() {
+        for status in ["completed", "partial", "failed"] {
+            assert!(is_finished(status), "{}", status);
+            assert!(!is_cancellable(status), "{}", status);
+        }
+        assert!(!is_finished("queued"));
+        assert!(!is_finished("processing"));
+        assert!(!is_finished(status_name(pb::ResourceStatus::Processing as i32)));
+        assert!(is_finished(status_name(pb::ResourceStatus::Partial as i32)));
+    }
+
+    #[test]
+    fn test_uploads_map_to_parsed_types() {
+        let kind = |declared, filename| upload_resource_type(declared, filename).unwrap();
+        assert_eq!(kind(None, "report.PDF"), pb::ResourceType::Pdf);
+        assert_eq!(kind(None, "notes.md"), pb::ResourceType::Markdown);
+        assert_eq!(kind(None, "main.rs"), pb::ResourceType::Code);
+        assert_eq!(kind(Some("file"), "page.html"), pb::ResourceType::Html);
+        assert_eq!(kind(Some("text"), "data.csv"), pb::ResourceType::Text);
+
+        assert!(matches!(
+            upload_resource_type(None, "sheet.xlsx"),
+            Err(ResourceError::UnsupportedFileType(kind)) if kind == "xlsx"
+        ));
+        assert!(upload_resource_type(Some("file"), "README").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_spooled_upload_knows_its_size() {
+        let body = futures::stream::iter(
+            ["%PDF-", "1.4", ""].map(|part| Ok::<_, Infallible>(Bytes::from(part))),
+        );
+        let spooled = SpooledFile::write(body).await.unwrap();
+        assert_eq!(spooled.size, 8);
+
+        let path = spooled.path.clone();
+        let read: Vec<Bytes> = spooled.into_stream().map(Result::unwrap).collect().await;
+        assert_eq!(read.concat(), b"%PDF-1.4");
+        assert!(!path.exists());
+
+        // A failed read is reported as a bad request
+        let failing = futures::stream::iter([Ok(Bytes::from("abc")), Err("connection reset")]);
+        assert!(matches!(
+            SpooledFile::write(failing).await,
+            Err(ResourceError::Validation(message)) if message == "connection reset"
+        ));
+    }
+
+    #[test]
+    fn test_raw_upload_requires_content_length() {
+        let with = |value: &str| {
+            let mut headers = HeaderMap::new();
+            headers.insert(header::CONTENT_LENGTH, value.parse().unwrap());
+            raw_upload_size(&headers)
+        };
+
+        assert_eq!(with("1048576").unwrap(), 1_048_576);
+        assert!(matches!(with("lots"), Err(ResourceError::LengthRequired)));
+        assert!(matches!(
+            raw_upload_size(&HeaderMap::new()),
+            Err(ResourceError::LengthRequired)
+        ));
+    }
+
+    #[test]
+    fn test_refused_status_lookup_is_not_found() {
+        let status = |status: pb::ResourceStatus, error: Option<&str>| pb::ResourceStatusResponse {
+            status: status as i32,
+            error: error.map(str::to_string),
+            ..Default::default()
+        };
+
+        let denied = status(
+            pb::ResourceStatus::Unspecified,
+            Some("Access denied: resource belongs to another user"),
+        );
+        assert!(matches!(found_status(denied), Err(ResourceError::ResourceNotFound)));
+
+        // A failed job keeps its error
+        let failed = status(pb::ResourceStatus::Failed, Some("parse error"));
+        assert_eq!(found_status(failed).unwrap().error.as_deref(), Some("parse error"));
+        assert!(found_status(status(pb::ResourceStatus::Unspecified, None)).is_ok());
+    }
+
+    #[test]
+    fn test_pdf_content_decoded_to_bytes() {
+        let (_, content) = resource_content("PDF", "JVBERi0xLjQ=").unwrap();
+        assert_eq!(
+            content,
+            pb::add_resource_request::Content::FileContent(b"%PDF-1.4".to_vec())
+        );
+
+        assert!(resource_content("pdf", "%PDF-1.4 not base64").is_err());
+    }
+}
@@ -1,6 +1,9 @@
+pub mod chunking;
 pub mod handlers;
 pub mod types;
 pub mod errors;
 pub mod models;
+pub mod search;
+pub mod ssrf;
 
 pub use handlers::*;
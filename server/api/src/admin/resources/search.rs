@@ -0,0 +1,160 @@
+//! Resource search
+//!
+//! Ingested resources are rows in `documents`, written by the Intelligence
+//! service into the shared database, so search runs here as a query instead
+//! of a gRPC call. Only stored documents are found, which are all fully
+//! ingested; jobs still queued or processing have no row yet.
+
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use super::types::ResourceItemResponse;
+
+/// Characters of content returned as the snippet, as in `ListResources`
+const SNIPPET_CHARS: i32 = 100;
+
+/// What to search for
+pub struct ResourceSearch<'a> {
+    pub user_id: Uuid,
+    /// Matched case-insensitively against title, content and metadata values
+    pub query: &'a str,
+    /// Lowercase type name, as reported in `type`
+    pub resource_type: Option<&'a str>,
+    pub limit: i64,
+    /// Position after which this page starts
+    pub after: Option<(DateTime<Utc>, Uuid)>,
+}
+
+/// One page of matches, newest first
+pub struct SearchPage {
+    pub items: Vec<ResourceItemResponse>,
+    /// Position of the last item, when there may be more
+    pub last: Option<(DateTime<Utc>, Uuid)>,
+    pub total: i64,
+}
+
+/// Escape `%`, `_` and `\` so the query matches literally in `LIKE`
+pub fn like_pattern(query: &str) -> String {
+    let mut pattern = String::with_capacity(query.len() + 2);
+    pattern.push('%');
+    for c in query.chars() {
+        if matches!(c, '%' | '_' | '\\') {
+            pattern.push('\\');
+        }
+        pattern.push(c);
+    }
+    pattern.push('%');
+    pattern
+}
+
+/// Search the user's own resources
+pub async fn search(db: &PgPool, search: ResourceSearch<'_>) -> Result<SearchPage, sqlx::Error> {
+    // Documents store their owner as text
+    let owner = search.user_id.to_string();
+    let pattern = like_pattern(search.query);
+    let (after_created_at, after_id) = search.after.unzip();
+
+    let rows = sqlx::query!(
+        r#"
+        SELECT d.id, d.title, left(d.content, $6) as "snippet!", d.metadata,
+               lower(COALESCE(d.metadata->>'original_type', d.document_type)) as "resource_type!",
+               d.is_global, d.created_at
+        FROM documents d
+        WHERE d.user_id = $1
+          AND (d.title ILIKE $2 OR d.content ILIKE $2
+               OR EXISTS (SELECT 1 FROM jsonb_each_text(d.metadata) m WHERE m.value ILIKE $2))
+          AND ($3::text IS NULL
+               OR lower(COALESCE(d.metadata->>'original_type', d.document_type)) = $3)
+          AND ($4::timestamptz IS NULL OR (d.created_at, d.id) < ($4, $5::uuid))
+        ORDER BY d.created_at DESC, d.id DESC
+        LIMIT $7
+        "#,
+        owner,
+        pattern,
+        search.resource_type,
+        after_created_at,
+        after_id,
+        SNIPPET_CHARS,
+        search.limit
+    )
+    .fetch_all(db)
+    .await?;
+
+    let total = sqlx::query_scalar!(
+        r#"
+        SELECT COUNT(*) as "count!"
+        FROM documents d
+        WHERE d.user_id = $1
+          AND (d.title ILIKE $2 OR d.content ILIKE $2
+               OR EXISTS (SELECT 1 FROM jsonb_each_text(d.metadata) m WHERE m.value ILIKE $2))
+          AND ($3::text IS NULL
+               OR lower(COALESCE(d.metadata->>'original_type', d.document_type)) = $3)
+        "#,
+        owner,
+        pattern,
+        search.resource_type
+    )
+    .fetch_one(db)
+    .await?;
+
+    let last = rows
+        .last()
+        .filter(|_| rows.len() as i64 == search.limit)
+        .map(|row| (row.created_at, row.id));
+
+    let items = rows
+        .into_iter()
+        .map(|row| ResourceItemResponse {
+            id: row.id.to_string(),
+            resource_type: row.resource_type,
+            content: row.snippet,
+            status: "completed".to_string(),
+            // Chunks are stored by the Intelligence service, not counted here
+            chunks_created: 0,
+            documents: 1,
+            metadata: string_map(row.metadata),
+            created_at: row.created_at.timestamp(),
+            title: Some(row.title),
+            is_global: row.is_global,
+        })
+        .collect();
+
+    Ok(SearchPage { items, last, total })
+}
+
+/// Flatten a JSON metadata object into the string map resources expose
+fn string_map(metadata: Value) -> HashMap<String, String> {
+    let Value::Object(fields) = metadata else {
+        return HashMap::new();
+    };
+    fields
+        .into_iter()
+        .map(|(key, value)| match value {
+            Value::String(value) => (key, value),
+            other => (key, other.to_string()),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_query_is_matched_literally() {
+        assert_eq!(like_pattern("report"), "%report%");
+        assert_eq!(like_pattern("100%_done\\"), "%100\\%\\_done\\\\%");
+    }
+
+    #[test]
+    fn test_metadata_values_become_strings() {
+        let map = string_map(serde_json::json!({"title": "Q3", "pages": 12}));
+        assert_eq!(map["title"], "Q3");
+        assert_eq!(map["pages"], "12");
+        assert!(string_map(Value::Null).is_empty());
+    }
+}
@@ -0,0 +1,247 @@
+//! SSRF protection for URLs sent to the Intelligence crawler
+//!
+//! The host is resolved here and rejected if any address is private,
+//! loopback, link-local (including cloud metadata endpoints) or otherwise
+//! reserved, unless it is on `INGESTION_URL_ALLOWLIST`. `INGESTION_URL_DENYLIST`
+//! blocks further hosts. The crawler resolves the host again when it fetches,
+//! so DNS rebinding and redirects still need egress rules on its side; this
+//! check stops requests that point at internal hosts directly.
+
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+use url::{Host, Url};
+
+use super::errors::ResourceError;
+use crate::config::env::{HostRule, IngestionConfig};
+
+/// Metadata service names that resolve to link-local addresses inside clouds
+const METADATA_HOSTS: &[&str] = &["metadata", "metadata.google.internal"];
+
+/// Parse a crawl URL, allowing only http(s) with a host
+pub fn parse_url(raw: &str) -> Result<Url, ResourceError> {
+    let url = Url::parse(raw).map_err(|e| ResourceError::InvalidUrl(e.to_string()))?;
+
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(ResourceError::InvalidUrl(format!(
+            "Only http and https URLs are allowed, got {}",
+            url.scheme()
+        )));
+    }
+
+    if url.host().is_none() {
+        return Err(ResourceError::InvalidUrl(
+            "URL must have a valid domain".to_string(),
+        ));
+    }
+
+    Ok(url)
+}
+
+/// Reject URLs whose host is, or resolves to, a non-public address
+pub async fn check_url(raw: &str, config: &IngestionConfig) -> Result<(), ResourceError> {
+    let url = parse_url(raw)?;
+    let port = url.port_or_known_default().unwrap_or(80);
+
+    let addresses = match url.host() {
+        Some(Host::Ipv4(ip)) => vec![IpAddr::V4(ip)],
+        Some(Host::Ipv6(ip)) => vec![IpAddr::V6(ip)],
+        Some(Host::Domain(domain)) => {
+            let domain = domain.trim_end_matches('.').to_ascii_lowercase();
+            if check_domain(&domain, config)? {
+                return Ok(());
+            }
+
+            tokio::net::lookup_host((domain.as_str(), port))
+                .await
+                .map_err(|_| {
+                    ResourceError::InvalidUrl(format!("Could not resolve host {}", domain))
+                })?
+                .map(|addr| addr.ip())
+                .collect()
+        }
+        None => unreachable!("parse_url requires a host"),
+    };
+
+    check_addresses(&addresses, config)
+}
+
+/// Apply the domain rules; `true` if allowlisted, so the resolved addresses
+/// don't need checking
+fn check_domain(domain: &str, config: &IngestionConfig) -> Result<bool, ResourceError> {
+    if METADATA_HOSTS.contains(&domain)
+        || config.url_denylist.iter().any(|r| r.matches_domain(domain))
+    {
+        return Err(ResourceError::InvalidUrl(format!(
+            "Host {} is not allowed",
+            domain
+        )));
+    }
+
+    Ok(config
+        .url_allowlist
+        .iter()
+        .any(|r| r.matches_domain(domain)))
+}
+
+/// Every address must be public or allowlisted, and none denylisted
+fn check_addresses(addresses: &[IpAddr], config: &IngestionConfig) -> Result<(), ResourceError> {
+    if addresses.is_empty() {
+        return Err(ResourceError::InvalidUrl(
+            "Host has no addresses".to_string(),
+        ));
+    }
+
+    let matches = |rules: &[HostRule], ip: IpAddr| rules.iter().any(|r| r.matches_ip(ip));
+
+    for &ip in addresses {
+        let allowed = !matches(&config.url_denylist, ip)
+            && (matches(&config.url_allowlist, ip) || is_public(ip));
+        if !allowed {
+            return Err(ResourceError::InvalidUrl(
+                "URL points to a private or reserved address".to_string(),
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// Whether an address is on the public internet
+fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_public_v4(ip),
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(mapped) => is_public_v4(mapped),
+            None => is_public_v6(ip),
+        },
+    }
+}
+
+fn is_public_v4(ip: Ipv4Addr) -> bool {
+    let [a, b, ..] = ip.octets();
+    !(ip.is_private()
+        || ip.is_loopback()
+        || ip.is_link_local()
+        || ip.is_unspecified()
+        || ip.is_broadcast()
+        || ip.is_multicast()
+        || ip.is_documentation()
+        // 0.0.0.0/8 "this network"
+        || a == 0
+        // 100.64.0.0/10 carrier-grade NAT
+        || (a == 100 && (64..128).contains(&b))
+        // 192.0.0.0/24 protocol assignments
+        || (a == 192 && b == 0 && ip.octets()[2] == 0)
+        // 198.18.0.0/15 benchmarking
+        || (a == 198 && (b == 18 || b == 19))
+        // 240.0.0.0/4 reserved
+        || a >= 240)
+}
+
+fn is_public_v6(ip: Ipv6Addr) -> bool {
+    let segments = ip.segments();
+
+    // 64:ff9b::/96 NAT64 embeds an IPv4 address in the last 32 bits
+    if segments[..6] == [0x64, 0xff9b, 0, 0, 0, 0] {
+        let [.., hi, lo] = segments;
+        return is_public_v4(Ipv4Addr::from(((hi as u32) << 16) | lo as u32));
+    }
+
+    !(ip.is_loopback()
+        || ip.is_unspecified()
+        || ip.is_multicast()
+        || ip.is_unique_local()
+        || ip.is_unicast_link_local()
+        // fec0::/10 deprecated site-local
+        || (segments[0] & 0xffc0) == 0xfec0
+        // 2001:db8::/32 documentation
+        || (segments[0] == 0x2001 && segments[1] == 0xdb8))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::env::parse_host_rules;
+
+    fn ips(addresses: &[&str]) -> Vec<IpAddr> {
+        addresses.iter().map(|a| a.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn test_only_http_urls_with_hosts() {
+        assert!(parse_url("https://example.com/docs").is_ok());
+        assert!(parse_url("ftp://example.com/file").is_err());
+        assert!(parse_url("file:///etc/passwd").is_err());
+        assert!(parse_url("gopher://localhost:6379/_INFO").is_err());
+        assert!(parse_url("http://").is_err());
+        assert!(parse_url("not a url").is_err());
+    }
+
+    #[test]
+    fn test_private_and_reserved_addresses_rejected() {
+        let config = IngestionConfig::default();
+        for ip in [
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "255.255.255.255",
+            "::1",
+            "::",
+            "fd00:ec2::254",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+            "64:ff9b::a9fe:a9fe",
+        ] {
+            assert!(check_addresses(&ips(&[ip]), &config).is_err(), "{}", ip);
+        }
+
+        assert!(check_addresses(&ips(&["93.184.216.34", "2606:4700::1"]), &config).is_ok());
+        // One private address among public ones is enough to reject
+        assert!(check_addresses(&ips(&["93.184.216.34", "10.0.0.1"]), &config).is_err());
+        assert!(check_addresses(&[], &config).is_err());
+    }
+
+    #[test]
+    fn test_allowlist_and_denylist() {
+        let config = IngestionConfig {
+            url_allowlist: parse_host_rules("ALLOW", "10.0.0.0/8, *.wiki.internal").unwrap(),
+            url_denylist: parse_host_rules("DENY", "93.184.216.34, blocked.example.com").unwrap(),
+        };
+
+        assert!(check_addresses(&ips(&["10.2.3.4"]), &config).is_ok());
+        assert!(check_addresses(&ips(&["192.168.1.1"]), &config).is_err());
+        assert!(check_addresses(&ips(&["93.184.216.34"]), &config).is_err());
+
+        assert!(check_domain("docs.wiki.internal", &config).unwrap());
+        assert!(check_domain("wiki.internal", &config).unwrap());
+        assert!(!check_domain("notwiki.internal", &config).unwrap());
+        assert!(check_domain("api.blocked.example.com", &config).is_err());
+        assert!(check_domain("metadata.google.internal", &config).is_err());
+
+        assert!(parse_host_rules("DENY", "bad/entry").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_ip_literal_hosts_checked() {
+        let config = IngestionConfig::default();
+        assert!(
+            check_url("http://169.254.169.254/latest/meta-data", &config)
+                .await
+                .is_err()
+        );
+        assert!(check_url("http://[::1]:4000/admin", &config).await.is_err());
+        assert!(
+            check_url("http://localhost:4000/admin", &config)
+                .await
+                .is_err()
+        );
+        // Decimal and short IPv4 forms are normalized by the URL parser
+        assert!(check_url("http://2130706433/", &config).await.is_err());
+        assert!(check_url("http://127.1/", &config).await.is_err());
+        assert!(check_url("http://93.184.216.34/", &config).await.is_ok());
+    }
+}
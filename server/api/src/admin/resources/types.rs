@@ -1,12 +1,16 @@
 #![allow(dead_code)]
 use super::errors::ResourceError;
+use super::ssrf;
 use serde::{Deserialize, Serialize};
 
 // Constants for validation
-const MAX_CONTENT_SIZE: usize = 10 * 1024 * 1024; // 10MB
+pub const MAX_CONTENT_SIZE: usize = 10 * 1024 * 1024; // 10MB
 const MAX_TITLE_LENGTH: usize = 500;
 const MIN_CONTENT_LENGTH: usize = 1;
 
+/// Types accepted by `POST /admin/resources`
+pub const RESOURCE_TYPES: &[&str] = &["url", "file", "text", "markdown", "pdf", "html", "code"];
+
 // ============================================================================
 // RESOURCE REQUEST/RESPONSE TYPES
 // ============================================================================
@@ -14,8 +18,8 @@ const MIN_CONTENT_LENGTH: usize = 1;
 #[derive(Debug, Deserialize)]
 pub struct AddResourceRequest {
     #[serde(rename = "type", alias = "resource_type")]
-    pub resource_type: String, // "url", "file", "text", "markdown", "pdf", "html", "code"
-    pub content: String,
+    pub resource_type: String, // one of RESOURCE_TYPES
+    pub content: String, // base64 for "pdf"
     pub title: Option<String>,
     pub metadata: Option<std::collections::HashMap<String, String>>,
     pub config: Option<ResourceConfig>,
@@ -26,13 +30,10 @@ impl AddResourceRequest {
     /// Validate the resource request
     pub fn validate(&self) -> Result<(), ResourceError> {
         // Validate resource type
-        match self.resource_type.to_lowercase().as_str() {
-            "url" | "text" | "markdown" | "pdf" | "html" | "code" | "file" => {}
-            _ => {
-                return Err(ResourceError::UnsupportedResourceType(
-                    self.resource_type.clone(),
-                ))
-            }
+        if !RESOURCE_TYPES.contains(&self.resource_type.to_lowercase().as_str()) {
+            return Err(ResourceError::UnsupportedResourceType(
+                self.resource_type.clone(),
+            ));
         }
 
         // Validate content length
@@ -56,13 +57,13 @@ impl AddResourceRequest {
         }
 
         // Validate title length if provided
-        if let Some(ref title) = self.title {
-            if title.len() > MAX_TITLE_LENGTH {
-                return Err(ResourceError::Validation(format!(
-                    "Title must be less than {} characters",
-                    MAX_TITLE_LENGTH
-                )));
-            }
+        if let Some(ref title) = self.title
+            && title.len() > MAX_TITLE_LENGTH
+        {
+            return Err(ResourceError::Validation(format!(
+                "Title must be less than {} characters",
+                MAX_TITLE_LENGTH
+            )));
         }
 
         // Validate config if provided
@@ -74,28 +75,11 @@ impl AddResourceRequest {
     }
 
     /// Validate URL format
+    ///
+    /// Where the URL points is checked separately by `ssrf::check_url`,
+    /// which needs DNS.
     fn validate_url(&self) -> Result<(), ResourceError> {
-        // Basic URL validation
-        if !self.content.starts_with("http://") && !self.content.starts_with("https://") {
-            return Err(ResourceError::InvalidUrl(
-                "URL must start with http:// or https://".to_string(),
-            ));
-        }
-
-        // Check if URL has at least a domain
-        let url_part = if self.content.starts_with("https://") {
-            &self.content[8..]
-        } else {
-            &self.content[7..]
-        };
-
-        if url_part.is_empty() || url_part.len() < 3 {
-            return Err(ResourceError::InvalidUrl(
-                "URL must have a valid domain".to_string(),
-            ));
-        }
-
-        Ok(())
+        ssrf::parse_url(&self.content).map(|_| ())
     }
 }
 
@@ -113,30 +97,30 @@ impl ResourceConfig {
     /// Validate the resource config
     pub fn validate(&self) -> Result<(), ResourceError> {
         // Validate depth
-        if let Some(depth) = self.depth {
-            if depth < 0 || depth > 10 {
-                return Err(ResourceError::Validation(
-                    "Depth must be between 0 and 10".to_string(),
-                ));
-            }
+        if let Some(depth) = self.depth
+            && !(0..=10).contains(&depth)
+        {
+            return Err(ResourceError::Validation(
+                "Depth must be between 0 and 10".to_string(),
+            ));
         }
 
         // Validate chunk_size
-        if let Some(size) = self.chunk_size {
-            if size < 100 || size > 10000 {
-                return Err(ResourceError::Validation(
-                    "Chunk size must be between 100 and 10000".to_string(),
-                ));
-            }
+        if let Some(size) = self.chunk_size
+            && !(100..=10000).contains(&size)
+        {
+            return Err(ResourceError::Validation(
+                "Chunk size must be between 100 and 10000".to_string(),
+            ));
         }
 
         // Validate chunk_overlap
-        if let Some(overlap) = self.chunk_overlap {
-            if overlap < 0 || overlap > 1000 {
-                return Err(ResourceError::Validation(
-                    "Chunk overlap must be between 0 and 1000".to_string(),
-                ));
-            }
+        if let Some(overlap) = self.chunk_overlap
+            && !(0..=1000).contains(&overlap)
+        {
+            return Err(ResourceError::Validation(
+                "Chunk overlap must be between 0 and 1000".to_string(),
+            ));
         }
 
         Ok(())
@@ -151,6 +135,39 @@ pub struct AddResourceResponse {
     pub created_at: i64,
 }
 
+/// Query for a raw-body file upload; the body is the file itself
+#[derive(Debug, Deserialize)]
+pub struct UploadResourceQuery {
+    pub filename: Option<String>,
+    /// Resource type (`pdf`, `text`, ...), guessed from the filename when absent
+    #[serde(rename = "type", alias = "resource_type")]
+    pub resource_type: Option<String>,
+    pub title: Option<String>,
+}
+
+/// `metadata` part of a multipart upload, sent before the `file` part
+#[derive(Debug, Default, Deserialize)]
+pub struct UploadResourceMetadata {
+    /// Overrides the file part's filename
+    pub filename: Option<String>,
+    #[serde(rename = "type", alias = "resource_type")]
+    pub resource_type: Option<String>,
+    pub title: Option<String>,
+    pub metadata: Option<std::collections::HashMap<String, String>>,
+    pub config: Option<ResourceConfig>,
+}
+
+impl From<UploadResourceQuery> for UploadResourceMetadata {
+    fn from(query: UploadResourceQuery) -> Self {
+        Self {
+            filename: query.filename,
+            resource_type: query.resource_type,
+            title: query.title,
+            ..Default::default()
+        }
+    }
+}
+
 #[derive(Debug, Deserialize)]
 pub struct ListResourcesQuery {
     pub limit: Option<i32>,
@@ -159,6 +176,124 @@ pub struct ListResourcesQuery {
     pub status: Option<String>,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct SearchResourcesQuery {
+    pub q: String,
+    #[serde(rename = "type", alias = "resource_type")]
+    pub resource_type: Option<String>,
+    pub status: Option<String>,
+    pub limit: Option<i32>,
+    pub cursor: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PreviewChunksRequest {
+    pub content: String,
+    pub chunk_size: Option<i32>,
+    pub chunk_overlap: Option<i32>,
+}
+
+impl PreviewChunksRequest {
+    /// Validate the preview request, returning (chunk_size, chunk_overlap)
+    pub fn validate(&self) -> Result<(usize, usize), ResourceError> {
+        if self.content.trim().is_empty() {
+            return Err(ResourceError::InvalidContent);
+        }
+
+        if self.content.len() > MAX_CONTENT_SIZE {
+            return Err(ResourceError::ContentTooLarge);
+        }
+
+        let config = ResourceConfig {
+            depth: None,
+            chunk_size: self.chunk_size,
+            chunk_overlap: self.chunk_overlap,
+            auto_clean: None,
+            generate_embeddings: None,
+            follow_links: None,
+        };
+        config.validate()?;
+
+        // Same defaults as ingestion
+        let chunk_size = self.chunk_size.unwrap_or(1000);
+        let chunk_overlap = self.chunk_overlap.unwrap_or(200);
+
+        if chunk_overlap >= chunk_size {
+            return Err(ResourceError::Validation(
+                "Chunk overlap must be less than chunk size".to_string(),
+            ));
+        }
+
+        Ok((chunk_size as usize, chunk_overlap as usize))
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ChunkPreview {
+    pub index: usize,
+    pub start_char: usize,
+    pub end_char: usize,
+    pub char_count: usize,
+    pub overlap_chars: usize,
+    pub content: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PreviewChunksResponse {
+    pub chunk_size: usize,
+    pub chunk_overlap: usize,
+    pub total_chars: usize,
+    pub chunk_count: usize,
+    pub chunks: Vec<ChunkPreview>,
+}
+
+/// Maximum number of resources accepted by a single bulk delete
+pub const MAX_BULK_DELETE: usize = 50;
+
+#[derive(Debug, Deserialize)]
+pub struct BulkDeleteRequest {
+    pub resource_ids: Vec<String>,
+}
+
+impl BulkDeleteRequest {
+    /// Validate the bulk delete request
+    pub fn validate(&self) -> Result<(), ResourceError> {
+        if self.resource_ids.is_empty() {
+            return Err(ResourceError::Validation(
+                "resource_ids must not be empty".to_string(),
+            ));
+        }
+
+        if self.resource_ids.len() > MAX_BULK_DELETE {
+            return Err(ResourceError::Validation(format!(
+                "At most {} resources can be deleted at once",
+                MAX_BULK_DELETE
+            )));
+        }
+
+        if let Some(id) = self.resource_ids.iter().find(|id| uuid::Uuid::parse_str(id).is_err()) {
+            return Err(ResourceError::Validation(format!(
+                "Invalid resource ID: {}",
+                id
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct BulkDeleteFailure {
+    pub id: String,
+    pub error: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BulkDeleteResponse {
+    pub deleted: Vec<String>,
+    pub failed: Vec<BulkDeleteFailure>,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct GetResourceStatusQuery {
     pub job_id: Option<String>,
@@ -237,6 +372,17 @@ pub struct ResourceStatusResponse {
     pub progress: f32,
 }
 
+/// Payload of `progress` and `done` SSE events
+#[derive(Debug, Serialize)]
+pub struct ResourceProgressEvent {
+    pub status: String,
+    pub progress: f32,
+    pub chunks_created: i32,
+    pub stage: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
 #[derive(Debug, Serialize)]
 pub struct ResourceProgress {
     pub stage: String, // "scraping", "cleaning", "embedding", "indexing"
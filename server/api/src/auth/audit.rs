@@ -0,0 +1,266 @@
+//! Authentication audit log
+//!
+//! Security-relevant account events are appended to `auth_events` so users
+//! can review their own history and admins can investigate incidents.
+//! Recording never fails the request that triggered it.
+
+use axum::http::{HeaderMap, header};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use utoipa::{IntoParams, ToSchema};
+use sqlx::PgPool;
+use sqlx::types::ipnetwork::IpNetwork;
+use uuid::Uuid;
+
+use crate::middleware::ClientIp;
+
+/// Default and maximum page size for event listings
+const DEFAULT_EVENT_LIMIT: i64 = 50;
+const MAX_EVENT_LIMIT: i64 = 200;
+
+/// Kinds of recorded events
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuthEventType {
+    SigninSuccess,
+    SigninFailure,
+    Signout,
+    PasswordChange,
+    PasswordReset,
+    EmailVerified,
+    OauthLink,
+    SessionRevoked,
+    ImpersonationStart,
+    ImpersonationStop,
+    AdminForceSignout,
+}
+
+impl AuthEventType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuthEventType::SigninSuccess => "signin_success",
+            AuthEventType::SigninFailure => "signin_failure",
+            AuthEventType::Signout => "signout",
+            AuthEventType::PasswordChange => "password_change",
+            AuthEventType::PasswordReset => "password_reset",
+            AuthEventType::EmailVerified => "email_verified",
+            AuthEventType::OauthLink => "oauth_link",
+            AuthEventType::SessionRevoked => "session_revoked",
+            AuthEventType::ImpersonationStart => "impersonation_start",
+            AuthEventType::ImpersonationStop => "impersonation_stop",
+            AuthEventType::AdminForceSignout => "admin_force_signout",
+        }
+    }
+}
+
+/// Where a request came from
+#[derive(Debug, Clone, Default)]
+pub struct ClientInfo {
+    pub ip_address: Option<IpNetwork>,
+    pub user_agent: Option<String>,
+}
+
+impl ClientInfo {
+    pub fn new(headers: &HeaderMap, ClientIp(ip): ClientIp) -> Self {
+        Self {
+            ip_address: Some(IpNetwork::from(ip)),
+            user_agent: headers
+                .get(header::USER_AGENT)
+                .and_then(|v| v.to_str().ok())
+                .map(|s| s.to_string()),
+        }
+    }
+}
+
+/// A recorded event
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AuthEvent {
+    pub id: Uuid,
+    pub event_type: String,
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+    pub metadata: serde_json::Value,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct AuthEventQuery {
+    pub limit: Option<i64>,
+    /// Return events older than this timestamp (the previous page's `next_cursor`)
+    pub before: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AuthEventListResponse {
+    pub events: Vec<AuthEvent>,
+    pub next_cursor: Option<DateTime<Utc>>,
+}
+
+/// SHA-256 of a trimmed, lowercased email, for events without an account
+///
+/// Repeated attempts against the same address can be correlated without the
+/// log holding the address itself.
+pub fn email_hash(email: &str) -> String {
+    hex::encode(Sha256::digest(email.trim().to_lowercase()))
+}
+
+/// Append an event to the audit log
+///
+/// Errors are logged rather than returned so auditing cannot break auth flows.
+pub async fn record(
+    db: &PgPool,
+    user_id: Option<Uuid>,
+    event_type: AuthEventType,
+    client: &ClientInfo,
+    metadata: serde_json::Value,
+) {
+    let result = sqlx::query!(
+        r#"
+        INSERT INTO auth_events (user_id, event_type, ip_address, user_agent, metadata)
+        VALUES ($1, $2, $3, $4, $5)
+        "#,
+        user_id,
+        event_type.as_str(),
+        client.ip_address,
+        client.user_agent,
+        metadata
+    )
+    .execute(db)
+    .await;
+
+    if let Err(e) = result {
+        tracing::error!(
+            user_id = ?user_id,
+            event_type = event_type.as_str(),
+            "Failed to record auth event: {}",
+            e
+        );
+    }
+}
+
+/// List a user's events, newest first
+pub async fn list_user_events(
+    db: &PgPool,
+    user_id: Uuid,
+    query: &AuthEventQuery,
+) -> Result<AuthEventListResponse, sqlx::Error> {
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_EVENT_LIMIT)
+        .clamp(1, MAX_EVENT_LIMIT);
+
+    let events = sqlx::query_as!(
+        AuthEvent,
+        r#"
+        SELECT id, event_type, ip_address::TEXT as "ip_address?", user_agent, metadata, created_at
+        FROM auth_events
+        WHERE user_id = $1 AND ($2::timestamptz IS NULL OR created_at < $2)
+        ORDER BY created_at DESC
+        LIMIT $3
+        "#,
+        user_id,
+        query.before,
+        limit
+    )
+    .fetch_all(db)
+    .await?;
+
+    let next_cursor = if events.len() as i64 == limit {
+        events.last().map(|e| e.created_at)
+    } else {
+        None
+    };
+
+    Ok(AuthEventListResponse {
+        events,
+        next_cursor,
+    })
+}
+
+/// Delete events older than the retention period
+pub async fn cleanup_old_events(db: &PgPool, retention_days: u32) -> Result<u64, sqlx::Error> {
+    let cutoff = Utc::now() - Duration::days(retention_days as i64);
+
+    let result = sqlx::query!("DELETE FROM auth_events WHERE created_at < $1", cutoff)
+        .execute(db)
+        .await?;
+
+    Ok(result.rows_affected())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_email_hash_ignores_case_and_whitespace() {
+        let hash = email_hash("user@example.com");
+        assert_eq!(hash.len(), 64);
+        assert!(!hash.contains("example"));
+        assert_eq!(email_hash(" User@Example.com "), hash);
+        assert_ne!(email_hash("other@example.com"), hash);
+    }
+
+    #[sqlx::test]
+    async fn test_migration_hashes_stored_emails(db: PgPool) {
+        sqlx::query!(
+            "INSERT INTO auth_events (event_type, metadata) VALUES ('signin_failure', $1)",
+            serde_json::json!({ "email": "User@Example.com", "reason": "unknown_email" })
+        )
+        .execute(&db)
+        .await
+        .unwrap();
+
+        sqlx::raw_sql(include_str!(
+            "../../migrations/20260201000026_hash_audit_event_emails.up.sql"
+        ))
+        .execute(&db)
+        .await
+        .unwrap();
+
+        let metadata = sqlx::query_scalar!("SELECT metadata FROM auth_events")
+            .fetch_one(&db)
+            .await
+            .unwrap();
+        assert_eq!(
+            metadata,
+            serde_json::json!({
+                "email_hash": email_hash("user@example.com"),
+                "reason": "unknown_email"
+            })
+        );
+    }
+
+    #[test]
+    fn test_event_type_names_match_serde() {
+        let all = [
+            AuthEventType::SigninSuccess,
+            AuthEventType::SigninFailure,
+            AuthEventType::Signout,
+            AuthEventType::PasswordChange,
+            AuthEventType::PasswordReset,
+            AuthEventType::EmailVerified,
+            AuthEventType::OauthLink,
+            AuthEventType::SessionRevoked,
+        ];
+
+        for event_type in all {
+            assert_eq!(
+                serde_json::to_value(event_type).unwrap(),
+                serde_json::Value::from(event_type.as_str())
+            );
+        }
+    }
+
+    #[test]
+    fn test_client_info_from_request() {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::USER_AGENT, "curl/8.0".parse().unwrap());
+
+        let client = ClientInfo::new(&headers, ClientIp("192.0.2.1".parse().unwrap()));
+
+        assert_eq!(client.user_agent.as_deref(), Some("curl/8.0"));
+        assert_eq!(client.ip_address.unwrap().ip().to_string(), "192.0.2.1");
+    }
+}
@@ -1,13 +1,70 @@
 use crate::common::background;
 use sqlx::PgPool;
 
+use super::jwt::{self, SessionDenylist};
+use crate::config::env::{OAuthConfig, SecurityConfig};
+
+/// How often the JWT session denylist is re-read from the database
+const DENYLIST_REFRESH_SECONDS: u64 = 30;
+
+/// How often provider tokens nearing expiry are refreshed
+const OAUTH_TOKEN_REFRESH_SECONDS: u64 = 900;
+
 /// Start session cleanup background task
-/// Runs every hour to remove expired sessions
-pub fn start_session_cleanup_task(db: PgPool) {
+/// Runs every `SESSION_CLEANUP_INTERVAL_SECONDS` to remove expired sessions in batches
+pub fn start_session_cleanup_task(db: PgPool, config: &SecurityConfig) {
+    let batch_size = config.session_cleanup_batch_size;
     background::start_periodic_task(
-        db,
+        db.clone(),
         "Session cleanup",
+        config.session_cleanup_interval_seconds,
+        move |db| async move { super::session::cleanup_expired_sessions(&db, batch_size).await },
+    );
+
+    background::start_periodic_task(
+        db,
+        "Revoked session cleanup",
         3600, // 1 hour
-        |db| async move { super::session::cleanup_expired_sessions(&db).await },
+        |db| async move { jwt::cleanup_revoked_sessions(&db).await },
+    );
+}
+
+/// Start auth event cleanup background task
+/// Runs daily to drop audit events past the retention period
+pub fn start_auth_event_cleanup_task(db: PgPool, retention_days: u32) {
+    background::start_periodic_task(
+        db,
+        "Auth event cleanup",
+        86400, // 24 hours
+        move |db| async move { super::audit::cleanup_old_events(&db, retention_days).await },
+    );
+}
+
+/// Start JWT denylist refresh background task
+/// Bounds how long a revoked session's access tokens keep working
+pub fn start_denylist_refresh_task(db: PgPool, denylist: SessionDenylist) {
+    background::start_periodic_task(
+        db,
+        "Session denylist refresh",
+        DENYLIST_REFRESH_SECONDS,
+        move |db| {
+            let denylist = denylist.clone();
+            // The count is the list's size, not rows cleaned up, so it isn't reported
+            async move { denylist.refresh(&db).await.map(|_| 0) }
+        },
+    );
+}
+
+/// Start OAuth token refresh background task
+/// Renews stored provider tokens that expire within the next hour
+pub fn start_oauth_token_refresh_task(db: PgPool, config: OAuthConfig) {
+    background::start_periodic_task(
+        db,
+        "OAuth token refresh",
+        OAUTH_TOKEN_REFRESH_SECONDS,
+        move |db| {
+            let config = config.clone();
+            async move { super::oauth::token_refresh::refresh_expiring_tokens(&db, &config).await }
+        },
     );
 }
@@ -0,0 +1,137 @@
+//! Optional CAPTCHA verification (hCaptcha / Turnstile / reCAPTCHA)
+//!
+//! Verification is skipped entirely when `CAPTCHA_PROVIDER`/`CAPTCHA_SECRET`
+//! are not configured, so self-hosted deployments don't need a provider.
+
+use serde::Deserialize;
+
+use super::AuthError;
+use crate::config::env::CaptchaConfig;
+
+/// Supported CAPTCHA providers
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CaptchaProvider {
+    HCaptcha,
+    Turnstile,
+    ReCaptcha,
+}
+
+impl CaptchaProvider {
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "hcaptcha" => Some(CaptchaProvider::HCaptcha),
+            "turnstile" => Some(CaptchaProvider::Turnstile),
+            "recaptcha" => Some(CaptchaProvider::ReCaptcha),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CaptchaProvider::HCaptcha => "hcaptcha",
+            CaptchaProvider::Turnstile => "turnstile",
+            CaptchaProvider::ReCaptcha => "recaptcha",
+        }
+    }
+
+    /// Provider endpoint that validates client tokens
+    pub fn siteverify_url(&self) -> &'static str {
+        match self {
+            CaptchaProvider::HCaptcha => "https://api.hcaptcha.com/siteverify",
+            CaptchaProvider::Turnstile => {
+                "https://challenges.cloudflare.com/turnstile/v0/siteverify"
+            }
+            CaptchaProvider::ReCaptcha => "https://www.google.com/recaptcha/api/siteverify",
+        }
+    }
+}
+
+/// Subset of the siteverify response shared by all providers
+#[derive(Debug, Deserialize)]
+struct SiteverifyResponse {
+    success: bool,
+    #[serde(default, rename = "error-codes")]
+    error_codes: Vec<String>,
+}
+
+/// Verify a client CAPTCHA token against the configured provider
+///
+/// Returns `Ok(())` without any network call when CAPTCHA is not configured.
+///
+/// # Errors
+/// Returns `CaptchaRequired` if the token is missing or rejected by the provider
+pub async fn verify_captcha(config: &CaptchaConfig, token: Option<&str>) -> Result<(), AuthError> {
+    let (Some(provider), Some(secret)) = (config.provider, config.secret.as_deref()) else {
+        return Ok(());
+    };
+
+    let token = token
+        .filter(|t| !t.trim().is_empty())
+        .ok_or(AuthError::CaptchaRequired)?;
+
+    let response = reqwest::Client::new()
+        .post(provider.siteverify_url())
+        .form(&[("secret", secret), ("response", token)])
+        .send()
+        .await
+        .map_err(|e| {
+            tracing::error!("CAPTCHA provider request failed: {}", e);
+            AuthError::Internal
+        })?
+        .json::<SiteverifyResponse>()
+        .await
+        .map_err(|e| {
+            tracing::error!("Invalid CAPTCHA provider response: {}", e);
+            AuthError::Internal
+        })?;
+
+    if !response.success {
+        tracing::debug!(errors = ?response.error_codes, "CAPTCHA verification failed");
+        return Err(AuthError::CaptchaRequired);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_provider_parsing() {
+        assert_eq!(
+            CaptchaProvider::from_str("hCaptcha"),
+            Some(CaptchaProvider::HCaptcha)
+        );
+        assert_eq!(
+            CaptchaProvider::from_str("turnstile"),
+            Some(CaptchaProvider::Turnstile)
+        );
+        assert_eq!(CaptchaProvider::from_str("unknown"), None);
+    }
+
+    #[tokio::test]
+    async fn test_unconfigured_skips_verification() {
+        let config = CaptchaConfig {
+            provider: None,
+            secret: None,
+        };
+        assert!(verify_captcha(&config, None).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_missing_token_rejected_when_configured() {
+        let config = CaptchaConfig {
+            provider: Some(CaptchaProvider::Turnstile),
+            secret: Some("secret".to_string()),
+        };
+        assert!(matches!(
+            verify_captcha(&config, None).await,
+            Err(AuthError::CaptchaRequired)
+        ));
+        assert!(matches!(
+            verify_captcha(&config, Some("  ")).await,
+            Err(AuthError::CaptchaRequired)
+        ));
+    }
+}
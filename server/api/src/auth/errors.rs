@@ -1,11 +1,14 @@
 use axum::{
-    http::StatusCode,
+    http::{HeaderValue, StatusCode, header},
     response::{IntoResponse, Response},
     Json,
 };
 use serde_json::json;
 use thiserror::Error;
 
+use crate::common::validation::ValidationErrors;
+use crate::config::database;
+
 #[derive(Debug, Error)]
 pub enum AuthError {
     #[error("Invalid credentials")]
@@ -21,7 +24,10 @@ pub enum AuthError {
     #[error("User already exists")]
     UserAlreadyExists,
 
-    #[error("Invalid token")]
+    #[error("Username already taken")]
+    UsernameAlreadyTaken,
+
+    #[error("Invalid or expired token")]
     InvalidToken,
 
     #[error("Token expired")]
@@ -48,6 +54,24 @@ pub enum AuthError {
     #[error("Validation error: {0}")]
     Validation(String),
 
+    /// Per-field failures, reported as 422 `validation_failed`
+    #[error("Validation failed: {0}")]
+    ValidationFields(ValidationErrors),
+
+    #[error("CAPTCHA verification required")]
+    CaptchaRequired,
+
+    #[error("Too many incorrect verification codes")]
+    OtpLocked,
+
+    /// Too many failed sign-ins; seconds until the account unlocks
+    #[error("Account locked, retry in {0}s")]
+    AccountLocked(u64),
+
+    /// An email was sent too recently; seconds until another is allowed
+    #[error("Email sent too recently, retry in {0}s")]
+    ResendCooldown(u64),
+
     #[allow(dead_code)] // Reserved for future use
     #[error("Internal auth error")]
     Internal,
@@ -55,12 +79,28 @@ pub enum AuthError {
 
 impl IntoResponse for AuthError {
     fn into_response(self) -> Response {
+        if let AuthError::Database(e) = &self
+            && database::is_pool_exhausted(e)
+        {
+            return database::pool_exhausted_response();
+        }
+
+        if let AuthError::ValidationFields(errors) = self {
+            return errors.into_response();
+        }
+
+        let retry_after = match self {
+            AuthError::ResendCooldown(seconds) | AuthError::AccountLocked(seconds) => Some(seconds),
+            _ => None,
+        };
+
         let (status, message) = match self {
             AuthError::InvalidCredentials => (StatusCode::UNAUTHORIZED, "Invalid credentials"),
             AuthError::Unauthorized => (StatusCode::UNAUTHORIZED, "Unauthorized"),
             AuthError::EmailAlreadyExists => (StatusCode::CONFLICT, "Email already exists"),
             AuthError::UserAlreadyExists => (StatusCode::CONFLICT, "User already exists"),
-            AuthError::InvalidToken => (StatusCode::UNAUTHORIZED, "Invalid token"),
+            AuthError::UsernameAlreadyTaken => (StatusCode::CONFLICT, "Username already taken"),
+            AuthError::InvalidToken => (StatusCode::UNAUTHORIZED, "Invalid or expired token"),
             AuthError::TokenExpired => (StatusCode::UNAUTHORIZED, "Token expired"),
             AuthError::WeakPassword => (StatusCode::BAD_REQUEST, "Password too weak"),
             AuthError::EmailNotVerified => (StatusCode::FORBIDDEN, "Email not verified"),
@@ -72,13 +112,50 @@ impl IntoResponse for AuthError {
             AuthError::HashError => (StatusCode::INTERNAL_SERVER_ERROR, "Hash error"),
             AuthError::Internal => (StatusCode::INTERNAL_SERVER_ERROR, "Internal error"),
             AuthError::Validation(ref msg) => (StatusCode::BAD_REQUEST, msg.as_str()),
+            AuthError::ValidationFields(_) => unreachable!("handled above"),
+            AuthError::CaptchaRequired => {
+                (StatusCode::BAD_REQUEST, "CAPTCHA verification required")
+            }
+            AuthError::OtpLocked => (
+                StatusCode::TOO_MANY_REQUESTS,
+                "Too many incorrect codes. Request a new verification email.",
+            ),
+            AuthError::AccountLocked(_) => (
+                StatusCode::LOCKED,
+                "Too many failed sign-in attempts. Try again later or reset your password.",
+            ),
+            AuthError::ResendCooldown(_) => (
+                StatusCode::TOO_MANY_REQUESTS,
+                "An email was sent recently. Please wait before requesting another.",
+            ),
+        };
+
+        // Machine-readable code for errors clients need to act on
+        let error = match self {
+            AuthError::CaptchaRequired => "captcha_required",
+            AuthError::OtpLocked => "otp_locked",
+            AuthError::AccountLocked(_) => "account_locked",
+            AuthError::ResendCooldown(_) => "resend_cooldown",
+            _ => message,
+        };
+
+        let Some(retry_after) = retry_after else {
+            let body = Json(json!({
+                "error": error,
+                "message": message,
+            }));
+            return (status, body).into_response();
         };
 
         let body = Json(json!({
-            "error": message,
+            "error": error,
             "message": message,
+            "retry_after": retry_after,
         }));
-
-        (status, body).into_response()
+        let mut response = (status, body).into_response();
+        response
+            .headers_mut()
+            .insert(header::RETRY_AFTER, HeaderValue::from(retry_after));
+        response
     }
 }
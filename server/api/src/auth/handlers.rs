@@ -1,35 +1,61 @@
 use axum::{
     Json,
-    extract::{ConnectInfo, Query, State},
+    extract::{Query, State},
     http::{header, HeaderMap},
 };
 pub use sqlx::types::ipnetwork::IpNetwork;
-use std::net::SocketAddr;
 
+use crate::common::validation::{self, ValidationErrors};
+use crate::feature_flags::NEW_DEVICE_ALERTS;
 use crate::gateway::AppState;
+use crate::gateway::openapi::ErrorBody;
+use crate::middleware::ClientIp;
 
 use super::{
-    AuthError, ForgotPasswordRequest, ForgotPasswordResponse, RecoverAccountRequest,
+    AuthError, ConfirmEmailChangeQuery, ConfirmEmailChangeResponse, ForgotPasswordRequest, ForgotPasswordResponse, RecoverAccountRequest,
     RecoverAccountResponse, RefreshRequest, RefreshResponse, ResendVerificationRequest,
     ResendVerificationResponse, ResetPasswordRequest, ResetPasswordResponse, SignInRequest,
     SignInResponse, SignUpRequest, SignUpResponse, VerifyEmailRequest, VerifyEmailResponse,
-    service,
+    audit::ClientInfo, captcha, service,
 };
 
 // ===== Sign Up =====
 
 /// POST /auth/signup
 /// Register a new user account
+#[utoipa::path(
+    post,
+    path = "/auth/signup",
+    tag = "auth",
+    request_body = SignUpRequest,
+    responses(
+        (
+            status = 200,
+            description = "Account created; a verification email is sent",
+            body = SignUpResponse
+        ),
+        (status = 400, description = "Invalid input", body = ErrorBody),
+        (status = 409, description = "Email or username already in use", body = ErrorBody),
+    )
+)]
 pub async fn signup(
     State(app_state): State<AppState>,
     Json(payload): Json<SignUpRequest>,
 ) -> Result<Json<SignUpResponse>, AuthError> {
-    crate::common::validation::validate_email(&payload.email)
-        .map_err(|e| AuthError::Validation(e))?;
-    crate::common::validation::validate_password(&payload.password)
-        .map_err(|e| AuthError::Validation(e))?;
+    let mut errors = ValidationErrors::new();
+    errors
+        .check("email", validation::validate_email(&payload.email))
+        .check("password", validation::validate_password(&payload.password));
+    if let Some(username) = &payload.username {
+        errors.check("username", validation::validate_username(username));
+    }
+    if let Some(name) = &payload.name {
+        errors.check("name", validation::validate_name(name));
+    }
+    errors.into_result().map_err(AuthError::ValidationFields)?;
+    captcha::verify_captcha(&app_state.config.captcha, payload.captcha_token.as_deref()).await?;
 
-    let response = service::signup(&app_state.db, payload, &app_state.config.email).await?;
+    let response = service::signup(&app_state.db, payload, &app_state.email).await?;
     Ok(Json(response))
 }
 
@@ -37,23 +63,51 @@ pub async fn signup(
 
 /// POST /auth/signin
 /// Authenticate user and create session
+#[utoipa::path(
+    post,
+    path = "/auth/signin",
+    tag = "auth",
+    request_body = SignInRequest,
+    responses(
+        (status = 200, description = "Signed in", body = SignInResponse),
+        (status = 400, description = "Invalid input", body = ErrorBody),
+        (status = 401, description = "Wrong email or password", body = ErrorBody),
+        (status = 403, description = "Email not verified", body = ErrorBody),
+        (status = 423, description = "Account locked after repeated failures", body = ErrorBody),
+    )
+)]
 pub async fn signin(
     State(app_state): State<AppState>,
     headers: HeaderMap,
-    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    client_ip: ClientIp,
     Json(payload): Json<SignInRequest>,
 ) -> Result<Json<SignInResponse>, AuthError> {
-    crate::common::validation::validate_email(&payload.email)
-        .map_err(|e| AuthError::Validation(e))?;
+    let mut errors = ValidationErrors::new();
+    errors.check("email", validation::validate_email(&payload.email));
+    if payload.password.is_empty() {
+        errors.add("password", "Password cannot be empty");
+    }
+    errors.into_result().map_err(AuthError::ValidationFields)?;
 
     let user_agent = headers
         .get(header::USER_AGENT)
         .and_then(|v| v.to_str().ok())
         .map(|s| s.to_string());
 
-    let ip_address = Some(IpNetwork::from(addr.ip()));
+    let ip_address = Some(IpNetwork::from(client_ip.0));
 
-    let response = service::signin(&app_state.db, payload, ip_address, user_agent).await?;
+    let response = service::signin(
+        &app_state.db,
+        app_state.cache.as_ref(),
+        payload,
+        ip_address,
+        user_agent,
+        &app_state.config.jwt,
+        app_state.config.security.max_sessions_per_user,
+        &app_state.email,
+        crate::is_enabled!(app_state, NEW_DEVICE_ALERTS),
+    )
+    .await?;
     Ok(Json(response))
 }
 
@@ -61,9 +115,20 @@ pub async fn signin(
 
 /// POST /auth/signout
 /// Invalidate current session
+#[utoipa::path(
+    post,
+    path = "/auth/signout",
+    tag = "auth",
+    responses(
+        (status = 200, description = "Signed out"),
+        (status = 401, description = "Missing or invalid session", body = ErrorBody),
+    ),
+    security(("bearer_auth" = []))
+)]
 pub async fn signout(
     State(app_state): State<AppState>,
     headers: HeaderMap,
+    client_ip: ClientIp,
 ) -> Result<Json<serde_json::Value>, AuthError> {
     // Extract Bearer token from Authorization header
     let auth_header = headers
@@ -75,7 +140,14 @@ pub async fn signout(
         .strip_prefix("Bearer ")
         .ok_or(AuthError::Unauthorized)?;
 
-    service::signout(&app_state.db, session_token).await?;
+    let client = ClientInfo::new(&headers, client_ip);
+    service::signout(
+        &app_state.db,
+        app_state.cache.as_ref(),
+        session_token,
+        &client,
+    )
+    .await?;
     Ok(Json(serde_json::json!({
         "message": "Signed out successfully"
     })))
@@ -85,10 +157,20 @@ pub async fn signout(
 
 /// POST /auth/refresh
 /// Refresh session token
+#[utoipa::path(
+    post,
+    path = "/auth/refresh",
+    tag = "auth",
+    request_body = RefreshRequest,
+    responses(
+        (status = 200, description = "New session token", body = RefreshResponse),
+        (status = 401, description = "Invalid or expired session", body = ErrorBody),
+    )
+)]
 pub async fn refresh(
     State(app_state): State<AppState>,
     headers: HeaderMap,
-    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    client_ip: ClientIp,
     Json(payload): Json<RefreshRequest>,
 ) -> Result<Json<RefreshResponse>, AuthError> {
     let user_agent = headers
@@ -96,9 +178,18 @@ pub async fn refresh(
         .and_then(|v| v.to_str().ok())
         .map(|s| s.to_string());
 
-    let ip_address = Some(IpNetwork::from(addr.ip()));
+    let ip_address = Some(IpNetwork::from(client_ip.0));
 
-    let response = service::refresh_session(&app_state.db, payload, ip_address, user_agent).await?;
+    let response = service::refresh_session(
+        &app_state.db,
+        app_state.cache.as_ref(),
+        payload,
+        ip_address,
+        user_agent,
+        &app_state.config.jwt,
+        app_state.config.security.max_sessions_per_user,
+    )
+    .await?;
     Ok(Json(response))
 }
 
@@ -106,21 +197,69 @@ pub async fn refresh(
 
 /// GET /auth/verify-email
 /// Verify user email address via token link
+#[utoipa::path(
+    get,
+    path = "/auth/verify-email",
+    tag = "auth",
+    params(VerifyEmailRequest),
+    responses(
+        (status = 200, description = "Email verified", body = VerifyEmailResponse),
+        (status = 400, description = "Invalid or expired token", body = ErrorBody),
+    )
+)]
 pub async fn verify_get(
     State(app_state): State<AppState>,
+    headers: HeaderMap,
+    client_ip: ClientIp,
     Query(params): Query<VerifyEmailRequest>,
 ) -> Result<Json<VerifyEmailResponse>, AuthError> {
-    let response = service::verify_email(&app_state.db, params).await?;
+    let client = ClientInfo::new(&headers, client_ip);
+    let response = service::verify_email(&app_state.db, params, &client).await?;
     Ok(Json(response))
 }
 
 /// POST /auth/verify-email
 /// Verify user email address via OTP or token
+#[utoipa::path(
+    post,
+    path = "/auth/verify-email",
+    tag = "auth",
+    request_body = VerifyEmailRequest,
+    responses(
+        (status = 200, description = "Email verified", body = VerifyEmailResponse),
+        (status = 400, description = "Invalid or expired token or code", body = ErrorBody),
+    )
+)]
 pub async fn verify_post(
     State(app_state): State<AppState>,
+    headers: HeaderMap,
+    client_ip: ClientIp,
     Json(payload): Json<VerifyEmailRequest>,
 ) -> Result<Json<VerifyEmailResponse>, AuthError> {
-    let response = service::verify_email(&app_state.db, payload).await?;
+    let client = ClientInfo::new(&headers, client_ip);
+    let response = service::verify_email(&app_state.db, payload, &client).await?;
+    Ok(Json(response))
+}
+
+// ===== Email Change Confirmation =====
+
+/// GET /auth/confirm-email-change
+/// Confirm a pending email change via token link
+#[utoipa::path(
+    get,
+    path = "/auth/confirm-email-change",
+    tag = "auth",
+    params(ConfirmEmailChangeQuery),
+    responses(
+        (status = 200, description = "Email changed", body = ConfirmEmailChangeResponse),
+        (status = 400, description = "Invalid or expired token", body = ErrorBody),
+    )
+)]
+pub async fn confirm_email_change(
+    State(app_state): State<AppState>,
+    Query(params): Query<ConfirmEmailChangeQuery>,
+) -> Result<Json<ConfirmEmailChangeResponse>, AuthError> {
+    let response = service::confirm_email_change(&app_state.db, params).await?;
     Ok(Json(response))
 }
 
@@ -128,12 +267,27 @@ pub async fn verify_post(
 
 /// POST /auth/forgot-password
 /// Send password reset email
+#[utoipa::path(
+    post,
+    path = "/auth/forgot-password",
+    tag = "auth",
+    request_body = ForgotPasswordRequest,
+    responses(
+        (
+            status = 200,
+            description = "Reset email sent if the account exists",
+            body = ForgotPasswordResponse
+        ),
+    )
+)]
 pub async fn forgot_password(
     State(app_state): State<AppState>,
     Json(payload): Json<ForgotPasswordRequest>,
 ) -> Result<Json<ForgotPasswordResponse>, AuthError> {
+    captcha::verify_captcha(&app_state.config.captcha, payload.captcha_token.as_deref()).await?;
+
     let response =
-        service::forgot_password(&app_state.db, payload, &app_state.config.email).await?;
+        service::forgot_password(&app_state.db, payload, &app_state.email).await?;
     Ok(Json(response))
 }
 
@@ -141,11 +295,25 @@ pub async fn forgot_password(
 
 /// POST /auth/reset-password
 /// Reset password with token
+#[utoipa::path(
+    post,
+    path = "/auth/reset-password",
+    tag = "auth",
+    request_body = ResetPasswordRequest,
+    responses(
+        (status = 200, description = "Password reset", body = ResetPasswordResponse),
+        (status = 400, description = "Invalid token or password", body = ErrorBody),
+    )
+)]
 pub async fn reset_password(
     State(app_state): State<AppState>,
+    headers: HeaderMap,
+    client_ip: ClientIp,
     Json(payload): Json<ResetPasswordRequest>,
 ) -> Result<Json<ResetPasswordResponse>, AuthError> {
-    let response = service::reset_password(&app_state.db, payload).await?;
+    let client = ClientInfo::new(&headers, client_ip);
+    let response =
+        service::reset_password(&app_state.db, app_state.cache.as_ref(), payload, &client).await?;
     Ok(Json(response))
 }
 
@@ -153,12 +321,25 @@ pub async fn reset_password(
 
 /// POST /auth/resend-verification
 /// Resend verification email to user
+#[utoipa::path(
+    post,
+    path = "/auth/resend-verification",
+    tag = "auth",
+    request_body = ResendVerificationRequest,
+    responses(
+        (
+            status = 200,
+            description = "Verification email sent if needed",
+            body = ResendVerificationResponse
+        ),
+    )
+)]
 pub async fn resend_verification(
     State(app_state): State<AppState>,
     Json(payload): Json<ResendVerificationRequest>,
 ) -> Result<Json<ResendVerificationResponse>, AuthError> {
     let response =
-        service::resend_verification_email(&app_state.db, payload, &app_state.config.email).await?;
+        service::resend_verification_email(&app_state.db, payload, &app_state.email).await?;
     Ok(Json(response))
 }
 
@@ -166,10 +347,25 @@ pub async fn resend_verification(
 
 /// POST /auth/recover-account
 /// Recover a soft-deleted account
+#[utoipa::path(
+    post,
+    path = "/auth/recover-account",
+    tag = "auth",
+    request_body = RecoverAccountRequest,
+    responses(
+        (
+            status = 200,
+            description = "Account restored and signed in",
+            body = RecoverAccountResponse
+        ),
+        (status = 401, description = "Wrong email or password", body = ErrorBody),
+        (status = 410, description = "Recovery period has passed", body = ErrorBody),
+    )
+)]
 pub async fn recover_account(
     State(app_state): State<AppState>,
     headers: HeaderMap,
-    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    client_ip: ClientIp,
     Json(payload): Json<RecoverAccountRequest>,
 ) -> Result<Json<RecoverAccountResponse>, AuthError> {
     let user_agent = headers
@@ -177,8 +373,17 @@ pub async fn recover_account(
         .and_then(|v| v.to_str().ok())
         .map(|s| s.to_string());
 
-    let ip_address = Some(IpNetwork::from(addr.ip()));
+    let ip_address = Some(IpNetwork::from(client_ip.0));
 
-    let response = service::recover_account(&app_state.db, payload, ip_address, user_agent).await?;
+    let response = service::recover_account(
+        &app_state.db,
+        app_state.cache.as_ref(),
+        payload,
+        ip_address,
+        user_agent,
+        app_state.config.security.max_sessions_per_user,
+        app_state.config.security.deleted_account_retention_days,
+    )
+    .await?;
     Ok(Json(response))
 }
@@ -0,0 +1,256 @@
+//! Optional short-lived JWT access tokens (HS256)
+//!
+//! When enabled, signin and refresh return a signed access token alongside
+//! the opaque session token. The auth middleware verifies these locally and
+//! only queries the database for opaque tokens. Revoked sessions are caught
+//! by an in-memory denylist synced from `revoked_sessions`.
+
+use std::collections::HashSet;
+use std::sync::{Arc, RwLock};
+
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use chrono::{DateTime, Duration, Utc};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use super::{AuthError, Role};
+use crate::config::env::JwtConfig;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Pre-encoded `{"alg":"HS256","typ":"JWT"}` header
+const HEADER: &str = "eyJhbGciOiJIUzI1NiIsInR5cCI6IkpXVCJ9";
+
+/// Access token claims
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Claims {
+    /// User ID
+    pub sub: Uuid,
+    pub role: Role,
+    /// Session the token was issued for
+    pub sid: Uuid,
+    pub iat: i64,
+    pub exp: i64,
+}
+
+fn mac(secret: &str) -> HmacSha256 {
+    HmacSha256::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length")
+}
+
+/// Issue an access token for a session
+/// Returns (token, expires_at)
+pub fn issue_access_token(
+    config: &JwtConfig,
+    user_id: Uuid,
+    role: Role,
+    session_id: Uuid,
+) -> Result<(String, DateTime<Utc>), AuthError> {
+    let secret = config.secret.as_deref().ok_or(AuthError::Internal)?;
+    let now = Utc::now();
+    let expires_at = now + Duration::seconds(config.access_token_ttl_seconds as i64);
+
+    let claims = Claims {
+        sub: user_id,
+        role,
+        sid: session_id,
+        iat: now.timestamp(),
+        exp: expires_at.timestamp(),
+    };
+    let payload = serde_json::to_vec(&claims).map_err(|_| AuthError::Internal)?;
+
+    let signing_input = format!("{}.{}", HEADER, URL_SAFE_NO_PAD.encode(payload));
+    let mut mac = mac(secret);
+    mac.update(signing_input.as_bytes());
+    let signature = URL_SAFE_NO_PAD.encode(mac.finalize().into_bytes());
+
+    Ok((format!("{}.{}", signing_input, signature), expires_at))
+}
+
+/// Verify an access token's signature and expiry
+///
+/// # Errors
+/// Returns `InvalidToken` for malformed or tampered tokens and `TokenExpired`
+/// once `exp` has passed
+pub fn verify_access_token(secret: &str, token: &str) -> Result<Claims, AuthError> {
+    let mut parts = token.split('.');
+    let (Some(header), Some(payload), Some(signature), None) =
+        (parts.next(), parts.next(), parts.next(), parts.next())
+    else {
+        return Err(AuthError::InvalidToken);
+    };
+
+    // Only our own HS256 header is accepted (rejects `alg: none` and friends)
+    if header != HEADER {
+        return Err(AuthError::InvalidToken);
+    }
+
+    let signature = URL_SAFE_NO_PAD
+        .decode(signature)
+        .map_err(|_| AuthError::InvalidToken)?;
+    let mut mac = mac(secret);
+    mac.update(header.as_bytes());
+    mac.update(b".");
+    mac.update(payload.as_bytes());
+    mac.verify_slice(&signature)
+        .map_err(|_| AuthError::InvalidToken)?;
+
+    let payload = URL_SAFE_NO_PAD
+        .decode(payload)
+        .map_err(|_| AuthError::InvalidToken)?;
+    let claims: Claims = serde_json::from_slice(&payload).map_err(|_| AuthError::InvalidToken)?;
+
+    if claims.exp <= Utc::now().timestamp() {
+        return Err(AuthError::TokenExpired);
+    }
+
+    Ok(claims)
+}
+
+/// Whether a bearer token looks like a JWT rather than an opaque session token
+pub fn is_jwt(token: &str) -> bool {
+    token.matches('.').count() == 2
+}
+
+/// In-memory set of revoked session IDs, synced from `revoked_sessions`
+#[derive(Debug, Clone, Default)]
+pub struct SessionDenylist {
+    revoked: Arc<RwLock<HashSet<Uuid>>>,
+}
+
+impl SessionDenylist {
+    pub fn is_revoked(&self, session_id: &Uuid) -> bool {
+        self.revoked
+            .read()
+            .map(|set| set.contains(session_id))
+            .unwrap_or(true)
+    }
+
+    /// Replace the denylist with the current contents of `revoked_sessions`
+    pub async fn refresh(&self, db: &PgPool) -> Result<usize, sqlx::Error> {
+        let ids = sqlx::query_scalar!(
+            r#"
+            SELECT session_id
+            FROM revoked_sessions
+            WHERE expires_at > NOW()
+            "#
+        )
+        .fetch_all(db)
+        .await?;
+
+        let count = ids.len();
+        if let Ok(mut set) = self.revoked.write() {
+            *set = ids.into_iter().collect();
+        }
+
+        Ok(count)
+    }
+
+    #[cfg(test)]
+    fn insert(&self, session_id: Uuid) {
+        self.revoked.write().unwrap().insert(session_id);
+    }
+}
+
+/// Remove denylist entries whose tokens can no longer be valid
+pub async fn cleanup_revoked_sessions(db: &PgPool) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query!(
+        r#"
+        DELETE FROM revoked_sessions
+        WHERE expires_at < NOW()
+        "#
+    )
+    .execute(db)
+    .await?;
+
+    Ok(result.rows_affected())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(ttl: u64) -> JwtConfig {
+        JwtConfig {
+            enabled: true,
+            secret: Some("test-secret-with-at-least-32-bytes!!".to_string()),
+            access_token_ttl_seconds: ttl,
+        }
+    }
+
+    #[test]
+    fn test_issue_and_verify_roundtrip() {
+        let config = config(300);
+        let (user_id, session_id) = (Uuid::new_v4(), Uuid::new_v4());
+
+        let (token, _) = issue_access_token(&config, user_id, Role::Admin, session_id).unwrap();
+        let claims = verify_access_token(config.secret.as_deref().unwrap(), &token).unwrap();
+
+        assert!(is_jwt(&token));
+        assert_eq!(claims.sub, user_id);
+        assert_eq!(claims.sid, session_id);
+        assert_eq!(claims.role, Role::Admin);
+    }
+
+    #[test]
+    fn test_expired_token_rejected() {
+        let config = config(0);
+        let (token, _) =
+            issue_access_token(&config, Uuid::new_v4(), Role::User, Uuid::new_v4()).unwrap();
+
+        assert!(matches!(
+            verify_access_token(config.secret.as_deref().unwrap(), &token),
+            Err(AuthError::TokenExpired)
+        ));
+    }
+
+    #[test]
+    fn test_tampered_token_rejected() {
+        let config = config(300);
+        let secret = config.secret.as_deref().unwrap();
+        let (token, _) =
+            issue_access_token(&config, Uuid::new_v4(), Role::User, Uuid::new_v4()).unwrap();
+
+        // Escalate the role in the payload while keeping the signature
+        let parts: Vec<&str> = token.split('.').collect();
+        let payload = String::from_utf8(URL_SAFE_NO_PAD.decode(parts[1]).unwrap()).unwrap();
+        let forged = URL_SAFE_NO_PAD.encode(payload.replace("\"user\"", "\"admin\""));
+        let tampered = format!("{}.{}.{}", parts[0], forged, parts[2]);
+        assert!(matches!(
+            verify_access_token(secret, &tampered),
+            Err(AuthError::InvalidToken)
+        ));
+
+        // Wrong key
+        assert!(matches!(
+            verify_access_token("another-secret-another-secret-1234", &token),
+            Err(AuthError::InvalidToken)
+        ));
+
+        // Unsigned token
+        let unsigned = format!("eyJhbGciOiJub25lIn0.{}.", parts[1]);
+        assert!(matches!(
+            verify_access_token(secret, &unsigned),
+            Err(AuthError::InvalidToken)
+        ));
+    }
+
+    #[test]
+    fn test_revoked_session_detected() {
+        let denylist = SessionDenylist::default();
+        let (revoked, active) = (Uuid::new_v4(), Uuid::new_v4());
+
+        denylist.insert(revoked);
+
+        assert!(denylist.is_revoked(&revoked));
+        assert!(!denylist.is_revoked(&active));
+    }
+
+    #[test]
+    fn test_opaque_tokens_not_treated_as_jwt() {
+        assert!(!is_jwt("c2Vzc2lvbi10b2tlbg"));
+        assert!(is_jwt("a.b.c"));
+    }
+}
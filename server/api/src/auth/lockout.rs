@@ -0,0 +1,122 @@
+//! Account lockout after repeated failed sign-ins
+//!
+//! IP rate limits don't stop credential stuffing spread over many addresses,
+//! so failures are also counted per account. From `LOCKOUT_THRESHOLD`
+//! consecutive failures on, each failure locks the account for twice as long
+//! as the previous one, starting at `BASE_LOCKOUT` and capped at
+//! `MAX_LOCKOUT`. A successful sign-in or password reset clears the count.
+
+use chrono::{DateTime, Duration, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// Consecutive failures before the account is locked
+pub const LOCKOUT_THRESHOLD: i32 = 5;
+
+/// Lock after the `LOCKOUT_THRESHOLD`th failure
+pub const BASE_LOCKOUT: Duration = Duration::minutes(1);
+
+/// Longest a single lock lasts
+pub const MAX_LOCKOUT: Duration = Duration::hours(24);
+
+/// How long to lock the account after its `failed_attempts`th consecutive failure
+pub fn lock_duration(failed_attempts: i32) -> Option<Duration> {
+    if failed_attempts < LOCKOUT_THRESHOLD {
+        return None;
+    }
+
+    let doublings = (failed_attempts - LOCKOUT_THRESHOLD) as u32;
+    let duration = 2i32
+        .checked_pow(doublings)
+        .and_then(|factor| BASE_LOCKOUT.checked_mul(factor))
+        .unwrap_or(MAX_LOCKOUT);
+    Some(duration.min(MAX_LOCKOUT))
+}
+
+/// Seconds until the lock ends, or `None` if the account isn't locked
+pub fn remaining_lock(locked_until: Option<DateTime<Utc>>, now: DateTime<Utc>) -> Option<u64> {
+    locked_until
+        .filter(|until| *until > now)
+        .map(|until| (until - now).num_seconds().max(1) as u64)
+}
+
+/// Count a failed sign-in, returning when the account is now locked until
+pub async fn record_failure(
+    db: &PgPool,
+    user_id: Uuid,
+) -> Result<Option<DateTime<Utc>>, sqlx::Error> {
+    let failed_attempts = sqlx::query_scalar!(
+        r#"
+        UPDATE users
+        SET failed_login_attempts = failed_login_attempts + 1
+        WHERE id = $1
+        RETURNING failed_login_attempts
+        "#,
+        user_id
+    )
+    .fetch_one(db)
+    .await?;
+
+    let Some(duration) = lock_duration(failed_attempts) else {
+        return Ok(None);
+    };
+
+    let locked_until = Utc::now() + duration;
+    sqlx::query!(
+        "UPDATE users SET locked_until = $2 WHERE id = $1",
+        user_id,
+        locked_until
+    )
+    .execute(db)
+    .await?;
+
+    tracing::warn!(
+        %user_id,
+        failed_attempts,
+        %locked_until,
+        "Account locked after repeated failed sign-ins"
+    );
+    Ok(Some(locked_until))
+}
+
+/// Forget earlier failures after the user proved who they are
+pub async fn clear(db: &PgPool, user_id: Uuid) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE users
+        SET failed_login_attempts = 0, locked_until = NULL
+        WHERE id = $1 AND (failed_login_attempts > 0 OR locked_until IS NOT NULL)
+        "#,
+        user_id
+    )
+    .execute(db)
+    .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lock_grows_exponentially() {
+        assert_eq!(lock_duration(0), None);
+        assert_eq!(lock_duration(LOCKOUT_THRESHOLD - 1), None);
+        assert_eq!(lock_duration(LOCKOUT_THRESHOLD), Some(BASE_LOCKOUT));
+        assert_eq!(lock_duration(LOCKOUT_THRESHOLD + 1), Some(BASE_LOCKOUT * 2));
+        assert_eq!(lock_duration(LOCKOUT_THRESHOLD + 3), Some(BASE_LOCKOUT * 8));
+        assert_eq!(lock_duration(LOCKOUT_THRESHOLD + 20), Some(MAX_LOCKOUT));
+        assert_eq!(lock_duration(i32::MAX), Some(MAX_LOCKOUT));
+    }
+
+    #[test]
+    fn test_remaining_lock() {
+        let now = Utc::now();
+        assert_eq!(remaining_lock(None, now), None);
+        assert_eq!(remaining_lock(Some(now - Duration::seconds(5)), now), None);
+        assert_eq!(
+            remaining_lock(Some(now + Duration::seconds(90)), now),
+            Some(90)
+        );
+    }
+}
@@ -1,7 +1,12 @@
+pub mod audit;
 pub mod authorization;
 pub mod background;
+pub mod captcha;
 pub mod errors;
 pub mod handlers;
+pub mod jwt;
+pub mod lockout;
+pub mod new_device;
 pub mod oauth;
 pub mod password;
 pub mod role;
@@ -0,0 +1,96 @@
+//! New device sign-in alerts
+//!
+//! A sign-in is from a new device when neither an existing session nor an
+//! earlier successful sign-in came from the same IP address and user agent.
+//! The user's first sign-in is never reported, as there is nothing to compare
+//! it with. Alerts are security alerts, so users can turn them off.
+
+use chrono::Utc;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::auth::audit::{AuthEventType, ClientInfo};
+use crate::email::{EmailService, NewSignin};
+use crate::user::device;
+use crate::user::notifications::{self, NotificationCategory};
+
+/// Whether the user has signed in before, but never from this IP and user agent
+///
+/// Must run before the new session is created and the sign-in recorded.
+pub async fn is_new_device(
+    db: &PgPool,
+    user_id: Uuid,
+    client: &ClientInfo,
+) -> Result<bool, sqlx::Error> {
+    let history = sqlx::query!(
+        r#"
+        SELECT
+            EXISTS (SELECT 1 FROM sessions WHERE user_id = $1 AND impersonated_by IS NULL)
+            OR EXISTS (
+                SELECT 1 FROM auth_events WHERE user_id = $1 AND event_type = $4
+            ) as "signed_in_before!",
+            EXISTS (
+                SELECT 1 FROM sessions
+                WHERE user_id = $1
+                  AND impersonated_by IS NULL
+                  AND ip_address IS NOT DISTINCT FROM $2
+                  AND user_agent IS NOT DISTINCT FROM $3
+            )
+            OR EXISTS (
+                SELECT 1 FROM auth_events
+                WHERE user_id = $1
+                  AND event_type = $4
+                  AND ip_address IS NOT DISTINCT FROM $2
+                  AND user_agent IS NOT DISTINCT FROM $3
+            ) as "seen_device!"
+        "#,
+        user_id,
+        client.ip_address,
+        client.user_agent,
+        AuthEventType::SigninSuccess.as_str()
+    )
+    .fetch_one(db)
+    .await?;
+
+    Ok(history.signed_in_before && !history.seen_device)
+}
+
+/// Email the user if this sign-in comes from a new device
+///
+/// Failures are logged rather than returned; they must not block sign-in.
+pub async fn alert_if_new_device(
+    db: &PgPool,
+    email_service: &EmailService,
+    user_id: Uuid,
+    email: &str,
+    client: &ClientInfo,
+) {
+    match is_new_device(db, user_id, client).await {
+        Ok(true) => {}
+        Ok(false) => return,
+        Err(e) => {
+            tracing::error!(%user_id, "Failed to check sign-in history: {}", e);
+            return;
+        }
+    }
+
+    if !notifications::is_enabled(db, user_id, NotificationCategory::SecurityAlerts).await {
+        return;
+    }
+
+    let signin = NewSignin {
+        device: device::device_label(client.user_agent.as_deref()),
+        ip_address: client
+            .ip_address
+            .map(|ip| ip.ip().to_string())
+            .unwrap_or_else(|| "unknown".to_string()),
+        signed_in_at: Utc::now(),
+    };
+
+    if let Err(e) = email_service
+        .send_new_signin_email(user_id, email, &signin)
+        .await
+    {
+        tracing::error!(%user_id, "Failed to send new sign-in alert: {:?}", e);
+    }
+}
@@ -3,19 +3,33 @@
 use axum::{
     Json,
     extract::{Path, Query, State},
-    http::StatusCode,
+    http::{HeaderMap, StatusCode},
     response::{IntoResponse, Redirect},
 };
 use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
 
 use super::{Provider, service};
-use crate::auth::AuthError;
+use crate::auth::{AuthError, audit::ClientInfo};
+use crate::feature_flags::NEW_DEVICE_ALERTS;
 use crate::gateway::AppState;
+use crate::gateway::openapi::ErrorBody;
+use crate::middleware::ClientIp;
 
 // ===== OAuth Authorize =====
 
 /// GET /auth/oauth/{provider}/authorize
 /// Redirect to OAuth provider for authorization
+#[utoipa::path(
+    get,
+    path = "/auth/oauth/{provider}/authorize",
+    tag = "auth",
+    params(("provider" = String, Path, description = "`google` or `github`")),
+    responses(
+        (status = 307, description = "Redirect to the provider"),
+        (status = 400, description = "Unknown provider"),
+    )
+)]
 pub async fn oauth_authorize(
     State(app_state): State<AppState>,
     Path(provider_str): Path<String>,
@@ -30,7 +44,7 @@ pub async fn oauth_authorize(
 
 // ===== OAuth Callback =====
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, IntoParams)]
 pub struct OAuthCallbackQuery {
     pub code: String,
     /// OAuth state parameter for CSRF protection (reserved for future use)
@@ -38,7 +52,7 @@ pub struct OAuthCallbackQuery {
     pub state: Option<String>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct OAuthCallbackResponse {
     pub user_id: String,
     pub email: String,
@@ -50,18 +64,43 @@ pub struct OAuthCallbackResponse {
 
 /// GET /auth/oauth/{provider}/callback
 /// Handle OAuth provider callback
+#[utoipa::path(
+    get,
+    path = "/auth/oauth/{provider}/callback",
+    tag = "auth",
+    params(
+        ("provider" = String, Path, description = "`google` or `github`"),
+        OAuthCallbackQuery,
+    ),
+    responses(
+        (
+            status = 200,
+            description = "Signed in, creating or linking the account as needed",
+            body = OAuthCallbackResponse
+        ),
+        (status = 400, description = "Unknown provider", body = ErrorBody),
+    )
+)]
 pub async fn oauth_callback(
     State(app_state): State<AppState>,
     Path(provider_str): Path<String>,
     Query(params): Query<OAuthCallbackQuery>,
+    headers: HeaderMap,
+    client_ip: ClientIp,
 ) -> Result<Json<OAuthCallbackResponse>, AuthError> {
     let provider = Provider::from_str(&provider_str).ok_or(AuthError::Internal)?;
+    let client = ClientInfo::new(&headers, client_ip);
 
     let result = service::handle_callback(
         &app_state.db,
+        app_state.cache.as_ref(),
         provider,
         params.code,
         &app_state.config.oauth,
+        &client,
+        app_state.config.security.max_sessions_per_user,
+        &app_state.email,
+        crate::is_enabled!(app_state, NEW_DEVICE_ALERTS),
     )
     .await?;
 
@@ -2,6 +2,7 @@ pub mod github;
 pub mod google;
 pub mod handlers;
 pub mod service;
+pub mod token_refresh;
 
 pub use handlers::*;
 
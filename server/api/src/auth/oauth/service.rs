@@ -3,9 +3,12 @@ use oauth2::{AuthorizationCode, CsrfToken, PkceCodeChallenge, Scope, TokenRespon
 use sqlx::PgPool;
 use uuid::Uuid;
 
-use super::{Provider, build_oauth_client, github, google};
-use crate::auth::{AuthError, session};
+use super::{Provider, build_oauth_client, github, google, token_refresh};
+use crate::auth::audit::{self, AuthEventType, ClientInfo};
+use crate::auth::{AuthError, new_device, session};
+use crate::config::cache::RedisPool;
 use crate::config::env::OAuthConfig;
+use crate::email::EmailService;
 
 /// OAuth callback response
 pub struct OAuthCallbackResponse {
@@ -25,22 +28,35 @@ pub fn get_authorization_url(
 
     let (pkce_challenge, _pkce_verifier) = PkceCodeChallenge::new_random_sha256();
 
-    let (auth_url, _csrf_token) = client
+    let mut request = client
         .authorize_url(CsrfToken::new_random)
         .add_scope(Scope::new("email".to_string()))
         .add_scope(Scope::new("profile".to_string()))
-        .set_pkce_challenge(pkce_challenge)
-        .url();
+        .set_pkce_challenge(pkce_challenge);
+    // Google only issues a refresh token for offline access, and only on
+    // the first consent unless it is asked for again
+    if let Provider::Google = provider {
+        request = request
+            .add_extra_param("access_type", "offline")
+            .add_extra_param("prompt", "consent");
+    }
+    let (auth_url, _csrf_token) = request.url();
 
     Ok(auth_url.to_string())
 }
 
 /// Handle OAuth callback and create/link account
+#[allow(clippy::too_many_arguments)]
 pub async fn handle_callback(
     db: &PgPool,
+    cache: Option<&RedisPool>,
     provider: Provider,
     code: String,
     config: &OAuthConfig,
+    client_info: &ClientInfo,
+    max_sessions: u32,
+    email_service: &EmailService,
+    alert_new_devices: bool,
 ) -> Result<OAuthCallbackResponse, AuthError> {
     let client = build_oauth_client(provider, config).map_err(|_| AuthError::Internal)?;
 
@@ -52,6 +68,8 @@ pub async fn handle_callback(
         .map_err(|_| AuthError::Internal)?;
 
     let access_token = token_result.access_token().secret();
+    let refresh_token = token_result.refresh_token().map(|t| t.secret());
+    let token_expires_at = token_refresh::token_expires_at(&token_result, Utc::now());
 
     // Fetch user info based on provider
     let (provider_account_id, email, name, avatar_url, email_verified) = match provider {
@@ -96,7 +114,7 @@ pub async fn handle_callback(
     // Check if account already exists
     let existing_account = sqlx::query!(
         r#"
-        SELECT user_id FROM accounts
+        SELECT id, user_id FROM accounts
         WHERE provider = $1 AND provider_account_id = $2
         "#,
         provider.as_str(),
@@ -106,7 +124,24 @@ pub async fn handle_callback(
     .await?;
 
     let (user_id, is_new_user) = if let Some(account) = existing_account {
-        // Existing OAuth account - just sign in
+        // Existing OAuth account - sign in and keep the latest tokens
+        sqlx::query!(
+            r#"
+            UPDATE accounts
+            SET access_token = $2,
+                refresh_token = COALESCE($3, refresh_token),
+                expires_at = $4,
+                updated_at = NOW()
+            WHERE id = $1
+            "#,
+            account.id,
+            access_token,
+            refresh_token,
+            token_expires_at
+        )
+        .execute(db)
+        .await?;
+
         (account.user_id, false)
     } else {
         // Check if user with this email exists
@@ -144,36 +179,72 @@ pub async fn handle_callback(
         // Create OAuth account link
         sqlx::query!(
             r#"
-            INSERT INTO accounts (user_id, provider, provider_account_id, access_token)
-            VALUES ($1, $2, $3, $4)
+            INSERT INTO accounts (
+                user_id, provider, provider_account_id, access_token, refresh_token, expires_at
+            )
+            VALUES ($1, $2, $3, $4, $5, $6)
             "#,
             user_id,
             provider.as_str(),
             provider_account_id,
-            access_token
+            access_token,
+            refresh_token,
+            token_expires_at
         )
         .execute(db)
         .await?;
 
+        audit::record(
+            db,
+            Some(user_id),
+            AuthEventType::OauthLink,
+            client_info,
+            serde_json::json!({ "provider": provider.as_str(), "new_user": is_new }),
+        )
+        .await;
+
         (user_id, is_new)
     };
 
     // Fetch user role for session creation
-    let user_role = sqlx::query!(
+    let user = sqlx::query!(
         r#"
-        SELECT role as "role: crate::auth::Role"
+        SELECT role as "role: crate::auth::Role", email
         FROM users
         WHERE id = $1
         "#,
         user_id
     )
     .fetch_one(db)
-    .await?
-    .role;
+    .await?;
+    let user_role = user.role;
+
+    // Alerts go to the account's address, which may differ from the provider's
+    if alert_new_devices && !is_new_user {
+        new_device::alert_if_new_device(db, email_service, user_id, &user.email, client_info)
+            .await;
+    }
 
     // Create session with user's role
-    let (session_token, expires_at) =
-        session::create_session(db, user_id, user_role, None, None).await?;
+    let (session_id, session_token, expires_at) = session::create_session(
+        db,
+        cache,
+        user_id,
+        user_role,
+        client_info.ip_address,
+        client_info.user_agent.clone(),
+        max_sessions,
+    )
+    .await?;
+
+    audit::record(
+        db,
+        Some(user_id),
+        AuthEventType::SigninSuccess,
+        client_info,
+        serde_json::json!({ "session_id": session_id, "provider": provider.as_str() }),
+    )
+    .await;
 
     Ok(OAuthCallbackResponse {
         user_id,
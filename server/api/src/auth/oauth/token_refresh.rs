@@ -0,0 +1,180 @@
+//! Keeping stored provider access tokens usable
+//!
+//! Provider tokens saved at sign-in are kept for later provider API calls.
+//! Tokens that expire (Google's do, GitHub OAuth app tokens don't) are
+//! renewed with the stored refresh token shortly before they run out, either
+//! on demand through `ensure_fresh_token` or ahead of time by the optional
+//! background task.
+
+use chrono::{DateTime, Duration, Utc};
+use oauth2::basic::{BasicErrorResponseType, BasicTokenResponse};
+use oauth2::{RefreshToken, RequestTokenError, TokenResponse};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use super::{Provider, build_oauth_client};
+use crate::auth::AuthError;
+use crate::config::env::OAuthConfig;
+
+/// Tokens this close to expiry are refreshed before use
+pub const REFRESH_MARGIN: Duration = Duration::minutes(5);
+
+/// The background task refreshes tokens expiring within this window
+pub const PRE_REFRESH_WINDOW: Duration = Duration::hours(1);
+
+/// Accounts refreshed per background run
+const PRE_REFRESH_BATCH_SIZE: i64 = 100;
+
+/// A linked provider account and its stored tokens
+#[derive(Debug, Clone)]
+pub struct OAuthAccount {
+    pub id: Uuid,
+    pub provider: String,
+    pub access_token: Option<String>,
+    pub refresh_token: Option<String>,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+/// When a freshly issued token expires, if the provider said
+pub fn token_expires_at(token: &BasicTokenResponse, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    token
+        .expires_in()
+        .and_then(|expires_in| Duration::from_std(expires_in).ok())
+        .map(|expires_in| now + expires_in)
+}
+
+/// Whether a token expiring at `expires_at` should be refreshed before use
+fn needs_refresh(expires_at: Option<DateTime<Utc>>, now: DateTime<Utc>) -> bool {
+    expires_at.is_some_and(|expires_at| expires_at < now + REFRESH_MARGIN)
+}
+
+/// The account's access token, refreshed first if it is about to expire
+///
+/// Fails with `TokenExpired` when the token has run out and can't be
+/// refreshed, in which case the user has to sign in with the provider again.
+#[allow(dead_code)] // For calls to provider APIs on the user's behalf
+pub async fn ensure_fresh_token(
+    db: &PgPool,
+    config: &OAuthConfig,
+    account: &OAuthAccount,
+) -> Result<String, AuthError> {
+    let access_token = account.access_token.clone().ok_or(AuthError::InvalidToken)?;
+    if !needs_refresh(account.expires_at, Utc::now()) {
+        return Ok(access_token);
+    }
+
+    refresh(db, config, account).await
+}
+
+/// Exchange the refresh token for a new access token and store both
+async fn refresh(
+    db: &PgPool,
+    config: &OAuthConfig,
+    account: &OAuthAccount,
+) -> Result<String, AuthError> {
+    let refresh_token = account.refresh_token.clone().ok_or(AuthError::TokenExpired)?;
+    let provider = Provider::from_str(&account.provider).ok_or(AuthError::Internal)?;
+    let client = build_oauth_client(provider, config).map_err(|_| AuthError::Internal)?;
+
+    let result = client
+        .exchange_refresh_token(&RefreshToken::new(refresh_token))
+        .request_async(oauth2::reqwest::async_http_client)
+        .await;
+    let token = match result {
+        Ok(token) => token,
+        Err(e) => {
+            tracing::warn!(
+                account_id = %account.id,
+                provider = %account.provider,
+                "OAuth token refresh failed: {}",
+                e
+            );
+            // A revoked or expired refresh token never works again; drop it
+            // so the account stops being picked up for refreshing
+            if let RequestTokenError::ServerResponse(response) = &e
+                && *response.error() == BasicErrorResponseType::InvalidGrant
+            {
+                forget_refresh_token(db, account.id).await?;
+            }
+            return Err(AuthError::TokenExpired);
+        }
+    };
+
+    let access_token = token.access_token().secret().clone();
+    // Providers may rotate the refresh token; keep the old one if they don't
+    sqlx::query!(
+        r#"
+        UPDATE accounts
+        SET access_token = $2,
+            refresh_token = COALESCE($3, refresh_token),
+            expires_at = $4,
+            updated_at = NOW()
+        WHERE id = $1
+        "#,
+        account.id,
+        access_token,
+        token.refresh_token().map(|t| t.secret().clone()),
+        token_expires_at(&token, Utc::now())
+    )
+    .execute(db)
+    .await?;
+
+    Ok(access_token)
+}
+
+/// Clear a refresh token the provider no longer accepts
+async fn forget_refresh_token(db: &PgPool, account_id: Uuid) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        "UPDATE accounts SET refresh_token = NULL, updated_at = NOW() WHERE id = $1",
+        account_id
+    )
+    .execute(db)
+    .await?;
+    Ok(())
+}
+
+/// Refresh tokens expiring within `PRE_REFRESH_WINDOW`, returning how many were renewed
+pub async fn refresh_expiring_tokens(db: &PgPool, config: &OAuthConfig) -> Result<u64, sqlx::Error> {
+    let accounts = sqlx::query_as!(
+        OAuthAccount,
+        r#"
+        SELECT id, provider, access_token, refresh_token, expires_at
+        FROM accounts
+        WHERE refresh_token IS NOT NULL
+          AND expires_at < NOW() + make_interval(secs => $1)
+        ORDER BY expires_at
+        LIMIT $2
+        "#,
+        PRE_REFRESH_WINDOW.num_seconds() as f64,
+        PRE_REFRESH_BATCH_SIZE
+    )
+    .fetch_all(db)
+    .await?;
+
+    let mut refreshed = 0;
+    for account in &accounts {
+        match refresh(db, config, account).await {
+            Ok(_) => refreshed += 1,
+            // A database failure would fail every other account too
+            Err(AuthError::Database(e)) => return Err(e),
+            // Already logged; retried on the next run unless the refresh
+            // token was rejected outright
+            Err(_) => {}
+        }
+    }
+    Ok(refreshed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_refresh_margin() {
+        let now = Utc::now();
+        assert!(!needs_refresh(None, now));
+        assert!(!needs_refresh(Some(now + Duration::minutes(30)), now));
+        assert!(needs_refresh(Some(now + Duration::minutes(4)), now));
+        assert!(needs_refresh(Some(now - Duration::minutes(1)), now));
+    }
+}
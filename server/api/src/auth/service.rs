@@ -1,28 +1,51 @@
-use chrono::{Duration, Utc};
+use chrono::Utc;
+use serde_json::json;
 use sqlx::PgPool;
 
 use super::{
-    AuthError, ForgotPasswordRequest, ForgotPasswordResponse, RecoverAccountRequest,
+    AuthError, BulkResendVerificationRequest, BulkResendVerificationResponse, ConfirmEmailChangeQuery, ConfirmEmailChangeResponse, ForgotPasswordRequest, ForgotPasswordResponse, RecoverAccountRequest,
     RecoverAccountResponse, RefreshRequest, RefreshResponse, ResendVerificationRequest,
     ResendVerificationResponse, ResetPasswordRequest, ResetPasswordResponse, SignInRequest,
     SignInResponse, SignUpRequest, SignUpResponse, VerifyEmailRequest, VerifyEmailResponse,
-    password, session, tokens,
+    audit::{self, AuthEventType, ClientInfo},
+    jwt, lockout, new_device, password, session, tokens, Role,
 };
+use super::tokens::ResendDecision;
 use sqlx::types::ipnetwork::IpNetwork;
+use crate::config::cache::RedisPool;
+use crate::config::env::JwtConfig;
 use crate::email::EmailService;
+use crate::user::notifications::{self, NotificationCategory};
+use crate::user::purge;
 
 // ===== Email/Password Authentication =====
 
+/// A signup that races another for the same email or username passes the
+/// existence checks and fails on the unique constraint instead
+fn signup_conflict(e: sqlx::Error) -> AuthError {
+    match e.as_database_error() {
+        Some(db_error) if db_error.is_unique_violation() => {
+            if db_error.constraint() == Some("users_email_key") {
+                AuthError::EmailAlreadyExists
+            } else {
+                AuthError::UsernameAlreadyTaken
+            }
+        }
+        _ => AuthError::from(e),
+    }
+}
+
 /// Sign up a new user with email and password
 /// - Validates email format and password strength
 /// - Hashes password
 /// - Creates user in database
 /// - Generates verification token
 /// - Sends verification email (stubbed for now)
+/// - Sends a welcome email once the verification email is queued
 pub async fn signup(
     db: &PgPool,
     req: SignUpRequest,
-    email_config: &crate::config::env::EmailConfig,
+    email_service: &EmailService,
 ) -> Result<SignUpResponse, AuthError> {
     // Validate password strength
     password::validate_password_strength(&req.password)?;
@@ -39,6 +62,19 @@ pub async fn signup(
         return Err(AuthError::EmailAlreadyExists);
     }
 
+    if let Some(username) = &req.username {
+        let taken = sqlx::query_scalar!(
+            r#"SELECT EXISTS(SELECT 1 FROM users WHERE LOWER(username) = LOWER($1)) AS "taken!""#,
+            username
+        )
+        .fetch_one(db)
+        .await?;
+
+        if taken {
+            return Err(AuthError::UsernameAlreadyTaken);
+        }
+    }
+
     // Create user
     let user = sqlx::query!(
         r#"
@@ -52,34 +88,48 @@ pub async fn signup(
         req.username
     )
     .fetch_one(db)
-    .await?;
+    .await
+    .map_err(signup_conflict)?;
 
     // Generate verification token and OTP
     let verification_token = tokens::generate_token();
     let otp = tokens::generate_otp();
-    let expires_at = Utc::now() + Duration::hours(24);
+    let expires_at = Utc::now() + tokens::VERIFICATION_TOKEN_LIFETIME;
 
     sqlx::query!(
         r#"
-        INSERT INTO verification_tokens (user_id, token, otp, expires_at)
+        INSERT INTO verification_tokens (user_id, token_hash, otp, expires_at)
         VALUES ($1, $2, $3, $4)
         "#,
         user.id,
-        verification_token,
+        tokens::hash_token(&verification_token),
         otp,
         expires_at
     )
     .execute(db)
     .await?;
 
-    // Send verification email
-    let email_service = EmailService::new(email_config.clone());
-    if let Err(e) = email_service
+    // Send verification email, then the welcome email only once it is queued
+    // so nobody is welcomed without a way to verify
+    let verification_queued = match email_service
         .send_verification_email(&req.email, &verification_token, &otp)
         .await
     {
-        tracing::error!("Failed to send verification email: {:?}", e);
-        // Don't fail signup if email fails, just log it
+        Ok(()) => true,
+        Err(e) => {
+            tracing::error!("Failed to send verification email: {:?}", e);
+            // Don't fail signup if email fails, just log it
+            false
+        }
+    };
+
+    if verification_queued
+        && notifications::is_enabled(db, user.id, NotificationCategory::ProductUpdates).await
+        && let Err(e) = email_service
+            .send_welcome_email(user.id, &req.email, req.name.as_deref())
+            .await
+    {
+        tracing::error!("Failed to send welcome email: {:?}", e);
     }
 
     Ok(SignUpResponse {
@@ -94,89 +144,279 @@ pub async fn signup(
 /// - Checks if email is verified
 /// - Creates session with role
 /// - Returns session token
+#[allow(clippy::too_many_arguments)]
 pub async fn signin(
     db: &PgPool,
+    cache: Option<&RedisPool>,
     req: SignInRequest,
     ip_address: Option<IpNetwork>,
     user_agent: Option<String>,
+    jwt_config: &JwtConfig,
+    max_sessions: u32,
+    email_service: &EmailService,
+    alert_new_devices: bool,
 ) -> Result<SignInResponse, AuthError> {
+    let client = ClientInfo {
+        ip_address,
+        user_agent: user_agent.clone(),
+    };
+
     // Find user by email
     let user = sqlx::query!(
         r#"
-        SELECT id, email, password_hash, email_verified, role as "role: crate::auth::Role"
+        SELECT id, email, password_hash, email_verified, role as "role: crate::auth::Role",
+               locked_until
         FROM users
         WHERE email = $1 AND deleted_at IS NULL
         "#,
         req.email
     )
     .fetch_optional(db)
-    .await?
-    .ok_or(AuthError::InvalidCredentials)?;
+    .await?;
+
+    let Some(user) = user else {
+        let metadata = json!({
+            "email_hash": audit::email_hash(&req.email),
+            "reason": "unknown_email"
+        });
+        audit::record(db, None, AuthEventType::SigninFailure, &client, metadata).await;
+        return Err(AuthError::InvalidCredentials);
+    };
+
+    // Locked accounts are refused before the password is even checked
+    if let Some(retry_after) = lockout::remaining_lock(user.locked_until, Utc::now()) {
+        let metadata = json!({ "reason": "account_locked" });
+        audit::record(db, Some(user.id), AuthEventType::SigninFailure, &client, metadata).await;
+        return Err(AuthError::AccountLocked(retry_after));
+    }
 
     // Verify password
-    let password_hash = user.password_hash.ok_or(AuthError::InvalidCredentials)?;
-    let is_valid = password::verify_password(&req.password, &password_hash)?;
+    let is_valid = match &user.password_hash {
+        Some(password_hash) => password::verify_password(&req.password, password_hash)?,
+        None => false,
+    };
 
     if !is_valid {
+        let metadata = json!({ "reason": "invalid_password" });
+        audit::record(db, Some(user.id), AuthEventType::SigninFailure, &client, metadata).await;
+
+        let locked_until = lockout::record_failure(db, user.id).await?;
+        if let Some(retry_after) = lockout::remaining_lock(locked_until, Utc::now()) {
+            return Err(AuthError::AccountLocked(retry_after));
+        }
         return Err(AuthError::InvalidCredentials);
     }
 
+    lockout::clear(db, user.id).await?;
+
     // Check if email is verified
     if !user.email_verified {
+        let metadata = json!({ "reason": "email_not_verified" });
+        audit::record(db, Some(user.id), AuthEventType::SigninFailure, &client, metadata).await;
         return Err(AuthError::EmailNotVerified);
     }
 
+    // Compared against earlier sign-ins, so before this one is recorded
+    if alert_new_devices {
+        new_device::alert_if_new_device(db, email_service, user.id, &user.email, &client).await;
+    }
+
     // Create session with user's role
-    let (session_token, expires_at) =
-        session::create_session(db, user.id, user.role, ip_address, user_agent).await?;
+    let (session_id, session_token, expires_at) = session::create_session(
+        db,
+        cache,
+        user.id,
+        user.role,
+        ip_address,
+        user_agent,
+        max_sessions,
+    )
+    .await?;
+
+    let metadata = json!({ "session_id": session_id });
+    audit::record(db, Some(user.id), AuthEventType::SigninSuccess, &client, metadata).await;
+
+    let (access_token, access_token_expires_at) =
+        issue_access_token(jwt_config, user.id, user.role, session_id)?;
 
     Ok(SignInResponse {
         user_id: user.id,
         email: user.email,
         session_token,
         expires_at,
+        access_token,
+        access_token_expires_at,
     })
 }
 
+/// Issue a JWT access token for a session when JWT mode is enabled
+fn issue_access_token(
+    jwt_config: &JwtConfig,
+    user_id: uuid::Uuid,
+    role: Role,
+    session_id: uuid::Uuid,
+) -> Result<(Option<String>, Option<chrono::DateTime<Utc>>), AuthError> {
+    if !jwt_config.enabled {
+        return Ok((None, None));
+    }
+
+    let (token, expires_at) = jwt::issue_access_token(jwt_config, user_id, role, session_id)?;
+    Ok((Some(token), Some(expires_at)))
+}
+
 /// Sign out a user by invalidating their session
-pub async fn signout(db: &PgPool, session_token: &str) -> Result<(), AuthError> {
-    session::invalidate_session(db, session_token).await
+pub async fn signout(
+    db: &PgPool,
+    cache: Option<&RedisPool>,
+    session_token: &str,
+    client: &ClientInfo,
+) -> Result<(), AuthError> {
+    if let Some(user_id) = session::invalidate_session(db, cache, session_token).await? {
+        audit::record(db, Some(user_id), AuthEventType::Signout, client, json!({})).await;
+    }
+
+    Ok(())
 }
 
 /// Refresh a session token (extend expiration)
 pub async fn refresh_session(
     db: &PgPool,
+    cache: Option<&RedisPool>,
     req: RefreshRequest,
     ip_address: Option<IpNetwork>,
     user_agent: Option<String>,
+    jwt_config: &JwtConfig,
+    max_sessions: u32,
 ) -> Result<RefreshResponse, AuthError> {
     // Validate current session and get user_id and role
-    let (user_id, role) = session::get_user_from_session(db, &req.session_token).await?;
+    let (user_id, role, impersonated_by) =
+        session::get_user_from_session(db, cache, &req.session_token).await?;
+
+    // Impersonation is limited to the one-hour session it started with
+    if impersonated_by.is_some() {
+        return Err(AuthError::Unauthorized);
+    }
+
+    // A refresh isn't a sign-in, so the new session keeps the old sign-in time
+    let authenticated_at = session::authenticated_at(
+        db,
+        &session::CurrentSession::Token(req.session_token.clone()),
+    )
+    .await?;
 
     // Invalidate old session
-    session::invalidate_session(db, &req.session_token).await?;
+    session::invalidate_session(db, cache, &req.session_token).await?;
 
     // Create new session with same role
-    let (new_token, expires_at) =
-        session::create_session(db, user_id, role, ip_address, user_agent).await?;
+    let (session_id, new_token, expires_at) = session::create_session(
+        db,
+        cache,
+        user_id,
+        role,
+        ip_address,
+        user_agent,
+        max_sessions,
+    )
+    .await?;
+
+    if let Some(authenticated_at) = authenticated_at {
+        session::set_authenticated_at(db, session_id, authenticated_at).await?;
+    }
+
+    let (access_token, access_token_expires_at) =
+        issue_access_token(jwt_config, user_id, role, session_id)?;
 
     Ok(RefreshResponse {
         session_token: new_token,
         expires_at,
+        access_token,
+        access_token_expires_at,
     })
 }
 
 // ===== Email Verification =====
 
+/// Wrong verification codes allowed before the code locks
+const MAX_OTP_ATTEMPTS: i32 = 5;
+
 struct VerificationTokenRow {
     user_id: uuid::Uuid,
     expires_at: chrono::DateTime<Utc>,
 }
 
+/// Check an emailed verification code against the user's latest token
+///
+/// Each guess uses up one of `MAX_OTP_ATTEMPTS`; once they are gone the code
+/// is locked and the user has to request a new verification email.
+async fn check_verification_otp(
+    db: &PgPool,
+    email: &str,
+    otp: &str,
+) -> Result<VerificationTokenRow, AuthError> {
+    // Count the attempt before comparing so concurrent guesses cannot exceed the limit
+    let record = sqlx::query!(
+        r#"
+        UPDATE verification_tokens
+        SET otp_attempts = otp_attempts + 1
+        WHERE id = (
+            SELECT vt.id
+            FROM verification_tokens vt
+            JOIN users u ON u.id = vt.user_id
+            WHERE u.email = $1
+            ORDER BY vt.created_at DESC
+            LIMIT 1
+        )
+        AND otp_attempts < $2
+        RETURNING user_id, otp, expires_at
+        "#,
+        email,
+        MAX_OTP_ATTEMPTS
+    )
+    .fetch_optional(db)
+    .await?;
+
+    let Some(record) = record else {
+        // Either no pending verification or the code is already locked
+        let locked = sqlx::query_scalar!(
+            r#"
+            SELECT EXISTS (
+                SELECT 1
+                FROM verification_tokens vt
+                JOIN users u ON u.id = vt.user_id
+                WHERE u.email = $1 AND vt.otp_attempts >= $2
+            ) as "locked!"
+            "#,
+            email,
+            MAX_OTP_ATTEMPTS
+        )
+        .fetch_one(db)
+        .await?;
+
+        return Err(if locked {
+            AuthError::OtpLocked
+        } else {
+            AuthError::InvalidToken
+        });
+    };
+
+    if !tokens::constant_time_eq(otp, &record.otp) {
+        return Err(AuthError::InvalidToken);
+    }
+
+    Ok(VerificationTokenRow {
+        user_id: record.user_id,
+        expires_at: record.expires_at,
+    })
+}
+
 /// Verify email address with token or OTP
+///
+/// Unknown, wrong and expired tokens all fail with the same `InvalidToken`
+/// error so responses don't reveal which tokens exist.
 pub async fn verify_email(
     db: &PgPool,
     req: VerifyEmailRequest,
+    client: &ClientInfo,
 ) -> Result<VerifyEmailResponse, AuthError> {
     // Find verification token record
     let token_record = if let Some(token) = req.token {
@@ -184,9 +424,9 @@ pub async fn verify_email(
             r#"
             SELECT user_id, expires_at
             FROM verification_tokens
-            WHERE token = $1
+            WHERE token_hash = $1
             "#,
-            token
+            tokens::hash_token(&token)
         )
         .fetch_optional(db)
         .await?
@@ -195,42 +435,16 @@ pub async fn verify_email(
             expires_at: r.expires_at,
         })
     } else if let (Some(email), Some(otp)) = (req.email, req.otp) {
-        // Find user first
-        let user = sqlx::query!("SELECT id FROM users WHERE email = $1", email)
-            .fetch_optional(db)
-            .await?;
-
-        if let Some(user) = user {
-            sqlx::query!(
-                r#"
-                SELECT user_id, expires_at
-                FROM verification_tokens
-                WHERE user_id = $1 AND otp = $2
-                "#,
-                user.id,
-                otp
-            )
-            .fetch_optional(db)
-            .await?
-            .map(|r| VerificationTokenRow {
-                user_id: r.user_id,
-                expires_at: r.expires_at,
-            })
-        } else {
-            None
-        }
+        Some(check_verification_otp(db, &email, &otp).await?)
     } else {
         return Err(AuthError::Validation(
             "Missing verification token or code".to_string(),
         ));
     };
 
-    let token_record = token_record.ok_or(AuthError::InvalidToken)?;
-
-    // Check if expired
-    if token_record.expires_at < Utc::now() {
-        return Err(AuthError::TokenExpired);
-    }
+    let token_record = token_record
+        .filter(|r| r.expires_at >= Utc::now())
+        .ok_or(AuthError::InvalidToken)?;
 
     // Mark email as verified
     sqlx::query!(
@@ -252,20 +466,110 @@ pub async fn verify_email(
     .execute(db)
     .await?;
 
+    audit::record(
+        db,
+        Some(token_record.user_id),
+        AuthEventType::EmailVerified,
+        client,
+        json!({}),
+    )
+    .await;
+
     Ok(VerifyEmailResponse {
         message: "Email verified successfully!".to_string(),
         email_verified: true,
     })
 }
 
+// ===== Email Change Confirmation =====
+
+/// Confirm a pending email change
+/// - Swaps users.email to the new address only once the link is used
+/// - Marks the new address as verified
+pub async fn confirm_email_change(
+    db: &PgPool,
+    req: ConfirmEmailChangeQuery,
+) -> Result<ConfirmEmailChangeResponse, AuthError> {
+    let token_record = sqlx::query!(
+        r#"
+        SELECT user_id, new_email, expires_at
+        FROM email_change_tokens
+        WHERE token = $1
+        "#,
+        req.token
+    )
+    .fetch_optional(db)
+    .await?
+    .ok_or(AuthError::InvalidToken)?;
+
+    // Check if expired
+    if token_record.expires_at < Utc::now() {
+        return Err(AuthError::TokenExpired);
+    }
+
+    // The address may have been claimed since the change was requested
+    let existing_user = sqlx::query!(
+        "SELECT id FROM users WHERE LOWER(email) = LOWER($1) AND id != $2",
+        token_record.new_email,
+        token_record.user_id
+    )
+    .fetch_optional(db)
+    .await?;
+
+    if existing_user.is_some() {
+        return Err(AuthError::EmailAlreadyExists);
+    }
+
+    // Swap email and mark it verified; a signup can still take the address
+    // between the check above and here
+    sqlx::query!(
+        r#"
+        UPDATE users
+        SET email = $1, email_verified = TRUE
+        WHERE id = $2
+        "#,
+        token_record.new_email,
+        token_record.user_id
+    )
+    .execute(db)
+    .await
+    .map_err(|e| match e.as_database_error() {
+        Some(db_error) if db_error.is_unique_violation() => AuthError::EmailAlreadyExists,
+        _ => AuthError::from(e),
+    })?;
+
+    // Delete pending email change tokens for this user
+    sqlx::query!(
+        "DELETE FROM email_change_tokens WHERE user_id = $1",
+        token_record.user_id
+    )
+    .execute(db)
+    .await?;
+
+    Ok(ConfirmEmailChangeResponse {
+        message: "Email address updated successfully!".to_string(),
+        email: token_record.new_email,
+    })
+}
+
 // ===== Password Reset =====
 
 /// Send password reset email
+///
+/// A reset link sent within the last hour is sent again instead of being
+/// replaced, and only one email per `RESEND_COOLDOWN` is allowed. Requests
+/// during the cooldown send nothing but get the same response as any other,
+/// so it doesn't reveal which emails have accounts.
 pub async fn forgot_password(
     db: &PgPool,
     req: ForgotPasswordRequest,
-    email_config: &crate::config::env::EmailConfig,
+    email_service: &EmailService,
 ) -> Result<ForgotPasswordResponse, AuthError> {
+    let response = ForgotPasswordResponse {
+        message: "If an account exists with that email, a password reset link has been sent."
+            .to_string(),
+    };
+
     // Find user by email
     let user = sqlx::query!(
         "SELECT id FROM users WHERE email = $1 AND deleted_at IS NULL",
@@ -276,33 +580,78 @@ pub async fn forgot_password(
 
     // Always return success (don't reveal if email exists)
     if let Some(user) = user {
-        // Generate reset token
-        let reset_token = tokens::generate_token();
-        let expires_at = Utc::now() + Duration::hours(1); // 1 hour expiry
-
-        // Delete any existing reset tokens for this user
-        sqlx::query!(
-            "DELETE FROM password_reset_tokens WHERE user_id = $1",
-            user.id
-        )
-        .execute(db)
-        .await?;
-
-        // Create new reset token
-        sqlx::query!(
+        let latest = sqlx::query!(
             r#"
-            INSERT INTO password_reset_tokens (user_id, token, expires_at)
-            VALUES ($1, $2, $3)
+            SELECT id, token, expires_at, created_at, last_sent_at
+            FROM password_reset_tokens
+            WHERE user_id = $1
+            ORDER BY created_at DESC
+            LIMIT 1
             "#,
-            user.id,
-            reset_token,
-            expires_at
+            user.id
         )
-        .execute(db)
+        .fetch_optional(db)
         .await?;
 
+        // Re-send a recent link rather than invalidating the one already sent
+        let reusable = match latest {
+            Some(t) => match tokens::resend_decision(
+                t.created_at,
+                t.last_sent_at,
+                t.expires_at,
+                Utc::now(),
+            ) {
+                ResendDecision::Cooldown(_) => {
+                    tracing::debug!(user_id = %user.id, "Password reset email in cooldown");
+                    return Ok(response);
+                }
+                ResendDecision::Reuse => Some((t.id, t.token)),
+                ResendDecision::Reissue => None,
+            },
+            None => None,
+        };
+
+        let reset_token = match reusable {
+            Some((id, token)) => {
+                sqlx::query!(
+                    "UPDATE password_reset_tokens SET last_sent_at = NOW() WHERE id = $1",
+                    id
+                )
+                .execute(db)
+                .await?;
+                token
+            }
+            None => {
+                // Generate reset token
+                let reset_token = tokens::generate_token();
+                let expires_at = Utc::now() + tokens::PASSWORD_RESET_TOKEN_LIFETIME;
+
+                // Delete any existing reset tokens for this user
+                sqlx::query!(
+                    "DELETE FROM password_reset_tokens WHERE user_id = $1",
+                    user.id
+                )
+                .execute(db)
+                .await?;
+
+                // Create new reset token
+                sqlx::query!(
+                    r#"
+                    INSERT INTO password_reset_tokens (user_id, token, expires_at)
+                    VALUES ($1, $2, $3)
+                    "#,
+                    user.id,
+                    reset_token,
+                    expires_at
+                )
+                .execute(db)
+                .await?;
+
+                reset_token
+            }
+        };
+
         // Send reset email
-        let email_service = EmailService::new(email_config.clone());
         if let Err(e) = email_service
             .send_password_reset_email(&req.email, &reset_token)
             .await
@@ -312,16 +661,15 @@ pub async fn forgot_password(
         }
     }
 
-    Ok(ForgotPasswordResponse {
-        message: "If an account exists with that email, a password reset link has been sent."
-            .to_string(),
-    })
+    Ok(response)
 }
 
 /// Reset password with token
 pub async fn reset_password(
     db: &PgPool,
+    cache: Option<&RedisPool>,
     req: ResetPasswordRequest,
+    client: &ClientInfo,
 ) -> Result<ResetPasswordResponse, AuthError> {
     // Validate password strength
     password::validate_password_strength(&req.new_password)?;
@@ -360,6 +708,9 @@ pub async fn reset_password(
     .execute(db)
     .await?;
 
+    // Whoever was guessing the old password no longer matters
+    lockout::clear(db, token_record.user_id).await?;
+
     // Delete reset token
     sqlx::query!(
         "DELETE FROM password_reset_tokens WHERE token = $1",
@@ -369,7 +720,16 @@ pub async fn reset_password(
     .await?;
 
     // Invalidate all sessions for security
-    session::invalidate_all_user_sessions(db, token_record.user_id).await?;
+    session::invalidate_all_user_sessions(db, cache, token_record.user_id).await?;
+
+    audit::record(
+        db,
+        Some(token_record.user_id),
+        AuthEventType::PasswordReset,
+        client,
+        json!({}),
+    )
+    .await;
 
     Ok(ResetPasswordResponse {
         message: "Password reset successfully. Please sign in with your new password.".to_string(),
@@ -379,10 +739,13 @@ pub async fn reset_password(
 // ===== Resend Verification Email =====
 
 /// Resend verification email to user
+///
+/// Same cooldown and reuse rules as `forgot_password`, including the same
+/// response during the cooldown.
 pub async fn resend_verification_email(
     db: &PgPool,
     req: ResendVerificationRequest,
-    email_config: &crate::config::env::EmailConfig,
+    email_service: &EmailService,
 ) -> Result<ResendVerificationResponse, AuthError> {
     // Find user by email
     let user = sqlx::query!(
@@ -405,39 +768,20 @@ pub async fn resend_verification_email(
             });
         }
 
-        // Delete old verification tokens
-        sqlx::query!(
-            "DELETE FROM verification_tokens WHERE user_id = $1",
-            user.id
-        )
-        .execute(db)
-        .await?;
-
-        // Generate new verification token and OTP
-        let verification_token = tokens::generate_token();
-        let otp = tokens::generate_otp();
-        let expires_at = Utc::now() + Duration::hours(24);
-
-        sqlx::query!(
-            r#"
-            INSERT INTO verification_tokens (user_id, token, otp, expires_at)
-            VALUES ($1, $2, $3, $4)
-            "#,
-            user.id,
-            verification_token,
-            otp,
-            expires_at
-        )
-        .execute(db)
-        .await?;
-
-        // Send verification email
-        let email_service = EmailService::new(email_config.clone());
-        if let Err(e) = email_service
-            .send_verification_email(&user.email, &verification_token, &otp)
-            .await
-        {
-            tracing::error!("Failed to send verification email: {:?}", e);
+        match verification_token_to_send(db, user.id, true).await {
+            Ok((verification_token, otp)) => {
+                // Send verification email
+                if let Err(e) = email_service
+                    .send_verification_email(&user.email, &verification_token, &otp)
+                    .await
+                {
+                    tracing::error!("Failed to send verification email: {:?}", e);
+                }
+            }
+            Err(AuthError::ResendCooldown(_)) => {
+                tracing::debug!(user_id = %user.id, "Verification email in cooldown");
+            }
+            Err(e) => return Err(e),
         }
     }
 
@@ -448,14 +792,240 @@ pub async fn resend_verification_email(
     })
 }
 
+/// Outcome of an admin resending verification to one user
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VerificationResend {
+    Sent,
+    AlreadyVerified,
+}
+
+/// Resend verification email to a user looked up by ID
+/// - For admins helping a user, so the resend cooldown doesn't apply
+/// - A recent link is re-sent rather than invalidated, as for the public endpoint
+///
+/// Returns `None` if the user doesn't exist
+pub async fn resend_verification_for_user(
+    db: &PgPool,
+    user_id: uuid::Uuid,
+    email_service: &EmailService,
+) -> Result<Option<VerificationResend>, AuthError> {
+    let Some(user) = sqlx::query!(
+        r#"
+        SELECT email, email_verified
+        FROM users
+        WHERE id = $1 AND deleted_at IS NULL
+        "#,
+        user_id
+    )
+    .fetch_optional(db)
+    .await?
+    else {
+        return Ok(None);
+    };
+
+    if user.email_verified {
+        return Ok(Some(VerificationResend::AlreadyVerified));
+    }
+
+    let (verification_token, otp) = verification_token_to_send(db, user_id, false).await?;
+    email_service
+        .send_verification_email(&user.email, &verification_token, &otp)
+        .await
+        .map_err(|e| {
+            tracing::error!(%user_id, "Failed to resend verification email: {:?}", e);
+            AuthError::Internal
+        })?;
+
+    Ok(Some(VerificationResend::Sent))
+}
+
+/// Verification token and code to (re-)send to an unverified user
+///
+/// Re-sends a recent code rather than invalidating the one already sent,
+/// unless it has been locked by wrong guesses. Only a hash of the link token
+/// is stored, so the link in a re-sent email is a new one. With
+/// `enforce_cooldown`, a code sent too recently is an error instead of being
+/// sent again.
+async fn verification_token_to_send(
+    db: &PgPool,
+    user_id: uuid::Uuid,
+    enforce_cooldown: bool,
+) -> Result<(String, String), AuthError> {
+    let latest = sqlx::query!(
+        r#"
+        SELECT id, otp, otp_attempts, expires_at, created_at, last_sent_at
+        FROM verification_tokens
+        WHERE user_id = $1
+        ORDER BY created_at DESC
+        LIMIT 1
+        "#,
+        user_id
+    )
+    .fetch_optional(db)
+    .await?;
+
+    let reusable = match latest {
+        Some(t) => match tokens::resend_decision(
+            t.created_at,
+            t.last_sent_at,
+            t.expires_at,
+            Utc::now(),
+        ) {
+            ResendDecision::Cooldown(seconds) if enforce_cooldown => {
+                return Err(AuthError::ResendCooldown(seconds));
+            }
+            ResendDecision::Cooldown(_) | ResendDecision::Reuse
+                if t.otp_attempts < MAX_OTP_ATTEMPTS =>
+            {
+                Some((t.id, t.otp))
+            }
+            ResendDecision::Cooldown(_) | ResendDecision::Reuse | ResendDecision::Reissue => None,
+        },
+        None => None,
+    };
+
+    match reusable {
+        Some((id, otp)) => {
+            let token = tokens::generate_token();
+            sqlx::query!(
+                r#"
+                UPDATE verification_tokens
+                SET token_hash = $2, last_sent_at = NOW()
+                WHERE id = $1
+                "#,
+                id,
+                tokens::hash_token(&token)
+            )
+            .execute(db)
+            .await?;
+            Ok((token, otp))
+        }
+        None => Ok(reissue_verification_token(db, user_id).await?),
+    }
+}
+
+const DEFAULT_BULK_RESEND_LIMIT: i64 = 100;
+const MAX_BULK_RESEND_LIMIT: i64 = 500;
+
+/// Re-send verification emails to unverified users matching an admin filter
+/// - Users are processed oldest first, up to the request limit
+/// - Each user gets a fresh token, invalidating any earlier link
+/// - Emails go through the queue, which paces delivery itself
+pub async fn resend_verification_emails(
+    db: &PgPool,
+    req: &BulkResendVerificationRequest,
+    email_service: &EmailService,
+) -> Result<BulkResendVerificationResponse, sqlx::Error> {
+    let candidates = bulk_resend_candidates(db, req).await?;
+
+    let mut result = BulkResendVerificationResponse {
+        processed: 0,
+        sent: 0,
+        failed: 0,
+    };
+
+    for (user_id, email) in candidates {
+        result.processed += 1;
+
+        let (verification_token, otp) = reissue_verification_token(db, user_id).await?;
+        match email_service
+            .send_verification_email(&email, &verification_token, &otp)
+            .await
+        {
+            Ok(()) => result.sent += 1,
+            Err(e) => {
+                tracing::error!(%user_id, "Failed to resend verification email: {:?}", e);
+                result.failed += 1;
+            }
+        }
+    }
+
+    tracing::info!(
+        "Bulk verification resend: {} processed, {} sent, {} failed",
+        result.processed,
+        result.sent,
+        result.failed
+    );
+
+    Ok(result)
+}
+
+/// Unverified, not deleted users matching the filter, as (id, email), oldest first
+async fn bulk_resend_candidates(
+    db: &PgPool,
+    req: &BulkResendVerificationRequest,
+) -> Result<Vec<(uuid::Uuid, String)>, sqlx::Error> {
+    let limit = req
+        .limit
+        .unwrap_or(DEFAULT_BULK_RESEND_LIMIT)
+        .clamp(1, MAX_BULK_RESEND_LIMIT);
+
+    let candidates = sqlx::query!(
+        r#"
+        SELECT id, email
+        FROM users
+        WHERE email_verified = false
+          AND deleted_at IS NULL
+          AND ($1::timestamptz IS NULL OR created_at >= $1)
+          AND ($2::timestamptz IS NULL OR created_at < $2)
+        ORDER BY created_at ASC
+        LIMIT $3
+        "#,
+        req.created_after,
+        req.created_before,
+        limit
+    )
+    .fetch_all(db)
+    .await?;
+
+    Ok(candidates.into_iter().map(|user| (user.id, user.email)).collect())
+}
+
+/// Replace a user's verification tokens with a new token and OTP
+async fn reissue_verification_token(
+    db: &PgPool,
+    user_id: uuid::Uuid,
+) -> Result<(String, String), sqlx::Error> {
+    // Delete old verification tokens
+    sqlx::query!(
+        "DELETE FROM verification_tokens WHERE user_id = $1",
+        user_id
+    )
+    .execute(db)
+    .await?;
+
+    // Generate new verification token and OTP
+    let verification_token = tokens::generate_token();
+    let otp = tokens::generate_otp();
+    let expires_at = Utc::now() + tokens::VERIFICATION_TOKEN_LIFETIME;
+
+    sqlx::query!(
+        r#"
+        INSERT INTO verification_tokens (user_id, token_hash, otp, expires_at)
+        VALUES ($1, $2, $3, $4)
+        "#,
+        user_id,
+        tokens::hash_token(&verification_token),
+        otp,
+        expires_at
+    )
+    .execute(db)
+    .await?;
+
+    Ok((verification_token, otp))
+}
+
 // ===== Account Recovery =====
 
 /// Recover a soft-deleted account
 pub async fn recover_account(
     db: &PgPool,
+    cache: Option<&RedisPool>,
     req: RecoverAccountRequest,
     ip_address: Option<IpNetwork>,
     user_agent: Option<String>,
+    max_sessions: u32,
+    retention_days: u32,
 ) -> Result<RecoverAccountResponse, AuthError> {
     // Find soft-deleted user by email
     let user = sqlx::query!(
@@ -478,11 +1048,9 @@ pub async fn recover_account(
         return Err(AuthError::InvalidCredentials);
     }
 
-    // Check if within recovery window (30 days)
+    // Check if within recovery window
     let deleted_at = user.deleted_at.ok_or(AuthError::InvalidCredentials)?;
-    let recovery_deadline = deleted_at + Duration::days(30);
-
-    if Utc::now() > recovery_deadline {
+    if purge::recovery_expired(deleted_at, Utc::now(), retention_days) {
         return Err(AuthError::AccountRecoveryExpired);
     }
 
@@ -499,8 +1067,16 @@ pub async fn recover_account(
     .await?;
 
     // Create new session with user's role
-    let (session_token, expires_at) =
-        session::create_session(db, user.id, user.role, ip_address, user_agent).await?;
+    let (_, session_token, expires_at) = session::create_session(
+        db,
+        cache,
+        user.id,
+        user.role,
+        ip_address,
+        user_agent,
+        max_sessions,
+    )
+    .await?;
 
     Ok(RecoverAccountResponse {
         user_id: user.id,
@@ -510,3 +1086,140 @@ pub async fn recover_account(
         message: "Account recovered successfully. Welcome back!".to_string(),
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{DateTime, TimeZone};
+
+    async fn create_user(
+        db: &PgPool,
+        email: &str,
+        verified: bool,
+        deleted: bool,
+        created_at: DateTime<Utc>,
+    ) {
+        sqlx::query!(
+            r#"
+            INSERT INTO users (email, email_verified, created_at, deleted_at)
+            VALUES ($1, $2, $3, CASE WHEN $4 THEN NOW() END)
+            "#,
+            email,
+            verified,
+            created_at,
+            deleted
+        )
+        .execute(db)
+        .await
+        .unwrap();
+    }
+
+    #[sqlx::test]
+    async fn test_signup_races_map_to_conflicts(db: PgPool) {
+        sqlx::query!("INSERT INTO users (email, username) VALUES ('first@example.com', 'taken')")
+            .execute(&db)
+            .await
+            .unwrap();
+
+        let insert = |email: &'static str, username: &'static str| {
+            sqlx::query!(
+                "INSERT INTO users (email, username) VALUES ($1, $2)",
+                email,
+                username
+            )
+            .execute(&db)
+        };
+        let same_username = insert("second@example.com", "TAKEN").await.unwrap_err();
+        assert!(matches!(
+            signup_conflict(same_username),
+            AuthError::UsernameAlreadyTaken
+        ));
+        let same_email = insert("first@example.com", "other").await.unwrap_err();
+        assert!(matches!(
+            signup_conflict(same_email),
+            AuthError::EmailAlreadyExists
+        ));
+    }
+
+    #[sqlx::test]
+    async fn test_bulk_resend_selects_unverified_users_in_window(db: PgPool) {
+        let at = |day| Utc.with_ymd_and_hms(2026, 1, day, 12, 0, 0).unwrap();
+        create_user(&db, "before@example.com", false, false, at(9)).await;
+        create_user(&db, "first@example.com", false, false, at(10)).await;
+        create_user(&db, "verified@example.com", true, false, at(12)).await;
+        create_user(&db, "deleted@example.com", false, true, at(13)).await;
+        create_user(&db, "second@example.com", false, false, at(15)).await;
+        create_user(&db, "at-end@example.com", false, false, at(20)).await;
+
+        let emails = |req: BulkResendVerificationRequest| {
+            let db = &db;
+            async move {
+                bulk_resend_candidates(db, &req)
+                    .await
+                    .unwrap()
+                    .into_iter()
+                    .map(|(_, email)| email)
+                    .collect::<Vec<_>>()
+            }
+        };
+
+        let window = |limit| BulkResendVerificationRequest {
+            created_after: Some(at(10)),
+            created_before: Some(at(20)),
+            limit,
+        };
+        assert_eq!(
+            emails(window(None)).await,
+            ["first@example.com", "second@example.com"]
+        );
+        assert_eq!(emails(window(Some(1))).await, ["first@example.com"]);
+
+        let open = serde_json::from_str("{}").unwrap();
+        assert_eq!(emails(open).await.len(), 4);
+    }
+
+    #[sqlx::test]
+    async fn test_verification_links_are_stored_hashed(db: PgPool) {
+        let user_id = sqlx::query_scalar!(
+            "INSERT INTO users (email) VALUES ('unverified@example.com') RETURNING id"
+        )
+        .fetch_one(&db)
+        .await
+        .unwrap();
+        let (first, otp) = reissue_verification_token(&db, user_id).await.unwrap();
+
+        let stored = sqlx::query_scalar!(
+            "SELECT token_hash FROM verification_tokens WHERE user_id = $1",
+            user_id
+        )
+        .fetch_one(&db)
+        .await
+        .unwrap();
+        assert_eq!(stored, tokens::hash_token(&first));
+
+        // A re-send keeps the code but can only mail a new link
+        let (second, resent_otp) = verification_token_to_send(&db, user_id, false)
+            .await
+            .unwrap();
+        assert_eq!(resent_otp, otp);
+        assert_ne!(second, first);
+
+        let verify = |token: String| {
+            let db = &db;
+            async move {
+                let req = VerifyEmailRequest {
+                    token: Some(token),
+                    email: None,
+                    otp: None,
+                };
+                let client = ClientInfo {
+                    ip_address: None,
+                    user_agent: None,
+                };
+                verify_email(db, req, &client).await
+            }
+        };
+        assert!(matches!(verify(first).await, Err(AuthError::InvalidToken)));
+        assert!(verify(second).await.is_ok());
+    }
+}
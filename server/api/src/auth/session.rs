@@ -1,26 +1,111 @@
 use chrono::{DateTime, Duration, Utc};
+use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
 use sqlx::PgPool;
 use uuid::Uuid;
 
 use sqlx::types::ipnetwork::IpNetwork;
 use super::{AuthError, Role, tokens};
+use crate::common::background;
+use crate::config::cache::RedisPool;
+
+/// Session lookup result cached in Redis under `session:{token}`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct CachedSession {
+    user_id: Uuid,
+    role: Role,
+    expires_at: DateTime<Utc>,
+    #[serde(default)]
+    impersonated_by: Option<Uuid>,
+}
+
+/// The session a request was authenticated with, set by `auth_middleware`
+///
+/// Opaque bearer tokens identify the session by token; JWT access tokens
+/// carry its ID.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CurrentSession {
+    Token(String),
+    Id(Uuid),
+}
+
+impl CurrentSession {
+    pub fn is(&self, session_id: Uuid, session_token: &str) -> bool {
+        match self {
+            CurrentSession::Token(token) => token == session_token,
+            CurrentSession::Id(id) => *id == session_id,
+        }
+    }
+}
+
+/// Whether the request uses a session an admin opened by impersonating the
+/// user, set by `auth_middleware`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IsImpersonated(pub bool);
+
+/// An active session counted against `MAX_SESSIONS_PER_USER`
+#[derive(Debug, Clone)]
+struct ActiveSession {
+    id: Uuid,
+    session_token: String,
+    created_at: DateTime<Utc>,
+}
 
 /// Create a new session for a user with their role
-/// Returns (session_token, expires_at)
+/// Returns (session_id, session_token, expires_at)
+///
+/// With `max_sessions` > 0, the user's oldest sessions are evicted in the
+/// same transaction so at most `max_sessions` remain, including the new one.
+/// Impersonation sessions don't count towards the limit.
 pub async fn create_session(
     db: &PgPool,
+    cache: Option<&RedisPool>,
     user_id: Uuid,
     role: Role,
     ip_address: Option<IpNetwork>,
     user_agent: Option<String>,
-) -> Result<(String, DateTime<Utc>), AuthError> {
+    max_sessions: u32,
+) -> Result<(Uuid, String, DateTime<Utc>), AuthError> {
     let session_token = tokens::generate_session_token();
     let expires_at = Utc::now() + Duration::hours(168); // 7 days
 
-    sqlx::query!(
+    let mut tx = db.begin().await?;
+
+    let evicted = if max_sessions > 0 {
+        // Lock the user so concurrent sign-ins can't both slip under the limit
+        sqlx::query!("SELECT id FROM users WHERE id = $1 FOR UPDATE", user_id)
+            .fetch_optional(&mut *tx)
+            .await?;
+
+        let active = sqlx::query_as!(
+            ActiveSession,
+            r#"
+            SELECT id, session_token, created_at
+            FROM sessions
+            WHERE user_id = $1 AND expires_at > NOW() AND impersonated_by IS NULL
+            "#,
+            user_id
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+
+        let evicted = sessions_to_evict(active, max_sessions);
+        if !evicted.is_empty() {
+            let ids: Vec<Uuid> = evicted.iter().map(|s| s.id).collect();
+            sqlx::query!("DELETE FROM sessions WHERE id = ANY($1)", &ids)
+                .execute(&mut *tx)
+                .await?;
+        }
+        evicted
+    } else {
+        Vec::new()
+    };
+
+    let session_id = sqlx::query_scalar!(
         r#"
         INSERT INTO sessions (user_id, session_token, expires_at, role, ip_address, user_agent)
         VALUES ($1, $2, $3, $4, $5, $6)
+        RETURNING id
         "#,
         user_id,
         session_token,
@@ -29,22 +114,148 @@ pub async fn create_session(
         ip_address,
         user_agent
     )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    if !evicted.is_empty() {
+        tracing::info!(
+            %user_id,
+            evicted = ?evicted.iter().map(|s| s.id).collect::<Vec<_>>(),
+            max_sessions,
+            "Evicted oldest sessions over the per-user limit"
+        );
+        let tokens: Vec<String> = evicted.into_iter().map(|s| s.session_token).collect();
+        cache_evict(cache, &tokens).await;
+    }
+
+    Ok((session_id, session_token, expires_at))
+}
+
+/// Oldest sessions to drop so that, with one new session, at most `max_sessions` remain
+fn sessions_to_evict(mut active: Vec<ActiveSession>, max_sessions: u32) -> Vec<ActiveSession> {
+    if max_sessions == 0 {
+        return Vec::new();
+    }
+
+    let excess = (active.len() + 1).saturating_sub(max_sessions as usize);
+    active.sort_by_key(|s| s.created_at);
+    active.truncate(excess);
+    active
+}
+
+/// Active sessions a user holds towards `MAX_SESSIONS_PER_USER`
+pub async fn count_active_sessions(db: &PgPool, user_id: Uuid) -> Result<i64, sqlx::Error> {
+    sqlx::query_scalar!(
+        r#"
+        SELECT COUNT(*) as "count!"
+        FROM sessions
+        WHERE user_id = $1 AND expires_at > NOW() AND impersonated_by IS NULL
+        "#,
+        user_id
+    )
+    .fetch_one(db)
+    .await
+}
+
+/// When the user last signed in to get this session, carried across refreshes
+///
+/// `None` for unknown, expired and impersonation sessions: an admin opening a
+/// session as the user is not the user signing in.
+pub async fn authenticated_at(
+    db: &PgPool,
+    current: &CurrentSession,
+) -> Result<Option<DateTime<Utc>>, sqlx::Error> {
+    let (session_id, session_token) = match current {
+        CurrentSession::Id(id) => (Some(*id), None),
+        CurrentSession::Token(token) => (None, Some(token.as_str())),
+    };
+
+    sqlx::query_scalar!(
+        r#"
+        SELECT authenticated_at
+        FROM sessions
+        WHERE (id = $1 OR session_token = $2)
+          AND expires_at > NOW()
+          AND impersonated_by IS NULL
+        "#,
+        session_id,
+        session_token
+    )
+    .fetch_optional(db)
+    .await
+}
+
+/// Keep the sign-in time of the session a refresh replaced
+pub async fn set_authenticated_at(
+    db: &PgPool,
+    session_id: Uuid,
+    authenticated_at: DateTime<Utc>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        "UPDATE sessions SET authenticated_at = $2 WHERE id = $1",
+        session_id,
+        authenticated_at
+    )
     .execute(db)
     .await?;
+    Ok(())
+}
+
+/// Create a short-lived session for `user_id` on behalf of an impersonating admin
+/// Returns (session_id, session_token, expires_at)
+pub async fn create_impersonation_session(
+    db: &PgPool,
+    user_id: Uuid,
+    role: Role,
+    admin_id: Uuid,
+    ip_address: Option<IpNetwork>,
+    user_agent: Option<String>,
+) -> Result<(Uuid, String, DateTime<Utc>), AuthError> {
+    let session_token = tokens::generate_session_token();
+    let expires_at = Utc::now() + Duration::hours(1);
+
+    let session_id = sqlx::query_scalar!(
+        r#"
+        INSERT INTO sessions (user_id, session_token, expires_at, role, ip_address, user_agent, impersonated_by)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        RETURNING id
+        "#,
+        user_id,
+        session_token,
+        expires_at,
+        role as Role,
+        ip_address,
+        user_agent,
+        admin_id
+    )
+    .fetch_one(db)
+    .await?;
 
-    Ok((session_token, expires_at))
+    Ok((session_id, session_token, expires_at))
 }
 
 /// Get user ID and role from session token
-/// Returns (user_id, role) if session is valid
+/// Returns (user_id, role, impersonated_by) if session is valid
 /// This eliminates the need for a separate DB query to fetch the role
+///
+/// With a Redis cache, valid sessions are served from `session:{token}` and
+/// cached on a miss until they expire.
 pub async fn get_user_from_session(
     db: &PgPool,
+    cache: Option<&RedisPool>,
     session_token: &str,
-) -> Result<(Uuid, Role), AuthError> {
+) -> Result<(Uuid, Role, Option<Uuid>), AuthError> {
+    if let Some(session) = cache_get(cache, session_token).await
+        && session.expires_at > Utc::now()
+    {
+        return Ok((session.user_id, session.role, session.impersonated_by));
+    }
+
     let result = sqlx::query!(
         r#"
-        SELECT user_id, expires_at, role as "role: Role"
+        SELECT user_id, expires_at, role as "role: Role", impersonated_by
         FROM sessions
         WHERE session_token = $1
         "#,
@@ -58,75 +269,281 @@ pub async fn get_user_from_session(
             // Check if expired
             if session.expires_at < Utc::now() {
                 // Delete expired session
-                invalidate_session(db, session_token).await?;
+                invalidate_session(db, cache, session_token).await?;
                 return Err(AuthError::TokenExpired);
             }
-            Ok((session.user_id, session.role))
+
+            let cached = CachedSession {
+                user_id: session.user_id,
+                role: session.role,
+                expires_at: session.expires_at,
+                impersonated_by: session.impersonated_by,
+            };
+            cache_put(cache, session_token, &cached).await;
+
+            Ok((session.user_id, session.role, session.impersonated_by))
         }
         None => Err(AuthError::SessionNotFound),
     }
 }
 
 /// Invalidate a session
-pub async fn invalidate_session(db: &PgPool, session_token: &str) -> Result<(), AuthError> {
-    sqlx::query!(
+/// Returns the session's user ID, or `None` if no session matched
+pub async fn invalidate_session(
+    db: &PgPool,
+    cache: Option<&RedisPool>,
+    session_token: &str,
+) -> Result<Option<Uuid>, AuthError> {
+    let user_id = sqlx::query_scalar!(
         r#"
         DELETE FROM sessions
         WHERE session_token = $1
+        RETURNING user_id
         "#,
         session_token
     )
-    .execute(db)
+    .fetch_optional(db)
     .await?;
 
-    Ok(())
+    cache_evict(cache, &[session_token.to_string()]).await;
+
+    Ok(user_id)
 }
 
 /// Invalidate all sessions for a user
-pub async fn invalidate_all_user_sessions(db: &PgPool, user_id: Uuid) -> Result<(), AuthError> {
-    sqlx::query!(
+/// Returns how many sessions were removed
+pub async fn invalidate_all_user_sessions(
+    db: &PgPool,
+    cache: Option<&RedisPool>,
+    user_id: Uuid,
+) -> Result<u64, AuthError> {
+    let tokens = sqlx::query_scalar!(
         r#"
         DELETE FROM sessions
         WHERE user_id = $1
+        RETURNING session_token
         "#,
         user_id
     )
-    .execute(db)
+    .fetch_all(db)
     .await?;
 
-    Ok(())
+    cache_evict(cache, &tokens).await;
+
+    Ok(tokens.len() as u64)
+}
+
+/// End every impersonation session opened for a user
+/// Returns how many sessions were removed
+pub async fn invalidate_impersonation_sessions(
+    db: &PgPool,
+    cache: Option<&RedisPool>,
+    user_id: Uuid,
+) -> Result<u64, AuthError> {
+    let tokens = sqlx::query_scalar!(
+        r#"
+        DELETE FROM sessions
+        WHERE user_id = $1 AND impersonated_by IS NOT NULL
+        RETURNING session_token
+        "#,
+        user_id
+    )
+    .fetch_all(db)
+    .await?;
+
+    cache_evict(cache, &tokens).await;
+
+    Ok(tokens.len() as u64)
 }
 
 /// Invalidate all sessions except the current one
 pub async fn invalidate_all_sessions_except(
     db: &PgPool,
+    cache: Option<&RedisPool>,
     user_id: Uuid,
     current_session_token: &str,
 ) -> Result<(), AuthError> {
-    sqlx::query!(
+    let tokens = sqlx::query_scalar!(
         r#"
         DELETE FROM sessions
         WHERE user_id = $1 AND session_token != $2
+        RETURNING session_token
         "#,
         user_id,
         current_session_token
     )
-    .execute(db)
+    .fetch_all(db)
     .await?;
 
+    cache_evict(cache, &tokens).await;
+
     Ok(())
 }
 
 /// Cleanup expired sessions (should be run periodically)
-pub async fn cleanup_expired_sessions(db: &PgPool) -> Result<u64, sqlx::Error> {
-    let result = sqlx::query!(
-        r#"
-        DELETE FROM sessions
-        WHERE expires_at < NOW()
-        "#
-    )
-    .execute(db)
-    .await?;
+/// Deletes at most `batch_size` rows per statement
+pub async fn cleanup_expired_sessions(db: &PgPool, batch_size: i64) -> Result<u64, sqlx::Error> {
+    background::delete_in_batches(batch_size, |limit| async move {
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM sessions
+            WHERE id IN (
+                SELECT id FROM sessions
+                WHERE expires_at < NOW()
+                LIMIT $1
+                FOR UPDATE SKIP LOCKED
+            )
+            "#,
+            limit
+        )
+        .execute(db)
+        .await?;
+
+        Ok(result.rows_affected())
+    })
+    .await
+}
+
+// ===== Redis session cache =====
 
-    Ok(result.rows_affected())
+fn cache_key(session_token: &str) -> String {
+    format!("session:{}", session_token)
+}
+
+/// Cached session for a token, if the cache is enabled and has it
+async fn cache_get(cache: Option<&RedisPool>, session_token: &str) -> Option<CachedSession> {
+    let mut conn = cache?.clone();
+
+    match conn
+        .get::<_, Option<String>>(cache_key(session_token))
+        .await
+    {
+        Ok(value) => value.and_then(|json| serde_json::from_str(&json).ok()),
+        Err(e) => {
+            tracing::warn!("Session cache read failed: {}", e);
+            None
+        }
+    }
+}
+
+/// Cache a session until it expires
+async fn cache_put(cache: Option<&RedisPool>, session_token: &str, session: &CachedSession) {
+    let Some(cache) = cache else {
+        return;
+    };
+
+    let ttl = (session.expires_at - Utc::now()).num_seconds();
+    if ttl <= 0 {
+        return;
+    }
+    let Ok(json) = serde_json::to_string(session) else {
+        return;
+    };
+
+    let mut conn = cache.clone();
+    if let Err(e) = conn
+        .set_ex::<_, _, ()>(cache_key(session_token), json, ttl as u64)
+        .await
+    {
+        tracing::warn!("Session cache write failed: {}", e);
+    }
+}
+
+/// Remove sessions from the cache
+///
+/// A failed delete leaves a revoked session usable from the cache until it
+/// expires, so it is logged as an error.
+pub async fn cache_evict(cache: Option<&RedisPool>, session_tokens: &[String]) {
+    let Some(cache) = cache else {
+        return;
+    };
+    if session_tokens.is_empty() {
+        return;
+    }
+
+    let keys: Vec<String> = session_tokens.iter().map(|t| cache_key(t)).collect();
+    let mut conn = cache.clone();
+    if let Err(e) = conn.del::<_, ()>(keys).await {
+        tracing::error!("Session cache eviction failed: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cached_session_roundtrip() {
+        let session = CachedSession {
+            user_id: Uuid::new_v4(),
+            role: Role::Admin,
+            expires_at: Utc::now(),
+            impersonated_by: Some(Uuid::new_v4()),
+        };
+
+        let json = serde_json::to_string(&session).unwrap();
+        assert_eq!(
+            serde_json::from_str::<CachedSession>(&json).unwrap(),
+            session
+        );
+        assert_eq!(cache_key("abc"), "session:abc");
+
+        // Entries cached before impersonation was tracked
+        let old = format!(
+            r#"{{"user_id":"{}","role":"user","expires_at":"2026-01-01T00:00:00Z"}}"#,
+            Uuid::nil()
+        );
+        let cached: CachedSession = serde_json::from_str(&old).unwrap();
+        assert_eq!(cached.impersonated_by, None);
+    }
+
+    #[test]
+    fn test_current_session_matches_token_or_id() {
+        let id = Uuid::new_v4();
+
+        assert!(CurrentSession::Token("abc".to_string()).is(Uuid::new_v4(), "abc"));
+        assert!(!CurrentSession::Token("abc".to_string()).is(id, "xyz"));
+        assert!(CurrentSession::Id(id).is(id, "xyz"));
+        assert!(!CurrentSession::Id(id).is(Uuid::new_v4(), "xyz"));
+    }
+
+    fn active_session(minutes_ago: i64) -> ActiveSession {
+        ActiveSession {
+            id: Uuid::new_v4(),
+            session_token: tokens::generate_session_token(),
+            created_at: Utc::now() - Duration::minutes(minutes_ago),
+        }
+    }
+
+    #[test]
+    fn test_evicts_oldest_sessions_first() {
+        let active = vec![
+            active_session(10),
+            active_session(30),
+            active_session(5),
+            active_session(20),
+        ];
+        let oldest = active[1].id;
+        let second_oldest = active[3].id;
+
+        // Room for the new session means two of the four must go
+        let evicted = sessions_to_evict(active.clone(), 3);
+        let ids: Vec<Uuid> = evicted.iter().map(|s| s.id).collect();
+        assert_eq!(ids, vec![oldest, second_oldest]);
+
+        assert!(sessions_to_evict(active.clone(), 5).is_empty());
+        assert_eq!(sessions_to_evict(active, 1).len(), 4);
+    }
+
+    #[test]
+    fn test_unlimited_sessions_never_evict() {
+        let active: Vec<ActiveSession> = (0..50).map(active_session).collect();
+        assert!(sessions_to_evict(active, 0).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_cache_disabled_is_a_miss() {
+        assert!(cache_get(None, "abc").await.is_none());
+        cache_evict(None, &["abc".to_string()]).await;
+    }
 }
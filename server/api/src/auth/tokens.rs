@@ -1,4 +1,50 @@
+use chrono::{DateTime, Duration, Utc};
 use rand::{Rng, distributions::Alphanumeric};
+use sha2::{Digest, Sha256};
+
+/// How long an email verification link or code stays valid
+pub const VERIFICATION_TOKEN_LIFETIME: Duration = Duration::hours(24);
+
+/// How long a password reset link stays valid
+pub const PASSWORD_RESET_TOKEN_LIFETIME: Duration = Duration::hours(1);
+
+/// Minimum time between two emails for the same verification or reset token
+pub const RESEND_COOLDOWN: Duration = Duration::seconds(60);
+
+/// Tokens younger than this are re-sent instead of replaced, so a link that is
+/// already in the user's inbox keeps working
+pub const TOKEN_REUSE_WINDOW: Duration = Duration::hours(1);
+
+/// What to do when a user asks for another email with a token in it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResendDecision {
+    /// Too soon after the last email; seconds until another is allowed
+    Cooldown(u64),
+    /// Send the existing token again
+    Reuse,
+    /// Replace the token with a new one
+    Reissue,
+}
+
+/// Decide how to handle a resend given the user's latest token
+pub fn resend_decision(
+    created_at: DateTime<Utc>,
+    last_sent_at: DateTime<Utc>,
+    expires_at: DateTime<Utc>,
+    now: DateTime<Utc>,
+) -> ResendDecision {
+    let wait = last_sent_at + RESEND_COOLDOWN - now;
+    if wait > Duration::zero() {
+        let seconds = (wait.num_milliseconds() as u64).div_ceil(1000);
+        return ResendDecision::Cooldown(seconds.max(1));
+    }
+
+    if expires_at > now && now - created_at < TOKEN_REUSE_WINDOW {
+        ResendDecision::Reuse
+    } else {
+        ResendDecision::Reissue
+    }
+}
 
 /// Generate a secure random token
 /// Returns a 32-character alphanumeric string
@@ -26,6 +72,24 @@ pub fn generate_otp() -> String {
     format!("{:06}", otp)
 }
 
+/// Hex SHA-256 of a token, which is what gets stored and looked up
+///
+/// Tokens are random, so an unsalted hash is enough to keep a leaked table
+/// from holding usable links.
+pub fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token))
+}
+
+/// Compare two secrets without short-circuiting on the first differing byte
+pub fn constant_time_eq(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a
+            .bytes()
+            .zip(b.bytes())
+            .fold(0u8, |acc, (x, y)| acc | (x ^ y))
+            == 0
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -41,6 +105,62 @@ mod tests {
         assert!(token1.chars().all(|c| c.is_alphanumeric()));
     }
 
+    #[test]
+    fn test_otp_generation() {
+        let otp = generate_otp();
+        assert_eq!(otp.len(), 6);
+        assert!(otp.chars().all(|c| c.is_ascii_digit()));
+    }
+
+    #[test]
+    fn test_hash_token() {
+        let token = generate_token();
+        assert_eq!(hash_token(&token), hash_token(&token));
+        assert_ne!(hash_token(&token), token);
+        assert_eq!(
+            hash_token("abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+
+    #[test]
+    fn test_constant_time_eq() {
+        assert!(constant_time_eq("123456", "123456"));
+        assert!(!constant_time_eq("123456", "123457"));
+        assert!(!constant_time_eq("123456", "12345"));
+    }
+
+    #[test]
+    fn test_resend_decision() {
+        let now = Utc::now();
+        let expires_at = now + Duration::hours(1);
+
+        // Just sent: wait out the rest of the cooldown
+        assert_eq!(
+            resend_decision(now, now - Duration::seconds(15), expires_at, now),
+            ResendDecision::Cooldown(45)
+        );
+
+        // Recent token sent a while ago: send the same one again
+        let created_at = now - Duration::minutes(10);
+        assert_eq!(
+            resend_decision(created_at, created_at, expires_at, now),
+            ResendDecision::Reuse
+        );
+
+        // Old or expired tokens are replaced
+        let created_at = now - Duration::hours(2);
+        assert_eq!(
+            resend_decision(created_at, created_at, now + Duration::hours(22), now),
+            ResendDecision::Reissue
+        );
+        let created_at = now - Duration::minutes(10);
+        assert_eq!(
+            resend_decision(created_at, created_at, now - Duration::seconds(1), now),
+            ResendDecision::Reissue
+        );
+    }
+
     #[test]
     fn test_session_token_generation() {
         let token = generate_session_token();
@@ -1,38 +1,47 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
 use uuid::Uuid;
 
 // ============================================================================
 // SIGN IN
 // ============================================================================
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct SignInRequest {
     pub email: String,
     pub password: String,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct SignInResponse {
     pub user_id: Uuid,
     pub email: String,
     pub session_token: String,
     pub expires_at: DateTime<Utc>,
+    /// Short-lived JWT, only issued when `JWT_ENABLED` is set
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub access_token: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub access_token_expires_at: Option<DateTime<Utc>>,
 }
 
 // ============================================================================
 // SIGN UP
 // ============================================================================
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct SignUpRequest {
     pub email: String,
     pub password: String,
     pub name: Option<String>,
     pub username: Option<String>,
+    /// Client CAPTCHA token, required only when CAPTCHA is configured
+    #[serde(default)]
+    pub captcha_token: Option<String>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct SignUpResponse {
     pub user_id: Uuid,
     pub email: String,
@@ -43,44 +52,67 @@ pub struct SignUpResponse {
 // REFRESH TOKEN
 // ============================================================================
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct RefreshRequest {
     pub session_token: String,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct RefreshResponse {
     pub session_token: String,
     pub expires_at: DateTime<Utc>,
+    /// Short-lived JWT, only issued when `JWT_ENABLED` is set
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub access_token: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub access_token_expires_at: Option<DateTime<Utc>>,
 }
 
 // ============================================================================
 // EMAIL VERIFICATION
 // ============================================================================
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema, IntoParams)]
 pub struct VerifyEmailRequest {
     pub token: Option<String>,
     pub email: Option<String>,
     pub otp: Option<String>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct VerifyEmailResponse {
     pub message: String,
     pub email_verified: bool,
 }
 
+// ============================================================================
+// EMAIL CHANGE CONFIRMATION
+// ============================================================================
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct ConfirmEmailChangeQuery {
+    pub token: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ConfirmEmailChangeResponse {
+    pub message: String,
+    pub email: String,
+}
+
 // ============================================================================
 // FORGOT PASSWORD
 // ============================================================================
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct ForgotPasswordRequest {
     pub email: String,
+    /// Client CAPTCHA token, required only when CAPTCHA is configured
+    #[serde(default)]
+    pub captcha_token: Option<String>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct ForgotPasswordResponse {
     pub message: String,
 }
@@ -89,13 +121,13 @@ pub struct ForgotPasswordResponse {
 // RESET PASSWORD
 // ============================================================================
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct ResetPasswordRequest {
     pub token: String,
     pub new_password: String,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct ResetPasswordResponse {
     pub message: String,
 }
@@ -104,27 +136,46 @@ pub struct ResetPasswordResponse {
 // RESEND VERIFICATION
 // ============================================================================
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct ResendVerificationRequest {
     pub email: String,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct ResendVerificationResponse {
     pub message: String,
 }
 
+/// Admin filter for re-sending verification emails in bulk
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct BulkResendVerificationRequest {
+    /// Only users created at or after this time
+    pub created_after: Option<DateTime<Utc>>,
+    /// Only users created before this time
+    pub created_before: Option<DateTime<Utc>>,
+    /// Maximum number of users to process (default 100, max 500)
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct BulkResendVerificationResponse {
+    /// Unverified users matched by the filter
+    pub processed: usize,
+    pub sent: usize,
+    pub failed: usize,
+}
+
 // ============================================================================
 // ACCOUNT RECOVERY
 // ============================================================================
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct RecoverAccountRequest {
     pub email: String,
     pub password: String,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct RecoverAccountResponse {
     pub user_id: Uuid,
     pub email: String,
@@ -6,6 +6,9 @@ use axum::{
 use serde_json::json;
 use thiserror::Error;
 
+use crate::common::pagination::InvalidCursor;
+use crate::config::database;
+
 /// Chat-specific errors
 #[derive(Debug, Error)]
 pub enum ChatError {
@@ -24,6 +27,9 @@ pub enum ChatError {
     #[error("Database error: {0}")]
     DatabaseError(String),
 
+    #[error("Database connection pool exhausted")]
+    PoolExhausted,
+
     #[error("Serialization error: {0}")]
     SerializationError(#[from] serde_json::Error),
 
@@ -46,10 +52,43 @@ pub enum ChatError {
 
     #[error("Intelligence service error: {0}")]
     IntelligenceError(String),
+
+    #[error("Daily token quota exceeded: {used} of {limit} tokens used")]
+    QuotaExceeded { used: i64, limit: i64 },
+
+    #[error("Monthly token budget exceeded: {used} of {limit} tokens used")]
+    BudgetExceeded { used: i64, limit: i64 },
+
+    #[error("Feedback already submitted for this message")]
+    FeedbackExists,
+
+    #[error("Invalid pagination cursor")]
+    InvalidCursor,
+
+    #[error("A response is still streaming into this conversation")]
+    StreamInProgress,
+
+    #[error("Idempotency-Key must be a UUID")]
+    InvalidIdempotencyKey,
+
+    #[error("A request with this Idempotency-Key is still in progress")]
+    IdempotencyKeyInUse,
+
+    #[error("Invalid tag: {0}")]
+    InvalidTag(String),
+}
+
+impl From<InvalidCursor> for ChatError {
+    fn from(_: InvalidCursor) -> Self {
+        ChatError::InvalidCursor
+    }
 }
 
 impl From<sqlx::Error> for ChatError {
     fn from(e: sqlx::Error) -> Self {
+        if database::is_pool_exhausted(&e) {
+            return ChatError::PoolExhausted;
+        }
         ChatError::DatabaseError(e.to_string())
     }
 }
@@ -154,6 +193,41 @@ impl IntoResponse for ChatError {
                 "intelligence_error",
                 self.to_string(),
             ),
+            ChatError::QuotaExceeded { .. } => (
+                StatusCode::TOO_MANY_REQUESTS,
+                "quota_exceeded",
+                self.to_string(),
+            ),
+            ChatError::BudgetExceeded { .. } => (
+                StatusCode::PAYMENT_REQUIRED,
+                "budget_exceeded",
+                self.to_string(),
+            ),
+            ChatError::InvalidCursor => {
+                (StatusCode::BAD_REQUEST, "invalid_cursor", self.to_string())
+            }
+            ChatError::InvalidTag(_) => {
+                (StatusCode::BAD_REQUEST, "invalid_tag", self.to_string())
+            }
+            ChatError::InvalidIdempotencyKey => (
+                StatusCode::BAD_REQUEST,
+                "invalid_idempotency_key",
+                self.to_string(),
+            ),
+            ChatError::IdempotencyKeyInUse => (
+                StatusCode::CONFLICT,
+                "idempotency_key_in_use",
+                self.to_string(),
+            ),
+            ChatError::FeedbackExists => {
+                (StatusCode::CONFLICT, "feedback_exists", self.to_string())
+            }
+            ChatError::StreamInProgress => (
+                StatusCode::CONFLICT,
+                "stream_in_progress",
+                self.to_string(),
+            ),
+            ChatError::PoolExhausted => return database::pool_exhausted_response(),
             ChatError::DatabaseError(_)
             | ChatError::SerializationError(_)
             | ChatError::InternalError(_) => {
@@ -0,0 +1,380 @@
+//! Conversation export
+//!
+//! `GET /chat/conversations/{id}/export` streams one conversation as a file,
+//! reading messages from the database a page at a time as it writes, so no
+//! connection is held while the client reads. Markdown renders each message
+//! under a role heading with its sources as footnotes; titles are escaped so
+//! they can't inject formatting, and only http(s) source URLs become links.
+//! JSON has the same shape as `ChatMessage` in the conversation API.
+
+use axum::body::Bytes;
+use chrono::{DateTime, Utc};
+use futures::Stream;
+use serde::Deserialize;
+use sqlx::PgPool;
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+use super::types::{ChatMessage, MessageRole};
+
+/// Messages fetched per query
+const MESSAGE_PAGE_SIZE: i64 = 200;
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct ExportConversationQuery {
+    /// `markdown` (default) or `json`
+    #[serde(default)]
+    pub format: ConversationExportFormat,
+}
+
+/// Formats a conversation can be exported in
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ConversationExportFormat {
+    #[default]
+    Markdown,
+    Json,
+}
+
+impl ConversationExportFormat {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ConversationExportFormat::Markdown => "text/markdown; charset=utf-8",
+            ConversationExportFormat::Json => "application/json",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ConversationExportFormat::Markdown => "md",
+            ConversationExportFormat::Json => "json",
+        }
+    }
+}
+
+/// The conversation row an export starts from
+pub struct ExportedConversation {
+    pub id: Uuid,
+    pub title: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Stream the conversation's messages, oldest first, in the given format
+pub fn export_stream(
+    db: PgPool,
+    conversation: ExportedConversation,
+    format: ConversationExportFormat,
+) -> impl Stream<Item = std::io::Result<Bytes>> {
+    async_stream::try_stream! {
+        yield Bytes::from(match format {
+            ConversationExportFormat::Markdown => markdown_header(&conversation),
+            ConversationExportFormat::Json => json_header(&conversation)?,
+        });
+
+        let mut footnotes = 0;
+        let mut first = true;
+        let mut after: Option<(DateTime<Utc>, Uuid)> = None;
+        loop {
+            let (after_created_at, after_id) = after.unzip();
+            let page = sqlx::query!(
+                r#"
+                SELECT id, role, content, sources, created_at
+                FROM chat_messages
+                WHERE conversation_id = $1
+                  AND ($2::timestamptz IS NULL OR (created_at, id) > ($2, $3::uuid))
+                ORDER BY created_at ASC, id ASC
+                LIMIT $4
+                "#,
+                conversation.id,
+                after_created_at,
+                after_id,
+                MESSAGE_PAGE_SIZE
+            )
+            .fetch_all(&db)
+            .await
+            .map_err(std::io::Error::other)?;
+
+            let last_page = (page.len() as i64) < MESSAGE_PAGE_SIZE;
+            after = page.last().map(|row| (row.created_at, row.id));
+
+            for row in page {
+                let message = ChatMessage {
+                    id: row.id,
+                    role: MessageRole::from_db(&row.role),
+                    content: row.content,
+                    sources: serde_json::from_value(row.sources).unwrap_or_default(),
+                    created_at: row.created_at.timestamp(),
+                };
+                yield Bytes::from(match format {
+                    ConversationExportFormat::Markdown => {
+                        markdown_message(&message, &mut footnotes)
+                    }
+                    ConversationExportFormat::Json => json_message(&message, first)?,
+                });
+                first = false;
+            }
+
+            if last_page {
+                break;
+            }
+        }
+
+        if format == ConversationExportFormat::Json {
+            yield Bytes::from_static(b"]}\n");
+        }
+    }
+}
+
+fn markdown_header(conversation: &ExportedConversation) -> String {
+    let title = conversation.title.as_deref().unwrap_or("Untitled conversation");
+    format!(
+        "# {}\n\n_Started {}_\n",
+        escape_markdown(title),
+        conversation.created_at.format("%Y-%m-%d %H:%M UTC")
+    )
+}
+
+/// Escape text for use inline, e.g. in a heading or link text
+///
+/// Line breaks become spaces, so the text can't end its line and start a
+/// block of its own.
+fn escape_markdown(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' | '`' | '*' | '_' | '[' | ']' | '(' | ')' | '<' | '>' | '#' | '|' | '!' => {
+                out.push('\\');
+                out.push(c);
+            }
+            '\n' | '\r' => out.push(' '),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// A source URL as a link destination, or `None` if it shouldn't be linked
+///
+/// Only http(s) URLs are linked. The destination is wrapped in `<>`, with
+/// the characters that could end it percent-encoded.
+fn link_destination(url: &str) -> Option<String> {
+    let lower = url.to_ascii_lowercase();
+    if !lower.starts_with("https://") && !lower.starts_with("http://") {
+        return None;
+    }
+
+    let mut out = String::with_capacity(url.len() + 2);
+    out.push('<');
+    for c in url.chars() {
+        match c {
+            c if matches!(c, '<' | '>' | '\\' | ' ') || c.is_control() => {
+                out.push_str(&format!("%{:02X}", c as u32));
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('>');
+    Some(out)
+}
+
+/// Render a message under a role heading, numbering its sources from
+/// `footnotes` so numbers stay unique across the document
+fn markdown_message(message: &ChatMessage, footnotes: &mut usize) -> String {
+    let role = match message.role {
+        MessageRole::User => "User",
+        MessageRole::Assistant => "Assistant",
+        MessageRole::System => "System",
+    };
+    let mut out = format!("\n## {}\n\n{}", role, message.content.trim_end());
+
+    let first = *footnotes + 1;
+    *footnotes += message.sources.len();
+    for n in first..=*footnotes {
+        out.push_str(&format!("[^{}]", n));
+    }
+    out.push('\n');
+
+    for (n, source) in (first..).zip(&message.sources) {
+        let title = source.document_title.as_deref().unwrap_or(&source.document_id);
+        let title = escape_markdown(title);
+        match source.source_url.as_deref().and_then(link_destination) {
+            Some(url) => out.push_str(&format!("\n[^{}]: [{}]({})", n, title, url)),
+            None => out.push_str(&format!("\n[^{}]: {}", n, title)),
+        }
+    }
+    if !message.sources.is_empty() {
+        out.push('\n');
+    }
+    out
+}
+
+fn json_header(conversation: &ExportedConversation) -> std::io::Result<String> {
+    Ok(format!(
+        "{{\"id\":{},\"title\":{},\"created_at\":{},\"messages\":[",
+        serde_json::to_string(&conversation.id)?,
+        serde_json::to_string(&conversation.title)?,
+        conversation.created_at.timestamp()
+    ))
+}
+
+fn json_message(message: &ChatMessage, first: bool) -> std::io::Result<String> {
+    let json = serde_json::to_string(message)?;
+    Ok(if first { json } else { format!(",{}", json) })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chat::types::SourceChunk;
+
+    fn message(role: MessageRole, content: &str, sources: Vec<SourceChunk>) -> ChatMessage {
+        ChatMessage {
+            id: Uuid::new_v4(),
+            role,
+            content: content.to_string(),
+            sources,
+            created_at: 1_700_000_000,
+        }
+    }
+
+    fn source(title: Option<&str>, url: Option<&str>) -> SourceChunk {
+        SourceChunk {
+            chunk_id: "chunk".to_string(),
+            document_id: "doc-1".to_string(),
+            content: "excerpt".to_string(),
+            relevance_score: 0.9,
+            document_title: title.map(str::to_string),
+            source_url: url.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_markdown_numbers_footnotes_across_messages() {
+        let mut footnotes = 0;
+        let question = markdown_message(&message(MessageRole::User, "Hi?", vec![]), &mut footnotes);
+        assert_eq!(question, "\n## User\n\nHi?\n");
+
+        let first = message(
+            MessageRole::Assistant,
+            "Answer.\n",
+            vec![source(Some("Guide"), Some("https://example.com/guide")), source(None, None)],
+        );
+        assert_eq!(
+            markdown_message(&first, &mut footnotes),
+            "\n## Assistant\n\nAnswer.[^1][^2]\n\
+             \n[^1]: [Guide](<https://example.com/guide>)\
+             \n[^2]: doc-1\n"
+        );
+
+        let second = message(MessageRole::Assistant, "More.", vec![source(Some("FAQ"), None)]);
+        assert!(markdown_message(&second, &mut footnotes).contains("More.[^3]\n\n[^3]: FAQ\n"));
+    }
+
+    #[test]
+    fn test_markdown_escapes_titles_and_urls() {
+        let conversation = ExportedConversation {
+            id: Uuid::new_v4(),
+            title: Some("*Plans*\n# [click](https://evil.example)".to_string()),
+            created_at: Utc::now(),
+        };
+        assert!(markdown_header(&conversation).starts_with(
+            "# \\*Plans\\* \\# \\[click\\]\\(https://evil.example\\)\n"
+        ));
+
+        let mut footnotes = 0;
+        let sources = vec![
+            source(Some("a](b"), Some("https://example.com/a b>c")),
+            source(Some("Script"), Some("javascript:alert(1)")),
+        ];
+        let rendered =
+            markdown_message(&message(MessageRole::Assistant, "x", sources), &mut footnotes);
+        assert!(rendered.contains("[^1]: [a\\]\\(b](<https://example.com/a%20b%3Ec>)"));
+        assert!(rendered.contains("[^2]: Script\n"));
+    }
+
+    #[sqlx::test]
+    async fn test_export_pages_through_every_message(db: PgPool) {
+        // Owned by the Intelligence service, so not created by our migrations
+        sqlx::query(
+            r#"
+            CREATE TABLE chat_messages (
+                id UUID PRIMARY KEY,
+                conversation_id UUID NOT NULL,
+                role TEXT NOT NULL,
+                content TEXT NOT NULL,
+                sources JSONB NOT NULL DEFAULT '[]',
+                created_at TIMESTAMPTZ NOT NULL
+            )
+            "#,
+        )
+        .execute(&db)
+        .await
+        .unwrap();
+
+        // Pairs share a timestamp, so pages must also be ordered by ID
+        let conversation_id = Uuid::new_v4();
+        let count = MESSAGE_PAGE_SIZE * 2 + 1;
+        sqlx::query(
+            r#"
+            INSERT INTO chat_messages (id, conversation_id, role, content, created_at)
+            SELECT gen_random_uuid(), $1, 'user', n::text,
+                   TIMESTAMPTZ '2026-01-01' + (n / 2) * INTERVAL '1 second'
+            FROM generate_series(1, $2) AS n
+            "#,
+        )
+        .bind(conversation_id)
+        .bind(count)
+        .execute(&db)
+        .await
+        .unwrap();
+
+        let conversation = ExportedConversation {
+            id: conversation_id,
+            title: None,
+            created_at: Utc::now(),
+        };
+        let chunks: Vec<Bytes> = futures::TryStreamExt::try_collect(export_stream(
+            db,
+            conversation,
+            ConversationExportFormat::Json,
+        ))
+        .await
+        .unwrap();
+        let parsed: serde_json::Value = serde_json::from_slice(&chunks.concat()).unwrap();
+
+        let mut contents: Vec<i64> = parsed["messages"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|m| m["content"].as_str().unwrap().parse().unwrap())
+            .collect();
+        assert_eq!(contents.len() as i64, count);
+        contents.sort();
+        contents.dedup();
+        assert_eq!(contents, (1..=count).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_json_export_is_one_document() {
+        let conversation = ExportedConversation {
+            id: Uuid::new_v4(),
+            title: Some("Plans \"2026\"".to_string()),
+            created_at: Utc::now(),
+        };
+        let user = message(MessageRole::User, "Hi", vec![]);
+        let reply = message(MessageRole::Assistant, "Hello", vec![source(Some("Guide"), None)]);
+
+        let document = [
+            json_header(&conversation).unwrap(),
+            json_message(&user, true).unwrap(),
+            json_message(&reply, false).unwrap(),
+            "]}\n".to_string(),
+        ]
+        .concat();
+
+        let parsed: serde_json::Value = serde_json::from_str(&document).unwrap();
+        assert_eq!(parsed["title"], "Plans \"2026\"");
+        assert_eq!(parsed["messages"][0]["role"], "user");
+        assert_eq!(parsed["messages"][1]["sources"][0]["document_title"], "Guide");
+    }
+}
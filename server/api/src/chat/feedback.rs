@@ -0,0 +1,255 @@
+//! Thumbs up/down feedback on assistant messages
+//!
+//! Each user can rate a message once. Ratings are stored as `1`/`-1` in
+//! `message_feedback` and feed the admin feedback listing and stats.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use super::error::{ChatError, ChatResult};
+
+/// Maximum length of a feedback comment
+pub const MAX_FEEDBACK_COMMENT_LENGTH: usize = 2000;
+
+/// Default and maximum page size for the admin listing
+const DEFAULT_FEEDBACK_LIMIT: i64 = 50;
+const MAX_FEEDBACK_LIMIT: i64 = 200;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum FeedbackRating {
+    Positive,
+    Negative,
+}
+
+impl FeedbackRating {
+    pub fn as_i16(&self) -> i16 {
+        match self {
+            FeedbackRating::Positive => 1,
+            FeedbackRating::Negative => -1,
+        }
+    }
+
+    pub fn from_i16(value: i16) -> Self {
+        if value > 0 {
+            FeedbackRating::Positive
+        } else {
+            FeedbackRating::Negative
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct SubmitFeedbackRequest {
+    pub rating: FeedbackRating,
+    pub comment: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct MessageFeedback {
+    pub id: Uuid,
+    pub message_id: Uuid,
+    pub user_id: Uuid,
+    pub rating: FeedbackRating,
+    pub comment: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FeedbackQuery {
+    pub rating: Option<FeedbackRating>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FeedbackListResponse {
+    pub feedback: Vec<MessageFeedback>,
+    pub total_count: i64,
+    pub limit: i64,
+    pub offset: i64,
+}
+
+/// Record a user's rating of a message in one of their conversations
+///
+/// Returns `ChatError::FeedbackExists` if the user already rated the message.
+pub async fn submit_feedback(
+    db: &PgPool,
+    user_id: Uuid,
+    conversation_id: Uuid,
+    message_id: Uuid,
+    req: SubmitFeedbackRequest,
+) -> ChatResult<MessageFeedback> {
+    let comment = req
+        .comment
+        .map(|c| c.trim().to_string())
+        .filter(|c| !c.is_empty());
+    if comment
+        .as_ref()
+        .is_some_and(|c| c.chars().count() > MAX_FEEDBACK_COMMENT_LENGTH)
+    {
+        return Err(ChatError::InvalidMessage(format!(
+            "Feedback comment exceeds {} characters",
+            MAX_FEEDBACK_COMMENT_LENGTH
+        )));
+    }
+
+    let owned = sqlx::query_scalar!(
+        r#"
+        SELECT m.id
+        FROM chat_messages m
+        JOIN conversations c ON c.id = m.conversation_id
+        WHERE m.id = $1 AND m.conversation_id = $2 AND c.user_id = $3
+        "#,
+        message_id,
+        conversation_id,
+        user_id.to_string()
+    )
+    .fetch_optional(db)
+    .await?
+    .is_some();
+    if !owned {
+        return Err(ChatError::NotFound(format!("Message {}", message_id)));
+    }
+
+    let row = sqlx::query!(
+        r#"
+        INSERT INTO message_feedback (message_id, user_id, rating, comment)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT (message_id, user_id) DO NOTHING
+        RETURNING id, created_at
+        "#,
+        message_id,
+        user_id,
+        req.rating.as_i16(),
+        comment
+    )
+    .fetch_optional(db)
+    .await?
+    .ok_or(ChatError::FeedbackExists)?;
+
+    Ok(MessageFeedback {
+        id: row.id,
+        message_id,
+        user_id,
+        rating: req.rating,
+        comment,
+        created_at: row.created_at,
+    })
+}
+
+/// List feedback for admins, newest first
+pub async fn list_feedback(
+    db: &PgPool,
+    query: &FeedbackQuery,
+) -> Result<FeedbackListResponse, sqlx::Error> {
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_FEEDBACK_LIMIT)
+        .clamp(1, MAX_FEEDBACK_LIMIT);
+    let offset = query.offset.unwrap_or(0).max(0);
+    let rating = query.rating.map(|r| r.as_i16());
+
+    let rows = sqlx::query!(
+        r#"
+        SELECT id, message_id, user_id, rating, comment, created_at
+        FROM message_feedback
+        WHERE ($1::smallint IS NULL OR rating = $1)
+          AND ($2::timestamptz IS NULL OR created_at >= $2)
+          AND ($3::timestamptz IS NULL OR created_at < $3)
+        ORDER BY created_at DESC
+        LIMIT $4 OFFSET $5
+        "#,
+        rating,
+        query.from,
+        query.to,
+        limit,
+        offset
+    )
+    .fetch_all(db)
+    .await?;
+
+    let total_count = sqlx::query_scalar!(
+        r#"
+        SELECT COUNT(*) as "count!"
+        FROM message_feedback
+        WHERE ($1::smallint IS NULL OR rating = $1)
+          AND ($2::timestamptz IS NULL OR created_at >= $2)
+          AND ($3::timestamptz IS NULL OR created_at < $3)
+        "#,
+        rating,
+        query.from,
+        query.to
+    )
+    .fetch_one(db)
+    .await?;
+
+    Ok(FeedbackListResponse {
+        feedback: rows
+            .into_iter()
+            .map(|row| MessageFeedback {
+                id: row.id,
+                message_id: row.message_id,
+                user_id: row.user_id,
+                rating: FeedbackRating::from_i16(row.rating),
+                comment: row.comment,
+                created_at: row.created_at,
+            })
+            .collect(),
+        total_count,
+        limit,
+        offset,
+    })
+}
+
+/// Share of all feedback that is positive (0.0 when there is none)
+pub async fn positive_feedback_rate(db: &PgPool) -> Result<f32, sqlx::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT
+            COUNT(*) FILTER (WHERE rating > 0) as "positive!",
+            COUNT(*) as "total!"
+        FROM message_feedback
+        "#
+    )
+    .fetch_one(db)
+    .await?;
+
+    Ok(positive_rate(row.positive, row.total))
+}
+
+fn positive_rate(positive: i64, total: i64) -> f32 {
+    if total == 0 {
+        0.0
+    } else {
+        positive as f32 / total as f32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rating_roundtrip() {
+        for rating in [FeedbackRating::Positive, FeedbackRating::Negative] {
+            assert_eq!(FeedbackRating::from_i16(rating.as_i16()), rating);
+        }
+
+        let req: SubmitFeedbackRequest = serde_json::from_str(r#"{"rating": "negative"}"#).unwrap();
+        assert_eq!(req.rating, FeedbackRating::Negative);
+        assert!(serde_json::from_str::<SubmitFeedbackRequest>(r#"{"rating": "meh"}"#).is_err());
+    }
+
+    #[test]
+    fn test_positive_rate() {
+        assert_eq!(positive_rate(0, 0), 0.0);
+        assert_eq!(positive_rate(3, 4), 0.75);
+        assert_eq!(positive_rate(0, 5), 0.0);
+    }
+}
@@ -1,7 +1,10 @@
 use axum::{
     Json,
+    body::Body,
     extract::{Extension, Path, Query, State},
+    http::{HeaderMap, HeaderValue, header},
     response::sse::{Event, KeepAlive, Sse},
+    response::{IntoResponse, Response},
 };
 use futures::Stream;
 use std::convert::Infallible;
@@ -9,8 +12,22 @@ use std::convert::Infallible;
 use uuid::Uuid;
 
 use super::error::{ChatError, ChatResult};
+use super::export::{self, ExportConversationQuery, ExportedConversation};
+use super::feedback::{self, MessageFeedback, SubmitFeedbackRequest};
+use super::history;
+use super::idempotency::{self, KeyClaim};
+use super::pending::{self, PendingMessage, QueuedMessageResponse};
+use super::quota;
+use super::share::{self, CreateShareRequest, RevokeShareResponse, ShareLink, SharedConversation};
+use super::tags::{self, TagListResponse};
 use super::types::*;
+use crate::common::pagination;
+use crate::config::env::MissingMetricsMode;
 use crate::gateway::AppState;
+use crate::gateway::openapi::ErrorBody;
+use crate::grpc::client::{MAX_TIMEOUT_OVERRIDE, timeout_override};
+use crate::middleware::{RequestId, request_id};
+use crate::user::preferences;
 
 // ============================================================================
 // CONVERSATION MANAGEMENT
@@ -18,24 +35,49 @@ use crate::gateway::AppState;
 
 /// Create a new conversation
 /// POST /chat/conversations
+#[utoipa::path(
+    post,
+    path = "/chat/conversations",
+    tag = "chat",
+    request_body = CreateConversationRequest,
+    responses(
+        (status = 200, description = "Conversation created", body = ConversationResponse),
+        (status = 400, description = "Invalid tags", body = ErrorBody),
+    ),
+    security(("bearer_auth" = []))
+)]
+#[tracing::instrument(
+    skip_all,
+    fields(
+        %user_id,
+        conversation_id = tracing::field::Empty,
+        request_id = %RequestId::current().unwrap_or_default()
+    )
+)]
 pub async fn create_conversation(
     State(state): State<AppState>,
     Extension(user_id): Extension<Uuid>,
     Json(req): Json<CreateConversationRequest>,
 ) -> ChatResult<Json<ConversationResponse>> {
     let conversation_id = Uuid::new_v4();
+    tracing::Span::current().record(
+        "conversation_id",
+        tracing::field::display(conversation_id),
+    );
     let metadata = req.metadata;
+    let tags = tags::normalize_tags(&req.tags)?;
 
     let row = sqlx::query!(
         r#"
-        INSERT INTO conversations (id, user_id, title, metadata)
-        VALUES ($1, $2, $3, $4)
-        RETURNING id, user_id, title, metadata, created_at, updated_at
+        INSERT INTO conversations (id, user_id, title, metadata, tags)
+        VALUES ($1, $2, $3, $4, $5)
+        RETURNING id, user_id, title, metadata, tags, created_at, updated_at
         "#,
         conversation_id,
         user_id.to_string(),
         req.title,
-        metadata
+        metadata,
+        &tags
     )
     .fetch_one(&state.db)
     .await
@@ -46,6 +88,7 @@ pub async fn create_conversation(
         user_id: row.user_id,
         title: row.title,
         message_count: 0,
+        tags: row.tags,
         created_at: row.created_at.timestamp(),
         updated_at: row.updated_at.timestamp(),
     }))
@@ -53,10 +96,34 @@ pub async fn create_conversation(
 
 /// Get conversation with messages
 /// GET /chat/conversations/{id}
+#[utoipa::path(
+    get,
+    path = "/chat/conversations/{id}",
+    tag = "chat",
+    params(
+        ("id" = Uuid, Path, description = "Conversation ID"),
+        ConversationQuery,
+    ),
+    responses(
+        (
+            status = 200,
+            description = "Conversation with a page of messages",
+            body = ConversationWithMessages
+        ),
+        (status = 400, description = "Invalid `before` cursor", body = ErrorBody),
+        (status = 404, description = "Conversation not found", body = ErrorBody),
+    ),
+    security(("bearer_auth" = []))
+)]
+#[tracing::instrument(
+    skip_all,
+    fields(%user_id, %conversation_id, request_id = %RequestId::current().unwrap_or_default())
+)]
 pub async fn get_conversation(
     State(state): State<AppState>,
     Extension(user_id): Extension<Uuid>,
     Path(conversation_id): Path<Uuid>,
+    Query(params): Query<ConversationQuery>,
 ) -> ChatResult<Json<ConversationWithMessages>> {
     // Check ownership and existence
     let conversation = sqlx::query!(
@@ -73,30 +140,60 @@ pub async fn get_conversation(
     .map_err(|e| ChatError::DatabaseError(e.to_string()))?
     .ok_or(ChatError::ConversationNotFound(conversation_id.to_string()))?;
 
-    // Fetch messages
+    let limit = params.limit();
+    // Keyset on (created_at, id); the nil id sorts first, so a bare timestamp
+    // excludes every message sent at that instant
+    let (before_at, before_id) = match params.cursor()? {
+        None => (None, Uuid::nil()),
+        Some(MessageCursor::Timestamp(at)) => (Some(at), Uuid::nil()),
+        Some(MessageCursor::Message(id)) => {
+            let at = sqlx::query_scalar!(
+                "SELECT created_at FROM chat_messages WHERE id = $1 AND conversation_id = $2",
+                id,
+                conversation_id
+            )
+            .fetch_optional(&state.db)
+            .await
+            .map_err(|e| ChatError::DatabaseError(e.to_string()))?
+            .ok_or(ChatError::InvalidCursor)?;
+            (Some(at), id)
+        }
+    };
+
+    // Fetch the newest page, plus one row to tell whether there is more
     // Note: Python Intelligence service persists to 'chat_messages'
-    let messages = sqlx::query!(
+    let mut messages = sqlx::query!(
         r#"
         SELECT id, role::text as "role!", content, sources, metadata, created_at
         FROM chat_messages
         WHERE conversation_id = $1
-        ORDER BY created_at ASC
+          AND ($2::timestamptz IS NULL OR (created_at, id) < ($2, $3))
+        ORDER BY created_at DESC, id DESC
+        LIMIT $4
         "#,
-        conversation_id
+        conversation_id,
+        before_at,
+        before_id,
+        limit + 1
     )
     .fetch_all(&state.db)
     .await
     .map_err(|e| ChatError::DatabaseError(e.to_string()))?;
 
+    let has_more = messages.len() as i64 > limit;
+    messages.truncate(limit as usize);
+    let next_before = if has_more {
+        messages.last().map(|msg| msg.id.to_string())
+    } else {
+        None
+    };
+
     let response_messages = messages
         .into_iter()
+        .rev()
         .map(|msg| ChatMessage {
             id: msg.id,
-            role: match msg.role.as_str() {
-                "user" => MessageRole::User,
-                "assistant" => MessageRole::Assistant,
-                _ => MessageRole::System,
-            },
+            role: MessageRole::from_db(&msg.role),
             content: msg.content,
             created_at: msg.created_at.timestamp(),
             sources: serde_json::from_value(msg.sources).unwrap_or_default(),
@@ -107,6 +204,8 @@ pub async fn get_conversation(
         id: conversation.id,
         title: conversation.title,
         messages: response_messages,
+        has_more,
+        next_before,
         created_at: conversation.created_at.timestamp(),
         updated_at: conversation.updated_at.timestamp(),
     }))
@@ -114,50 +213,84 @@ pub async fn get_conversation(
 
 /// List user's conversations with pagination
 /// GET /chat/conversations?limit=20&cursor=abc
+///
+/// Pages by position rather than offset: `next_cursor` marks the last
+/// conversation returned, so conversations updated while a client pages
+/// through the list are not repeated. Cursors from the old offset scheme
+/// are rejected. `total_count` is only computed with `include_total=true`.
+/// `tag` narrows the list to conversations with that tag.
+#[utoipa::path(
+    get,
+    path = "/chat/conversations",
+    tag = "chat",
+    params(ListConversationsQuery),
+    responses(
+        (
+            status = 200,
+            description = "Conversations, most recently updated first",
+            body = ConversationListResponse
+        ),
+        (status = 400, description = "Invalid cursor", body = ErrorBody),
+    ),
+    security(("bearer_auth" = []))
+)]
+#[tracing::instrument(
+    skip_all,
+    fields(%user_id, request_id = %RequestId::current().unwrap_or_default())
+)]
 pub async fn list_conversations(
     State(state): State<AppState>,
     Extension(user_id): Extension<Uuid>,
     Query(params): Query<ListConversationsQuery>,
 ) -> ChatResult<Json<ConversationListResponse>> {
     let limit = params.limit.min(50) as i64;
-    let offset = params
-        .cursor
-        .and_then(|c| c.parse::<i64>().ok())
-        .unwrap_or(0);
+    let cursor_secret = &state.config.security.cursor_signing_secret;
+    let after = pagination::decode_keyset_cursor(cursor_secret, params.cursor.as_deref())?;
+    let (after_updated_at, after_id) = after.unzip();
+    let tag = params.tag.as_deref().map(tags::normalize_tag).transpose()?;
 
     let conversations = sqlx::query!(
         r#"
-        SELECT c.id, c.title, c.created_at, c.updated_at,
+        SELECT c.id, c.title, c.tags, c.created_at, c.updated_at,
                (SELECT COUNT(*) FROM chat_messages m WHERE m.conversation_id = c.id) as "message_count!",
                (SELECT content FROM chat_messages m WHERE m.conversation_id = c.id ORDER BY created_at DESC LIMIT 1) as "last_message_preview"
         FROM conversations c
         WHERE c.user_id = $1
-        ORDER BY c.updated_at DESC
-        LIMIT $2 OFFSET $3
+          AND ($3::timestamptz IS NULL OR (c.updated_at, c.id) < ($3, $4::uuid))
+          AND ($5::text IS NULL OR $5 = ANY(c.tags))
+        ORDER BY c.updated_at DESC, c.id DESC
+        LIMIT $2
         "#,
         user_id.to_string(),
         limit,
-        offset
+        after_updated_at,
+        after_id,
+        tag
     )
     .fetch_all(&state.db)
     .await
     .map_err(|e| ChatError::DatabaseError(e.to_string()))?;
 
-    let total_count = sqlx::query!(
-        r#"
-        SELECT COUNT(*) as count
-        FROM conversations
-        WHERE user_id = $1
-        "#,
-        user_id.to_string()
-    )
-    .fetch_one(&state.db)
-    .await
-    .map_err(|e| ChatError::DatabaseError(e.to_string()))?
-    .count
-    .unwrap_or(0) as i32;
+    let total_count = if params.include_total {
+        let count = sqlx::query_scalar!(
+            r#"
+            SELECT COUNT(*) as "count!"
+            FROM conversations
+            WHERE user_id = $1 AND ($2::text IS NULL OR $2 = ANY(tags))
+            "#,
+            user_id.to_string(),
+            tag
+        )
+        .fetch_one(&state.db)
+        .await
+        .map_err(|e| ChatError::DatabaseError(e.to_string()))?;
+        Some(count as i32)
+    } else {
+        None
+    };
 
     let loaded_count = conversations.len() as i64;
+    let last = conversations.last().map(|row| (row.updated_at, row.id));
 
     let response_conversations = conversations
         .into_iter()
@@ -166,16 +299,15 @@ pub async fn list_conversations(
             title: row.title,
             message_count: row.message_count as i32,
             last_message_preview: row.last_message_preview,
+            tags: row.tags,
             created_at: row.created_at.timestamp(),
             updated_at: row.updated_at.timestamp(),
         })
         .collect();
 
-    let next_cursor = if loaded_count < limit {
-        None
-    } else {
-        Some((offset + limit).to_string())
-    };
+    let next_cursor = last
+        .filter(|_| loaded_count == limit)
+        .map(|(updated_at, id)| pagination::encode_keyset_cursor(cursor_secret, updated_at, id));
 
     Ok(Json(ConversationListResponse {
         conversations: response_conversations,
@@ -184,25 +316,70 @@ pub async fn list_conversations(
     }))
 }
 
+/// List the user's conversation tags with how many conversations use each
+/// GET /chat/tags
+#[utoipa::path(
+    get,
+    path = "/chat/tags",
+    tag = "chat",
+    responses(
+        (status = 200, description = "Tags, most used first", body = TagListResponse),
+    ),
+    security(("bearer_auth" = []))
+)]
+#[tracing::instrument(
+    skip_all,
+    fields(%user_id, request_id = %RequestId::current().unwrap_or_default())
+)]
+pub async fn list_tags(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+) -> ChatResult<Json<TagListResponse>> {
+    Ok(Json(tags::list_tags(&state.db, user_id).await?))
+}
+
 /// Update conversation metadata (title, tags, etc.)
 /// PATCH /chat/conversations/{id}
+///
+/// `tags` replaces the whole set, which is how tags are renamed or removed.
+#[utoipa::path(
+    patch,
+    path = "/chat/conversations/{id}",
+    tag = "chat",
+    params(("id" = Uuid, Path, description = "Conversation ID")),
+    request_body = UpdateConversationRequest,
+    responses(
+        (status = 200, description = "Conversation updated", body = ConversationResponse),
+        (status = 400, description = "Invalid tags", body = ErrorBody),
+        (status = 404, description = "Conversation not found", body = ErrorBody),
+    ),
+    security(("bearer_auth" = []))
+)]
+#[tracing::instrument(
+    skip_all,
+    fields(%user_id, %conversation_id, request_id = %RequestId::current().unwrap_or_default())
+)]
 pub async fn update_conversation(
     State(state): State<AppState>,
     Extension(user_id): Extension<Uuid>,
     Path(conversation_id): Path<Uuid>,
     Json(req): Json<UpdateConversationRequest>,
 ) -> ChatResult<Json<ConversationResponse>> {
+    let tags = req.tags.as_deref().map(tags::normalize_tags).transpose()?;
+
     let conversation = sqlx::query!(
         r#"
         UPDATE conversations
         SET title = COALESCE($3, title),
+            tags = COALESCE($4, tags),
             updated_at = NOW()
         WHERE id = $1 AND user_id = $2
-        RETURNING id, user_id, title, metadata, created_at, updated_at
+        RETURNING id, user_id, title, metadata, tags, created_at, updated_at
         "#,
         conversation_id,
         user_id.to_string(),
-        req.title
+        req.title,
+        tags.as_deref()
     )
     .fetch_optional(&state.db)
     .await
@@ -224,6 +401,7 @@ pub async fn update_conversation(
         user_id: conversation.user_id,
         title: conversation.title,
         message_count: message_count as i32,
+        tags: conversation.tags,
         created_at: conversation.created_at.timestamp(),
         updated_at: conversation.updated_at.timestamp(),
     }))
@@ -231,6 +409,25 @@ pub async fn update_conversation(
 
 /// Delete conversation
 /// DELETE /chat/conversations/{id}
+#[utoipa::path(
+    delete,
+    path = "/chat/conversations/{id}",
+    tag = "chat",
+    params(("id" = Uuid, Path, description = "Conversation ID")),
+    responses(
+        (
+            status = 200,
+            description = "Conversation and its messages deleted",
+            body = DeleteConversationResponse
+        ),
+        (status = 404, description = "Conversation not found", body = ErrorBody),
+    ),
+    security(("bearer_auth" = []))
+)]
+#[tracing::instrument(
+    skip_all,
+    fields(%user_id, %conversation_id, request_id = %RequestId::current().unwrap_or_default())
+)]
 pub async fn delete_conversation(
     State(state): State<AppState>,
     Extension(user_id): Extension<Uuid>,
@@ -281,6 +478,22 @@ pub async fn delete_conversation(
 
 /// Generate conversation title using AI
 /// POST /chat/conversations/{id}/generate-title
+#[utoipa::path(
+    post,
+    path = "/chat/conversations/{id}/generate-title",
+    tag = "chat",
+    params(("id" = Uuid, Path, description = "Conversation ID")),
+    request_body = GenerateTitleRequest,
+    responses(
+        (status = 200, description = "Generated title", body = GenerateTitleResponse),
+        (status = 404, description = "Conversation not found", body = ErrorBody),
+    ),
+    security(("bearer_auth" = []))
+)]
+#[tracing::instrument(
+    skip_all,
+    fields(%user_id, %conversation_id, request_id = %RequestId::current().unwrap_or_default())
+)]
 pub async fn generate_conversation_title(
     State(state): State<AppState>,
     Extension(user_id): Extension<Uuid>,
@@ -333,79 +546,431 @@ pub async fn generate_conversation_title(
 /// 
 /// NOTE: Message persistence is handled by the Intelligence service to avoid
 /// dual storage and data inconsistency. The API only validates and forwards.
+///
+/// With `CHAT_QUEUE_ON_OUTAGE` set, a message that can't reach the service is
+/// stored as pending and answered with `202 Accepted` instead of a 503.
+///
+/// An `X-Timeout-Ms` header overrides the Intelligence call's deadline.
+///
+/// With an `Idempotency-Key` header, a retry within 24 hours of a successful
+/// send to the same conversation gets the original reply back instead of
+/// sending the message again; a retry while the first is in flight gets 409.
+#[utoipa::path(
+    post,
+    path = "/chat/conversations/{id}/messages",
+    tag = "chat",
+    params(
+        ("id" = Uuid, Path, description = "Conversation ID"),
+        (
+            "Idempotency-Key" = Option<Uuid>,
+            Header,
+            description = "Replays the stored reply when retried within 24 hours"
+        ),
+    ),
+    request_body = SendMessageRequest,
+    responses(
+        (status = 200, description = "Assistant response", body = MessageResponse),
+        (
+            status = 202,
+            description = "Queued while the Intelligence service is unreachable",
+            body = QueuedMessageResponse
+        ),
+        (status = 400, description = "Invalid message", body = ErrorBody),
+        (status = 402, description = "Monthly token budget used up", body = ErrorBody),
+        (status = 404, description = "Conversation not found", body = ErrorBody),
+        (status = 409, description = "Idempotency-Key still in use", body = ErrorBody),
+        (status = 429, description = "Daily token quota used up", body = ErrorBody),
+        (status = 503, description = "Intelligence service unavailable", body = ErrorBody),
+    ),
+    security(("bearer_auth" = []))
+)]
+#[tracing::instrument(
+    skip_all,
+    fields(%user_id, %conversation_id, request_id = %RequestId::current().unwrap_or_default())
+)]
 pub async fn send_message(
     State(state): State<AppState>,
     Extension(user_id): Extension<Uuid>,
     Path(conversation_id): Path<Uuid>,
+    headers: HeaderMap,
     Json(req): Json<SendMessageRequest>,
-) -> ChatResult<Json<MessageResponse>> {
-    // Validate message length
-    if req.message.is_empty() {
-        return Err(ChatError::InvalidMessage(
-            "Message cannot be empty".to_string(),
-        ));
-    }
-    if req.message.len() > 10000 {
-        return Err(ChatError::MessageTooLong(req.message.len(), 10000));
-    }
+) -> ChatResult<Response> {
+    validate_message(&req.message)?;
 
     // Verify conversation exists and belongs to user before forwarding to Intelligence
-    let conversation_exists = sqlx::query!(
-        r#"SELECT id FROM conversations WHERE id = $1 AND user_id = $2"#,
+    let conversation = sqlx::query!(
+        r#"SELECT title FROM conversations WHERE id = $1 AND user_id = $2"#,
         conversation_id,
         user_id.to_string()
     )
     .fetch_optional(&state.db)
     .await
     .map_err(|e| ChatError::DatabaseError(e.to_string()))?
-    .is_some();
+    .ok_or_else(|| ChatError::ConversationNotFound(conversation_id.to_string()))?;
 
-    if !conversation_exists {
-        return Err(ChatError::ConversationNotFound(conversation_id.to_string()));
+    // A retry of a send that already went through gets the same reply back;
+    // claiming the key first keeps a concurrent retry from sending it twice
+    let idempotency_key = idempotency::idempotency_key(&headers)?;
+    if let Some(key) = idempotency_key
+        && let KeyClaim::Replay(cached) =
+            idempotency::claim_key(&state.db, user_id, conversation_id, key).await?
+    {
+        // The message was delivered, so a copy queued during an outage is done
+        if let Some(pending_id) = req.pending_id {
+            clear_pending(&state.db, user_id, pending_id).await;
+        }
+        return Ok((
+            [(idempotency::REPLAYED_HEADER, "true")],
+            Json(cached),
+        )
+            .into_response());
+    }
+
+    // Sent from its own task, so a client that disconnects mid-send doesn't
+    // leave the key claimed without a reply
+    let delivery = {
+        let (state, headers, req) = (state.clone(), headers.clone(), req.clone());
+        request_id::spawn(async move {
+            let delivery =
+                deliver_message(&state, user_id, conversation_id, &headers, &req).await;
+            if let Some(key) = idempotency_key {
+                settle_key(&state.db, user_id, conversation_id, key, &delivery).await;
+            }
+            delivery
+        })
+        .await
+        .map_err(|e| ChatError::InternalError(format!("Message send task failed: {}", e)))?
+    };
+
+    let reply = match delivery? {
+        Delivery::Replied(reply) => reply,
+        Delivery::Queued(queued) => return Ok(queued.into_response()),
+    };
+
+    // Untitled conversations get a title from the first exchange, off the request path
+    if conversation.title.is_none() {
+        spawn_title_generation(&state, conversation_id, req.message, reply.content.clone());
+    }
+
+    // NOTE: Message persistence is handled by the Intelligence service
+    // We only return the response to the client without local storage
+
+    Ok(Json(reply).into_response())
+}
+
+/// Store the reply under a claimed idempotency key, or release the key
+async fn settle_key(
+    db: &sqlx::PgPool,
+    user_id: Uuid,
+    conversation_id: Uuid,
+    key: Uuid,
+    delivery: &ChatResult<Delivery>,
+) {
+    match delivery {
+        // The message was sent either way; a failed store only costs a replay
+        Ok(Delivery::Replied(reply)) => {
+            let stored =
+                idempotency::store_response(db, user_id, conversation_id, key, reply).await;
+            if let Err(e) = stored {
+                tracing::error!(idempotency_key = %key, "Failed to store reply: {}", e);
+            }
+        }
+        // Nothing was answered, so a retry should try again
+        Ok(Delivery::Queued(_)) | Err(_) => {
+            idempotency::release_key(db, user_id, conversation_id, key).await;
+        }
     }
+}
+
+/// What became of a sent message
+enum Delivery {
+    Replied(MessageResponse),
+    /// Stored as pending during an Intelligence outage
+    Queued(QueuedMessageResponse),
+}
+
+/// Send a message to the Intelligence service, queueing it during an outage
+async fn deliver_message(
+    state: &AppState,
+    user_id: Uuid,
+    conversation_id: Uuid,
+    headers: &HeaderMap,
+    req: &SendMessageRequest,
+) -> ChatResult<Delivery> {
+    // Refuse before spending tokens if today's quota or this month's budget is used up
+    quota::enforce_daily_quota(&state.db, user_id).await?;
+    quota::enforce_monthly_budget(&state.db, user_id, state.config.chat.monthly_token_budget)
+        .await?;
+
+    let config = resolve_chat_config(state, user_id, req.config.clone()).await?;
 
     // Call Python intelligence service via gRPC
     // Intelligence service handles message persistence (single source of truth)
     let mut client = state.intelligence_client.clone();
 
-    let grpc_req = crate::grpc::proto::opentier::intelligence::v1::ChatRequest {
-        user_id: user_id.to_string(),
-        conversation_id: conversation_id.to_string(),
-        message: req.message.clone(),
-        metadata: std::collections::HashMap::new(),
-        config: req.config.as_ref().map(|c| {
-            crate::grpc::proto::opentier::intelligence::v1::ChatConfig {
-                temperature: c.temperature,
-                max_tokens: c.max_tokens,
-                use_rag: Some(c.use_rag),
-                model: c.model.clone(),
-                context_limit: None,
+    let grpc_req = chat_request(
+        user_id,
+        conversation_id,
+        req.message.clone(),
+        config.as_ref(),
+        std::collections::HashMap::new(),
+    );
+
+    let timeout = timeout_override(headers, MAX_TIMEOUT_OVERRIDE);
+    let response = match client.send_message(grpc_req, timeout).await {
+        Ok(response) => response.into_inner(),
+        Err(status) if state.config.chat.queue_on_outage && pending::is_outage(&status) => {
+            tracing::warn!(
+                conversation_id = %conversation_id,
+                "Intelligence service unavailable, queueing message: {}",
+                status
+            );
+            let queued = match req.pending_id {
+                Some(pending_id) => {
+                    pending::find_pending(&state.db, user_id, conversation_id, pending_id).await?
+                }
+                None => None,
+            };
+            let queued = match queued {
+                // A retry of an already queued message keeps its original entry
+                Some(pending) => QueuedMessageResponse::from(pending),
+                None => pending::store_pending(
+                    &state.db,
+                    user_id,
+                    conversation_id,
+                    &req.message,
+                    req.config.as_ref(),
+                )
+                .await?
+                .into(),
+            };
+            return Ok(Delivery::Queued(queued));
+        }
+        Err(status) => return Err(status.into()),
+    };
+
+    if let Some(pending_id) = req.pending_id {
+        clear_pending(&state.db, user_id, pending_id).await;
+    }
+
+    let reply = message_response(state, user_id, conversation_id, response).await?;
+    Ok(Delivery::Replied(reply))
+}
+
+/// Remove a pending message that has been delivered
+///
+/// The message already went through, so a failure is only logged.
+async fn clear_pending(db: &sqlx::PgPool, user_id: Uuid, pending_id: Uuid) {
+    if let Err(e) = pending::delete_pending(db, user_id, pending_id).await {
+        tracing::error!(
+            pending_id = %pending_id,
+            "Failed to clear delivered pending message: {}",
+            e
+        );
+    }
+}
+
+/// Edit a user message and regenerate the conversation from it
+/// PUT /chat/conversations/{id}/messages/{message_id}
+///
+/// The message and everything after it are deleted, then the new content goes
+/// through the same Intelligence call as `send_message`, which stores it under
+/// a new ID along with the new reply. If that call fails, the deleted messages
+/// are put back.
+#[utoipa::path(
+    put,
+    path = "/chat/conversations/{id}/messages/{message_id}",
+    tag = "chat",
+    params(
+        ("id" = Uuid, Path, description = "Conversation ID"),
+        ("message_id" = Uuid, Path, description = "ID of the user message to replace"),
+    ),
+    request_body = EditMessageRequest,
+    responses(
+        (status = 200, description = "New assistant response", body = MessageResponse),
+        (status = 400, description = "Invalid message, or not a user message", body = ErrorBody),
+        (status = 402, description = "Monthly token budget used up", body = ErrorBody),
+        (status = 404, description = "Conversation or message not found", body = ErrorBody),
+        (status = 409, description = "A response is still streaming", body = ErrorBody),
+        (status = 429, description = "Daily token quota used up", body = ErrorBody),
+    ),
+    security(("bearer_auth" = []))
+)]
+#[tracing::instrument(
+    skip_all,
+    fields(
+        %user_id,
+        %conversation_id,
+        %message_id,
+        request_id = %RequestId::current().unwrap_or_default()
+    )
+)]
+pub async fn edit_message(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Path((conversation_id, message_id)): Path<(Uuid, Uuid)>,
+    headers: HeaderMap,
+    Json(req): Json<EditMessageRequest>,
+) -> ChatResult<Json<MessageResponse>> {
+    validate_message(&req.message)?;
+
+    let conversation = sqlx::query!(
+        r#"SELECT title FROM conversations WHERE id = $1 AND user_id = $2"#,
+        conversation_id,
+        user_id.to_string()
+    )
+    .fetch_optional(&state.db)
+    .await?
+    .ok_or_else(|| ChatError::ConversationNotFound(conversation_id.to_string()))?;
+
+    // Truncating underneath a stream would leave its reply after the new one
+    if state.active_streams.is_streaming(conversation_id) {
+        return Err(ChatError::StreamInProgress);
+    }
+
+    let target = sqlx::query!(
+        "SELECT role, created_at FROM chat_messages WHERE id = $1 AND conversation_id = $2",
+        message_id,
+        conversation_id
+    )
+    .fetch_optional(&state.db)
+    .await?
+    .ok_or_else(|| ChatError::NotFound(format!("Message {}", message_id)))?;
+
+    if target.role != "user" {
+        return Err(ChatError::InvalidMessage(
+            "Only user messages can be edited".to_string(),
+        ));
+    }
+
+    quota::enforce_daily_quota(&state.db, user_id).await?;
+    quota::enforce_monthly_budget(&state.db, user_id, state.config.chat.monthly_token_budget)
+        .await?;
+
+    let config = resolve_chat_config(&state, user_id, req.config).await?;
+    let removed = history::truncate_from(&state.db, conversation_id, target.created_at).await?;
+
+    // The Intelligence service cuts its own history at the same message
+    let metadata = std::collections::HashMap::from([(
+        "edited_message_id".to_string(),
+        message_id.to_string(),
+    )]);
+    let grpc_req = chat_request(
+        user_id,
+        conversation_id,
+        req.message.clone(),
+        config.as_ref(),
+        metadata,
+    );
+
+    let mut client = state.intelligence_client.clone();
+    let timeout = timeout_override(&headers, MAX_TIMEOUT_OVERRIDE);
+    let response = match client.send_message(grpc_req, timeout).await {
+        Ok(response) => response.into_inner(),
+        Err(status) => {
+            if let Err(e) = history::restore(&state.db, conversation_id, &removed).await {
+                tracing::error!(
+                    conversation_id = %conversation_id,
+                    "Failed to restore messages after a failed edit: {}",
+                    e
+                );
             }
-        }),
+            return Err(status.into());
+        }
     };
 
-    let response = client.send_message(grpc_req).await?.into_inner();
+    let reply = message_response(&state, user_id, conversation_id, response).await?;
 
+    if conversation.title.is_none() {
+        spawn_title_generation(&state, conversation_id, req.message, reply.content.clone());
+    }
+
+    Ok(Json(reply))
+}
+
+/// Check a message's length before it is sent
+fn validate_message(message: &str) -> ChatResult<()> {
+    if message.is_empty() {
+        return Err(ChatError::InvalidMessage(
+            "Message cannot be empty".to_string(),
+        ));
+    }
+    if message.len() > 10000 {
+        return Err(ChatError::MessageTooLong(message.len(), 10000));
+    }
+    Ok(())
+}
+
+/// Fill settings the request leaves out from the user's saved default, if any
+async fn resolve_chat_config(
+    state: &AppState,
+    user_id: Uuid,
+    config: Option<ChatConfig>,
+) -> ChatResult<Option<ChatConfig>> {
+    let defaults = preferences::default_chat_config(&state.db, user_id).await?;
+    Ok(match config {
+        Some(config) => Some(config.with_defaults(defaults.as_ref())),
+        None => defaults,
+    })
+}
+
+/// Build the Intelligence request for a user message
+fn chat_request(
+    user_id: Uuid,
+    conversation_id: Uuid,
+    message: String,
+    config: Option<&ChatConfig>,
+    metadata: std::collections::HashMap<String, String>,
+) -> crate::grpc::proto::opentier::intelligence::v1::ChatRequest {
+    crate::grpc::proto::opentier::intelligence::v1::ChatRequest {
+        user_id: user_id.to_string(),
+        conversation_id: conversation_id.to_string(),
+        message,
+        metadata,
+        config: config.map(|c| crate::grpc::proto::opentier::intelligence::v1::ChatConfig {
+            temperature: c.temperature,
+            max_tokens: c.max_tokens,
+            use_rag: Some(c.use_rag),
+            model: c.model.clone(),
+            context_limit: None,
+        }),
+    }
+}
+
+/// Convert the Intelligence reply into a `MessageResponse`, recording token usage
+async fn message_response(
+    state: &AppState,
+    user_id: Uuid,
+    conversation_id: Uuid,
+    response: crate::grpc::proto::opentier::intelligence::v1::ChatResponse,
+) -> ChatResult<MessageResponse> {
     // Parse response
     let message_id = Uuid::parse_str(&response.message_id)
         .map_err(|e| ChatError::InternalError(format!("Invalid message ID: {}", e)))?;
 
-    // Extract metrics from nested structure with warning if missing
-    let metrics = match response.metrics {
-        Some(m) => m,
-        None => {
-            tracing::warn!(
-                conversation_id = %response.conversation_id,
-                message_id = %response.message_id,
-                "Chat response missing metrics from Intelligence service"
-            );
-            Default::default()
-        }
-    };
-
     // Calculate sources_retrieved before moving sources
     let sources_count = response.sources.len() as i32;
 
+    if response.metrics.is_none() {
+        tracing::warn!(
+            conversation_id = %response.conversation_id,
+            message_id = %response.message_id,
+            "Chat response missing metrics from Intelligence service"
+        );
+    }
+    let metrics = resolve_metrics(
+        response.metrics,
+        sources_count,
+        state.config.chat.missing_metrics,
+    )?;
+
+    if let Some(m) = &metrics {
+        // The response has already been generated; never fail it over accounting
+        if let Err(e) = quota::record_token_usage(&state.db, user_id, m.tokens_used as i64).await {
+            tracing::error!(user_id = %user_id, "Failed to record token usage: {}", e);
+        }
+    }
+
     // Convert to SourceChunk (map all fields from proto ContextChunk)
     let source_chunks: Vec<SourceChunk> = response
         .sources
@@ -420,23 +985,307 @@ pub async fn send_message(
         })
         .collect();
 
-    // NOTE: Message persistence is handled by the Intelligence service
-    // We only return the response to the client without local storage
-
-    Ok(Json(MessageResponse {
+    Ok(MessageResponse {
         message_id,
         conversation_id,
         role: MessageRole::Assistant,
         content: response.response,
         sources: source_chunks,
-        metrics: ChatMetrics {
-            tokens_used: metrics.tokens_used,
-            context_tokens: metrics.prompt_tokens,
-            response_tokens: metrics.completion_tokens,
-            latency_ms: metrics.latency_ms,
-            sources_retrieved: sources_count,
-        },
+        metrics,
         created_at: response.created_at,
+    })
+}
+
+/// List messages queued during an Intelligence outage
+/// GET /chat/conversations/{id}/pending
+#[utoipa::path(
+    get,
+    path = "/chat/conversations/{id}/pending",
+    tag = "chat",
+    params(("id" = Uuid, Path, description = "Conversation ID")),
+    responses(
+        (
+            status = 200,
+            description = "Messages queued during an outage, oldest first",
+            body = Vec<PendingMessage>
+        ),
+        (status = 404, description = "Conversation not found", body = ErrorBody),
+    ),
+    security(("bearer_auth" = []))
+)]
+#[tracing::instrument(
+    skip_all,
+    fields(%user_id, %conversation_id, request_id = %RequestId::current().unwrap_or_default())
+)]
+pub async fn list_pending_messages(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Path(conversation_id): Path<Uuid>,
+) -> ChatResult<Json<Vec<PendingMessage>>> {
+    let messages = pending::list_pending(&state.db, user_id, conversation_id).await?;
+    Ok(Json(messages))
+}
+
+/// Rate a message in one of the user's conversations
+/// POST /chat/conversations/{id}/messages/{message_id}/feedback
+#[utoipa::path(
+    post,
+    path = "/chat/conversations/{id}/messages/{message_id}/feedback",
+    tag = "chat",
+    params(
+        ("id" = Uuid, Path, description = "Conversation ID"),
+        ("message_id" = Uuid, Path, description = "Message ID"),
+    ),
+    request_body = SubmitFeedbackRequest,
+    responses(
+        (status = 200, description = "Feedback recorded", body = MessageFeedback),
+        (status = 404, description = "Message not found", body = ErrorBody),
+        (status = 409, description = "Message already rated", body = ErrorBody),
+    ),
+    security(("bearer_auth" = []))
+)]
+#[tracing::instrument(
+    skip_all,
+    fields(
+        %user_id,
+        %conversation_id,
+        %message_id,
+        request_id = %RequestId::current().unwrap_or_default()
+    )
+)]
+pub async fn submit_message_feedback(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Path((conversation_id, message_id)): Path<(Uuid, Uuid)>,
+    Json(req): Json<SubmitFeedbackRequest>,
+) -> ChatResult<Json<MessageFeedback>> {
+    let feedback =
+        feedback::submit_feedback(&state.db, user_id, conversation_id, message_id, req).await?;
+    Ok(Json(feedback))
+}
+
+/// Download a conversation as a Markdown or JSON file
+/// GET /chat/conversations/{id}/export?format=markdown|json
+///
+/// Messages are streamed from the database, so long conversations aren't
+/// held in memory.
+#[utoipa::path(
+    get,
+    path = "/chat/conversations/{id}/export",
+    tag = "chat",
+    params(
+        ("id" = Uuid, Path, description = "Conversation ID"),
+        ExportConversationQuery,
+    ),
+    responses(
+        (status = 200, description = "Conversation file, with sources"),
+        (status = 404, description = "Conversation not found", body = ErrorBody),
+    ),
+    security(("bearer_auth" = []))
+)]
+#[tracing::instrument(
+    skip_all,
+    fields(%user_id, %conversation_id, request_id = %RequestId::current().unwrap_or_default())
+)]
+pub async fn export_conversation(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Path(conversation_id): Path<Uuid>,
+    Query(params): Query<ExportConversationQuery>,
+) -> ChatResult<Response> {
+    let conversation = sqlx::query_as!(
+        ExportedConversation,
+        r#"SELECT id, title, created_at FROM conversations WHERE id = $1 AND user_id = $2"#,
+        conversation_id,
+        user_id.to_string()
+    )
+    .fetch_optional(&state.db)
+    .await?
+    .ok_or_else(|| ChatError::ConversationNotFound(conversation_id.to_string()))?;
+
+    let format = params.format;
+    let disposition = format!(
+        "attachment; filename=\"conversation-{}.{}\"",
+        conversation_id,
+        format.extension()
+    );
+    let headers = [
+        (header::CONTENT_TYPE, HeaderValue::from_static(format.content_type())),
+        (
+            header::CONTENT_DISPOSITION,
+            HeaderValue::from_str(&disposition)
+                .map_err(|e| ChatError::InternalError(e.to_string()))?,
+        ),
+    ];
+
+    let stream = export::export_stream(state.db.clone(), conversation, format);
+    Ok((headers, Body::from_stream(stream)).into_response())
+}
+
+/// Create a public, read-only share link, replacing any existing one
+/// POST /chat/conversations/{id}/share
+#[utoipa::path(
+    post,
+    path = "/chat/conversations/{id}/share",
+    tag = "chat",
+    params(("id" = Uuid, Path, description = "Conversation ID")),
+    request_body = CreateShareRequest,
+    responses(
+        (status = 200, description = "Share link created", body = ShareLink),
+        (status = 400, description = "Invalid expiry", body = ErrorBody),
+        (status = 404, description = "Conversation not found", body = ErrorBody),
+    ),
+    security(("bearer_auth" = []))
+)]
+#[tracing::instrument(
+    skip_all,
+    fields(%user_id, %conversation_id, request_id = %RequestId::current().unwrap_or_default())
+)]
+pub async fn create_share_link(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Path(conversation_id): Path<Uuid>,
+    Json(req): Json<CreateShareRequest>,
+) -> ChatResult<Json<ShareLink>> {
+    let link = share::create_share(
+        &state.db,
+        user_id,
+        conversation_id,
+        req,
+        &state.config.email.frontend_url,
+    )
+    .await?;
+    Ok(Json(link))
+}
+
+/// Revoke a conversation's share link
+/// DELETE /chat/conversations/{id}/share
+#[utoipa::path(
+    delete,
+    path = "/chat/conversations/{id}/share",
+    tag = "chat",
+    params(("id" = Uuid, Path, description = "Conversation ID")),
+    responses(
+        (status = 200, description = "Share link revoked", body = RevokeShareResponse),
+        (status = 404, description = "Conversation not found", body = ErrorBody),
+    ),
+    security(("bearer_auth" = []))
+)]
+#[tracing::instrument(
+    skip_all,
+    fields(%user_id, %conversation_id, request_id = %RequestId::current().unwrap_or_default())
+)]
+pub async fn revoke_share_link(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Path(conversation_id): Path<Uuid>,
+) -> ChatResult<Json<RevokeShareResponse>> {
+    let revoked = share::revoke_share(&state.db, user_id, conversation_id).await?;
+    Ok(Json(RevokeShareResponse { revoked }))
+}
+
+/// Read a conversation through its share link (no authentication)
+/// GET /chat/shared/{token}
+#[utoipa::path(
+    get,
+    path = "/chat/shared/{token}",
+    tag = "chat",
+    params(("token" = String, Path, description = "Share token")),
+    responses(
+        (status = 200, description = "Shared conversation", body = SharedConversation),
+        (status = 404, description = "Unknown, revoked or expired link", body = ErrorBody),
+    )
+)]
+pub async fn get_shared_conversation(
+    State(state): State<AppState>,
+    Path(token): Path<String>,
+) -> ChatResult<Json<SharedConversation>> {
+    let conversation = share::shared_conversation(&state.db, &token).await?;
+    Ok(Json(conversation))
+}
+
+/// Generate and store a title for an untitled conversation in the background
+///
+/// Failures are only logged; the conversation stays untitled and the next
+/// message tries again. The `title IS NULL` guard keeps user-set titles.
+fn spawn_title_generation(
+    state: &AppState,
+    conversation_id: Uuid,
+    user_message: String,
+    assistant_message: String,
+) {
+    let db = state.db.clone();
+    let mut client = state.intelligence_client.clone();
+
+    request_id::spawn(async move {
+        let request = crate::grpc::proto::opentier::intelligence::v1::GenerateTitleRequest {
+            conversation_id: conversation_id.to_string(),
+            user_message,
+            assistant_message,
+        };
+
+        let title = match client.generate_title(request).await {
+            Ok(response) => response.into_inner().title,
+            Err(e) => {
+                tracing::warn!(
+                    conversation_id = %conversation_id,
+                    "Failed to auto-generate conversation title: {}",
+                    e
+                );
+                return;
+            }
+        };
+
+        let title = title.trim();
+        if title.is_empty() {
+            return;
+        }
+
+        let result = sqlx::query!(
+            r#"
+            UPDATE conversations
+            SET title = $1, updated_at = NOW()
+            WHERE id = $2 AND title IS NULL
+            "#,
+            title,
+            conversation_id
+        )
+        .execute(&db)
+        .await;
+
+        if let Err(e) = result {
+            tracing::warn!(
+                conversation_id = %conversation_id,
+                "Failed to save generated conversation title: {}",
+                e
+            );
+        }
+    });
+}
+
+/// Apply the configured `MissingMetricsMode` to a chat response's metrics
+fn resolve_metrics(
+    metrics: Option<crate::grpc::proto::opentier::intelligence::v1::ChatMetrics>,
+    sources_retrieved: i32,
+    mode: MissingMetricsMode,
+) -> ChatResult<Option<ChatMetrics>> {
+    let metrics = match (metrics, mode) {
+        (Some(m), _) => m,
+        (None, MissingMetricsMode::Default) => Default::default(),
+        (None, MissingMetricsMode::Omit) => return Ok(None),
+        (None, MissingMetricsMode::Error) => {
+            return Err(ChatError::IntelligenceError(
+                "Response did not include usage metrics".to_string(),
+            ));
+        }
+    };
+
+    Ok(Some(ChatMetrics {
+        tokens_used: metrics.tokens_used,
+        context_tokens: metrics.prompt_tokens,
+        response_tokens: metrics.completion_tokens,
+        latency_ms: metrics.latency_ms,
+        sources_retrieved,
     }))
 }
 
@@ -446,6 +1295,31 @@ pub async fn send_message(
 
 /// Stream chat response in real-time (Server-Sent Events)
 /// GET /chat/conversations/{id}/stream?message=hello&temperature=0.7
+///
+/// Settings missing from the query come from the user's default chat config,
+/// then from the built-in defaults.
+#[utoipa::path(
+    get,
+    path = "/chat/conversations/{id}/stream",
+    tag = "chat",
+    params(
+        ("id" = Uuid, Path, description = "Conversation ID"),
+        StreamChatQuery,
+    ),
+    responses(
+        (
+            status = 200,
+            description = "Server-sent events: `message`, `source`, `metrics` and `error`"
+        ),
+        (status = 402, description = "Monthly token budget used up", body = ErrorBody),
+        (status = 429, description = "Daily token quota used up", body = ErrorBody),
+    ),
+    security(("bearer_auth" = []))
+)]
+#[tracing::instrument(
+    skip_all,
+    fields(%user_id, %conversation_id, request_id = %RequestId::current().unwrap_or_default())
+)]
 pub async fn stream_chat(
     State(state): State<AppState>,
     Extension(user_id): Extension<Uuid>,
@@ -454,7 +1328,13 @@ pub async fn stream_chat(
 ) -> ChatResult<Sse<impl Stream<Item = Result<Event, Infallible>>>> {
     use futures::StreamExt;
 
+    quota::enforce_daily_quota(&state.db, user_id).await?;
+    quota::enforce_monthly_budget(&state.db, user_id, state.config.chat.monthly_token_budget)
+        .await?;
+
     let mut client = state.intelligence_client.clone();
+    let defaults = preferences::default_chat_config(&state.db, user_id).await?;
+    let config = params.chat_config(defaults.as_ref());
 
     let request = crate::grpc::proto::opentier::intelligence::v1::ChatRequest {
         user_id: user_id.to_string(),
@@ -462,21 +1342,25 @@ pub async fn stream_chat(
         message: params.message,
         metadata: std::collections::HashMap::new(),
         config: Some(crate::grpc::proto::opentier::intelligence::v1::ChatConfig {
-            temperature: Some(params.temperature),
-            max_tokens: Some(params.max_tokens),
-            use_rag: Some(params.use_rag),
-            model: params.model,
+            temperature: config.temperature,
+            max_tokens: config.max_tokens,
+            use_rag: Some(config.use_rag),
+            model: config.model,
             context_limit: None,
         }),
     };
 
+    let stream_guard = state.active_streams.start(conversation_id);
     let grpc_stream = client
         .stream_chat(request)
         .await
-        .map_err(|e| ChatError::GrpcError(e))?
+        .map_err(ChatError::GrpcError)?
         .into_inner();
 
-    let sse_stream = grpc_stream.map(|result| {
+    let db = state.db.clone();
+    let sse_stream = grpc_stream.map(move |result| {
+        // Owned by the stream, so the conversation counts as streaming until it's dropped
+        let _streaming = &stream_guard;
         match result {
             Ok(chunk) => {
                 match chunk.chunk_type {
@@ -499,6 +1383,19 @@ pub async fn stream_chat(
                         Ok(Event::default().event("source").data(data))
                     }
                     Some(crate::grpc::proto::opentier::intelligence::v1::chat_stream_chunk::ChunkType::Metrics(metrics)) => {
+                        // Recorded off the stream so a slow write doesn't hold up the client
+                        let db = db.clone();
+                        let tokens = metrics.tokens_used as i64;
+                        request_id::spawn(async move {
+                            if let Err(e) = quota::record_token_usage(&db, user_id, tokens).await {
+                                tracing::error!(
+                                    user_id = %user_id,
+                                    "Failed to record token usage: {}",
+                                    e
+                                );
+                            }
+                        });
+
                         // Serialize metrics to JSON
                         let m = ChatMetrics {
                             tokens_used: metrics.tokens_used,
@@ -519,5 +1416,169 @@ pub async fn stream_chat(
         }
     });
 
-    Ok(Sse::new(sse_stream).keep_alive(KeepAlive::default()))
+    Ok(Sse::new(request_id::scope_stream(sse_stream)).keep_alive(KeepAlive::default()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grpc::proto::opentier::intelligence::v1 as pb;
+
+    fn sample_metrics() -> pb::ChatMetrics {
+        pb::ChatMetrics {
+            tokens_used: 30,
+            prompt_tokens: 20,
+            completion_tokens: 10,
+            latency_ms: 12.5,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_present_metrics_pass_through_in_every_mode() {
+        for mode in [
+            MissingMetricsMode::Default,
+            MissingMetricsMode::Omit,
+            MissingMetricsMode::Error,
+        ] {
+            let metrics = resolve_metrics(Some(sample_metrics()), 2, mode)
+                .unwrap()
+                .unwrap();
+            assert_eq!(metrics.tokens_used, 30);
+            assert_eq!(metrics.sources_retrieved, 2);
+        }
+    }
+
+    #[test]
+    fn test_missing_metrics_default_mode_zeroes() {
+        let metrics = resolve_metrics(None, 3, MissingMetricsMode::Default)
+            .unwrap()
+            .unwrap();
+        assert_eq!(metrics.tokens_used, 0);
+        assert_eq!(metrics.response_tokens, 0);
+        assert_eq!(metrics.sources_retrieved, 3);
+    }
+
+    #[test]
+    fn test_missing_metrics_omit_mode() {
+        assert!(resolve_metrics(None, 3, MissingMetricsMode::Omit)
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn test_missing_metrics_error_mode() {
+        assert!(matches!(
+            resolve_metrics(None, 3, MissingMetricsMode::Error),
+            Err(ChatError::IntelligenceError(_))
+        ));
+    }
+
+    #[test]
+    fn test_message_cursor_parsing() {
+        let query = |limit: i64, before: Option<&str>| ConversationQuery {
+            limit,
+            before: before.map(str::to_string),
+        };
+
+        assert_eq!(query(1000, None).limit(), MAX_MESSAGE_LIMIT);
+        assert_eq!(query(0, None).limit(), 1);
+        assert_eq!(query(50, None).cursor(), Ok(None));
+
+        let id = Uuid::new_v4();
+        assert_eq!(
+            query(50, Some(&id.to_string())).cursor(),
+            Ok(Some(MessageCursor::Message(id)))
+        );
+        assert_eq!(
+            query(50, Some("1700000000")).cursor(),
+            Ok(Some(MessageCursor::Timestamp(
+                chrono::DateTime::from_timestamp(1_700_000_000, 0).unwrap()
+            )))
+        );
+        assert!(query(50, Some("yesterday")).cursor().is_err());
+    }
+
+    #[sqlx::test]
+    async fn test_conversation_pages_have_no_duplicates(db: sqlx::PgPool) {
+        use crate::auth::{Role, session};
+        use axum::{body::Body, extract::ConnectInfo, http::Request};
+        use chrono::{Duration, TimeZone, Utc};
+        use std::net::SocketAddr;
+        use tower::ServiceExt;
+
+        // Owned by the Intelligence service, so not created by our migrations
+        sqlx::query(
+            "CREATE TABLE chat_messages \
+             (conversation_id UUID, content TEXT, created_at TIMESTAMPTZ)",
+        )
+        .execute(&db)
+        .await
+        .unwrap();
+        let user_id = sqlx::query_scalar!(
+            "INSERT INTO users (email) VALUES ('pager@example.com') RETURNING id"
+        )
+        .fetch_one(&db)
+        .await
+        .unwrap();
+        let (_, token, _) = session::create_session(&db, None, user_id, Role::User, None, None, 0)
+            .await
+            .unwrap();
+
+        // 55 conversations where pairs share `updated_at`, so the id breaks ties
+        let start = Utc.with_ymd_and_hms(2026, 3, 1, 12, 0, 0).unwrap();
+        let mut expected = Vec::new();
+        for i in 0..55 {
+            let id = Uuid::new_v4();
+            sqlx::query!(
+                "INSERT INTO conversations (id, user_id, updated_at) VALUES ($1, $2, $3)",
+                id,
+                user_id.to_string(),
+                start - Duration::seconds(i / 2)
+            )
+            .execute(&db)
+            .await
+            .unwrap();
+            expected.push(id);
+        }
+
+        let app = crate::gateway::test_router(db).await;
+        let mut seen = Vec::new();
+        let mut cursor: Option<String> = None;
+        let mut pages = 0;
+        loop {
+            let uri = match &cursor {
+                Some(cursor) => format!("/chat/conversations?limit=20&cursor={}", cursor),
+                None => "/chat/conversations?limit=20".to_string(),
+            };
+            let mut request = Request::get(uri)
+                .header("authorization", format!("Bearer {}", token))
+                .body(Body::empty())
+                .unwrap();
+            request
+                .extensions_mut()
+                .insert(ConnectInfo(SocketAddr::from(([127, 0, 0, 1], 4000))));
+            let response = app.clone().oneshot(request).await.unwrap();
+            assert_eq!(response.status(), axum::http::StatusCode::OK);
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            let page: serde_json::Value = serde_json::from_slice(&body).unwrap();
+
+            pages += 1;
+            for conversation in page["conversations"].as_array().unwrap() {
+                seen.push(conversation["id"].as_str().unwrap().parse::<Uuid>().unwrap());
+            }
+            match page["next_cursor"].as_str() {
+                Some(next) => cursor = Some(next.to_string()),
+                None => break,
+            }
+        }
+
+        assert_eq!(pages, 3);
+        assert_eq!(seen.len(), 55);
+        seen.sort();
+        expected.sort();
+        assert_eq!(seen, expected);
+    }
 }
@@ -0,0 +1,75 @@
+//! Rewriting conversation history for message edits
+//!
+//! The Intelligence service stores both sides of an exchange in one
+//! transaction, so a user message and its reply share a `created_at`. An
+//! edit removes the edited message and everything from that instant on,
+//! then resends the new content as a fresh message.
+
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use super::error::ChatResult;
+
+/// A message removed by `truncate_from`, kept so it can be put back
+#[derive(Debug)]
+pub struct RemovedMessage {
+    pub id: Uuid,
+    pub role: String,
+    pub content: String,
+    pub sources: serde_json::Value,
+    pub metadata: serde_json::Value,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Delete every message in the conversation created at or after `from`
+pub async fn truncate_from(
+    db: &PgPool,
+    conversation_id: Uuid,
+    from: DateTime<Utc>,
+) -> ChatResult<Vec<RemovedMessage>> {
+    let removed = sqlx::query_as!(
+        RemovedMessage,
+        r#"
+        DELETE FROM chat_messages
+        WHERE conversation_id = $1 AND created_at >= $2
+        RETURNING id, role, content, sources, metadata, created_at
+        "#,
+        conversation_id,
+        from
+    )
+    .fetch_all(db)
+    .await?;
+
+    Ok(removed)
+}
+
+/// Put back messages removed by `truncate_from`
+pub async fn restore(
+    db: &PgPool,
+    conversation_id: Uuid,
+    messages: &[RemovedMessage],
+) -> ChatResult<()> {
+    let mut tx = db.begin().await?;
+    for message in messages {
+        sqlx::query!(
+            r#"
+            INSERT INTO chat_messages
+                (id, conversation_id, role, content, sources, metadata, created_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            ON CONFLICT (id) DO NOTHING
+            "#,
+            message.id,
+            conversation_id,
+            message.role,
+            message.content,
+            message.sources,
+            message.metadata,
+            message.created_at
+        )
+        .execute(&mut *tx)
+        .await?;
+    }
+    tx.commit().await?;
+    Ok(())
+}
@@ -0,0 +1,340 @@
+//! Idempotent message sends
+//!
+//! A client that may retry `POST /chat/conversations/{id}/messages` sends an
+//! `Idempotency-Key: <uuid>` header. Keys are scoped to the user and the
+//! conversation. The key is claimed before the message is sent, so of two
+//! concurrent requests with the same key only one reaches the Intelligence
+//! service. The first successful reply is stored under that key for
+//! `KEY_TTL_HOURS`; a retry with the same key gets the stored reply back
+//! instead of sending the message again. A send that fails releases the key
+//! so it can be retried. A claim left without a reply, e.g. by a restart
+//! mid-send, lapses after `CLAIM_LEASE_MINUTES`.
+
+use axum::http::{HeaderMap, HeaderName};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use super::error::{ChatError, ChatResult};
+use super::types::MessageResponse;
+use crate::common::background;
+
+pub const IDEMPOTENCY_KEY_HEADER: HeaderName = HeaderName::from_static("idempotency-key");
+
+/// Set on a response served from a stored reply
+pub const REPLAYED_HEADER: HeaderName = HeaderName::from_static("idempotent-replayed");
+
+/// How long a stored reply is replayed
+const KEY_TTL_HOURS: i32 = 24;
+
+/// How long a claim without a reply blocks retries; longer than any send can
+/// take (the chat RPC timeout is 20 minutes), so a live send is never doubled
+const CLAIM_LEASE_MINUTES: i32 = 30;
+
+/// The request's idempotency key, if it sent one
+pub fn idempotency_key(headers: &HeaderMap) -> ChatResult<Option<Uuid>> {
+    let Some(value) = headers.get(IDEMPOTENCY_KEY_HEADER) else {
+        return Ok(None);
+    };
+
+    value
+        .to_str()
+        .ok()
+        .and_then(|v| Uuid::parse_str(v.trim()).ok())
+        .map(Some)
+        .ok_or(ChatError::InvalidIdempotencyKey)
+}
+
+/// Outcome of claiming a key
+#[derive(Debug)]
+pub enum KeyClaim {
+    /// This request sends the message
+    Claimed,
+    /// A previous request already did; this is its reply
+    Replay(serde_json::Value),
+}
+
+/// Claim a key for this conversation, or return the reply stored under it
+///
+/// Fails with `IdempotencyKeyInUse` while another request holds the claim.
+/// Expired keys and claims whose lease lapsed without a reply are claimed
+/// afresh.
+pub async fn claim_key(
+    db: &PgPool,
+    user_id: Uuid,
+    conversation_id: Uuid,
+    key: Uuid,
+) -> ChatResult<KeyClaim> {
+    // The conditional upsert lets only one of two concurrent requests through
+    let claimed = sqlx::query_scalar!(
+        r#"
+        INSERT INTO message_idempotency_keys (key, user_id, conversation_id)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (user_id, conversation_id, key) DO UPDATE
+        SET response_json = NULL, created_at = NOW()
+        WHERE message_idempotency_keys.created_at <= NOW() - make_interval(hours => $4)
+           OR (message_idempotency_keys.response_json IS NULL
+               AND message_idempotency_keys.created_at <= NOW() - make_interval(mins => $5))
+        RETURNING key
+        "#,
+        key.to_string(),
+        user_id,
+        conversation_id,
+        KEY_TTL_HOURS,
+        CLAIM_LEASE_MINUTES
+    )
+    .fetch_optional(db)
+    .await?;
+
+    if claimed.is_some() {
+        return Ok(KeyClaim::Claimed);
+    }
+
+    let response = sqlx::query_scalar!(
+        r#"
+        SELECT response_json
+        FROM message_idempotency_keys
+        WHERE user_id = $1 AND conversation_id = $2 AND key = $3
+        "#,
+        user_id,
+        conversation_id,
+        key.to_string()
+    )
+    .fetch_optional(db)
+    .await?
+    .flatten();
+
+    response
+        .map(KeyClaim::Replay)
+        .ok_or(ChatError::IdempotencyKeyInUse)
+}
+
+/// Store the reply under a claimed key
+pub async fn store_response(
+    db: &PgPool,
+    user_id: Uuid,
+    conversation_id: Uuid,
+    key: Uuid,
+    response: &MessageResponse,
+) -> ChatResult<()> {
+    let response_json = serde_json::to_value(response)?;
+
+    sqlx::query!(
+        r#"
+        UPDATE message_idempotency_keys
+        SET response_json = $4
+        WHERE user_id = $1 AND conversation_id = $2 AND key = $3
+        "#,
+        user_id,
+        conversation_id,
+        key.to_string(),
+        response_json
+    )
+    .execute(db)
+    .await?;
+
+    Ok(())
+}
+
+/// Give up a claimed key after a failed send, so a retry can claim it
+pub async fn release_key(db: &PgPool, user_id: Uuid, conversation_id: Uuid, key: Uuid) {
+    let released = sqlx::query!(
+        r#"
+        DELETE FROM message_idempotency_keys
+        WHERE user_id = $1 AND conversation_id = $2 AND key = $3
+          AND response_json IS NULL
+        "#,
+        user_id,
+        conversation_id,
+        key.to_string()
+    )
+    .execute(db)
+    .await;
+
+    if let Err(e) = released {
+        tracing::error!(idempotency_key = %key, "Failed to release idempotency key: {}", e);
+    }
+}
+
+/// Delete keys past their replay window
+pub async fn cleanup_expired_keys(db: &PgPool) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query!(
+        r#"
+        DELETE FROM message_idempotency_keys
+        WHERE created_at < NOW() - make_interval(hours => $1)
+        "#,
+        KEY_TTL_HOURS
+    )
+    .execute(db)
+    .await?;
+
+    Ok(result.rows_affected())
+}
+
+/// Start idempotency key cleanup background task
+/// Runs hourly to drop keys older than `KEY_TTL_HOURS`
+pub fn start_cleanup_task(db: PgPool) {
+    background::start_periodic_task(
+        db,
+        "Idempotency key cleanup",
+        3600, // 1 hour
+        |db| async move { cleanup_expired_keys(&db).await },
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chat::types::MessageRole;
+    use crate::grpc::client::RpcTimeouts;
+
+    #[test]
+    fn test_idempotency_key_header() {
+        let mut headers = HeaderMap::new();
+        assert!(matches!(idempotency_key(&headers), Ok(None)));
+
+        let key = Uuid::new_v4();
+        headers.insert(IDEMPOTENCY_KEY_HEADER, key.to_string().parse().unwrap());
+        assert!(matches!(idempotency_key(&headers), Ok(Some(k)) if k == key));
+
+        headers.insert(IDEMPOTENCY_KEY_HEADER, "retry-1".parse().unwrap());
+        assert!(matches!(
+            idempotency_key(&headers),
+            Err(ChatError::InvalidIdempotencyKey)
+        ));
+    }
+
+    #[sqlx::test]
+    async fn test_keys_are_claimed_once_per_conversation(db: PgPool) {
+        let user_id = sqlx::query_scalar!(
+            "INSERT INTO users (email) VALUES ('keys@example.com') RETURNING id"
+        )
+        .fetch_one(&db)
+        .await
+        .unwrap();
+        let conversation = || async {
+            let id = Uuid::new_v4();
+            sqlx::query!(
+                "INSERT INTO conversations (id, user_id) VALUES ($1, $2)",
+                id,
+                user_id.to_string()
+            )
+            .execute(&db)
+            .await
+            .unwrap();
+            id
+        };
+        let (first, second) = (conversation().await, conversation().await);
+        let key = Uuid::new_v4();
+
+        assert!(matches!(
+            claim_key(&db, user_id, first, key).await,
+            Ok(KeyClaim::Claimed)
+        ));
+        // A concurrent retry doesn't send the message again
+        assert!(matches!(
+            claim_key(&db, user_id, first, key).await,
+            Err(ChatError::IdempotencyKeyInUse)
+        ));
+        // The same key in another conversation is a different request
+        assert!(matches!(
+            claim_key(&db, user_id, second, key).await,
+            Ok(KeyClaim::Claimed)
+        ));
+
+        let reply = MessageResponse {
+            message_id: Uuid::new_v4(),
+            conversation_id: first,
+            role: MessageRole::Assistant,
+            content: "Hello".to_string(),
+            sources: Vec::new(),
+            metrics: None,
+            created_at: 0,
+        };
+        store_response(&db, user_id, first, key, &reply).await.unwrap();
+        let Ok(KeyClaim::Replay(replayed)) = claim_key(&db, user_id, first, key).await else {
+            panic!("stored reply not replayed");
+        };
+        assert_eq!(replayed["content"], "Hello");
+
+        // A failed send can be retried
+        release_key(&db, user_id, second, key).await;
+        assert!(matches!(
+            claim_key(&db, user_id, second, key).await,
+            Ok(KeyClaim::Claimed)
+        ));
+    }
+
+    #[test]
+    fn test_claim_lease_outlasts_a_send() {
+        let lease = std::time::Duration::from_secs(CLAIM_LEASE_MINUTES as u64 * 60);
+        assert!(lease > RpcTimeouts::default().chat);
+    }
+
+    #[sqlx::test]
+    async fn test_claim_left_without_reply_lapses(db: PgPool) {
+        let user_id = sqlx::query_scalar!(
+            "INSERT INTO users (email) VALUES ('lease@example.com') RETURNING id"
+        )
+        .fetch_one(&db)
+        .await
+        .unwrap();
+        let conversation_id = Uuid::new_v4();
+        sqlx::query!(
+            "INSERT INTO conversations (id, user_id) VALUES ($1, $2)",
+            conversation_id,
+            user_id.to_string()
+        )
+        .execute(&db)
+        .await
+        .unwrap();
+        let key = Uuid::new_v4();
+        let backdate = |minutes: i32| {
+            let db = db.clone();
+            async move {
+                sqlx::query!(
+                    r#"
+                    UPDATE message_idempotency_keys
+                    SET created_at = NOW() - make_interval(mins => $1)
+                    WHERE key = $2
+                    "#,
+                    minutes,
+                    key.to_string()
+                )
+                .execute(&db)
+                .await
+                .unwrap();
+            }
+        };
+
+        // The request that claimed the key died before storing or releasing it
+        claim_key(&db, user_id, conversation_id, key).await.unwrap();
+        backdate(CLAIM_LEASE_MINUTES - 1).await;
+        assert!(matches!(
+            claim_key(&db, user_id, conversation_id, key).await,
+            Err(ChatError::IdempotencyKeyInUse)
+        ));
+
+        backdate(CLAIM_LEASE_MINUTES + 1).await;
+        assert!(matches!(
+            claim_key(&db, user_id, conversation_id, key).await,
+            Ok(KeyClaim::Claimed)
+        ));
+
+        // A stored reply is still replayed past the lease
+        let reply = MessageResponse {
+            message_id: Uuid::new_v4(),
+            conversation_id,
+            role: MessageRole::Assistant,
+            content: "Hello".to_string(),
+            sources: Vec::new(),
+            metrics: None,
+            created_at: 0,
+        };
+        store_response(&db, user_id, conversation_id, key, &reply).await.unwrap();
+        backdate(CLAIM_LEASE_MINUTES + 1).await;
+        assert!(matches!(
+            claim_key(&db, user_id, conversation_id, key).await,
+            Ok(KeyClaim::Replay(_))
+        ));
+    }
+}
@@ -1,3 +1,12 @@
 pub mod error;
+pub mod export;
+pub mod feedback;
 pub mod handlers;
+pub mod history;
+pub mod idempotency;
+pub mod pending;
+pub mod quota;
+pub mod share;
+pub mod streams;
+pub mod tags;
 pub mod types;
@@ -0,0 +1,283 @@
+//! Messages queued while the Intelligence service is unreachable
+//!
+//! With `CHAT_QUEUE_ON_OUTAGE` enabled, a message that cannot be delivered
+//! because the service is down is stored in `pending_messages` and answered
+//! with `202 Accepted`. The client resends it later, passing `pending_id` so
+//! the stored copy is removed once it goes through, or once a replayed
+//! idempotent send shows it already did.
+
+use axum::{
+    Json,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use utoipa::ToSchema;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use super::error::ChatResult;
+use super::types::ChatConfig;
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct PendingMessage {
+    pub id: Uuid,
+    pub conversation_id: Uuid,
+    pub content: String,
+    pub config: Option<ChatConfig>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// `202 Accepted` body for a message stored during an outage
+#[derive(Debug, Serialize, ToSchema)]
+pub struct QueuedMessageResponse {
+    /// Always `"queued"`
+    pub status: &'static str,
+    pub pending_id: Uuid,
+    pub conversation_id: Uuid,
+    pub message: String,
+}
+
+impl From<PendingMessage> for QueuedMessageResponse {
+    fn from(pending: PendingMessage) -> Self {
+        Self {
+            status: "queued",
+            pending_id: pending.id,
+            conversation_id: pending.conversation_id,
+            message: "Intelligence service is unavailable; your message was saved and can be resent later"
+                .to_string(),
+        }
+    }
+}
+
+impl IntoResponse for QueuedMessageResponse {
+    fn into_response(self) -> Response {
+        (StatusCode::ACCEPTED, Json(self)).into_response()
+    }
+}
+
+/// Whether a failed call means the service is down, rather than that it
+/// rejected or failed this particular message
+pub fn is_outage(status: &tonic::Status) -> bool {
+    status.code() == tonic::Code::Unavailable
+}
+
+/// Store a message that could not be delivered
+pub async fn store_pending(
+    db: &PgPool,
+    user_id: Uuid,
+    conversation_id: Uuid,
+    content: &str,
+    config: Option<&ChatConfig>,
+) -> ChatResult<PendingMessage> {
+    let config_json = config.map(serde_json::to_value).transpose()?;
+
+    let row = sqlx::query!(
+        r#"
+        INSERT INTO pending_messages (conversation_id, user_id, content, config)
+        VALUES ($1, $2, $3, $4)
+        RETURNING id, created_at
+        "#,
+        conversation_id,
+        user_id,
+        content,
+        config_json
+    )
+    .fetch_one(db)
+    .await?;
+
+    Ok(PendingMessage {
+        id: row.id,
+        conversation_id,
+        content: content.to_string(),
+        config: config.cloned(),
+        created_at: row.created_at,
+    })
+}
+
+/// A user's pending messages in a conversation, oldest first
+pub async fn list_pending(
+    db: &PgPool,
+    user_id: Uuid,
+    conversation_id: Uuid,
+) -> ChatResult<Vec<PendingMessage>> {
+    let rows = sqlx::query!(
+        r#"
+        SELECT id, conversation_id, content, config, created_at
+        FROM pending_messages
+        WHERE user_id = $1 AND conversation_id = $2
+        ORDER BY created_at ASC
+        "#,
+        user_id,
+        conversation_id
+    )
+    .fetch_all(db)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| PendingMessage {
+            id: row.id,
+            conversation_id: row.conversation_id,
+            content: row.content,
+            config: row.config.and_then(|c| serde_json::from_value(c).ok()),
+            created_at: row.created_at,
+        })
+        .collect())
+}
+
+/// One of the user's pending messages in a conversation
+///
+/// `None` for IDs that don't exist, were already delivered, or belong to
+/// another user or conversation.
+pub async fn find_pending(
+    db: &PgPool,
+    user_id: Uuid,
+    conversation_id: Uuid,
+    pending_id: Uuid,
+) -> ChatResult<Option<PendingMessage>> {
+    let row = sqlx::query!(
+        r#"
+        SELECT id, conversation_id, content, config, created_at
+        FROM pending_messages
+        WHERE id = $1 AND user_id = $2 AND conversation_id = $3
+        "#,
+        pending_id,
+        user_id,
+        conversation_id
+    )
+    .fetch_optional(db)
+    .await?;
+
+    Ok(row.map(|row| PendingMessage {
+        id: row.id,
+        conversation_id: row.conversation_id,
+        content: row.content,
+        config: row.config.and_then(|c| serde_json::from_value(c).ok()),
+        created_at: row.created_at,
+    }))
+}
+
+/// Remove a pending message once it has been delivered
+pub async fn delete_pending(db: &PgPool, user_id: Uuid, pending_id: Uuid) -> ChatResult<bool> {
+    let result = sqlx::query!(
+        "DELETE FROM pending_messages WHERE id = $1 AND user_id = $2",
+        pending_id,
+        user_id
+    )
+    .execute(db)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grpc::client::IntelligenceClient;
+    use crate::grpc::proto::opentier::intelligence::v1::ChatRequest;
+
+    #[tokio::test]
+    async fn test_unreachable_service_is_an_outage() {
+        // Nothing listens on port 1, so the call fails like an outage would
+        let mut client = IntelligenceClient::connect_lazy("http://127.0.0.1:1")
+            .await
+            .unwrap();
+
+        let status = client
+            .send_message(
+                ChatRequest {
+                    user_id: Uuid::new_v4().to_string(),
+                    conversation_id: Uuid::new_v4().to_string(),
+                    message: "hello".to_string(),
+                    metadata: Default::default(),
+                    config: None,
+                },
+                None,
+            )
+            .await
+            .unwrap_err();
+
+        assert!(is_outage(&status));
+        assert!(!is_outage(&tonic::Status::invalid_argument("bad")));
+    }
+
+    async fn create_conversation(db: &PgPool, user_id: Uuid) -> Uuid {
+        let id = Uuid::new_v4();
+        sqlx::query!(
+            "INSERT INTO conversations (id, user_id) VALUES ($1, $2)",
+            id,
+            user_id.to_string()
+        )
+        .execute(db)
+        .await
+        .unwrap();
+        id
+    }
+
+    async fn create_user(db: &PgPool) -> Uuid {
+        sqlx::query_scalar!(
+            "INSERT INTO users (email) VALUES ($1) RETURNING id",
+            format!("{}@example.com", Uuid::new_v4())
+        )
+        .fetch_one(db)
+        .await
+        .unwrap()
+    }
+
+    #[sqlx::test]
+    async fn test_pending_messages_are_only_found_by_their_owner(db: PgPool) {
+        let owner = create_user(&db).await;
+        let other = create_user(&db).await;
+        let conversation_id = create_conversation(&db, owner).await;
+        let other_conversation = create_conversation(&db, owner).await;
+
+        let stored = store_pending(&db, owner, conversation_id, "hello", None)
+            .await
+            .unwrap();
+
+        let found = find_pending(&db, owner, conversation_id, stored.id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(found.content, "hello");
+
+        // Another user, another conversation, or a made-up ID find nothing
+        assert!(find_pending(&db, other, conversation_id, stored.id).await.unwrap().is_none());
+        assert!(
+            find_pending(&db, owner, other_conversation, stored.id)
+                .await
+                .unwrap()
+                .is_none()
+        );
+        assert!(
+            find_pending(&db, owner, conversation_id, Uuid::new_v4())
+                .await
+                .unwrap()
+                .is_none()
+        );
+
+        // Nor can anyone else clear it
+        assert!(!delete_pending(&db, other, stored.id).await.unwrap());
+        assert!(delete_pending(&db, owner, stored.id).await.unwrap());
+        assert!(find_pending(&db, owner, conversation_id, stored.id).await.unwrap().is_none());
+    }
+
+    #[test]
+    fn test_queued_message_is_accepted() {
+        let pending = PendingMessage {
+            id: Uuid::new_v4(),
+            conversation_id: Uuid::new_v4(),
+            content: "hello".to_string(),
+            config: None,
+            created_at: Utc::now(),
+        };
+        let pending_id = pending.id;
+
+        let queued = QueuedMessageResponse::from(pending);
+        assert_eq!(queued.status, "queued");
+        assert_eq!(queued.pending_id, pending_id);
+        assert_eq!(queued.into_response().status(), StatusCode::ACCEPTED);
+    }
+}
@@ -0,0 +1,281 @@
+//! Per-user daily token quotas and monthly token budgets
+//!
+//! Token usage from chat responses is aggregated per UTC day in
+//! `user_token_usage`; limits live in `user_quotas` (no row = unlimited).
+//! Monthly usage is the sum of the month's daily rows. A user without a
+//! monthly budget of their own gets `CHAT_MONTHLY_TOKEN_BUDGET`.
+
+use chrono::{DateTime, Datelike, Duration, Months, NaiveDate, Utc};
+use serde::Serialize;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use super::error::{ChatError, ChatResult};
+
+/// A user's token usage for the current day
+#[derive(Debug, Clone, Serialize)]
+pub struct TokenQuota {
+    pub period_start: NaiveDate,
+    pub tokens_used: i64,
+    /// `None` when the user has no daily limit
+    pub max_tokens_per_day: Option<i64>,
+    pub resets_at: DateTime<Utc>,
+}
+
+impl TokenQuota {
+    pub fn is_exceeded(&self) -> bool {
+        self.max_tokens_per_day
+            .is_some_and(|limit| self.tokens_used >= limit)
+    }
+}
+
+/// A user's token usage for the current calendar month
+#[derive(Debug, Clone, Serialize)]
+pub struct TokenBudget {
+    pub period_start: NaiveDate,
+    pub tokens_used: i64,
+    /// `None` when the user has no monthly budget
+    pub token_budget: Option<i64>,
+    pub resets_at: DateTime<Utc>,
+}
+
+impl TokenBudget {
+    pub fn is_exceeded(&self) -> bool {
+        self.token_budget
+            .is_some_and(|budget| self.tokens_used >= budget)
+    }
+
+    pub fn remaining(&self) -> Option<i64> {
+        self.token_budget
+            .map(|budget| (budget - self.tokens_used).max(0))
+    }
+}
+
+/// Current UTC day used as the usage period
+fn current_period() -> NaiveDate {
+    Utc::now().date_naive()
+}
+
+/// First day of the month containing `day`, and of the month after
+fn month_bounds(day: NaiveDate) -> (NaiveDate, NaiveDate) {
+    let start = day.with_day(1).unwrap_or(day);
+    let end = start
+        .checked_add_months(Months::new(1))
+        .unwrap_or(NaiveDate::MAX);
+    (start, end)
+}
+
+/// Fetch today's usage and the user's daily limit
+pub async fn get_daily_quota(db: &PgPool, user_id: Uuid) -> Result<TokenQuota, sqlx::Error> {
+    let period_start = current_period();
+
+    let row = sqlx::query!(
+        r#"
+        SELECT
+            (SELECT tokens_used FROM user_token_usage
+             WHERE user_id = $1 AND period_start = $2) as "tokens_used?",
+            (SELECT max_tokens_per_day FROM user_quotas
+             WHERE user_id = $1) as "max_tokens_per_day?"
+        "#,
+        user_id,
+        period_start
+    )
+    .fetch_one(db)
+    .await?;
+
+    Ok(TokenQuota {
+        period_start,
+        tokens_used: row.tokens_used.unwrap_or(0),
+        max_tokens_per_day: row.max_tokens_per_day,
+        resets_at: (period_start + Duration::days(1))
+            .and_hms_opt(0, 0, 0)
+            .unwrap_or_default()
+            .and_utc(),
+    })
+}
+
+/// Refuse the request if the user has used up today's quota
+pub async fn enforce_daily_quota(db: &PgPool, user_id: Uuid) -> ChatResult<()> {
+    let quota = get_daily_quota(db, user_id).await?;
+
+    match quota.max_tokens_per_day {
+        Some(limit) if quota.is_exceeded() => Err(ChatError::QuotaExceeded {
+            used: quota.tokens_used,
+            limit,
+        }),
+        _ => Ok(()),
+    }
+}
+
+/// Fetch this month's usage and the user's monthly budget
+///
+/// `default_budget` applies when the user has no budget of their own.
+pub async fn get_monthly_budget(
+    db: &PgPool,
+    user_id: Uuid,
+    default_budget: Option<i64>,
+) -> Result<TokenBudget, sqlx::Error> {
+    let (period_start, period_end) = month_bounds(current_period());
+
+    let row = sqlx::query!(
+        r#"
+        SELECT
+            (SELECT SUM(tokens_used) FROM user_token_usage
+             WHERE user_id = $1 AND period_start >= $2 AND period_start < $3)::BIGINT
+                as "tokens_used?",
+            (SELECT max_tokens_per_month FROM user_quotas
+             WHERE user_id = $1) as "max_tokens_per_month?"
+        "#,
+        user_id,
+        period_start,
+        period_end
+    )
+    .fetch_one(db)
+    .await?;
+
+    Ok(TokenBudget {
+        period_start,
+        tokens_used: row.tokens_used.unwrap_or(0),
+        token_budget: row.max_tokens_per_month.or(default_budget),
+        resets_at: period_end.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc(),
+    })
+}
+
+/// Refuse the request if the user has used up this month's budget
+pub async fn enforce_monthly_budget(
+    db: &PgPool,
+    user_id: Uuid,
+    default_budget: Option<i64>,
+) -> ChatResult<()> {
+    let budget = get_monthly_budget(db, user_id, default_budget).await?;
+
+    match budget.token_budget {
+        Some(limit) if budget.is_exceeded() => Err(ChatError::BudgetExceeded {
+            used: budget.tokens_used,
+            limit,
+        }),
+        _ => Ok(()),
+    }
+}
+
+/// Add tokens consumed by a response to today's usage
+pub async fn record_token_usage(
+    db: &PgPool,
+    user_id: Uuid,
+    tokens: i64,
+) -> Result<(), sqlx::Error> {
+    if tokens <= 0 {
+        return Ok(());
+    }
+
+    sqlx::query!(
+        r#"
+        INSERT INTO user_token_usage (user_id, period_start, tokens_used)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (user_id, period_start)
+        DO UPDATE SET tokens_used = user_token_usage.tokens_used + EXCLUDED.tokens_used
+        "#,
+        user_id,
+        current_period(),
+        tokens
+    )
+    .execute(db)
+    .await?;
+
+    Ok(())
+}
+
+/// Set (or clear, with `None`) a user's daily token limit
+pub async fn set_daily_limit(
+    db: &PgPool,
+    user_id: Uuid,
+    max_tokens_per_day: Option<i64>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO user_quotas (user_id, max_tokens_per_day)
+        VALUES ($1, $2)
+        ON CONFLICT (user_id)
+        DO UPDATE SET max_tokens_per_day = EXCLUDED.max_tokens_per_day, updated_at = NOW()
+        "#,
+        user_id,
+        max_tokens_per_day
+    )
+    .execute(db)
+    .await?;
+
+    Ok(())
+}
+
+/// Set (or clear, with `None`) a user's monthly token budget
+///
+/// Cleared budgets fall back to the configured default.
+pub async fn set_monthly_limit(
+    db: &PgPool,
+    user_id: Uuid,
+    max_tokens_per_month: Option<i64>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO user_quotas (user_id, max_tokens_per_month)
+        VALUES ($1, $2)
+        ON CONFLICT (user_id)
+        DO UPDATE SET max_tokens_per_month = EXCLUDED.max_tokens_per_month, updated_at = NOW()
+        "#,
+        user_id,
+        max_tokens_per_month
+    )
+    .execute(db)
+    .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn quota(tokens_used: i64, max_tokens_per_day: Option<i64>) -> TokenQuota {
+        TokenQuota {
+            period_start: current_period(),
+            tokens_used,
+            max_tokens_per_day,
+            resets_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_quota_exceeded_at_limit() {
+        assert!(!quota(999, Some(1000)).is_exceeded());
+        assert!(quota(1000, Some(1000)).is_exceeded());
+        assert!(quota(1, Some(0)).is_exceeded());
+    }
+
+    #[test]
+    fn test_unlimited_quota_never_exceeded() {
+        assert!(!quota(i64::MAX, None).is_exceeded());
+    }
+
+    #[test]
+    fn test_month_bounds() {
+        let day = |y, m, d| NaiveDate::from_ymd_opt(y, m, d).unwrap();
+        assert_eq!(month_bounds(day(2026, 3, 15)), (day(2026, 3, 1), day(2026, 4, 1)));
+        assert_eq!(month_bounds(day(2026, 12, 31)), (day(2026, 12, 1), day(2027, 1, 1)));
+        assert_eq!(month_bounds(day(2026, 2, 1)), (day(2026, 2, 1), day(2026, 3, 1)));
+    }
+
+    #[test]
+    fn test_budget_remaining() {
+        let budget = |tokens_used, token_budget| TokenBudget {
+            period_start: current_period(),
+            tokens_used,
+            token_budget,
+            resets_at: Utc::now(),
+        };
+        assert_eq!(budget(400, Some(1000)).remaining(), Some(600));
+        assert_eq!(budget(1500, Some(1000)).remaining(), Some(0));
+        assert!(budget(1000, Some(1000)).is_exceeded());
+        assert_eq!(budget(1500, None).remaining(), None);
+        assert!(!budget(1500, None).is_exceeded());
+    }
+}
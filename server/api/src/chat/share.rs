@@ -0,0 +1,276 @@
+//! Public share links for conversations
+//!
+//! A conversation has at most one link in `conversation_shares`. Minting a
+//! new one replaces the old token, and every link expires. Anyone holding
+//! the token can read the conversation through `GET /chat/shared/{token}`,
+//! which shows message text only: no IDs, owner, metadata or RAG sources.
+//! Links stop working while the owner's account is soft-deleted and work
+//! again if it is recovered.
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use super::error::{ChatError, ChatResult};
+use super::types::MessageRole;
+use crate::auth::tokens;
+
+/// Link lifetime when the request doesn't pick one
+const DEFAULT_SHARE_HOURS: i64 = 7 * 24;
+
+/// Longest lifetime a link can be given
+pub const MAX_SHARE_HOURS: i64 = 30 * 24;
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CreateShareRequest {
+    /// Hours until the link stops working (default 168, max 720)
+    pub expires_in_hours: Option<i64>,
+    /// Defaults to true; shown to viewers so a client can offer to continue
+    /// the conversation when it is false
+    pub read_only: Option<bool>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ShareLink {
+    pub conversation_id: Uuid,
+    pub token: String,
+    /// Frontend page for the shared conversation
+    pub url: String,
+    pub read_only: bool,
+    pub expires_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct RevokeShareResponse {
+    /// False when the conversation had no link
+    pub revoked: bool,
+}
+
+/// A conversation as seen through its share link
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SharedConversation {
+    pub title: Option<String>,
+    pub read_only: bool,
+    /// Oldest first
+    pub messages: Vec<SharedMessage>,
+    pub created_at: i64,
+    pub expires_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SharedMessage {
+    pub role: MessageRole,
+    pub content: String,
+    pub created_at: i64,
+}
+
+/// When a link created now should expire
+fn share_expiry(expires_in_hours: Option<i64>, now: DateTime<Utc>) -> ChatResult<DateTime<Utc>> {
+    let hours = expires_in_hours.unwrap_or(DEFAULT_SHARE_HOURS);
+    if !(1..=MAX_SHARE_HOURS).contains(&hours) {
+        return Err(ChatError::InvalidMessage(format!(
+            "expires_in_hours must be between 1 and {}",
+            MAX_SHARE_HOURS
+        )));
+    }
+    Ok(now + Duration::hours(hours))
+}
+
+/// Mint a share link for one of the user's conversations, replacing any
+/// existing link
+pub async fn create_share(
+    db: &PgPool,
+    user_id: Uuid,
+    conversation_id: Uuid,
+    req: CreateShareRequest,
+    frontend_url: &str,
+) -> ChatResult<ShareLink> {
+    let expires_at = share_expiry(req.expires_in_hours, Utc::now())?;
+    let read_only = req.read_only.unwrap_or(true);
+    ensure_owned(db, user_id, conversation_id).await?;
+
+    let row = sqlx::query!(
+        r#"
+        INSERT INTO conversation_shares (conversation_id, token, read_only, expires_at)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT (conversation_id) DO UPDATE
+        SET token = EXCLUDED.token,
+            read_only = EXCLUDED.read_only,
+            expires_at = EXCLUDED.expires_at,
+            created_at = NOW()
+        RETURNING token, created_at
+        "#,
+        conversation_id,
+        tokens::generate_token(),
+        read_only,
+        expires_at
+    )
+    .fetch_one(db)
+    .await?;
+
+    Ok(ShareLink {
+        conversation_id,
+        url: format!("{}/shared/{}", frontend_url.trim_end_matches('/'), row.token),
+        token: row.token,
+        read_only,
+        expires_at,
+        created_at: row.created_at,
+    })
+}
+
+/// Revoke the conversation's share link, if it has one
+pub async fn revoke_share(db: &PgPool, user_id: Uuid, conversation_id: Uuid) -> ChatResult<bool> {
+    ensure_owned(db, user_id, conversation_id).await?;
+
+    let result = sqlx::query!(
+        "DELETE FROM conversation_shares WHERE conversation_id = $1",
+        conversation_id
+    )
+    .execute(db)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// The conversation behind an unexpired share token
+///
+/// Unknown, revoked and expired tokens, and links of deleted accounts, all
+/// look the same to the caller.
+pub async fn shared_conversation(db: &PgPool, token: &str) -> ChatResult<SharedConversation> {
+    let share = sqlx::query!(
+        r#"
+        SELECT s.conversation_id, s.read_only, s.expires_at, c.title, c.created_at
+        FROM conversation_shares s
+        JOIN conversations c ON c.id = s.conversation_id
+        JOIN users u ON u.id::text = c.user_id
+        WHERE s.token = $1 AND s.expires_at > NOW() AND u.deleted_at IS NULL
+        "#,
+        token
+    )
+    .fetch_optional(db)
+    .await?
+    .ok_or_else(|| ChatError::NotFound("Shared conversation".to_string()))?;
+
+    let messages = sqlx::query!(
+        r#"
+        SELECT role, content, created_at
+        FROM chat_messages
+        WHERE conversation_id = $1
+        ORDER BY created_at ASC, id ASC
+        "#,
+        share.conversation_id
+    )
+    .fetch_all(db)
+    .await?;
+
+    Ok(SharedConversation {
+        title: share.title,
+        read_only: share.read_only,
+        messages: messages
+            .into_iter()
+            .map(|msg| SharedMessage {
+                role: MessageRole::from_db(&msg.role),
+                content: msg.content,
+                created_at: msg.created_at.timestamp(),
+            })
+            .collect(),
+        created_at: share.created_at.timestamp(),
+        expires_at: share.expires_at,
+    })
+}
+
+async fn ensure_owned(db: &PgPool, user_id: Uuid, conversation_id: Uuid) -> ChatResult<()> {
+    sqlx::query_scalar!(
+        "SELECT id FROM conversations WHERE id = $1 AND user_id = $2",
+        conversation_id,
+        user_id.to_string()
+    )
+    .fetch_optional(db)
+    .await?
+    .ok_or_else(|| ChatError::ConversationNotFound(conversation_id.to_string()))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_share_expiry_bounds() {
+        let now = Utc::now();
+        assert_eq!(
+            share_expiry(None, now).unwrap(),
+            now + Duration::hours(DEFAULT_SHARE_HOURS)
+        );
+        assert_eq!(share_expiry(Some(1), now).unwrap(), now + Duration::hours(1));
+        assert!(share_expiry(Some(MAX_SHARE_HOURS), now).is_ok());
+        assert!(share_expiry(Some(0), now).is_err());
+        assert!(share_expiry(Some(MAX_SHARE_HOURS + 1), now).is_err());
+    }
+
+    #[sqlx::test]
+    async fn test_links_of_deleted_accounts_stop_working(db: PgPool) {
+        // Owned by the Intelligence service, so not created by our migrations
+        sqlx::query(
+            "CREATE TABLE chat_messages \
+             (id UUID, conversation_id UUID, role TEXT, content TEXT, created_at TIMESTAMPTZ)",
+        )
+        .execute(&db)
+        .await
+        .unwrap();
+        let user_id = sqlx::query_scalar!(
+            "INSERT INTO users (email) VALUES ('sharer@example.com') RETURNING id"
+        )
+        .fetch_one(&db)
+        .await
+        .unwrap();
+        let conversation_id = Uuid::new_v4();
+        sqlx::query!(
+            "INSERT INTO conversations (id, user_id) VALUES ($1, $2)",
+            conversation_id,
+            user_id.to_string()
+        )
+        .execute(&db)
+        .await
+        .unwrap();
+        let req = CreateShareRequest {
+            expires_in_hours: None,
+            read_only: None,
+        };
+        let link = create_share(&db, user_id, conversation_id, req, "https://app.example.com")
+            .await
+            .unwrap();
+        assert!(shared_conversation(&db, &link.token).await.is_ok());
+
+        let set_deleted = |deleted: bool| {
+            let db = db.clone();
+            async move {
+                sqlx::query!(
+                    r#"
+                    UPDATE users
+                    SET deleted_at = CASE WHEN $2 THEN NOW() END
+                    WHERE id = $1
+                    "#,
+                    user_id,
+                    deleted
+                )
+                .execute(&db)
+                .await
+                .unwrap();
+            }
+        };
+
+        set_deleted(true).await;
+        assert!(matches!(
+            shared_conversation(&db, &link.token).await,
+            Err(ChatError::NotFound(_))
+        ));
+
+        // A recovered account's links work again
+        set_deleted(false).await;
+        assert!(shared_conversation(&db, &link.token).await.is_ok());
+    }
+}
@@ -0,0 +1,81 @@
+//! Conversations with a streamed response in progress
+//!
+//! `stream_chat` holds a `StreamGuard` for as long as its SSE stream is alive,
+//! so requests that rewrite history can refuse to run underneath it. Tracking
+//! is per instance; a stream served by another instance isn't seen here.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use uuid::Uuid;
+
+#[derive(Clone, Default)]
+pub struct ActiveStreams {
+    counts: Arc<Mutex<HashMap<Uuid, usize>>>,
+}
+
+impl ActiveStreams {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Mark the conversation as streaming until the guard is dropped
+    pub fn start(&self, conversation_id: Uuid) -> StreamGuard {
+        *self.lock().entry(conversation_id).or_insert(0) += 1;
+        StreamGuard {
+            streams: self.clone(),
+            conversation_id,
+        }
+    }
+
+    /// Whether any response is streaming into the conversation
+    pub fn is_streaming(&self, conversation_id: Uuid) -> bool {
+        self.lock().contains_key(&conversation_id)
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<Uuid, usize>> {
+        self.counts
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// Keeps a conversation marked as streaming while alive
+pub struct StreamGuard {
+    streams: ActiveStreams,
+    conversation_id: Uuid,
+}
+
+impl Drop for StreamGuard {
+    fn drop(&mut self) {
+        let mut counts = self.streams.lock();
+        if let Some(count) = counts.get_mut(&self.conversation_id) {
+            *count -= 1;
+            if *count == 0 {
+                counts.remove(&self.conversation_id);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_streaming_until_last_guard_dropped() {
+        let streams = ActiveStreams::new();
+        let conversation_id = Uuid::new_v4();
+        assert!(!streams.is_streaming(conversation_id));
+
+        let first = streams.start(conversation_id);
+        let second = streams.start(conversation_id);
+        assert!(streams.is_streaming(conversation_id));
+        assert!(!streams.is_streaming(Uuid::new_v4()));
+
+        drop(first);
+        assert!(streams.is_streaming(conversation_id));
+        drop(second);
+        assert!(!streams.is_streaming(conversation_id));
+    }
+}
@@ -0,0 +1,132 @@
+//! Conversation tags
+//!
+//! Tags live in `conversations.tags`. Create and update requests carry the
+//! full set, so renaming or removing a tag is an update with the new set.
+//! Tags are trimmed and lowercased, and duplicates are dropped.
+
+use serde::Serialize;
+use sqlx::PgPool;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use super::error::{ChatError, ChatResult};
+
+/// Most tags a conversation can have
+pub const MAX_TAGS: usize = 10;
+
+/// Longest tag, in characters
+pub const MAX_TAG_CHARS: usize = 32;
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TagCount {
+    pub tag: String,
+    /// Conversations with this tag
+    pub count: i64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TagListResponse {
+    /// Most used first
+    pub tags: Vec<TagCount>,
+}
+
+/// Trim and lowercase a tag, rejecting empty and overlong ones
+pub fn normalize_tag(tag: &str) -> ChatResult<String> {
+    let tag = tag.trim().to_lowercase();
+    if tag.is_empty() {
+        return Err(ChatError::InvalidTag("Tags cannot be empty".to_string()));
+    }
+    if tag.chars().count() > MAX_TAG_CHARS {
+        return Err(ChatError::InvalidTag(format!(
+            "Tags can be at most {} characters",
+            MAX_TAG_CHARS
+        )));
+    }
+    Ok(tag)
+}
+
+/// Normalize a tag set, keeping the first occurrence of each tag
+pub fn normalize_tags(tags: &[String]) -> ChatResult<Vec<String>> {
+    let mut normalized: Vec<String> = Vec::with_capacity(tags.len());
+    for tag in tags {
+        let tag = normalize_tag(tag)?;
+        if !normalized.contains(&tag) {
+            normalized.push(tag);
+        }
+    }
+
+    if normalized.len() > MAX_TAGS {
+        return Err(ChatError::InvalidTag(format!(
+            "A conversation can have at most {} tags",
+            MAX_TAGS
+        )));
+    }
+    Ok(normalized)
+}
+
+/// Every tag the user has used, with how many conversations carry it
+pub async fn list_tags(db: &PgPool, user_id: Uuid) -> ChatResult<TagListResponse> {
+    let rows = sqlx::query!(
+        r#"
+        SELECT tag as "tag!", COUNT(*) as "count!"
+        FROM conversations c, unnest(c.tags) AS tag
+        WHERE c.user_id = $1
+        GROUP BY tag
+        ORDER BY 2 DESC, tag
+        "#,
+        user_id.to_string()
+    )
+    .fetch_all(db)
+    .await?;
+
+    Ok(TagListResponse {
+        tags: rows
+            .into_iter()
+            .map(|row| TagCount {
+                tag: row.tag,
+                count: row.count,
+            })
+            .collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tags(values: &[&str]) -> Vec<String> {
+        values.iter().map(|t| t.to_string()).collect()
+    }
+
+    #[test]
+    fn test_tags_are_normalized() {
+        assert_eq!(
+            normalize_tags(&tags(&[" Work ", "rust", "WORK", "Draft"])).unwrap(),
+            tags(&["work", "rust", "draft"])
+        );
+        assert!(normalize_tags(&[]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_tag_limits() {
+        let longest = "é".repeat(MAX_TAG_CHARS);
+        assert_eq!(normalize_tag(&longest).unwrap(), longest);
+        assert!(normalize_tag(&format!("{}a", longest)).is_err());
+        assert!(matches!(
+            normalize_tag("   "),
+            Err(ChatError::InvalidTag(_))
+        ));
+
+        let ten: Vec<String> = (0..MAX_TAGS).map(|i| format!("tag{}", i)).collect();
+        assert_eq!(normalize_tags(&ten).unwrap().len(), MAX_TAGS);
+
+        // Duplicates don't count towards the limit
+        let mut with_duplicate = ten.clone();
+        with_duplicate.push("TAG0".to_string());
+        assert!(normalize_tags(&with_duplicate).is_ok());
+
+        let mut eleven = ten;
+        eleven.push("tag10".to_string());
+        assert!(normalize_tags(&eleven).is_err());
+    }
+}
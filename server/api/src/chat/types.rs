@@ -1,25 +1,38 @@
 #![allow(dead_code)]
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
 use uuid::Uuid;
 
+use crate::common::pagination::InvalidCursor;
+
 // ============================================================================
 // REQUEST TYPES
 // ============================================================================
 
 /// Create a new conversation
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct CreateConversationRequest {
     pub title: Option<String>,
     #[serde(default)]
     pub metadata: serde_json::Value,
+    /// Up to 10 tags of at most 32 characters, stored lowercased
+    #[serde(default)]
+    pub tags: Vec<String>,
 }
 
 /// List conversations query parameters
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, IntoParams)]
 pub struct ListConversationsQuery {
     #[serde(default = "default_limit")]
     pub limit: i32,
+    /// `next_cursor` from the previous page
     pub cursor: Option<String>,
+    /// Also count all of the user's conversations into `total_count`
+    #[serde(default)]
+    pub include_total: bool,
+    /// Only conversations with this tag (case-insensitive)
+    pub tag: Option<String>,
 }
 
 fn default_limit() -> i32 {
@@ -27,46 +40,88 @@ fn default_limit() -> i32 {
 }
 
 /// Get conversation query parameters
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, IntoParams)]
 pub struct ConversationQuery {
     #[serde(default = "default_message_limit")]
-    pub limit: i32,
-    pub before: Option<Uuid>, // message_id for pagination
+    pub limit: i64,
+    /// Only return messages older than this: a message id or a Unix timestamp
+    pub before: Option<String>,
 }
 
-fn default_message_limit() -> i32 {
+fn default_message_limit() -> i64 {
     100
 }
 
+pub const MAX_MESSAGE_LIMIT: i64 = 200;
+
+/// Where a page of messages ends
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MessageCursor {
+    Message(Uuid),
+    Timestamp(DateTime<Utc>),
+}
+
+impl ConversationQuery {
+    pub fn limit(&self) -> i64 {
+        self.limit.clamp(1, MAX_MESSAGE_LIMIT)
+    }
+
+    pub fn cursor(&self) -> Result<Option<MessageCursor>, InvalidCursor> {
+        let Some(before) = self.before.as_deref() else {
+            return Ok(None);
+        };
+        if let Ok(id) = before.parse::<Uuid>() {
+            return Ok(Some(MessageCursor::Message(id)));
+        }
+        before
+            .parse::<i64>()
+            .ok()
+            .and_then(|seconds| DateTime::from_timestamp(seconds, 0))
+            .map(|at| Some(MessageCursor::Timestamp(at)))
+            .ok_or(InvalidCursor)
+    }
+}
+
 /// Update conversation metadata
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct UpdateConversationRequest {
     pub title: Option<String>,
     pub metadata: Option<serde_json::Value>,
+    /// Replaces the whole tag set; `[]` removes every tag
+    pub tags: Option<Vec<String>>,
 }
 
 /// Generate conversation title with AI
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct GenerateTitleRequest {
     pub user_message: String,
     pub assistant_message: String,
 }
 
 /// Generate title response
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct GenerateTitleResponse {
     pub title: String,
 }
 
 /// Send a message (non-streaming)
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize, ToSchema)]
 pub struct SendMessageRequest {
     pub message: String,
     pub config: Option<ChatConfig>,
+    /// Set when resending a message queued during an outage
+    pub pending_id: Option<Uuid>,
+}
+
+/// Replace a user message and regenerate the reply
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct EditMessageRequest {
+    pub message: String,
+    pub config: Option<ChatConfig>,
 }
 
 /// Chat configuration
-#[derive(Debug, Deserialize, Serialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone, ToSchema)]
 pub struct ChatConfig {
     pub temperature: Option<f32>,
     pub max_tokens: Option<i32>,
@@ -79,19 +134,58 @@ fn default_use_rag() -> bool {
     true
 }
 
+impl ChatConfig {
+    /// Fill in the settings this config leaves out from `defaults`
+    ///
+    /// `use_rag` always has a value, so it is never taken from `defaults`.
+    pub fn with_defaults(self, defaults: Option<&ChatConfig>) -> ChatConfig {
+        let Some(defaults) = defaults else {
+            return self;
+        };
+        ChatConfig {
+            temperature: self.temperature.or(defaults.temperature),
+            max_tokens: self.max_tokens.or(defaults.max_tokens),
+            use_rag: self.use_rag,
+            model: self.model.or_else(|| defaults.model.clone()),
+        }
+    }
+}
+
 /// Stream chat query parameters (SSE)
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, IntoParams)]
 pub struct StreamChatQuery {
     pub message: String,
-    #[serde(default = "default_temperature")]
-    pub temperature: f32,
-    #[serde(default = "default_max_tokens")]
-    pub max_tokens: i32,
-    #[serde(default = "default_use_rag")]
-    pub use_rag: bool,
+    pub temperature: Option<f32>,
+    pub max_tokens: Option<i32>,
+    pub use_rag: Option<bool>,
     pub model: Option<String>,
 }
 
+impl StreamChatQuery {
+    /// The query's settings, with missing ones taken from `defaults` and then
+    /// the built-in defaults
+    pub fn chat_config(&self, defaults: Option<&ChatConfig>) -> ChatConfig {
+        ChatConfig {
+            temperature: self
+                .temperature
+                .or(defaults.and_then(|d| d.temperature))
+                .or(Some(default_temperature())),
+            max_tokens: self
+                .max_tokens
+                .or(defaults.and_then(|d| d.max_tokens))
+                .or(Some(default_max_tokens())),
+            use_rag: self
+                .use_rag
+                .or(defaults.map(|d| d.use_rag))
+                .unwrap_or(default_use_rag()),
+            model: self
+                .model
+                .clone()
+                .or_else(|| defaults.and_then(|d| d.model.clone())),
+        }
+    }
+}
+
 fn default_temperature() -> f32 {
     0.7
 }
@@ -105,47 +199,56 @@ fn default_max_tokens() -> i32 {
 // ============================================================================
 
 /// Conversation response
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct ConversationResponse {
     pub id: Uuid,
     pub user_id: String,
     pub title: Option<String>,
     pub message_count: i32,
+    pub tags: Vec<String>,
     pub created_at: i64,
     pub updated_at: i64,
 }
 
 /// List conversations response
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct ConversationListResponse {
     pub conversations: Vec<ConversationSummary>,
     pub next_cursor: Option<String>,
-    pub total_count: i32,
+    /// Only present with `include_total=true`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total_count: Option<i32>,
 }
 
 /// Conversation summary for list view
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct ConversationSummary {
     pub id: Uuid,
     pub title: Option<String>,
     pub message_count: i32,
     pub last_message_preview: Option<String>,
+    pub tags: Vec<String>,
     pub created_at: i64,
     pub updated_at: i64,
 }
 
 /// Conversation with messages
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct ConversationWithMessages {
     pub id: Uuid,
     pub title: Option<String>,
+    /// Oldest first
     pub messages: Vec<ChatMessage>,
+    /// Whether older messages exist
+    pub has_more: bool,
+    /// Pass as `before` to load the previous page
+    pub next_before: Option<String>,
     pub created_at: i64,
     pub updated_at: i64,
 }
 
 /// Chat message
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct ChatMessage {
     pub id: Uuid,
     pub role: MessageRole,
@@ -156,7 +259,7 @@ pub struct ChatMessage {
 }
 
 /// Message role
-#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, ToSchema)]
 #[serde(rename_all = "lowercase")]
 pub enum MessageRole {
     User,
@@ -164,8 +267,19 @@ pub enum MessageRole {
     System,
 }
 
+impl MessageRole {
+    /// Parse the role column of `chat_messages`; unknown roles read as system
+    pub fn from_db(role: &str) -> Self {
+        match role {
+            "user" => MessageRole::User,
+            "assistant" => MessageRole::Assistant,
+            _ => MessageRole::System,
+        }
+    }
+}
+
 /// Source chunk from RAG retrieval
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
 pub struct SourceChunk {
     pub chunk_id: String,
     pub document_id: String,
@@ -178,19 +292,22 @@ pub struct SourceChunk {
 }
 
 /// Message response (non-streaming)
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct MessageResponse {
     pub message_id: Uuid,
     pub conversation_id: Uuid,
     pub role: MessageRole,
     pub content: String,
     pub sources: Vec<SourceChunk>,
-    pub metrics: ChatMetrics,
+    /// Absent when the Intelligence service sent none and
+    /// `CHAT_MISSING_METRICS=omit`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metrics: Option<ChatMetrics>,
     pub created_at: i64,
 }
 
 /// Chat metrics
-#[derive(Debug, Serialize, Clone)]
+#[derive(Debug, Serialize, Clone, ToSchema)]
 pub struct ChatMetrics {
     pub tokens_used: i32,
     pub context_tokens: i32,
@@ -200,7 +317,7 @@ pub struct ChatMetrics {
 }
 
 /// Delete conversation response
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct DeleteConversationResponse {
     pub success: bool,
     pub conversation_id: Uuid,
@@ -212,7 +329,7 @@ pub struct DeleteConversationResponse {
 // ============================================================================
 
 /// SSE event types
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum StreamEvent {
     MessageStart {
@@ -237,3 +354,52 @@ pub enum StreamEvent {
         message: String,
     },
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(
+        temperature: Option<f32>,
+        max_tokens: Option<i32>,
+        use_rag: bool,
+        model: Option<&str>,
+    ) -> ChatConfig {
+        ChatConfig {
+            temperature,
+            max_tokens,
+            use_rag,
+            model: model.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_request_settings_override_defaults() {
+        let defaults = config(Some(0.2), Some(512), false, Some("small"));
+        let merged = config(Some(0.9), Some(2048), true, Some("large"))
+            .with_defaults(Some(&defaults));
+
+        assert_eq!(merged.temperature, Some(0.9));
+        assert_eq!(merged.max_tokens, Some(2048));
+        assert!(merged.use_rag);
+        assert_eq!(merged.model.as_deref(), Some("large"));
+    }
+
+    #[test]
+    fn test_missing_settings_fall_back_to_defaults() {
+        let defaults = config(Some(0.2), Some(512), false, Some("small"));
+        let merged = config(None, None, true, None).with_defaults(Some(&defaults));
+
+        assert_eq!(merged.temperature, Some(0.2));
+        assert_eq!(merged.max_tokens, Some(512));
+        // Always set in the request, so never taken from the defaults
+        assert!(merged.use_rag);
+        assert_eq!(merged.model.as_deref(), Some("small"));
+
+        let unset = config(None, None, false, None).with_defaults(None);
+        assert_eq!(unset.temperature, None);
+        assert_eq!(unset.max_tokens, None);
+        assert!(!unset.use_rag);
+        assert_eq!(unset.model, None);
+    }
+}
@@ -38,3 +38,90 @@ pub fn start_periodic_task<F, Fut>(
         interval_seconds
     );
 }
+
+/// Run a bounded delete repeatedly until a batch comes back short
+///
+/// Keeps each statement (and the locks it holds) small on large tables.
+/// `delete_batch` receives the batch size and returns the rows it deleted.
+/// Returns the total number of rows deleted.
+pub async fn delete_in_batches<F, Fut>(batch_size: i64, mut delete_batch: F) -> Result<u64, sqlx::Error>
+where
+    F: FnMut(i64) -> Fut,
+    Fut: std::future::Future<Output = Result<u64, sqlx::Error>>,
+{
+    let batch_size = batch_size.max(1);
+    let mut total = 0;
+
+    loop {
+        let deleted = delete_batch(batch_size).await?;
+        total += deleted;
+
+        if deleted < batch_size as u64 {
+            return Ok(total);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    #[tokio::test]
+    async fn test_delete_in_batches_until_short_batch() {
+        let mut remaining = 2500u64;
+        let mut calls = 0;
+
+        let total = delete_in_batches(1000, |limit| {
+            calls += 1;
+            let deleted = remaining.min(limit as u64);
+            remaining -= deleted;
+            async move { Ok(deleted) }
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(total, 2500);
+        assert_eq!(calls, 3);
+    }
+
+    #[tokio::test]
+    async fn test_delete_in_batches_exact_multiple_needs_final_empty_batch() {
+        let mut remaining = 2000u64;
+        let mut calls = 0;
+
+        let total = delete_in_batches(1000, |limit| {
+            calls += 1;
+            let deleted = remaining.min(limit as u64);
+            remaining -= deleted;
+            async move { Ok(deleted) }
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(total, 2000);
+        assert_eq!(calls, 3);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_periodic_task_uses_configured_interval() {
+        let db = sqlx::postgres::PgPoolOptions::new()
+            .connect_lazy("postgres://localhost/unused")
+            .unwrap();
+        let runs = Arc::new(AtomicU64::new(0));
+
+        let counter = runs.clone();
+        start_periodic_task(db, "Test task", 30, move |_| {
+            let counter = counter.clone();
+            async move {
+                counter.fetch_add(1, Ordering::SeqCst);
+                Ok(0)
+            }
+        });
+
+        // First tick fires immediately, then every 30s
+        tokio::time::sleep(Duration::from_secs(61)).await;
+        assert_eq!(runs.load(Ordering::SeqCst), 3);
+    }
+}
@@ -1,3 +1,5 @@
 pub mod background;
 pub mod openapi;
+pub mod pagination;
+pub mod patch;
 pub mod validation;
@@ -0,0 +1,128 @@
+//! Signed pagination cursors
+//!
+//! Cursors handed to clients are opaque: a base64url payload followed by an
+//! HMAC-SHA256 signature. A tampered or malformed cursor is rejected instead
+//! of silently restarting pagination from the first page.
+
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use thiserror::Error;
+use uuid::Uuid;
+
+type HmacSha256 = Hmac<Sha256>;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+#[error("Invalid cursor")]
+pub struct InvalidCursor;
+
+fn mac(secret: &str) -> HmacSha256 {
+    HmacSha256::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length")
+}
+
+/// Sign an arbitrary cursor payload
+pub fn encode_cursor(secret: &str, payload: &str) -> String {
+    let encoded = URL_SAFE_NO_PAD.encode(payload);
+    let mut mac = mac(secret);
+    mac.update(encoded.as_bytes());
+    let signature = URL_SAFE_NO_PAD.encode(mac.finalize().into_bytes());
+
+    format!("{}.{}", encoded, signature)
+}
+
+/// Verify a cursor's signature and return its payload
+pub fn decode_cursor(secret: &str, cursor: &str) -> Result<String, InvalidCursor> {
+    let (encoded, signature) = cursor.split_once('.').ok_or(InvalidCursor)?;
+    let signature = URL_SAFE_NO_PAD
+        .decode(signature)
+        .map_err(|_| InvalidCursor)?;
+
+    let mut mac = mac(secret);
+    mac.update(encoded.as_bytes());
+    mac.verify_slice(&signature).map_err(|_| InvalidCursor)?;
+
+    let payload = URL_SAFE_NO_PAD.decode(encoded).map_err(|_| InvalidCursor)?;
+    String::from_utf8(payload).map_err(|_| InvalidCursor)
+}
+
+/// Cursor for keyset pagination over `(timestamp, id)`, newest first
+///
+/// Timestamps keep Postgres' microsecond precision so the next page starts
+/// exactly after the last row.
+pub fn encode_keyset_cursor(secret: &str, at: DateTime<Utc>, id: Uuid) -> String {
+    encode_cursor(secret, &format!("{}:{}", at.timestamp_micros(), id))
+}
+
+/// Position after which the next page starts; a missing cursor is the first
+/// page
+pub fn decode_keyset_cursor(
+    secret: &str,
+    cursor: Option<&str>,
+) -> Result<Option<(DateTime<Utc>, Uuid)>, InvalidCursor> {
+    let Some(cursor) = cursor else {
+        return Ok(None);
+    };
+
+    let payload = decode_cursor(secret, cursor)?;
+    let (micros, id) = payload.split_once(':').ok_or(InvalidCursor)?;
+    let at = micros
+        .parse::<i64>()
+        .ok()
+        .and_then(DateTime::from_timestamp_micros)
+        .ok_or(InvalidCursor)?;
+    let id = Uuid::parse_str(id).map_err(|_| InvalidCursor)?;
+
+    Ok(Some((at, id)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECRET: &str = "test-cursor-secret";
+
+    #[test]
+    fn test_valid_cursor_roundtrip() {
+        let at = DateTime::from_timestamp_micros(1_700_000_000_123_456).unwrap();
+        let id = Uuid::new_v4();
+        let cursor = encode_keyset_cursor(SECRET, at, id);
+        assert_eq!(decode_keyset_cursor(SECRET, Some(&cursor)), Ok(Some((at, id))));
+    }
+
+    #[test]
+    fn test_missing_cursor_is_first_page() {
+        assert_eq!(decode_keyset_cursor(SECRET, None), Ok(None));
+    }
+
+    #[test]
+    fn test_tampered_cursor_rejected() {
+        let cursor = encode_keyset_cursor(SECRET, Utc::now(), Uuid::new_v4());
+        let (_, signature) = cursor.split_once('.').unwrap();
+        let forged = format!("{}.{}", URL_SAFE_NO_PAD.encode("0"), signature);
+
+        assert_eq!(
+            decode_keyset_cursor(SECRET, Some(&forged)),
+            Err(InvalidCursor)
+        );
+        assert_eq!(
+            decode_keyset_cursor("other-secret", Some(&cursor)),
+            Err(InvalidCursor)
+        );
+        assert_eq!(decode_keyset_cursor(SECRET, Some("40")), Err(InvalidCursor));
+        assert_eq!(decode_keyset_cursor(SECRET, Some("")), Err(InvalidCursor));
+    }
+
+    #[test]
+    fn test_signed_malformed_position_rejected() {
+        // Offset cursors from before keyset pagination are signed but no
+        // longer valid positions
+        for payload in ["20", "-20:not-a-uuid", &format!("x:{}", Uuid::new_v4())] {
+            let cursor = encode_cursor(SECRET, payload);
+            assert_eq!(
+                decode_keyset_cursor(SECRET, Some(&cursor)),
+                Err(InvalidCursor)
+            );
+        }
+    }
+}
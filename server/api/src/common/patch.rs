@@ -0,0 +1,91 @@
+//! Tri-state fields for partial updates
+//!
+//! `Option<T>` can't tell a field that was left out of a PATCH body from one
+//! sent as `null`. With `#[serde(default)]`, a `Patch<T>` field is
+//! `Unchanged` when absent, `Clear` when `null` and `Set` otherwise.
+
+use serde::{Deserialize, Deserializer};
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum Patch<T> {
+    /// Field absent: keep the stored value
+    #[default]
+    Unchanged,
+    /// Field sent as `null`: remove the stored value
+    Clear,
+    /// Field sent with a value: replace the stored value
+    Set(T),
+}
+
+impl<T> Patch<T> {
+    /// The new value, if one was sent
+    pub fn as_set(&self) -> Option<&T> {
+        match self {
+            Patch::Set(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn is_unchanged(&self) -> bool {
+        matches!(self, Patch::Unchanged)
+    }
+
+    /// Split into "should the column be written" and the value to write, for
+    /// `CASE WHEN $n THEN $m ELSE column END` updates
+    pub fn into_update(self) -> (bool, Option<T>) {
+        match self {
+            Patch::Unchanged => (false, None),
+            Patch::Clear => (true, None),
+            Patch::Set(value) => (true, Some(value)),
+        }
+    }
+}
+
+impl<'de, T> Deserialize<'de> for Patch<T>
+where
+    T: Deserialize<'de>,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        // Only called when the field is present; absence falls back to `Default`
+        Ok(match Option::<T>::deserialize(deserializer)? {
+            Some(value) => Patch::Set(value),
+            None => Patch::Clear,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Deserialize)]
+    struct Update {
+        #[serde(default)]
+        name: Patch<String>,
+    }
+
+    fn parse(json: &str) -> Patch<String> {
+        serde_json::from_str::<Update>(json).unwrap().name
+    }
+
+    #[test]
+    fn test_absent_null_and_value() {
+        assert_eq!(parse(r#"{}"#), Patch::Unchanged);
+        assert_eq!(parse(r#"{"name": null}"#), Patch::Clear);
+        assert_eq!(parse(r#"{"name": "Ada"}"#), Patch::Set("Ada".to_string()));
+        assert!(serde_json::from_str::<Update>(r#"{"name": 3}"#).is_err());
+    }
+
+    #[test]
+    fn test_into_update() {
+        assert_eq!(Patch::<String>::Unchanged.into_update(), (false, None));
+        assert_eq!(Patch::<String>::Clear.into_update(), (true, None));
+        assert_eq!(
+            Patch::Set("Ada".to_string()).into_update(),
+            (true, Some("Ada".to_string()))
+        );
+    }
+}
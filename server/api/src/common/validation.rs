@@ -1,10 +1,107 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use axum::{
+    Json,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
 use once_cell::sync::Lazy;
 use regex::Regex;
+use serde::Serialize;
+use serde_json::json;
 
 /// Email validation regex
 static EMAIL_REGEX: Lazy<Regex> =
     Lazy::new(|| Regex::new(r"^[a-zA-Z0-9._%+-]+@[a-zA-Z0-9.-]+\.[a-zA-Z]{2,}$").unwrap());
 
+/// Usernames: letters, digits, `_`, `-` and `.`
+static USERNAME_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"^[a-z0-9_-]+$").unwrap());
+
+const USERNAME_MIN_LENGTH: usize = 3;
+const USERNAME_MAX_LENGTH: usize = 32;
+const NAME_MAX_LENGTH: usize = 100;
+
+/// Usernames that could be mistaken for the service or its staff, or clash
+/// with routes
+const RESERVED_USERNAMES: &[&str] = &[
+    "admin",
+    "administrator",
+    "api",
+    "auth",
+    "help",
+    "me",
+    "moderator",
+    "null",
+    "opentier",
+    "root",
+    "security",
+    "settings",
+    "staff",
+    "support",
+    "system",
+    "undefined",
+    "user",
+];
+
+/// Validation failures keyed by request field
+///
+/// Responds with `422 {"error": "validation_failed", "message", "fields": {field: [messages]}}`
+/// so clients can show each message next to its form field.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(transparent)]
+pub struct ValidationErrors(BTreeMap<String, Vec<String>>);
+
+impl ValidationErrors {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a failure for `field`
+    pub fn add(&mut self, field: &str, message: impl Into<String>) {
+        self.0
+            .entry(field.to_string())
+            .or_default()
+            .push(message.into());
+    }
+
+    /// Record the error from a single-field validator, if any
+    pub fn check(&mut self, field: &str, result: Result<(), String>) -> &mut Self {
+        if let Err(message) = result {
+            self.add(field, message);
+        }
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// `Ok` when nothing failed
+    pub fn into_result(self) -> Result<(), Self> {
+        if self.is_empty() { Ok(()) } else { Err(self) }
+    }
+}
+
+impl fmt::Display for ValidationErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let messages: Vec<&str> = self.0.values().flatten().map(String::as_str).collect();
+        f.write_str(&messages.join("; "))
+    }
+}
+
+impl IntoResponse for ValidationErrors {
+    fn into_response(self) -> Response {
+        let body = Json(json!({
+            "error": "validation_failed",
+            // Older clients only read `message`
+            "message": self.to_string(),
+            "fields": self,
+        }));
+        (StatusCode::UNPROCESSABLE_ENTITY, body).into_response()
+    }
+}
+
 /// Validate email format
 pub fn validate_email(email: &str) -> Result<(), String> {
     if email.is_empty() {
@@ -43,6 +140,52 @@ pub fn validate_password(password: &str) -> Result<(), String> {
     Ok(())
 }
 
+/// Validate username format
+///
+/// Usernames are lowercase so they can't differ from another only by case.
+pub fn validate_username(username: &str) -> Result<(), String> {
+    let length = username.chars().count();
+    if !(USERNAME_MIN_LENGTH..=USERNAME_MAX_LENGTH).contains(&length) {
+        return Err(format!(
+            "Username must be {} to {} characters long",
+            USERNAME_MIN_LENGTH, USERNAME_MAX_LENGTH
+        ));
+    }
+
+    if !USERNAME_REGEX.is_match(username) {
+        return Err(
+            "Username may only contain lowercase letters, numbers, underscores and hyphens"
+                .to_string(),
+        );
+    }
+
+    if username.starts_with(['_', '-']) || username.ends_with(['_', '-']) {
+        return Err("Username must start and end with a letter or number".to_string());
+    }
+
+    if RESERVED_USERNAMES.contains(&username) {
+        return Err(format!("Username \"{}\" is reserved", username));
+    }
+
+    Ok(())
+}
+
+/// Validate display name length
+pub fn validate_name(name: &str) -> Result<(), String> {
+    if name.trim().is_empty() {
+        return Err("Name cannot be empty".to_string());
+    }
+
+    if name.chars().count() > NAME_MAX_LENGTH {
+        return Err(format!(
+            "Name too long (max {} characters)",
+            NAME_MAX_LENGTH
+        ));
+    }
+
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -64,4 +207,45 @@ mod tests {
         assert!(validate_password("ALLUPPERCASE123").is_err());
         assert!(validate_password("NoNumbers").is_err());
     }
+
+    #[test]
+    fn test_username_and_name_validation() {
+        assert!(validate_username("ada_lovelace-1").is_ok());
+        assert!(validate_username("ab").is_err());
+        assert!(validate_username(&"a".repeat(33)).is_err());
+        assert!(validate_username("ada lovelace").is_err());
+        assert!(validate_username("Ada").is_err());
+        assert!(validate_username("ada.lovelace").is_err());
+        assert!(validate_username("ada@example.com").is_err());
+        assert!(validate_username("ad\u{e9}").is_err());
+        assert!(validate_username("_ada").is_err());
+        assert!(validate_username("ada-").is_err());
+        assert!(validate_username("admin").is_err());
+        assert!(validate_username("support").is_err());
+        assert!(validate_username("admin_ada").is_ok());
+
+        assert!(validate_name("Ada Lovelace").is_ok());
+        assert!(validate_name("   ").is_err());
+        assert!(validate_name(&"a".repeat(101)).is_err());
+    }
+
+    #[test]
+    fn test_validation_errors_accumulate() {
+        let mut errors = ValidationErrors::new();
+        errors
+            .check("email", validate_email("invalid"))
+            .check("password", validate_password("Password123"))
+            .check("username", validate_username("ab"));
+        errors.add("email", "Email already exists");
+
+        let fields = serde_json::to_value(&errors).unwrap();
+        assert_eq!(fields["email"].as_array().unwrap().len(), 2);
+        assert!(fields.get("password").is_none());
+        assert_eq!(
+            fields["username"][0],
+            "Username must be 3 to 32 characters long"
+        );
+        assert!(errors.into_result().is_err());
+        assert!(ValidationErrors::new().into_result().is_ok());
+    }
 }
@@ -0,0 +1,57 @@
+//! Optional Redis cache
+//!
+//! Enabled by setting `REDIS_URL`. Callers must behave identically without
+//! it: a missing cache is `None`, and Redis errors are logged and treated as
+//! cache misses so PostgreSQL stays the source of truth.
+
+use std::time::Duration;
+
+use redis::{
+    Client, RedisError,
+    aio::{ConnectionManager, ConnectionManagerConfig},
+};
+
+/// Shared, auto-reconnecting Redis connection (cheap to clone)
+pub type RedisPool = ConnectionManager;
+
+/// How long startup waits for Redis before running without it
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Commands slower than this fail so callers fall back quickly
+const RESPONSE_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Connect to Redis if `redis_url` is set
+///
+/// Returns `None` (with a warning) when Redis cannot be reached.
+pub async fn connect(redis_url: Option<&str>) -> Option<RedisPool> {
+    let url = redis_url?;
+
+    match try_connect(url).await {
+        Ok(pool) => Some(pool),
+        Err(e) => {
+            tracing::warn!("Redis unreachable, continuing without cache: {}", e);
+            None
+        }
+    }
+}
+
+async fn try_connect(url: &str) -> Result<RedisPool, RedisError> {
+    let client = Client::open(url)?;
+    let config = ConnectionManagerConfig::new()
+        .set_connection_timeout(CONNECT_TIMEOUT)
+        .set_response_timeout(RESPONSE_TIMEOUT)
+        .set_number_of_retries(1);
+
+    ConnectionManager::new_with_config(client, config).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_runs_without_redis() {
+        assert!(connect(None).await.is_none());
+        assert!(connect(Some("redis://127.0.0.1:1")).await.is_none());
+    }
+}
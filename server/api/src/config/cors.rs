@@ -5,6 +5,8 @@ use axum::http::{
 use tower_http::cors::{Any, CorsLayer};
 
 use super::env::CorsConfig;
+use crate::chat::idempotency::{IDEMPOTENCY_KEY_HEADER, REPLAYED_HEADER};
+use crate::middleware::request_id::REQUEST_ID_HEADER;
 
 /// Build CORS layer from configuration
 pub fn build_cors_layer(config: &CorsConfig) -> CorsLayer {
@@ -15,6 +17,7 @@ pub fn build_cors_layer(config: &CorsConfig) -> CorsLayer {
             .allow_origin(Any)
             .allow_methods(Any)
             .allow_headers(Any)
+            .expose_headers([REQUEST_ID_HEADER, REPLAYED_HEADER])
             .allow_credentials(false); // Cannot use credentials with wildcard origin
     }
 
@@ -36,6 +39,7 @@ pub fn build_cors_layer(config: &CorsConfig) -> CorsLayer {
             .allow_origin(Any)
             .allow_methods(Any)
             .allow_headers(Any)
+            .expose_headers([REQUEST_ID_HEADER, REPLAYED_HEADER])
             .allow_credentials(false);
     }
 
@@ -56,7 +60,14 @@ pub fn build_cors_layer(config: &CorsConfig) -> CorsLayer {
             Method::DELETE,
             Method::OPTIONS,
         ])
-        .allow_headers([AUTHORIZATION, CONTENT_TYPE, ACCEPT])
+        .allow_headers([
+            AUTHORIZATION,
+            CONTENT_TYPE,
+            ACCEPT,
+            REQUEST_ID_HEADER,
+            IDEMPOTENCY_KEY_HEADER,
+        ])
+        .expose_headers([REQUEST_ID_HEADER, REPLAYED_HEADER])
         .allow_credentials(true)
 }
 
@@ -1,11 +1,81 @@
+use axum::{
+    Json,
+    http::{HeaderValue, StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use serde_json::json;
 use sqlx::{PgPool, postgres::PgPoolOptions};
 use std::time::Duration;
 
-pub async fn connect(database_url: &str) -> PgPool {
+use crate::config::env::DatabaseConfig;
+
+pub async fn connect(config: &DatabaseConfig) -> PgPool {
     PgPoolOptions::new()
-        .max_connections(10)
-        .acquire_timeout(Duration::from_secs(5))
-        .connect(database_url)
+        .max_connections(config.max_connections)
+        .min_connections(config.min_connections)
+        .acquire_timeout(Duration::from_secs(config.connect_timeout_seconds))
+        .idle_timeout(config.idle_timeout_seconds.map(Duration::from_secs))
+        .connect(&config.url)
         .await
         .expect("Failed to connect to Postgres")
 }
+
+/// Whether a query failed because no pooled connection freed up in time
+pub fn is_pool_exhausted(error: &sqlx::Error) -> bool {
+    matches!(error, sqlx::Error::PoolTimedOut)
+}
+
+/// `503 pool_exhausted`, shared by every error type that wraps `sqlx::Error`
+///
+/// The pool being busy is temporary, so clients are told to retry rather
+/// than getting a generic 500.
+pub fn pool_exhausted_response() -> Response {
+    tracing::error!("Database pool exhausted: timed out waiting for a connection");
+
+    let mut response = (
+        StatusCode::SERVICE_UNAVAILABLE,
+        Json(json!({
+            "error": "pool_exhausted",
+            "message": "The server is busy, please retry shortly",
+        })),
+    )
+        .into_response();
+    response
+        .headers_mut()
+        .insert(header::RETRY_AFTER, HeaderValue::from_static("1"));
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::AuthError;
+    use crate::chat::error::ChatError;
+
+    async fn error_code(response: Response) -> (StatusCode, serde_json::Value) {
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_pool_timeout_is_service_unavailable() {
+        let responses = [
+            AuthError::Database(sqlx::Error::PoolTimedOut).into_response(),
+            ChatError::from(sqlx::Error::PoolTimedOut).into_response(),
+        ];
+
+        for response in responses {
+            assert_eq!(response.headers()[header::RETRY_AFTER], "1");
+            let (status, body) = error_code(response).await;
+            assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+            assert_eq!(body["error"], "pool_exhausted");
+        }
+
+        let (status, _) =
+            error_code(AuthError::Database(sqlx::Error::RowNotFound).into_response()).await;
+        assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+    }
+}
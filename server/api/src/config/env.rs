@@ -1,5 +1,13 @@
 use std::env;
 
+use ipnetwork::IpNetwork;
+
+use crate::auth::captcha::CaptchaProvider;
+use crate::grpc::circuit_breaker::BreakerConfig;
+use crate::middleware::rate_limit::RateLimitBackend;
+use crate::observability::request_log;
+use crate::storage::StorageBackend;
+
 /// Centralized environment configuration
 #[derive(Debug, Clone)]
 pub struct Config {
@@ -10,23 +18,42 @@ pub struct Config {
     pub security: SecurityConfig,
     pub cors: CorsConfig,
     pub rate_limit: RateLimitConfig,
+    pub captcha: CaptchaConfig,
+    pub logging: LoggingConfig,
+    pub chat: ChatConfig,
+    pub jwt: JwtConfig,
+    pub storage: StorageConfig,
+    pub cache: CacheConfig,
+    pub telemetry: TelemetryConfig,
+    pub ingestion: IngestionConfig,
+    pub intelligence: IntelligenceConfig,
 }
 
 #[derive(Debug, Clone)]
 pub struct DatabaseConfig {
     pub url: String,
+    pub max_connections: u32,
+    pub min_connections: u32,
+    /// How long a query waits for a free connection before failing
+    pub connect_timeout_seconds: u64,
+    /// Close connections idle for longer than this (sqlx default when unset)
+    pub idle_timeout_seconds: Option<u64>,
 }
 
 #[derive(Debug, Clone)]
 pub struct ServerConfig {
     pub host: String,
     pub port: u16,
+    /// Reverse proxies whose `X-Forwarded-For` / `X-Real-IP` are believed
+    pub trusted_proxies: Vec<IpNetwork>,
 }
 
 #[derive(Debug, Clone)]
 pub struct OAuthConfig {
     pub google: GoogleOAuthConfig,
     pub github: GitHubOAuthConfig,
+    /// Refresh stored provider tokens ahead of expiry in the background
+    pub token_refresh_enabled: bool,
 }
 
 #[derive(Debug, Clone)]
@@ -50,8 +77,17 @@ pub struct EmailConfig {
     pub smtp_username: String,
     pub smtp_password: String,
     pub from_email: String,
+    /// Product name used in email subjects and bodies
+    pub app_name: String,
+    /// Send a welcome email after signup
+    pub send_welcome_email: bool,
     pub frontend_url: String,
     pub api_url: String,
+    pub templates_dir: String,
+    /// Capture emails in memory instead of sending them (development only)
+    pub dev_mailbox: bool,
+    /// HMAC key for unsubscribe links
+    pub unsubscribe_signing_secret: String,
 }
 
 #[derive(Debug, Clone)]
@@ -59,6 +95,18 @@ pub struct SecurityConfig {
     pub session_expiry_seconds: u64,
     pub verification_token_expiry_seconds: u64,
     pub password_reset_token_expiry_seconds: u64,
+    pub auth_event_retention_days: u32,
+    /// Days a soft-deleted account can be recovered before it is purged
+    pub deleted_account_retention_days: u32,
+    pub session_cleanup_interval_seconds: u64,
+    /// Maximum sessions deleted per statement during cleanup
+    pub session_cleanup_batch_size: i64,
+    /// HMAC key for pagination cursors
+    pub cursor_signing_secret: String,
+    /// Active sessions allowed per user before the oldest is evicted (0 = unlimited)
+    pub max_sessions_per_user: u32,
+    /// `Content-Security-Policy` for responses (`default-src 'self'` when unset)
+    pub csp_policy: Option<String>,
 }
 
 #[derive(Debug, Clone)]
@@ -68,10 +116,163 @@ pub struct CorsConfig {
 
 #[derive(Debug, Clone)]
 pub struct RateLimitConfig {
+    /// In-memory (default) or Redis for limits shared across replicas
+    pub backend: RateLimitBackend,
+    /// Per-IP limit on standard auth routes (signin, signup, OAuth)
+    pub auth: IpRateLimit,
+    /// Per-IP limit on sensitive auth routes (password reset, recovery)
+    pub sensitive: IpRateLimit,
+    /// Per-user limits for authenticated route groups
+    pub user_chat: UserRateLimit,
+    pub user_account: UserRateLimit,
+    pub user_admin: UserRateLimit,
+}
+
+/// Per-IP rate limit: `max_requests` per `window_seconds`, all usable as a burst
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IpRateLimit {
     pub max_requests: u32,
     pub window_seconds: u64,
 }
 
+/// Token bucket settings for a per-user rate limit
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UserRateLimit {
+    pub per_minute: u32,
+    pub burst: u32,
+}
+
+/// HTTP request logging levels; `None` disables logging
+#[derive(Debug, Clone)]
+pub struct LoggingConfig {
+    pub request_level: Option<tracing::Level>,
+    pub route_levels: Vec<(String, Option<tracing::Level>)>,
+}
+
+/// How to handle chat responses that arrive without usage metrics
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MissingMetricsMode {
+    /// Log a warning and report zeroed metrics
+    #[default]
+    Default,
+    /// Leave `metrics` out of the response
+    Omit,
+    /// Fail the request
+    Error,
+}
+
+impl MissingMetricsMode {
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "default" | "zero" => Some(MissingMetricsMode::Default),
+            "omit" => Some(MissingMetricsMode::Omit),
+            "error" => Some(MissingMetricsMode::Error),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ChatConfig {
+    pub missing_metrics: MissingMetricsMode,
+    /// Models advertised to clients; empty means the Intelligence default only
+    pub models: Vec<String>,
+    /// Store messages as pending with a 202 when the Intelligence service is down
+    pub queue_on_outage: bool,
+    /// Monthly token budget for users without their own (`None` = unlimited)
+    pub monthly_token_budget: Option<i64>,
+}
+
+/// Optional stateless access tokens issued alongside session tokens
+#[derive(Debug, Clone)]
+pub struct JwtConfig {
+    pub enabled: bool,
+    pub secret: Option<String>,
+    pub access_token_ttl_seconds: u64,
+}
+
+/// Object storage settings
+#[derive(Debug, Clone)]
+pub struct StorageConfig {
+    pub backend: StorageBackend,
+    /// Root directory for the local backend
+    pub local_root: String,
+    /// Base URL of this API, used in local presigned URLs
+    pub public_url: String,
+    /// Key for signing local presigned URLs
+    pub signing_secret: String,
+    pub s3_bucket: Option<String>,
+    pub s3_region: Option<String>,
+    /// Custom endpoint for S3-compatible services
+    pub s3_endpoint: Option<String>,
+}
+
+/// Optional Redis connection shared by the session cache and rate limiting
+#[derive(Debug, Clone)]
+pub struct CacheConfig {
+    pub redis_url: Option<String>,
+}
+
+/// OpenTelemetry trace export; disabled unless an OTLP endpoint is set
+#[derive(Debug, Clone)]
+pub struct TelemetryConfig {
+    pub otlp_endpoint: Option<String>,
+    pub service_name: String,
+}
+
+/// A host or network in the ingestion URL allow/deny lists
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HostRule {
+    /// Matches addresses in the network
+    Network(IpNetwork),
+    /// Matches the domain and its subdomains
+    Domain(String),
+}
+
+impl HostRule {
+    /// Whether `host`, a lowercase domain, is covered by this rule
+    pub fn matches_domain(&self, host: &str) -> bool {
+        match self {
+            HostRule::Domain(domain) => {
+                host == domain
+                    || host
+                        .strip_suffix(domain.as_str())
+                        .is_some_and(|prefix| prefix.ends_with('.'))
+            }
+            HostRule::Network(_) => false,
+        }
+    }
+
+    pub fn matches_ip(&self, ip: std::net::IpAddr) -> bool {
+        match self {
+            HostRule::Network(network) => network.contains(ip),
+            HostRule::Domain(_) => false,
+        }
+    }
+}
+
+/// Restrictions on URLs the Intelligence crawler is asked to fetch
+#[derive(Debug, Clone, Default)]
+pub struct IngestionConfig {
+    /// Allowed even when they resolve to private addresses (e.g. an internal wiki)
+    pub url_allowlist: Vec<HostRule>,
+    /// Always rejected, in addition to private and reserved addresses
+    pub url_denylist: Vec<HostRule>,
+}
+
+/// How calls to the Intelligence service behave while it is failing
+#[derive(Debug, Clone, Default)]
+pub struct IntelligenceConfig {
+    pub circuit_breaker: BreakerConfig,
+}
+
+/// Optional CAPTCHA settings; verification is disabled unless both are set
+#[derive(Debug, Clone)]
+pub struct CaptchaConfig {
+    pub provider: Option<CaptchaProvider>,
+    pub secret: Option<String>,
+}
+
 impl Config {
     /// Load configuration from environment variables
     pub fn from_env() -> Result<Self, Box<dyn std::error::Error>> {
@@ -83,14 +284,168 @@ impl Config {
             security: SecurityConfig::from_env()?,
             cors: CorsConfig::from_env()?,
             rate_limit: RateLimitConfig::from_env()?,
+            captcha: CaptchaConfig::from_env()?,
+            logging: LoggingConfig::from_env()?,
+            chat: ChatConfig::from_env()?,
+            jwt: JwtConfig::from_env()?,
+            storage: StorageConfig::from_env()?,
+            cache: CacheConfig::from_env()?,
+            telemetry: TelemetryConfig::from_env()?,
+            ingestion: IngestionConfig::from_env()?,
+            intelligence: IntelligenceConfig::from_env()?,
         })
     }
 }
 
+#[cfg(test)]
+impl Config {
+    /// Configuration for tests that run the full router: in-memory rate
+    /// limits, no SMTP, JWTs and CAPTCHA off, storage under the temp dir
+    pub fn for_tests() -> Self {
+        let user_limit = UserRateLimit {
+            per_minute: 600,
+            burst: 100,
+        };
+        let ip_limit = IpRateLimit {
+            max_requests: 100,
+            window_seconds: 60,
+        };
+        let oauth_app = |provider: &str| {
+            (
+                String::new(),
+                String::new(),
+                format!("http://localhost:4000/auth/oauth/{}/callback", provider),
+            )
+        };
+        let (google_id, google_secret, google_redirect) = oauth_app("google");
+        let (github_id, github_secret, github_redirect) = oauth_app("github");
+
+        Self {
+            database: DatabaseConfig {
+                url: String::new(),
+                max_connections: 5,
+                min_connections: 0,
+                connect_timeout_seconds: 30,
+                idle_timeout_seconds: None,
+            },
+            server: ServerConfig {
+                host: "127.0.0.1".to_string(),
+                port: 4000,
+                trusted_proxies: Vec::new(),
+            },
+            oauth: OAuthConfig {
+                google: GoogleOAuthConfig {
+                    client_id: google_id,
+                    client_secret: google_secret,
+                    redirect_url: google_redirect,
+                },
+                github: GitHubOAuthConfig {
+                    client_id: github_id,
+                    client_secret: github_secret,
+                    redirect_url: github_redirect,
+                },
+                token_refresh_enabled: false,
+            },
+            email: EmailConfig {
+                smtp_host: "localhost".to_string(),
+                smtp_port: 587,
+                smtp_username: String::new(),
+                smtp_password: String::new(),
+                from_email: "noreply@example.com".to_string(),
+                app_name: "OpenTier".to_string(),
+                send_welcome_email: false,
+                frontend_url: "http://localhost:3000".to_string(),
+                api_url: "http://localhost:4000".to_string(),
+                templates_dir: "templates/email".to_string(),
+                dev_mailbox: true,
+                unsubscribe_signing_secret: "test-unsubscribe-secret".to_string(),
+            },
+            security: SecurityConfig {
+                session_expiry_seconds: 604800,
+                verification_token_expiry_seconds: 86400,
+                password_reset_token_expiry_seconds: 3600,
+                auth_event_retention_days: 90,
+                deleted_account_retention_days: 30,
+                session_cleanup_interval_seconds: 3600,
+                session_cleanup_batch_size: 1000,
+                cursor_signing_secret: "test-cursor-secret".to_string(),
+                max_sessions_per_user: 0,
+                csp_policy: None,
+            },
+            cors: CorsConfig {
+                allowed_origins: vec!["http://localhost:3000".to_string()],
+            },
+            rate_limit: RateLimitConfig {
+                backend: RateLimitBackend::Memory,
+                auth: ip_limit,
+                sensitive: ip_limit,
+                user_chat: user_limit,
+                user_account: user_limit,
+                user_admin: user_limit,
+            },
+            captcha: CaptchaConfig {
+                provider: None,
+                secret: None,
+            },
+            logging: LoggingConfig {
+                request_level: None,
+                route_levels: Vec::new(),
+            },
+            chat: ChatConfig {
+                missing_metrics: MissingMetricsMode::Default,
+                models: Vec::new(),
+                queue_on_outage: false,
+                monthly_token_budget: None,
+            },
+            jwt: JwtConfig {
+                enabled: false,
+                secret: None,
+                access_token_ttl_seconds: 900,
+            },
+            storage: StorageConfig {
+                backend: StorageBackend::Local,
+                local_root: env::temp_dir()
+                    .join("opentier-test-storage")
+                    .to_string_lossy()
+                    .into_owned(),
+                public_url: "http://localhost:4000".to_string(),
+                signing_secret: "test-storage-secret".to_string(),
+                s3_bucket: None,
+                s3_region: None,
+                s3_endpoint: None,
+            },
+            cache: CacheConfig { redis_url: None },
+            telemetry: TelemetryConfig {
+                otlp_endpoint: None,
+                service_name: "opentier-api".to_string(),
+            },
+            ingestion: IngestionConfig::default(),
+            intelligence: IntelligenceConfig::default(),
+        }
+    }
+}
+
 impl DatabaseConfig {
     pub fn from_env() -> Result<Self, Box<dyn std::error::Error>> {
         Ok(Self {
             url: env::var("DATABASE_URL")?,
+            max_connections: env::var("DB_MAX_CONNECTIONS")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .filter(|&n| n > 0)
+                .unwrap_or(10),
+            min_connections: env::var("DB_MIN_CONNECTIONS")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(1),
+            connect_timeout_seconds: env::var("DB_CONNECT_TIMEOUT")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .filter(|&s| s > 0)
+                .unwrap_or(30),
+            idle_timeout_seconds: env::var("DB_IDLE_TIMEOUT")
+                .ok()
+                .and_then(|s| s.parse().ok()),
         })
     }
 }
@@ -103,15 +458,35 @@ impl ServerConfig {
                 .ok()
                 .and_then(|p| p.parse().ok())
                 .unwrap_or(4000),
+            trusted_proxies: parse_trusted_proxies(
+                &env::var("TRUSTED_PROXIES").unwrap_or_default(),
+            )?,
         })
     }
 }
 
+/// Comma-separated CIDRs (or bare IPs) from `TRUSTED_PROXIES`
+pub(crate) fn parse_trusted_proxies(value: &str) -> Result<Vec<IpNetwork>, Box<dyn std::error::Error>> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(|s| {
+            s.parse::<IpNetwork>()
+                .map_err(|e| format!("Invalid TRUSTED_PROXIES entry {:?}: {}", s, e).into())
+        })
+        .collect()
+}
+
 impl OAuthConfig {
     pub fn from_env() -> Result<Self, Box<dyn std::error::Error>> {
         Ok(Self {
             google: GoogleOAuthConfig::from_env()?,
             github: GitHubOAuthConfig::from_env()?,
+            token_refresh_enabled: env::var("OAUTH_TOKEN_REFRESH_ENABLED")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(false),
         })
     }
 }
@@ -150,13 +525,38 @@ impl EmailConfig {
             smtp_password: env::var("SMTP_PASSWORD").unwrap_or_default(),
             from_email: env::var("FROM_EMAIL")
                 .unwrap_or_else(|_| "noreply@example.com".to_string()),
+            app_name: env::var("APP_NAME").unwrap_or_else(|_| "OpenTier".to_string()),
+            send_welcome_email: env::var("SEND_WELCOME_EMAIL")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(true),
             frontend_url: env::var("FRONTEND_URL")
                 .unwrap_or_else(|_| "http://localhost:3000".to_string()),
             api_url: env::var("API_URL").unwrap_or_else(|_| "http://localhost:4000".to_string()),
+            templates_dir: env::var("EMAIL_TEMPLATES_DIR")
+                .unwrap_or_else(|_| "templates/email".to_string()),
+            dev_mailbox: env::var("DEV_MAILBOX")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(false),
+            // Links sit in inboxes indefinitely, so the key can't change
+            unsubscribe_signing_secret: required_secret("UNSUBSCRIBE_SIGNING_SECRET")?,
         })
     }
 }
 
+/// Shortest accepted signing secret, in bytes
+const MIN_SECRET_BYTES: usize = 32;
+
+/// A signing secret that must be configured, so signatures survive restarts
+/// and every instance accepts the others'
+fn required_secret(name: &str) -> Result<String, Box<dyn std::error::Error>> {
+    match env::var(name) {
+        Ok(secret) if secret.len() >= MIN_SECRET_BYTES => Ok(secret),
+        _ => Err(format!("{} must be set to at least {} bytes", name, MIN_SECRET_BYTES).into()),
+    }
+}
+
 impl SecurityConfig {
     pub fn from_env() -> Result<Self, Box<dyn std::error::Error>> {
         Ok(Self {
@@ -172,6 +572,38 @@ impl SecurityConfig {
                 .ok()
                 .and_then(|s| s.parse().ok())
                 .unwrap_or(3600), // 1 hour
+            auth_event_retention_days: env::var("AUTH_EVENT_RETENTION_DAYS")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(90),
+            deleted_account_retention_days: env::var("DELETED_ACCOUNT_RETENTION_DAYS")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(30),
+            session_cleanup_interval_seconds: env::var("SESSION_CLEANUP_INTERVAL_SECONDS")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .filter(|&s| s > 0)
+                .unwrap_or(3600), // 1 hour
+            session_cleanup_batch_size: env::var("SESSION_CLEANUP_BATCH_SIZE")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .filter(|&s| s > 0)
+                .unwrap_or(1000),
+            // Cursors must verify on whichever instance serves the next page
+            cursor_signing_secret: required_secret("CURSOR_SIGNING_SECRET")?,
+            max_sessions_per_user: env::var("MAX_SESSIONS_PER_USER")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(0),
+            csp_policy: match env::var("CSP_POLICY") {
+                Ok(policy) if !policy.trim().is_empty() => {
+                    axum::http::HeaderValue::from_str(policy.trim())
+                        .map_err(|_| "CSP_POLICY must be a valid header value")?;
+                    Some(policy.trim().to_string())
+                }
+                _ => None,
+            },
         })
     }
 }
@@ -189,15 +621,303 @@ impl CorsConfig {
 
 impl RateLimitConfig {
     pub fn from_env() -> Result<Self, Box<dyn std::error::Error>> {
+        let backend = match env::var("RATE_LIMIT_BACKEND").ok().filter(|s| !s.is_empty()) {
+            Some(name) => RateLimitBackend::from_str(&name)
+                .ok_or_else(|| format!("Unsupported RATE_LIMIT_BACKEND: {}", name))?,
+            None => RateLimitBackend::default(),
+        };
+
         Ok(Self {
-            max_requests: env::var("RATE_LIMIT_MAX_REQUESTS")
+            backend,
+            auth: IpRateLimit::from_env("AUTH", 10, 60),
+            sensitive: IpRateLimit::from_env("SENSITIVE", 3, 60),
+            user_chat: UserRateLimit::from_env("CHAT", 30, 10),
+            user_account: UserRateLimit::from_env("USER", 60, 20),
+            user_admin: UserRateLimit::from_env("ADMIN", 120, 40),
+        })
+    }
+}
+
+impl IpRateLimit {
+    /// Read `RATE_LIMIT_{tier}_MAX_REQUESTS` and `RATE_LIMIT_{tier}_WINDOW_SECONDS`
+    fn from_env(tier: &str, default_max_requests: u32, default_window_seconds: u64) -> Self {
+        Self::from_lookup(
+            tier,
+            default_max_requests,
+            default_window_seconds,
+            |key| env::var(key).ok(),
+        )
+    }
+
+    pub(crate) fn from_lookup(
+        tier: &str,
+        default_max_requests: u32,
+        default_window_seconds: u64,
+        lookup: impl Fn(&str) -> Option<String>,
+    ) -> Self {
+        let max_requests = lookup(&format!("RATE_LIMIT_{}_MAX_REQUESTS", tier))
+            .and_then(|s| s.parse().ok())
+            .filter(|&n| n > 0)
+            .unwrap_or(default_max_requests);
+        let window_seconds = lookup(&format!("RATE_LIMIT_{}_WINDOW_SECONDS", tier))
+            .and_then(|s| s.parse().ok())
+            .filter(|&n| n > 0)
+            .unwrap_or(default_window_seconds);
+
+        Self {
+            max_requests,
+            window_seconds,
+        }
+    }
+
+    /// Time for one request's worth of quota to replenish
+    pub fn replenish_interval(&self) -> std::time::Duration {
+        let millis = self.window_seconds * 1000 / self.max_requests.max(1) as u64;
+        std::time::Duration::from_millis(millis.max(1))
+    }
+}
+
+impl UserRateLimit {
+    /// Read `USER_RATE_LIMIT_{group}_PER_MINUTE` and `USER_RATE_LIMIT_{group}_BURST`
+    fn from_env(group: &str, default_per_minute: u32, default_burst: u32) -> Self {
+        let read = |name: &str, default: u32| {
+            env::var(format!("USER_RATE_LIMIT_{}_{}", group, name))
                 .ok()
                 .and_then(|s| s.parse().ok())
-                .unwrap_or(100),
-            window_seconds: env::var("RATE_LIMIT_WINDOW_SECONDS")
+                .filter(|&n| n > 0)
+                .unwrap_or(default)
+        };
+
+        Self {
+            per_minute: read("PER_MINUTE", default_per_minute),
+            burst: read("BURST", default_burst),
+        }
+    }
+}
+
+impl LoggingConfig {
+    pub fn from_env() -> Result<Self, Box<dyn std::error::Error>> {
+        let request_level =
+            env::var("LOG_REQUEST_LEVEL").unwrap_or_else(|_| "debug".to_string());
+        let route_levels =
+            env::var("LOG_ROUTE_LEVELS").unwrap_or_else(|_| "/health=off".to_string());
+
+        Ok(Self {
+            request_level: request_log::parse_level(&request_level)?,
+            route_levels: request_log::parse_route_levels(&route_levels)?,
+        })
+    }
+}
+
+impl ChatConfig {
+    pub fn from_env() -> Result<Self, Box<dyn std::error::Error>> {
+        let missing_metrics = match env::var("CHAT_MISSING_METRICS") {
+            Ok(mode) => MissingMetricsMode::from_str(&mode)
+                .ok_or_else(|| format!("Unsupported CHAT_MISSING_METRICS: {}", mode))?,
+            Err(_) => MissingMetricsMode::default(),
+        };
+
+        let models = env::var("CHAT_MODELS")
+            .unwrap_or_default()
+            .split(',')
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+            .collect();
+
+        let queue_on_outage = env::var("CHAT_QUEUE_ON_OUTAGE")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(false);
+
+        let monthly_token_budget = match env::var("CHAT_MONTHLY_TOKEN_BUDGET") {
+            Ok(budget) if !budget.trim().is_empty() => Some(
+                budget
+                    .trim()
+                    .parse::<i64>()
+                    .ok()
+                    .filter(|budget| *budget >= 0)
+                    .ok_or_else(|| format!("Invalid CHAT_MONTHLY_TOKEN_BUDGET: {}", budget))?,
+            ),
+            _ => None,
+        };
+
+        Ok(Self {
+            missing_metrics,
+            models,
+            queue_on_outage,
+            monthly_token_budget,
+        })
+    }
+}
+
+impl JwtConfig {
+    pub fn from_env() -> Result<Self, Box<dyn std::error::Error>> {
+        let enabled = env::var("JWT_ENABLED")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(false);
+        let secret = env::var("JWT_SECRET").ok().filter(|s| !s.is_empty());
+        let access_token_ttl_seconds = env::var("JWT_ACCESS_TOKEN_TTL_SECONDS")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(300); // 5 minutes
+
+        if enabled {
+            match &secret {
+                Some(secret) if secret.len() >= 32 => {}
+                _ => return Err("JWT_SECRET must be at least 32 bytes when JWT_ENABLED=true".into()),
+            }
+            // Revoked session entries are only kept for a day
+            if access_token_ttl_seconds > 86400 {
+                return Err("JWT_ACCESS_TOKEN_TTL_SECONDS must not exceed 86400".into());
+            }
+        }
+
+        Ok(Self {
+            enabled,
+            secret,
+            access_token_ttl_seconds,
+        })
+    }
+}
+
+impl StorageConfig {
+    pub fn from_env() -> Result<Self, Box<dyn std::error::Error>> {
+        let backend = match env::var("STORAGE_BACKEND").ok().filter(|s| !s.is_empty()) {
+            Some(name) => StorageBackend::from_str(&name)
+                .ok_or_else(|| format!("Unsupported STORAGE_BACKEND: {}", name))?,
+            None => StorageBackend::default(),
+        };
+
+        // Without a configured secret, presigned URLs only survive until restart
+        let signing_secret = env::var("STORAGE_SIGNING_SECRET")
+            .ok()
+            .filter(|s| !s.is_empty())
+            .unwrap_or_else(crate::auth::tokens::generate_token);
+
+        Ok(Self {
+            backend,
+            local_root: env::var("STORAGE_LOCAL_ROOT").unwrap_or_else(|_| "storage".to_string()),
+            public_url: env::var("API_URL").unwrap_or_else(|_| "http://localhost:4000".to_string()),
+            signing_secret,
+            s3_bucket: env::var("S3_BUCKET").ok().filter(|s| !s.is_empty()),
+            s3_region: env::var("S3_REGION").ok().filter(|s| !s.is_empty()),
+            s3_endpoint: env::var("S3_ENDPOINT").ok().filter(|s| !s.is_empty()),
+        })
+    }
+}
+
+impl CacheConfig {
+    pub fn from_env() -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(Self {
+            redis_url: env::var("REDIS_URL").ok().filter(|s| !s.is_empty()),
+        })
+    }
+}
+
+impl TelemetryConfig {
+    pub fn from_env() -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(Self {
+            otlp_endpoint: env::var("OTEL_EXPORTER_OTLP_ENDPOINT")
                 .ok()
-                .and_then(|s| s.parse().ok())
-                .unwrap_or(60),
+                .filter(|s| !s.is_empty()),
+            service_name: env::var("OTEL_SERVICE_NAME")
+                .ok()
+                .filter(|s| !s.is_empty())
+                .unwrap_or_else(|| "opentier-api".to_string()),
+        })
+    }
+}
+
+impl IngestionConfig {
+    pub fn from_env() -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(Self {
+            url_allowlist: parse_host_rules(
+                "INGESTION_URL_ALLOWLIST",
+                &env::var("INGESTION_URL_ALLOWLIST").unwrap_or_default(),
+            )?,
+            url_denylist: parse_host_rules(
+                "INGESTION_URL_DENYLIST",
+                &env::var("INGESTION_URL_DENYLIST").unwrap_or_default(),
+            )?,
         })
     }
 }
+
+/// Comma-separated domains, IPs and CIDRs; `*.example.com` is the same as
+/// `example.com` since subdomains always match
+pub(crate) fn parse_host_rules(
+    name: &str,
+    value: &str,
+) -> Result<Vec<HostRule>, Box<dyn std::error::Error>> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(|s| {
+            if let Ok(network) = s.parse::<IpNetwork>() {
+                return Ok(HostRule::Network(network));
+            }
+
+            let domain = s.trim_start_matches("*.").to_ascii_lowercase();
+            let valid = !domain.is_empty()
+                && domain
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '.');
+            if !valid {
+                return Err(format!("Invalid {} entry {:?}", name, s).into());
+            }
+            Ok(HostRule::Domain(domain))
+        })
+        .collect()
+}
+
+impl IntelligenceConfig {
+    /// Breaker defaults overridden by `INTELLIGENCE_BREAKER_THRESHOLD`,
+    /// `INTELLIGENCE_BREAKER_WINDOW_SECONDS` and `INTELLIGENCE_BREAKER_OPEN_SECONDS`
+    pub fn from_env() -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(Self::from_lookup(|key| env::var(key).ok()))
+    }
+
+    pub(crate) fn from_lookup(lookup: impl Fn(&str) -> Option<String>) -> Self {
+        let defaults = BreakerConfig::default();
+        let positive = |key: &str| {
+            lookup(key)
+                .and_then(|v| v.parse::<u64>().ok())
+                .filter(|&v| v > 0)
+        };
+
+        Self {
+            circuit_breaker: BreakerConfig {
+                failure_threshold: positive("INTELLIGENCE_BREAKER_THRESHOLD")
+                    .map_or(defaults.failure_threshold, |v| v as u32),
+                failure_window: positive("INTELLIGENCE_BREAKER_WINDOW_SECONDS")
+                    .map_or(defaults.failure_window, std::time::Duration::from_secs),
+                open_duration: positive("INTELLIGENCE_BREAKER_OPEN_SECONDS")
+                    .map_or(defaults.open_duration, std::time::Duration::from_secs),
+            },
+        }
+    }
+}
+
+impl CaptchaConfig {
+    pub fn from_env() -> Result<Self, Box<dyn std::error::Error>> {
+        let provider = match env::var("CAPTCHA_PROVIDER").ok().filter(|s| !s.is_empty()) {
+            Some(name) => Some(
+                CaptchaProvider::from_str(&name)
+                    .ok_or_else(|| format!("Unsupported CAPTCHA_PROVIDER: {}", name))?,
+            ),
+            None => None,
+        };
+
+        Ok(Self {
+            provider,
+            secret: env::var("CAPTCHA_SECRET").ok().filter(|s| !s.is_empty()),
+        })
+    }
+
+    /// Whether CAPTCHA verification should be enforced
+    pub fn is_enabled(&self) -> bool {
+        self.provider.is_some() && self.secret.is_some()
+    }
+}
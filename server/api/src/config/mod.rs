@@ -1,3 +1,4 @@
+pub mod cache;
 pub mod cors;
 pub mod database;
 pub mod env;
@@ -0,0 +1,101 @@
+//! Emails the delivery worker gave up on
+//!
+//! After its last retry the worker records the email in `email_failures` so
+//! admins can see what never arrived.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// Default and maximum page size for the admin listing
+const DEFAULT_FAILURE_LIMIT: i64 = 50;
+const MAX_FAILURE_LIMIT: i64 = 200;
+
+#[derive(Debug, Serialize)]
+pub struct EmailFailure {
+    pub id: Uuid,
+    pub to: String,
+    pub subject: String,
+    pub error: String,
+    pub attempts: i32,
+    pub attempted_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct EmailQueueQuery {
+    /// Include undelivered emails
+    #[serde(default)]
+    pub failed: bool,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct EmailFailureList {
+    pub emails: Vec<EmailFailure>,
+    pub total_count: i64,
+    pub limit: i64,
+    pub offset: i64,
+}
+
+/// Record an email that could not be delivered
+pub async fn record_failure(
+    db: &PgPool,
+    to: &str,
+    subject: &str,
+    error: &str,
+    attempts: u32,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO email_failures (to_email, subject, error, attempts)
+        VALUES ($1, $2, $3, $4)
+        "#,
+        to,
+        subject,
+        error,
+        attempts as i32
+    )
+    .execute(db)
+    .await?;
+
+    Ok(())
+}
+
+/// List undelivered emails, newest first
+pub async fn list_failures(
+    db: &PgPool,
+    query: &EmailQueueQuery,
+) -> Result<EmailFailureList, sqlx::Error> {
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_FAILURE_LIMIT)
+        .clamp(1, MAX_FAILURE_LIMIT);
+    let offset = query.offset.unwrap_or(0).max(0);
+
+    let emails = sqlx::query_as!(
+        EmailFailure,
+        r#"
+        SELECT id, to_email AS "to", subject, error, attempts, attempted_at
+        FROM email_failures
+        ORDER BY attempted_at DESC
+        LIMIT $1 OFFSET $2
+        "#,
+        limit,
+        offset
+    )
+    .fetch_all(db)
+    .await?;
+
+    let total_count = sqlx::query_scalar!(r#"SELECT COUNT(*) as "count!" FROM email_failures"#)
+        .fetch_one(db)
+        .await?;
+
+    Ok(EmailFailureList {
+        emails,
+        total_count,
+        limit,
+        offset,
+    })
+}
@@ -0,0 +1,46 @@
+use axum::{
+    Json,
+    extract::{Query, State},
+};
+use serde::{Deserialize, Serialize};
+
+use super::unsubscribe;
+use crate::gateway::AppState;
+use crate::user::UserError;
+use crate::user::notifications::{self, NotificationCategory, UpdateNotificationsRequest};
+
+#[derive(Debug, Deserialize)]
+pub struct UnsubscribeQuery {
+    pub token: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UnsubscribeResponse {
+    pub category: NotificationCategory,
+    pub message: String,
+}
+
+/// GET /email/unsubscribe?token=...
+/// Turn off the category of email an unsubscribe link was sent with
+///
+/// The signed token stands in for authentication, so no session is needed.
+pub async fn unsubscribe(
+    State(state): State<AppState>,
+    Query(query): Query<UnsubscribeQuery>,
+) -> Result<Json<UnsubscribeResponse>, UserError> {
+    let secret = &state.config.email.unsubscribe_signing_secret;
+    let (user_id, category) = unsubscribe::verify_unsubscribe_token(secret, &query.token)
+        .ok_or_else(|| UserError::Validation("Invalid unsubscribe link".to_string()))?;
+
+    let req = UpdateNotificationsRequest::opt_out(category);
+    notifications::update_preferences(&state.db, user_id, &req)
+        .await?
+        .ok_or(UserError::NotFound)?;
+
+    tracing::info!(%user_id, category = category.as_str(), "Unsubscribed via email link");
+
+    Ok(Json(UnsubscribeResponse {
+        category,
+        message: "You have been unsubscribed".to_string(),
+    }))
+}
@@ -0,0 +1,116 @@
+//! In-memory mailbox for development and end-to-end tests
+//!
+//! With `DEV_MAILBOX=true` the email worker stores messages here instead of
+//! delivering them, and admins can read them back from `/admin/dev/mailbox`.
+
+use std::collections::VecDeque;
+use std::sync::{Arc, RwLock};
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use super::queue::EmailJob;
+
+/// Oldest emails are dropped beyond this many
+const MAILBOX_CAPACITY: usize = 500;
+
+/// An email captured instead of being sent
+#[derive(Debug, Clone, Serialize)]
+pub struct CapturedEmail {
+    pub to: String,
+    pub subject: String,
+    pub html_body: String,
+    pub text_body: Option<String>,
+    pub captured_at: DateTime<Utc>,
+}
+
+/// Shared capture buffer
+#[derive(Debug, Clone, Default)]
+pub struct Mailbox {
+    emails: Arc<RwLock<VecDeque<CapturedEmail>>>,
+}
+
+impl Mailbox {
+    pub fn capture(&self, job: EmailJob) {
+        let Ok(mut emails) = self.emails.write() else {
+            return;
+        };
+
+        if emails.len() == MAILBOX_CAPACITY {
+            emails.pop_front();
+        }
+        emails.push_back(CapturedEmail {
+            to: job.to,
+            subject: job.subject,
+            html_body: job.html_body,
+            text_body: job.text_body,
+            captured_at: Utc::now(),
+        });
+    }
+
+    /// Captured emails, newest first, optionally only those sent to `to`
+    pub fn list(&self, to: Option<&str>) -> Vec<CapturedEmail> {
+        let Ok(emails) = self.emails.read() else {
+            return Vec::new();
+        };
+
+        emails
+            .iter()
+            .rev()
+            .filter(|email| to.is_none_or(|to| email.to.eq_ignore_ascii_case(to)))
+            .cloned()
+            .collect()
+    }
+
+    /// Remove all captured emails, returning how many there were
+    pub fn clear(&self) -> usize {
+        self.emails
+            .write()
+            .map(|mut emails| std::mem::take(&mut *emails).len())
+            .unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn job(to: &str, subject: &str) -> EmailJob {
+        EmailJob {
+            to: to.to_string(),
+            subject: subject.to_string(),
+            html_body: "<p>body</p>".to_string(),
+            text_body: None,
+        }
+    }
+
+    #[test]
+    fn test_capture_and_filter() {
+        let mailbox = Mailbox::default();
+        mailbox.capture(job("a@example.com", "first"));
+        mailbox.capture(job("b@example.com", "second"));
+        mailbox.capture(job("A@example.com", "third"));
+
+        let all = mailbox.list(None);
+        assert_eq!(all.len(), 3);
+        assert_eq!(all[0].subject, "third");
+
+        let to_a = mailbox.list(Some("a@example.com"));
+        assert_eq!(to_a.len(), 2);
+
+        assert_eq!(mailbox.clear(), 3);
+        assert!(mailbox.list(None).is_empty());
+    }
+
+    #[test]
+    fn test_capacity_drops_oldest() {
+        let mailbox = Mailbox::default();
+        for i in 0..=MAILBOX_CAPACITY {
+            mailbox.capture(job("a@example.com", &i.to_string()));
+        }
+
+        let all = mailbox.list(None);
+        assert_eq!(all.len(), MAILBOX_CAPACITY);
+        assert_eq!(all.last().unwrap().subject, "1");
+    }
+}
@@ -1,32 +1,59 @@
+pub mod failures;
+pub mod handlers;
+pub mod mailbox;
+pub mod queue;
+pub mod templates;
+pub mod unsubscribe;
+
+use chrono::{DateTime, Utc};
+use tera::Context;
+use uuid::Uuid;
+
+use crate::auth::tokens::{PASSWORD_RESET_TOKEN_LIFETIME, VERIFICATION_TOKEN_LIFETIME};
 use crate::config::env::EmailConfig;
-use lettre::{
-    AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor, message::header::ContentType,
-    transport::smtp::authentication::Credentials,
-};
+use crate::user::notifications::NotificationCategory;
+use mailbox::Mailbox;
+use queue::{EmailJob, EmailQueue};
+use templates::EmailTemplates;
 
-/// Email service for sending verification and reset emails
+/// Email service for sending account emails
+///
+/// Emails are rendered immediately and delivered by the background worker.
+#[derive(Clone)]
 pub struct EmailService {
-    smtp_host: String,
-    smtp_port: u16,
-    smtp_username: String,
-    smtp_password: String,
-    from_email: String,
     frontend_url: String,
+    api_url: String,
+    app_name: String,
+    send_welcome_email: bool,
+    unsubscribe_secret: String,
+    templates: EmailTemplates,
+    queue: EmailQueue,
 }
 
 impl EmailService {
-    /// Create a new email service from config
-    pub fn new(config: EmailConfig) -> Self {
+    /// Create a new email service from config and the delivery queue
+    pub fn new(config: EmailConfig, queue: EmailQueue) -> Self {
         Self {
-            smtp_host: config.smtp_host,
-            smtp_port: config.smtp_port,
-            smtp_username: config.smtp_username,
-            smtp_password: config.smtp_password,
-            from_email: config.from_email,
             frontend_url: config.frontend_url,
+            api_url: config.api_url,
+            app_name: config.app_name,
+            send_welcome_email: config.send_welcome_email,
+            unsubscribe_secret: config.unsubscribe_signing_secret,
+            templates: EmailTemplates::new(config.templates_dir),
+            queue,
         }
     }
 
+    /// The development capture mailbox, if enabled
+    pub fn mailbox(&self) -> Option<&Mailbox> {
+        self.queue.mailbox()
+    }
+
+    /// The delivery queue
+    pub fn queue(&self) -> &EmailQueue {
+        &self.queue
+    }
+
     /// Send verification email
     pub async fn send_verification_email(
         &self,
@@ -39,26 +66,12 @@ impl EmailService {
             self.frontend_url, verification_token
         );
 
-        let email_body = format!(
-            r#"
-            <html>
-                <body>
-                    <h2>Verify Your Email</h2>
-                    <p>Your verification code is: <h3 style="display:inline;">{}</h3></p>
-                    <p>Or click the link below to verify your email address:</p>
-                    <p><a href="{}">Verify Email</a></p>
-                    <p>Or copy and paste this link into your browser:</p>
-                    <p>{}</p>
-                    <p>This link will expire in 24 hours.</p>
-                    <p>If you didn't create an account, you can safely ignore this email.</p>
-                </body>
-            </html>
-            "#,
-            verification_code, verification_url, verification_url
-        );
+        let mut context = Context::new();
+        context.insert("verification_code", verification_code);
+        context.insert("verification_url", &verification_url);
+        context.insert("expiry_hours", &VERIFICATION_TOKEN_LIFETIME.num_hours());
 
-        self.send_email(to_email, "Verify Your Email Address", &email_body)
-            .await
+        self.send_templated(to_email, "verification", context).await
     }
 
     /// Send password reset email
@@ -69,62 +82,154 @@ impl EmailService {
     ) -> Result<(), Box<dyn std::error::Error>> {
         let reset_url = format!("{}/auth/reset-password?token={}", self.frontend_url, reset_token);
 
-        let email_body = format!(
-            r#"
-            <html>
-                <body>
-                    <h2>Reset Your Password</h2>
-                    <p>We received a request to reset your password. Click the link below to create a new password:</p>
-                    <p><a href="{}">Reset Password</a></p>
-                    <p>Or copy and paste this link into your browser:</p>
-                    <p>{}</p>
-                    <p>This link will expire in 1 hour.</p>
-                    <p>If you didn't request a password reset, you can safely ignore this email.</p>
-                </body>
-            </html>
-            "#,
-            reset_url, reset_url
+        let mut context = Context::new();
+        context.insert("reset_url", &reset_url);
+        context.insert("expiry_hours", &PASSWORD_RESET_TOKEN_LIFETIME.num_hours());
+
+        self.send_templated(to_email, "password_reset", context).await
+    }
+
+    /// Send email change confirmation to the new address
+    ///
+    /// The link goes straight to the API's confirmation endpoint.
+    pub async fn send_email_change_email(
+        &self,
+        to_email: &str,
+        confirmation_token: &str,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let confirmation_url = format!(
+            "{}/auth/confirm-email-change?token={}",
+            self.api_url, confirmation_token
         );
 
-        self.send_email(to_email, "Reset Your Password", &email_body)
+        let mut context = Context::new();
+        context.insert("confirmation_url", &confirmation_url);
+
+        self.send_templated(to_email, "email_change", context).await
+    }
+
+    /// Tell an OAuth-only user that a password was added to their account
+    pub async fn send_password_set_email(
+        &self,
+        to_email: &str,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.send_templated(to_email, "password_set", Context::new())
             .await
     }
 
-    /// Internal method to send email via SMTP
-    async fn send_email(
+    /// Warn the user about a sign-in from a device not seen before
+    ///
+    /// A security alert, so it carries an unsubscribe link; callers check the
+    /// user's notification preferences first.
+    pub async fn send_new_signin_email(
         &self,
+        user_id: Uuid,
         to_email: &str,
-        subject: &str,
-        html_body: &str,
+        signin: &NewSignin,
     ) -> Result<(), Box<dyn std::error::Error>> {
-        // If SMTP is not configured, just log the email
-        if self.smtp_username.is_empty() || self.smtp_username.contains("your-email") {
-            tracing::info!(
-                "📧 Email would be sent to {}: {}\n{}",
-                to_email,
-                subject,
-                html_body
-            );
+        let mut context = Context::new();
+        context.insert("device", &signin.device);
+        context.insert("ip_address", &signin.ip_address);
+        context.insert(
+            "signed_in_at",
+            &signin.signed_in_at.format("%Y-%m-%d %H:%M UTC").to_string(),
+        );
+        context.insert(
+            "unsubscribe_url",
+            &self.unsubscribe_url(user_id, NotificationCategory::SecurityAlerts),
+        );
+
+        self.send_templated(to_email, "new_signin", context).await
+    }
+
+    /// Send welcome email to a new user, unless `SEND_WELCOME_EMAIL` is off
+    ///
+    /// Greets the user by name, or by the local part of their address. Counts
+    /// as a product update, so it carries an unsubscribe link; callers check
+    /// the user's notification preferences first.
+    pub async fn send_welcome_email(
+        &self,
+        user_id: Uuid,
+        to_email: &str,
+        name: Option<&str>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        if !self.send_welcome_email {
             return Ok(());
         }
 
-        let email = Message::builder()
-            .from(self.from_email.parse()?)
-            .to(to_email.parse()?)
-            .subject(subject)
-            .header(ContentType::TEXT_HTML)
-            .body(html_body.to_string())?;
+        let mut context = Context::new();
+        context.insert("name", &greeting_name(to_email, name));
+        context.insert(
+            "unsubscribe_url",
+            &self.unsubscribe_url(user_id, NotificationCategory::ProductUpdates),
+        );
 
-        let creds = Credentials::new(self.smtp_username.clone(), self.smtp_password.clone());
+        self.send_templated(to_email, "welcome", context).await
+    }
+
+    /// Link that turns off a category of email for the user without signing in
+    pub fn unsubscribe_url(&self, user_id: Uuid, category: NotificationCategory) -> String {
+        let token = unsubscribe::unsubscribe_token(&self.unsubscribe_secret, user_id, category);
+        format!("{}/email/unsubscribe?token={}", self.api_url, token)
+    }
+
+    /// Render a named template and queue it for delivery
+    ///
+    /// Templates with a plain-text part are sent as `multipart/alternative`.
+    /// `frontend_url`, `api_url` and `app_name` are always available to
+    /// templates.
+    pub async fn send_templated(
+        &self,
+        to_email: &str,
+        template_name: &str,
+        mut context: Context,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        for (key, value) in [
+            ("frontend_url", &self.frontend_url),
+            ("api_url", &self.api_url),
+            ("app_name", &self.app_name),
+        ] {
+            if !context.contains_key(key) {
+                context.insert(key, value);
+            }
+        }
 
-        let mailer = AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&self.smtp_host)?
-            .credentials(creds)
-            .port(self.smtp_port)
-            .build();
+        let rendered = self.templates.render(template_name, &context)?;
 
-        mailer.send(email).await?;
-        tracing::info!("✅ Email sent successfully to {}", to_email);
+        self.queue.enqueue(EmailJob {
+            to: to_email.to_string(),
+            subject: rendered.subject,
+            html_body: rendered.html_body,
+            text_body: rendered.text_body,
+        })?;
 
         Ok(())
     }
 }
+
+/// Details of a sign-in shown in the new device alert
+pub struct NewSignin {
+    /// e.g. "Chrome on macOS"
+    pub device: String,
+    pub ip_address: String,
+    pub signed_in_at: DateTime<Utc>,
+}
+
+/// The name to greet a user by
+fn greeting_name<'a>(email: &'a str, name: Option<&'a str>) -> &'a str {
+    name.map(str::trim)
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| email.split('@').next().unwrap_or(email))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_greeting_name_falls_back_to_email() {
+        assert_eq!(greeting_name("ada@example.com", Some(" Ada ")), "Ada");
+        assert_eq!(greeting_name("ada@example.com", Some("  ")), "ada");
+        assert_eq!(greeting_name("ada@example.com", None), "ada");
+    }
+}
@@ -0,0 +1,382 @@
+//! Background email delivery
+//!
+//! Handlers enqueue rendered emails and return immediately; a worker task
+//! hands each email to its own delivery task, which retries transient
+//! failures. Up to `MAX_DELIVERIES` emails are in delivery at once, and of
+//! those at most `MAX_CONCURRENT_SENDS` talk to the SMTP server, so an email
+//! waiting for a retry doesn't hold up the others. Emails that still fail
+//! are recorded in `email_failures`.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use lettre::{
+    AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor,
+    message::{MultiPart, header::ContentType},
+    transport::smtp::authentication::Credentials,
+};
+use sqlx::PgPool;
+use tokio::sync::{Semaphore, mpsc};
+
+use super::failures;
+use super::mailbox::Mailbox;
+use crate::config::env::EmailConfig;
+
+/// Maximum number of emails waiting for delivery
+const QUEUE_CAPACITY: usize = 1000;
+/// Emails being delivered at once, including those waiting to retry;
+/// beyond this the queue fills up again
+const MAX_DELIVERIES: usize = 100;
+/// SMTP sends in flight at once
+const MAX_CONCURRENT_SENDS: usize = 4;
+/// Delay before each retry; an email is given up after the last retry fails
+const RETRY_DELAYS: [Duration; 3] = [
+    Duration::from_secs(1),
+    Duration::from_secs(5),
+    Duration::from_secs(25),
+];
+
+/// A rendered email waiting to be delivered
+#[derive(Debug)]
+pub struct EmailJob {
+    pub to: String,
+    pub subject: String,
+    pub html_body: String,
+    /// Plain-text alternative; sent as `multipart/alternative` when present
+    pub text_body: Option<String>,
+}
+
+/// Handle for enqueueing emails
+#[derive(Clone)]
+pub struct EmailQueue {
+    sender: mpsc::Sender<EmailJob>,
+    /// Set when emails are captured instead of sent (`DEV_MAILBOX`)
+    mailbox: Option<Mailbox>,
+}
+
+impl EmailQueue {
+    /// Queue an email for delivery without waiting for SMTP
+    pub fn enqueue(&self, job: EmailJob) -> Result<(), String> {
+        self.sender.try_send(job).map_err(|e| match e {
+            mpsc::error::TrySendError::Full(job) => {
+                format!("Email queue is full, dropping email to {}", job.to)
+            }
+            mpsc::error::TrySendError::Closed(job) => {
+                format!("Email worker stopped, dropping email to {}", job.to)
+            }
+        })
+    }
+
+    /// The capture mailbox, when `DEV_MAILBOX` is enabled
+    pub fn mailbox(&self) -> Option<&Mailbox> {
+        self.mailbox.as_ref()
+    }
+
+    /// Emails waiting for the worker
+    pub fn queued(&self) -> usize {
+        self.sender.max_capacity() - self.sender.capacity()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.sender.max_capacity()
+    }
+}
+
+/// Start the email delivery worker
+pub fn start_email_worker(config: &EmailConfig, db: PgPool) -> EmailQueue {
+    let (sender, mut receiver) = mpsc::channel::<EmailJob>(QUEUE_CAPACITY);
+    let smtp = Arc::new(SmtpSender::new(config, db));
+    let mailbox = config.dev_mailbox.then(Mailbox::default);
+
+    let capture = mailbox.clone();
+    let deliveries = Arc::new(Semaphore::new(MAX_DELIVERIES));
+    tokio::spawn(async move {
+        while let Some(job) = receiver.recv().await {
+            match &capture {
+                Some(mailbox) => mailbox.capture(job),
+                None => {
+                    let Ok(permit) = deliveries.clone().acquire_owned().await else {
+                        break;
+                    };
+                    let smtp = smtp.clone();
+                    tokio::spawn(async move {
+                        smtp.send_with_retry(job).await;
+                        drop(permit);
+                    });
+                }
+            }
+        }
+    });
+
+    if mailbox.is_some() {
+        tracing::warn!("📬 DEV_MAILBOX enabled: emails are captured in memory, not sent");
+    }
+    tracing::info!("✅ Email worker started (queue capacity {})", QUEUE_CAPACITY);
+
+    EmailQueue { sender, mailbox }
+}
+
+/// Why a delivery attempt failed
+struct SendFailure {
+    error: String,
+    /// Whether trying again could succeed
+    retryable: bool,
+}
+
+/// Delay before the next attempt after `attempts` failed ones, or `None` to give up
+fn retry_delay(attempts: u32, retryable: bool) -> Option<Duration> {
+    if !retryable {
+        return None;
+    }
+    let retry = (attempts as usize).checked_sub(1)?;
+    RETRY_DELAYS.get(retry).copied()
+}
+
+/// Call `send` until it succeeds or `retry_delay` gives up
+///
+/// Returns the last failure and how many attempts were made on giving up.
+async fn retry_send<F, Fut>(to: &str, mut send: F) -> Result<(), (SendFailure, u32)>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<(), SendFailure>>,
+{
+    let mut attempts = 0;
+    loop {
+        attempts += 1;
+        let failure = match send().await {
+            Ok(()) => return Ok(()),
+            Err(failure) => failure,
+        };
+
+        match retry_delay(attempts, failure.retryable) {
+            Some(delay) => {
+                tracing::warn!(
+                    "Email to {} failed (attempt {}/{}), retrying in {:?}: {}",
+                    to,
+                    attempts,
+                    RETRY_DELAYS.len() + 1,
+                    delay,
+                    failure.error
+                );
+                tokio::time::sleep(delay).await;
+            }
+            None => return Err((failure, attempts)),
+        }
+    }
+}
+
+/// SMTP transport shared by all deliveries
+struct SmtpSender {
+    /// `None` when SMTP is not configured; emails are logged instead
+    transport: Option<AsyncSmtpTransport<Tokio1Executor>>,
+    from_email: String,
+    db: PgPool,
+    /// Limits concurrent sends; not held while waiting to retry
+    sends: Semaphore,
+}
+
+impl SmtpSender {
+    fn new(config: &EmailConfig, db: PgPool) -> Self {
+        let configured =
+            !config.smtp_username.is_empty() && !config.smtp_username.contains("your-email");
+
+        let transport = if configured {
+            match AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&config.smtp_host) {
+                Ok(builder) => Some(
+                    builder
+                        .credentials(Credentials::new(
+                            config.smtp_username.clone(),
+                            config.smtp_password.clone(),
+                        ))
+                        .port(config.smtp_port)
+                        .build(),
+                ),
+                Err(e) => {
+                    tracing::error!("Invalid SMTP relay {}: {}", config.smtp_host, e);
+                    None
+                }
+            }
+        } else {
+            None
+        };
+
+        Self {
+            transport,
+            from_email: config.from_email.clone(),
+            db,
+            sends: Semaphore::new(MAX_CONCURRENT_SENDS),
+        }
+    }
+
+    async fn send_with_retry(&self, job: EmailJob) {
+        // If SMTP is not configured, just log the email
+        let Some(transport) = &self.transport else {
+            tracing::info!(
+                "📧 Email would be sent to {}: {}\n{}",
+                job.to,
+                job.subject,
+                job.html_body
+            );
+            return;
+        };
+
+        // The builder's error isn't `Send`, so keep only its message
+        let message = match self.build_message(&job).map_err(|e| e.to_string()) {
+            Ok(message) => message,
+            Err(e) => {
+                self.give_up(&job, &format!("Failed to build email: {}", e), 0)
+                    .await;
+                return;
+            }
+        };
+
+        let message = &message;
+        let result = retry_send(&job.to, || async move {
+            let _permit = self.sends.acquire().await;
+            transport
+                .send(message.clone())
+                .await
+                .map(|_| ())
+                .map_err(|e| SendFailure {
+                    // Permanent (5xx) and client errors will fail the same way again
+                    retryable: !e.is_permanent() && !e.is_client(),
+                    error: e.to_string(),
+                })
+        })
+        .await;
+
+        match result {
+            Ok(()) => tracing::info!("✅ Email sent successfully to {}", job.to),
+            Err((failure, attempts)) => self.give_up(&job, &failure.error, attempts).await,
+        }
+    }
+
+    /// Log and record an email that will not be delivered
+    async fn give_up(&self, job: &EmailJob, error: &str, attempts: u32) {
+        tracing::error!(
+            "Failed to send email to {} after {} attempt(s): {}",
+            job.to,
+            attempts,
+            error
+        );
+
+        if let Err(e) =
+            failures::record_failure(&self.db, &job.to, &job.subject, error, attempts).await
+        {
+            tracing::error!("Failed to record undelivered email to {}: {}", job.to, e);
+        }
+    }
+
+    fn build_message(&self, job: &EmailJob) -> Result<Message, Box<dyn std::error::Error>> {
+        let builder = Message::builder()
+            .from(self.from_email.parse()?)
+            .to(job.to.parse()?)
+            .subject(job.subject.as_str());
+
+        let message = match &job.text_body {
+            Some(text_body) => builder.multipart(MultiPart::alternative_plain_html(
+                text_body.clone(),
+                job.html_body.clone(),
+            ))?,
+            None => builder
+                .header(ContentType::TEXT_HTML)
+                .body(job.html_body.clone())?,
+        };
+
+        Ok(message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    use crate::email::failures::{EmailQueueQuery, list_failures};
+
+    fn failing(retryable: bool) -> Result<(), SendFailure> {
+        Err(SendFailure {
+            error: "451 try again later".to_string(),
+            retryable,
+        })
+    }
+
+    #[test]
+    fn test_retry_schedule() {
+        assert_eq!(retry_delay(1, true), Some(Duration::from_secs(1)));
+        assert_eq!(retry_delay(2, true), Some(Duration::from_secs(5)));
+        assert_eq!(retry_delay(3, true), Some(Duration::from_secs(25)));
+        assert_eq!(retry_delay(4, true), None);
+        assert_eq!(retry_delay(1, false), None);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_transient_failures_are_retried_until_given_up() {
+        let calls = AtomicU32::new(0);
+        let started = tokio::time::Instant::now();
+
+        let result = retry_send("user@example.com", || async {
+            calls.fetch_add(1, Ordering::SeqCst);
+            failing(true)
+        })
+        .await;
+
+        let Err((failure, attempts)) = result else {
+            panic!("delivery should have been given up");
+        };
+        assert_eq!(attempts, 4);
+        assert_eq!(calls.load(Ordering::SeqCst), 4);
+        assert_eq!(failure.error, "451 try again later");
+        assert_eq!(started.elapsed(), Duration::from_secs(31));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_retry_stops_on_success_or_permanent_failure() {
+        let calls = AtomicU32::new(0);
+        let result = retry_send("user@example.com", || async {
+            match calls.fetch_add(1, Ordering::SeqCst) {
+                0 | 1 => failing(true),
+                _ => Ok(()),
+            }
+        })
+        .await;
+        assert!(result.is_ok());
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+
+        let started = tokio::time::Instant::now();
+        let result = retry_send("user@example.com", || async { failing(false) }).await;
+        assert!(matches!(result, Err((_, 1))));
+        assert_eq!(started.elapsed(), Duration::ZERO);
+    }
+
+    #[sqlx::test]
+    async fn test_given_up_emails_are_recorded(db: PgPool) {
+        let sender = SmtpSender {
+            transport: None,
+            from_email: "noreply@example.com".to_string(),
+            db: db.clone(),
+            sends: Semaphore::new(1),
+        };
+        let job = EmailJob {
+            to: "user@example.com".to_string(),
+            subject: "Verify your email".to_string(),
+            html_body: String::new(),
+            text_body: None,
+        };
+
+        sender.give_up(&job, "550 mailbox unavailable", 4).await;
+
+        let query = EmailQueueQuery {
+            failed: true,
+            limit: None,
+            offset: None,
+        };
+        let failures = list_failures(&db, &query).await.unwrap();
+        assert_eq!(failures.total_count, 1);
+        let failure = &failures.emails[0];
+        assert_eq!(failure.to, "user@example.com");
+        assert_eq!(failure.subject, "Verify your email");
+        assert_eq!(failure.error, "550 mailbox unavailable");
+        assert_eq!(failure.attempts, 4);
+    }
+}
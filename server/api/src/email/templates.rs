@@ -0,0 +1,308 @@
+//! Email templates rendered with Tera
+//!
+//! Templates are loaded once, on first use, from `EMAIL_TEMPLATES_DIR`
+//! (`<name>.html`, plus an optional `<name>.txt` plain-text alternative). The
+//! copies under `templates/email/` are compiled into the binary and used for
+//! any template the directory doesn't provide or that fails to load or render.
+
+use std::sync::{Arc, LazyLock, OnceLock};
+
+use tera::{Context, Tera};
+
+/// A built-in email template
+struct BuiltinTemplate {
+    name: &'static str,
+    subject: &'static str,
+    body: &'static str,
+    text: &'static str,
+}
+
+const BUILTIN_TEMPLATES: &[BuiltinTemplate] = &[
+    BuiltinTemplate {
+        name: "verification",
+        subject: "Verify Your Email Address",
+        body: include_str!("../../templates/email/verification.html"),
+        text: include_str!("../../templates/email/verification.txt"),
+    },
+    BuiltinTemplate {
+        name: "password_reset",
+        subject: "Reset Your Password",
+        body: include_str!("../../templates/email/password_reset.html"),
+        text: include_str!("../../templates/email/password_reset.txt"),
+    },
+    BuiltinTemplate {
+        name: "email_change",
+        subject: "Confirm Your New Email Address",
+        body: include_str!("../../templates/email/email_change.html"),
+        text: include_str!("../../templates/email/email_change.txt"),
+    },
+    BuiltinTemplate {
+        name: "password_set",
+        subject: "A Password Was Added to Your Account",
+        body: include_str!("../../templates/email/password_set.html"),
+        text: include_str!("../../templates/email/password_set.txt"),
+    },
+    BuiltinTemplate {
+        name: "new_signin",
+        subject: "New Sign-in to Your {{ app_name }} Account",
+        body: include_str!("../../templates/email/new_signin.html"),
+        text: include_str!("../../templates/email/new_signin.txt"),
+    },
+    BuiltinTemplate {
+        name: "welcome",
+        subject: "Welcome to {{ app_name }}",
+        body: include_str!("../../templates/email/welcome.html"),
+        text: include_str!("../../templates/email/welcome.txt"),
+    },
+];
+
+static BUILTINS: LazyLock<Tera> = LazyLock::new(|| {
+    let mut tera = Tera::default();
+    tera.add_raw_templates(BUILTIN_TEMPLATES.iter().flat_map(|t| {
+        [
+            (format!("{}.html", t.name), t.body),
+            (format!("{}.txt", t.name), t.text),
+        ]
+    }))
+    .expect("built-in email templates must parse");
+    tera.set_escape_fn(escape_html);
+    tera
+});
+
+/// A rendered email
+pub struct RenderedEmail {
+    pub subject: String,
+    pub html_body: String,
+    pub text_body: Option<String>,
+}
+
+/// Email templates, preferring overrides from the templates directory
+#[derive(Clone)]
+pub struct EmailTemplates {
+    dir: String,
+    overrides: Arc<OnceLock<Tera>>,
+}
+
+impl EmailTemplates {
+    pub fn new(dir: impl Into<String>) -> Self {
+        Self {
+            dir: dir.into(),
+            overrides: Arc::new(OnceLock::new()),
+        }
+    }
+
+    fn overrides(&self) -> &Tera {
+        self.overrides.get_or_init(|| load_overrides(&self.dir))
+    }
+
+    /// Render the subject and both bodies of a named template
+    pub fn render(&self, name: &str, context: &Context) -> Result<RenderedEmail, String> {
+        let builtin = BUILTIN_TEMPLATES.iter().find(|t| t.name == name);
+        let html = format!("{}.html", name);
+        let txt = format!("{}.txt", name);
+
+        let (html_body, overridden) = match render_override(self.overrides(), &html, context) {
+            Some(body) => (body, true),
+            None if builtin.is_some() => (render_builtin(&html, context)?, false),
+            None => return Err(format!("Unknown email template: {}", name)),
+        };
+
+        // A built-in text part would not match an overridden HTML body
+        let text_body = match render_override(self.overrides(), &txt, context) {
+            Some(text) => Some(text),
+            None if !overridden => Some(render_builtin(&txt, context)?),
+            None => None,
+        };
+
+        // Custom templates without a built-in counterpart reuse their name
+        let subject = match builtin {
+            Some(t) => Tera::one_off(t.subject, context, false).unwrap_or_else(|e| {
+                tracing::error!("Failed to render subject of email template {}: {}", name, e);
+                t.subject.to_string()
+            }),
+            None => name.replace('_', " "),
+        };
+
+        Ok(RenderedEmail {
+            subject,
+            html_body,
+            text_body,
+        })
+    }
+}
+
+/// Parse every template in `dir`, or none if any of them is invalid
+fn load_overrides(dir: &str) -> Tera {
+    let glob = format!("{}/**/*.{{html,txt}}", dir.trim_end_matches('/'));
+    match Tera::new(&glob) {
+        Ok(mut tera) => {
+            tera.set_escape_fn(escape_html);
+            tera
+        }
+        Err(e) => {
+            tracing::error!(
+                "Failed to load email templates from {}, using built-in templates: {:?}",
+                dir,
+                e
+            );
+            Tera::default()
+        }
+    }
+}
+
+/// Render a template from the directory, if it has one that renders
+fn render_override(tera: &Tera, name: &str, context: &Context) -> Option<String> {
+    if !tera.get_template_names().any(|n| n == name) {
+        return None;
+    }
+
+    tera.render(name, context)
+        .inspect_err(|e| {
+            tracing::error!(
+                "Failed to render email template {}, using built-in template: {:?}",
+                name,
+                e
+            )
+        })
+        .ok()
+}
+
+fn render_builtin(name: &str, context: &Context) -> Result<String, String> {
+    BUILTINS
+        .render(name, context)
+        .map_err(|e| format!("Failed to render email template {}: {:?}", name, e))
+}
+
+fn escape_html(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn templates_dir(files: &[(&str, &str)]) -> String {
+        let dir = std::env::temp_dir().join(format!("email-templates-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        for (name, content) in files {
+            std::fs::write(dir.join(name), content).unwrap();
+        }
+        dir.to_string_lossy().into_owned()
+    }
+
+    fn reset_context() -> Context {
+        let mut context = Context::new();
+        context.insert("reset_url", "https://example.com/?a=1&b=2");
+        context.insert("app_name", "<OpenTier>");
+        context.insert("expiry_hours", &1);
+        context
+    }
+
+    #[test]
+    fn test_builtin_html_escaped_text_not() {
+        let templates = EmailTemplates::new("/nonexistent");
+
+        let rendered = templates
+            .render("password_reset", &reset_context())
+            .unwrap();
+
+        assert_eq!(rendered.subject, "Reset Your Password");
+        assert!(
+            rendered
+                .html_body
+                .contains("https://example.com/?a=1&amp;b=2")
+        );
+        assert!(rendered.html_body.contains("&lt;OpenTier&gt;"));
+        assert!(rendered.html_body.contains("expire in 1 hour."));
+        assert!(rendered.text_body.unwrap().contains("?a=1&b=2"));
+
+        assert!(templates.render("unknown", &reset_context()).is_err());
+    }
+
+    #[test]
+    fn test_override_replaces_builtin() {
+        let dir = templates_dir(&[
+            (
+                "password_reset.html",
+                "<a href=\"{{ reset_url }}\">Reset</a>",
+            ),
+            ("notice.html", "Hello from {{ app_name }}"),
+        ]);
+        let templates = EmailTemplates::new(&dir);
+
+        let rendered = templates
+            .render("password_reset", &reset_context())
+            .unwrap();
+        assert_eq!(
+            rendered.html_body,
+            "<a href=\"https://example.com/?a=1&amp;b=2\">Reset</a>"
+        );
+        // The built-in text part would not match the custom HTML
+        assert!(rendered.text_body.is_none());
+
+        let rendered = templates.render("notice", &reset_context()).unwrap();
+        assert_eq!(rendered.subject, "notice");
+        assert_eq!(rendered.html_body, "Hello from &lt;OpenTier&gt;");
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_broken_override_falls_back_to_builtin() {
+        let dir = templates_dir(&[("password_reset.html", "{{ undefined_variable }}")]);
+        let templates = EmailTemplates::new(&dir);
+
+        let rendered = templates
+            .render("password_reset", &reset_context())
+            .unwrap();
+        assert!(rendered.html_body.contains("Reset Your Password"));
+        assert!(rendered.text_body.is_some());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_welcome_subject_uses_app_name() {
+        let mut context = Context::new();
+        context.insert("app_name", "OpenTier");
+        context.insert("name", "Ada");
+        context.insert("frontend_url", "http://localhost:3000");
+
+        let rendered = EmailTemplates::new("/nonexistent")
+            .render("welcome", &context)
+            .unwrap();
+
+        assert_eq!(rendered.subject, "Welcome to OpenTier");
+        assert!(rendered.html_body.contains("Ada"));
+    }
+
+    #[test]
+    fn test_new_signin_lists_device() {
+        let mut context = Context::new();
+        context.insert("app_name", "OpenTier");
+        context.insert("frontend_url", "http://localhost:3000");
+        context.insert("device", "Firefox on Linux");
+        context.insert("ip_address", "203.0.113.7");
+        context.insert("signed_in_at", "2026-01-01 12:00 UTC");
+
+        let rendered = EmailTemplates::new("/nonexistent")
+            .render("new_signin", &context)
+            .unwrap();
+
+        assert_eq!(rendered.subject, "New Sign-in to Your OpenTier Account");
+        let text = rendered.text_body.unwrap();
+        assert!(text.contains("Firefox on Linux") && text.contains("203.0.113.7"));
+        assert!(!text.contains("Unsubscribe"));
+    }
+}
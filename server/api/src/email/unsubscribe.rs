@@ -0,0 +1,81 @@
+//! One-click unsubscribe links
+//!
+//! Optional emails carry a link to `GET /email/unsubscribe?token=...` that
+//! turns off their category without signing in. The token is the base64url
+//! `user_id:category` payload and its HMAC-SHA256, keyed by
+//! `UNSUBSCRIBE_SIGNING_SECRET` and bound to this purpose, so no other signed
+//! value passes as one. It doesn't expire, so links in old emails keep
+//! working.
+
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use uuid::Uuid;
+
+use crate::user::notifications::NotificationCategory;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Signed ahead of the payload to keep these signatures apart from any
+/// other made with the same key
+const PURPOSE: &[u8] = b"opentier-unsubscribe\0";
+
+fn mac(secret: &str, encoded_payload: &str) -> HmacSha256 {
+    let mut mac =
+        HmacSha256::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(PURPOSE);
+    mac.update(encoded_payload.as_bytes());
+    mac
+}
+
+/// Sign an unsubscribe token for one user and category
+pub fn unsubscribe_token(secret: &str, user_id: Uuid, category: NotificationCategory) -> String {
+    let payload = URL_SAFE_NO_PAD.encode(format!("{}:{}", user_id, category.as_str()));
+    let signature = URL_SAFE_NO_PAD.encode(mac(secret, &payload).finalize().into_bytes());
+    format!("{}.{}", payload, signature)
+}
+
+/// The user and category a token unsubscribes, if it is valid
+pub fn verify_unsubscribe_token(secret: &str, token: &str) -> Option<(Uuid, NotificationCategory)> {
+    let (encoded, signature) = token.split_once('.')?;
+    let signature = URL_SAFE_NO_PAD.decode(signature).ok()?;
+    mac(secret, encoded).verify_slice(&signature).ok()?;
+
+    let payload = String::from_utf8(URL_SAFE_NO_PAD.decode(encoded).ok()?).ok()?;
+    let (user_id, category) = payload.split_once(':')?;
+
+    Some((
+        Uuid::parse_str(user_id).ok()?,
+        NotificationCategory::from_str(category)?,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECRET: &str = "test-unsubscribe-secret";
+
+    #[test]
+    fn test_token_roundtrip() {
+        let user_id = Uuid::new_v4();
+        let token = unsubscribe_token(SECRET, user_id, NotificationCategory::ProductUpdates);
+
+        assert_eq!(
+            verify_unsubscribe_token(SECRET, &token),
+            Some((user_id, NotificationCategory::ProductUpdates))
+        );
+        assert_eq!(verify_unsubscribe_token("other-secret", &token), None);
+    }
+
+    #[test]
+    fn test_forged_tokens_rejected() {
+        // The same payload signed for another purpose with the same key
+        let payload = format!("{}:{}", Uuid::new_v4(), "product_updates");
+        let cursor = crate::common::pagination::encode_cursor(SECRET, &payload);
+        assert_eq!(verify_unsubscribe_token(SECRET, &cursor), None);
+
+        assert_eq!(verify_unsubscribe_token(SECRET, "not-a-token"), None);
+        assert_eq!(verify_unsubscribe_token(SECRET, "a.b.c"), None);
+    }
+}
@@ -0,0 +1,78 @@
+use std::collections::BTreeMap;
+
+use axum::{
+    Extension, Json,
+    extract::{Path, State},
+};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::validate_name;
+use crate::admin::management::errors::ManagementError;
+use crate::gateway::AppState;
+use crate::gateway::openapi::ErrorBody;
+
+#[derive(Debug, Deserialize)]
+pub struct SetFeatureFlagRequest {
+    pub enabled: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FeatureFlagResponse {
+    pub name: String,
+    pub enabled: bool,
+}
+
+/// List feature flags and whether they are on
+/// GET /admin/feature-flags
+#[utoipa::path(
+    get,
+    path = "/admin/feature-flags",
+    tag = "admin",
+    responses(
+        (status = 200, description = "Flags by name"),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn list_feature_flags(State(state): State<AppState>) -> Json<BTreeMap<String, bool>> {
+    Json(state.feature_flags.all())
+}
+
+/// Turn a feature flag on or off
+/// PUT /admin/feature-flags/{name}
+///
+/// Takes effect immediately on this instance and within a minute on others.
+#[utoipa::path(
+    put,
+    path = "/admin/feature-flags/{name}",
+    tag = "admin",
+    params(("name" = String, Path, description = "Flag name")),
+    responses(
+        (status = 200, description = "Flag updated"),
+        (status = 400, description = "Unknown flag name", body = ErrorBody),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn set_feature_flag(
+    State(state): State<AppState>,
+    Extension(admin_id): Extension<Uuid>,
+    Path(name): Path<String>,
+    Json(req): Json<SetFeatureFlagRequest>,
+) -> Result<Json<FeatureFlagResponse>, ManagementError> {
+    validate_name(&name).map_err(ManagementError::Validation)?;
+    state
+        .feature_flags
+        .set(&state.db, &name, req.enabled)
+        .await?;
+
+    tracing::warn!(
+        %admin_id,
+        flag = %name,
+        enabled = req.enabled,
+        "Admin changed feature flag"
+    );
+    Ok(Json(FeatureFlagResponse {
+        name,
+        enabled: req.enabled,
+    }))
+}
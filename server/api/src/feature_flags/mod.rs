@@ -0,0 +1,215 @@
+//! Feature flags
+//!
+//! Flags are stored in `system_config` under `feature_flag:<name>` and cached
+//! in memory, so checking one costs a map lookup. Each instance re-reads them
+//! every minute, which lets admins toggle a feature without a restart. Only
+//! flags listed in `KNOWN_FLAGS` can be set; those never set are off unless
+//! listed in `DEFAULTS`.
+
+pub mod handlers;
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+use serde_json::Value;
+use sqlx::PgPool;
+
+use crate::common::background;
+
+/// Prefix of the `system_config` keys holding flags
+const KEY_PREFIX: &str = "feature_flag:";
+
+/// How often the cache is re-read from the database
+const REFRESH_INTERVAL_SECONDS: u64 = 60;
+
+/// Email users when they sign in from a device they haven't used before
+pub const NEW_DEVICE_ALERTS: &str = "new_device_alerts";
+
+/// Serve the Swagger UI at `/swagger-ui`
+pub const SWAGGER_UI: &str = "swagger_ui_enabled";
+
+/// Every flag the code checks
+const KNOWN_FLAGS: &[&str] = &[NEW_DEVICE_ALERTS, SWAGGER_UI];
+
+/// Flags that are on until an admin turns them off
+const DEFAULTS: &[(&str, bool)] = &[(NEW_DEVICE_ALERTS, true)];
+
+/// Check a flag on anything with a `feature_flags` field, e.g. `AppState`
+#[macro_export]
+macro_rules! is_enabled {
+    ($state:expr, $flag:expr) => {
+        $state.feature_flags.is_enabled($flag)
+    };
+}
+
+/// Reject names that aren't in `KNOWN_FLAGS`, so a typo can't be saved as
+/// a flag nothing checks
+pub fn validate_name(name: &str) -> Result<(), String> {
+    if KNOWN_FLAGS.contains(&name) {
+        Ok(())
+    } else {
+        Err(format!(
+            "Unknown feature flag \"{}\"; known flags are {}",
+            name,
+            KNOWN_FLAGS.join(", ")
+        ))
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct FeatureFlags {
+    flags: Arc<RwLock<HashMap<String, bool>>>,
+}
+
+impl FeatureFlags {
+    /// Read the stored flags
+    pub async fn load(db: &PgPool) -> Result<Self, sqlx::Error> {
+        let flags = Self::default();
+        flags.refresh(db).await?;
+        Ok(flags)
+    }
+
+    /// Replace the cache with the stored flags
+    pub async fn refresh(&self, db: &PgPool) -> Result<(), sqlx::Error> {
+        let rows = sqlx::query!(
+            "SELECT key, value FROM system_config WHERE starts_with(key, $1)",
+            KEY_PREFIX
+        )
+        .fetch_all(db)
+        .await?;
+
+        let stored = rows.into_iter().filter_map(|row| {
+            let name = row.key.strip_prefix(KEY_PREFIX)?.to_string();
+            // Set by an instance running a newer version, or left over from an older one
+            if validate_name(&name).is_err() {
+                return None;
+            }
+            match row.value {
+                Value::Bool(enabled) => Some((name, enabled)),
+                other => {
+                    tracing::warn!("Ignoring feature flag {} with value {}", name, other);
+                    None
+                }
+            }
+        });
+        self.replace(stored);
+        Ok(())
+    }
+
+    pub fn is_enabled(&self, name: &str) -> bool {
+        self.read().get(name).copied().unwrap_or(false)
+    }
+
+    /// Every known flag, by name
+    pub fn all(&self) -> BTreeMap<String, bool> {
+        self.read()
+            .iter()
+            .map(|(name, &enabled)| (name.clone(), enabled))
+            .collect()
+    }
+
+    /// Names of the flags that are on, sorted
+    pub fn enabled(&self) -> Vec<String> {
+        self.all()
+            .into_iter()
+            .filter_map(|(name, enabled)| enabled.then_some(name))
+            .collect()
+    }
+
+    /// Save a flag, then apply it
+    ///
+    /// Other instances pick the change up on their next refresh.
+    pub async fn set(&self, db: &PgPool, name: &str, enabled: bool) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"
+            INSERT INTO system_config (key, value)
+            VALUES ($1, $2)
+            ON CONFLICT (key) DO UPDATE SET value = EXCLUDED.value, updated_at = NOW()
+            "#,
+            format!("{}{}", KEY_PREFIX, name),
+            Value::Bool(enabled)
+        )
+        .execute(db)
+        .await?;
+
+        self.write().insert(name.to_string(), enabled);
+        Ok(())
+    }
+
+    /// Use `stored` on top of the defaults
+    fn replace(&self, stored: impl IntoIterator<Item = (String, bool)>) {
+        let mut flags: HashMap<String, bool> = KNOWN_FLAGS
+            .iter()
+            .map(|&name| (name.to_string(), false))
+            .collect();
+        flags.extend(
+            DEFAULTS
+                .iter()
+                .map(|&(name, enabled)| (name.to_string(), enabled)),
+        );
+        flags.extend(stored);
+        *self.write() = flags;
+    }
+
+    fn read(&self) -> RwLockReadGuard<'_, HashMap<String, bool>> {
+        self.flags
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn write(&self) -> RwLockWriteGuard<'_, HashMap<String, bool>> {
+        self.flags
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// Start feature flag refresh background task
+/// Picks up flags changed through other instances
+pub fn start_refresh_task(db: PgPool, flags: FeatureFlags) {
+    background::start_periodic_task(
+        db,
+        "Feature flag refresh",
+        REFRESH_INTERVAL_SECONDS,
+        move |db| {
+            let flags = flags.clone();
+            async move {
+                flags.refresh(&db).await?;
+                Ok(0)
+            }
+        },
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stored_flags_override_defaults() {
+        let flags = FeatureFlags::default();
+        flags.replace([]);
+        assert!(flags.is_enabled(NEW_DEVICE_ALERTS));
+        assert!(!flags.is_enabled(SWAGGER_UI));
+        // Every known flag is listed, set or not
+        assert_eq!(flags.all().len(), KNOWN_FLAGS.len());
+
+        flags.replace([
+            (NEW_DEVICE_ALERTS.to_string(), false),
+            (SWAGGER_UI.to_string(), true),
+        ]);
+        assert!(!flags.is_enabled(NEW_DEVICE_ALERTS));
+        assert!(flags.is_enabled(SWAGGER_UI));
+        assert_eq!(flags.enabled(), vec![SWAGGER_UI.to_string()]);
+    }
+
+    #[test]
+    fn test_only_known_flags_can_be_set() {
+        for name in KNOWN_FLAGS {
+            assert!(validate_name(name).is_ok());
+        }
+        assert!(validate_name("magic_links").is_err());
+        assert!(validate_name("").is_err());
+        assert!(validate_name("Swagger_UI_Enabled").is_err());
+    }
+}
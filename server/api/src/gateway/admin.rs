@@ -1,21 +1,67 @@
 use crate::gateway::AppState;
 use axum::{
-    routing::{get, patch, post},
+    extract::DefaultBodyLimit,
+    routing::{delete, get, patch, post, put},
     Router,
 };
 
-use crate::admin::{management, resources};
+use tower::ServiceBuilder;
+use tower_http::limit::RequestBodyLimitLayer;
+
+use crate::admin::{dev, management, resources};
+use crate::gateway::body_limit::{RESOURCE_BODY_LIMIT, UPLOAD_BODY_LIMIT};
+use crate::feature_flags::handlers as feature_flags;
+use crate::maintenance::handlers as maintenance;
+use crate::notices::handlers as notices;
 
 pub fn router() -> Router<AppState> {
     Router::new()
         // Management routes
         .route("/users", get(management::list_users))
+        .route("/users/export", get(management::export_users))
+        .route(
+            "/users/resend-verifications",
+            post(management::resend_verifications),
+        )
         .route(
             "/users/{id}",
             get(management::get_user).delete(management::delete_user),
         )
         .route("/users/{id}/role", patch(management::update_user_role))
+        .route("/users/{id}/usage", get(management::get_user_usage))
+        .route(
+            "/users/{id}/resend-verification",
+            post(management::resend_user_verification),
+        )
+        .route(
+            "/users/{id}/impersonate",
+            post(management::impersonate_user).delete(management::stop_impersonation),
+        )
+        .route(
+            "/users/{id}/revoke-sessions",
+            post(management::revoke_user_sessions),
+        )
+        .route("/users/{id}/sessions", delete(management::force_signout_user))
+        .route("/users/{id}/purge", post(management::purge_user))
+        .route("/users/{id}/quota", put(management::update_user_quota))
+        .route("/users/{id}/events", get(management::get_user_events))
         .route("/stats", get(management::get_stats))
+        .route("/stats/timeseries", get(management::get_stats_timeseries))
+        .route("/feedback", get(management::list_feedback))
+        .route("/email-queue", get(management::get_email_queue))
+        .route(
+            "/notices",
+            post(notices::create_notice).get(notices::list_notices),
+        )
+        .route("/notices/{id}", delete(notices::delete_notice))
+        .route("/maintenance", put(maintenance::set_maintenance))
+        .route("/feature-flags", get(feature_flags::list_feature_flags))
+        .route("/feature-flags/{name}", put(feature_flags::set_feature_flag))
+        // Development routes (404 unless enabled)
+        .route(
+            "/dev/mailbox",
+            get(dev::list_mailbox).delete(dev::clear_mailbox),
+        )
         // Resource routes
         .nest("/resources", resource_routes())
 }
@@ -24,10 +70,33 @@ fn resource_routes() -> Router<AppState> {
     Router::new()
         .route(
             "/",
-            post(resources::add_resource).get(resources::list_resources),
+            post(resources::add_resource)
+                .get(resources::list_resources)
+                .delete(resources::bulk_delete_resources),
         )
+        // Raw uploads stream the request body, which `DefaultBodyLimit` doesn't
+        // see, so `RequestBodyLimitLayer` bounds them as well
+        .route(
+            "/upload",
+            post(resources::upload_resource).layer(
+                ServiceBuilder::new()
+                    .layer(RequestBodyLimitLayer::new(UPLOAD_BODY_LIMIT))
+                    .layer(DefaultBodyLimit::max(UPLOAD_BODY_LIMIT)),
+            ),
+        )
+        .route("/search", get(resources::search_resources))
+        .route("/preview-chunks", post(resources::preview_chunks))
         .route(
             "/{id}",
-            get(resources::get_resource_status).delete(resources::delete_resource),
+            get(resources::get_resource_status)
+                .put(resources::update_resource)
+                .delete(resources::delete_resource),
+        )
+        .route("/{id}/cancel", post(resources::cancel_ingestion))
+        .route("/{id}/progress", get(resources::stream_resource_progress))
+        .route(
+            "/{id}/progress/stream",
+            get(resources::stream_resource_progress),
         )
+        .layer(DefaultBodyLimit::max(RESOURCE_BODY_LIMIT))
 }
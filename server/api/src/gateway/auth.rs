@@ -4,18 +4,19 @@ use axum::{
 };
 
 use crate::auth::{
-    forgot_password, oauth::oauth_authorize, oauth::oauth_callback, recover_account, refresh,
+    confirm_email_change, forgot_password, oauth::oauth_authorize, oauth::oauth_callback, recover_account, refresh,
     resend_verification, reset_password, signin, signout, signup, verify_get, verify_post,
 };
+use crate::config::env::RateLimitConfig;
 use crate::gateway::AppState;
-use crate::middleware::{auth_rate_limiter, sensitive_auth_rate_limiter};
+use crate::middleware::{RateLimiters, auth_rate_limiter, sensitive_auth_rate_limiter};
 
-pub fn routes() -> Router<AppState> {
+pub fn routes(rate_limiters: &RateLimiters, config: &RateLimitConfig) -> Router<AppState> {
     // OAuth routes (standard rate limiting)
     let oauth_routes = Router::new()
         .route("/oauth/{provider}/authorize", get(oauth_authorize))
         .route("/oauth/{provider}/callback", get(oauth_callback))
-        .layer(auth_rate_limiter());
+        .layer(auth_rate_limiter(rate_limiters, config));
 
     // Standard auth routes (signin, signup, refresh, signout)
     let standard_auth_routes = Router::new()
@@ -23,17 +24,18 @@ pub fn routes() -> Router<AppState> {
         .route("/signup", post(signup))
         .route("/signout", post(signout))
         .route("/refresh", post(refresh))
-        .route("/verify-email", get(verify_get).post(verify_post))
-        .layer(auth_rate_limiter());
+        .route("/confirm-email-change", get(confirm_email_change))
+        .layer(auth_rate_limiter(rate_limiters, config));
 
-    // Sensitive auth routes (password reset, account recovery)
+    // Sensitive auth routes (password reset, account recovery, email verification)
     // These get stricter rate limiting
     let sensitive_auth_routes = Router::new()
+        .route("/verify-email", get(verify_get).post(verify_post))
         .route("/forgot-password", post(forgot_password))
         .route("/reset-password", post(reset_password))
         .route("/resend-verification", post(resend_verification))
         .route("/recover-account", post(recover_account))
-        .layer(sensitive_auth_rate_limiter());
+        .layer(sensitive_auth_rate_limiter(rate_limiters, config));
 
     // Merge all routes
     Router::new()
@@ -0,0 +1,124 @@
+//! Request body size limits per route group
+//!
+//! Applied with `DefaultBodyLimit` where each group's router is built; routes
+//! without a group limit keep axum's 2MB default.
+
+use crate::admin::resources::types::MAX_CONTENT_SIZE;
+
+/// Auth routes carry credentials and tokens only
+pub const AUTH_BODY_LIMIT: usize = 64 * 1024;
+
+/// User account routes: profile, preferences, notifications
+pub const USER_BODY_LIMIT: usize = 256 * 1024;
+
+/// Admin resource routes, whose JSON bodies carry inline content
+pub const RESOURCE_BODY_LIMIT: usize = MAX_CONTENT_SIZE;
+
+/// Multipart and raw file uploads to `POST /admin/resources/upload`
+pub const UPLOAD_BODY_LIMIT: usize = 100 * 1024 * 1024;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::{Role, session};
+    use axum::{
+        Router,
+        body::Body,
+        extract::{ConnectInfo, Request},
+        http::{StatusCode, header},
+    };
+    use sqlx::PgPool;
+    use std::net::SocketAddr;
+    use tower::ServiceExt;
+
+    /// A session token for a new user with the given role
+    async fn session_token(db: &PgPool, role: Role) -> String {
+        let user_id = sqlx::query_scalar!(
+            "INSERT INTO users (email, role) VALUES ($1, $2) RETURNING id",
+            format!("{}@example.com", role),
+            role as Role
+        )
+        .fetch_one(db)
+        .await
+        .unwrap();
+        let (_, token, _) = session::create_session(db, None, user_id, role, None, None, 0)
+            .await
+            .unwrap();
+        token
+    }
+
+    /// POST `len` bytes to `path` through the real router
+    ///
+    /// Only JSON bodies are buffered, so for uploads just `Content-Length`
+    /// claims the size; `RequestBodyLimitLayer` rejects on the header alone.
+    async fn post(
+        app: &Router,
+        path: &str,
+        token: Option<&str>,
+        content_type: &str,
+        len: usize,
+    ) -> StatusCode {
+        let body = if content_type == "application/json" {
+            vec![b'a'; len]
+        } else {
+            Vec::new()
+        };
+        let mut request = Request::post(path)
+            .header(header::CONTENT_TYPE, content_type)
+            .header(header::CONTENT_LENGTH, len);
+        if let Some(token) = token {
+            request = request.header(header::AUTHORIZATION, format!("Bearer {}", token));
+        }
+        let mut request = request.body(Body::from(body)).unwrap();
+        request
+            .extensions_mut()
+            .insert(ConnectInfo(SocketAddr::from(([127, 0, 0, 1], 4000))));
+        app.clone().oneshot(request).await.unwrap().status()
+    }
+
+    #[sqlx::test]
+    async fn test_route_groups_accept_exact_and_reject_one_over(db: PgPool) {
+        let user = session_token(&db, Role::User).await;
+        let admin = session_token(&db, Role::Admin).await;
+        let app = crate::gateway::test_router(db).await;
+
+        let json = "application/json";
+        let cases = [
+            ("/auth/signin", None, AUTH_BODY_LIMIT),
+            ("/user/change-password", Some(user.as_str()), USER_BODY_LIMIT),
+            ("/admin/resources", Some(admin.as_str()), RESOURCE_BODY_LIMIT),
+        ];
+        for (path, token, limit) in cases {
+            // Within the limit the body reaches the handler, which rejects it as bad JSON
+            assert_eq!(
+                post(&app, path, token, json, limit).await,
+                StatusCode::BAD_REQUEST,
+                "{}",
+                path
+            );
+            assert_eq!(
+                post(&app, path, token, json, limit + 1).await,
+                StatusCode::PAYLOAD_TOO_LARGE,
+                "{}",
+                path
+            );
+        }
+    }
+
+    #[sqlx::test]
+    async fn test_upload_limit_applies_before_the_body_is_read(db: PgPool) {
+        let admin = session_token(&db, Role::Admin).await;
+        let app = crate::gateway::test_router(db).await;
+
+        let multipart = "multipart/form-data; boundary=limit";
+        let path = "/admin/resources/upload";
+        assert_ne!(
+            post(&app, path, Some(&admin), multipart, UPLOAD_BODY_LIMIT).await,
+            StatusCode::PAYLOAD_TOO_LARGE
+        );
+        assert_eq!(
+            post(&app, path, Some(&admin), multipart, UPLOAD_BODY_LIMIT + 1).await,
+            StatusCode::PAYLOAD_TOO_LARGE
+        );
+    }
+}
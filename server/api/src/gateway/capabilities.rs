@@ -0,0 +1,136 @@
+//! Feature discovery for clients
+//!
+//! Reports which optional features this deployment has enabled so the
+//! frontend can adapt at runtime instead of hardcoding them. Everything is
+//! read from the loaded config and the feature flags, never asserted here.
+
+use axum::{Json, extract::State};
+use serde::Serialize;
+
+use crate::admin::resources::types::MAX_CONTENT_SIZE;
+use crate::config::env::{CaptchaConfig, ChatConfig, OAuthConfig};
+use crate::gateway::AppState;
+
+#[derive(Debug, Serialize)]
+pub struct Capabilities {
+    /// OAuth providers with client credentials configured
+    pub oauth_providers: Vec<&'static str>,
+    /// CAPTCHA provider whose token signup and password reset require
+    pub captcha_provider: Option<&'static str>,
+    pub chat: ChatCapabilities,
+    /// Largest resource content accepted for ingestion
+    pub max_upload_bytes: usize,
+    /// Feature flags that are on, sorted
+    pub feature_flags: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ChatCapabilities {
+    /// Selectable models; empty when only the default model is available
+    pub models: Vec<String>,
+}
+
+impl Capabilities {
+    pub fn new(
+        oauth: &OAuthConfig,
+        captcha: &CaptchaConfig,
+        chat: &ChatConfig,
+        feature_flags: Vec<String>,
+    ) -> Self {
+        let mut oauth_providers = Vec::new();
+        if !oauth.google.client_id.is_empty() {
+            oauth_providers.push("google");
+        }
+        if !oauth.github.client_id.is_empty() {
+            oauth_providers.push("github");
+        }
+
+        // Matches `verify_captcha`, which skips checks unless both are set
+        let captcha_provider = match (captcha.provider, &captcha.secret) {
+            (Some(provider), Some(_)) => Some(provider.as_str()),
+            _ => None,
+        };
+
+        Self {
+            oauth_providers,
+            captcha_provider,
+            chat: ChatCapabilities {
+                models: chat.models.clone(),
+            },
+            max_upload_bytes: MAX_CONTENT_SIZE,
+            feature_flags,
+        }
+    }
+}
+
+/// Features enabled in this deployment
+/// GET /capabilities
+pub async fn get_capabilities(State(state): State<AppState>) -> Json<Capabilities> {
+    let config = &state.config;
+    Json(Capabilities::new(
+        &config.oauth,
+        &config.captcha,
+        &config.chat,
+        state.feature_flags.enabled(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::captcha::CaptchaProvider;
+    use crate::config::env::{GitHubOAuthConfig, GoogleOAuthConfig, MissingMetricsMode};
+
+    fn oauth(google_client_id: &str, github_client_id: &str) -> OAuthConfig {
+        OAuthConfig {
+            google: GoogleOAuthConfig {
+                client_id: google_client_id.to_string(),
+                client_secret: "secret".to_string(),
+                redirect_url: String::new(),
+            },
+            github: GitHubOAuthConfig {
+                client_id: github_client_id.to_string(),
+                client_secret: "secret".to_string(),
+                redirect_url: String::new(),
+            },
+            token_refresh_enabled: false,
+        }
+    }
+
+    #[test]
+    fn test_capabilities_follow_config() {
+        let chat = ChatConfig {
+            missing_metrics: MissingMetricsMode::Default,
+            models: Vec::new(),
+            queue_on_outage: false,
+            monthly_token_budget: None,
+        };
+        let no_captcha = CaptchaConfig {
+            provider: None,
+            secret: None,
+        };
+
+        let caps =
+            Capabilities::new(&oauth("google-id", "github-id"), &no_captcha, &chat, Vec::new());
+        assert_eq!(caps.oauth_providers, vec!["google", "github"]);
+        assert_eq!(caps.captcha_provider, None);
+        assert!(caps.chat.models.is_empty());
+        assert!(caps.feature_flags.is_empty());
+
+        let chat = ChatConfig {
+            models: vec!["small".to_string(), "large".to_string()],
+            ..chat
+        };
+        let captcha = CaptchaConfig {
+            provider: Some(CaptchaProvider::Turnstile),
+            secret: Some("secret".to_string()),
+        };
+
+        let flags = vec![crate::feature_flags::SWAGGER_UI.to_string()];
+        let caps = Capabilities::new(&oauth("google-id", ""), &captcha, &chat, flags);
+        assert_eq!(caps.oauth_providers, vec!["google"]);
+        assert_eq!(caps.captcha_provider, Some("turnstile"));
+        assert_eq!(caps.chat.models, vec!["small", "large"]);
+        assert_eq!(caps.feature_flags, vec!["swagger_ui_enabled"]);
+    }
+}
@@ -1,5 +1,5 @@
 use axum::{
-    routing::{get, post},
+    routing::{get, post, put},
     Router,
 };
 
@@ -12,6 +12,7 @@ pub fn routes() -> Router<AppState> {
         // Conversation management
         .route("/conversations", post(create_conversation))
         .route("/conversations", get(list_conversations))
+        .route("/tags", get(list_tags))
         .route(
             "/conversations/{id}",
             get(get_conversation)
@@ -25,6 +26,26 @@ pub fn routes() -> Router<AppState> {
         )
         // Messaging
         .route("/conversations/{id}/messages", post(send_message))
+        .route(
+            "/conversations/{id}/messages/{message_id}",
+            put(edit_message),
+        )
+        .route("/conversations/{id}/pending", get(list_pending_messages))
+        .route(
+            "/conversations/{id}/messages/{message_id}/feedback",
+            post(submit_message_feedback),
+        )
+        .route("/conversations/{id}/export", get(export_conversation))
+        // Sharing
+        .route(
+            "/conversations/{id}/share",
+            post(create_share_link).delete(revoke_share_link),
+        )
         // Streaming
         .route("/conversations/{id}/stream", get(stream_chat))
 }
+
+/// Public chat routes (no auth)
+pub fn public_routes() -> Router<AppState> {
+    Router::new().route("/shared/{token}", get(get_shared_conversation))
+}
@@ -0,0 +1,8 @@
+use axum::{Router, routing::get};
+
+use crate::email::handlers::unsubscribe;
+use crate::gateway::AppState;
+
+pub fn routes() -> Router<AppState> {
+    Router::new().route("/unsubscribe", get(unsubscribe))
+}
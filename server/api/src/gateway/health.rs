@@ -1,9 +1,14 @@
+use std::time::{Duration, Instant};
+
 use axum::extract::State;
+use axum::http::StatusCode;
 use axum::{Json, Router, routing::get};
 use serde::Serialize;
+use sqlx::PgPool;
 use tracing::error;
 
 use crate::gateway::AppState;
+use crate::grpc::circuit_breaker::BreakerSnapshot;
 
 #[derive(Serialize)]
 pub struct HealthResponse {
@@ -12,37 +17,235 @@ pub struct HealthResponse {
     uptime_seconds: u64,
 }
 
+#[derive(Serialize)]
+pub struct IntelligenceHealthResponse {
+    #[serde(flatten)]
+    health: HealthResponse,
+    circuit_breaker: BreakerSnapshot,
+}
+
+/// Maximum time the database check may take before it counts as down
+const DB_CHECK_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Serialize)]
+pub struct DbHealthResponse {
+    status: String,
+    latency_ms: Option<u64>,
+    pool_size: u32,
+    pool_idle: usize,
+    pool_in_use: u32,
+    pool_max: u32,
+}
+
+/// Snapshot of the database connection pool
+#[derive(Serialize)]
+pub struct PoolStats {
+    /// Open connections, idle or in use
+    size: u32,
+    idle: usize,
+    num_connections_in_use: u32,
+    /// Configured `max_connections`
+    max: u32,
+}
+
+impl PoolStats {
+    fn read(db: &PgPool) -> Self {
+        let size = db.size();
+        let idle = db.num_idle();
+        Self {
+            size,
+            idle,
+            num_connections_in_use: size.saturating_sub(idle as u32),
+            max: db.options().get_max_connections(),
+        }
+    }
+
+    /// The pool can serve another request: an idle connection or room to open one
+    fn has_capacity(&self) -> bool {
+        self.idle > 0 || self.size < self.max
+    }
+}
+
+#[derive(Serialize)]
+pub struct ReadinessChecks {
+    database: bool,
+    intelligence: bool,
+    pool_capacity: bool,
+}
+
+#[derive(Serialize)]
+pub struct ReadinessResponse {
+    ready: bool,
+    checks: ReadinessChecks,
+}
+
 pub fn routes() -> Router<AppState> {
     Router::new()
         .route("/api", get(api_health))
         .route("/intelligence", get(intelligence_health))
+        .route("/db", get(db_health))
+        .route("/pool", get(pool_health))
+        .route("/circuit-breaker", get(circuit_breaker_health))
+        .route("/ready", get(readiness))
 }
 
-pub async fn api_health(State(state): State<AppState>) -> Json<HealthResponse> {
-    Json(HealthResponse {
-        status: "healthy".to_string(),
+/// API health; `status` is `maintenance` while maintenance mode is on
+/// GET /health/api
+///
+/// `X-Feature-Flags` lists the flags that are on, comma-separated.
+pub async fn api_health(
+    State(state): State<AppState>,
+) -> ([(&'static str, String); 1], Json<HealthResponse>) {
+    let status = if state.maintenance.is_enabled() {
+        "maintenance"
+    } else {
+        "healthy"
+    };
+
+    let feature_flags = state.feature_flags.enabled().join(",");
+    let health = Json(HealthResponse {
+        status: status.to_string(),
         version: "v0.1.0".to_string(),
         uptime_seconds: state.start_time.elapsed().as_secs(),
-    })
+    });
+    ([("x-feature-flags", feature_flags)], health)
 }
 
-pub async fn intelligence_health(State(mut state): State<AppState>) -> Json<HealthResponse> {
-    match state.intelligence_client.check_health().await {
+/// Intelligence service health, including the client's circuit breaker
+/// GET /health/intelligence
+pub async fn intelligence_health(
+    State(mut state): State<AppState>,
+) -> Json<IntelligenceHealthResponse> {
+    let health = match state.intelligence_client.check_health().await {
         Ok(response) => {
             let inner = response.into_inner();
-            Json(HealthResponse {
+            HealthResponse {
                 status: inner.status,
                 version: inner.version.unwrap_or_else(|| "unknown".to_string()),
                 uptime_seconds: inner.uptime_seconds.unwrap_or(0) as u64,
-            })
+            }
         }
         Err(e) => {
             error!("Health check failed: {}", e);
-            Json(HealthResponse {
+            HealthResponse {
                 status: "unhealthy".to_string(),
                 version: "unknown".to_string(),
                 uptime_seconds: 0,
-            })
+            }
         }
+    };
+
+    Json(IntelligenceHealthResponse {
+        health,
+        circuit_breaker: state.intelligence_client.circuit_breaker().snapshot(),
+    })
+}
+
+/// Database health: `SELECT 1` with a short timeout
+/// GET /health/db (503 when the database is unreachable)
+pub async fn db_health(State(state): State<AppState>) -> (StatusCode, Json<DbHealthResponse>) {
+    let latency = ping_db(&state.db).await;
+    let pool = PoolStats::read(&state.db);
+
+    let (status, label) = if latency.is_some() {
+        (StatusCode::OK, "healthy")
+    } else {
+        (StatusCode::SERVICE_UNAVAILABLE, "unhealthy")
+    };
+
+    (
+        status,
+        Json(DbHealthResponse {
+            status: label.to_string(),
+            latency_ms: latency.map(|d| d.as_millis() as u64),
+            pool_size: pool.size,
+            pool_idle: pool.idle,
+            pool_in_use: pool.num_connections_in_use,
+            pool_max: pool.max,
+        }),
+    )
+}
+
+/// Connection pool usage, for spotting an exhausted pool
+/// GET /health/pool
+pub async fn pool_health(State(state): State<AppState>) -> Json<PoolStats> {
+    Json(PoolStats::read(&state.db))
+}
+
+/// State of the breaker around Intelligence service calls
+/// GET /health/circuit-breaker
+pub async fn circuit_breaker_health(State(state): State<AppState>) -> Json<BreakerSnapshot> {
+    Json(state.intelligence_client.circuit_breaker().snapshot())
+}
+
+/// Readiness probe: database, Intelligence service and pool capacity
+/// GET /health/ready (503 unless every check passes)
+///
+/// `/health/api` stays the liveness probe; this one fails while dependencies
+/// are down so the instance is taken out of rotation instead of restarted.
+pub async fn readiness(State(mut state): State<AppState>) -> (StatusCode, Json<ReadinessResponse>) {
+    let (database, intelligence) = tokio::join!(ping_db(&state.db), async {
+        match state.intelligence_client.check_ready().await {
+            Ok(response) => response.into_inner().ready,
+            Err(e) => {
+                error!("Readiness check failed: {}", e);
+                false
+            }
+        }
+    });
+
+    let checks = ReadinessChecks {
+        database: database.is_some(),
+        intelligence,
+        pool_capacity: PoolStats::read(&state.db).has_capacity(),
+    };
+    let ready = checks.database && checks.intelligence && checks.pool_capacity;
+
+    let status = if ready {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+
+    (status, Json(ReadinessResponse { ready, checks }))
+}
+
+/// Run `SELECT 1`, returning its latency or `None` on error or timeout
+async fn ping_db(db: &PgPool) -> Option<Duration> {
+    let started = Instant::now();
+
+    match tokio::time::timeout(DB_CHECK_TIMEOUT, sqlx::query("SELECT 1").execute(db)).await {
+        Ok(Ok(_)) => Some(started.elapsed()),
+        Ok(Err(e)) => {
+            error!("Database health check failed: {}", e);
+            None
+        }
+        Err(_) => {
+            error!(
+                "Database health check timed out after {:?}",
+                DB_CHECK_TIMEOUT
+            );
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pool_has_capacity() {
+        let stats = |size: u32, idle: usize, max| PoolStats {
+            size,
+            idle,
+            num_connections_in_use: size - idle as u32,
+            max,
+        };
+
+        assert!(stats(0, 0, 10).has_capacity());
+        assert!(stats(10, 1, 10).has_capacity());
+        assert!(stats(5, 0, 10).has_capacity());
+        assert!(!stats(10, 0, 10).has_capacity());
     }
 }
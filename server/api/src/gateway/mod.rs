@@ -1,16 +1,36 @@
 pub mod admin;
 pub mod auth;
+pub mod body_limit;
+pub mod capabilities;
 pub mod chat;
+pub mod email;
 pub mod health;
+pub mod openapi;
+pub mod storage;
 pub mod user;
 
-use axum::{Router, extract::FromRef, middleware, response::Html};
+use axum::{
+    Router,
+    extract::{DefaultBodyLimit, FromRef, State},
+    http::{HeaderMap, HeaderValue, header},
+    middleware,
+    response::{Html, IntoResponse},
+};
 use sqlx::PgPool;
 
 use tower_http::services::ServeFile;
 
-use crate::config::{cors::build_cors_layer, env::Config};
+use crate::auth::jwt::SessionDenylist;
+use crate::chat::streams::ActiveStreams;
+use crate::config::{cache::RedisPool, cors::build_cors_layer, env::Config};
+use crate::email::{EmailService, queue::EmailQueue};
+use crate::feature_flags::FeatureFlags;
 use crate::grpc::IntelligenceClient;
+use crate::maintenance::MaintenanceMode;
+use crate::middleware::RateLimiters;
+use crate::notices::NoticeCache;
+use crate::storage::SharedStorage;
+use crate::user::activity::ActivityTracker;
 
 // Define shared state type
 #[derive(Clone)]
@@ -18,6 +38,23 @@ pub struct AppState {
     pub db: PgPool,
     pub config: Config,
     pub intelligence_client: IntelligenceClient,
+    pub email: EmailService,
+    /// Revoked sessions, checked when verifying JWT access tokens
+    pub session_denylist: SessionDenylist,
+    /// Object storage for avatars, attachments and exports
+    pub storage: SharedStorage,
+    /// Optional Redis cache (session lookups); `None` when `REDIS_URL` is unset
+    pub cache: Option<RedisPool>,
+    /// Active system notices, cached in process
+    pub notices: NoticeCache,
+    /// Throttled `last_active_at` updates
+    pub activity: ActivityTracker,
+    /// Whether non-admin requests are turned away
+    pub maintenance: MaintenanceMode,
+    /// Cached feature flags; check them with `is_enabled!`
+    pub feature_flags: FeatureFlags,
+    /// Conversations with an SSE response in progress on this instance
+    pub active_streams: ActiveStreams,
     pub start_time: std::time::Instant,
 }
 
@@ -28,36 +65,110 @@ impl FromRef<AppState> for PgPool {
     }
 }
 
-pub fn router(db: PgPool, config: Config, intelligence_client: IntelligenceClient) -> Router {
+impl FromRef<AppState> for Option<RedisPool> {
+    fn from_ref(state: &AppState) -> Option<RedisPool> {
+        state.cache.clone()
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn router(
+    db: PgPool,
+    config: Config,
+    intelligence_client: IntelligenceClient,
+    email_queue: EmailQueue,
+    session_denylist: SessionDenylist,
+    storage: SharedStorage,
+    cache: Option<RedisPool>,
+    maintenance: MaintenanceMode,
+    feature_flags: FeatureFlags,
+) -> Router {
+    // Rate limiters share the Redis connection when RATE_LIMIT_BACKEND=redis
+    let rate_limiters = RateLimiters::from_config(&config.rate_limit, cache.as_ref());
+    if rate_limiters.is_redis() {
+        tracing::info!("🚦 Rate limits shared through Redis");
+    }
+
     let app_state = AppState {
         db,
         config: config.clone(),
         intelligence_client,
+        email: EmailService::new(config.email.clone(), email_queue),
+        session_denylist,
+        storage,
+        cache,
+        notices: NoticeCache::new(),
+        activity: ActivityTracker::new(),
+        maintenance,
+        feature_flags,
+        active_streams: ActiveStreams::new(),
         start_time: std::time::Instant::now(),
     };
 
     // Build CORS layer from configuration
     let cors = build_cors_layer(&config.cors);
 
-    // Request logging layer
-    let trace = tower_http::trace::TraceLayer::new_for_http();
+    // Request logging layer (per-route verbosity)
+    let trace = crate::observability::request_log::trace_layer(&config.logging);
+
+    let security_headers =
+        crate::middleware::SecurityHeadersLayer::new(config.security.csp_policy.as_deref());
 
     Router::new()
         .merge(Router::new().route("/", axum::routing::get(home)))
+        .route(
+            "/capabilities",
+            axum::routing::get(capabilities::get_capabilities),
+        )
         .nest("/health", health::routes())
-        .nest("/auth", auth::routes())
+        .merge(openapi::routes(app_state.clone()))
+        .nest(
+            "/auth",
+            auth::routes(&rate_limiters, &config.rate_limit)
+                .layer(DefaultBodyLimit::max(body_limit::AUTH_BODY_LIMIT)),
+        )
+        .nest("/storage", storage::routes())
+        .nest("/email", email::routes())
+        .route(
+            "/avatars/{file}",
+            axum::routing::get(crate::storage::handlers::download_avatar),
+        )
+        .route(
+            "/notices",
+            axum::routing::get(crate::notices::handlers::active_notices),
+        )
         .nest(
             "/user",
             user::routes()
+                .layer(DefaultBodyLimit::max(body_limit::USER_BODY_LIMIT))
+                .layer(crate::middleware::user_rate_limiter(
+                    &rate_limiters,
+                    "user",
+                    config.rate_limit.user_account,
+                ))
                 // Apply auth middleware to all user routes
                 .layer(middleware::from_fn_with_state(
                     app_state.clone(),
                     crate::middleware::auth_middleware,
                 )),
         )
+        // Share links are read without an account; limited per client IP
+        .nest(
+            "/chat",
+            chat::public_routes().layer(crate::middleware::user_rate_limiter(
+                &rate_limiters,
+                "chat_shared",
+                config.rate_limit.user_chat,
+            )),
+        )
         .nest(
             "/chat",
             chat::routes()
+                .layer(crate::middleware::user_rate_limiter(
+                    &rate_limiters,
+                    "chat",
+                    config.rate_limit.user_chat,
+                ))
                 // Apply auth middleware to all chat routes
                 .layer(middleware::from_fn_with_state(
                     app_state.clone(),
@@ -67,6 +178,11 @@ pub fn router(db: PgPool, config: Config, intelligence_client: IntelligenceClien
         .nest(
             "/admin",
             admin::router()
+                .layer(crate::middleware::user_rate_limiter(
+                    &rate_limiters,
+                    "admin",
+                    config.rate_limit.user_admin,
+                ))
                 .layer(middleware::from_fn_with_state(
                     app_state.clone(),
                     crate::middleware::require_admin,
@@ -76,14 +192,40 @@ pub fn router(db: PgPool, config: Config, intelligence_client: IntelligenceClien
                     crate::middleware::auth_middleware,
                 )),
         )
+        // Before auth, so blocked requests never reach a handler
+        .layer(middleware::from_fn_with_state(
+            app_state.clone(),
+            crate::middleware::maintenance_middleware,
+        ))
         .layer(cors) // Apply CORS to all routes
+        // Before any handler or rate limiter reads the client IP
+        .layer(middleware::from_fn_with_state(
+            app_state.clone(),
+            crate::middleware::client_ip_middleware,
+        ))
+        // Inside the trace layer so the ID lands on the request span
+        .layer(middleware::from_fn(crate::middleware::request_id_middleware))
         .layer(trace) // Apply Request Logging
         .with_state(app_state)
         .route_service("/favicon.ico", ServeFile::new("public/favicon.ico"))
+        .layer(security_headers)
 }
 
-async fn home() -> Html<&'static str> {
-    Html(
+/// Inline styles need their own CSP; the default policy would block them
+const HOME_CSP: &str = "default-src 'self'; style-src 'unsafe-inline'";
+
+/// Landing page; `X-System-Notice-Count` tells clients to fetch `/notices`
+async fn home(State(state): State<AppState>) -> impl IntoResponse {
+    let mut headers = HeaderMap::new();
+    headers.insert(header::CONTENT_SECURITY_POLICY, HeaderValue::from_static(HOME_CSP));
+    match state.notices.active(&state.db).await {
+        Ok(notices) => {
+            headers.insert("x-system-notice-count", HeaderValue::from(notices.len()));
+        }
+        Err(e) => tracing::warn!("Failed to load system notices: {}", e),
+    }
+
+    let page = Html(
     r##"
       <!DOCTYPE html>
       <html lang="en">
@@ -142,5 +284,38 @@ async fn home() -> Html<&'static str> {
         </body>
       </html>
       "##,
+    );
+
+    (headers, page)
+}
+
+/// The full router over a test database, as `main` builds it with
+/// `Config::for_tests`; the Intelligence client connects lazily and is
+/// never reached unless a test calls through to it
+#[cfg(test)]
+pub async fn test_router(db: PgPool) -> Router {
+    use crate::grpc::client::{RetryConfig, RpcTimeouts};
+
+    let config = Config::for_tests();
+    let intelligence_client = IntelligenceClient::connect_lazy_with_config(
+        "http://[::1]:50051",
+        RpcTimeouts::default(),
+        RetryConfig::default(),
+    )
+    .await
+    .unwrap();
+    let email_queue = crate::email::queue::start_email_worker(&config.email, db.clone());
+    let storage = crate::storage::from_config(&config.storage).unwrap();
+
+    router(
+        db,
+        config,
+        intelligence_client,
+        email_queue,
+        SessionDenylist::default(),
+        storage,
+        None,
+        MaintenanceMode::default(),
+        FeatureFlags::default(),
     )
 }
@@ -0,0 +1,268 @@
+//! OpenAPI description of the public API
+//!
+//! Paths come from the `#[utoipa::path]` annotations on the handlers, so a
+//! handler added to a router also needs adding to `ApiDoc`. The spec is always
+//! served at `/openapi.json`; the Swagger UI at `/swagger-ui` is off unless the
+//! `swagger_ui_enabled` feature flag is on.
+
+use axum::{
+    Json, Router,
+    extract::{Request, State},
+    http::{HeaderValue, StatusCode, header},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    routing::get,
+};
+use serde::Serialize;
+use utoipa::openapi::security::{Http, HttpAuthScheme, SecurityScheme};
+use utoipa::{Modify, OpenApi, ToSchema};
+use utoipa_swagger_ui::{Config, SwaggerUi};
+
+use crate::feature_flags::SWAGGER_UI;
+use crate::gateway::AppState;
+use crate::{admin, auth, chat, feature_flags, maintenance, notices, user};
+
+/// Swagger UI styles itself inline and uses data URIs for icons
+const SWAGGER_UI_CSP: &str =
+    "default-src 'self'; style-src 'self' 'unsafe-inline'; img-src 'self' data:";
+
+/// JSON body of error responses
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ErrorBody {
+    /// Machine-readable code, e.g. `quota_exceeded`
+    pub error: String,
+    pub message: String,
+}
+
+/// Registers the bearer token scheme the protected paths refer to
+struct BearerAuth;
+
+impl Modify for BearerAuth {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "bearer_auth",
+            SecurityScheme::Http(Http::new(HttpAuthScheme::Bearer)),
+        );
+    }
+}
+
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "OpenTier API", description = "OpenTier API gateway"),
+    paths(
+        // Auth
+        auth::handlers::signup,
+        auth::handlers::signin,
+        auth::handlers::signout,
+        auth::handlers::refresh,
+        auth::handlers::verify_get,
+        auth::handlers::verify_post,
+        auth::handlers::confirm_email_change,
+        auth::handlers::forgot_password,
+        auth::handlers::reset_password,
+        auth::handlers::resend_verification,
+        auth::handlers::recover_account,
+        auth::oauth::handlers::oauth_authorize,
+        auth::oauth::handlers::oauth_callback,
+        // User
+        user::handlers::me,
+        user::handlers::update_profile,
+        user::handlers::get_preferences,
+        user::handlers::update_preferences,
+        user::handlers::get_notifications,
+        user::handlers::update_notifications,
+        user::handlers::upload_avatar,
+        user::handlers::change_password,
+        user::handlers::set_password,
+        user::handlers::change_email,
+        user::handlers::delete_account,
+        user::handlers::export_data,
+        user::handlers::list_sessions,
+        user::handlers::rename_session,
+        user::handlers::revoke_session,
+        user::handlers::get_quota,
+        user::handlers::get_usage,
+        user::handlers::security_events,
+        // Chat
+        chat::handlers::create_conversation,
+        chat::handlers::list_conversations,
+        chat::handlers::list_tags,
+        chat::handlers::get_conversation,
+        chat::handlers::update_conversation,
+        chat::handlers::delete_conversation,
+        chat::handlers::generate_conversation_title,
+        chat::handlers::send_message,
+        chat::handlers::edit_message,
+        chat::handlers::list_pending_messages,
+        chat::handlers::submit_message_feedback,
+        chat::handlers::export_conversation,
+        chat::handlers::create_share_link,
+        chat::handlers::revoke_share_link,
+        chat::handlers::get_shared_conversation,
+        chat::handlers::stream_chat,
+        // Notices
+        notices::handlers::active_notices,
+        // Admin
+        admin::management::handlers::list_users,
+        admin::management::handlers::export_users,
+        admin::management::handlers::get_user,
+        admin::management::handlers::get_user_usage,
+        admin::management::handlers::update_user_role,
+        admin::management::handlers::delete_user,
+        admin::management::handlers::get_stats,
+        admin::management::handlers::get_stats_timeseries,
+        admin::management::handlers::list_feedback,
+        admin::management::handlers::get_email_queue,
+        admin::management::handlers::impersonate_user,
+        admin::management::handlers::stop_impersonation,
+        admin::management::handlers::revoke_user_sessions,
+        admin::management::handlers::force_signout_user,
+        admin::management::handlers::purge_user,
+        admin::management::handlers::update_user_quota,
+        admin::management::handlers::get_user_events,
+        admin::management::handlers::resend_verifications,
+        admin::management::handlers::resend_user_verification,
+        admin::resources::handlers::add_resource,
+        admin::resources::handlers::update_resource,
+        admin::resources::handlers::upload_resource,
+        admin::resources::handlers::list_resources,
+        admin::resources::handlers::search_resources,
+        admin::resources::handlers::preview_chunks,
+        admin::resources::handlers::get_resource_status,
+        admin::resources::handlers::cancel_ingestion,
+        admin::resources::handlers::stream_resource_progress,
+        admin::resources::handlers::delete_resource,
+        admin::resources::handlers::bulk_delete_resources,
+        admin::dev::list_mailbox,
+        admin::dev::clear_mailbox,
+        notices::handlers::create_notice,
+        notices::handlers::list_notices,
+        notices::handlers::delete_notice,
+        maintenance::handlers::set_maintenance,
+        feature_flags::handlers::list_feature_flags,
+        feature_flags::handlers::set_feature_flag,
+    ),
+    // Types not reachable from a path, listed so clients can generate them
+    components(schemas(
+        auth::types::SignInRequest,
+        auth::types::SignInResponse,
+        auth::types::SignUpRequest,
+        auth::types::SignUpResponse,
+        auth::types::RefreshRequest,
+        auth::types::RefreshResponse,
+        auth::types::VerifyEmailRequest,
+        auth::types::VerifyEmailResponse,
+        auth::types::ConfirmEmailChangeResponse,
+        auth::types::ForgotPasswordRequest,
+        auth::types::ForgotPasswordResponse,
+        auth::types::ResetPasswordRequest,
+        auth::types::ResetPasswordResponse,
+        auth::types::ResendVerificationRequest,
+        auth::types::ResendVerificationResponse,
+        auth::types::BulkResendVerificationRequest,
+        auth::types::BulkResendVerificationResponse,
+        auth::types::RecoverAccountRequest,
+        auth::types::RecoverAccountResponse,
+        chat::types::CreateConversationRequest,
+        chat::types::UpdateConversationRequest,
+        chat::types::GenerateTitleRequest,
+        chat::types::GenerateTitleResponse,
+        chat::types::SendMessageRequest,
+        chat::types::EditMessageRequest,
+        chat::types::ChatConfig,
+        chat::types::ConversationResponse,
+        chat::types::ConversationListResponse,
+        chat::types::ConversationSummary,
+        chat::types::ConversationWithMessages,
+        chat::types::ChatMessage,
+        chat::types::MessageRole,
+        chat::types::SourceChunk,
+        chat::types::MessageResponse,
+        chat::types::ChatMetrics,
+        chat::types::DeleteConversationResponse,
+        chat::types::StreamEvent,
+        user::types::UserResponse,
+        user::types::AvatarResponse,
+        user::types::UpdateProfileRequest,
+        user::types::ChangePasswordRequest,
+        user::types::ChangePasswordResponse,
+        user::types::SetPasswordRequest,
+        user::types::SetPasswordResponse,
+        user::types::ChangeEmailRequest,
+        user::types::ChangeEmailResponse,
+        user::types::Account,
+        user::types::Session,
+        user::types::RenameSessionRequest,
+        user::types::SessionListResponse,
+        user::types::TokenQuotaResponse,
+        user::types::TokenUsageResponse,
+        user::types::DeleteAccountRequest,
+        user::types::DeleteAccountResponse,
+    )),
+    modifiers(&BearerAuth),
+    tags(
+        (name = "auth", description = "Sign-up, sign-in and account recovery"),
+        (name = "user", description = "The signed-in user's account"),
+        (name = "chat", description = "Conversations with the assistant"),
+        (name = "notices", description = "System notices"),
+        (name = "admin", description = "Administration (admin role required)"),
+    )
+)]
+pub struct ApiDoc;
+
+/// `/openapi.json` and the flag-gated `/swagger-ui`
+pub fn routes(app_state: AppState) -> Router<AppState> {
+    let swagger_ui: Router<AppState> = SwaggerUi::new("/swagger-ui")
+        .config(Config::from("/openapi.json"))
+        .into();
+
+    Router::new()
+        .route("/openapi.json", get(openapi_json))
+        .merge(swagger_ui.layer(middleware::from_fn_with_state(
+            app_state,
+            require_swagger_ui,
+        )))
+}
+
+/// GET /openapi.json
+async fn openapi_json() -> Json<utoipa::openapi::OpenApi> {
+    Json(ApiDoc::openapi())
+}
+
+/// 404 unless the Swagger UI feature flag is on
+async fn require_swagger_ui(State(state): State<AppState>, req: Request, next: Next) -> Response {
+    if !crate::is_enabled!(state, SWAGGER_UI) {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+
+    let mut response = next.run(req).await;
+    response.headers_mut().insert(
+        header::CONTENT_SECURITY_POLICY,
+        HeaderValue::from_static(SWAGGER_UI_CSP),
+    );
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spec_covers_public_routes() {
+        let spec = ApiDoc::openapi();
+        for path in [
+            "/auth/signin",
+            "/user/me",
+            "/chat/conversations/{id}/messages",
+            "/admin/feature-flags/{name}",
+        ] {
+            assert!(spec.paths.paths.contains_key(path), "missing {}", path);
+        }
+
+        let schemas = &spec.components.expect("components").schemas;
+        assert!(schemas.contains_key("SignInRequest"));
+        assert!(schemas.contains_key("StreamEvent"));
+        assert!(schemas.contains_key("ErrorBody"));
+    }
+}
@@ -0,0 +1,8 @@
+use axum::{Router, routing::get};
+
+use crate::gateway::AppState;
+use crate::storage::handlers::download_presigned;
+
+pub fn routes() -> Router<AppState> {
+    Router::new().route("/{*key}", get(download_presigned))
+}
@@ -1,20 +1,43 @@
 use axum::{
     Router,
+    extract::DefaultBodyLimit,
     routing::{delete, get, patch, post},
 };
 
 use crate::gateway::AppState;
 use crate::user::{
-    change_password, delete_account, list_sessions, me, revoke_session,
-    update_profile,
+    avatar, change_email, change_password, delete_account, export_data, get_notifications,
+    get_preferences, get_quota, get_usage, list_sessions, me, rename_session, revoke_session,
+    security_events, set_password, update_notifications, update_preferences, update_profile,
+    upload_avatar,
 };
 
 pub fn routes() -> Router<AppState> {
     Router::new()
         .route("/me", get(me))
         .route("/update-profile", patch(update_profile))
+        .route(
+            "/preferences",
+            get(get_preferences).patch(update_preferences),
+        )
+        .route(
+            "/notifications",
+            get(get_notifications).patch(update_notifications),
+        )
+        .route(
+            "/avatar",
+            // Leave room for the multipart framing around a maximum size image
+            post(upload_avatar).layer(DefaultBodyLimit::max(avatar::MAX_AVATAR_BYTES + 64 * 1024)),
+        )
         .route("/change-password", post(change_password))
+        .route("/set-password", post(set_password))
+        .route("/change-email", post(change_email))
         .route("/delete-account", delete(delete_account))
+        .route("/export-data", post(export_data).get(export_data))
         .route("/list-sessions", get(list_sessions))
         .route("/revoke-session/{session_id}", delete(revoke_session))
+        .route("/sessions/{session_id}", patch(rename_session))
+        .route("/quota", get(get_quota))
+        .route("/usage", get(get_usage))
+        .route("/security/events", get(security_events))
 }
@@ -0,0 +1,307 @@
+//! Circuit breaker for Intelligence service calls
+//!
+//! While the service is failing, calls fail fast with
+//! `Status::unavailable("circuit open")` instead of each request waiting
+//! through its own retries and timeouts.
+//!
+//! - **Closed**: calls pass through; failures within `failure_window` are
+//!   counted and `failure_threshold` of them in a row open the circuit.
+//! - **Open**: calls are rejected until `open_duration` has passed.
+//! - **Half-open**: a single trial call is let through. Success closes the
+//!   circuit, failure opens it again.
+
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use tokio::time::Instant;
+
+#[derive(Debug, Clone)]
+pub struct BreakerConfig {
+    /// Consecutive failures that open the circuit
+    pub failure_threshold: u32,
+    /// Failures further apart than this start a new count
+    pub failure_window: Duration,
+    /// How long the circuit stays open before a trial call
+    pub open_duration: Duration,
+}
+
+impl Default for BreakerConfig {
+    fn default() -> Self {
+        Self {
+            failure_threshold: 5,
+            failure_window: Duration::from_secs(10),
+            open_duration: Duration::from_secs(30),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CircuitState {
+    Closed,
+    Open,
+    HalfOpen,
+}
+
+#[derive(Debug)]
+pub struct BreakerState {
+    state: CircuitState,
+    consecutive_failures: u32,
+    /// First failure of the current run, for the failure window
+    first_failure_at: Option<Instant>,
+    /// When the circuit opened, or when the half-open trial started
+    opened_at: Option<Instant>,
+    trial_in_flight: bool,
+    last_trip: Option<DateTime<Utc>>,
+}
+
+/// Breaker state as reported by `/health/circuit-breaker`
+#[derive(Debug, Serialize)]
+pub struct BreakerSnapshot {
+    pub state: CircuitState,
+    pub failure_count: u32,
+    pub last_trip: Option<DateTime<Utc>>,
+}
+
+/// Shared breaker; clones observe and update the same state
+#[derive(Debug, Clone)]
+pub struct CircuitBreaker {
+    config: BreakerConfig,
+    state: Arc<Mutex<BreakerState>>,
+}
+
+impl Default for CircuitBreaker {
+    fn default() -> Self {
+        Self::new(BreakerConfig::default())
+    }
+}
+
+/// Whether a failed call says something about the service's health
+///
+/// Errors like `NotFound` or `InvalidArgument` mean the service answered,
+/// so they count as successes for the breaker.
+fn is_breaker_failure(status: &tonic::Status) -> bool {
+    matches!(
+        status.code(),
+        tonic::Code::Unavailable
+            | tonic::Code::DeadlineExceeded
+            | tonic::Code::Unknown
+            | tonic::Code::Internal
+    )
+}
+
+impl CircuitBreaker {
+    pub fn new(config: BreakerConfig) -> Self {
+        Self {
+            config,
+            state: Arc::new(Mutex::new(BreakerState {
+                state: CircuitState::Closed,
+                consecutive_failures: 0,
+                first_failure_at: None,
+                opened_at: None,
+                trial_in_flight: false,
+                last_trip: None,
+            })),
+        }
+    }
+
+    /// Run a call through the breaker
+    pub async fn call<T, F>(&self, call: F) -> Result<T, tonic::Status>
+    where
+        F: Future<Output = Result<T, tonic::Status>>,
+    {
+        self.acquire()?;
+        let result = call.await;
+        self.record(result.as_ref().err());
+        result
+    }
+
+    pub fn snapshot(&self) -> BreakerSnapshot {
+        let state = self.lock();
+        BreakerSnapshot {
+            state: state.state,
+            failure_count: state.consecutive_failures,
+            last_trip: state.last_trip,
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, BreakerState> {
+        // The state stays consistent even if a holder panicked
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Check whether a call may proceed
+    fn acquire(&self) -> Result<(), tonic::Status> {
+        let mut state = self.lock();
+        let now = Instant::now();
+        let waited_out = state
+            .opened_at
+            .is_some_and(|at| now.duration_since(at) >= self.config.open_duration);
+
+        match state.state {
+            CircuitState::Closed => Ok(()),
+            // A trial that never reported back (e.g. a cancelled request)
+            // doesn't block the circuit forever
+            CircuitState::Open | CircuitState::HalfOpen
+                if waited_out
+                    || (state.state == CircuitState::HalfOpen && !state.trial_in_flight) =>
+            {
+                if state.state == CircuitState::Open {
+                    tracing::info!("Intelligence circuit half-open, allowing a trial call");
+                }
+                state.state = CircuitState::HalfOpen;
+                state.opened_at = Some(now);
+                state.trial_in_flight = true;
+                Ok(())
+            }
+            CircuitState::Open | CircuitState::HalfOpen => {
+                Err(tonic::Status::unavailable("circuit open"))
+            }
+        }
+    }
+
+    /// Record the outcome of a call let through by `acquire`
+    fn record(&self, error: Option<&tonic::Status>) {
+        let mut state = self.lock();
+        let now = Instant::now();
+
+        if !error.is_some_and(is_breaker_failure) {
+            if state.state != CircuitState::Closed {
+                tracing::info!("Intelligence circuit closed");
+            }
+            state.state = CircuitState::Closed;
+            state.consecutive_failures = 0;
+            state.first_failure_at = None;
+            state.opened_at = None;
+            state.trial_in_flight = false;
+            return;
+        }
+
+        let window_expired = state
+            .first_failure_at
+            .is_none_or(|at| now.duration_since(at) > self.config.failure_window);
+        if window_expired && state.state == CircuitState::Closed {
+            state.consecutive_failures = 0;
+            state.first_failure_at = Some(now);
+        }
+        state.consecutive_failures += 1;
+
+        let trip = state.state == CircuitState::HalfOpen
+            || state.consecutive_failures >= self.config.failure_threshold;
+        if trip {
+            if state.state == CircuitState::Closed {
+                tracing::warn!(
+                    failures = state.consecutive_failures,
+                    "Intelligence circuit opened"
+                );
+            }
+            state.state = CircuitState::Open;
+            state.opened_at = Some(now);
+            state.trial_in_flight = false;
+            state.last_trip = Some(Utc::now());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::env::IntelligenceConfig;
+
+    async fn fail(breaker: &CircuitBreaker) -> tonic::Status {
+        breaker
+            .call(async { Err::<(), _>(tonic::Status::unavailable("down")) })
+            .await
+            .unwrap_err()
+    }
+
+    async fn succeed(breaker: &CircuitBreaker) -> Result<(), tonic::Status> {
+        breaker.call(async { Ok(()) }).await
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_opens_after_threshold_and_recovers() {
+        let breaker = CircuitBreaker::default();
+
+        for _ in 0..5 {
+            assert_eq!(fail(&breaker).await.message(), "down");
+        }
+        let snapshot = breaker.snapshot();
+        assert_eq!(snapshot.state, CircuitState::Open);
+        assert_eq!(snapshot.failure_count, 5);
+        assert!(snapshot.last_trip.is_some());
+
+        // Rejected without running the call
+        let status = breaker
+            .call(async { Err::<(), _>(tonic::Status::internal("call should not run")) })
+            .await
+            .unwrap_err();
+        assert_eq!(status.message(), "circuit open");
+
+        tokio::time::advance(Duration::from_secs(30)).await;
+        assert!(succeed(&breaker).await.is_ok());
+        assert_eq!(breaker.snapshot().state, CircuitState::Closed);
+        assert_eq!(breaker.snapshot().failure_count, 0);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_failed_trial_reopens() {
+        let breaker = CircuitBreaker::default();
+        for _ in 0..5 {
+            fail(&breaker).await;
+        }
+
+        tokio::time::advance(Duration::from_secs(30)).await;
+        assert_eq!(fail(&breaker).await.message(), "down");
+        assert_eq!(breaker.snapshot().state, CircuitState::Open);
+        assert_eq!(
+            succeed(&breaker).await.unwrap_err().message(),
+            "circuit open"
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_configured_threshold() {
+        let env = std::collections::HashMap::from([
+            ("INTELLIGENCE_BREAKER_THRESHOLD", "2"),
+            ("INTELLIGENCE_BREAKER_OPEN_SECONDS", "5"),
+            ("INTELLIGENCE_BREAKER_WINDOW_SECONDS", "0"),
+        ]);
+        let config = IntelligenceConfig::from_lookup(|key| env.get(key).map(|v| v.to_string()))
+            .circuit_breaker;
+        assert_eq!(config.failure_threshold, 2);
+        assert_eq!(config.open_duration, Duration::from_secs(5));
+        assert_eq!(config.failure_window, Duration::from_secs(10));
+
+        let breaker = CircuitBreaker::new(config);
+        fail(&breaker).await;
+        fail(&breaker).await;
+        assert_eq!(breaker.snapshot().state, CircuitState::Open);
+
+        tokio::time::advance(Duration::from_secs(5)).await;
+        assert!(succeed(&breaker).await.is_ok());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_spread_out_failures_do_not_open() {
+        let breaker = CircuitBreaker::default();
+
+        for _ in 0..4 {
+            fail(&breaker).await;
+        }
+        tokio::time::advance(Duration::from_secs(11)).await;
+        fail(&breaker).await;
+
+        assert_eq!(breaker.snapshot().state, CircuitState::Closed);
+        assert_eq!(breaker.snapshot().failure_count, 1);
+
+        // Answers from a healthy service reset the count
+        let _ = breaker
+            .call(async { Err::<(), _>(tonic::Status::not_found("missing")) })
+            .await;
+        assert_eq!(breaker.snapshot().failure_count, 0);
+    }
+}
@@ -1,8 +1,17 @@
 use std::time::Duration;
+use axum::http::HeaderMap;
+use bytes::{Bytes, BytesMut};
+use futures::{Stream, StreamExt};
+use sha2::{Digest, Sha256};
+use tonic::metadata::{Ascii, MetadataValue};
 use tonic::transport::{Channel, Endpoint};
+use tokio::sync::oneshot;
 use tokio::time::sleep;
 use uuid::Uuid;
 
+use crate::grpc::circuit_breaker::{BreakerConfig, CircuitBreaker};
+use crate::middleware::RequestId;
+
 use crate::grpc::proto::opentier::intelligence::v1 as pb;
 use crate::grpc::proto::opentier::intelligence::v1::chat_client::ChatClient;
 use crate::grpc::proto::opentier::intelligence::v1::health_client::HealthClient;
@@ -21,6 +30,31 @@ pub struct RpcTimeouts {
     pub health: Duration,
 }
 
+/// Header a caller can use to set its own deadline for an Intelligence call
+pub const TIMEOUT_HEADER: &str = "x-timeout-ms";
+
+/// Upper bound for `X-Timeout-Ms` on user calls, so a caller can't hold a
+/// call open for long
+pub const MAX_TIMEOUT_OVERRIDE: Duration = Duration::from_secs(5 * 60);
+
+/// Upper bound for `X-Timeout-Ms` on admin calls such as large ingestions
+pub const MAX_ADMIN_TIMEOUT_OVERRIDE: Duration = Duration::from_secs(30 * 60);
+
+/// Per-call timeout requested via `X-Timeout-Ms`, clamped to `limit`
+///
+/// Missing, zero or unparseable values mean "use the configured timeout".
+pub fn timeout_override(headers: &HeaderMap, limit: Duration) -> Option<Duration> {
+    headers
+        .get(TIMEOUT_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().parse::<u64>().ok())
+        .filter(|ms| *ms > 0)
+        .map(|ms| Duration::from_millis(ms).min(limit))
+}
+
+/// Size of the data chunks sent by chunked uploads
+const UPLOAD_CHUNK_SIZE: usize = 10 * 1024 * 1024; // 10MB chunks
+
 /// Retry configuration for transient failures
 #[derive(Clone)]
 pub struct RetryConfig {
@@ -32,6 +66,8 @@ pub struct RetryConfig {
     pub max_backoff: Duration,
     /// Backoff multiplier (exponential factor)
     pub backoff_multiplier: f64,
+    /// When to stop calling a failing service altogether
+    pub circuit_breaker: BreakerConfig,
 }
 
 impl Default for RetryConfig {
@@ -41,6 +77,7 @@ impl Default for RetryConfig {
             initial_backoff: Duration::from_millis(100),
             max_backoff: Duration::from_secs(10),
             backoff_multiplier: 2.0,
+            circuit_breaker: BreakerConfig::default(),
         }
     }
 }
@@ -64,6 +101,7 @@ pub struct IntelligenceClient {
     health_client: HealthClient<Channel>,
     timeouts: RpcTimeouts,
     retry_config: RetryConfig,
+    breaker: CircuitBreaker,
 }
 
 /// Check if a gRPC status code is retryable
@@ -78,6 +116,37 @@ fn is_retryable(status: &tonic::Status) -> bool {
     )
 }
 
+/// How long the server asked us to wait before retrying
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RetryHint {
+    After(Duration),
+    /// Negative `grpc-retry-pushback-ms`: the server does not want a retry
+    DoNotRetry,
+}
+
+/// Read `grpc-retry-pushback-ms` (milliseconds) or `retry-after` (seconds)
+/// from the status metadata
+fn retry_hint(status: &tonic::Status) -> Option<RetryHint> {
+    let metadata = status.metadata();
+
+    if let Some(value) = metadata.get("grpc-retry-pushback-ms") {
+        return match value.to_str().ok()?.trim().parse::<i64>() {
+            Ok(ms) if ms >= 0 => Some(RetryHint::After(Duration::from_millis(ms as u64))),
+            _ => Some(RetryHint::DoNotRetry),
+        };
+    }
+
+    let seconds = metadata
+        .get("retry-after")?
+        .to_str()
+        .ok()?
+        .trim()
+        .parse::<f64>()
+        .ok()
+        .filter(|s| s.is_finite() && *s >= 0.0)?;
+    Some(RetryHint::After(Duration::from_secs_f64(seconds)))
+}
+
 impl IntelligenceClient {
     /// Connect to intelligence service with default timeouts
     pub async fn connect(uri: &str) -> Result<Self, tonic::transport::Error> {
@@ -104,11 +173,12 @@ impl IntelligenceClient {
         timeouts: RpcTimeouts,
         retry_config: RetryConfig,
     ) -> Result<Self, tonic::transport::Error> {
-        // Use the longest timeout as the channel default
+        // Use the longest timeout as the channel default, leaving room for overrides
         let max_timeout = timeouts
             .chat
             .max(timeouts.stream)
-            .max(timeouts.resource);
+            .max(timeouts.resource)
+            .max(MAX_ADMIN_TIMEOUT_OVERRIDE);
 
         let endpoint = Endpoint::from_shared(uri.to_string())?
             .timeout(max_timeout)
@@ -126,6 +196,7 @@ impl IntelligenceClient {
             chat_client: ChatClient::new(channel.clone()),
             resource_client: ResourceServiceClient::new(channel.clone()),
             health_client: HealthClient::new(channel),
+            breaker: CircuitBreaker::new(retry_config.circuit_breaker.clone()),
             timeouts,
             retry_config,
         })
@@ -138,12 +209,13 @@ impl IntelligenceClient {
         timeouts: RpcTimeouts,
         retry_config: RetryConfig,
     ) -> Result<Self, tonic::transport::Error> {
-        // Use the longest timeout as the channel default
+        // Use the longest timeout as the channel default, leaving room for overrides
         // Per-RPC timeouts are set via request metadata
         let max_timeout = timeouts
             .chat
             .max(timeouts.stream)
-            .max(timeouts.resource);
+            .max(timeouts.resource)
+            .max(MAX_ADMIN_TIMEOUT_OVERRIDE);
 
         let endpoint = Endpoint::from_shared(uri.to_string())?
             .timeout(max_timeout)
@@ -160,11 +232,17 @@ impl IntelligenceClient {
             chat_client: ChatClient::new(channel.clone()),
             resource_client: ResourceServiceClient::new(channel.clone()),
             health_client: HealthClient::new(channel),
+            breaker: CircuitBreaker::new(retry_config.circuit_breaker.clone()),
             timeouts,
             retry_config,
         })
     }
 
+    /// Breaker guarding every call; shared by all clones of this client
+    pub fn circuit_breaker(&self) -> &CircuitBreaker {
+        &self.breaker
+    }
+
     /// Create a request with the specified timeout
     fn request_with_timeout<T>(&self, inner: T, timeout: Duration) -> tonic::Request<T> {
         let mut request = tonic::Request::new(inner);
@@ -173,16 +251,22 @@ impl IntelligenceClient {
     }
 
     /// Create a request with the specified timeout and a correlation ID for tracing
+    ///
+    /// The ID is the HTTP request's `X-Request-ID` when called while handling
+    /// one, so API and Intelligence logs can be joined on it.
     fn request_with_correlation<T>(&self, inner: T, timeout: Duration) -> tonic::Request<T> {
         let mut request = tonic::Request::new(inner);
         request.set_timeout(timeout);
         
         // Add correlation ID for distributed tracing
-        let correlation_id = Uuid::new_v4().to_string();
-        request.metadata_mut().insert(
-            "x-correlation-id",
-            correlation_id.parse().unwrap_or_else(|_| "unknown".parse().unwrap()),
-        );
+        let correlation_id = RequestId::current()
+            .map(|id| id.0)
+            .unwrap_or_else(|| Uuid::new_v4().to_string());
+        if let Ok(value) = correlation_id.parse::<MetadataValue<Ascii>>() {
+            request.metadata_mut().insert("x-request-id", value.clone());
+            request.metadata_mut().insert("x-correlation-id", value);
+        }
+        crate::observability::telemetry::inject_current_context(request.metadata_mut());
         
         request
     }
@@ -195,9 +279,47 @@ impl IntelligenceClient {
         )
     }
 
+    /// Delay before the next attempt: the server's hint when it sent one
+    /// (capped at `max_backoff`), otherwise our own backoff
+    fn retry_delay(&self, status: &tonic::Status, backoff: Duration) -> Duration {
+        match retry_hint(status) {
+            Some(RetryHint::After(delay)) => delay.min(self.retry_config.max_backoff),
+            _ => backoff,
+        }
+    }
+
     /// Check if we should retry based on attempt count and status
     fn should_retry(&self, status: &tonic::Status, attempts: u32) -> bool {
-        is_retryable(status) && attempts < self.retry_config.max_retries
+        is_retryable(status)
+            && attempts < self.retry_config.max_retries
+            && retry_hint(status) != Some(RetryHint::DoNotRetry)
+    }
+
+    /// Run `call` until it succeeds or fails in a way that isn't worth
+    /// retrying, backing off exponentially between attempts
+    ///
+    /// Only for idempotent calls; `call` builds a fresh request each time.
+    async fn with_retry<T, F, Fut>(&self, mut call: F) -> Result<T, tonic::Status>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, tonic::Status>>,
+    {
+        let mut attempts = 0;
+        let mut backoff = self.retry_config.initial_backoff;
+
+        loop {
+            match call().await {
+                Ok(result) => return Ok(result),
+                Err(status) if self.should_retry(&status, attempts) => {
+                    attempts += 1;
+                    let delay = self.retry_delay(&status, backoff);
+                    self.log_retry(&status, delay, attempts);
+                    sleep(delay).await;
+                    backoff = self.next_backoff(backoff);
+                }
+                Err(status) => return Err(status),
+            }
+        }
     }
 
     /// Log retry attempt
@@ -212,222 +334,201 @@ impl IntelligenceClient {
     }
 
     // Chat Methods
+
+    /// `timeout` overrides the configured chat timeout for this call
     pub async fn send_message(
         &mut self,
         request: pb::ChatRequest,
+        timeout: Option<Duration>,
     ) -> Result<tonic::Response<pb::ChatResponse>, tonic::Status> {
-        // Note: send_message is NOT idempotent, so we don't retry to avoid duplicate messages
-        // Use correlation ID for distributed tracing
-        let req = self.request_with_correlation(request, self.timeouts.chat);
-        self.chat_client.send_message(req).await
+        let timeout = timeout.unwrap_or(self.timeouts.chat);
+        let breaker = self.breaker.clone();
+        breaker
+            .call(async {
+                // Note: send_message is NOT idempotent, so we don't retry to avoid duplicate messages
+                // Use correlation ID for distributed tracing
+                let req = self.request_with_correlation(request, timeout);
+                self.chat_client.send_message(req).await
+            })
+            .await
     }
 
     pub async fn stream_chat(
         &mut self,
         request: pb::ChatRequest,
     ) -> Result<tonic::Response<tonic::codec::Streaming<pb::ChatStreamChunk>>, tonic::Status> {
-        // Note: stream_chat is NOT idempotent, so we don't retry
-        // Use correlation ID for distributed tracing
-        let req = self.request_with_correlation(request, self.timeouts.stream);
-        self.chat_client.stream_chat(req).await
+        let breaker = self.breaker.clone();
+        breaker
+            .call(async {
+                // Note: stream_chat is NOT idempotent, so we don't retry
+                // Use correlation ID for distributed tracing
+                let req = self.request_with_correlation(request, self.timeouts.stream);
+                self.chat_client.stream_chat(req).await
+            })
+            .await
     }
 
     pub async fn get_conversation(
         &mut self,
         request: pb::GetConversationRequest,
     ) -> Result<tonic::Response<pb::ConversationResponse>, tonic::Status> {
-        //  Retry for read-only operations with exponential backoff
-        let mut attempts = 0;
-        let mut backoff = self.retry_config.initial_backoff;
-
-        loop {
-            let req = self.request_with_timeout(request.clone(), self.timeouts.chat);
-            match self.chat_client.get_conversation(req).await {
-                Ok(result) => return Ok(result),
-                Err(status) if self.should_retry(&status, attempts) => {
-                    attempts += 1;
-                    self.log_retry(&status, backoff, attempts);
-                    sleep(backoff).await;
-                    backoff = self.next_backoff(backoff);
-                }
-                Err(status) => return Err(status),
-            }
-        }
+        let breaker = self.breaker.clone();
+        breaker
+            .call(async {
+                //  Retry for read-only operations with exponential backoff
+                self.with_retry(|| {
+                    let mut client = self.chat_client.clone();
+                    let req = self.request_with_timeout(request.clone(), self.timeouts.chat);
+                    async move { client.get_conversation(req).await }
+                })
+                .await
+            })
+            .await
     }
 
     pub async fn delete_conversation(
         &mut self,
         request: pb::DeleteConversationRequest,
     ) -> Result<tonic::Response<pb::DeleteConversationResponse>, tonic::Status> {
-        //  Delete is idempotent, safe to retry
-        let mut attempts = 0;
-        let mut backoff = self.retry_config.initial_backoff;
-
-        loop {
-            let req = self.request_with_timeout(request.clone(), self.timeouts.chat);
-            match self.chat_client.delete_conversation(req).await {
-                Ok(result) => return Ok(result),
-                Err(status) if self.should_retry(&status, attempts) => {
-                    attempts += 1;
-                    self.log_retry(&status, backoff, attempts);
-                    sleep(backoff).await;
-                    backoff = self.next_backoff(backoff);
-                }
-                Err(status) => return Err(status),
-            }
-        }
+        let breaker = self.breaker.clone();
+        breaker
+            .call(async {
+                //  Delete is idempotent, safe to retry
+                self.with_retry(|| {
+                    let mut client = self.chat_client.clone();
+                    let req = self.request_with_timeout(request.clone(), self.timeouts.chat);
+                    async move { client.delete_conversation(req).await }
+                })
+                .await
+            })
+            .await
     }
 
     pub async fn generate_title(
         &mut self,
         request: pb::GenerateTitleRequest,
     ) -> Result<tonic::Response<pb::GenerateTitleResponse>, tonic::Status> {
-        // Title generation is idempotent (same input = same output), safe to retry
-        let mut attempts = 0;
-        let mut backoff = self.retry_config.initial_backoff;
-
-        loop {
-            let req = self.request_with_timeout(request.clone(), self.timeouts.chat);
-            match self.chat_client.generate_title(req).await {
-                Ok(result) => return Ok(result),
-                Err(status) if self.should_retry(&status, attempts) => {
-                    attempts += 1;
-                    self.log_retry(&status, backoff, attempts);
-                    sleep(backoff).await;
-                    backoff = self.next_backoff(backoff);
-                }
-                Err(status) => return Err(status),
-            }
-        }
+        let breaker = self.breaker.clone();
+        breaker
+            .call(async {
+                // Title generation is idempotent (same input = same output), safe to retry
+                self.with_retry(|| {
+                    let mut client = self.chat_client.clone();
+                    let req = self.request_with_timeout(request.clone(), self.timeouts.chat);
+                    async move { client.generate_title(req).await }
+                })
+                .await
+            })
+            .await
     }
 
     // Resource Methods
+
+    /// `timeout` overrides the configured resource timeout for this call
     pub async fn add_resource(
         &mut self,
         request: pb::AddResourceRequest,
+        timeout: Option<Duration>,
     ) -> Result<tonic::Response<pb::AddResourceResponse>, tonic::Status> {
-        // Note: add_resource is NOT idempotent unless resource_id is provided
-        // Only retry if resource_id is set (makes it idempotent)
-        if request.resource_id.is_empty() {
-            let req = self.request_with_timeout(request, self.timeouts.resource);
-            self.resource_client.add_resource(req).await
-        } else {
-            //  Retry when resource_id provided (idempotent)
-            let mut attempts = 0;
-            let mut backoff = self.retry_config.initial_backoff;
-
-            loop {
-                let req = self.request_with_timeout(request.clone(), self.timeouts.resource);
-                match self.resource_client.add_resource(req).await {
-                    Ok(result) => return Ok(result),
-                    Err(status) if self.should_retry(&status, attempts) => {
-                        attempts += 1;
-                        self.log_retry(&status, backoff, attempts);
-                        sleep(backoff).await;
-                        backoff = self.next_backoff(backoff);
-                    }
-                    Err(status) => return Err(status),
+        let timeout = timeout.unwrap_or(self.timeouts.resource);
+        let breaker = self.breaker.clone();
+        breaker
+            .call(async {
+                // Note: add_resource is NOT idempotent unless resource_id is provided
+                // Only retry if resource_id is set (makes it idempotent)
+                if request.resource_id.is_empty() {
+                    let req = self.request_with_timeout(request, timeout);
+                    self.resource_client.add_resource(req).await
+                } else {
+                    //  Retry when resource_id provided (idempotent)
+                    self.with_retry(|| {
+                        let mut client = self.resource_client.clone();
+                        let req = self.request_with_timeout(request.clone(), timeout);
+                        async move { client.add_resource(req).await }
+                    })
+                    .await
                 }
-            }
-        }
+            })
+            .await
     }
 
     pub async fn get_resource_status(
         &mut self,
         request: pb::GetResourceStatusRequest,
     ) -> Result<tonic::Response<pb::ResourceStatusResponse>, tonic::Status> {
-        //  Retry for read-only operations
-        let mut attempts = 0;
-        let mut backoff = self.retry_config.initial_backoff;
-
-        loop {
-            let req = self.request_with_timeout(request.clone(), self.timeouts.resource);
-            match self.resource_client.get_resource_status(req).await {
-                Ok(result) => return Ok(result),
-                Err(status) if self.should_retry(&status, attempts) => {
-                    attempts += 1;
-                    self.log_retry(&status, backoff, attempts);
-                    sleep(backoff).await;
-                    backoff = self.next_backoff(backoff);
-                }
-                Err(status) => return Err(status),
-            }
-        }
+        let breaker = self.breaker.clone();
+        breaker
+            .call(async {
+                //  Retry for read-only operations
+                self.with_retry(|| {
+                    let mut client = self.resource_client.clone();
+                    let req = self.request_with_timeout(request.clone(), self.timeouts.resource);
+                    async move { client.get_resource_status(req).await }
+                })
+                .await
+            })
+            .await
     }
 
     pub async fn list_resources(
         &mut self,
         request: pb::ListResourcesRequest,
     ) -> Result<tonic::Response<pb::ListResourcesResponse>, tonic::Status> {
-        //  Retry for read-only operations
-        let mut attempts = 0;
-        let mut backoff = self.retry_config.initial_backoff;
-
-        loop {
-            let req = self.request_with_timeout(request.clone(), self.timeouts.resource);
-            match self.resource_client.list_resources(req).await {
-                Ok(result) => return Ok(result),
-                Err(status) if self.should_retry(&status, attempts) => {
-                    attempts += 1;
-                    self.log_retry(&status, backoff, attempts);
-                    sleep(backoff).await;
-                    backoff = self.next_backoff(backoff);
-                }
-                Err(status) => return Err(status),
-            }
-        }
+        let breaker = self.breaker.clone();
+        breaker
+            .call(async {
+                //  Retry for read-only operations
+                self.with_retry(|| {
+                    let mut client = self.resource_client.clone();
+                    let req = self.request_with_timeout(request.clone(), self.timeouts.resource);
+                    async move { client.list_resources(req).await }
+                })
+                .await
+            })
+            .await
     }
 
     pub async fn delete_resource(
         &mut self,
         request: pb::DeleteResourceRequest,
     ) -> Result<tonic::Response<pb::DeleteResourceResponse>, tonic::Status> {
-        //  Delete is idempotent, safe to retry
-        let mut attempts = 0;
-        let mut backoff = self.retry_config.initial_backoff;
-
-        loop {
-            let req = self.request_with_timeout(request.clone(), self.timeouts.resource);
-            match self.resource_client.delete_resource(req).await {
-                Ok(result) => return Ok(result),
-                Err(status) if self.should_retry(&status, attempts) => {
-                    attempts += 1;
-                    self.log_retry(&status, backoff, attempts);
-                    sleep(backoff).await;
-                    backoff = self.next_backoff(backoff);
-                }
-                Err(status) => return Err(status),
-            }
-        }
+        let breaker = self.breaker.clone();
+        breaker
+            .call(async {
+                //  Delete is idempotent, safe to retry
+                self.with_retry(|| {
+                    let mut client = self.resource_client.clone();
+                    let req = self.request_with_timeout(request.clone(), self.timeouts.resource);
+                    async move { client.delete_resource(req).await }
+                })
+                .await
+            })
+            .await
     }
 
     pub async fn cancel_ingestion(
         &mut self,
         request: pb::CancelIngestionRequest,
     ) -> Result<tonic::Response<pb::CancelIngestionResponse>, tonic::Status> {
-        //  Cancel is idempotent, safe to retry
-        let mut attempts = 0;
-        let mut backoff = self.retry_config.initial_backoff;
-
-        loop {
-            let req = self.request_with_timeout(request.clone(), self.timeouts.resource);
-            match self.resource_client.cancel_ingestion(req).await {
-                Ok(result) => return Ok(result),
-                Err(status) if self.should_retry(&status, attempts) => {
-                    attempts += 1;
-                    self.log_retry(&status, backoff, attempts);
-                    sleep(backoff).await;
-                    backoff = self.next_backoff(backoff);
-                }
-                Err(status) => return Err(status),
-            }
-        }
+        let breaker = self.breaker.clone();
+        breaker
+            .call(async {
+                //  Cancel is idempotent, safe to retry
+                self.with_retry(|| {
+                    let mut client = self.resource_client.clone();
+                    let req = self.request_with_timeout(request.clone(), self.timeouts.resource);
+                    async move { client.cancel_ingestion(req).await }
+                })
+                .await
+            })
+            .await
     }
 
     /// Upload a large file using chunked streaming
     /// 
     /// This method handles files > 100MB by streaming chunks to the server.
     /// The file is split into 10MB chunks and streamed with integrity verification.
+    #[allow(clippy::too_many_arguments)]
     pub async fn chunked_upload(
         &mut self,
         user_id: String,
@@ -440,61 +541,125 @@ impl IntelligenceClient {
         metadata: std::collections::HashMap<String, String>,
         config: Option<pb::IngestionConfig>,
     ) -> Result<tonic::Response<pb::ChunkedUploadResponse>, tonic::Status> {
-        use sha2::{Sha256, Digest};
-        
-        const CHUNK_SIZE: usize = 10 * 1024 * 1024; // 10MB chunks
+        let breaker = self.breaker.clone();
+        breaker
+            .call(async {
+                const CHUNK_SIZE: usize = UPLOAD_CHUNK_SIZE;
         
-        let total_size = file_data.len() as i64;
-        let total_chunks = ((file_data.len() + CHUNK_SIZE - 1) / CHUNK_SIZE) as i32;
+                let total_size = file_data.len() as i64;
+                let total_chunks = file_data.len().div_ceil(CHUNK_SIZE) as i32;
         
-        // Compute checksum
-        let mut hasher = Sha256::new();
-        hasher.update(&file_data);
-        let checksum = format!("{:x}", hasher.finalize());
+                // Compute checksum
+                let mut hasher = Sha256::new();
+                hasher.update(&file_data);
+                let checksum = format!("{:x}", hasher.finalize());
         
-        let resource_id = resource_id.unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+                let resource_id = resource_id.unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
         
-        // Build data chunks first (collect to owned Vec to avoid lifetime issues)
-        let file_len = file_data.len();
-        let data_chunks: Vec<pb::FileChunk> = file_data
-            .chunks(CHUNK_SIZE)
-            .enumerate()
-            .map(|(i, chunk)| {
-                let is_last = (i + 1) * CHUNK_SIZE >= file_len;
-                pb::FileChunk {
-                    payload: Some(pb::file_chunk::Payload::Data(chunk.to_vec())),
-                    chunk_index: (i + 1) as i32,
-                    is_last,
-                }
-            })
-            .collect();
+                // Build data chunks first (collect to owned Vec to avoid lifetime issues)
+                let file_len = file_data.len();
+                let data_chunks: Vec<pb::FileChunk> = file_data
+                    .chunks(CHUNK_SIZE)
+                    .enumerate()
+                    .map(|(i, chunk)| {
+                        let is_last = (i + 1) * CHUNK_SIZE >= file_len;
+                        pb::FileChunk {
+                            payload: Some(pb::file_chunk::Payload::Data(chunk.to_vec())),
+                            chunk_index: (i + 1) as i32,
+                            is_last,
+                        }
+                    })
+                    .collect();
         
-        // Build complete chunk stream with metadata first
-        let metadata_chunk = pb::FileChunk {
-            payload: Some(pb::file_chunk::Payload::Metadata(pb::ChunkMetadata {
-                user_id: user_id.clone(),
-                resource_id: resource_id.clone(),
-                filename: filename.clone(),
-                content_type,
-                total_size,
-                total_chunks,
-                r#type: resource_type.into(),
-                title,
-                metadata,
-                config,
-                checksum: Some(checksum),
-            })),
-            chunk_index: 0,
-            is_last: false,
-        };
+                // Build complete chunk stream with metadata first
+                let metadata_chunk = pb::FileChunk {
+                    payload: Some(pb::file_chunk::Payload::Metadata(pb::ChunkMetadata {
+                        user_id: user_id.clone(),
+                        resource_id: resource_id.clone(),
+                        filename: filename.clone(),
+                        content_type,
+                        total_size,
+                        total_chunks,
+                        r#type: resource_type.into(),
+                        title,
+                        metadata,
+                        config,
+                        checksum: Some(checksum),
+                    })),
+                    chunk_index: 0,
+                    is_last: false,
+                };
         
-        let chunks: Vec<pb::FileChunk> = std::iter::once(metadata_chunk)
-            .chain(data_chunks)
-            .collect();
+                let chunks: Vec<pb::FileChunk> = std::iter::once(metadata_chunk)
+                    .chain(data_chunks)
+                    .collect();
         
-        let request = tonic::Request::new(futures::stream::iter(chunks));
+                let request = tonic::Request::new(futures::stream::iter(chunks));
         
-        self.resource_client.chunked_upload(request).await
+                self.resource_client.chunked_upload(request).await
+            })
+            .await
+    }
+
+    /// Upload a file from a byte stream, such as a request body, without buffering it
+    ///
+    /// Chunks are sent as the stream produces them, so only about two chunks
+    /// are held in memory whatever the file size. `metadata` describes the
+    /// file; its size fields come from `total_size`, which must be the exact
+    /// length of `body` since the server rejects any mismatch, and an empty
+    /// `resource_id` gets a generated one. The SHA-256 is only known at the
+    /// end, so it is computed while streaming and checked against the
+    /// checksum the server reports instead of being sent up front.
+    pub async fn chunked_upload_stream<S, E>(
+        &mut self,
+        metadata: pb::ChunkMetadata,
+        total_size: u64,
+        body: S,
+    ) -> Result<tonic::Response<pb::ChunkedUploadResponse>, tonic::Status>
+    where
+        S: Stream<Item = Result<Bytes, E>> + Send + 'static,
+        E: std::fmt::Display + Send + 'static,
+    {
+        let (outcome_tx, mut outcome_rx) = oneshot::channel();
+        let chunks = upload_chunks(metadata, total_size, body, UPLOAD_CHUNK_SIZE, outcome_tx);
+
+        let breaker = self.breaker.clone();
+        breaker
+            .call(async {
+                // Not retried: the body can only be read once
+                let req = self.request_with_timeout(chunks, self.timeouts.resource);
+                let result = self.resource_client.chunked_upload(req).await;
+
+                // A failed body read explains whatever the server said
+                let checksum = match outcome_rx.try_recv() {
+                    Ok(Ok(checksum)) => checksum,
+                    Ok(Err(e)) => {
+                        return Err(tonic::Status::aborted(format!(
+                            "Failed to read upload body: {}",
+                            e
+                        )));
+                    }
+                    Err(_) => {
+                        result?;
+                        return Err(tonic::Status::aborted(
+                            "Upload finished before the whole body was sent",
+                        ));
+                    }
+                };
+
+                let response = result?;
+                if let Some(server_checksum) = &response.get_ref().checksum
+                    && !server_checksum.eq_ignore_ascii_case(&checksum)
+                {
+                    return Err(tonic::Status::data_loss(format!(
+                        "Upload checksum mismatch: sent {}, server computed {}",
+                        checksum, server_checksum
+                    )));
+                }
+
+                Ok(response)
+            })
+            .await
     }
 
     /// Synchronize resource metadata between API and Intelligence databases
@@ -505,67 +670,360 @@ impl IntelligenceClient {
         &mut self,
         request: pb::SyncMetadataRequest,
     ) -> Result<tonic::Response<pb::SyncMetadataResponse>, tonic::Status> {
-        //  Sync is idempotent, safe to retry
-        let mut attempts = 0;
-        let mut backoff = self.retry_config.initial_backoff;
-
-        loop {
-            let req = self.request_with_timeout(request.clone(), self.timeouts.resource);
-            match self.resource_client.sync_resource_metadata(req).await {
-                Ok(result) => return Ok(result),
-                Err(status) if self.should_retry(&status, attempts) => {
-                    attempts += 1;
-                    self.log_retry(&status, backoff, attempts);
-                    sleep(backoff).await;
-                    backoff = self.next_backoff(backoff);
-                }
-                Err(status) => return Err(status),
-            }
-        }
+        let breaker = self.breaker.clone();
+        breaker
+            .call(async {
+                //  Sync is idempotent, safe to retry
+                self.with_retry(|| {
+                    let mut client = self.resource_client.clone();
+                    let req = self.request_with_timeout(request.clone(), self.timeouts.resource);
+                    async move { client.sync_resource_metadata(req).await }
+                })
+                .await
+            })
+            .await
     }
 
     // Health Methods
     pub async fn check_health(
         &mut self,
     ) -> Result<tonic::Response<pb::HealthCheckResponse>, tonic::Status> {
-        //  Retry for health checks
-        let mut attempts = 0;
-        let mut backoff = self.retry_config.initial_backoff;
-
-        loop {
-            let req = self.request_with_timeout(pb::HealthCheckRequest {}, self.timeouts.health);
-            match self.health_client.check(req).await {
-                Ok(result) => return Ok(result),
-                Err(status) if self.should_retry(&status, attempts) => {
-                    attempts += 1;
-                    self.log_retry(&status, backoff, attempts);
-                    sleep(backoff).await;
-                    backoff = self.next_backoff(backoff);
-                }
-                Err(status) => return Err(status),
-            }
-        }
+        let breaker = self.breaker.clone();
+        breaker
+            .call(async {
+                //  Retry for health checks
+                self.with_retry(|| {
+                    let mut client = self.health_client.clone();
+                    let req = self.request_with_timeout(pb::HealthCheckRequest {}, self.timeouts.health);
+                    async move { client.check(req).await }
+                })
+                .await
+            })
+            .await
     }
 
     pub async fn check_ready(
         &mut self,
     ) -> Result<tonic::Response<pb::ReadyCheckResponse>, tonic::Status> {
-        //  Retry for health checks
-        let mut attempts = 0;
-        let mut backoff = self.retry_config.initial_backoff;
+        let breaker = self.breaker.clone();
+        breaker
+            .call(async {
+                //  Retry for health checks
+                self.with_retry(|| {
+                    let mut client = self.health_client.clone();
+                    let req = self.request_with_timeout(pb::ReadyCheckRequest {}, self.timeouts.health);
+                    async move { client.ready(req).await }
+                })
+                .await
+            })
+            .await
+    }
+}
 
-        loop {
-            let req = self.request_with_timeout(pb::ReadyCheckRequest {}, self.timeouts.health);
-            match self.health_client.ready(req).await {
-                Ok(result) => return Ok(result),
-                Err(status) if self.should_retry(&status, attempts) => {
-                    attempts += 1;
-                    self.log_retry(&status, backoff, attempts);
-                    sleep(backoff).await;
-                    backoff = self.next_backoff(backoff);
+/// Re-chunk a byte stream into upload data chunks, numbered from 1
+///
+/// The metadata chunk followed by `body` split into `chunk_size` data chunks
+///
+/// The metadata's size fields describe `total_size` bytes in `chunk_size`
+/// chunks, matching what [`data_chunks`] produces for a body of that length.
+fn upload_chunks<S, E>(
+    mut metadata: pb::ChunkMetadata,
+    total_size: u64,
+    body: S,
+    chunk_size: usize,
+    outcome: oneshot::Sender<Result<String, E>>,
+) -> impl Stream<Item = pb::FileChunk>
+where
+    S: Stream<Item = Result<Bytes, E>> + Send + 'static,
+    E: Send + 'static,
+{
+    if metadata.resource_id.is_empty() {
+        metadata.resource_id = Uuid::new_v4().to_string();
+    }
+    metadata.total_size = total_size as i64;
+    metadata.total_chunks = total_size.div_ceil(chunk_size as u64) as i32;
+    metadata.checksum = None;
+
+    let metadata_chunk = pb::FileChunk {
+        payload: Some(pb::file_chunk::Payload::Metadata(metadata)),
+        chunk_index: 0,
+        is_last: false,
+    };
+    futures::stream::once(async { metadata_chunk }).chain(data_chunks(body, chunk_size, outcome))
+}
+
+/// Each full chunk is held back until the next data arrives so the final one
+/// can be flagged `is_last`. When the input ends, the hex SHA-256 of all the
+/// bytes is sent on `outcome`; if it fails, the stream stops early and the
+/// error is sent instead.
+fn data_chunks<S, E>(
+    body: S,
+    chunk_size: usize,
+    outcome: oneshot::Sender<Result<String, E>>,
+) -> impl Stream<Item = pb::FileChunk>
+where
+    S: Stream<Item = Result<Bytes, E>> + Send + 'static,
+    E: Send + 'static,
+{
+    let data_chunk = |data: Vec<u8>, chunk_index: i32, is_last: bool| pb::FileChunk {
+        payload: Some(pb::file_chunk::Payload::Data(data)),
+        chunk_index,
+        is_last,
+    };
+
+    async_stream::stream! {
+        let mut hasher = Sha256::new();
+        let mut buffer = BytesMut::new();
+        let mut held: Option<Vec<u8>> = None;
+        let mut chunk_index = 1;
+
+        futures::pin_mut!(body);
+        while let Some(bytes) = body.next().await {
+            let bytes = match bytes {
+                Ok(bytes) => bytes,
+                Err(e) => {
+                    let _ = outcome.send(Err(e));
+                    return;
+                }
+            };
+            hasher.update(&bytes);
+            buffer.extend_from_slice(&bytes);
+
+            while buffer.len() >= chunk_size {
+                let chunk = buffer.split_to(chunk_size).to_vec();
+                if let Some(previous) = held.replace(chunk) {
+                    yield data_chunk(previous, chunk_index, false);
+                    chunk_index += 1;
                 }
-                Err(status) => return Err(status),
             }
         }
+
+        if !buffer.is_empty()
+            && let Some(previous) = held.replace(buffer.to_vec())
+        {
+            yield data_chunk(previous, chunk_index, false);
+            chunk_index += 1;
+        }
+        if let Some(last) = held {
+            yield data_chunk(last, chunk_index, true);
+        }
+
+        let _ = outcome.send(Ok(format!("{:x}", hasher.finalize())));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn status_with(key: &'static str, value: &str) -> tonic::Status {
+        let mut status = tonic::Status::resource_exhausted("slow down");
+        status.metadata_mut().insert(key, value.parse().unwrap());
+        status
+    }
+
+    async fn collect_chunks(
+        parts: Vec<Result<&'static str, &'static str>>,
+    ) -> (Vec<(String, i32, bool)>, Result<String, &'static str>) {
+        let (tx, rx) = oneshot::channel();
+        let body = futures::stream::iter(parts.into_iter().map(|part| part.map(Bytes::from)));
+        let chunks = data_chunks(body, 4, tx)
+            .map(|chunk| match chunk.payload {
+                Some(pb::file_chunk::Payload::Data(data)) => (
+                    String::from_utf8(data).unwrap(),
+                    chunk.chunk_index,
+                    chunk.is_last,
+                ),
+                _ => panic!("expected a data chunk"),
+            })
+            .collect()
+            .await;
+        (chunks, rx.await.unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_stream_is_rechunked_with_checksum() {
+        let (chunks, outcome) = collect_chunks(vec![Ok("abc"), Ok("defgh"), Ok("ij")]).await;
+        assert_eq!(
+            chunks,
+            vec![
+                ("abcd".to_string(), 1, false),
+                ("efgh".to_string(), 2, false),
+                ("ij".to_string(), 3, true),
+            ]
+        );
+        assert_eq!(
+            outcome.unwrap(),
+            format!("{:x}", Sha256::digest(b"abcdefghij"))
+        );
+
+        // An exact multiple still flags the final full chunk
+        let (chunks, _) = collect_chunks(vec![Ok("abcdefgh")]).await;
+        assert_eq!(
+            chunks,
+            vec![
+                ("abcd".to_string(), 1, false),
+                ("efgh".to_string(), 2, true)
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_upload_metadata_describes_streamed_data() {
+        let (tx, _rx) = oneshot::channel::<Result<String, &'static str>>();
+        let body = futures::stream::iter(["abc", "defgh", "ij"].map(|part| Ok(Bytes::from(part))));
+        let chunks: Vec<pb::FileChunk> =
+            upload_chunks(pb::ChunkMetadata::default(), 10, body, 4, tx)
+                .collect()
+                .await;
+
+        let Some(pb::file_chunk::Payload::Metadata(metadata)) = &chunks[0].payload else {
+            panic!("expected the metadata chunk first");
+        };
+        assert_eq!(chunks[0].chunk_index, 0);
+        assert!(!metadata.resource_id.is_empty());
+
+        // The same checks the Intelligence service's ChunkedUpload makes
+        let data = &chunks[1..];
+        let sent: usize = data
+            .iter()
+            .map(|chunk| match &chunk.payload {
+                Some(pb::file_chunk::Payload::Data(data)) => data.len(),
+                _ => panic!("expected a data chunk"),
+            })
+            .sum();
+        assert_eq!(metadata.total_size, sent as i64);
+        assert_eq!(metadata.total_chunks, data.len() as i32);
+        assert!(data.last().unwrap().is_last);
+        for (i, chunk) in data.iter().enumerate() {
+            assert_eq!(chunk.chunk_index, i as i32 + 1);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_stream_error_stops_upload() {
+        let (chunks, outcome) =
+            collect_chunks(vec![Ok("abcdefgh"), Err("connection reset"), Ok("ij")]).await;
+
+        // Nothing is flagged last, so the server sees an incomplete upload
+        assert_eq!(chunks, vec![("abcd".to_string(), 1, false)]);
+        assert_eq!(outcome, Err("connection reset"));
+    }
+
+    #[test]
+    fn test_timeout_override_from_header() {
+        let with = |value: &str| {
+            let mut headers = HeaderMap::new();
+            headers.insert(TIMEOUT_HEADER, value.parse().unwrap());
+            timeout_override(&headers, MAX_TIMEOUT_OVERRIDE)
+        };
+
+        assert_eq!(with("1500"), Some(Duration::from_millis(1500)));
+        assert_eq!(with("999999999"), Some(Duration::from_secs(300)));
+        assert_eq!(with("0"), None);
+        assert_eq!(with("-5"), None);
+        assert_eq!(with("soon"), None);
+        assert_eq!(timeout_override(&HeaderMap::new(), MAX_TIMEOUT_OVERRIDE), None);
+
+        // Admin calls may wait longer, but not indefinitely either
+        let mut headers = HeaderMap::new();
+        headers.insert(TIMEOUT_HEADER, "900000".parse().unwrap());
+        assert_eq!(
+            timeout_override(&headers, MAX_ADMIN_TIMEOUT_OVERRIDE),
+            Some(Duration::from_secs(900))
+        );
+        headers.insert(TIMEOUT_HEADER, "999999999".parse().unwrap());
+        assert_eq!(
+            timeout_override(&headers, MAX_ADMIN_TIMEOUT_OVERRIDE),
+            Some(Duration::from_secs(30 * 60))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_server_retry_hint_overrides_backoff() {
+        let client = IntelligenceClient::connect_lazy("http://127.0.0.1:1")
+            .await
+            .unwrap();
+        let backoff = Duration::from_millis(100);
+
+        let pushback = status_with("grpc-retry-pushback-ms", "1500");
+        assert_eq!(
+            client.retry_delay(&pushback, backoff),
+            Duration::from_millis(1500)
+        );
+
+        let retry_after = status_with("retry-after", "2");
+        assert_eq!(
+            client.retry_delay(&retry_after, backoff),
+            Duration::from_secs(2)
+        );
+
+        // Capped at max_backoff (10s by default)
+        let too_long = status_with("retry-after", "3600");
+        assert_eq!(
+            client.retry_delay(&too_long, backoff),
+            Duration::from_secs(10)
+        );
+
+        let no_hint = tonic::Status::unavailable("down");
+        assert_eq!(client.retry_delay(&no_hint, backoff), backoff);
+        assert!(client.should_retry(&no_hint, 0));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_with_retry_stops_on_success_or_permanent_errors() {
+        let client = IntelligenceClient::connect_lazy("http://127.0.0.1:1")
+            .await
+            .unwrap();
+
+        let mut calls = 0;
+        let result = client
+            .with_retry(|| {
+                calls += 1;
+                let attempt = calls;
+                async move {
+                    if attempt < 3 {
+                        Err(tonic::Status::unavailable("down"))
+                    } else {
+                        Ok(attempt)
+                    }
+                }
+            })
+            .await;
+        assert_eq!(result.unwrap(), 3);
+
+        let mut calls = 0;
+        let result: Result<(), _> = client
+            .with_retry(|| {
+                calls += 1;
+                async { Err(tonic::Status::invalid_argument("bad")) }
+            })
+            .await;
+        assert_eq!(result.unwrap_err().code(), tonic::Code::InvalidArgument);
+        assert_eq!(calls, 1);
+
+        // Gives up after `max_retries` retries
+        let mut calls = 0;
+        let result: Result<(), _> = client
+            .with_retry(|| {
+                calls += 1;
+                async { Err(tonic::Status::unavailable("down")) }
+            })
+            .await;
+        assert!(result.is_err());
+        assert_eq!(calls, 4);
+    }
+
+    #[tokio::test]
+    async fn test_negative_pushback_stops_retries() {
+        let client = IntelligenceClient::connect_lazy("http://127.0.0.1:1")
+            .await
+            .unwrap();
+
+        let status = status_with("grpc-retry-pushback-ms", "-1");
+        assert_eq!(retry_hint(&status), Some(RetryHint::DoNotRetry));
+        assert!(!client.should_retry(&status, 0));
+
+        assert_eq!(retry_hint(&status_with("retry-after", "soon")), None);
     }
 }
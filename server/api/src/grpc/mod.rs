@@ -1,3 +1,4 @@
+pub mod circuit_breaker;
 pub mod client;
 pub mod proto;
 
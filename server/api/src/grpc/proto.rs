@@ -4,6 +4,7 @@
 // Wrap the generated code in the expected module structure
 pub mod opentier {
     pub mod intelligence {
+        #[allow(clippy::all)]
         pub mod v1 {
             // Include the generated proto code
             // The file is generated in OUT_DIR during build
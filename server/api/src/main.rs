@@ -4,10 +4,14 @@ mod chat;
 mod common;
 mod config;
 mod email;
+mod feature_flags;
 mod gateway;
 mod grpc;
+mod maintenance;
 mod middleware;
+mod notices;
 mod observability;
+mod storage;
 mod user;
 
 use std::net::SocketAddr;
@@ -21,25 +25,74 @@ async fn main() {
         .expect("Failed to load configuration. Please check your .env file and ensure all required variables are set.");
 
     // ---- Logging / observability ----
-    observability::logging::init();
+    let tracer_provider = observability::logging::init(&config.telemetry);
 
     tracing::info!("🔧 Configuration loaded successfully");
+    if config.jwt.enabled {
+        tracing::info!(
+            "🔑 JWT access tokens enabled ({}s TTL)",
+            config.jwt.access_token_ttl_seconds
+        );
+    }
+    if let Some(endpoint) = tracer_provider
+        .as_ref()
+        .and(config.telemetry.otlp_endpoint.as_deref())
+    {
+        tracing::info!("🔭 Exporting traces to {}", endpoint);
+    }
+    if config.captcha.is_enabled() {
+        tracing::info!("🛡️  CAPTCHA verification enabled for signup and password reset");
+    }
     // tracing::debug!("Server: {}:{}", config.server.host, config.server.port);
     // tracing::debug!("Database: {}", config.database.url);
 
     // ---- DB ----
-    let db = config::database::connect(&config.database.url).await;
+    let db = config::database::connect(&config.database).await;
+
+    // ---- Object Storage ----
+    let storage = storage::from_config(&config.storage).expect("Failed to initialize object storage");
+    tracing::info!("🗄️  Object storage backend: {:?}", config.storage.backend);
+
+    // ---- Redis (optional) ----
+    let cache = config::cache::connect(config.cache.redis_url.as_deref()).await;
+    if cache.is_some() {
+        tracing::info!("🧠 Redis session cache enabled");
+    }
 
     // ---- Background Tasks ----
-    auth::background::start_session_cleanup_task(db.clone());
+    auth::background::start_session_cleanup_task(db.clone(), &config.security);
+    chat::idempotency::start_cleanup_task(db.clone());
+    auth::background::start_auth_event_cleanup_task(
+        db.clone(),
+        config.security.auth_event_retention_days,
+    );
+    if config.oauth.token_refresh_enabled {
+        auth::background::start_oauth_token_refresh_task(db.clone(), config.oauth.clone());
+    }
+    let email_queue = email::queue::start_email_worker(&config.email, db.clone());
+    let session_denylist = auth::jwt::SessionDenylist::default();
+    if config.jwt.enabled {
+        auth::background::start_denylist_refresh_task(db.clone(), session_denylist.clone());
+    }
 
     // ---- gRPC Client ----
     let intelligence_url = std::env::var("INTELLIGENCE_SERVICE_URL")
         .unwrap_or_else(|_| "http://[::1]:50051".to_string());
+    let rpc_timeouts = crate::grpc::client::RpcTimeouts::default();
+    let retry_config = crate::grpc::client::RetryConfig {
+        circuit_breaker: config.intelligence.circuit_breaker.clone(),
+        ..Default::default()
+    };
 
     // Attempt connection with graceful degradation
     // If Intelligence service is unavailable, log warning but continue startup
-    let intelligence_client = match crate::grpc::client::IntelligenceClient::connect(&intelligence_url).await {
+    let intelligence_client = match crate::grpc::client::IntelligenceClient::connect_with_config(
+        &intelligence_url,
+        rpc_timeouts.clone(),
+        retry_config.clone(),
+    )
+    .await
+    {
         Ok(client) => {
             tracing::info!("✅ Connected to Intelligence service at {}", intelligence_url);
             client
@@ -52,7 +105,13 @@ async fn main() {
                 e
             );
             // Create client that will attempt lazy reconnection on first use
-            match crate::grpc::client::IntelligenceClient::connect_lazy(&intelligence_url).await {
+            match crate::grpc::client::IntelligenceClient::connect_lazy_with_config(
+                &intelligence_url,
+                rpc_timeouts.clone(),
+                retry_config.clone(),
+            )
+            .await
+            {
                 Ok(client) => client,
                 Err(lazy_err) => {
                     tracing::error!(
@@ -61,16 +120,55 @@ async fn main() {
                         lazy_err
                     );
                     // Still try to create the client - it will error on actual use
-                    crate::grpc::client::IntelligenceClient::connect(&intelligence_url)
-                        .await
+                    crate::grpc::client::IntelligenceClient::connect_with_config(
+                        &intelligence_url,
+                        rpc_timeouts,
+                        retry_config,
+                    )
+                    .await
                         .expect("Failed to connect to intelligence service after multiple attempts")
                 }
             }
         }
     };
+    user::purge::start_account_purge_task(
+        db.clone(),
+        user::purge::PurgeTargets {
+            client: intelligence_client.clone(),
+            storage: storage.clone(),
+            cache: cache.clone(),
+            api_url: config.storage.public_url.clone(),
+        },
+        &config.security,
+    );
+
+    // ---- Maintenance Mode ----
+    let maintenance = maintenance::MaintenanceMode::load(&db)
+        .await
+        .expect("Failed to load maintenance mode state");
+    if maintenance.is_enabled() {
+        tracing::warn!("🚧 Maintenance mode is on; only admins are served");
+    }
+    maintenance::start_refresh_task(db.clone(), maintenance.clone());
+
+    // ---- Feature Flags ----
+    let feature_flags = feature_flags::FeatureFlags::load(&db)
+        .await
+        .expect("Failed to load feature flags");
+    feature_flags::start_refresh_task(db.clone(), feature_flags.clone());
 
     // ---- Router ----
-    let app = gateway::router(db.clone(), config.clone(), intelligence_client);
+    let app = gateway::router(
+        db.clone(),
+        config.clone(),
+        intelligence_client,
+        email_queue,
+        session_denylist,
+        storage,
+        cache,
+        maintenance,
+        feature_flags,
+    );
 
     // ---- Listener ----
     let addr = config::server::addr(&config.server.host, config.server.port);
@@ -81,11 +179,17 @@ async fn main() {
 
     // ---- Serve ----
     // IMPORTANT: Use into_make_service_with_connect_info for rate limiting to work
-    // This allows PeerIpKeyExtractor to extract client IP addresses
+    // The socket address is the starting point for resolving client IPs
     axum::serve(
         listener,
         app.into_make_service_with_connect_info::<SocketAddr>(),
     )
     .await
     .unwrap();
+
+    if let Some(provider) = tracer_provider
+        && let Err(e) = provider.shutdown()
+    {
+        tracing::warn!("Failed to flush traces on shutdown: {}", e);
+    }
 }
@@ -0,0 +1,37 @@
+use axum::{Extension, Json, extract::State};
+use uuid::Uuid;
+
+use super::{MaintenanceState, SetMaintenanceRequest};
+use crate::admin::management::errors::ManagementError;
+use crate::gateway::AppState;
+use crate::gateway::openapi::ErrorBody;
+
+/// Turn maintenance mode on or off
+/// PUT /admin/maintenance
+///
+/// Takes effect immediately on this instance and is kept across restarts.
+#[utoipa::path(
+    put,
+    path = "/admin/maintenance",
+    tag = "admin",
+    responses(
+        (status = 200, description = "Maintenance state in effect"),
+        (status = 400, description = "Message too long", body = ErrorBody),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn set_maintenance(
+    State(state): State<AppState>,
+    Extension(admin_id): Extension<Uuid>,
+    Json(req): Json<SetMaintenanceRequest>,
+) -> Result<Json<MaintenanceState>, ManagementError> {
+    let new_state = req.into_state().map_err(ManagementError::Validation)?;
+    state.maintenance.set(&state.db, new_state.clone()).await?;
+
+    tracing::warn!(
+        %admin_id,
+        enabled = new_state.enabled,
+        "Admin changed maintenance mode"
+    );
+    Ok(Json(new_state))
+}
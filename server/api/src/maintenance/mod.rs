@@ -0,0 +1,229 @@
+//! Maintenance mode
+//!
+//! While enabled, `maintenance_middleware` answers every request outside
+//! `/admin` and `/health` with a 503, unless it comes from an admin. The flag
+//! is checked on every request, so it lives in memory; it is also stored in
+//! `system_config` so a restart doesn't silently reopen the service, and
+//! re-read every `REFRESH_INTERVAL_SECONDS` so a change made through one
+//! instance reaches the others.
+
+pub mod handlers;
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
+
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+
+use crate::common::background;
+
+/// `system_config` key holding the maintenance state
+const CONFIG_KEY: &str = "maintenance";
+
+/// How often the state is re-read from the database
+const REFRESH_INTERVAL_SECONDS: u64 = 15;
+
+pub const MAX_MESSAGE_LENGTH: usize = 500;
+
+/// Shown when the admin didn't give a message
+pub const DEFAULT_MESSAGE: &str = "The service is undergoing maintenance, please try again later";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MaintenanceState {
+    pub enabled: bool,
+    pub message: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SetMaintenanceRequest {
+    pub enabled: bool,
+    pub message: Option<String>,
+}
+
+impl SetMaintenanceRequest {
+    /// The state to store; blank messages are dropped
+    pub fn into_state(self) -> Result<MaintenanceState, String> {
+        let message = self
+            .message
+            .map(|m| m.trim().to_string())
+            .filter(|m| !m.is_empty());
+
+        if let Some(message) = &message
+            && message.len() > MAX_MESSAGE_LENGTH
+        {
+            return Err(format!(
+                "message must be at most {} characters",
+                MAX_MESSAGE_LENGTH
+            ));
+        }
+
+        Ok(MaintenanceState {
+            enabled: self.enabled,
+            message,
+        })
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct MaintenanceMode {
+    enabled: Arc<AtomicBool>,
+    message: Arc<RwLock<Option<String>>>,
+}
+
+impl MaintenanceMode {
+    /// Restore the state saved by the last `set`, off if none was saved
+    pub async fn load(db: &PgPool) -> Result<Self, sqlx::Error> {
+        let mode = Self::default();
+        mode.refresh(db).await?;
+        Ok(mode)
+    }
+
+    /// Re-read the stored state, picking up changes made through other instances
+    pub async fn refresh(&self, db: &PgPool) -> Result<(), sqlx::Error> {
+        let stored =
+            sqlx::query_scalar!("SELECT value FROM system_config WHERE key = $1", CONFIG_KEY)
+                .fetch_optional(db)
+                .await?;
+
+        let Some(value) = stored else {
+            return Ok(());
+        };
+        match serde_json::from_value::<MaintenanceState>(value) {
+            Ok(state) => self.apply(state),
+            Err(e) => tracing::warn!("Ignoring malformed maintenance state: {}", e),
+        }
+        Ok(())
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    /// Message for blocked requests
+    pub fn message(&self) -> String {
+        self.message
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone()
+            .unwrap_or_else(|| DEFAULT_MESSAGE.to_string())
+    }
+
+    pub fn state(&self) -> MaintenanceState {
+        MaintenanceState {
+            enabled: self.is_enabled(),
+            message: self
+                .message
+                .read()
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                .clone(),
+        }
+    }
+
+    /// Save the state, then apply it
+    ///
+    /// Saving first means a failed write leaves this instance unchanged rather
+    /// than out of step with what the next restart will load.
+    pub async fn set(&self, db: &PgPool, state: MaintenanceState) -> Result<(), sqlx::Error> {
+        let value = serde_json::to_value(&state).expect("maintenance state serializes");
+        sqlx::query!(
+            r#"
+            INSERT INTO system_config (key, value)
+            VALUES ($1, $2)
+            ON CONFLICT (key) DO UPDATE SET value = EXCLUDED.value, updated_at = NOW()
+            "#,
+            CONFIG_KEY,
+            value
+        )
+        .execute(db)
+        .await?;
+
+        self.apply(state);
+        Ok(())
+    }
+
+    fn apply(&self, state: MaintenanceState) {
+        *self
+            .message
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = state.message;
+        self.enabled.store(state.enabled, Ordering::Relaxed);
+    }
+}
+
+/// Keep the in-memory state in step with `system_config`
+pub fn start_refresh_task(db: PgPool, mode: MaintenanceMode) {
+    background::start_periodic_task(
+        db,
+        "Maintenance mode refresh",
+        REFRESH_INTERVAL_SECONDS,
+        move |db| {
+            let mode = mode.clone();
+            async move {
+                mode.refresh(&db).await?;
+                Ok(0)
+            }
+        },
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_request_message_normalized() {
+        let req = SetMaintenanceRequest {
+            enabled: true,
+            message: Some("   ".to_string()),
+        };
+        assert_eq!(
+            req.into_state(),
+            Ok(MaintenanceState {
+                enabled: true,
+                message: None,
+            })
+        );
+
+        let req = SetMaintenanceRequest {
+            enabled: true,
+            message: Some("x".repeat(MAX_MESSAGE_LENGTH + 1)),
+        };
+        assert!(req.into_state().is_err());
+    }
+
+    #[test]
+    fn test_message_falls_back_to_default() {
+        let mode = MaintenanceMode::default();
+        assert!(!mode.is_enabled());
+        assert_eq!(mode.message(), DEFAULT_MESSAGE);
+
+        mode.apply(MaintenanceState {
+            enabled: true,
+            message: Some("Back at 10:00 UTC".to_string()),
+        });
+        assert!(mode.is_enabled());
+        assert_eq!(mode.message(), "Back at 10:00 UTC");
+    }
+
+    #[sqlx::test]
+    async fn test_refresh_picks_up_changes_from_other_instances(db: PgPool) {
+        let this = MaintenanceMode::load(&db).await.unwrap();
+        let other = MaintenanceMode::load(&db).await.unwrap();
+        assert!(!this.is_enabled());
+
+        let on = MaintenanceState {
+            enabled: true,
+            message: Some("Upgrading".to_string()),
+        };
+        other.set(&db, on.clone()).await.unwrap();
+        this.refresh(&db).await.unwrap();
+        assert_eq!(this.state(), on);
+
+        other
+            .set(&db, MaintenanceState { enabled: false, message: None })
+            .await
+            .unwrap();
+        this.refresh(&db).await.unwrap();
+        assert!(!this.is_enabled());
+    }
+}
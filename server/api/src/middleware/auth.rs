@@ -4,12 +4,18 @@
 
 use axum::{
     extract::{Request, State},
-    http::{StatusCode, header},
+    http::{HeaderMap, StatusCode, header},
     middleware::Next,
     response::Response,
 };
+use sqlx::PgPool;
+use uuid::Uuid;
 
-use crate::auth::{AuthError, Role, session};
+use crate::auth::jwt::SessionDenylist;
+use crate::auth::session::{CurrentSession, IsImpersonated};
+use crate::auth::{AuthError, Role, jwt, session};
+use crate::config::cache::RedisPool;
+use crate::config::env::JwtConfig;
 use crate::gateway::AppState;
 
 // ===== Authentication Middleware =====
@@ -17,7 +23,11 @@ use crate::gateway::AppState;
 /// Auth middleware that validates session and injects user_id and role
 ///
 /// Extracts the Bearer token from the Authorization header, validates the session,
-/// and injects both user_id and role into request extensions for downstream handlers.
+/// and injects both user_id and role into request extensions for downstream handlers,
+/// along with `IsImpersonated` for sessions an admin opened as the user. The user's
+/// `last_active_at` is refreshed in the background (at most every 5 minutes).
+/// When JWT mode is enabled, access tokens are verified by signature and checked
+/// against the in-memory session denylist instead of querying the database.
 /// This eliminates the need for additional DB queries in authorization middleware.
 ///
 /// # Errors
@@ -25,14 +35,51 @@ use crate::gateway::AppState;
 /// - Authorization header is missing
 /// - Bearer token is invalid
 /// - Session is not found or expired
+/// - Access token is invalid, expired, or belongs to a revoked session
 pub async fn auth_middleware(
     State(app_state): State<AppState>,
     mut request: Request,
     next: Next,
 ) -> Result<Response, StatusCode> {
+    let Authenticated {
+        user_id,
+        role,
+        session: current_session,
+        impersonated,
+    } = authenticate(&app_state, request.headers()).await?;
+
+    // Inject user_id, role and the presented session into request extensions
+    request.extensions_mut().insert(user_id);
+    request.extensions_mut().insert(role);
+    request.extensions_mut().insert(current_session);
+    request.extensions_mut().insert(IsImpersonated(impersonated));
+
+    // An admin impersonating the user isn't the user being active
+    if !impersonated {
+        app_state.activity.touch(&app_state.db, user_id);
+    }
+
+    Ok(next.run(request).await)
+}
+
+/// The owner of a valid bearer token
+pub struct Authenticated {
+    pub user_id: Uuid,
+    pub role: Role,
+    pub session: CurrentSession,
+    pub impersonated: bool,
+}
+
+/// Validate the bearer token in the request headers
+///
+/// Used by `auth_middleware`, and by middleware that runs before it and only
+/// needs to know who is asking.
+pub async fn authenticate(
+    app_state: &AppState,
+    headers: &HeaderMap,
+) -> Result<Authenticated, StatusCode> {
     // Extract Authorization header
-    let auth_header = request
-        .headers()
+    let auth_header = headers
         .get(header::AUTHORIZATION)
         .and_then(|v| v.to_str().ok())
         .ok_or(StatusCode::UNAUTHORIZED)?;
@@ -42,20 +89,61 @@ pub async fn auth_middleware(
         .strip_prefix("Bearer ")
         .ok_or(StatusCode::UNAUTHORIZED)?;
 
-    // Validate session and get user_id AND role (single DB query)
-    let (user_id, role) = session::get_user_from_session(&app_state.db, session_token)
-        .await
-        .map_err(|e| match e {
-            AuthError::SessionNotFound => StatusCode::UNAUTHORIZED,
-            AuthError::TokenExpired => StatusCode::UNAUTHORIZED,
-            _ => StatusCode::INTERNAL_SERVER_ERROR,
-        })?;
+    verify_bearer(
+        &app_state.db,
+        app_state.cache.as_ref(),
+        &app_state.config.jwt,
+        &app_state.session_denylist,
+        session_token,
+    )
+    .await
+}
 
-    // Inject both user_id and role into request extensions
-    request.extensions_mut().insert(user_id);
-    request.extensions_mut().insert(role);
+/// Validate a bearer token, either a JWT access token or a session token
+async fn verify_bearer(
+    db: &PgPool,
+    cache: Option<&RedisPool>,
+    jwt_config: &JwtConfig,
+    session_denylist: &SessionDenylist,
+    session_token: &str,
+) -> Result<Authenticated, StatusCode> {
+    match jwt_config.secret.as_deref() {
+        // JWT access tokens are verified locally (no DB query)
+        Some(secret) if jwt_config.enabled && jwt::is_jwt(session_token) => {
+            let claims = jwt::verify_access_token(secret, session_token)
+                .map_err(|_| StatusCode::UNAUTHORIZED)?;
 
-    Ok(next.run(request).await)
+            if session_denylist.is_revoked(&claims.sid) {
+                return Err(StatusCode::UNAUTHORIZED);
+            }
+
+            // Impersonation sessions can't be refreshed, so never get access tokens
+            Ok(Authenticated {
+                user_id: claims.sub,
+                role: claims.role,
+                session: CurrentSession::Id(claims.sid),
+                impersonated: false,
+            })
+        }
+        // Validate session and get user_id AND role (single DB query)
+        _ => {
+            let (user_id, role, impersonated_by) =
+                session::get_user_from_session(db, cache, session_token)
+                    .await
+                    .map_err(|e| match e {
+                        AuthError::SessionNotFound => StatusCode::UNAUTHORIZED,
+                        AuthError::TokenExpired => StatusCode::UNAUTHORIZED,
+                        _ => StatusCode::INTERNAL_SERVER_ERROR,
+                    })?;
+
+            Ok(Authenticated {
+                user_id,
+                role,
+                session: CurrentSession::Token(session_token.to_string()),
+                impersonated: impersonated_by.is_some(),
+            })
+        }
+    }
 }
 
 // ===== Authorization Middleware =====
@@ -90,3 +178,51 @@ pub async fn require_admin(
 
     Ok(next.run(request).await)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[sqlx::test]
+    async fn test_tokens_issued_before_revoking_all_sessions_are_rejected(db: PgPool) {
+        let jwt_config = JwtConfig {
+            enabled: true,
+            secret: Some("a".repeat(32)),
+            access_token_ttl_seconds: 900,
+        };
+        let denylist = SessionDenylist::default();
+
+        let user_id = sqlx::query_scalar!(
+            "INSERT INTO users (email) VALUES ('revoked@example.com') RETURNING id"
+        )
+        .fetch_one(&db)
+        .await
+        .unwrap();
+        let (session_id, session_token, _) =
+            session::create_session(&db, None, user_id, Role::User, None, None, 0)
+                .await
+                .unwrap();
+        let (access_token, _) =
+            jwt::issue_access_token(&jwt_config, user_id, Role::User, session_id).unwrap();
+
+        let verify = |token: String| {
+            let (db, jwt_config, denylist) = (&db, &jwt_config, &denylist);
+            async move {
+                verify_bearer(db, None, jwt_config, denylist, &token)
+                    .await
+                    .map(|authenticated| authenticated.user_id)
+            }
+        };
+        assert_eq!(verify(session_token.clone()).await, Ok(user_id));
+        assert_eq!(verify(access_token.clone()).await, Ok(user_id));
+
+        // What the admin revoke-sessions endpoint does
+        session::invalidate_all_user_sessions(&db, None, user_id)
+            .await
+            .unwrap();
+        denylist.refresh(&db).await.unwrap();
+
+        assert_eq!(verify(session_token).await, Err(StatusCode::UNAUTHORIZED));
+        assert_eq!(verify(access_token).await, Err(StatusCode::UNAUTHORIZED));
+    }
+}
@@ -0,0 +1,204 @@
+//! Client IP resolution behind reverse proxies
+//!
+//! The socket peer is only the client when nothing sits in front of the API.
+//! When the peer is one of `TRUSTED_PROXIES`, the client is the rightmost
+//! `X-Forwarded-For` address that is not itself a trusted proxy (falling back
+//! to `X-Real-IP`). Forwarding headers from any other peer are ignored, since
+//! clients can set them to anything.
+//!
+//! `client_ip_middleware` resolves the address once per request and stores it
+//! as a `ClientIp` extension, which rate limiting, sessions and audit logs all
+//! read so they agree on who made the request.
+
+use std::net::{IpAddr, SocketAddr};
+
+use axum::{
+    extract::{ConnectInfo, FromRequestParts, Request, State},
+    http::{HeaderMap, StatusCode, request::Parts},
+    middleware::Next,
+    response::Response,
+};
+use ipnetwork::IpNetwork;
+use tower_governor::{GovernorError, key_extractor::KeyExtractor};
+
+use crate::gateway::AppState;
+
+const X_FORWARDED_FOR: &str = "x-forwarded-for";
+const X_REAL_IP: &str = "x-real-ip";
+
+/// The resolved address of the client that made the request
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClientIp(pub IpAddr);
+
+impl ClientIp {
+    /// Resolved IP, or the socket peer when the middleware did not run
+    pub fn from_extensions(extensions: &axum::http::Extensions) -> Option<Self> {
+        extensions.get::<ClientIp>().copied().or_else(|| {
+            extensions
+                .get::<ConnectInfo<SocketAddr>>()
+                .map(|ConnectInfo(addr)| ClientIp(addr.ip()))
+        })
+    }
+}
+
+impl<S> FromRequestParts<S> for ClientIp
+where
+    S: Send + Sync,
+{
+    type Rejection = StatusCode;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        ClientIp::from_extensions(&parts.extensions).ok_or_else(|| {
+            tracing::error!("Client IP unavailable (missing connect info)");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })
+    }
+}
+
+/// Work out the client address for a request received from `peer`
+pub fn resolve_client_ip(peer: IpAddr, headers: &HeaderMap, trusted: &[IpNetwork]) -> IpAddr {
+    let is_trusted = |ip: &IpAddr| trusted.iter().any(|net| net.contains(*ip));
+
+    if !is_trusted(&peer) {
+        return peer;
+    }
+
+    // Every proxy appends the address it received from, so walking right to
+    // left skips our own proxies and stops at the first hop we can't vouch for
+    let forwarded: Vec<IpAddr> = headers
+        .get_all(X_FORWARDED_FOR)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .filter_map(|entry| entry.trim().parse().ok())
+        .collect();
+
+    if let Some(ip) = forwarded.iter().rev().find(|ip| !is_trusted(ip)) {
+        return *ip;
+    }
+
+    headers
+        .get(X_REAL_IP)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().parse().ok())
+        .or_else(|| forwarded.first().copied())
+        .unwrap_or(peer)
+}
+
+/// Resolve the client IP and store it as a `ClientIp` extension
+pub async fn client_ip_middleware(
+    State(state): State<AppState>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let peer = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip());
+
+    if let Some(peer) = peer {
+        let ip = resolve_client_ip(
+            peer,
+            request.headers(),
+            &state.config.server.trusted_proxies,
+        );
+        request.extensions_mut().insert(ClientIp(ip));
+    }
+
+    next.run(request).await
+}
+
+/// Keys governor limiters by the resolved client IP
+#[derive(Debug, Clone, Copy)]
+pub struct ClientIpKeyExtractor;
+
+impl KeyExtractor for ClientIpKeyExtractor {
+    type Key = IpAddr;
+
+    fn extract<T>(&self, req: &axum::http::Request<T>) -> Result<Self::Key, GovernorError> {
+        ClientIp::from_extensions(req.extensions())
+            .map(|ClientIp(ip)| ip)
+            .ok_or(GovernorError::UnableToExtractKey)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ip(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    fn headers(pairs: &[(&'static str, &str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            headers.append(*name, value.parse().unwrap());
+        }
+        headers
+    }
+
+    fn trusted() -> Vec<IpNetwork> {
+        vec!["10.0.0.0/8".parse().unwrap(), "::1/128".parse().unwrap()]
+    }
+
+    #[test]
+    fn test_untrusted_peer_headers_are_ignored() {
+        let headers = headers(&[
+            (X_FORWARDED_FOR, "198.51.100.1"),
+            (X_REAL_IP, "198.51.100.2"),
+        ]);
+        assert_eq!(
+            resolve_client_ip(ip("203.0.113.9"), &headers, &trusted()),
+            ip("203.0.113.9")
+        );
+        assert_eq!(
+            resolve_client_ip(ip("10.0.0.2"), &headers, &[]),
+            ip("10.0.0.2")
+        );
+    }
+
+    #[test]
+    fn test_rightmost_untrusted_forwarded_address() {
+        // A client-supplied entry on the left cannot override the real hop
+        let headers = headers(&[
+            (X_FORWARDED_FOR, "1.2.3.4, 198.51.100.7"),
+            (X_FORWARDED_FOR, "10.0.0.5"),
+        ]);
+        assert_eq!(
+            resolve_client_ip(ip("10.0.0.2"), &headers, &trusted()),
+            ip("198.51.100.7")
+        );
+    }
+
+    #[test]
+    fn test_real_ip_and_fallbacks() {
+        let real_ip = headers(&[(X_REAL_IP, "198.51.100.3")]);
+        assert_eq!(
+            resolve_client_ip(ip("::1"), &real_ip, &trusted()),
+            ip("198.51.100.3")
+        );
+
+        let only_proxies = headers(&[(X_FORWARDED_FOR, "10.0.0.9, garbage")]);
+        assert_eq!(
+            resolve_client_ip(ip("10.0.0.2"), &only_proxies, &trusted()),
+            ip("10.0.0.9")
+        );
+
+        assert_eq!(
+            resolve_client_ip(ip("10.0.0.2"), &HeaderMap::new(), &trusted()),
+            ip("10.0.0.2")
+        );
+    }
+
+    #[test]
+    fn test_trusted_proxies_from_env_value() {
+        use crate::config::env::parse_trusted_proxies;
+
+        let proxies = parse_trusted_proxies("10.0.0.0/8, 172.16.0.1 ,").unwrap();
+        assert_eq!(proxies.len(), 2);
+        assert!(proxies[1].contains(ip("172.16.0.1")));
+        assert!(parse_trusted_proxies("").unwrap().is_empty());
+        assert!(parse_trusted_proxies("10.0.0.0/33").is_err());
+    }
+}
@@ -0,0 +1,93 @@
+//! Maintenance mode gate
+//!
+//! Runs before routing and auth. While maintenance mode is on, only admin
+//! and health routes, sign-in, token refresh and requests carrying an
+//! admin's token get through; everything else receives a 503.
+
+use axum::{
+    Json,
+    extract::{Request, State},
+    http::{HeaderValue, StatusCode, header},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use serde_json::json;
+
+use super::auth::authenticate;
+use crate::auth::Role;
+use crate::gateway::AppState;
+
+/// Seconds clients are asked to wait before retrying
+const RETRY_AFTER_SECS: &str = "300";
+
+pub async fn maintenance_middleware(
+    State(app_state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if !app_state.maintenance.is_enabled() || is_exempt(request.uri().path()) {
+        return next.run(request).await;
+    }
+
+    if let Ok(user) = authenticate(&app_state, request.headers()).await
+        && user.role == Role::Admin
+    {
+        return next.run(request).await;
+    }
+
+    maintenance_response(app_state.maintenance.message())
+}
+
+/// Routes that stay up during maintenance
+///
+/// Sign-in and token refresh are kept open so admins whose session or access
+/// token expired can get back in.
+fn is_exempt(path: &str) -> bool {
+    ["/admin", "/health"]
+        .iter()
+        .any(|prefix| path == *prefix || path.starts_with(&format!("{}/", prefix)))
+        || matches!(path, "/auth/signin" | "/auth/refresh")
+}
+
+fn maintenance_response(message: String) -> Response {
+    let mut response = (
+        StatusCode::SERVICE_UNAVAILABLE,
+        Json(json!({
+            "error": "maintenance",
+            "message": message,
+        })),
+    )
+        .into_response();
+    response.headers_mut().insert(
+        header::RETRY_AFTER,
+        HeaderValue::from_static(RETRY_AFTER_SECS),
+    );
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exempt_paths() {
+        for path in [
+            "/admin",
+            "/admin/maintenance",
+            "/health/api",
+            "/auth/signin",
+            "/auth/refresh",
+        ] {
+            assert!(is_exempt(path), "{}", path);
+        }
+        for path in [
+            "/",
+            "/chat/conversations",
+            "/administrator",
+            "/healthz",
+            "/auth/signup",
+        ] {
+            assert!(!is_exempt(path), "{}", path);
+        }
+    }
+}
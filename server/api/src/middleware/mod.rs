@@ -12,11 +12,22 @@ use uuid::Uuid;
 use crate::auth::Role;
 
 pub mod auth;
+pub mod client_ip;
+pub mod maintenance;
 pub mod rate_limit;
+pub mod redis_rate_limit;
+pub mod request_id;
+pub mod security_headers;
 
 // Re-export commonly used middleware
 pub use auth::{auth_middleware, require_admin};
-pub use rate_limit::{auth_rate_limiter, sensitive_auth_rate_limiter};
+pub use client_ip::{ClientIp, client_ip_middleware};
+pub use maintenance::maintenance_middleware;
+pub use request_id::{RequestId, request_id_middleware};
+pub use security_headers::SecurityHeadersLayer;
+pub use rate_limit::{
+    RateLimiters, auth_rate_limiter, sensitive_auth_rate_limiter, user_rate_limiter,
+};
 
 /// Authenticated user extractor
 ///
@@ -3,52 +3,145 @@
 //! Provides rate limiting middleware using tower_governor for Axum applications.
 //!
 //! **IMPORTANT**: Server MUST use `.into_make_service_with_connect_info::<SocketAddr>()`
-//! for the PeerIpKeyExtractor to extract client IPs correctly.
+//! so client IPs can be resolved. Per-IP limiters key on the `ClientIp` set by
+//! `client_ip_middleware`, which honours `X-Forwarded-For` from trusted proxies.
+//!
+//! Authenticated route groups are additionally limited per user with
+//! `user_rate_limiter`, which keys on the user ID set by `auth_middleware`
+//! and falls back to the client IP for requests without one.
+//!
+//! With `RATE_LIMIT_BACKEND=redis` the limiters keep their state in Redis so
+//! limits hold across replicas (see `redis_rate_limit`). The in-memory
+//! governor limiters remain the default and the fallback.
+//!
+//! Either backend is wrapped in `RateLimitResetLayer`, which adds
+//! `X-RateLimit-Reset` next to the limit and remaining headers.
 
-use axum::body::Body;
-use governor::middleware::NoOpMiddleware;
+use axum::{
+    Json,
+    body::Body,
+    http::{HeaderMap, HeaderName, HeaderValue, Request, StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use futures::future::BoxFuture;
+use governor::middleware::StateInformationMiddleware;
+use serde_json::json;
 use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tower::layer::util::Stack;
+use tower::util::Either;
+use tower::{Layer, Service};
 use tower_governor::{
-    GovernorLayer,
+    GovernorError, GovernorLayer,
     governor::{GovernorConfig, GovernorConfigBuilder},
-    key_extractor::PeerIpKeyExtractor,
+    key_extractor::KeyExtractor,
 };
+use uuid::Uuid;
 
-/// Rate limit configuration presets
-#[derive(Debug, Clone, Copy)]
-pub struct RateLimitConfig {
-    pub per_second: u64,
-    pub burst_size: u32,
+use super::client_ip::{ClientIp, ClientIpKeyExtractor};
+use super::redis_rate_limit::{RedisRateLimitLayer, RedisRateLimiter};
+use crate::config::cache::RedisPool;
+use crate::config::env::{IpRateLimit, RateLimitConfig, UserRateLimit};
+
+/// Where limiter state is kept, selected by `RATE_LIMIT_BACKEND`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RateLimitBackend {
+    #[default]
+    Memory,
+    Redis,
 }
 
-impl RateLimitConfig {
-    /// Strict: ~3 requests per minute with burst of 3
-    /// Use for: sensitive operations like password reset, account recovery
-    pub const STRICT: Self = Self {
-        per_second: 20,
-        burst_size: 3,
-    };
+impl RateLimitBackend {
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "memory" | "local" => Some(RateLimitBackend::Memory),
+            "redis" => Some(RateLimitBackend::Redis),
+            _ => None,
+        }
+    }
+}
 
-    /// Standard: ~10 requests per minute with burst of 10
-    /// Use for: authentication endpoints (signin, signup)
-    pub const STANDARD: Self = Self {
-        per_second: 6,
-        burst_size: 10,
-    };
+/// What a limiter counts requests by
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RateLimitKey {
+    ClientIp,
+    /// The authenticated user, or the client IP when there is none
+    UserId,
+}
+
+impl RateLimitKey {
+    pub fn extract<T>(&self, req: &Request<T>) -> Option<String> {
+        match self {
+            RateLimitKey::ClientIp => {
+                ClientIp::from_extensions(req.extensions()).map(|ClientIp(ip)| ip.to_string())
+            }
+            RateLimitKey::UserId => match req.extensions().get::<Uuid>() {
+                Some(id) => Some(id.to_string()),
+                None => RateLimitKey::ClientIp.extract(req),
+            },
+        }
+    }
+}
+
+/// Limiter backend shared by all rate-limited routes, chosen once at startup
+#[derive(Clone, Default)]
+pub struct RateLimiters {
+    redis: Option<RedisRateLimiter>,
 }
 
-/// Type alias for the default GovernorConfig using PeerIpKeyExtractor
-pub type DefaultGovernorConfig = GovernorConfig<PeerIpKeyExtractor, NoOpMiddleware>;
+impl RateLimiters {
+    /// Per-process limiters only
+    pub fn in_memory() -> Self {
+        Self::default()
+    }
+
+    /// Use Redis when the backend is `redis` and a connection is available
+    ///
+    /// Falls back to in-memory limiters with a warning if `REDIS_URL` is
+    /// missing or Redis could not be reached at startup.
+    pub fn from_config(config: &RateLimitConfig, redis: Option<&RedisPool>) -> Self {
+        if config.backend != RateLimitBackend::Redis {
+            return Self::in_memory();
+        }
+
+        match redis {
+            Some(redis) => Self {
+                redis: Some(RedisRateLimiter::new(redis.clone())),
+            },
+            None => {
+                tracing::warn!(
+                    "RATE_LIMIT_BACKEND=redis but Redis is not available, using in-memory rate limits"
+                );
+                Self::in_memory()
+            }
+        }
+    }
+
+    pub fn is_redis(&self) -> bool {
+        self.redis.is_some()
+    }
+}
+
+/// Type alias for the default GovernorConfig using ClientIpKeyExtractor
+pub type DefaultGovernorConfig = GovernorConfig<ClientIpKeyExtractor, StateInformationMiddleware>;
 
 /// Type alias for the default GovernorLayer
-pub type DefaultGovernorLayer = GovernorLayer<PeerIpKeyExtractor, NoOpMiddleware, Body>;
+pub type DefaultGovernorLayer =
+    GovernorLayer<ClientIpKeyExtractor, StateInformationMiddleware, Body>;
+
+/// Per-IP limiter on whichever backend `RateLimiters` selected
+pub type IpRateLimitLayer =
+    Stack<Either<DefaultGovernorLayer, RedisRateLimitLayer>, RateLimitResetLayer>;
 
 /// Create a GovernorConfig from rate limit settings
-fn create_governor_config(config: RateLimitConfig) -> Arc<DefaultGovernorConfig> {
+fn create_governor_config(limit: IpRateLimit) -> Arc<DefaultGovernorConfig> {
     Arc::new(
         GovernorConfigBuilder::default()
-            .per_second(config.per_second)
-            .burst_size(config.burst_size)
+            .key_extractor(ClientIpKeyExtractor)
+            .period(limit.replenish_interval())
+            .burst_size(limit.max_requests)
+            .use_headers()
             .finish()
             .expect("Failed to build governor config"),
     )
@@ -56,34 +149,562 @@ fn create_governor_config(config: RateLimitConfig) -> Arc<DefaultGovernorConfig>
 
 /// Create a rate limiting layer using the governor config
 fn rate_limiter_layer(config: Arc<DefaultGovernorConfig>) -> DefaultGovernorLayer {
-    GovernorLayer::new(config)
+    GovernorLayer::new(config).error_handler(rate_limit_error_response)
 }
 
-/// Standard rate limiter: ~10 req/min with burst of 10
-/// Suitable for authentication endpoints (signin, signup, OAuth)
-pub fn standard_rate_limiter() -> DefaultGovernorLayer {
-    let config = create_governor_config(RateLimitConfig::STANDARD);
-    rate_limiter_layer(config)
-}
+/// Per-IP limiter for a tier, in Redis when available
+///
+/// Redis buckets are named by `name`, so every router using the same
+/// limiter function shares one bucket per IP.
+fn ip_rate_limiter(
+    limiters: &RateLimiters,
+    name: &'static str,
+    limit: IpRateLimit,
+) -> IpRateLimitLayer {
+    let layer = match &limiters.redis {
+        Some(redis) => Either::Right(RedisRateLimitLayer::new(
+            redis.clone(),
+            name,
+            RateLimitKey::ClientIp,
+            limit.replenish_interval(),
+            limit.max_requests,
+        )),
+        None => Either::Left(rate_limiter_layer(create_governor_config(limit))),
+    };
 
-/// Strict rate limiter: ~3 req/min with burst of 3
-/// Suitable for sensitive operations (password reset, account recovery)
-pub fn strict_rate_limiter() -> DefaultGovernorLayer {
-    let config = create_governor_config(RateLimitConfig::STRICT);
-    rate_limiter_layer(config)
+    Stack::new(layer, RateLimitResetLayer::new(limit.replenish_interval()))
 }
 
 // Convenience functions for auth-specific rate limiting
 
 /// Create rate limiter for standard authentication endpoints (signin, signup)
-/// 10 requests per minute with burst of 10
-pub fn auth_rate_limiter() -> DefaultGovernorLayer {
-    standard_rate_limiter()
+/// `RATE_LIMIT_AUTH_*`, 10 requests per minute by default
+pub fn auth_rate_limiter(limiters: &RateLimiters, config: &RateLimitConfig) -> IpRateLimitLayer {
+    ip_rate_limiter(limiters, "auth", config.auth)
 }
 
 /// Create rate limiter for sensitive authentication operations
 /// (password reset, forgot password, account recovery)
-/// 3 requests per minute with burst of 3
-pub fn sensitive_auth_rate_limiter() -> DefaultGovernorLayer {
-    strict_rate_limiter()
+/// `RATE_LIMIT_SENSITIVE_*`, 3 requests per minute by default
+pub fn sensitive_auth_rate_limiter(
+    limiters: &RateLimiters,
+    config: &RateLimitConfig,
+) -> IpRateLimitLayer {
+    ip_rate_limiter(limiters, "auth_sensitive", config.sensitive)
+}
+
+// Per-user rate limiting
+
+/// Who a per-user bucket belongs to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum UserRateLimitKey {
+    User(Uuid),
+    /// Requests without an authenticated user, e.g. on optional-auth routes
+    Ip(std::net::IpAddr),
+}
+
+/// Keys requests by the authenticated user's ID
+///
+/// The ID is inserted by `auth_middleware`, so users sharing an IP (behind
+/// NAT or an office proxy) get separate buckets. Requests without one fall
+/// back to the client IP.
+#[derive(Debug, Clone, Copy)]
+pub struct UserIdKeyExtractor;
+
+impl KeyExtractor for UserIdKeyExtractor {
+    type Key = UserRateLimitKey;
+
+    fn extract<T>(&self, req: &Request<T>) -> Result<Self::Key, GovernorError> {
+        let extensions = req.extensions();
+        extensions
+            .get::<Uuid>()
+            .map(|id| UserRateLimitKey::User(*id))
+            .or_else(|| {
+                ClientIp::from_extensions(extensions).map(|ClientIp(ip)| UserRateLimitKey::Ip(ip))
+            })
+            .ok_or(GovernorError::UnableToExtractKey)
+    }
+}
+
+/// Type alias for the per-user GovernorLayer
+pub type UserGovernorLayer = GovernorLayer<UserIdKeyExtractor, StateInformationMiddleware, Body>;
+
+/// Per-user limiter on whichever backend `RateLimiters` selected
+pub type UserRateLimitLayer =
+    Stack<Either<UserGovernorLayer, RedisRateLimitLayer>, RateLimitResetLayer>;
+
+/// Per-user rate limiter for an authenticated route group
+///
+/// Must be added *before* `auth_middleware` with `.layer()` so that it runs
+/// after authentication. `group` names the Redis buckets.
+pub fn user_rate_limiter(
+    limiters: &RateLimiters,
+    group: &'static str,
+    limit: UserRateLimit,
+) -> UserRateLimitLayer {
+    let interval = Duration::from_millis((60_000 / limit.per_minute.max(1) as u64).max(1));
+
+    if let Some(redis) = &limiters.redis {
+        return Stack::new(
+            Either::Right(RedisRateLimitLayer::new(
+                redis.clone(),
+                group,
+                RateLimitKey::UserId,
+                interval,
+                limit.burst,
+            )),
+            RateLimitResetLayer::new(interval),
+        );
+    }
+
+    let config = GovernorConfigBuilder::default()
+        .key_extractor(UserIdKeyExtractor)
+        .per_millisecond(interval.as_millis() as u64)
+        .burst_size(limit.burst)
+        .use_headers()
+        .finish()
+        .expect("Failed to build governor config");
+
+    Stack::new(
+        Either::Left(GovernorLayer::new(Arc::new(config)).error_handler(rate_limit_error_response)),
+        RateLimitResetLayer::new(interval),
+    )
+}
+
+// Reset header
+
+const X_RATELIMIT_LIMIT: &str = "x-ratelimit-limit";
+const X_RATELIMIT_REMAINING: &str = "x-ratelimit-remaining";
+const X_RATELIMIT_RESET: &str = "x-ratelimit-reset";
+
+/// Adds `X-RateLimit-Reset` to responses from a limiter
+///
+/// The limiters only report the bucket size and what is left, so the reset
+/// time is worked out from those and the bucket's replenish interval.
+#[derive(Debug, Clone, Copy)]
+pub struct RateLimitResetLayer {
+    interval: Duration,
+}
+
+impl RateLimitResetLayer {
+    pub fn new(interval: Duration) -> Self {
+        Self { interval }
+    }
+}
+
+impl<S> Layer<S> for RateLimitResetLayer {
+    type Service = RateLimitReset<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RateLimitReset {
+            inner,
+            interval: self.interval,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RateLimitReset<S> {
+    inner: S,
+    interval: Duration,
+}
+
+impl<S> Service<Request<Body>> for RateLimitReset<S>
+where
+    S: Service<Request<Body>, Response = Response> + Clone + Send + 'static,
+    S::Error: Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = BoxFuture<'static, Result<Response, S::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<Body>) -> Self::Future {
+        // Use the service that was polled ready and leave a fresh clone behind
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let interval = self.interval;
+
+        Box::pin(async move {
+            let mut response = inner.call(req).await?;
+            insert_reset_header(response.headers_mut(), interval);
+            Ok(response)
+        })
+    }
+}
+
+fn header_u64(headers: &HeaderMap, name: impl axum::http::header::AsHeaderName) -> Option<u64> {
+    headers.get(name)?.to_str().ok()?.parse().ok()
+}
+
+/// Add `X-RateLimit-Reset` if the limiter reported its quota
+fn insert_reset_header(headers: &mut HeaderMap, interval: Duration) {
+    let (Some(limit), Some(remaining)) = (
+        header_u64(headers, X_RATELIMIT_LIMIT),
+        header_u64(headers, X_RATELIMIT_REMAINING),
+    ) else {
+        return;
+    };
+    let retry_after = header_u64(headers, header::RETRY_AFTER).map(Duration::from_secs);
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    let reset = now + time_until_full(interval, limit, remaining, retry_after);
+
+    headers.insert(
+        HeaderName::from_static(X_RATELIMIT_RESET),
+        HeaderValue::from(reset.as_secs_f64().ceil() as u64),
+    );
+}
+
+/// How long until the bucket holds `limit` requests again
+///
+/// Each missing request takes one interval to come back. A rejected request
+/// knows exactly when the next one returns (`Retry-After`), and the rest
+/// follow one interval apart.
+fn time_until_full(
+    interval: Duration,
+    limit: u64,
+    remaining: u64,
+    retry_after: Option<Duration>,
+) -> Duration {
+    let missing = limit.saturating_sub(remaining) as u32;
+    match retry_after {
+        Some(wait) => wait + interval * missing.saturating_sub(1),
+        None => interval * missing,
+    }
+}
+
+/// 429 with a JSON body and `Retry-After`
+pub(super) fn too_many_requests(wait_time: u64) -> Response {
+    let mut response = (
+        StatusCode::TOO_MANY_REQUESTS,
+        Json(json!({
+            "error": "rate_limited",
+            "message": format!("Too many requests, retry in {}s", wait_time),
+        })),
+    )
+        .into_response();
+    response
+        .headers_mut()
+        .insert(header::RETRY_AFTER, HeaderValue::from(wait_time));
+    response
+}
+
+/// `X-RateLimit-Limit` and `X-RateLimit-Remaining`, matching governor's headers
+pub(super) fn insert_quota_headers(headers: &mut HeaderMap, limit: u32, remaining: u32) {
+    headers.insert(
+        HeaderName::from_static(X_RATELIMIT_LIMIT),
+        HeaderValue::from(limit),
+    );
+    headers.insert(
+        HeaderName::from_static(X_RATELIMIT_REMAINING),
+        HeaderValue::from(remaining),
+    );
+}
+
+/// Response when a limiter cannot find its key, which is a wiring bug
+pub(super) fn missing_key_response() -> Response {
+    tracing::error!("Rate limiter could not extract its key (missing auth or connect info)");
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(json!({
+            "error": "internal_error",
+            "message": "An internal error occurred",
+        })),
+    )
+        .into_response()
+}
+
+/// JSON error body for rate limit rejections, keeping governor's headers
+fn rate_limit_error_response(error: GovernorError) -> Response<Body> {
+    match error {
+        GovernorError::TooManyRequests { wait_time, headers } => {
+            let mut response = too_many_requests(wait_time);
+            if let Some(headers) = headers {
+                response.headers_mut().extend(headers);
+            }
+            response
+        }
+        GovernorError::UnableToExtractKey => missing_key_response(),
+        GovernorError::Other { code, msg, .. } => {
+            let message = msg.unwrap_or_else(|| "Request rejected".to_string());
+            (
+                code,
+                Json(json!({
+                    "error": message,
+                    "message": message,
+                })),
+            )
+                .into_response()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{Router, routing::get};
+    use governor::clock::Clock;
+    use tower::ServiceExt;
+
+    fn app(limit: UserRateLimit) -> Router {
+        Router::new()
+            .route("/", get(|| async { "ok" }))
+            .layer(user_rate_limiter(&RateLimiters::in_memory(), "test", limit))
+    }
+
+    fn request(user_id: Option<Uuid>) -> Request<Body> {
+        let mut req = Request::builder().uri("/").body(Body::empty()).unwrap();
+        if let Some(user_id) = user_id {
+            req.extensions_mut().insert(user_id);
+        }
+        req
+    }
+
+    #[tokio::test]
+    async fn test_limits_each_user_separately() {
+        let app = app(UserRateLimit {
+            per_minute: 1,
+            burst: 2,
+        });
+        let alice = Uuid::new_v4();
+        let bob = Uuid::new_v4();
+
+        for remaining in ["1", "0"] {
+            let res = app.clone().oneshot(request(Some(alice))).await.unwrap();
+            assert_eq!(res.status(), StatusCode::OK);
+            assert_eq!(res.headers()["x-ratelimit-limit"], "2");
+            assert_eq!(res.headers()["x-ratelimit-remaining"], remaining);
+        }
+
+        let res = app.clone().oneshot(request(Some(alice))).await.unwrap();
+        assert_eq!(res.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert!(res.headers().contains_key(header::RETRY_AFTER));
+        assert_eq!(res.headers()["x-ratelimit-limit"], "2");
+        assert_eq!(res.headers()["x-ratelimit-remaining"], "0");
+        assert_eq!(res.headers()[header::CONTENT_TYPE], "application/json");
+
+        let body = axum::body::to_bytes(res.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["error"], "rate_limited");
+
+        let res = app.clone().oneshot(request(Some(bob))).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+
+    fn unix_now() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+    }
+
+    #[tokio::test]
+    async fn test_ip_limit_headers_on_allowed_and_blocked_requests() {
+        let config = RateLimitConfig {
+            auth: IpRateLimit {
+                max_requests: 2,
+                window_seconds: 60,
+            },
+            ..test_config(RateLimitBackend::Memory)
+        };
+        let app = Router::new()
+            .route("/", get(|| async { "ok" }))
+            .layer(auth_rate_limiter(&RateLimiters::in_memory(), &config));
+        let request = || {
+            let mut req = request(None);
+            req.extensions_mut()
+                .insert(ClientIp("192.0.2.7".parse().unwrap()));
+            req
+        };
+
+        // One token comes back every 30s
+        let res = app.clone().oneshot(request()).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.headers()["x-ratelimit-limit"], "2");
+        assert_eq!(res.headers()["x-ratelimit-remaining"], "1");
+        let reset: u64 = res.headers()["x-ratelimit-reset"]
+            .to_str()
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert!((unix_now() + 29..=unix_now() + 31).contains(&reset));
+
+        app.clone().oneshot(request()).await.unwrap();
+
+        let res = app.clone().oneshot(request()).await.unwrap();
+        assert_eq!(res.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(res.headers()["x-ratelimit-limit"], "2");
+        assert_eq!(res.headers()["x-ratelimit-remaining"], "0");
+        let reset: u64 = res.headers()["x-ratelimit-reset"]
+            .to_str()
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert!((unix_now() + 59..=unix_now() + 61).contains(&reset));
+    }
+
+    #[test]
+    fn test_time_until_full() {
+        let interval = Duration::from_secs(6);
+
+        assert_eq!(time_until_full(interval, 10, 10, None), Duration::ZERO);
+        assert_eq!(
+            time_until_full(interval, 10, 7, None),
+            Duration::from_secs(18)
+        );
+        assert_eq!(
+            time_until_full(interval, 10, 0, Some(Duration::from_secs(2))),
+            Duration::from_secs(56)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_missing_user_falls_back_to_client_ip() {
+        let app = app(UserRateLimit {
+            per_minute: 1,
+            burst: 1,
+        });
+        let anonymous = |ip: &str| {
+            let mut req = request(None);
+            req.extensions_mut().insert(ClientIp(ip.parse().unwrap()));
+            req
+        };
+
+        let res = app.clone().oneshot(anonymous("192.0.2.7")).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        let res = app.clone().oneshot(anonymous("192.0.2.7")).await.unwrap();
+        assert_eq!(res.status(), StatusCode::TOO_MANY_REQUESTS);
+
+        // Other addresses and signed-in users have their own buckets
+        let res = app.clone().oneshot(anonymous("192.0.2.8")).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        let mut signed_in = anonymous("192.0.2.7");
+        signed_in.extensions_mut().insert(Uuid::new_v4());
+        let res = app.clone().oneshot(signed_in).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+
+        // Without a user or an address there is nothing to key on
+        let res = app.oneshot(request(None)).await.unwrap();
+        assert_eq!(res.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    #[test]
+    fn test_backend_from_str() {
+        assert_eq!(
+            RateLimitBackend::from_str("Redis"),
+            Some(RateLimitBackend::Redis)
+        );
+        assert_eq!(
+            RateLimitBackend::from_str("memory"),
+            Some(RateLimitBackend::Memory)
+        );
+        assert_eq!(RateLimitBackend::from_str("memcached"), None);
+    }
+
+    #[test]
+    fn test_key_extraction() {
+        let user_id = Uuid::new_v4();
+        let mut req = request(Some(user_id));
+        req.extensions_mut().insert(axum::extract::ConnectInfo(
+            "10.0.0.2:5000".parse::<std::net::SocketAddr>().unwrap(),
+        ));
+        req.extensions_mut()
+            .insert(ClientIp("192.0.2.7".parse().unwrap()));
+
+        assert_eq!(
+            RateLimitKey::UserId.extract(&req),
+            Some(user_id.to_string())
+        );
+        assert_eq!(
+            RateLimitKey::ClientIp.extract(&req).as_deref(),
+            Some("192.0.2.7")
+        );
+        assert_eq!(RateLimitKey::ClientIp.extract(&request(None)), None);
+
+        let mut anonymous = request(None);
+        anonymous
+            .extensions_mut()
+            .insert(ClientIp("192.0.2.7".parse().unwrap()));
+        assert_eq!(
+            RateLimitKey::UserId.extract(&anonymous).as_deref(),
+            Some("192.0.2.7")
+        );
+    }
+
+    #[test]
+    fn test_ip_quota_follows_env_values() {
+        let env = std::collections::HashMap::from([
+            ("RATE_LIMIT_AUTH_MAX_REQUESTS", "2"),
+            ("RATE_LIMIT_AUTH_WINDOW_SECONDS", "60"),
+        ]);
+        let limit =
+            IpRateLimit::from_lookup("AUTH", 10, 60, |key| env.get(key).map(|v| v.to_string()));
+        assert_eq!(
+            limit,
+            IpRateLimit {
+                max_requests: 2,
+                window_seconds: 60,
+            }
+        );
+
+        let config = create_governor_config(limit);
+        let ip: std::net::IpAddr = "192.0.2.7".parse().unwrap();
+        assert!(config.limiter().check_key(&ip).is_ok());
+        assert!(config.limiter().check_key(&ip).is_ok());
+
+        let not_until = config.limiter().check_key(&ip).unwrap_err();
+        let wait = not_until.wait_time_from(governor::clock::DefaultClock::default().now());
+        assert!(wait > Duration::from_secs(29) && wait <= Duration::from_secs(30));
+    }
+
+    #[test]
+    fn test_ip_quota_defaults() {
+        let limit = IpRateLimit::from_lookup("SENSITIVE", 3, 60, |_| None);
+        assert_eq!(limit.max_requests, 3);
+        assert_eq!(limit.replenish_interval(), Duration::from_secs(20));
+
+        let invalid = IpRateLimit::from_lookup("AUTH", 10, 60, |_| Some("0".to_string()));
+        assert_eq!(invalid.replenish_interval(), Duration::from_secs(6));
+    }
+
+    fn test_config(backend: RateLimitBackend) -> RateLimitConfig {
+        RateLimitConfig {
+            backend,
+            auth: IpRateLimit {
+                max_requests: 10,
+                window_seconds: 60,
+            },
+            sensitive: IpRateLimit {
+                max_requests: 3,
+                window_seconds: 60,
+            },
+            user_chat: UserRateLimit {
+                per_minute: 1,
+                burst: 1,
+            },
+            user_account: UserRateLimit {
+                per_minute: 1,
+                burst: 1,
+            },
+            user_admin: UserRateLimit {
+                per_minute: 1,
+                burst: 1,
+            },
+        }
+    }
+
+    #[test]
+    fn test_missing_redis_falls_back_to_memory() {
+        let config = test_config(RateLimitBackend::Redis);
+        assert!(!RateLimiters::from_config(&config, None).is_redis());
+    }
 }
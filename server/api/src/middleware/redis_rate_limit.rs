@@ -0,0 +1,237 @@
+//! Redis-backed rate limiting
+//!
+//! Shares limiter state between API replicas. Each bucket is a single Redis
+//! key holding a GCRA "theoretical arrival time", updated atomically by a Lua
+//! script using the Redis server clock so replicas agree on time.
+//!
+//! If Redis errors at request time, the request is checked against an
+//! in-process limiter with the same quota instead, so an outage degrades to
+//! per-instance limits rather than no limits.
+
+use std::convert::Infallible;
+use std::num::NonZeroU32;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use axum::{extract::Request, response::Response};
+use futures::future::BoxFuture;
+use governor::{
+    Quota, RateLimiter,
+    clock::{Clock, DefaultClock},
+    middleware::StateInformationMiddleware,
+    state::keyed::DefaultKeyedStateStore,
+};
+use redis::{RedisError, Script};
+use tower::{Layer, Service};
+
+use super::rate_limit::{
+    RateLimitKey, insert_quota_headers, missing_key_response, too_many_requests,
+};
+use crate::config::cache::RedisPool;
+
+/// GCRA token bucket
+///
+/// KEYS[1] = bucket key, ARGV[1] = emission interval (ms), ARGV[2] = burst.
+/// Returns `{1, 0, remaining}` when allowed or `{0, retry_after_ms, 0}` when
+/// limited.
+const GCRA_SCRIPT: &str = r#"
+local interval = tonumber(ARGV[1])
+local burst = tonumber(ARGV[2])
+local time = redis.call('TIME')
+local now = tonumber(time[1]) * 1000 + math.floor(tonumber(time[2]) / 1000)
+
+local tat = tonumber(redis.call('GET', KEYS[1]) or now)
+if tat < now then
+    tat = now
+end
+
+local new_tat = tat + interval
+local allowed_at = new_tat - interval * burst
+if allowed_at > now then
+    return {0, allowed_at - now, 0}
+end
+
+redis.call('SET', KEYS[1], new_tat, 'PX', new_tat - now)
+return {1, 0, math.floor((now - allowed_at) / interval)}
+"#;
+
+/// Shared Redis connection and limiter script
+#[derive(Clone)]
+pub struct RedisRateLimiter {
+    connection: RedisPool,
+    script: Arc<Script>,
+}
+
+impl RedisRateLimiter {
+    pub fn new(connection: RedisPool) -> Self {
+        Self {
+            connection,
+            script: Arc::new(Script::new(GCRA_SCRIPT)),
+        }
+    }
+
+    /// Take one token from `key`
+    async fn check(
+        &self,
+        key: &str,
+        interval: Duration,
+        burst: u32,
+    ) -> Result<Decision, RedisError> {
+        let mut connection = self.connection.clone();
+        let (allowed, retry_after_ms, remaining): (i64, i64, i64) = self
+            .script
+            .key(key)
+            .arg(interval.as_millis() as u64)
+            .arg(burst)
+            .invoke_async(&mut connection)
+            .await?;
+
+        if allowed == 1 {
+            Ok(Decision::Allowed {
+                remaining: remaining.max(0) as u32,
+            })
+        } else {
+            Ok(Decision::Limited {
+                wait_seconds: (retry_after_ms as u64).div_ceil(1000).max(1),
+            })
+        }
+    }
+}
+
+/// Outcome of taking a token from a bucket
+enum Decision {
+    Allowed { remaining: u32 },
+    Limited { wait_seconds: u64 },
+}
+
+/// One rate limit rule backed by Redis
+struct Rule {
+    /// Namespaces the Redis keys of this rule
+    name: &'static str,
+    key: RateLimitKey,
+    interval: Duration,
+    burst: u32,
+    fallback: RateLimiter<
+        String,
+        DefaultKeyedStateStore<String>,
+        DefaultClock,
+        StateInformationMiddleware,
+    >,
+}
+
+/// Tower layer applying a Redis-backed rule
+#[derive(Clone)]
+pub struct RedisRateLimitLayer {
+    limiter: RedisRateLimiter,
+    rule: Arc<Rule>,
+}
+
+impl RedisRateLimitLayer {
+    pub fn new(
+        limiter: RedisRateLimiter,
+        name: &'static str,
+        key: RateLimitKey,
+        interval: Duration,
+        burst: u32,
+    ) -> Self {
+        let burst = burst.max(1);
+        let quota = Quota::with_period(interval)
+            .expect("rate limit interval must be non-zero")
+            .allow_burst(NonZeroU32::new(burst).expect("burst is at least 1"));
+
+        Self {
+            limiter,
+            rule: Arc::new(Rule {
+                name,
+                key,
+                interval,
+                burst,
+                fallback: RateLimiter::keyed(quota).with_middleware(),
+            }),
+        }
+    }
+}
+
+impl<S> Layer<S> for RedisRateLimitLayer {
+    type Service = RedisRateLimit<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RedisRateLimit {
+            inner,
+            limiter: self.limiter.clone(),
+            rule: self.rule.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct RedisRateLimit<S> {
+    inner: S,
+    limiter: RedisRateLimiter,
+    rule: Arc<Rule>,
+}
+
+impl<S> Service<Request> for RedisRateLimit<S>
+where
+    S: Service<Request, Response = Response, Error = Infallible> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = Infallible;
+    type Future = BoxFuture<'static, Result<Response, Infallible>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request) -> Self::Future {
+        // Use the service that was polled ready and leave a fresh clone behind
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let limiter = self.limiter.clone();
+        let rule = self.rule.clone();
+
+        Box::pin(async move {
+            let Some(key) = rule.key.extract(&req) else {
+                return Ok(missing_key_response());
+            };
+
+            let redis_key = format!("ratelimit:{}:{}", rule.name, key);
+            let decision = match limiter.check(&redis_key, rule.interval, rule.burst).await {
+                Ok(decision) => decision,
+                Err(e) => {
+                    tracing::warn!(
+                        rule = rule.name,
+                        "Redis rate limiter unavailable, using in-memory limit: {}",
+                        e
+                    );
+                    match rule.fallback.check_key(&key) {
+                        Ok(snapshot) => Decision::Allowed {
+                            remaining: snapshot.remaining_burst_capacity(),
+                        },
+                        Err(not_until) => Decision::Limited {
+                            wait_seconds: not_until
+                                .wait_time_from(DefaultClock::default().now())
+                                .as_secs()
+                                .max(1),
+                        },
+                    }
+                }
+            };
+
+            match decision {
+                Decision::Allowed { remaining } => {
+                    let mut response = inner.call(req).await?;
+                    insert_quota_headers(response.headers_mut(), rule.burst, remaining);
+                    Ok(response)
+                }
+                Decision::Limited { wait_seconds } => {
+                    let mut response = too_many_requests(wait_seconds);
+                    insert_quota_headers(response.headers_mut(), rule.burst, 0);
+                    Ok(response)
+                }
+            }
+        })
+    }
+}
@@ -0,0 +1,279 @@
+//! Request ID middleware
+//!
+//! Gives every request an ID that appears on its log lines, in the
+//! `X-Request-ID` response header and in the `x-request-id` metadata of
+//! Intelligence gRPC calls made while handling it. An incoming
+//! `X-Request-ID` is reused so IDs survive proxy chains.
+//!
+//! JSON error bodies of 5xx responses also get a `request_id` field, so users
+//! can quote it in a support ticket without digging through headers.
+//!
+//! Must be layered inside the `TraceLayer` so the request span already
+//! exists when the ID is recorded on it.
+//!
+//! The ID only lives as long as the handler's future, so work that outlives
+//! it goes through [`spawn`] and [`scope_stream`] to keep the ID and span:
+//! background tasks, and response streams polled after the handler returns.
+
+use axum::{
+    body::Body,
+    extract::Request,
+    http::{HeaderName, HeaderValue, header},
+    middleware::Next,
+    response::Response,
+};
+use futures::Stream;
+use tracing::Instrument;
+use uuid::Uuid;
+
+pub const REQUEST_ID_HEADER: HeaderName = HeaderName::from_static("x-request-id");
+
+/// Longest incoming ID that is reused instead of replaced
+const MAX_REQUEST_ID_LENGTH: usize = 128;
+
+/// Largest error body that is rewritten to include the request ID
+const MAX_ERROR_BODY_BYTES: usize = 64 * 1024;
+
+tokio::task_local! {
+    static CURRENT_REQUEST_ID: RequestId;
+}
+
+/// ID of the current request, available as a request extension
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RequestId(pub String);
+
+impl std::fmt::Display for RequestId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl RequestId {
+    /// ID of the request being handled on this task, if any
+    pub fn current() -> Option<RequestId> {
+        CURRENT_REQUEST_ID.try_with(|id| id.clone()).ok()
+    }
+
+    /// Reuse a well-formed incoming header or generate a new ID
+    fn from_header(value: Option<&HeaderValue>) -> Self {
+        value
+            .and_then(|v| v.to_str().ok())
+            .map(str::trim)
+            .filter(|v| is_valid_request_id(v))
+            .map(|v| RequestId(v.to_string()))
+            .unwrap_or_else(|| RequestId(Uuid::new_v4().to_string()))
+    }
+}
+
+/// Keep IDs short and printable so they are safe to log and forward
+fn is_valid_request_id(value: &str) -> bool {
+    !value.is_empty()
+        && value.len() <= MAX_REQUEST_ID_LENGTH
+        && value
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | ':'))
+}
+
+/// `tokio::spawn`, keeping the current request's ID and span in the task
+pub fn spawn<F>(future: F) -> tokio::task::JoinHandle<F::Output>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    let future = future.instrument(tracing::Span::current());
+    match RequestId::current() {
+        Some(id) => tokio::spawn(CURRENT_REQUEST_ID.scope(id, future)),
+        None => tokio::spawn(future),
+    }
+}
+
+/// Poll `stream` with the current request's ID and span, for response
+/// bodies that are still streaming after the handler has returned
+pub fn scope_stream<S: Stream>(stream: S) -> impl Stream<Item = S::Item> {
+    let id = RequestId::current();
+    let span = tracing::Span::current();
+    let mut stream = Box::pin(stream);
+
+    futures::stream::poll_fn(move |cx| {
+        let _entered = span.enter();
+        match &id {
+            Some(id) => CURRENT_REQUEST_ID.sync_scope(id.clone(), || stream.as_mut().poll_next(cx)),
+            None => stream.as_mut().poll_next(cx),
+        }
+    })
+}
+
+/// Assign the request ID, record it on the span and echo it in the response
+pub async fn request_id_middleware(mut req: Request, next: Next) -> Response {
+    let request_id = RequestId::from_header(req.headers().get(&REQUEST_ID_HEADER));
+
+    tracing::Span::current().record("request_id", request_id.0.as_str());
+    req.extensions_mut().insert(request_id.clone());
+
+    let header_value = HeaderValue::from_str(&request_id.0).ok();
+    let mut response = CURRENT_REQUEST_ID
+        .scope(request_id.clone(), next.run(req))
+        .await;
+
+    if response.status().is_server_error() {
+        response = add_request_id_to_error(response, &request_id).await;
+    }
+
+    if let Some(value) = header_value {
+        response.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
+    response
+}
+
+/// Add `request_id` to a JSON object error body; other bodies pass unchanged
+async fn add_request_id_to_error(response: Response, request_id: &RequestId) -> Response {
+    let is_json = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .is_some_and(|v| v.as_bytes().starts_with(b"application/json"));
+    if !is_json {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let Ok(bytes) = axum::body::to_bytes(body, MAX_ERROR_BODY_BYTES).await else {
+        // Oversized or broken body; the header still carries the ID
+        return Response::from_parts(parts, Body::empty());
+    };
+
+    let body = match serde_json::from_slice::<serde_json::Value>(&bytes) {
+        Ok(serde_json::Value::Object(mut object)) => {
+            object.insert("request_id".to_string(), request_id.0.clone().into());
+            parts.headers.remove(header::CONTENT_LENGTH);
+            Body::from(serde_json::Value::Object(object).to_string())
+        }
+        _ => Body::from(bytes),
+    };
+    Response::from_parts(parts, body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{Extension, Router, body::Body, routing::get};
+    use tower::ServiceExt;
+
+    fn app() -> Router {
+        Router::new()
+            .route(
+                "/",
+                get(|Extension(id): Extension<RequestId>| async move {
+                    assert_eq!(RequestId::current(), Some(id.clone()));
+                    id.0
+                }),
+            )
+            .layer(axum::middleware::from_fn(request_id_middleware))
+    }
+
+    #[tokio::test]
+    async fn test_spawned_tasks_and_streams_keep_the_request_id() {
+        use futures::StreamExt;
+
+        let id = RequestId("req-1".to_string());
+        let (task, stream) = CURRENT_REQUEST_ID
+            .scope(id.clone(), async {
+                let task = spawn(async { RequestId::current() });
+                let stream = scope_stream(futures::stream::repeat_with(RequestId::current));
+                (task, stream)
+            })
+            .await;
+
+        assert_eq!(task.await.unwrap(), Some(id.clone()));
+        // Polled outside the request's scope, as a response body is
+        let ids: Vec<_> = stream.take(2).collect().await;
+        assert_eq!(ids, vec![Some(id.clone()), Some(id)]);
+
+        assert_eq!(spawn(async { RequestId::current() }).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_response_carries_request_id() {
+        let res = app()
+            .oneshot(Request::builder().uri("/").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+
+        let header = res.headers()[REQUEST_ID_HEADER]
+            .to_str()
+            .unwrap()
+            .to_string();
+        assert!(Uuid::parse_str(&header).is_ok());
+
+        let body = axum::body::to_bytes(res.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(body, header.as_bytes());
+    }
+
+    #[tokio::test]
+    async fn test_incoming_request_id_is_reused() {
+        let res = app()
+            .oneshot(
+                Request::builder()
+                    .uri("/")
+                    .header(REQUEST_ID_HEADER, "edge-1234")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(res.headers()[REQUEST_ID_HEADER], "edge-1234");
+
+        let res = app()
+            .oneshot(
+                Request::builder()
+                    .uri("/")
+                    .header(REQUEST_ID_HEADER, "bad id\twith spaces")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_ne!(res.headers()[REQUEST_ID_HEADER], "bad id\twith spaces");
+    }
+
+    #[tokio::test]
+    async fn test_server_errors_quote_request_id() {
+        use axum::{Json, http::StatusCode};
+
+        let app = Router::new()
+            .route(
+                "/",
+                get(|| async {
+                    (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        Json(serde_json::json!({
+                            "error": "internal_error",
+                            "message": "An internal error occurred",
+                        })),
+                    )
+                }),
+            )
+            .layer(axum::middleware::from_fn(request_id_middleware));
+
+        let res = app
+            .oneshot(
+                Request::builder()
+                    .uri("/")
+                    .header(REQUEST_ID_HEADER, "support-42")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(res.headers()[REQUEST_ID_HEADER], "support-42");
+
+        let body = axum::body::to_bytes(res.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["request_id"], "support-42");
+        assert_eq!(body["error"], "internal_error");
+    }
+}
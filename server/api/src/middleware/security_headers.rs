@@ -0,0 +1,200 @@
+//! Security response headers
+//!
+//! `SecurityHeadersLayer` adds browser hardening headers to every response.
+//! Headers a handler already set are left alone, so a route can relax one
+//! (e.g. the home page allows its inline styles). HSTS is only sent when the
+//! request arrived over HTTPS at the TLS-terminating proxy, as reported by
+//! `X-Forwarded-Proto`; browsers ignore it over plain HTTP anyway.
+
+use std::task::{Context, Poll};
+
+use axum::{
+    extract::Request,
+    http::{HeaderMap, HeaderName, HeaderValue, header},
+    response::Response,
+};
+use futures::future::BoxFuture;
+use tower::{Layer, Service};
+
+/// Used when `CSP_POLICY` is not set
+pub const DEFAULT_CSP_POLICY: &str = "default-src 'self'";
+
+const HSTS: &str = "max-age=31536000; includeSubDomains";
+const X_FORWARDED_PROTO: &str = "x-forwarded-proto";
+
+/// Adds security headers to every response
+#[derive(Debug, Clone)]
+pub struct SecurityHeadersLayer {
+    csp: HeaderValue,
+}
+
+impl SecurityHeadersLayer {
+    /// `csp` replaces the default `Content-Security-Policy`
+    ///
+    /// Panics if the policy is not a valid header value; `SecurityConfig`
+    /// checks this when loading.
+    pub fn new(csp: Option<&str>) -> Self {
+        let csp = HeaderValue::from_str(csp.unwrap_or(DEFAULT_CSP_POLICY))
+            .expect("CSP policy must be a valid header value");
+        Self { csp }
+    }
+}
+
+impl<S> Layer<S> for SecurityHeadersLayer {
+    type Service = SecurityHeaders<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        SecurityHeaders {
+            inner,
+            csp: self.csp.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SecurityHeaders<S> {
+    inner: S,
+    csp: HeaderValue,
+}
+
+impl<S> Service<Request> for SecurityHeaders<S>
+where
+    S: Service<Request, Response = Response> + Clone + Send + 'static,
+    S::Error: Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = BoxFuture<'static, Result<Response, S::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request) -> Self::Future {
+        // Use the service that was polled ready and leave a fresh clone behind
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let csp = self.csp.clone();
+        let https = is_https(req.headers());
+
+        Box::pin(async move {
+            let mut response = inner.call(req).await?;
+            let headers = response.headers_mut();
+
+            let mut set = |name: HeaderName, value: HeaderValue| {
+                headers.entry(name).or_insert(value);
+            };
+            set(header::X_FRAME_OPTIONS, HeaderValue::from_static("DENY"));
+            set(
+                header::X_CONTENT_TYPE_OPTIONS,
+                HeaderValue::from_static("nosniff"),
+            );
+            set(
+                header::REFERRER_POLICY,
+                HeaderValue::from_static("strict-origin-when-cross-origin"),
+            );
+            set(header::CONTENT_SECURITY_POLICY, csp);
+            if https {
+                set(
+                    header::STRICT_TRANSPORT_SECURITY,
+                    HeaderValue::from_static(HSTS),
+                );
+            }
+
+            Ok(response)
+        })
+    }
+}
+
+/// Whether the proxy in front of us received the request over HTTPS
+fn is_https(headers: &HeaderMap) -> bool {
+    headers
+        .get(X_FORWARDED_PROTO)
+        .and_then(|value| value.to_str().ok())
+        // Chained proxies list one protocol each; the first is the client's
+        .and_then(|value| value.split(',').next())
+        .is_some_and(|proto| proto.trim().eq_ignore_ascii_case("https"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{Router, body::Body, routing::get};
+    use tower::ServiceExt;
+
+    fn app(csp: Option<&str>) -> Router {
+        Router::new()
+            .route("/", get(|| async { "ok" }))
+            .route(
+                "/relaxed",
+                get(|| async { ([(header::CONTENT_SECURITY_POLICY, "default-src *")], "ok") }),
+            )
+            .layer(SecurityHeadersLayer::new(csp))
+    }
+
+    fn get_request(uri: &str, forwarded_proto: Option<&str>) -> Request {
+        let mut builder = Request::builder().uri(uri);
+        if let Some(proto) = forwarded_proto {
+            builder = builder.header(X_FORWARDED_PROTO, proto);
+        }
+        builder.body(Body::empty()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_headers_on_every_response() {
+        let res = app(None)
+            .oneshot(get_request("/", Some("https")))
+            .await
+            .unwrap();
+        let headers = res.headers();
+
+        assert_eq!(headers[header::X_FRAME_OPTIONS], "DENY");
+        assert_eq!(headers[header::X_CONTENT_TYPE_OPTIONS], "nosniff");
+        assert_eq!(
+            headers[header::REFERRER_POLICY],
+            "strict-origin-when-cross-origin"
+        );
+        assert_eq!(headers[header::CONTENT_SECURITY_POLICY], DEFAULT_CSP_POLICY);
+        assert_eq!(headers[header::STRICT_TRANSPORT_SECURITY], HSTS);
+
+        // Unknown routes get them too
+        let res = app(None)
+            .oneshot(get_request("/missing", None))
+            .await
+            .unwrap();
+        assert_eq!(res.headers()[header::X_FRAME_OPTIONS], "DENY");
+    }
+
+    #[tokio::test]
+    async fn test_hsts_only_over_https() {
+        for proto in [None, Some("http"), Some("http, https")] {
+            let res = app(None).oneshot(get_request("/", proto)).await.unwrap();
+            assert!(
+                !res.headers()
+                    .contains_key(header::STRICT_TRANSPORT_SECURITY)
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_configured_and_handler_csp() {
+        let res = app(Some("default-src 'none'"))
+            .oneshot(get_request("/", None))
+            .await
+            .unwrap();
+        assert_eq!(
+            res.headers()[header::CONTENT_SECURITY_POLICY],
+            "default-src 'none'"
+        );
+
+        let res = app(None)
+            .oneshot(get_request("/relaxed", None))
+            .await
+            .unwrap();
+        assert_eq!(
+            res.headers()[header::CONTENT_SECURITY_POLICY],
+            "default-src *"
+        );
+    }
+}
@@ -0,0 +1,105 @@
+use axum::{
+    Extension, Json,
+    extract::{Path, State},
+};
+use chrono::Utc;
+use uuid::Uuid;
+
+use super::{CreateNoticeRequest, Notice, NoticeListResponse, PublicNotice};
+use crate::admin::management::errors::ManagementError;
+use crate::gateway::AppState;
+use crate::gateway::openapi::ErrorBody;
+
+/// List the notices active right now
+/// GET /notices
+///
+/// Public; served from a cache that is at most 30 seconds old.
+#[utoipa::path(
+    get,
+    path = "/notices",
+    tag = "notices",
+    responses(
+        (status = 200, description = "Active system notices"),
+    )
+)]
+pub async fn active_notices(
+    State(state): State<AppState>,
+) -> Result<Json<NoticeListResponse<PublicNotice>>, ManagementError> {
+    let notices = state.notices.active(&state.db).await?;
+    Ok(Json(NoticeListResponse {
+        notices: notices.into_iter().map(PublicNotice::from).collect(),
+    }))
+}
+
+/// Post a notice
+/// POST /admin/notices
+#[utoipa::path(
+    post,
+    path = "/admin/notices",
+    tag = "admin",
+    responses(
+        (status = 200, description = "Notice created"),
+        (status = 400, description = "Invalid notice", body = ErrorBody),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn create_notice(
+    State(state): State<AppState>,
+    Extension(admin_id): Extension<Uuid>,
+    Json(req): Json<CreateNoticeRequest>,
+) -> Result<Json<Notice>, ManagementError> {
+    let active_from = req
+        .validate(Utc::now())
+        .map_err(ManagementError::Validation)?;
+
+    let notice = super::create_notice(&state.db, &req, active_from, admin_id).await?;
+    state.notices.invalidate().await;
+
+    tracing::info!(%admin_id, notice_id = %notice.id, "Admin posted system notice");
+    Ok(Json(notice))
+}
+
+/// List every notice, including past and scheduled ones
+/// GET /admin/notices
+#[utoipa::path(
+    get,
+    path = "/admin/notices",
+    tag = "admin",
+    responses(
+        (status = 200, description = "All notices"),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn list_notices(
+    State(state): State<AppState>,
+) -> Result<Json<NoticeListResponse<Notice>>, ManagementError> {
+    let notices = super::list_all(&state.db).await?;
+    Ok(Json(NoticeListResponse { notices }))
+}
+
+/// Remove a notice
+/// DELETE /admin/notices/{id}
+#[utoipa::path(
+    delete,
+    path = "/admin/notices/{id}",
+    tag = "admin",
+    params(("id" = Uuid, Path, description = "Notice ID")),
+    responses(
+        (status = 200, description = "Notice deleted"),
+        (status = 404, description = "Notice not found", body = ErrorBody),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn delete_notice(
+    State(state): State<AppState>,
+    Extension(admin_id): Extension<Uuid>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<()>, ManagementError> {
+    if !super::delete_notice(&state.db, id).await? {
+        return Err(ManagementError::NotFound("Notice not found".to_string()));
+    }
+    state.notices.invalidate().await;
+
+    tracing::info!(%admin_id, notice_id = %id, "Admin deleted system notice");
+    Ok(Json(()))
+}
@@ -0,0 +1,256 @@
+//! System notices
+//!
+//! Admins post announcements (maintenance windows, incidents) with an active
+//! window. Everyone, signed in or not, can list the currently active ones,
+//! so they are cached in process for `CACHE_TTL` instead of being queried on
+//! every request. Creating or deleting a notice clears the cache.
+
+pub mod handlers;
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+/// How long the active notices are served from memory
+const CACHE_TTL: Duration = Duration::from_secs(30);
+
+pub const MAX_TITLE_LENGTH: usize = 200;
+pub const MAX_BODY_LENGTH: usize = 5000;
+
+/// How prominently frontends should show a notice
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(type_name = "text", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Info,
+    Warning,
+    Critical,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Notice {
+    pub id: Uuid,
+    pub title: String,
+    pub body: String,
+    pub severity: Severity,
+    pub active_from: DateTime<Utc>,
+    pub active_until: DateTime<Utc>,
+    pub created_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateNoticeRequest {
+    pub title: String,
+    pub body: String,
+    #[serde(default = "default_severity")]
+    pub severity: Severity,
+    /// Defaults to now
+    pub active_from: Option<DateTime<Utc>>,
+    pub active_until: DateTime<Utc>,
+}
+
+fn default_severity() -> Severity {
+    Severity::Info
+}
+
+impl CreateNoticeRequest {
+    /// Check the request, returning the start of the active window
+    pub fn validate(&self, now: DateTime<Utc>) -> Result<DateTime<Utc>, String> {
+        if self.title.trim().is_empty() || self.title.len() > MAX_TITLE_LENGTH {
+            return Err(format!(
+                "title must be 1 to {} characters",
+                MAX_TITLE_LENGTH
+            ));
+        }
+        if self.body.trim().is_empty() || self.body.len() > MAX_BODY_LENGTH {
+            return Err(format!("body must be 1 to {} characters", MAX_BODY_LENGTH));
+        }
+
+        let active_from = self.active_from.unwrap_or(now);
+        if self.active_until <= active_from {
+            return Err("active_until must be later than active_from".to_string());
+        }
+        Ok(active_from)
+    }
+}
+
+/// A notice as shown to visitors, without who posted it
+#[derive(Debug, Serialize)]
+pub struct PublicNotice {
+    pub id: Uuid,
+    pub title: String,
+    pub body: String,
+    pub severity: Severity,
+    pub active_from: DateTime<Utc>,
+    pub active_until: DateTime<Utc>,
+}
+
+impl From<Notice> for PublicNotice {
+    fn from(notice: Notice) -> Self {
+        Self {
+            id: notice.id,
+            title: notice.title,
+            body: notice.body,
+            severity: notice.severity,
+            active_from: notice.active_from,
+            active_until: notice.active_until,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct NoticeListResponse<T> {
+    pub notices: Vec<T>,
+}
+
+/// Active notices and when they were loaded
+type CachedNotices = (Vec<Notice>, Instant);
+
+#[derive(Clone, Default)]
+pub struct NoticeCache {
+    inner: Arc<RwLock<Option<CachedNotices>>>,
+}
+
+impl NoticeCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Currently active notices, from memory if loaded in the last `CACHE_TTL`
+    pub async fn active(&self, db: &PgPool) -> Result<Vec<Notice>, sqlx::Error> {
+        if let Some((notices, loaded_at)) = self.inner.read().await.as_ref()
+            && loaded_at.elapsed() < CACHE_TTL
+        {
+            return Ok(notices.clone());
+        }
+
+        // Only one request reloads; the rest wait and reuse its result
+        let mut cached = self.inner.write().await;
+        if let Some((notices, loaded_at)) = cached.as_ref()
+            && loaded_at.elapsed() < CACHE_TTL
+        {
+            return Ok(notices.clone());
+        }
+
+        let notices = list_active(db).await?;
+        *cached = Some((notices.clone(), Instant::now()));
+        Ok(notices)
+    }
+
+    /// Drop the cached notices so the next read sees a change immediately
+    pub async fn invalidate(&self) {
+        *self.inner.write().await = None;
+    }
+}
+
+pub async fn create_notice(
+    db: &PgPool,
+    req: &CreateNoticeRequest,
+    active_from: DateTime<Utc>,
+    created_by: Uuid,
+) -> Result<Notice, sqlx::Error> {
+    sqlx::query_as!(
+        Notice,
+        r#"
+        INSERT INTO system_notices (title, body, severity, active_from, active_until, created_by)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        RETURNING id, title, body, severity as "severity: Severity", active_from, active_until,
+                  created_by, created_at
+        "#,
+        req.title.trim(),
+        req.body.trim(),
+        req.severity as Severity,
+        active_from,
+        req.active_until,
+        created_by
+    )
+    .fetch_one(db)
+    .await
+}
+
+/// Every notice, newest first
+pub async fn list_all(db: &PgPool) -> Result<Vec<Notice>, sqlx::Error> {
+    sqlx::query_as!(
+        Notice,
+        r#"
+        SELECT id, title, body, severity as "severity: Severity", active_from, active_until,
+               created_by, created_at
+        FROM system_notices
+        ORDER BY created_at DESC
+        "#
+    )
+    .fetch_all(db)
+    .await
+}
+
+/// Notices whose window contains the current time, most severe first
+async fn list_active(db: &PgPool) -> Result<Vec<Notice>, sqlx::Error> {
+    sqlx::query_as!(
+        Notice,
+        r#"
+        SELECT id, title, body, severity as "severity: Severity", active_from, active_until,
+               created_by, created_at
+        FROM system_notices
+        WHERE NOW() BETWEEN active_from AND active_until
+        ORDER BY CASE severity WHEN 'critical' THEN 0 WHEN 'warning' THEN 1 ELSE 2 END,
+                 active_from DESC
+        "#
+    )
+    .fetch_all(db)
+    .await
+}
+
+/// Returns `false` if no notice had that ID
+pub async fn delete_notice(db: &PgPool, id: Uuid) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query!("DELETE FROM system_notices WHERE id = $1", id)
+        .execute(db)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration as ChronoDuration;
+
+    use super::*;
+
+    fn request(
+        active_from: Option<DateTime<Utc>>,
+        active_until: DateTime<Utc>,
+    ) -> CreateNoticeRequest {
+        CreateNoticeRequest {
+            title: "Maintenance".to_string(),
+            body: "Chat is read-only tonight".to_string(),
+            severity: Severity::Warning,
+            active_from,
+            active_until,
+        }
+    }
+
+    #[test]
+    fn test_notice_window_validation() {
+        let now = Utc::now();
+        let later = now + ChronoDuration::hours(1);
+
+        assert_eq!(request(None, later).validate(now), Ok(now));
+        assert!(request(Some(later), now).validate(now).is_err());
+        assert!(request(None, now).validate(now).is_err());
+
+        let mut untitled = request(None, later);
+        untitled.title = "  ".to_string();
+        assert!(untitled.validate(now).is_err());
+    }
+
+    #[test]
+    fn test_severity_names() {
+        let parsed: Severity = serde_json::from_str(r#""critical""#).unwrap();
+        assert_eq!(parsed, Severity::Critical);
+        assert!(serde_json::from_str::<Severity>(r#""urgent""#).is_err());
+    }
+}
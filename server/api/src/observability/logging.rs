@@ -1,10 +1,33 @@
+use opentelemetry_sdk::trace::SdkTracerProvider;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
-pub fn init() {
+use super::telemetry;
+use crate::config::env::TelemetryConfig;
+
+/// Install the global subscriber, exporting spans over OTLP when configured
+///
+/// Returns the tracer provider so pending spans can be flushed on shutdown.
+pub fn init(config: &TelemetryConfig) -> Option<SdkTracerProvider> {
+    let (provider, otel_layer) = match telemetry::init_tracer(config) {
+        Ok(Some((provider, tracer))) => (
+            Some(provider),
+            Some(tracing_opentelemetry::layer().with_tracer(tracer)),
+        ),
+        Ok(None) => (None, None),
+        Err(e) => {
+            // The subscriber isn't installed yet, so report on stderr
+            eprintln!("Failed to initialize OpenTelemetry export: {}", e);
+            (None, None)
+        }
+    };
+
     tracing_subscriber::registry()
         .with(tracing_subscriber::EnvFilter::new(
             std::env::var("RUST_LOG").unwrap_or_else(|_| "api=debug".into()),
         ))
         .with(tracing_subscriber::fmt::layer())
+        .with(otel_layer)
         .init();
+
+    provider
 }
@@ -1 +1,3 @@
 pub mod logging;
+pub mod request_log;
+pub mod telemetry;
@@ -0,0 +1,205 @@
+//! Per-route request logging verbosity for the HTTP `TraceLayer`
+//!
+//! Levels are resolved by longest matching path prefix, e.g.
+//! `LOG_ROUTE_LEVELS=/health=off,/chat=trace`.
+
+use std::{sync::Arc, time::Duration};
+
+use axum::http::{Request, Response};
+use tower_http::trace::{MakeSpan, OnResponse, TraceLayer};
+use tracing::{Level, Span};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+use super::telemetry;
+use crate::config::env::LoggingConfig;
+
+/// Parse a log level, where `off` disables logging entirely
+pub fn parse_level(s: &str) -> Result<Option<Level>, String> {
+    match s.trim().to_lowercase().as_str() {
+        "off" | "none" => Ok(None),
+        other => other
+            .parse::<Level>()
+            .map(Some)
+            .map_err(|_| format!("Invalid log level: {}", s)),
+    }
+}
+
+/// Parse `prefix=level` pairs separated by commas
+pub fn parse_route_levels(s: &str) -> Result<Vec<(String, Option<Level>)>, String> {
+    s.split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| {
+            let (prefix, level) = entry
+                .split_once('=')
+                .ok_or_else(|| format!("Expected prefix=level, got: {}", entry))?;
+            Ok((prefix.trim().trim_end_matches('/').to_string(), parse_level(level)?))
+        })
+        .collect()
+}
+
+/// Resolved request log levels shared by the span and response hooks
+#[derive(Debug, Clone)]
+pub struct RouteLogLevels {
+    default: Option<Level>,
+    routes: Vec<(String, Option<Level>)>,
+}
+
+impl RouteLogLevels {
+    pub fn new(config: &LoggingConfig) -> Self {
+        let mut routes = config.route_levels.clone();
+        // Longest prefix first so the most specific rule wins
+        routes.sort_by_key(|(prefix, _)| std::cmp::Reverse(prefix.len()));
+
+        Self {
+            default: config.request_level,
+            routes,
+        }
+    }
+
+    /// Level for a request path, or `None` if logging is disabled for it
+    pub fn level_for(&self, path: &str) -> Option<Level> {
+        self.routes
+            .iter()
+            .find(|(prefix, _)| {
+                path.strip_prefix(prefix.as_str())
+                    .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+            })
+            .map(|(_, level)| *level)
+            .unwrap_or(self.default)
+    }
+}
+
+/// Creates the request span at the route's configured level
+#[derive(Debug, Clone)]
+pub struct RouteMakeSpan {
+    levels: Arc<RouteLogLevels>,
+}
+
+impl<B> MakeSpan<B> for RouteMakeSpan {
+    fn make_span(&mut self, request: &Request<B>) -> Span {
+        let method = request.method();
+        let uri = request.uri();
+        let version = request.version();
+
+        macro_rules! request_span {
+            ($level:expr) => {
+                tracing::span!(
+                    $level,
+                    "request",
+                    %method,
+                    %uri,
+                    ?version,
+                    // Filled in by `request_id_middleware`
+                    request_id = tracing::field::Empty,
+                )
+            };
+        }
+
+        let span = match self.levels.level_for(uri.path()) {
+            Some(Level::ERROR) => request_span!(Level::ERROR),
+            Some(Level::WARN) => request_span!(Level::WARN),
+            Some(Level::INFO) => request_span!(Level::INFO),
+            Some(Level::DEBUG) => request_span!(Level::DEBUG),
+            Some(_) => request_span!(Level::TRACE),
+            None => Span::none(),
+        };
+
+        // Continue the caller's trace when it sent a `traceparent`
+        let _ = span.set_parent(telemetry::parent_from_headers(request.headers()));
+        span
+    }
+}
+
+/// Logs the response at the same level as its request span
+#[derive(Debug, Clone, Copy)]
+pub struct RouteOnResponse;
+
+impl<B> OnResponse<B> for RouteOnResponse {
+    fn on_response(self, response: &Response<B>, latency: Duration, span: &Span) {
+        // Disabled routes get `Span::none()`, which carries no metadata
+        let Some(level) = span.metadata().map(|meta| *meta.level()) else {
+            return;
+        };
+
+        let status = response.status().as_u16();
+        let latency_ms = latency.as_millis();
+
+        macro_rules! response_event {
+            ($level:expr) => {
+                tracing::event!(parent: span, $level, status, latency_ms, "finished processing request")
+            };
+        }
+
+        match level {
+            Level::ERROR => response_event!(Level::ERROR),
+            Level::WARN => response_event!(Level::WARN),
+            Level::INFO => response_event!(Level::INFO),
+            Level::DEBUG => response_event!(Level::DEBUG),
+            _ => response_event!(Level::TRACE),
+        }
+    }
+}
+
+/// Build the HTTP request logging layer from config
+pub fn trace_layer(
+    config: &LoggingConfig,
+) -> TraceLayer<
+    tower_http::classify::SharedClassifier<tower_http::classify::ServerErrorsAsFailures>,
+    RouteMakeSpan,
+    tower_http::trace::DefaultOnRequest,
+    RouteOnResponse,
+> {
+    let levels = Arc::new(RouteLogLevels::new(config));
+
+    TraceLayer::new_for_http()
+        .make_span_with(RouteMakeSpan { levels })
+        .on_response(RouteOnResponse)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn levels(route_levels: &str) -> RouteLogLevels {
+        RouteLogLevels::new(&LoggingConfig {
+            request_level: Some(Level::DEBUG),
+            route_levels: parse_route_levels(route_levels).unwrap(),
+        })
+    }
+
+    #[test]
+    fn test_health_logs_below_chat() {
+        let levels = levels("/health=info,/chat=trace");
+
+        let health = levels.level_for("/health/ready").unwrap();
+        let chat = levels.level_for("/chat/conversations").unwrap();
+
+        // `tracing` orders more verbose levels as greater
+        assert!(health < chat);
+    }
+
+    #[test]
+    fn test_disabled_route_and_default() {
+        let levels = levels("/health=off");
+
+        assert_eq!(levels.level_for("/health"), None);
+        assert_eq!(levels.level_for("/healthz"), Some(Level::DEBUG));
+        assert_eq!(levels.level_for("/auth/signin"), Some(Level::DEBUG));
+    }
+
+    #[test]
+    fn test_longest_prefix_wins() {
+        let levels = levels("/admin=warn,/admin/resources/=trace");
+
+        assert_eq!(levels.level_for("/admin/users"), Some(Level::WARN));
+        assert_eq!(levels.level_for("/admin/resources/abc"), Some(Level::TRACE));
+    }
+
+    #[test]
+    fn test_invalid_entries_rejected() {
+        assert!(parse_route_levels("/chat").is_err());
+        assert!(parse_route_levels("/chat=loud").is_err());
+        assert!(parse_route_levels("").unwrap().is_empty());
+    }
+}
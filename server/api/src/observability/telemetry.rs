@@ -0,0 +1,123 @@
+//! OpenTelemetry trace export and W3C trace context propagation
+//!
+//! When `OTEL_EXPORTER_OTLP_ENDPOINT` is set, `tracing` spans are exported
+//! over OTLP/gRPC. Incoming `traceparent` headers become the parent of the
+//! HTTP request span, and the current span's context is injected into
+//! Intelligence gRPC metadata, so a request is traced end to end.
+
+use axum::http::HeaderMap;
+use opentelemetry::{
+    Context, global,
+    propagation::{Extractor, Injector},
+    trace::TracerProvider,
+};
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::{
+    Resource, propagation::TraceContextPropagator, trace::SdkTracer, trace::SdkTracerProvider,
+};
+use tonic::metadata::{MetadataKey, MetadataMap, MetadataValue};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+use crate::config::env::TelemetryConfig;
+
+/// Build the OTLP tracer provider, or `None` when export is not configured
+///
+/// Also installs the W3C trace context propagator used by
+/// `parent_from_headers` and `inject_current_context`.
+pub fn init_tracer(
+    config: &TelemetryConfig,
+) -> Result<Option<(SdkTracerProvider, SdkTracer)>, Box<dyn std::error::Error>> {
+    let Some(endpoint) = &config.otlp_endpoint else {
+        return Ok(None);
+    };
+
+    let exporter = opentelemetry_otlp::SpanExporter::builder()
+        .with_tonic()
+        .with_endpoint(endpoint)
+        .build()?;
+
+    let provider = SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(
+            Resource::builder()
+                .with_service_name(config.service_name.clone())
+                .build(),
+        )
+        .build();
+    let tracer = provider.tracer("api");
+
+    global::set_text_map_propagator(TraceContextPropagator::new());
+    global::set_tracer_provider(provider.clone());
+
+    Ok(Some((provider, tracer)))
+}
+
+/// Trace context sent by the caller, if any
+pub fn parent_from_headers(headers: &HeaderMap) -> Context {
+    global::get_text_map_propagator(|propagator| propagator.extract(&HeaderExtractor(headers)))
+}
+
+/// Add the current span's trace context to outgoing gRPC metadata
+pub fn inject_current_context(metadata: &mut MetadataMap) {
+    let context = tracing::Span::current().context();
+    global::get_text_map_propagator(|propagator| {
+        propagator.inject_context(&context, &mut MetadataInjector(metadata))
+    });
+}
+
+struct HeaderExtractor<'a>(&'a HeaderMap);
+
+impl Extractor for HeaderExtractor<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|value| value.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(|key| key.as_str()).collect()
+    }
+}
+
+struct MetadataInjector<'a>(&'a mut MetadataMap);
+
+impl Injector for MetadataInjector<'_> {
+    fn set(&mut self, key: &str, value: String) {
+        if let (Ok(key), Ok(value)) = (
+            MetadataKey::from_bytes(key.as_bytes()),
+            MetadataValue::try_from(value.as_str()),
+        ) {
+            self.0.insert(key, value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use opentelemetry::propagation::TextMapPropagator;
+    use opentelemetry::trace::TraceContextExt;
+
+    const TRACEPARENT: &str = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+
+    #[test]
+    fn test_trace_context_passes_from_headers_to_metadata() {
+        let propagator = TraceContextPropagator::new();
+
+        let mut headers = HeaderMap::new();
+        headers.insert("traceparent", TRACEPARENT.parse().unwrap());
+        let context = propagator.extract(&HeaderExtractor(&headers));
+        assert!(context.span().span_context().is_remote());
+
+        let mut metadata = MetadataMap::new();
+        propagator.inject_context(&context, &mut MetadataInjector(&mut metadata));
+        assert_eq!(metadata.get("traceparent").unwrap(), TRACEPARENT);
+    }
+
+    #[test]
+    fn test_disabled_without_endpoint() {
+        let config = TelemetryConfig {
+            otlp_endpoint: None,
+            service_name: "api".to_string(),
+        };
+        assert!(init_tracer(&config).unwrap().is_none());
+    }
+}
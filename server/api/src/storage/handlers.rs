@@ -0,0 +1,59 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::header,
+    response::IntoResponse,
+};
+use serde::Deserialize;
+
+use super::{StorageBackend, StorageError, local};
+use crate::gateway::AppState;
+
+#[derive(Debug, Deserialize)]
+pub struct PresignedQuery {
+    pub expires: i64,
+    pub signature: String,
+}
+
+/// GET /storage/{*key}
+/// Download an object through a presigned URL (local backend only)
+pub async fn download_presigned(
+    State(state): State<AppState>,
+    Path(key): Path<String>,
+    Query(query): Query<PresignedQuery>,
+) -> Result<impl IntoResponse, StorageError> {
+    let config = &state.config.storage;
+
+    // S3 presigned URLs point at the bucket, never at this route
+    if config.backend != StorageBackend::Local {
+        return Err(StorageError::NotFound(key));
+    }
+
+    if !local::verify_signature(&config.signing_secret, &key, query.expires, &query.signature) {
+        return Err(StorageError::InvalidSignature);
+    }
+
+    let data = state.storage.get(&key).await?;
+
+    Ok((
+        [(header::CONTENT_TYPE, "application/octet-stream")],
+        data,
+    ))
+}
+
+/// GET /avatars/{file}
+/// Serve an uploaded avatar; file names contain a hash of the image, so a
+/// given URL never changes and can be cached indefinitely
+pub async fn download_avatar(
+    State(state): State<AppState>,
+    Path(file): Path<String>,
+) -> Result<impl IntoResponse, StorageError> {
+    let data = state.storage.get(&format!("avatars/{}", file)).await?;
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "image/png"),
+            (header::CACHE_CONTROL, "public, max-age=31536000, immutable"),
+        ],
+        data,
+    ))
+}
@@ -0,0 +1,211 @@
+//! Local disk storage
+//!
+//! Objects live under `STORAGE_LOCAL_ROOT`. Presigned URLs point at the API's
+//! `/storage/{key}` route and carry an HMAC over the key and expiry.
+
+use std::time::Duration;
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use object_store::{ObjectStore, local::LocalFileSystem};
+use sha2::Sha256;
+
+use super::{Storage, StorageError, object_path};
+use crate::config::env::StorageConfig;
+
+type HmacSha256 = Hmac<Sha256>;
+
+pub struct LocalStorage {
+    store: LocalFileSystem,
+    public_url: String,
+    signing_secret: String,
+}
+
+impl LocalStorage {
+    pub fn new(config: &StorageConfig) -> Result<Self, StorageError> {
+        std::fs::create_dir_all(&config.local_root).map_err(|e| {
+            StorageError::Config(format!(
+                "cannot create storage root {}: {}",
+                config.local_root, e
+            ))
+        })?;
+
+        let store = LocalFileSystem::new_with_prefix(&config.local_root)?;
+
+        Ok(Self {
+            store,
+            public_url: config.public_url.trim_end_matches('/').to_string(),
+            signing_secret: config.signing_secret.clone(),
+        })
+    }
+}
+
+#[async_trait]
+impl Storage for LocalStorage {
+    /// The content type is not persisted on disk
+    async fn put(
+        &self,
+        key: &str,
+        data: Bytes,
+        _content_type: Option<&str>,
+    ) -> Result<(), StorageError> {
+        self.store.put(&object_path(key)?, data.into()).await?;
+        Ok(())
+    }
+
+    async fn get(&self, key: &str) -> Result<Bytes, StorageError> {
+        let result = self.store.get(&object_path(key)?).await?;
+        Ok(result.bytes().await?)
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), StorageError> {
+        match self.store.delete(&object_path(key)?).await {
+            Ok(()) | Err(object_store::Error::NotFound { .. }) => Ok(()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn presign(&self, key: &str, expires_in: Duration) -> Result<String, StorageError> {
+        let path = object_path(key)?;
+        let expires = Utc::now().timestamp() + expires_in.as_secs() as i64;
+        let signature = sign(&self.signing_secret, path.as_ref(), expires);
+
+        Ok(format!(
+            "{}/storage/{}?expires={}&signature={}",
+            self.public_url, path, expires, signature
+        ))
+    }
+}
+
+/// Hex-encoded HMAC-SHA256 over `key:expires`
+fn sign(secret: &str, key: &str, expires: i64) -> String {
+    let mut mac =
+        HmacSha256::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(format!("{}:{}", key, expires).as_bytes());
+    hex::encode(mac.finalize().into_bytes())
+}
+
+/// Check a presigned URL's signature and expiry
+pub fn verify_signature(secret: &str, key: &str, expires: i64, signature: &str) -> bool {
+    if expires < Utc::now().timestamp() {
+        return false;
+    }
+
+    let Ok(signature) = hex::decode(signature) else {
+        return false;
+    };
+
+    let mut mac =
+        HmacSha256::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(format!("{}:{}", key, expires).as_bytes());
+    mac.verify_slice(&signature).is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::{self, StorageBackend};
+
+    const SECRET: &str = "test-signing-secret";
+
+    fn config() -> StorageConfig {
+        let root = std::env::temp_dir().join(format!("opentier-storage-{}", uuid::Uuid::new_v4()));
+        StorageConfig {
+            backend: StorageBackend::Local,
+            local_root: root.to_string_lossy().into_owned(),
+            public_url: "http://localhost:4000/".to_string(),
+            signing_secret: SECRET.to_string(),
+            s3_bucket: None,
+            s3_region: None,
+            s3_endpoint: None,
+        }
+    }
+
+    /// Parse `(key, expires, signature)` back out of a presigned URL
+    fn parse_presigned(url: &str) -> (String, i64, String) {
+        let rest = url.strip_prefix("http://localhost:4000/storage/").unwrap();
+        let (key, query) = rest.split_once('?').unwrap();
+        let params: std::collections::HashMap<_, _> = query
+            .split('&')
+            .filter_map(|pair| pair.split_once('='))
+            .collect();
+        (
+            key.to_string(),
+            params["expires"].parse().unwrap(),
+            params["signature"].to_string(),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_put_get_delete_roundtrip() {
+        let config = config();
+        let storage = LocalStorage::new(&config).unwrap();
+        let data = Bytes::from_static(b"avatar bytes");
+
+        storage
+            .put("avatars/user.png", data.clone(), Some("image/png"))
+            .await
+            .unwrap();
+        assert_eq!(storage.get("avatars/user.png").await.unwrap(), data);
+
+        storage.delete("avatars/user.png").await.unwrap();
+        assert!(matches!(
+            storage.get("avatars/user.png").await,
+            Err(StorageError::NotFound(_))
+        ));
+
+        // Deleting again is a no-op
+        storage.delete("avatars/user.png").await.unwrap();
+
+        std::fs::remove_dir_all(&config.local_root).ok();
+    }
+
+    #[tokio::test]
+    async fn test_presign_produces_verifiable_url() {
+        let config = config();
+        let storage = LocalStorage::new(&config).unwrap();
+
+        let url = storage
+            .presign("exports/report.json", Duration::from_secs(60))
+            .await
+            .unwrap();
+        let (key, expires, signature) = parse_presigned(&url);
+
+        assert_eq!(key, "exports/report.json");
+        assert!(verify_signature(SECRET, &key, expires, &signature));
+        assert!(!verify_signature(SECRET, "exports/other.json", expires, &signature));
+        assert!(!verify_signature(SECRET, &key, expires + 1, &signature));
+        assert!(!verify_signature("wrong-secret", &key, expires, &signature));
+
+        std::fs::remove_dir_all(&config.local_root).ok();
+    }
+
+    #[test]
+    fn test_expired_signature_rejected() {
+        let expires = Utc::now().timestamp() - 1;
+        let signature = sign(SECRET, "exports/report.json", expires);
+
+        assert!(!verify_signature(SECRET, "exports/report.json", expires, &signature));
+    }
+
+    #[tokio::test]
+    async fn test_from_config_selects_backend() {
+        let mut config = config();
+        let storage = storage::from_config(&config).unwrap();
+        storage
+            .put("a.txt", Bytes::from_static(b"a"), None)
+            .await
+            .unwrap();
+        assert!(std::path::Path::new(&config.local_root).join("a.txt").exists());
+        std::fs::remove_dir_all(&config.local_root).ok();
+
+        // S3 requires a bucket
+        config.backend = StorageBackend::S3;
+        assert!(matches!(
+            storage::from_config(&config),
+            Err(StorageError::Config(_))
+        ));
+    }
+}
@@ -0,0 +1,160 @@
+//! Object storage shared by avatars, attachments and exports
+//!
+//! Features talk to the `Storage` trait only; the backend (local disk or S3)
+//! is chosen once at startup from `StorageConfig`.
+
+pub mod handlers;
+pub mod local;
+pub mod s3;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use axum::{
+    Json,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use bytes::Bytes;
+use serde_json::json;
+use thiserror::Error;
+
+use crate::config::env::StorageConfig;
+use local::LocalStorage;
+use s3::S3Storage;
+
+/// Storage backend selected by `STORAGE_BACKEND`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StorageBackend {
+    #[default]
+    Local,
+    S3,
+}
+
+impl StorageBackend {
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "local" | "disk" => Some(StorageBackend::Local),
+            "s3" => Some(StorageBackend::S3),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum StorageError {
+    #[error("Object not found: {0}")]
+    NotFound(String),
+
+    #[error("Invalid object key: {0}")]
+    InvalidKey(String),
+
+    #[error("Invalid or expired link")]
+    InvalidSignature,
+
+    #[error("Storage misconfigured: {0}")]
+    Config(String),
+
+    #[error("Storage backend error: {0}")]
+    Backend(String),
+}
+
+impl IntoResponse for StorageError {
+    fn into_response(self) -> Response {
+        let (status, message) = match &self {
+            StorageError::NotFound(_) => (StatusCode::NOT_FOUND, "Object not found".to_string()),
+            StorageError::InvalidKey(_) => (StatusCode::BAD_REQUEST, self.to_string()),
+            StorageError::InvalidSignature => (StatusCode::FORBIDDEN, self.to_string()),
+            StorageError::Config(_) | StorageError::Backend(_) => {
+                tracing::error!("Storage error: {}", self);
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "Storage error".to_string(),
+                )
+            }
+        };
+
+        let body = Json(json!({
+            "error": message,
+            "message": message,
+        }));
+
+        (status, body).into_response()
+    }
+}
+
+impl From<object_store::Error> for StorageError {
+    fn from(e: object_store::Error) -> Self {
+        match e {
+            object_store::Error::NotFound { path, .. } => StorageError::NotFound(path),
+            e => StorageError::Backend(e.to_string()),
+        }
+    }
+}
+
+/// Object storage operations
+///
+/// Keys are `/`-separated relative paths such as `avatars/<user_id>.png`.
+#[async_trait]
+pub trait Storage: Send + Sync {
+    /// Store an object, replacing any existing object with the same key
+    async fn put(
+        &self,
+        key: &str,
+        data: Bytes,
+        content_type: Option<&str>,
+    ) -> Result<(), StorageError>;
+
+    /// Read an object
+    async fn get(&self, key: &str) -> Result<Bytes, StorageError>;
+
+    /// Delete an object; deleting a missing object is not an error
+    async fn delete(&self, key: &str) -> Result<(), StorageError>;
+
+    /// Create a time-limited URL that downloads the object without credentials
+    async fn presign(&self, key: &str, expires_in: Duration) -> Result<String, StorageError>;
+}
+
+/// Storage handle shared through `AppState`
+pub type SharedStorage = Arc<dyn Storage>;
+
+/// Build the configured storage backend
+pub fn from_config(config: &StorageConfig) -> Result<SharedStorage, StorageError> {
+    match config.backend {
+        StorageBackend::Local => Ok(Arc::new(LocalStorage::new(config)?)),
+        StorageBackend::S3 => Ok(Arc::new(S3Storage::new(config)?)),
+    }
+}
+
+/// Validate a key and convert it to an object store path
+///
+/// Rejects empty keys and `.`/`..` segments so keys cannot escape the root.
+fn object_path(key: &str) -> Result<object_store::path::Path, StorageError> {
+    if key.trim_matches('/').is_empty() {
+        return Err(StorageError::InvalidKey("key must not be empty".to_string()));
+    }
+
+    object_store::path::Path::parse(key).map_err(|e| StorageError::InvalidKey(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_object_path_rejects_traversal() {
+        assert!(object_path("avatars/user.png").is_ok());
+        assert!(object_path("").is_err());
+        assert!(object_path("/").is_err());
+        assert!(object_path("../secrets").is_err());
+        assert!(object_path("avatars/../../etc/passwd").is_err());
+    }
+
+    #[test]
+    fn test_backend_from_str() {
+        assert_eq!(StorageBackend::from_str("LOCAL"), Some(StorageBackend::Local));
+        assert_eq!(StorageBackend::from_str("s3"), Some(StorageBackend::S3));
+        assert_eq!(StorageBackend::from_str("gcs"), None);
+    }
+}
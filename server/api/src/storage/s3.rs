@@ -0,0 +1,92 @@
+//! S3 (and S3-compatible) storage
+//!
+//! Credentials come from the standard `AWS_*` environment variables or the
+//! instance role; bucket, region and endpoint come from `StorageConfig`.
+
+use std::time::Duration;
+
+use async_trait::async_trait;
+use axum::http::Method;
+use bytes::Bytes;
+use object_store::{
+    Attribute, Attributes, ObjectStore, PutOptions,
+    aws::{AmazonS3, AmazonS3Builder},
+    signer::Signer,
+};
+
+use super::{Storage, StorageError, object_path};
+use crate::config::env::StorageConfig;
+
+pub struct S3Storage {
+    store: AmazonS3,
+}
+
+impl S3Storage {
+    pub fn new(config: &StorageConfig) -> Result<Self, StorageError> {
+        let bucket = config
+            .s3_bucket
+            .as_deref()
+            .ok_or_else(|| StorageError::Config("S3_BUCKET is required for S3 storage".into()))?;
+
+        let mut builder = AmazonS3Builder::from_env().with_bucket_name(bucket);
+        if let Some(region) = &config.s3_region {
+            builder = builder.with_region(region);
+        }
+        if let Some(endpoint) = &config.s3_endpoint {
+            // S3-compatible services (MinIO, R2, ...) usually need path-style URLs
+            builder = builder
+                .with_endpoint(endpoint)
+                .with_virtual_hosted_style_request(false)
+                .with_allow_http(endpoint.starts_with("http://"));
+        }
+
+        Ok(Self {
+            store: builder
+                .build()
+                .map_err(|e| StorageError::Config(e.to_string()))?,
+        })
+    }
+}
+
+#[async_trait]
+impl Storage for S3Storage {
+    async fn put(
+        &self,
+        key: &str,
+        data: Bytes,
+        content_type: Option<&str>,
+    ) -> Result<(), StorageError> {
+        let mut attributes = Attributes::new();
+        if let Some(content_type) = content_type {
+            attributes.insert(Attribute::ContentType, content_type.to_string().into());
+        }
+
+        let options = PutOptions {
+            attributes,
+            ..Default::default()
+        };
+        self.store
+            .put_opts(&object_path(key)?, data.into(), options)
+            .await?;
+        Ok(())
+    }
+
+    async fn get(&self, key: &str) -> Result<Bytes, StorageError> {
+        let result = self.store.get(&object_path(key)?).await?;
+        Ok(result.bytes().await?)
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), StorageError> {
+        // S3 deletes are idempotent
+        self.store.delete(&object_path(key)?).await?;
+        Ok(())
+    }
+
+    async fn presign(&self, key: &str, expires_in: Duration) -> Result<String, StorageError> {
+        let url = self
+            .store
+            .signed_url(Method::GET, &object_path(key)?, expires_in)
+            .await?;
+        Ok(url.to_string())
+    }
+}
@@ -0,0 +1,98 @@
+//! Last-seen tracking
+//!
+//! `auth_middleware` reports every authenticated request here, and
+//! `users.last_active_at` is written at most once per `ACTIVITY_INTERVAL` per
+//! user. Recent writes are remembered in memory so most requests skip the
+//! database entirely; the UPDATE repeats the check so several instances don't
+//! all write for the same user.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::middleware::request_id;
+
+/// Minimum time between two writes for the same user
+pub const ACTIVITY_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+/// Remembered users beyond which stale entries are dropped
+const PRUNE_THRESHOLD: usize = 10_000;
+
+#[derive(Clone, Default)]
+pub struct ActivityTracker {
+    last_recorded: Arc<Mutex<HashMap<Uuid, Instant>>>,
+}
+
+impl ActivityTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record that the user made a request, in the background
+    pub fn touch(&self, db: &PgPool, user_id: Uuid) {
+        if !self.should_record(user_id, Instant::now()) {
+            return;
+        }
+
+        let db = db.clone();
+        request_id::spawn(async move {
+            let result = sqlx::query!(
+                r#"
+                UPDATE users
+                SET last_active_at = NOW()
+                WHERE id = $1
+                  AND (last_active_at IS NULL
+                       OR last_active_at < NOW() - make_interval(secs => $2))
+                "#,
+                user_id,
+                ACTIVITY_INTERVAL.as_secs_f64()
+            )
+            .execute(&db)
+            .await;
+
+            if let Err(e) = result {
+                tracing::warn!(%user_id, "Failed to record user activity: {}", e);
+            }
+        });
+    }
+
+    /// Whether enough time has passed since this instance last wrote for the user
+    fn should_record(&self, user_id: Uuid, now: Instant) -> bool {
+        let mut last_recorded = self
+            .last_recorded
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        if let Some(last) = last_recorded.get(&user_id)
+            && now.duration_since(*last) < ACTIVITY_INTERVAL
+        {
+            return false;
+        }
+
+        if last_recorded.len() >= PRUNE_THRESHOLD {
+            last_recorded.retain(|_, last| now.duration_since(*last) < ACTIVITY_INTERVAL);
+        }
+        last_recorded.insert(user_id, now);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_records_once_per_interval() {
+        let tracker = ActivityTracker::new();
+        let user_id = Uuid::new_v4();
+        let start = Instant::now();
+
+        assert!(tracker.should_record(user_id, start));
+        assert!(!tracker.should_record(user_id, start + Duration::from_secs(60)));
+        assert!(tracker.should_record(Uuid::new_v4(), start));
+        assert!(tracker.should_record(user_id, start + ACTIVITY_INTERVAL));
+    }
+}
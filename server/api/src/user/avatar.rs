@@ -0,0 +1,152 @@
+//! Avatar uploads
+//!
+//! Uploaded images are checked by their magic bytes, cropped and resized to
+//! `AVATAR_SIZE` square, re-encoded as PNG and stored under `avatars/` in the
+//! configured object storage. Keys include a hash of the image, so each upload
+//! gets a new URL and the old object is removed once nothing points at it.
+
+use std::io::Cursor;
+
+use image::{ImageFormat, ImageReader, Limits, imageops::FilterType};
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+use super::UserError;
+use crate::middleware::request_id;
+use crate::storage::SharedStorage;
+
+/// Largest accepted upload
+pub const MAX_AVATAR_BYTES: usize = 5 * 1024 * 1024;
+
+/// Width and height of stored avatars
+const AVATAR_SIZE: u32 = 256;
+
+/// Larger images are rejected before decoding
+const MAX_SOURCE_DIMENSION: u32 = 8192;
+
+const ALLOWED_TYPES: &[&str] = &["image/jpeg", "image/png", "image/webp"];
+
+/// Storage prefix, also the public path avatars are served from
+const AVATAR_PREFIX: &str = "avatars";
+
+/// Validate an uploaded image and turn it into a square PNG avatar
+pub fn process_avatar(data: &[u8]) -> Result<Vec<u8>, UserError> {
+    let mime = infer::get(data)
+        .map(|kind| kind.mime_type())
+        .filter(|mime| ALLOWED_TYPES.contains(mime))
+        .ok_or_else(|| {
+            UserError::Validation("Avatar must be a JPEG, PNG or WEBP image".to_string())
+        })?;
+    let format = ImageFormat::from_mime_type(mime).ok_or(UserError::Internal)?;
+
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(MAX_SOURCE_DIMENSION);
+    limits.max_image_height = Some(MAX_SOURCE_DIMENSION);
+
+    let mut reader = ImageReader::with_format(Cursor::new(data), format);
+    reader.limits(limits);
+    let image = reader
+        .decode()
+        .map_err(|e| UserError::Validation(format!("Invalid image: {}", e)))?;
+
+    let avatar = image.resize_to_fill(AVATAR_SIZE, AVATAR_SIZE, FilterType::Lanczos3);
+    let mut png = Vec::new();
+    avatar
+        .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+        .map_err(|e| {
+            tracing::error!("Failed to encode avatar: {}", e);
+            UserError::Internal
+        })?;
+
+    Ok(png)
+}
+
+/// Storage key for a processed avatar
+pub fn avatar_key(user_id: Uuid, png: &[u8]) -> String {
+    let hash = hex::encode(Sha256::digest(png));
+    format!("{}/{}-{}.png", AVATAR_PREFIX, user_id, &hash[..16])
+}
+
+/// Public URL an avatar is served from
+pub fn avatar_url(api_url: &str, key: &str) -> String {
+    format!("{}/{}", api_url.trim_end_matches('/'), key)
+}
+
+/// Storage key behind an avatar URL, if the URL points at an uploaded avatar
+pub fn key_from_url(api_url: &str, url: &str) -> Option<String> {
+    let prefix = format!("{}/{}/", api_url.trim_end_matches('/'), AVATAR_PREFIX);
+    let file = url.strip_prefix(&prefix)?;
+    (!file.is_empty() && !file.contains('/')).then(|| format!("{}/{}", AVATAR_PREFIX, file))
+}
+
+/// Delete the previous avatar in the background once it has been replaced
+pub fn remove_replaced_avatar(
+    storage: SharedStorage,
+    api_url: &str,
+    previous: Option<&str>,
+    current: Option<&str>,
+) {
+    let Some(key) = previous.and_then(|url| key_from_url(api_url, url)) else {
+        return;
+    };
+    if current.and_then(|url| key_from_url(api_url, url)).as_ref() == Some(&key) {
+        return;
+    }
+
+    request_id::spawn(async move {
+        if let Err(e) = storage.delete(&key).await {
+            tracing::warn!("Failed to delete replaced avatar {}: {}", key, e);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{DynamicImage, GenericImageView, RgbImage};
+
+    use super::*;
+
+    fn encode(width: u32, height: u32, format: ImageFormat) -> Vec<u8> {
+        let mut data = Vec::new();
+        DynamicImage::ImageRgb8(RgbImage::new(width, height))
+            .write_to(&mut Cursor::new(&mut data), format)
+            .unwrap();
+        data
+    }
+
+    #[test]
+    fn test_avatar_resized_to_square_png() {
+        for format in [ImageFormat::Png, ImageFormat::Jpeg, ImageFormat::WebP] {
+            let png = process_avatar(&encode(640, 480, format)).unwrap();
+            let avatar = image::load_from_memory_with_format(&png, ImageFormat::Png).unwrap();
+            assert_eq!(avatar.dimensions(), (AVATAR_SIZE, AVATAR_SIZE));
+        }
+    }
+
+    #[test]
+    fn test_non_images_rejected() {
+        assert!(process_avatar(b"<svg xmlns=\"http://www.w3.org/2000/svg\"/>").is_err());
+        assert!(process_avatar(b"GIF89a\x01\x00\x01\x00\x00\x00\x00;").is_err());
+        // PNG magic bytes followed by garbage
+        assert!(process_avatar(b"\x89PNG\r\n\x1a\nnot really a png").is_err());
+    }
+
+    #[test]
+    fn test_key_from_url() {
+        let api = "http://localhost:8080/";
+        let user_id = Uuid::nil();
+        let key = avatar_key(user_id, b"png");
+        let url = avatar_url(api, &key);
+
+        assert_eq!(key_from_url(api, &url), Some(key));
+        assert_eq!(
+            key_from_url(api, "https://cdn.example.com/avatars/a.png"),
+            None
+        );
+        assert_eq!(key_from_url(api, "http://localhost:8080/avatars/"), None);
+        assert_eq!(
+            key_from_url(api, "http://localhost:8080/avatars/a/b.png"),
+            None
+        );
+    }
+}
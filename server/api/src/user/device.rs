@@ -0,0 +1,97 @@
+//! Human-readable device labels for the session list
+//!
+//! Only the common browsers and operating systems are recognised; anything
+//! else is shown as "Unknown device" rather than the raw user agent.
+
+const UNKNOWN_DEVICE: &str = "Unknown device";
+
+/// Browser markers, most specific first (Edge and Opera also claim Chrome,
+/// and Chrome claims Safari)
+const BROWSERS: &[(&str, &str)] = &[
+    ("Edg/", "Edge"),
+    ("OPR/", "Opera"),
+    ("SamsungBrowser/", "Samsung Internet"),
+    ("Firefox/", "Firefox"),
+    ("FxiOS/", "Firefox"),
+    ("CriOS/", "Chrome"),
+    ("Chrome/", "Chrome"),
+    ("Safari/", "Safari"),
+    ("curl/", "curl"),
+];
+
+/// OS markers, most specific first (Android and ChromeOS also say Linux,
+/// and iOS says "like Mac OS X")
+const OPERATING_SYSTEMS: &[(&str, &str)] = &[
+    ("iPhone", "iOS"),
+    ("iPad", "iPadOS"),
+    ("Android", "Android"),
+    ("CrOS", "ChromeOS"),
+    ("Windows", "Windows"),
+    ("Macintosh", "macOS"),
+    ("Linux", "Linux"),
+];
+
+fn find(user_agent: &str, markers: &[(&str, &'static str)]) -> Option<&'static str> {
+    markers
+        .iter()
+        .find(|(marker, _)| user_agent.contains(marker))
+        .map(|(_, name)| *name)
+}
+
+/// "Browser on OS" from a user agent, e.g. "Chrome on macOS"
+pub fn device_label(user_agent: Option<&str>) -> String {
+    let Some(user_agent) = user_agent else {
+        return UNKNOWN_DEVICE.to_string();
+    };
+
+    match (
+        find(user_agent, BROWSERS),
+        find(user_agent, OPERATING_SYSTEMS),
+    ) {
+        (Some(browser), Some(os)) => format!("{} on {}", browser, os),
+        (Some(name), None) | (None, Some(name)) => name.to_string(),
+        (None, None) => UNKNOWN_DEVICE.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_common_user_agents() {
+        let cases = [
+            (
+                "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36",
+                "Chrome on macOS",
+            ),
+            (
+                "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36 Edg/120.0.0.0",
+                "Edge on Windows",
+            ),
+            (
+                "Mozilla/5.0 (iPhone; CPU iPhone OS 17_0 like Mac OS X) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/17.0 Mobile/15E148 Safari/604.1",
+                "Safari on iOS",
+            ),
+            (
+                "Mozilla/5.0 (X11; Linux x86_64; rv:121.0) Gecko/20100101 Firefox/121.0",
+                "Firefox on Linux",
+            ),
+            (
+                "Mozilla/5.0 (Linux; Android 14; Pixel 8) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Mobile Safari/537.36",
+                "Chrome on Android",
+            ),
+            ("curl/8.4.0", "curl"),
+        ];
+
+        for (user_agent, expected) in cases {
+            assert_eq!(device_label(Some(user_agent)), expected, "{}", user_agent);
+        }
+    }
+
+    #[test]
+    fn test_unknown_user_agents() {
+        assert_eq!(device_label(None), "Unknown device");
+        assert_eq!(device_label(Some("my-script/1.0")), "Unknown device");
+    }
+}
@@ -1,10 +1,14 @@
 use axum::{
-    http::StatusCode,
+    http::{header, HeaderValue, StatusCode},
     response::{IntoResponse, Response},
     Json,
 };
 use serde_json::json;
 
+use crate::common::validation::ValidationErrors;
+use crate::config::database;
+use crate::user::reauth::ConfirmationFailure;
+
 #[derive(Debug, thiserror::Error)]
 pub enum UserError {
     #[allow(dead_code)] // Reserved for future use
@@ -20,9 +24,42 @@ pub enum UserError {
     #[error("Invalid current password")]
     InvalidCurrentPassword,
 
+    /// `change-password` on an account that only signs in through OAuth
+    #[error("No password set")]
+    NoPasswordSet,
+
+    /// `set-password` on an account that already has one
+    #[error("Password already set")]
+    PasswordAlreadySet,
+
+    /// Sensitive action without a password or recent sign-in, reported as 403
+    #[error("Confirmation failed: {}", .0.code())]
+    ConfirmationFailed(ConfirmationFailure),
+
+    /// Account credentials changed through an admin's impersonation session
+    #[error("Not allowed while impersonating")]
+    ImpersonationForbidden,
+
     #[error("Session not found")]
     SessionNotFound,
 
+    #[error("Email already in use")]
+    EmailAlreadyInUse,
+
+    #[error("Validation error: {0}")]
+    Validation(String),
+
+    /// Per-field failures, reported as 422 `validation_failed`
+    #[error("Validation failed: {0}")]
+    ValidationFields(ValidationErrors),
+
+    #[error("Avatar too large")]
+    AvatarTooLarge,
+
+    /// Seconds until the next data export is allowed
+    #[error("Data export requested too recently")]
+    ExportCooldown(u64),
+
     #[error("Database error: {0}")]
     Database(#[from] sqlx::Error),
 
@@ -32,14 +69,72 @@ pub enum UserError {
 
 impl IntoResponse for UserError {
     fn into_response(self) -> Response {
-        let (status, message) = match self {
+        if let UserError::Database(e) = &self
+            && database::is_pool_exhausted(e)
+        {
+            return database::pool_exhausted_response();
+        }
+
+        if let UserError::ValidationFields(errors) = self {
+            return errors.into_response();
+        }
+
+        if let UserError::ConfirmationFailed(failure) = self {
+            let body = Json(json!({
+                "error": failure.code(),
+                "message": failure.message(),
+            }));
+            return (StatusCode::FORBIDDEN, body).into_response();
+        }
+
+        if let UserError::ImpersonationForbidden = self {
+            let body = Json(json!({
+                "error": "impersonation_forbidden",
+                "message": "This action is not allowed while impersonating the user",
+            }));
+            return (StatusCode::FORBIDDEN, body).into_response();
+        }
+
+        if let UserError::ExportCooldown(retry_after) = self {
+            let body = Json(json!({
+                "error": "export_cooldown",
+                "message": "A data export was requested recently, please try again later",
+                "retry_after": retry_after,
+            }));
+            let mut response = (StatusCode::TOO_MANY_REQUESTS, body).into_response();
+            response
+                .headers_mut()
+                .insert(header::RETRY_AFTER, HeaderValue::from(retry_after));
+            return response;
+        }
+
+        let (status, message) = match &self {
             UserError::NotFound => (StatusCode::NOT_FOUND, "User not found"),
             UserError::Unauthorized => (StatusCode::UNAUTHORIZED, "Unauthorized"),
             UserError::UsernameAlreadyTaken => (StatusCode::CONFLICT, "Username already taken"),
             UserError::InvalidCurrentPassword => {
                 (StatusCode::UNAUTHORIZED, "Invalid current password")
             }
+            UserError::NoPasswordSet => (
+                StatusCode::BAD_REQUEST,
+                "No password set, use /user/set-password to add one",
+            ),
+            UserError::PasswordAlreadySet => (
+                StatusCode::CONFLICT,
+                "Password already set, use /user/change-password to change it",
+            ),
             UserError::SessionNotFound => (StatusCode::NOT_FOUND, "Session not found"),
+            UserError::EmailAlreadyInUse => (StatusCode::CONFLICT, "Email already in use"),
+            UserError::Validation(msg) => (StatusCode::BAD_REQUEST, msg.as_str()),
+            UserError::AvatarTooLarge => {
+                (StatusCode::PAYLOAD_TOO_LARGE, "Avatar must be at most 5MB")
+            }
+            UserError::ValidationFields(_)
+            | UserError::ConfirmationFailed(_)
+            | UserError::ImpersonationForbidden
+            | UserError::ExportCooldown(_) => {
+                unreachable!("handled above")
+            }
             UserError::Database(_) => (StatusCode::INTERNAL_SERVER_ERROR, "Database error"),
             UserError::Internal => (StatusCode::INTERNAL_SERVER_ERROR, "Internal server error"),
         };
@@ -52,3 +147,9 @@ impl IntoResponse for UserError {
         (status, body).into_response()
     }
 }
+
+impl From<ConfirmationFailure> for UserError {
+    fn from(failure: ConfirmationFailure) -> Self {
+        UserError::ConfirmationFailed(failure)
+    }
+}
@@ -0,0 +1,432 @@
+//! Personal data export
+//!
+//! `POST /user/export-data` (or `GET`) returns a ZIP with one JSON file per
+//! kind of data the user owns. Secrets (password hash, session tokens, OAuth
+//! tokens) are left out. API keys are not stored by this service, so there
+//! is no file for them. Conversations, then uploaded resources, are added a
+//! page at a time until the uncompressed JSON reaches `MAX_EXPORT_BYTES`,
+//! which also bounds the archive; any that don't fit are left out and the
+//! export is marked truncated.
+
+use std::io::{Cursor, Write};
+
+use chrono::{DateTime, Duration, Utc};
+use serde_json::Value;
+use sqlx::PgPool;
+use uuid::Uuid;
+use zip::{CompressionMethod, ZipWriter, write::SimpleFileOptions};
+
+use super::UserError;
+
+/// Minimum time between two exports by the same user
+pub const EXPORT_COOLDOWN: Duration = Duration::minutes(15);
+
+/// Upper bound on the exported JSON, and so on the archive
+pub const MAX_EXPORT_BYTES: usize = 50 * 1024 * 1024;
+
+/// Conversations fetched per query
+const CONVERSATION_PAGE_SIZE: i64 = 100;
+
+/// Resources fetched per query; these carry full document text
+const RESOURCE_PAGE_SIZE: i64 = 50;
+
+/// A finished export
+pub struct DataExport {
+    pub archive: Vec<u8>,
+    /// Some conversations or resources were left out to stay under
+    /// `MAX_EXPORT_BYTES`
+    pub truncated: bool,
+}
+
+/// Seconds until another export is allowed, if the last one was too recent
+pub fn cooldown_remaining(last_requested: DateTime<Utc>, now: DateTime<Utc>) -> Option<u64> {
+    let remaining = last_requested + EXPORT_COOLDOWN - now;
+    (remaining > Duration::zero()).then(|| remaining.num_seconds().max(1) as u64)
+}
+
+/// Collect everything the user owns into a ZIP archive
+///
+/// A failed export doesn't count towards the cooldown, so it can be retried
+/// right away.
+pub async fn export_user_data(db: &PgPool, user_id: Uuid) -> Result<DataExport, UserError> {
+    let claimed_at = claim_export(db, user_id).await?;
+
+    let export = build_export(db, user_id).await;
+    if export.is_err() {
+        release_export(db, user_id, claimed_at).await;
+    }
+    export
+}
+
+async fn build_export(db: &PgPool, user_id: Uuid) -> Result<DataExport, UserError> {
+    let profile = sqlx::query_scalar!(
+        r#"SELECT to_jsonb(u) - 'password_hash' AS "row!" FROM users u WHERE id = $1"#,
+        user_id
+    )
+    .fetch_optional(db)
+    .await?
+    .ok_or(UserError::NotFound)?;
+
+    let sessions = sqlx::query_scalar!(
+        r#"
+        SELECT to_jsonb(s) - 'session_token' AS "row!"
+        FROM sessions s
+        WHERE user_id = $1
+        ORDER BY created_at
+        "#,
+        user_id
+    )
+    .fetch_all(db)
+    .await?;
+
+    let accounts = sqlx::query_scalar!(
+        r#"
+        SELECT to_jsonb(a) - 'access_token' - 'refresh_token' - 'id_token' AS "row!"
+        FROM accounts a
+        WHERE user_id = $1
+        ORDER BY created_at
+        "#,
+        user_id
+    )
+    .fetch_all(db)
+    .await?;
+
+    let audit_events = sqlx::query_scalar!(
+        r#"
+        SELECT to_jsonb(e) AS "row!"
+        FROM auth_events e
+        WHERE user_id = $1
+        ORDER BY created_at
+        "#,
+        user_id
+    )
+    .fetch_all(db)
+    .await?;
+
+    // The intelligence service keys memories by the user ID as text
+    let memories = sqlx::query_scalar!(
+        r#"
+        SELECT to_jsonb(m) AS "row!"
+        FROM user_memories m
+        WHERE user_id = $1
+        ORDER BY updated_at
+        "#,
+        user_id.to_string()
+    )
+    .fetch_all(db)
+    .await?;
+
+    let mut files = vec![
+        ("profile.json", profile.to_string().into_bytes()),
+        (
+            "sessions.json",
+            Value::Array(sessions).to_string().into_bytes(),
+        ),
+        (
+            "oauth_accounts.json",
+            Value::Array(accounts).to_string().into_bytes(),
+        ),
+        (
+            "audit_events.json",
+            Value::Array(audit_events).to_string().into_bytes(),
+        ),
+        (
+            "memories.json",
+            Value::Array(memories).to_string().into_bytes(),
+        ),
+    ];
+
+    let mut used: usize = files.iter().map(|(_, data)| data.len()).sum();
+    let mut conversations = BoundedJsonArray::new(MAX_EXPORT_BYTES.saturating_sub(used));
+    let mut truncated = !add_conversations(db, user_id, &mut conversations).await?;
+    let conversations = conversations.finish();
+    used += conversations.len();
+    files.push(("conversations.json", conversations));
+
+    let mut resources = BoundedJsonArray::new(MAX_EXPORT_BYTES.saturating_sub(used));
+    truncated |= !add_resources(db, user_id, &mut resources).await?;
+    files.push(("resources.json", resources.finish()));
+
+    let archive = tokio::task::spawn_blocking(move || build_archive(&files))
+        .await
+        .map_err(|e| e.to_string())
+        .and_then(|result| result.map_err(|e| e.to_string()))
+        .map_err(|e| {
+            tracing::error!("Failed to build data export for {}: {}", user_id, e);
+            UserError::Internal
+        })?;
+
+    Ok(DataExport { archive, truncated })
+}
+
+/// Record the export, or fail if the previous one was too recent
+///
+/// Returns the time the export was recorded at.
+async fn claim_export(db: &PgPool, user_id: Uuid) -> Result<DateTime<Utc>, UserError> {
+    let cooldown_seconds = EXPORT_COOLDOWN.num_seconds() as f64;
+
+    // The conditional upsert lets only one of two concurrent requests through
+    let claimed = sqlx::query_scalar!(
+        r#"
+        INSERT INTO data_exports (user_id)
+        VALUES ($1)
+        ON CONFLICT (user_id) DO UPDATE SET requested_at = NOW()
+        WHERE data_exports.requested_at <= NOW() - make_interval(secs => $2)
+        RETURNING requested_at
+        "#,
+        user_id,
+        cooldown_seconds
+    )
+    .fetch_optional(db)
+    .await?;
+
+    if let Some(claimed_at) = claimed {
+        return Ok(claimed_at);
+    }
+
+    let last_requested = sqlx::query_scalar!(
+        "SELECT requested_at FROM data_exports WHERE user_id = $1",
+        user_id
+    )
+    .fetch_one(db)
+    .await?;
+
+    let retry_after = cooldown_remaining(last_requested, Utc::now()).unwrap_or(1);
+    Err(UserError::ExportCooldown(retry_after))
+}
+
+/// Forget the claim made at `claimed_at`, unless a later export replaced it
+///
+/// Any earlier export is past the cooldown, so forgetting it changes nothing.
+async fn release_export(db: &PgPool, user_id: Uuid, claimed_at: DateTime<Utc>) {
+    let released = sqlx::query!(
+        "DELETE FROM data_exports WHERE user_id = $1 AND requested_at = $2",
+        user_id,
+        claimed_at
+    )
+    .execute(db)
+    .await;
+
+    if let Err(e) = released {
+        tracing::warn!("Failed to release data export claim for {}: {}", user_id, e);
+    }
+}
+
+/// Add conversations with their messages, returning `false` if some didn't fit
+async fn add_conversations(
+    db: &PgPool,
+    user_id: Uuid,
+    out: &mut BoundedJsonArray,
+) -> Result<bool, sqlx::Error> {
+    // Conversations store the user ID as text; the intelligence service
+    // writes their messages to chat_messages
+    let owner = user_id.to_string();
+    let mut offset = 0;
+
+    loop {
+        let page = sqlx::query_scalar!(
+            r#"
+            SELECT to_jsonb(c) || jsonb_build_object('messages', COALESCE(
+                (SELECT jsonb_agg(to_jsonb(m) ORDER BY m.created_at)
+                 FROM chat_messages m
+                 WHERE m.conversation_id = c.id),
+                '[]'::jsonb
+            )) AS "row!"
+            FROM conversations c
+            WHERE user_id = $1
+            ORDER BY created_at, id
+            LIMIT $2 OFFSET $3
+            "#,
+            owner,
+            CONVERSATION_PAGE_SIZE,
+            offset
+        )
+        .fetch_all(db)
+        .await?;
+
+        let last_page = (page.len() as i64) < CONVERSATION_PAGE_SIZE;
+        for conversation in &page {
+            if !out.push(conversation) {
+                return Ok(false);
+            }
+        }
+
+        if last_page {
+            return Ok(true);
+        }
+        offset += CONVERSATION_PAGE_SIZE;
+    }
+}
+
+/// Add documents the user uploaded, returning `false` if some didn't fit
+async fn add_resources(
+    db: &PgPool,
+    user_id: Uuid,
+    out: &mut BoundedJsonArray,
+) -> Result<bool, sqlx::Error> {
+    // Documents are written by the intelligence service, which stores the
+    // owner as text
+    let owner = user_id.to_string();
+    let mut offset = 0;
+
+    loop {
+        let page = sqlx::query_scalar!(
+            r#"
+            SELECT to_jsonb(d) AS "row!"
+            FROM documents d
+            WHERE user_id = $1
+            ORDER BY created_at, id
+            LIMIT $2 OFFSET $3
+            "#,
+            owner,
+            RESOURCE_PAGE_SIZE,
+            offset
+        )
+        .fetch_all(db)
+        .await?;
+
+        let last_page = (page.len() as i64) < RESOURCE_PAGE_SIZE;
+        for resource in &page {
+            if !out.push(resource) {
+                return Ok(false);
+            }
+        }
+
+        if last_page {
+            return Ok(true);
+        }
+        offset += RESOURCE_PAGE_SIZE;
+    }
+}
+
+/// A JSON array that stops accepting items once it would exceed `limit` bytes
+struct BoundedJsonArray {
+    buf: Vec<u8>,
+    limit: usize,
+}
+
+impl BoundedJsonArray {
+    fn new(limit: usize) -> Self {
+        Self {
+            buf: b"[".to_vec(),
+            limit,
+        }
+    }
+
+    /// Append an item, or return `false` if it doesn't fit
+    fn push(&mut self, item: &Value) -> bool {
+        let item = item.to_string();
+        let separator = usize::from(self.buf.len() > 1);
+        // Leave room for the closing bracket
+        if self.buf.len() + separator + item.len() + 1 > self.limit {
+            return false;
+        }
+
+        if separator == 1 {
+            self.buf.push(b',');
+        }
+        self.buf.extend_from_slice(item.as_bytes());
+        true
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        self.buf.push(b']');
+        self.buf
+    }
+}
+
+/// Deflate the files into an in-memory ZIP archive
+fn build_archive(files: &[(&str, Vec<u8>)]) -> zip::result::ZipResult<Vec<u8>> {
+    let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+
+    for (name, data) in files {
+        writer.start_file(*name, options)?;
+        writer.write_all(data)?;
+    }
+
+    Ok(writer.finish()?.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_cooldown_remaining() {
+        let now = Utc::now();
+
+        assert_eq!(cooldown_remaining(now, now), Some(900));
+        assert_eq!(
+            cooldown_remaining(now - Duration::minutes(14), now),
+            Some(60)
+        );
+        assert_eq!(cooldown_remaining(now - Duration::minutes(15), now), None);
+        assert_eq!(cooldown_remaining(now - Duration::hours(1), now), None);
+    }
+
+    #[sqlx::test]
+    async fn test_failed_export_releases_cooldown(db: PgPool) {
+        let user_id = sqlx::query_scalar!(
+            "INSERT INTO users (email) VALUES ('export@example.com') RETURNING id"
+        )
+        .fetch_one(&db)
+        .await
+        .unwrap();
+
+        // Memories live in a table owned by the Intelligence service, which
+        // the test database doesn't have, so the export fails after the claim
+        assert!(matches!(
+            export_user_data(&db, user_id).await,
+            Err(UserError::Database(_))
+        ));
+
+        claim_export(&db, user_id).await.unwrap();
+        assert!(matches!(
+            claim_export(&db, user_id).await,
+            Err(UserError::ExportCooldown(_))
+        ));
+    }
+
+    #[test]
+    fn test_bounded_array_stops_at_limit() {
+        let item = json!({"id": 1});
+        let item_len = item.to_string().len();
+
+        // Room for exactly two items: "[", item, ",", item, "]"
+        let mut array = BoundedJsonArray::new(2 * item_len + 3);
+        assert!(array.push(&item));
+        assert!(array.push(&item));
+        assert!(!array.push(&item));
+
+        let bytes = array.finish();
+        assert_eq!(bytes.len(), 2 * item_len + 3);
+        let parsed: Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(parsed, json!([{"id": 1}, {"id": 1}]));
+
+        assert_eq!(BoundedJsonArray::new(0).finish(), b"[]");
+    }
+
+    #[test]
+    fn test_archive_round_trip() {
+        let files = [
+            ("profile.json", br#"{"email":"a@example.com"}"#.to_vec()),
+            ("sessions.json", b"[]".to_vec()),
+        ];
+
+        let archive = build_archive(&files).unwrap();
+        let mut zip = zip::ZipArchive::new(Cursor::new(archive)).unwrap();
+        assert_eq!(zip.len(), 2);
+
+        let mut profile = String::new();
+        zip.by_name("profile.json")
+            .unwrap()
+            .read_to_string(&mut profile)
+            .unwrap();
+        assert_eq!(profile, r#"{"email":"a@example.com"}"#);
+    }
+}
@@ -1,20 +1,44 @@
 use axum::{
     Extension, Json,
-    extract::{Path, State},
-    http::HeaderMap,
+    body::Body,
+    extract::{Multipart, Path, Query, State, multipart::MultipartError},
+    http::{HeaderMap, HeaderValue, StatusCode, header},
+    response::{IntoResponse, Response},
 };
+use serde_json::{Map, Value};
 use sqlx::PgPool;
 use uuid::Uuid;
 
+use crate::auth::audit::{self, AuthEventListResponse, AuthEventQuery, ClientInfo};
+use crate::auth::session::{CurrentSession, IsImpersonated};
+use crate::common::validation::{self, ValidationErrors};
+use crate::config::cache::RedisPool;
+use crate::gateway::AppState;
+use crate::gateway::openapi::ErrorBody;
+use crate::middleware::ClientIp;
 use crate::user::{
-    ChangePasswordRequest, ChangePasswordResponse, DeleteAccountResponse, SessionListResponse,
-    UpdateProfileRequest, UserError, UserResponse, service,
+    AvatarResponse, ChangeEmailRequest, ChangeEmailResponse, ChangePasswordRequest,
+    ChangePasswordResponse, DeleteAccountRequest, DeleteAccountResponse, RenameSessionRequest,
+    SessionListResponse, SetPasswordRequest, SetPasswordResponse, TokenQuotaResponse,
+    TokenUsageResponse, UpdateProfileRequest, UserError, UserResponse, avatar, export,
+    notifications::{self, NotificationPreferences, UpdateNotificationsRequest},
+    preferences::{self, UserPreferences},
+    service,
 };
 
 // ===== Get Current User =====
 
 /// GET /user/me
 /// Get current authenticated user's information
+#[utoipa::path(
+    get,
+    path = "/user/me",
+    tag = "user",
+    responses(
+        (status = 200, description = "Current user", body = UserResponse),
+    ),
+    security(("bearer_auth" = []))
+)]
 pub async fn me(
     State(db): State<PgPool>,
     Extension(user_id): Extension<Uuid>,
@@ -27,25 +51,269 @@ pub async fn me(
 
 /// PATCH /user/update-profile
 /// Update user profile information
+///
+/// Fields left out of the body keep their value, `null` clears them and a
+/// string replaces them. Clearing `avatar_url` also deletes an uploaded avatar.
+#[utoipa::path(
+    patch,
+    path = "/user/update-profile",
+    tag = "user",
+    request_body = UpdateProfileRequest,
+    responses(
+        (status = 200, description = "Updated user", body = UserResponse),
+        (status = 400, description = "Invalid input", body = ErrorBody),
+        (status = 409, description = "Username already taken", body = ErrorBody),
+    ),
+    security(("bearer_auth" = []))
+)]
 pub async fn update_profile(
-    State(db): State<PgPool>,
+    State(state): State<AppState>,
     Extension(user_id): Extension<Uuid>,
     Json(payload): Json<UpdateProfileRequest>,
 ) -> Result<Json<UserResponse>, UserError> {
-    let user = service::update_profile(&db, user_id, payload).await?;
+    let mut errors = ValidationErrors::new();
+    if let Some(username) = payload.username.as_set() {
+        errors.check("username", validation::validate_username(username));
+    }
+    if let Some(name) = payload.name.as_set() {
+        errors.check("name", validation::validate_name(name));
+    }
+    errors.into_result().map_err(UserError::ValidationFields)?;
+
+    let previous_avatar = if payload.avatar_url.is_unchanged() {
+        None
+    } else {
+        service::get_user_by_id(&state.db, user_id).await?.avatar_url
+    };
+
+    let user = service::update_profile(&state.db, user_id, payload).await?;
+    avatar::remove_replaced_avatar(
+        state.storage.clone(),
+        &state.config.storage.public_url,
+        previous_avatar.as_deref(),
+        user.avatar_url.as_deref(),
+    );
     Ok(Json(user))
 }
 
+// ===== Preferences =====
+
+/// GET /user/preferences
+/// Get the current user's settings
+#[utoipa::path(
+    get,
+    path = "/user/preferences",
+    tag = "user",
+    responses(
+        (status = 200, description = "Saved preferences", body = UserPreferences),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn get_preferences(
+    State(db): State<PgPool>,
+    Extension(user_id): Extension<Uuid>,
+) -> Result<Json<UserPreferences>, UserError> {
+    let preferences = preferences::get_preferences(&db, user_id).await?;
+    Ok(Json(preferences))
+}
+
+/// PATCH /user/preferences
+/// Replace the given top-level settings, removing those set to `null`
+#[utoipa::path(
+    patch,
+    path = "/user/preferences",
+    tag = "user",
+    request_body = UserPreferences,
+    responses(
+        (status = 200, description = "Preferences after the merge", body = UserPreferences),
+        (status = 400, description = "Invalid preferences", body = ErrorBody),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn update_preferences(
+    State(db): State<PgPool>,
+    Extension(user_id): Extension<Uuid>,
+    Json(patch): Json<Map<String, Value>>,
+) -> Result<Json<UserPreferences>, UserError> {
+    let preferences = preferences::update_preferences(&db, user_id, patch).await?;
+    Ok(Json(preferences))
+}
+
+// ===== Notifications =====
+
+/// GET /user/notifications
+/// Get which optional emails the current user receives
+#[utoipa::path(
+    get,
+    path = "/user/notifications",
+    tag = "user",
+    responses(
+        (status = 200, description = "Email notification settings", body = NotificationPreferences),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn get_notifications(
+    State(db): State<PgPool>,
+    Extension(user_id): Extension<Uuid>,
+) -> Result<Json<NotificationPreferences>, UserError> {
+    let preferences = notifications::get_preferences(&db, user_id).await?;
+    Ok(Json(preferences))
+}
+
+/// PATCH /user/notifications
+/// Turn categories of optional email on or off
+#[utoipa::path(
+    patch,
+    path = "/user/notifications",
+    tag = "user",
+    request_body = UpdateNotificationsRequest,
+    responses(
+        (
+            status = 200,
+            description = "Updated notification settings",
+            body = NotificationPreferences
+        ),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn update_notifications(
+    State(db): State<PgPool>,
+    Extension(user_id): Extension<Uuid>,
+    Json(req): Json<UpdateNotificationsRequest>,
+) -> Result<Json<NotificationPreferences>, UserError> {
+    let preferences = notifications::update_preferences(&db, user_id, &req)
+        .await?
+        .ok_or(UserError::NotFound)?;
+    Ok(Json(preferences))
+}
+
+// ===== Avatar =====
+
+/// POST /user/avatar
+/// Upload a profile picture as the `avatar` field of a multipart form
+///
+/// JPEG, PNG and WEBP images up to 5MB are accepted and stored as a 256x256
+/// PNG. The previous uploaded avatar is deleted.
+#[utoipa::path(
+    post,
+    path = "/user/avatar",
+    tag = "user",
+    request_body(content = String, content_type = "multipart/form-data"),
+    responses(
+        (status = 200, description = "Avatar stored", body = AvatarResponse),
+        (status = 400, description = "Unsupported or oversized image", body = ErrorBody),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn upload_avatar(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    mut multipart: Multipart,
+) -> Result<Json<AvatarResponse>, UserError> {
+    let mut upload = None;
+    while let Some(mut field) = multipart.next_field().await.map_err(multipart_error)? {
+        if field.name() != Some("avatar") {
+            continue;
+        }
+
+        let mut data = Vec::new();
+        while let Some(chunk) = field.chunk().await.map_err(multipart_error)? {
+            if data.len() + chunk.len() > avatar::MAX_AVATAR_BYTES {
+                return Err(UserError::AvatarTooLarge);
+            }
+            data.extend_from_slice(&chunk);
+        }
+        upload = Some(data);
+        break;
+    }
+    let data = upload.ok_or_else(|| UserError::Validation("Missing avatar field".to_string()))?;
+
+    // Decoding and resizing are CPU bound
+    let png = tokio::task::spawn_blocking(move || avatar::process_avatar(&data))
+        .await
+        .map_err(|_| UserError::Internal)??;
+
+    let key = avatar::avatar_key(user_id, &png);
+    state
+        .storage
+        .put(&key, png.into(), Some("image/png"))
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to store avatar {}: {}", key, e);
+            UserError::Internal
+        })?;
+
+    let api_url = &state.config.storage.public_url;
+    let avatar_url = avatar::avatar_url(api_url, &key);
+    let previous = service::set_avatar_url(&state.db, user_id, &avatar_url).await?;
+    avatar::remove_replaced_avatar(
+        state.storage.clone(),
+        api_url,
+        previous.as_deref(),
+        Some(&avatar_url),
+    );
+
+    Ok(Json(AvatarResponse { avatar_url }))
+}
+
+fn multipart_error(e: MultipartError) -> UserError {
+    if e.status() == StatusCode::PAYLOAD_TOO_LARGE {
+        UserError::AvatarTooLarge
+    } else {
+        UserError::Validation(e.body_text())
+    }
+}
+
+/// Refuse credential and account changes made through an impersonation session
+///
+/// An admin acting as the user may look around, but not lock the user out.
+fn forbid_impersonation(IsImpersonated(impersonated): IsImpersonated) -> Result<(), UserError> {
+    if impersonated {
+        return Err(UserError::ImpersonationForbidden);
+    }
+    Ok(())
+}
+
 // ===== Change Password =====
 
 /// POST /user/change-password
 /// Change user password
+///
+/// 403 `impersonation_forbidden` from an impersonation session.
+#[utoipa::path(
+    post,
+    path = "/user/change-password",
+    tag = "user",
+    request_body = ChangePasswordRequest,
+    responses(
+        (status = 200, description = "Password changed", body = ChangePasswordResponse),
+        (status = 400, description = "Invalid password or none set", body = ErrorBody),
+        (status = 401, description = "Wrong current password", body = ErrorBody),
+        (status = 403, description = "Impersonation session", body = ErrorBody),
+    ),
+    security(("bearer_auth" = []))
+)]
 pub async fn change_password(
     State(db): State<PgPool>,
+    State(cache): State<Option<RedisPool>>,
     Extension(user_id): Extension<Uuid>,
+    Extension(impersonated): Extension<IsImpersonated>,
     headers: HeaderMap,
+    client_ip: ClientIp,
     Json(payload): Json<ChangePasswordRequest>,
 ) -> Result<Json<ChangePasswordResponse>, UserError> {
+    forbid_impersonation(impersonated)?;
+
+    let mut errors = ValidationErrors::new();
+    if payload.current_password.is_empty() {
+        errors.add("current_password", "Current password cannot be empty");
+    }
+    errors.check(
+        "new_password",
+        validation::validate_password(&payload.new_password),
+    );
+    errors.into_result().map_err(UserError::ValidationFields)?;
+
     // Extract current session token from headers
     let session_token = headers
         .get(axum::http::header::AUTHORIZATION)
@@ -53,7 +321,113 @@ pub async fn change_password(
         .and_then(|v| v.strip_prefix("Bearer "))
         .ok_or(UserError::Unauthorized)?;
 
-    let response = service::change_password(&db, user_id, session_token, payload).await?;
+    let client = ClientInfo::new(&headers, client_ip);
+    let response = service::change_password(
+        &db,
+        cache.as_ref(),
+        user_id,
+        session_token,
+        payload,
+        &client,
+    )
+    .await?;
+    Ok(Json(response))
+}
+
+// ===== Set Password =====
+
+/// POST /user/set-password
+/// Add a password to an account that only signs in through OAuth
+///
+/// Needs a sign-in within the last 10 minutes (403 `recent_signin_required`)
+/// and fails with 409 if the account already has a password. 403
+/// `impersonation_forbidden` from an impersonation session.
+#[utoipa::path(
+    post,
+    path = "/user/set-password",
+    tag = "user",
+    request_body = SetPasswordRequest,
+    responses(
+        (status = 200, description = "Password set", body = SetPasswordResponse),
+        (status = 403, description = "Recent sign-in required", body = ErrorBody),
+        (status = 409, description = "A password is already set", body = ErrorBody),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn set_password(
+    State(app_state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Extension(current): Extension<CurrentSession>,
+    Extension(impersonated): Extension<IsImpersonated>,
+    headers: HeaderMap,
+    client_ip: ClientIp,
+    Json(payload): Json<SetPasswordRequest>,
+) -> Result<Json<SetPasswordResponse>, UserError> {
+    forbid_impersonation(impersonated)?;
+
+    let mut errors = ValidationErrors::new();
+    errors.check(
+        "new_password",
+        validation::validate_password(&payload.new_password),
+    );
+    errors.into_result().map_err(UserError::ValidationFields)?;
+
+    let client = ClientInfo::new(&headers, client_ip);
+    let response = service::set_password(
+        &app_state.db,
+        user_id,
+        payload,
+        &current,
+        &app_state.email,
+        &client,
+    )
+    .await?;
+    Ok(Json(response))
+}
+
+// ===== Change Email =====
+
+/// POST /user/change-email
+/// Request an email change (confirmed via link sent to the new address)
+///
+/// Confirmed like account deletion: 403 `password_required`,
+/// `invalid_password` or `recent_signin_required`, and 403
+/// `impersonation_forbidden` from an impersonation session.
+#[utoipa::path(
+    post,
+    path = "/user/change-email",
+    tag = "user",
+    request_body = ChangeEmailRequest,
+    responses(
+        (
+            status = 200,
+            description = "Confirmation sent to the new address",
+            body = ChangeEmailResponse
+        ),
+        (status = 403, description = "Identity confirmation failed", body = ErrorBody),
+        (status = 409, description = "Email already in use", body = ErrorBody),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn change_email(
+    State(app_state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Extension(current): Extension<CurrentSession>,
+    Extension(impersonated): Extension<IsImpersonated>,
+    Json(payload): Json<ChangeEmailRequest>,
+) -> Result<Json<ChangeEmailResponse>, UserError> {
+    forbid_impersonation(impersonated)?;
+    crate::common::validation::validate_email(&payload.new_email)
+        .map_err(UserError::Validation)?;
+
+    let response = service::request_email_change(
+        &app_state.db,
+        user_id,
+        payload,
+        &current,
+        &app_state.email,
+    )
+    .await?;
     Ok(Json(response))
 }
 
@@ -61,33 +435,246 @@ pub async fn change_password(
 
 /// DELETE /user/delete-account
 /// Soft delete user account
+///
+/// Requires `current_password` in the body, or for OAuth-only accounts a
+/// sign-in within the last 10 minutes. Otherwise 403 `password_required`,
+/// `invalid_password` or `recent_signin_required`. Never allowed from an
+/// impersonation session (403 `impersonation_forbidden`).
+#[utoipa::path(
+    delete,
+    path = "/user/delete-account",
+    tag = "user",
+    request_body = DeleteAccountRequest,
+    responses(
+        (
+            status = 200,
+            description = "Account scheduled for deletion",
+            body = DeleteAccountResponse
+        ),
+        (status = 403, description = "Identity confirmation failed", body = ErrorBody),
+    ),
+    security(("bearer_auth" = []))
+)]
 pub async fn delete_account(
-    State(db): State<PgPool>,
+    State(state): State<AppState>,
     Extension(user_id): Extension<Uuid>,
+    Extension(current): Extension<CurrentSession>,
+    Extension(impersonated): Extension<IsImpersonated>,
+    payload: Option<Json<DeleteAccountRequest>>,
 ) -> Result<Json<DeleteAccountResponse>, UserError> {
-    let response = service::soft_delete_account(&db, user_id).await?;
+    forbid_impersonation(impersonated)?;
+    let Json(payload) = payload.unwrap_or_default();
+    let response = service::soft_delete_account(
+        &state.db,
+        state.cache.as_ref(),
+        user_id,
+        payload,
+        &current,
+        state.config.security.deleted_account_retention_days,
+    )
+    .await?;
     Ok(Json(response))
 }
 
+// ===== Data Export =====
+
+/// POST /user/export-data
+/// Download everything stored about the current user as a ZIP of JSON files
+///
+/// Also served on `GET`. Limited to one export per 15 minutes.
+/// `X-Export-Truncated: true` means some conversations or resources were
+/// left out to keep the archive under 50MB.
+#[utoipa::path(
+    method(post, get),
+    path = "/user/export-data",
+    tag = "user",
+    responses(
+        (status = 200, description = "ZIP archive of the user's data"),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn export_data(
+    State(db): State<PgPool>,
+    Extension(user_id): Extension<Uuid>,
+) -> Result<Response, UserError> {
+    let export = export::export_user_data(&db, user_id).await?;
+
+    let mut headers = HeaderMap::new();
+    headers.insert(header::CONTENT_TYPE, HeaderValue::from_static("application/zip"));
+    let disposition = format!("attachment; filename=\"export-{}.zip\"", user_id);
+    headers.insert(
+        header::CONTENT_DISPOSITION,
+        HeaderValue::from_str(&disposition).map_err(|_| UserError::Internal)?,
+    );
+    if export.truncated {
+        headers.insert("x-export-truncated", HeaderValue::from_static("true"));
+    }
+
+    Ok((headers, Body::from(export.archive)).into_response())
+}
+
 // ===== Session Management =====
 
 /// GET /user/list-sessions
 /// List all active sessions for the current user
+#[utoipa::path(
+    get,
+    path = "/user/list-sessions",
+    tag = "user",
+    responses(
+        (status = 200, description = "Active sessions", body = SessionListResponse),
+    ),
+    security(("bearer_auth" = []))
+)]
 pub async fn list_sessions(
-    State(db): State<PgPool>,
+    State(state): State<AppState>,
     Extension(user_id): Extension<Uuid>,
+    current: Option<Extension<CurrentSession>>,
 ) -> Result<Json<SessionListResponse>, UserError> {
-    let response = service::get_user_sessions(&db, user_id).await?;
+    let response = service::get_user_sessions(
+        &state.db,
+        user_id,
+        current.as_ref().map(|Extension(c)| c),
+        state.config.security.max_sessions_per_user,
+    )
+    .await?;
     Ok(Json(response))
 }
 
+/// PATCH /user/sessions/{session_id}
+/// Give a session a custom name
+#[utoipa::path(
+    patch,
+    path = "/user/sessions/{session_id}",
+    tag = "user",
+    params(("session_id" = Uuid, Path, description = "Session ID")),
+    request_body = RenameSessionRequest,
+    responses(
+        (status = 200, description = "Session renamed"),
+        (status = 404, description = "Session not found", body = ErrorBody),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn rename_session(
+    State(db): State<PgPool>,
+    Extension(user_id): Extension<Uuid>,
+    Path(session_id): Path<Uuid>,
+    Json(req): Json<RenameSessionRequest>,
+) -> Result<Json<()>, UserError> {
+    service::rename_session(&db, user_id, session_id, req.name.as_deref()).await?;
+    Ok(Json(()))
+}
+
 /// DELETE /user/sessions/{session_id}
 /// Revoke a specific session
+#[utoipa::path(
+    delete,
+    path = "/user/revoke-session/{session_id}",
+    tag = "user",
+    params(("session_id" = Uuid, Path, description = "Session ID")),
+    responses(
+        (status = 200, description = "Session revoked"),
+        (status = 404, description = "Session not found", body = ErrorBody),
+    ),
+    security(("bearer_auth" = []))
+)]
 pub async fn revoke_session(
     State(db): State<PgPool>,
+    State(cache): State<Option<RedisPool>>,
     Extension(user_id): Extension<Uuid>,
     Path(session_id): Path<Uuid>,
+    headers: HeaderMap,
+    client_ip: ClientIp,
 ) -> Result<Json<()>, UserError> {
-    service::revoke_session(&db, user_id, session_id).await?;
+    let client = ClientInfo::new(&headers, client_ip);
+    service::revoke_session(&db, cache.as_ref(), user_id, session_id, &client).await?;
     Ok(Json(()))
 }
+
+// ===== Token Quota =====
+
+/// GET /user/quota
+/// Get today's token usage and daily limit
+#[utoipa::path(
+    get,
+    path = "/user/quota",
+    tag = "user",
+    responses(
+        (status = 200, description = "Today's token usage and limit", body = TokenQuotaResponse),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn get_quota(
+    State(db): State<PgPool>,
+    Extension(user_id): Extension<Uuid>,
+) -> Result<Json<TokenQuotaResponse>, UserError> {
+    let quota = service::get_token_quota(&db, user_id).await?;
+    Ok(Json(quota))
+}
+
+/// GET /user/usage
+/// Get this month's token usage and budget
+#[utoipa::path(
+    get,
+    path = "/user/usage",
+    tag = "user",
+    responses(
+        (
+            status = 200,
+            description = "This month's token usage and budget",
+            body = TokenUsageResponse
+        ),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn get_usage(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+) -> Result<Json<TokenUsageResponse>, UserError> {
+    let usage =
+        service::get_token_usage(&state.db, user_id, state.config.chat.monthly_token_budget)
+            .await?;
+    Ok(Json(usage))
+}
+
+// ===== Security Events =====
+
+/// GET /user/security/events
+/// List the current user's authentication history, newest first
+#[utoipa::path(
+    get,
+    path = "/user/security/events",
+    tag = "user",
+    params(AuthEventQuery),
+    responses(
+        (
+            status = 200,
+            description = "Authentication events, newest first",
+            body = AuthEventListResponse
+        ),
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn security_events(
+    State(db): State<PgPool>,
+    Extension(user_id): Extension<Uuid>,
+    Query(query): Query<AuthEventQuery>,
+) -> Result<Json<AuthEventListResponse>, UserError> {
+    let events = audit::list_user_events(&db, user_id, &query).await?;
+    Ok(Json(events))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::response::IntoResponse;
+
+    #[test]
+    fn test_impersonation_sessions_cannot_change_credentials() {
+        assert!(forbid_impersonation(IsImpersonated(false)).is_ok());
+
+        let error = forbid_impersonation(IsImpersonated(true)).unwrap_err();
+        assert!(matches!(error, UserError::ImpersonationForbidden));
+        assert_eq!(error.into_response().status(), StatusCode::FORBIDDEN);
+    }
+}
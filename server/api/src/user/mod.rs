@@ -1,5 +1,13 @@
+pub mod activity;
+pub mod avatar;
+pub mod device;
 pub mod errors;
+pub mod export;
 pub mod handlers;
+pub mod notifications;
+pub mod preferences;
+pub mod purge;
+pub mod reauth;
 pub mod service;
 pub mod types;
 
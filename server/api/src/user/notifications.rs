@@ -0,0 +1,194 @@
+//! Email notification opt-outs
+//!
+//! Transactional mail (verification, password reset, email change) is always
+//! sent. Everything else belongs to a `NotificationCategory` the user can turn
+//! off, either from `PATCH /user/notifications` or from the signed unsubscribe
+//! link in the email itself. Users without a stored row get every category.
+
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// Kinds of optional email
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationCategory {
+    SecurityAlerts,
+    ProductUpdates,
+    WeeklyDigest,
+}
+
+impl NotificationCategory {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            NotificationCategory::SecurityAlerts => "security_alerts",
+            NotificationCategory::ProductUpdates => "product_updates",
+            NotificationCategory::WeeklyDigest => "weekly_digest",
+        }
+    }
+
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "security_alerts" => Some(NotificationCategory::SecurityAlerts),
+            "product_updates" => Some(NotificationCategory::ProductUpdates),
+            "weekly_digest" => Some(NotificationCategory::WeeklyDigest),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+pub struct NotificationPreferences {
+    pub security_alerts: bool,
+    pub product_updates: bool,
+    pub weekly_digest: bool,
+}
+
+impl Default for NotificationPreferences {
+    fn default() -> Self {
+        Self {
+            security_alerts: true,
+            product_updates: true,
+            weekly_digest: true,
+        }
+    }
+}
+
+impl NotificationPreferences {
+    pub fn allows(&self, category: NotificationCategory) -> bool {
+        match category {
+            NotificationCategory::SecurityAlerts => self.security_alerts,
+            NotificationCategory::ProductUpdates => self.product_updates,
+            NotificationCategory::WeeklyDigest => self.weekly_digest,
+        }
+    }
+}
+
+/// Omitted fields keep their current value
+#[derive(Debug, Default, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct UpdateNotificationsRequest {
+    pub security_alerts: Option<bool>,
+    pub product_updates: Option<bool>,
+    pub weekly_digest: Option<bool>,
+}
+
+impl UpdateNotificationsRequest {
+    /// A request turning off a single category
+    pub fn opt_out(category: NotificationCategory) -> Self {
+        let mut req = Self::default();
+        match category {
+            NotificationCategory::SecurityAlerts => req.security_alerts = Some(false),
+            NotificationCategory::ProductUpdates => req.product_updates = Some(false),
+            NotificationCategory::WeeklyDigest => req.weekly_digest = Some(false),
+        }
+        req
+    }
+}
+
+pub async fn get_preferences(
+    db: &PgPool,
+    user_id: Uuid,
+) -> Result<NotificationPreferences, sqlx::Error> {
+    let stored = sqlx::query_as!(
+        NotificationPreferences,
+        r#"
+        SELECT security_alerts, product_updates, weekly_digest
+        FROM notification_preferences
+        WHERE user_id = $1
+        "#,
+        user_id
+    )
+    .fetch_optional(db)
+    .await?;
+
+    Ok(stored.unwrap_or_default())
+}
+
+/// Apply the given changes and return the resulting preferences
+///
+/// Returns `None` if the user doesn't exist.
+pub async fn update_preferences(
+    db: &PgPool,
+    user_id: Uuid,
+    req: &UpdateNotificationsRequest,
+) -> Result<Option<NotificationPreferences>, sqlx::Error> {
+    let defaults = NotificationPreferences::default();
+
+    sqlx::query_as!(
+        NotificationPreferences,
+        r#"
+        INSERT INTO notification_preferences
+            (user_id, security_alerts, product_updates, weekly_digest)
+        SELECT id, COALESCE($2::BOOLEAN, $5), COALESCE($3::BOOLEAN, $6), COALESCE($4::BOOLEAN, $7)
+        FROM users
+        WHERE id = $1
+        ON CONFLICT (user_id) DO UPDATE SET
+            security_alerts = COALESCE($2::BOOLEAN, notification_preferences.security_alerts),
+            product_updates = COALESCE($3::BOOLEAN, notification_preferences.product_updates),
+            weekly_digest = COALESCE($4::BOOLEAN, notification_preferences.weekly_digest),
+            updated_at = NOW()
+        RETURNING security_alerts, product_updates, weekly_digest
+        "#,
+        user_id,
+        req.security_alerts,
+        req.product_updates,
+        req.weekly_digest,
+        defaults.security_alerts,
+        defaults.product_updates,
+        defaults.weekly_digest
+    )
+    .fetch_optional(db)
+    .await
+}
+
+/// Whether the user wants email of this kind
+///
+/// Lookup failures are logged and treated as opted in, so a database hiccup
+/// doesn't swallow a security alert.
+pub async fn is_enabled(db: &PgPool, user_id: Uuid, category: NotificationCategory) -> bool {
+    match get_preferences(db, user_id).await {
+        Ok(preferences) => preferences.allows(category),
+        Err(e) => {
+            tracing::error!(
+                %user_id,
+                category = category.as_str(),
+                "Failed to load notification preferences: {}",
+                e
+            );
+            true
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_category_names_roundtrip() {
+        for category in [
+            NotificationCategory::SecurityAlerts,
+            NotificationCategory::ProductUpdates,
+            NotificationCategory::WeeklyDigest,
+        ] {
+            assert_eq!(
+                NotificationCategory::from_str(category.as_str()),
+                Some(category)
+            );
+
+            let preferences = NotificationPreferences::default();
+            assert!(preferences.allows(category));
+
+            let req = UpdateNotificationsRequest::opt_out(category);
+            let opted_out = NotificationPreferences {
+                security_alerts: req.security_alerts.unwrap_or(true),
+                product_updates: req.product_updates.unwrap_or(true),
+                weekly_digest: req.weekly_digest.unwrap_or(true),
+            };
+            assert!(!opted_out.allows(category));
+        }
+        assert_eq!(NotificationCategory::from_str("marketing"), None);
+    }
+}
@@ -0,0 +1,206 @@
+//! Per-user settings stored in `users.preferences`
+//!
+//! `PATCH /user/preferences` is a shallow merge: each top-level key in the
+//! body replaces the stored value, and `null` removes it. The merge happens in
+//! a single UPDATE, so concurrent PATCHes to different keys don't overwrite
+//! each other. Unknown keys are rejected.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use utoipa::ToSchema;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use super::UserError;
+use crate::chat::types::ChatConfig;
+use crate::common::validation::ValidationErrors;
+
+const MAX_TEMPERATURE: f32 = 2.0;
+const MAX_MODEL_LENGTH: usize = 100;
+const MAX_LOCALE_LENGTH: usize = 35;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Theme {
+    Light,
+    Dark,
+    System,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct UserPreferences {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub theme: Option<Theme>,
+    /// BCP 47 language tag, e.g. `en-GB`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub locale: Option<String>,
+    /// Used by chat requests that don't specify a config
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default_chat_config: Option<ChatConfig>,
+}
+
+impl UserPreferences {
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::new();
+
+        if let Some(locale) = &self.locale {
+            errors.check("locale", validate_locale(locale));
+        }
+
+        if let Some(config) = &self.default_chat_config {
+            if let Some(temperature) = config.temperature
+                && !(0.0..=MAX_TEMPERATURE).contains(&temperature)
+            {
+                errors.add(
+                    "default_chat_config",
+                    format!("temperature must be between 0 and {}", MAX_TEMPERATURE),
+                );
+            }
+            if config.max_tokens.is_some_and(|tokens| tokens < 1) {
+                errors.add("default_chat_config", "max_tokens must be positive");
+            }
+            if let Some(model) = &config.model
+                && (model.trim().is_empty() || model.len() > MAX_MODEL_LENGTH)
+            {
+                errors.add(
+                    "default_chat_config",
+                    format!("model must be 1 to {} characters", MAX_MODEL_LENGTH),
+                );
+            }
+        }
+
+        errors.into_result()
+    }
+}
+
+fn validate_locale(locale: &str) -> Result<(), String> {
+    let mut parts = locale.split('-');
+    let language = parts.next().unwrap_or_default();
+    let valid = locale.len() <= MAX_LOCALE_LENGTH
+        && (2..=3).contains(&language.len())
+        && language.chars().all(|c| c.is_ascii_alphabetic())
+        && parts
+            .all(|p| (1..=8).contains(&p.len()) && p.chars().all(|c| c.is_ascii_alphanumeric()));
+
+    if valid {
+        Ok(())
+    } else {
+        Err("locale must be a language tag such as en or en-GB".to_string())
+    }
+}
+
+/// Check a PATCH body before it is merged
+fn validate_patch(patch: &Map<String, Value>) -> Result<(), UserError> {
+    // `null` removes a key, so it is valid for every known key
+    let preferences: UserPreferences = serde_json::from_value(Value::Object(patch.clone()))
+        .map_err(|e| UserError::Validation(format!("Invalid preferences: {}", e)))?;
+
+    preferences.validate().map_err(UserError::ValidationFields)
+}
+
+/// Parse stored preferences, dropping them if they no longer fit the schema
+fn parse_stored(user_id: Uuid, value: Value) -> UserPreferences {
+    serde_json::from_value(value).unwrap_or_else(|e| {
+        tracing::warn!("Ignoring invalid preferences of user {}: {}", user_id, e);
+        UserPreferences::default()
+    })
+}
+
+pub async fn get_preferences(db: &PgPool, user_id: Uuid) -> Result<UserPreferences, UserError> {
+    let stored = sqlx::query_scalar!("SELECT preferences FROM users WHERE id = $1", user_id)
+        .fetch_optional(db)
+        .await?
+        .ok_or(UserError::NotFound)?;
+
+    Ok(parse_stored(user_id, stored))
+}
+
+/// Merge `patch` into the stored preferences and return the result
+pub async fn update_preferences(
+    db: &PgPool,
+    user_id: Uuid,
+    patch: Map<String, Value>,
+) -> Result<UserPreferences, UserError> {
+    validate_patch(&patch)?;
+
+    // `||` replaces top-level keys; stripping nulls then removes cleared keys
+    let merged = sqlx::query_scalar!(
+        r#"
+        UPDATE users
+        SET preferences = jsonb_strip_nulls(preferences || $1::jsonb)
+        WHERE id = $2
+        RETURNING preferences
+        "#,
+        Value::Object(patch),
+        user_id
+    )
+    .fetch_optional(db)
+    .await?
+    .ok_or(UserError::NotFound)?;
+
+    Ok(parse_stored(user_id, merged))
+}
+
+/// The user's default chat config, if they have set one
+pub async fn default_chat_config(
+    db: &PgPool,
+    user_id: Uuid,
+) -> Result<Option<ChatConfig>, sqlx::Error> {
+    let stored = sqlx::query_scalar!(
+        "SELECT preferences -> 'default_chat_config' FROM users WHERE id = $1",
+        user_id
+    )
+    .fetch_optional(db)
+    .await?
+    .flatten();
+
+    Ok(stored.and_then(|config| serde_json::from_value(config).ok()))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn patch(value: Value) -> Map<String, Value> {
+        match value {
+            Value::Object(map) => map,
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn test_patch_validation() {
+        assert!(
+            validate_patch(&patch(json!({
+                "theme": "dark",
+                "locale": "en-GB",
+                "default_chat_config": {"model": "gpt-4o", "temperature": 0.2}
+            })))
+            .is_ok()
+        );
+        // Clearing keys
+        assert!(validate_patch(&patch(json!({"theme": null, "locale": null}))).is_ok());
+
+        assert!(validate_patch(&patch(json!({"colour": "red"}))).is_err());
+        assert!(validate_patch(&patch(json!({"theme": "neon"}))).is_err());
+        assert!(validate_patch(&patch(json!({"locale": "english please"}))).is_err());
+        assert!(
+            validate_patch(&patch(json!({"default_chat_config": {"temperature": 3.5}}))).is_err()
+        );
+        assert!(validate_patch(&patch(json!({"default_chat_config": {"max_tokens": 0}}))).is_err());
+        assert!(validate_patch(&patch(json!({"default_chat_config": {"model": " "}}))).is_err());
+    }
+
+    #[test]
+    fn test_invalid_stored_preferences_ignored() {
+        let stored = parse_stored(Uuid::nil(), json!({"theme": "neon"}));
+        assert!(stored.theme.is_none());
+
+        let stored = parse_stored(Uuid::nil(), json!({"theme": "light", "locale": "fr"}));
+        assert_eq!(stored.theme, Some(Theme::Light));
+        assert_eq!(stored.locale.as_deref(), Some("fr"));
+    }
+}
@@ -0,0 +1,427 @@
+//! Purging accounts past their recovery window
+//!
+//! Soft-deleted accounts can be recovered for `DELETED_ACCOUNT_RETENTION_DAYS`.
+//! After that a daily task deletes them for good, or an admin can purge one
+//! right away with `POST /admin/users/{id}/purge`.
+//!
+//! A purge first asks the Intelligence service to delete each of the user's
+//! conversations and personal resources, so its indexes go too, and removes
+//! the uploaded avatar from storage. Only when every call succeeds are the
+//! rows removed here: conversations (and their messages), personal documents,
+//! memories, audit events, undelivered emails to the account's address, and
+//! the user row, which cascades to sessions, tokens, OAuth accounts, quotas,
+//! feedback and pending messages. This frees the email address for a new
+//! signup. Cached sessions are evicted once the rows are gone. An account
+//! whose Intelligence data or avatar couldn't be deleted is kept and retried
+//! on the next run. Global resources uploaded by the user stay, as they
+//! belong to the shared knowledge base.
+//!
+//! Only soft-deleted accounts are purged, and each is locked while it is, so
+//! replicas and admin purges never work on the same account at once.
+
+use chrono::{DateTime, Duration, Utc};
+use sqlx::PgPool;
+use thiserror::Error;
+use tonic::Code;
+use uuid::Uuid;
+
+use crate::auth::session;
+use crate::common::background;
+use crate::config::{cache::RedisPool, env::SecurityConfig};
+use crate::grpc::IntelligenceClient;
+use crate::grpc::proto::opentier::intelligence::v1 as pb;
+use crate::storage::{SharedStorage, StorageError};
+use crate::user::avatar;
+
+/// How often purged accounts are looked for
+const PURGE_INTERVAL_SECONDS: u64 = 86400; // 24 hours
+
+/// Whether an account deleted at `deleted_at` can no longer be recovered
+///
+/// Recovery is allowed up to and including the last instant of the window.
+pub fn recovery_expired(
+    deleted_at: DateTime<Utc>,
+    now: DateTime<Utc>,
+    retention_days: u32,
+) -> bool {
+    deleted_at < purge_cutoff(now, retention_days)
+}
+
+/// Accounts deleted before this instant are purged
+fn purge_cutoff(now: DateTime<Utc>, retention_days: u32) -> DateTime<Utc> {
+    now - Duration::days(retention_days as i64)
+}
+
+#[derive(Debug, Error)]
+pub enum PurgeError {
+    #[error("Database error: {0}")]
+    Database(#[from] sqlx::Error),
+
+    #[error("Intelligence service error: {0}")]
+    Intelligence(#[from] tonic::Status),
+
+    #[error("Storage error: {0}")]
+    Storage(#[from] StorageError),
+}
+
+/// What a purge removed
+#[derive(Debug)]
+pub struct PurgedAccount {
+    pub conversations: u64,
+    pub resources: u64,
+}
+
+/// Where a purge deletes the user's data besides the database
+#[derive(Clone)]
+pub struct PurgeTargets {
+    pub client: IntelligenceClient,
+    pub storage: SharedStorage,
+    pub cache: Option<RedisPool>,
+    /// Base URL avatar URLs start with, to find their storage keys
+    pub api_url: String,
+}
+
+/// Start the deleted account purge background task
+pub fn start_account_purge_task(db: PgPool, targets: PurgeTargets, config: &SecurityConfig) {
+    let retention_days = config.deleted_account_retention_days;
+    let batch_size = config.session_cleanup_batch_size;
+    background::start_periodic_task(
+        db,
+        "Deleted account purge",
+        PURGE_INTERVAL_SECONDS,
+        move |db| {
+            let targets = targets.clone();
+            async move { purge_deleted_accounts(&db, &targets, retention_days, batch_size).await }
+        },
+    );
+}
+
+/// Permanently delete accounts past the recovery window, returning how many
+pub async fn purge_deleted_accounts(
+    db: &PgPool,
+    targets: &PurgeTargets,
+    retention_days: u32,
+    batch_size: i64,
+) -> Result<u64, sqlx::Error> {
+    let cutoff = purge_cutoff(Utc::now(), retention_days);
+    background::delete_in_batches(batch_size, |limit| purge_batch(db, targets, cutoff, limit))
+        .await
+}
+
+/// Up to `limit` accounts deleted before `cutoff`, longest deleted first
+async fn purgeable_accounts(
+    db: &PgPool,
+    cutoff: DateTime<Utc>,
+    limit: i64,
+) -> Result<Vec<Uuid>, sqlx::Error> {
+    sqlx::query_scalar!(
+        r#"
+        SELECT id FROM users
+        WHERE deleted_at < $1
+        ORDER BY deleted_at
+        LIMIT $2
+        "#,
+        cutoff,
+        limit
+    )
+    .fetch_all(db)
+    .await
+}
+
+/// Purge up to `limit` accounts, returning how many were removed
+///
+/// Accounts that fail are logged and left for the next run, which also ends
+/// the current run since the batch comes up short.
+async fn purge_batch(
+    db: &PgPool,
+    targets: &PurgeTargets,
+    cutoff: DateTime<Utc>,
+    limit: i64,
+) -> Result<u64, sqlx::Error> {
+    let user_ids = purgeable_accounts(db, cutoff, limit).await?;
+
+    let mut purged = 0;
+    for user_id in user_ids {
+        match purge_account(db, targets, user_id).await {
+            Ok(Some(_)) => purged += 1,
+            // Recovered meanwhile, or being purged by another replica
+            Ok(None) => {}
+            Err(PurgeError::Database(e)) => return Err(e),
+            Err(e) => tracing::warn!(%user_id, "Deferring account purge: {}", e),
+        }
+    }
+
+    if purged > 0 {
+        tracing::info!(users = purged, "Purged accounts deleted before {}", cutoff);
+    }
+
+    Ok(purged)
+}
+
+/// Delete a soft-deleted user's Intelligence-side data and avatar, then the
+/// account and its rows
+///
+/// Returns `None` if the user doesn't exist, isn't deleted, or is being
+/// purged elsewhere. Deletes are idempotent, so a purge that failed halfway
+/// can simply be run again.
+pub async fn purge_account(
+    db: &PgPool,
+    targets: &PurgeTargets,
+    user_id: Uuid,
+) -> Result<Option<PurgedAccount>, PurgeError> {
+    let mut tx = db.begin().await?;
+
+    // SKIP LOCKED keeps concurrent purges of the same account from both
+    // running; the lock is held until the rows are deleted
+    let Some(user) = sqlx::query!(
+        r#"
+        SELECT email, avatar_url FROM users
+        WHERE id = $1 AND deleted_at IS NOT NULL
+        FOR UPDATE SKIP LOCKED
+        "#,
+        user_id
+    )
+    .fetch_optional(&mut *tx)
+    .await?
+    else {
+        return Ok(None);
+    };
+    let mut client = targets.client.clone();
+
+    // Conversations and documents store the user ID as text and have no
+    // foreign key
+    let owner = user_id.to_string();
+    let conversation_ids =
+        sqlx::query_scalar!("SELECT id FROM conversations WHERE user_id = $1", owner)
+            .fetch_all(&mut *tx)
+            .await?;
+    let resource_ids = sqlx::query_scalar!(
+        "SELECT id FROM documents WHERE user_id = $1 AND NOT is_global",
+        owner
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+
+    for id in &conversation_ids {
+        let request = pb::DeleteConversationRequest {
+            user_id: owner.clone(),
+            conversation_id: id.to_string(),
+        };
+        ignore_not_found(client.delete_conversation(request).await)?;
+    }
+    for id in &resource_ids {
+        let request = pb::DeleteResourceRequest {
+            user_id: owner.clone(),
+            resource_id: id.to_string(),
+        };
+        ignore_not_found(client.delete_resource(request).await)?;
+    }
+
+    // Replaced avatars are removed on upload, so only the current one is left
+    if let Some(key) = user
+        .avatar_url
+        .and_then(|url| avatar::key_from_url(&targets.api_url, &url))
+    {
+        targets.storage.delete(&key).await?;
+    }
+
+    // Whatever the Intelligence service left behind
+    sqlx::query!("DELETE FROM conversations WHERE user_id = $1", owner)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query!(
+        "DELETE FROM documents WHERE user_id = $1 AND NOT is_global",
+        owner
+    )
+    .execute(&mut *tx)
+    .await?;
+    sqlx::query!("DELETE FROM user_memories WHERE user_id = $1", owner)
+        .execute(&mut *tx)
+        .await?;
+
+    sqlx::query!("DELETE FROM auth_events WHERE user_id = $1", user_id)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query!(
+        "DELETE FROM email_failures WHERE LOWER(to_email) = LOWER($1)",
+        user.email
+    )
+    .execute(&mut *tx)
+    .await?;
+    // Deleted here rather than by the cascade, to evict them from the cache
+    let session_tokens = sqlx::query_scalar!(
+        "DELETE FROM sessions WHERE user_id = $1 RETURNING session_token",
+        user_id
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+    sqlx::query!("DELETE FROM users WHERE id = $1", user_id)
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+
+    session::cache_evict(targets.cache.as_ref(), &session_tokens).await;
+
+    tracing::info!(
+        %user_id,
+        conversations = conversation_ids.len(),
+        resources = resource_ids.len(),
+        "Purged account"
+    );
+
+    Ok(Some(PurgedAccount {
+        conversations: conversation_ids.len() as u64,
+        resources: resource_ids.len() as u64,
+    }))
+}
+
+/// Treat data the Intelligence service no longer has as deleted
+fn ignore_not_found<T>(result: Result<T, tonic::Status>) -> Result<(), tonic::Status> {
+    match result {
+        Err(status) if status.code() != Code::NotFound => Err(status),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_recovery_window_boundary() {
+        let now = Utc::now();
+        let last_day = now - Duration::days(30);
+
+        // Exactly 30 days ago is still the last recoverable instant
+        assert!(!recovery_expired(last_day, now, 30));
+        assert!(recovery_expired(last_day - Duration::seconds(1), now, 30));
+        assert!(!recovery_expired(last_day + Duration::hours(23), now, 30));
+        assert!(recovery_expired(now - Duration::days(31), now, 30));
+
+        assert!(!recovery_expired(now - Duration::days(6), now, 7));
+        assert!(recovery_expired(now - Duration::days(8), now, 7));
+    }
+
+    async fn deleted_user(db: &PgPool, email: &str, deleted_at: DateTime<Utc>) -> Uuid {
+        sqlx::query_scalar!(
+            "INSERT INTO users (email, deleted_at) VALUES ($1, $2) RETURNING id",
+            email,
+            deleted_at
+        )
+        .fetch_one(db)
+        .await
+        .unwrap()
+    }
+
+    #[sqlx::test]
+    async fn test_purge_selects_only_unrecoverable_accounts(db: PgPool) {
+        // Whole seconds, which survive the round trip through PostgreSQL
+        let now = Utc.with_ymd_and_hms(2026, 3, 1, 12, 0, 0).unwrap();
+        let cutoff = purge_cutoff(now, 30);
+        let expired = deleted_user(&db, "expired@example.com", cutoff - Duration::seconds(1)).await;
+        let last = deleted_user(&db, "last@example.com", cutoff).await;
+        let recent = deleted_user(&db, "recent@example.com", cutoff + Duration::seconds(1)).await;
+        sqlx::query!("INSERT INTO users (email) VALUES ('active@example.com')")
+            .execute(&db)
+            .await
+            .unwrap();
+
+        assert_eq!(purgeable_accounts(&db, cutoff, 10).await.unwrap(), vec![expired]);
+
+        // Every account left out can still be recovered
+        for user_id in [last, recent] {
+            let deleted_at = sqlx::query_scalar!(
+                r#"SELECT deleted_at as "deleted_at!" FROM users WHERE id = $1"#,
+                user_id
+            )
+            .fetch_one(&db)
+            .await
+            .unwrap();
+            assert!(!recovery_expired(deleted_at, now, 30));
+        }
+    }
+
+    #[sqlx::test]
+    async fn test_purge_removes_undelivered_emails_and_avatar(db: PgPool) {
+        // Owned by the Intelligence service, so not created by our migrations
+        sqlx::query("CREATE TABLE documents (id UUID, user_id TEXT, is_global BOOLEAN)")
+            .execute(&db)
+            .await
+            .unwrap();
+        sqlx::query("CREATE TABLE user_memories (user_id TEXT)")
+            .execute(&db)
+            .await
+            .unwrap();
+
+        let config = crate::config::env::Config::for_tests();
+        let targets = PurgeTargets {
+            cache: None,
+            // Never called: the user has no conversations or resources
+            client: IntelligenceClient::connect_lazy("http://[::1]:50051")
+                .await
+                .unwrap(),
+            storage: crate::storage::from_config(&config.storage).unwrap(),
+            api_url: config.storage.public_url.clone(),
+        };
+
+        let user_id = deleted_user(&db, "gone@example.com", Utc::now()).await;
+        let key = avatar::avatar_key(user_id, b"png");
+        targets
+            .storage
+            .put(&key, b"png".to_vec().into(), Some("image/png"))
+            .await
+            .unwrap();
+        sqlx::query!(
+            "UPDATE users SET avatar_url = $1 WHERE id = $2",
+            avatar::avatar_url(&targets.api_url, &key),
+            user_id
+        )
+        .execute(&db)
+        .await
+        .unwrap();
+        for to in ["Gone@example.com", "kept@example.com"] {
+            sqlx::query!(
+                r#"
+                INSERT INTO email_failures (to_email, subject, error, attempts)
+                VALUES ($1, 'Verify your email', 'mailbox unavailable', 4)
+                "#,
+                to
+            )
+            .execute(&db)
+            .await
+            .unwrap();
+        }
+
+        let active = sqlx::query_scalar!(
+            "INSERT INTO users (email) VALUES ('active@example.com') RETURNING id"
+        )
+        .fetch_one(&db)
+        .await
+        .unwrap();
+        assert!(purge_account(&db, &targets, active).await.unwrap().is_none());
+
+        assert!(purge_account(&db, &targets, user_id).await.unwrap().is_some());
+
+        let failures = sqlx::query_scalar!("SELECT to_email FROM email_failures")
+            .fetch_all(&db)
+            .await
+            .unwrap();
+        assert_eq!(failures, vec!["kept@example.com"]);
+        assert!(matches!(
+            targets.storage.get(&key).await,
+            Err(StorageError::NotFound(_))
+        ));
+        assert!(purge_account(&db, &targets, user_id).await.unwrap().is_none());
+    }
+
+    #[test]
+    fn test_missing_intelligence_data_counts_as_deleted() {
+        assert!(ignore_not_found(Ok(())).is_ok());
+        assert!(ignore_not_found::<()>(Err(tonic::Status::not_found("gone"))).is_ok());
+
+        let unavailable = ignore_not_found::<()>(Err(tonic::Status::unavailable("down")));
+        assert_eq!(unavailable.unwrap_err().code(), Code::Unavailable);
+    }
+}
@@ -0,0 +1,102 @@
+//! Confirmation for destructive account changes
+//!
+//! A valid session alone isn't enough to delete the account or move it to
+//! another email address, since the session may belong to an unattended
+//! laptop or a stolen token. Accounts with a password must send it; accounts
+//! that only sign in through OAuth must have signed in within
+//! `REAUTH_WINDOW`.
+
+use chrono::{DateTime, Duration, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::auth::password;
+use crate::auth::session::{self, CurrentSession};
+use crate::user::UserError;
+
+/// How recent an OAuth sign-in must be to stand in for a password
+pub const REAUTH_WINDOW: Duration = Duration::minutes(10);
+
+/// Why an action wasn't confirmed, reported as 403 with `code()` as the error
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfirmationFailure {
+    PasswordRequired,
+    InvalidPassword,
+    RecentSigninRequired,
+}
+
+impl ConfirmationFailure {
+    pub fn code(&self) -> &'static str {
+        match self {
+            ConfirmationFailure::PasswordRequired => "password_required",
+            ConfirmationFailure::InvalidPassword => "invalid_password",
+            ConfirmationFailure::RecentSigninRequired => "recent_signin_required",
+        }
+    }
+
+    pub fn message(&self) -> &'static str {
+        match self {
+            ConfirmationFailure::PasswordRequired => {
+                "Confirm this action with your current password"
+            }
+            ConfirmationFailure::InvalidPassword => "Current password is incorrect",
+            ConfirmationFailure::RecentSigninRequired => "Sign in again to confirm this action",
+        }
+    }
+}
+
+/// Check that the request really comes from the account owner
+///
+/// `current_password` is required when the account has a password; otherwise
+/// the session must come from a sign-in within `REAUTH_WINDOW`.
+pub async fn confirm_identity(
+    db: &PgPool,
+    user_id: Uuid,
+    current_password: Option<&str>,
+    current: &CurrentSession,
+) -> Result<(), UserError> {
+    let password_hash = sqlx::query_scalar!(
+        "SELECT password_hash FROM users WHERE id = $1 AND deleted_at IS NULL",
+        user_id
+    )
+    .fetch_one(db)
+    .await?;
+
+    let Some(password_hash) = password_hash else {
+        let authenticated_at = session::authenticated_at(db, current).await?;
+        if !is_recent(authenticated_at, Utc::now()) {
+            return Err(ConfirmationFailure::RecentSigninRequired.into());
+        }
+        return Ok(());
+    };
+
+    let current_password = current_password
+        .filter(|p| !p.is_empty())
+        .ok_or(ConfirmationFailure::PasswordRequired)?;
+
+    let is_valid = password::verify_password(current_password, &password_hash)
+        .map_err(|_| ConfirmationFailure::InvalidPassword)?;
+    if !is_valid {
+        return Err(ConfirmationFailure::InvalidPassword.into());
+    }
+    Ok(())
+}
+
+/// Whether a sign-in at `authenticated_at` still counts as recent
+fn is_recent(authenticated_at: Option<DateTime<Utc>>, now: DateTime<Utc>) -> bool {
+    authenticated_at.is_some_and(|at| now - at <= REAUTH_WINDOW)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recent_signin_window() {
+        let now = Utc::now();
+        assert!(is_recent(Some(now - Duration::minutes(2)), now));
+        assert!(is_recent(Some(now - REAUTH_WINDOW), now));
+        assert!(!is_recent(Some(now - Duration::minutes(11)), now));
+        assert!(!is_recent(None, now));
+    }
+}
@@ -1,10 +1,18 @@
+use chrono::{Duration, Utc};
 use sqlx::PgPool;
 use uuid::Uuid;
 
-use crate::auth::{password, session};
+use crate::auth::audit::{self, AuthEventType, ClientInfo};
+use crate::auth::session::CurrentSession;
+use crate::auth::{password, session, tokens};
+use crate::chat::quota;
+use crate::config::cache::RedisPool;
+use crate::email::EmailService;
 use crate::user::{
-    ChangePasswordRequest, ChangePasswordResponse, DeleteAccountResponse, SessionListResponse,
-    UpdateProfileRequest, UserError, UserResponse,
+    ChangeEmailRequest, ChangeEmailResponse, ChangePasswordRequest, ChangePasswordResponse,
+    DeleteAccountRequest, DeleteAccountResponse, Session, SessionListResponse, SetPasswordRequest,
+    SetPasswordResponse, TokenQuotaResponse, TokenUsageResponse, UpdateProfileRequest, UserError,
+    UserResponse, device, reauth,
 };
 
 // ===== User Retrieval =====
@@ -31,16 +39,16 @@ pub async fn get_user_by_id(db: &PgPool, user_id: Uuid) -> Result<UserResponse,
 
 /// Update user profile
 /// - Validates username uniqueness if changed
-/// - Updates name, username, avatar_url
+/// - Sets, clears or keeps each of name, username, avatar_url
 pub async fn update_profile(
     db: &PgPool,
     user_id: Uuid,
     req: UpdateProfileRequest,
 ) -> Result<UserResponse, UserError> {
     // Check username uniqueness if provided
-    if let Some(ref username) = req.username {
+    if let Some(username) = req.username.as_set() {
         let existing = sqlx::query!(
-            "SELECT id FROM users WHERE username = $1 AND id != $2",
+            "SELECT id FROM users WHERE LOWER(username) = LOWER($1) AND id != $2",
             username,
             user_id
         )
@@ -52,27 +60,64 @@ pub async fn update_profile(
         }
     }
 
+    let (update_name, name) = req.name.into_update();
+    let (update_username, username) = req.username.into_update();
+    let (update_avatar, avatar_url) = req.avatar_url.into_update();
+
     // Update profile
     sqlx::query!(
         r#"
         UPDATE users
-        SET name = COALESCE($1, name),
-            username = COALESCE($2, username),
-            avatar_url = COALESCE($3, avatar_url)
-        WHERE id = $4
+        SET name = CASE WHEN $1 THEN $2 ELSE name END,
+            username = CASE WHEN $3 THEN $4 ELSE username END,
+            avatar_url = CASE WHEN $5 THEN $6 ELSE avatar_url END
+        WHERE id = $7
         "#,
-        req.name,
-        req.username,
-        req.avatar_url,
+        update_name,
+        name,
+        update_username,
+        username,
+        update_avatar,
+        avatar_url,
         user_id
     )
     .execute(db)
-    .await?;
+    .await
+    // Another account can take the username between the check and here
+    .map_err(|e| match e.as_database_error() {
+        Some(db_error) if db_error.is_unique_violation() => UserError::UsernameAlreadyTaken,
+        _ => UserError::from(e),
+    })?;
 
     // Return updated user
     get_user_by_id(db, user_id).await
 }
 
+/// Point the user's avatar at a new URL, returning the previous one
+pub async fn set_avatar_url(
+    db: &PgPool,
+    user_id: Uuid,
+    avatar_url: &str,
+) -> Result<Option<String>, UserError> {
+    let updated = sqlx::query!(
+        r#"
+        WITH old AS (SELECT id, avatar_url FROM users WHERE id = $2 FOR UPDATE)
+        UPDATE users
+        SET avatar_url = $1
+        FROM old
+        WHERE users.id = old.id
+        RETURNING old.avatar_url AS "previous?"
+        "#,
+        avatar_url,
+        user_id
+    )
+    .fetch_optional(db)
+    .await?
+    .ok_or(UserError::NotFound)?;
+
+    Ok(updated.previous)
+}
+
 // ===== Password Management =====
 
 /// Change user password
@@ -83,18 +128,18 @@ pub async fn update_profile(
 /// - Invalidates all sessions except current (for security)
 pub async fn change_password(
     db: &PgPool,
+    cache: Option<&RedisPool>,
     user_id: Uuid,
     current_session_token: &str,
     req: ChangePasswordRequest,
+    client: &ClientInfo,
 ) -> Result<ChangePasswordResponse, UserError> {
     // Get current password hash
     let user = sqlx::query!("SELECT password_hash FROM users WHERE id = $1", user_id)
         .fetch_one(db)
         .await?;
 
-    let current_hash = user
-        .password_hash
-        .ok_or(UserError::InvalidCurrentPassword)?;
+    let current_hash = user.password_hash.ok_or(UserError::NoPasswordSet)?;
 
     // Verify current password
     let is_valid = password::verify_password(&req.current_password, &current_hash)
@@ -121,53 +166,214 @@ pub async fn change_password(
     .await?;
 
     // Invalidate all sessions except current
-    session::invalidate_all_sessions_except(db, user_id, current_session_token)
+    session::invalidate_all_sessions_except(db, cache, user_id, current_session_token)
         .await
         .map_err(|_| UserError::Internal)?;
 
+    audit::record(
+        db,
+        Some(user_id),
+        AuthEventType::PasswordChange,
+        client,
+        serde_json::json!({}),
+    )
+    .await;
+
     Ok(ChangePasswordResponse {
         message: "Password changed successfully. All other sessions have been logged out."
             .to_string(),
     })
 }
 
+/// Add a password to an account that only signs in through OAuth
+/// - Requires a recent sign-in, as there is no password to confirm with
+/// - Hashes and stores the password, only if none is set
+/// - Emails the account so an unexpected password gets noticed
+pub async fn set_password(
+    db: &PgPool,
+    user_id: Uuid,
+    req: SetPasswordRequest,
+    current: &CurrentSession,
+    email_service: &EmailService,
+    client: &ClientInfo,
+) -> Result<SetPasswordResponse, UserError> {
+    let has_password = sqlx::query_scalar!(
+        r#"SELECT password_hash IS NOT NULL as "has_password!" FROM users WHERE id = $1"#,
+        user_id
+    )
+    .fetch_one(db)
+    .await?;
+    if has_password {
+        return Err(UserError::PasswordAlreadySet);
+    }
+
+    reauth::confirm_identity(db, user_id, None, current).await?;
+
+    let new_hash = password::hash_password(&req.new_password).map_err(|_| UserError::Internal)?;
+
+    // Guarded so a concurrent set-password can't overwrite the first one
+    let email = sqlx::query_scalar!(
+        r#"
+        UPDATE users
+        SET password_hash = $1
+        WHERE id = $2 AND password_hash IS NULL
+        RETURNING email
+        "#,
+        new_hash,
+        user_id
+    )
+    .fetch_optional(db)
+    .await?
+    .ok_or(UserError::PasswordAlreadySet)?;
+
+    audit::record(
+        db,
+        Some(user_id),
+        AuthEventType::PasswordChange,
+        client,
+        serde_json::json!({ "password_set": true }),
+    )
+    .await;
+
+    if let Err(e) = email_service.send_password_set_email(&email).await {
+        tracing::error!(%user_id, "Failed to send password set confirmation: {:?}", e);
+    }
+
+    Ok(SetPasswordResponse {
+        message: "Password set. You can now sign in with your email and password.".to_string(),
+    })
+}
+
+// ===== Email Management =====
+
+/// Request an email address change
+/// - Confirms the request with the current password or a recent sign-in
+/// - Rejects addresses already used by another account
+/// - Stores a pending change token (old email stays active)
+/// - Sends a confirmation link to the NEW address
+pub async fn request_email_change(
+    db: &PgPool,
+    user_id: Uuid,
+    req: ChangeEmailRequest,
+    current: &CurrentSession,
+    email_service: &EmailService,
+) -> Result<ChangeEmailResponse, UserError> {
+    reauth::confirm_identity(db, user_id, req.current_password.as_deref(), current).await?;
+
+    let user = sqlx::query!(
+        "SELECT email FROM users WHERE id = $1 AND deleted_at IS NULL",
+        user_id
+    )
+    .fetch_one(db)
+    .await?;
+
+    if user.email.eq_ignore_ascii_case(&req.new_email) {
+        return Err(UserError::Validation(
+            "New email must be different from the current email".to_string(),
+        ));
+    }
+
+    // Reject if the new email already belongs to another account, in any case
+    let existing = sqlx::query!(
+        "SELECT id FROM users WHERE LOWER(email) = LOWER($1) AND id != $2",
+        req.new_email,
+        user_id
+    )
+    .fetch_optional(db)
+    .await?;
+
+    if existing.is_some() {
+        return Err(UserError::EmailAlreadyInUse);
+    }
+
+    // Replace any pending change request for this user
+    sqlx::query!(
+        "DELETE FROM email_change_tokens WHERE user_id = $1",
+        user_id
+    )
+    .execute(db)
+    .await?;
+
+    let confirmation_token = tokens::generate_token();
+    let expires_at = Utc::now() + Duration::hours(24);
+
+    sqlx::query!(
+        r#"
+        INSERT INTO email_change_tokens (user_id, new_email, token, expires_at)
+        VALUES ($1, $2, $3, $4)
+        "#,
+        user_id,
+        req.new_email,
+        confirmation_token,
+        expires_at
+    )
+    .execute(db)
+    .await?;
+
+    // Send confirmation email to the new address
+    if let Err(e) = email_service
+        .send_email_change_email(&req.new_email, &confirmation_token)
+        .await
+    {
+        tracing::error!("Failed to send email change confirmation: {:?}", e);
+        return Err(UserError::Internal);
+    }
+
+    Ok(ChangeEmailResponse {
+        message: "Confirmation link sent to the new email address. Your current email remains active until it is confirmed.".to_string(),
+    })
+}
+
 // ===== Account Deletion =====
 
 /// Soft delete user account
+/// - Confirms the request with the current password or a recent sign-in
 /// - Sets deleted_at timestamp
 /// - Invalidates all sessions
-/// - Data can be recovered within a period
+/// - Data can be recovered for `retention_days`, then it is purged
 pub async fn soft_delete_account(
     db: &PgPool,
+    cache: Option<&RedisPool>,
     user_id: Uuid,
+    req: DeleteAccountRequest,
+    current: &CurrentSession,
+    retention_days: u32,
 ) -> Result<DeleteAccountResponse, UserError> {
+    reauth::confirm_identity(db, user_id, req.current_password.as_deref(), current).await?;
+
     // Set deleted_at
     sqlx::query!("UPDATE users SET deleted_at = NOW() WHERE id = $1", user_id)
         .execute(db)
         .await?;
 
     // Invalidate all sessions
-    session::invalidate_all_user_sessions(db, user_id)
+    session::invalidate_all_user_sessions(db, cache, user_id)
         .await
         .map_err(|_| UserError::Internal)?;
 
     Ok(DeleteAccountResponse {
-        message: "Account deactivated. Contact support within 30 days to recover.".to_string(),
+        message: format!(
+            "Account deactivated. Contact support within {} days to recover.",
+            retention_days
+        ),
     })
 }
 
 // ===== Session Management =====
 
 /// Get all active sessions for a user
+/// `current` marks the session the request was made with
 pub async fn get_user_sessions(
     db: &PgPool,
     user_id: Uuid,
+    current: Option<&CurrentSession>,
+    max_sessions: u32,
 ) -> Result<SessionListResponse, UserError> {
-    let sessions = sqlx::query_as!(
-        crate::user::Session,
+    let rows = sqlx::query!(
         r#"
         SELECT id, user_id, session_token, expires_at, 
-               ip_address::TEXT as "ip_address?", user_agent, created_at
+               ip_address::TEXT as "ip_address?", user_agent, created_at,
+               impersonated_by, impersonated_by IS NOT NULL as "is_impersonated!", name
         FROM sessions
         WHERE user_id = $1 AND expires_at > NOW()
         ORDER BY created_at DESC
@@ -177,14 +383,53 @@ pub async fn get_user_sessions(
     .fetch_all(db)
     .await?;
 
-    Ok(SessionListResponse { sessions })
+    let sessions = rows
+        .into_iter()
+        .map(|row| Session {
+            is_current: current.is_some_and(|c| c.is(row.id, &row.session_token)),
+            device: device::device_label(row.user_agent.as_deref()),
+            id: row.id,
+            user_id: row.user_id,
+            expires_at: row.expires_at,
+            ip_address: row.ip_address,
+            user_agent: row.user_agent,
+            created_at: row.created_at,
+            impersonated_by: row.impersonated_by,
+            is_impersonated: row.is_impersonated,
+            name: row.name,
+        })
+        .collect();
+
+    let active_sessions = session::count_active_sessions(db, user_id).await?;
+
+    Ok(SessionListResponse {
+        sessions,
+        active_sessions,
+        max_sessions: (max_sessions > 0).then_some(max_sessions),
+    })
 }
 
-/// Revoke a specific session
-pub async fn revoke_session(db: &PgPool, user_id: Uuid, session_id: Uuid) -> Result<(), UserError> {
-    // Verify session belongs to user before deleting
+/// Longest custom session name
+const MAX_SESSION_NAME_LENGTH: usize = 64;
+
+/// Name or rename one of the user's sessions; an empty name clears it
+pub async fn rename_session(
+    db: &PgPool,
+    user_id: Uuid,
+    session_id: Uuid,
+    name: Option<&str>,
+) -> Result<(), UserError> {
+    let name = name.map(str::trim).filter(|n| !n.is_empty());
+    if name.is_some_and(|n| n.chars().count() > MAX_SESSION_NAME_LENGTH) {
+        return Err(UserError::Validation(format!(
+            "Session name must be at most {} characters",
+            MAX_SESSION_NAME_LENGTH
+        )));
+    }
+
     let result = sqlx::query!(
-        "DELETE FROM sessions WHERE id = $1 AND user_id = $2",
+        "UPDATE sessions SET name = $1 WHERE id = $2 AND user_id = $3",
+        name,
         session_id,
         user_id
     )
@@ -194,6 +439,71 @@ pub async fn revoke_session(db: &PgPool, user_id: Uuid, session_id: Uuid) -> Res
     if result.rows_affected() == 0 {
         return Err(UserError::SessionNotFound);
     }
+    Ok(())
+}
+
+/// Revoke a specific session
+pub async fn revoke_session(
+    db: &PgPool,
+    cache: Option<&RedisPool>,
+    user_id: Uuid,
+    session_id: Uuid,
+    client: &ClientInfo,
+) -> Result<(), UserError> {
+    // Verify session belongs to user before deleting
+    let session_token = sqlx::query_scalar!(
+        "SELECT session_token FROM sessions WHERE id = $1 AND user_id = $2",
+        session_id,
+        user_id
+    )
+    .fetch_optional(db)
+    .await?
+    .ok_or(UserError::SessionNotFound)?;
+
+    session::invalidate_session(db, cache, &session_token)
+        .await
+        .map_err(|_| UserError::Internal)?;
+
+    audit::record(
+        db,
+        Some(user_id),
+        AuthEventType::SessionRevoked,
+        client,
+        serde_json::json!({ "session_id": session_id }),
+    )
+    .await;
 
     Ok(())
 }
+
+/// Get the user's token usage and budget for the current month
+pub async fn get_token_usage(
+    db: &PgPool,
+    user_id: Uuid,
+    default_budget: Option<i64>,
+) -> Result<TokenUsageResponse, UserError> {
+    let budget = quota::get_monthly_budget(db, user_id, default_budget).await?;
+
+    Ok(TokenUsageResponse {
+        period_start: budget.period_start,
+        tokens_used: budget.tokens_used,
+        token_budget: budget.token_budget,
+        remaining_tokens: budget.remaining(),
+        resets_at: budget.resets_at,
+    })
+}
+
+/// Get the user's token usage and limit for the current day
+pub async fn get_token_quota(db: &PgPool, user_id: Uuid) -> Result<TokenQuotaResponse, UserError> {
+    let quota = quota::get_daily_quota(db, user_id).await?;
+
+    Ok(TokenQuotaResponse {
+        period_start: quota.period_start,
+        tokens_used: quota.tokens_used,
+        max_tokens_per_day: quota.max_tokens_per_day,
+        remaining_tokens: quota
+            .max_tokens_per_day
+            .map(|limit| (limit - quota.tokens_used).max(0)),
+        resets_at: quota.resets_at,
+    })
+}
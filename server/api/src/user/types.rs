@@ -1,10 +1,12 @@
 use crate::auth::Role;
-use chrono::{DateTime, Utc};
+use crate::common::patch::Patch;
+use chrono::{DateTime, NaiveDate, Utc};
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 use uuid::Uuid;
 
 // ===== User Response =====
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct UserResponse {
     pub id: Uuid,
     pub email: String,
@@ -16,29 +18,66 @@ pub struct UserResponse {
     pub created_at: DateTime<Utc>,
 }
 
+// ===== Avatar =====
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AvatarResponse {
+    pub avatar_url: String,
+}
+
 // ===== Update Profile =====
-#[derive(Debug, Deserialize)]
+/// Omitted fields are left alone; `null` clears a field
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct UpdateProfileRequest {
-    pub name: Option<String>,
-    pub username: Option<String>,
-    pub avatar_url: Option<String>,
+    #[serde(default)]
+    #[schema(value_type = Option<String>)]
+    pub name: Patch<String>,
+    #[serde(default)]
+    #[schema(value_type = Option<String>)]
+    pub username: Patch<String>,
+    #[serde(default)]
+    #[schema(value_type = Option<String>)]
+    pub avatar_url: Patch<String>,
 }
 
 // ===== Change Password =====
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct ChangePasswordRequest {
     pub current_password: String,
     pub new_password: String,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct ChangePasswordResponse {
     pub message: String,
 }
 
+// ===== Set Password =====
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct SetPasswordRequest {
+    pub new_password: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SetPasswordResponse {
+    pub message: String,
+}
+
+// ===== Change Email =====
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ChangeEmailRequest {
+    pub new_email: String,
+    /// Required unless the account only signs in through OAuth
+    pub current_password: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ChangeEmailResponse {
+    pub message: String,
+}
+
 // ===== Account (OAuth) =====
 #[allow(dead_code)] // Reserved for OAuth implementation
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct Account {
     pub id: Uuid,
     pub user_id: Uuid,
@@ -51,24 +90,73 @@ pub struct Account {
 }
 
 // ===== Session =====
-#[derive(Debug, Serialize, Deserialize)]
+/// A session as listed to its owner; the token itself is never returned
+#[derive(Debug, Serialize, ToSchema)]
 pub struct Session {
     pub id: Uuid,
     pub user_id: Uuid,
-    pub session_token: String,
     pub expires_at: DateTime<Utc>,
     pub ip_address: Option<String>,
     pub user_agent: Option<String>,
     pub created_at: DateTime<Utc>,
+    /// Set when an admin is signed in as this user
+    pub impersonated_by: Option<Uuid>,
+    pub is_impersonated: bool,
+    /// Whether this is the session making the request
+    pub is_current: bool,
+    /// Browser and OS from the user agent, e.g. "Chrome on macOS"
+    pub device: String,
+    /// Name the user gave this session, if any
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct RenameSessionRequest {
+    /// New name; empty or `null` clears it
+    pub name: Option<String>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct SessionListResponse {
     pub sessions: Vec<Session>,
+    /// Sessions counted towards `max_sessions` (impersonation excluded)
+    pub active_sessions: i64,
+    /// Per-user session limit; `None` when unlimited. Signing in beyond it
+    /// logs out the oldest session.
+    pub max_sessions: Option<u32>,
+}
+
+// ===== Token Quota =====
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TokenQuotaResponse {
+    pub period_start: NaiveDate,
+    pub tokens_used: i64,
+    /// `None` when the user has no daily limit
+    pub max_tokens_per_day: Option<i64>,
+    pub remaining_tokens: Option<i64>,
+    pub resets_at: DateTime<Utc>,
+}
+
+// ===== Token Budget =====
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TokenUsageResponse {
+    /// First day of the current month (UTC)
+    pub period_start: NaiveDate,
+    pub tokens_used: i64,
+    /// `None` when the user has no monthly budget
+    pub token_budget: Option<i64>,
+    pub remaining_tokens: Option<i64>,
+    pub resets_at: DateTime<Utc>,
 }
 
 // ===== Delete Account =====
-#[derive(Debug, Serialize)]
+#[derive(Debug, Default, Deserialize, ToSchema)]
+pub struct DeleteAccountRequest {
+    /// Required unless the account only signs in through OAuth
+    pub current_password: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
 pub struct DeleteAccountResponse {
     pub message: String,
 }
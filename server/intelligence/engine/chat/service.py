@@ -96,6 +96,19 @@ class ChatService:
                 user_id=user_id, conversation_id=conversation_id
             )
 
+            # 1.5 An edit replaces the edited message and everything after it,
+            # so the old exchange doesn't end up in the context
+            edited_message_id = (metadata or {}).get("edited_message_id")
+            if edited_message_id:
+                try:
+                    await conv_storage.truncate_from_message(
+                        conv.id, uuid.UUID(edited_message_id)
+                    )
+                except ValueError:
+                    logger.warning(
+                        f"Ignoring invalid edited_message_id: {edited_message_id}"
+                    )
+
             # 2. Save User Message
             await conv_storage.add_message(
                 conversation_id=conv.id,
@@ -97,6 +97,31 @@ class ConversationStorage:
         )
         return list(result.scalars().all())
 
+    async def truncate_from_message(
+        self, conversation_id: uuid.UUID, message_id: uuid.UUID
+    ) -> int:
+        """Delete a message and everything after it in its conversation.
+
+        Returns the number of messages deleted; 0 if the message isn't there.
+        """
+        result = await self.session.execute(
+            select(ChatMessage.created_at).where(
+                ChatMessage.id == message_id,
+                ChatMessage.conversation_id == conversation_id,
+            )
+        )
+        created_at = result.scalar_one_or_none()
+        if created_at is None:
+            return 0
+
+        result = await self.session.execute(
+            delete(ChatMessage).where(
+                ChatMessage.conversation_id == conversation_id,
+                ChatMessage.created_at >= created_at,
+            )
+        )
+        return result.rowcount
+
     async def delete_conversation(self, conversation_id: uuid.UUID) -> bool:
         """Delete conversation and all messages."""
         result = await self.session.execute(
@@ -54,6 +54,10 @@ class DocumentProcessor:
         """
         Process a single document through the pipeline.
 
+        A document that already exists under ``document.id`` is replaced, but
+        only once the new version and its chunks are stored; on failure the
+        previous version is kept.
+
         Args:
             user_id: User ID
             document: Document proto message
@@ -67,6 +71,9 @@ class DocumentProcessor:
             ValidationError: If input validation fails
             Exception: If processing fails
         """
+        # Savepoint around storing the document, so a failure rolls back to
+        # the version it was replacing instead of leaving neither
+        savepoint = None
         try:
             # Validate inputs
             validate_user_id(user_id)
@@ -141,6 +148,10 @@ class DocumentProcessor:
                         f"{cleaning_metrics.html_tags_removed} HTML tags removed"
                     )
 
+            savepoint = await self.session.begin_nested()
+            if document.id:
+                await self._remove_previous_version(uuid.UUID(document.id), user_id)
+
             # Create document in database
             try:
                 doc_id = uuid.UUID(document.id) if document.id else None
@@ -223,6 +234,8 @@ class DocumentProcessor:
                 await self.doc_storage.delete_document(db_doc.id)
                 raise
 
+            await savepoint.commit()
+
             # Update job progress if job_id provided
             if job_id:
                 await self.job_storage.increment_processed(job_id)
@@ -235,6 +248,8 @@ class DocumentProcessor:
 
         except ValidationError as e:
             logger.error(f"Validation error processing document: {e}")
+            if savepoint is not None and savepoint.is_active:
+                await savepoint.rollback()
             if job_id:
                 await self.job_storage.increment_failed(
                     job_id, f"Validation error: {e}"
@@ -242,11 +257,27 @@ class DocumentProcessor:
             raise
         except Exception as e:
             logger.error(f"Error processing document: {e}", exc_info=True)
+            if savepoint is not None and savepoint.is_active:
+                await savepoint.rollback()
             # Update job with error if job_id provided
             if job_id:
                 await self.job_storage.increment_failed(job_id, str(e))
             raise
 
+    async def _remove_previous_version(self, doc_id: uuid.UUID, user_id: str) -> None:
+        """Delete the document being re-ingested under ``doc_id``, if any."""
+        existing = await self.doc_storage.get_document(doc_id)
+        if existing is None:
+            return
+        if existing.user_id != user_id:
+            raise ValidationError(f"Document {doc_id} belongs to another user")
+
+        await self.doc_storage.delete_document(doc_id)
+        # The new version is added under the same primary key
+        if existing in self.session:
+            self.session.expunge(existing)
+        logger.info(f"Replacing previous version of document {doc_id}")
+
     async def process_batch(
         self,
         user_id: str,
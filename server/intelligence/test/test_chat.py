@@ -231,3 +231,53 @@ async def test_chat_metrics_consistency(chat_client):
     assert metrics.tokens_used >= metrics.completion_tokens, (
         "Total tokens should be >= completion tokens"
     )
+
+
+@pytest.mark.asyncio
+async def test_edit_truncates_conversation(chat_client):
+    """
+    Test that an edit cuts the conversation at the edited message.
+    The gateway sends the new content with `edited_message_id` metadata.
+    """
+    user_id = "test-edit"
+
+    first = await chat_client.SendMessage(
+        intelligence_pb2.ChatRequest(
+            user_id=user_id, message="First question", conversation_id=""
+        )
+    )
+    conversation_id = first.conversation_id
+    await chat_client.SendMessage(
+        intelligence_pb2.ChatRequest(
+            user_id=user_id, message="Second question", conversation_id=conversation_id
+        )
+    )
+
+    conversation = await chat_client.GetConversation(
+        intelligence_pb2.GetConversationRequest(
+            user_id=user_id, conversation_id=conversation_id
+        )
+    )
+    assert len(conversation.messages) == 4
+    edited = conversation.messages[2]
+    assert edited.content == "Second question"
+
+    await chat_client.SendMessage(
+        intelligence_pb2.ChatRequest(
+            user_id=user_id,
+            message="Edited question",
+            conversation_id=conversation_id,
+            metadata={"edited_message_id": edited.message_id},
+        )
+    )
+
+    conversation = await chat_client.GetConversation(
+        intelligence_pb2.GetConversationRequest(
+            user_id=user_id, conversation_id=conversation_id
+        )
+    )
+    contents = [m.content for m in conversation.messages]
+    assert len(contents) == 4
+    assert contents[0] == "First question"
+    assert contents[2] == "Edited question"
+    assert "Second question" not in contents
@@ -9,6 +9,8 @@ Tests cover:
 - Resource ingestion and chunking
 """
 
+import uuid
+
 import pytest
 from generated import intelligence_pb2
 
@@ -275,6 +277,50 @@ async def test_resource_status_tracking(resource_client):
     ]
 
 
+@pytest.mark.asyncio
+async def test_reingest_keeps_resource_id(resource_client):
+    """
+    Re-adding a resource under its ID replaces its content in place, and a
+    failed re-ingest leaves the previous version untouched.
+    """
+    user_id = "test-reingest-user"
+    resource_id = str(uuid.uuid4())
+
+    async def add(text):
+        return await resource_client.AddResource(
+            intelligence_pb2.AddResourceRequest(
+                user_id=user_id,
+                resource_id=resource_id,
+                text=text,
+                title="Docs",
+                type=intelligence_pb2.RESOURCE_TYPE_TEXT,
+            )
+        )
+
+    async def stored_content():
+        listing = await resource_client.ListResources(
+            intelligence_pb2.ListResourcesRequest(user_id=user_id, limit=10)
+        )
+        return [item.content for item in listing.items if item.id == resource_id]
+
+    await add("First version of the documentation.")
+    updated = await add("Second version of the documentation.")
+    assert updated.resource_id == resource_id
+    assert await stored_content() == ["Second version of the documentation."]
+
+    # Empty content fails validation; the second version stays
+    failed = await add("")
+    status = await resource_client.GetResourceStatus(
+        intelligence_pb2.GetResourceStatusRequest(job_id=failed.job_id)
+    )
+    assert status.status == intelligence_pb2.RESOURCE_STATUS_FAILED
+    assert await stored_content() == ["Second version of the documentation."]
+
+    await resource_client.DeleteResource(
+        intelligence_pb2.DeleteResourceRequest(resource_id=resource_id)
+    )
+
+
 @pytest.mark.asyncio
 async def test_delete_nonexistent_resource(resource_client):
     """